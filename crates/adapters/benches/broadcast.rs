@@ -0,0 +1,85 @@
+//! Cost of broadcasting a Coinbase ticker to the collectors of many pools.
+//!
+//! A broadcast channel clones a message for every receiver, which for a
+//! [`CoinbaseMessage`] means cloning the whole ticker. Broadcasting an
+//! [`Arc`] only bumps a reference count instead.
+//!
+//! Run with `cargo bench -p sikkara-adapters --bench broadcast`.
+
+use std::sync::Arc;
+
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput};
+use sikkara_adapters::CoinbaseMessage;
+use tokio::sync::broadcast;
+
+/// Number of collectors receiving every message
+const SUBSCRIBERS: usize = 16;
+
+fn ticker() -> CoinbaseMessage {
+    serde_json::from_value(serde_json::json!({
+        "type": "ticker",
+        "sequence": 75193216603_u64,
+        "product_id": "ETH-USD",
+        "price": "2687.37",
+        "open_24h": "2621.85",
+        "volume_24h": "132964.98967648",
+        "low_24h": "2548",
+        "high_24h": "2695.87",
+        "volume_30d": "5204346.20541330",
+        "best_bid": "2686.83",
+        "best_bid_size": "2.01571863",
+        "best_ask": "2687.37",
+        "best_ask_size": "0.03375599",
+        "side": "buy",
+        "time": "2025-02-12T21:12:33.778451Z",
+        "trade_id": 610049064_u64,
+        "last_size": "0.0007456"
+    }))
+    .expect("valid ticker")
+}
+
+/// Sends `message` and receives it on every receiver.
+fn fan_out<T: Clone>(
+    sender: &broadcast::Sender<T>,
+    receivers: &mut [broadcast::Receiver<T>],
+    message: T,
+) {
+    sender.send(message).expect("receivers are subscribed");
+    for receiver in receivers.iter_mut() {
+        std::hint::black_box(receiver.try_recv().expect("message is received"));
+    }
+}
+
+fn broadcast_ticker(c: &mut Criterion) {
+    let mut group = c.benchmark_group("broadcast_ticker");
+    group.throughput(Throughput::Elements(SUBSCRIBERS as u64));
+
+    let (sender, _) = broadcast::channel::<CoinbaseMessage>(16);
+    let mut receivers = (0..SUBSCRIBERS)
+        .map(|_| sender.subscribe())
+        .collect::<Vec<_>>();
+    group.bench_function(BenchmarkId::new("clone", SUBSCRIBERS), |b| {
+        b.iter_batched(
+            ticker,
+            |message| fan_out(&sender, &mut receivers, message),
+            BatchSize::SmallInput,
+        )
+    });
+
+    let (sender, _) = broadcast::channel::<Arc<CoinbaseMessage>>(16);
+    let mut receivers = (0..SUBSCRIBERS)
+        .map(|_| sender.subscribe())
+        .collect::<Vec<_>>();
+    group.bench_function(BenchmarkId::new("arc", SUBSCRIBERS), |b| {
+        b.iter_batched(
+            || Arc::new(ticker()),
+            |message| fan_out(&sender, &mut receivers, message),
+            BatchSize::SmallInput,
+        )
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, broadcast_ticker);
+criterion_main!(benches);
@@ -25,9 +25,11 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let pool_id = compute_pool_id(weth_address, usdc_address, 500, 10, Address::ZERO)?;
     let invert = weth_address < usdc_address;
     println!("Invert: {}", invert);
-    let mut stream = manager.watch_pool(pool_id, Duration::from_secs(30), invert);
+    // WETH has 18 decimals and USDC 6
+    let mut stream = manager.watch_pool(pool_id, Duration::from_secs(30), 18, 6, invert);
 
     while let Some(pool_data) = stream.next().await {
+        let pool_data = pool_data?;
         println!("Current Pool Data: {:?}", pool_data);
         println!("Spot Price: {}", pool_data.spot_price.to_fixed(2, None));
     }
@@ -0,0 +1,10 @@
+#[allow(unused)]
+mod models;
+#[allow(unused)]
+pub use models::{
+    BinanceBookTickerMessage, BinanceMessage, BinanceRequest, BinanceRequestMethod,
+    BinanceResponse, BinanceStream, BinanceSymbol, BinanceTickerMessage,
+};
+
+mod wsclient;
+pub use wsclient::{BinanceWsCallback, BinanceWsClient};
@@ -0,0 +1,282 @@
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+/// Request of the Binance websocket API, e.g. `{"method": "SUBSCRIBE",
+/// "params": ["ethusdt@ticker"], "id": 1}`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BinanceRequest {
+    pub method: BinanceRequestMethod,
+    /// Stream names, see [`BinanceStream::name`]
+    pub params: Vec<String>,
+    /// Identifier echoed back in the response
+    pub id: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum BinanceRequestMethod {
+    Subscribe,
+    Unsubscribe,
+}
+
+/// Market data streams of a symbol.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinanceStream {
+    /// Rolling 24 hour statistics, pushed every second
+    Ticker,
+    /// Best bid and ask, pushed on every change
+    BookTicker,
+}
+
+impl BinanceStream {
+    /// Returns the name of the stream of a symbol, e.g. `ethusdt@bookTicker`.
+    pub fn name(&self, symbol: &BinanceSymbol) -> String {
+        let stream = match self {
+            BinanceStream::Ticker => "ticker",
+            BinanceStream::BookTicker => "bookTicker",
+        };
+        format!("{}@{}", symbol.to_string().to_lowercase(), stream)
+    }
+}
+
+/// Message received from a raw Binance stream, i.e. without the `stream` and
+/// `data` envelope of combined streams.
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum BinanceMessage {
+    Ticker(BinanceTickerMessage),
+    BookTicker(BinanceBookTickerMessage),
+    Response(BinanceResponse),
+}
+
+/// Message of the `<symbol>@ticker` stream.
+#[derive(Debug, Clone, Deserialize)]
+pub struct BinanceTickerMessage {
+    /// Always `24hrTicker`
+    #[serde(rename = "e")]
+    pub event_type: String,
+    #[serde(rename = "E", with = "sikkara_core::timestamp_millis_serializer")]
+    pub event_time: jiff::Timestamp,
+    #[serde(rename = "s")]
+    pub symbol: BinanceSymbol,
+    #[serde(rename = "p")]
+    pub price_change: Decimal,
+    #[serde(rename = "P")]
+    pub price_change_percent: Decimal,
+    #[serde(rename = "w")]
+    pub weighted_avg_price: Decimal,
+    /// Price of the last trade
+    #[serde(rename = "c")]
+    pub last_price: Decimal,
+    #[serde(rename = "Q")]
+    pub last_qty: Decimal,
+    #[serde(rename = "b")]
+    pub best_bid: Decimal,
+    #[serde(rename = "B")]
+    pub best_bid_qty: Decimal,
+    #[serde(rename = "a")]
+    pub best_ask: Decimal,
+    #[serde(rename = "A")]
+    pub best_ask_qty: Decimal,
+    #[serde(rename = "o")]
+    pub open_price: Decimal,
+    #[serde(rename = "h")]
+    pub high_price: Decimal,
+    #[serde(rename = "l")]
+    pub low_price: Decimal,
+    /// Traded volume in the base asset
+    #[serde(rename = "v")]
+    pub volume: Decimal,
+    /// Traded volume in the quote asset
+    #[serde(rename = "q")]
+    pub quote_volume: Decimal,
+    #[serde(rename = "O", with = "sikkara_core::timestamp_millis_serializer")]
+    pub open_time: jiff::Timestamp,
+    #[serde(rename = "C", with = "sikkara_core::timestamp_millis_serializer")]
+    pub close_time: jiff::Timestamp,
+    /// First trade id of the window, -1 if there was no trade
+    #[serde(rename = "F")]
+    pub first_trade_id: i64,
+    #[serde(rename = "L")]
+    pub last_trade_id: i64,
+    #[serde(rename = "n")]
+    pub trade_count: u64,
+}
+
+/// Message of the `<symbol>@bookTicker` stream, which carries no timestamp.
+#[derive(Debug, Clone, Deserialize)]
+pub struct BinanceBookTickerMessage {
+    #[serde(rename = "u")]
+    pub update_id: u64,
+    #[serde(rename = "s")]
+    pub symbol: BinanceSymbol,
+    #[serde(rename = "b")]
+    pub best_bid: Decimal,
+    #[serde(rename = "B")]
+    pub best_bid_qty: Decimal,
+    #[serde(rename = "a")]
+    pub best_ask: Decimal,
+    #[serde(rename = "A")]
+    pub best_ask_qty: Decimal,
+}
+
+/// Response to a [`BinanceRequest`], a null result on success.
+#[derive(Debug, Clone, Deserialize)]
+pub struct BinanceResponse {
+    pub result: Option<serde_json::Value>,
+    pub id: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BinanceSymbol {
+    EthUsdt,
+    BtcUsdt,
+    BnbUsdt,
+}
+
+impl std::fmt::Display for BinanceSymbol {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BinanceSymbol::EthUsdt => write!(f, "ETHUSDT"),
+            BinanceSymbol::BtcUsdt => write!(f, "BTCUSDT"),
+            BinanceSymbol::BnbUsdt => write!(f, "BNBUSDT"),
+        }
+    }
+}
+
+impl Serialize for BinanceSymbol {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for BinanceSymbol {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        match s.as_str() {
+            "ETHUSDT" => Ok(BinanceSymbol::EthUsdt),
+            "BTCUSDT" => Ok(BinanceSymbol::BtcUsdt),
+            "BNBUSDT" => Ok(BinanceSymbol::BnbUsdt),
+            _ => Err(serde::de::Error::unknown_variant(&s, &["ETHUSDT", "BTCUSDT", "BNBUSDT"])),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal_macros::dec;
+
+    use super::*;
+
+    #[test]
+    fn test_binance_subscribe_request_serialize() {
+        let request = BinanceRequest {
+            method: BinanceRequestMethod::Subscribe,
+            params: vec![
+                BinanceStream::Ticker.name(&BinanceSymbol::EthUsdt),
+                BinanceStream::BookTicker.name(&BinanceSymbol::BtcUsdt),
+            ],
+            id: 7,
+        };
+
+        let serialized = serde_json::to_value(&request).unwrap();
+        assert_eq!(
+            serialized,
+            serde_json::json!({
+                "method": "SUBSCRIBE",
+                "params": ["ethusdt@ticker", "btcusdt@bookTicker"],
+                "id": 7
+            })
+        );
+    }
+
+    #[test]
+    fn test_binance_ticker_message_deserialize() {
+        let json = serde_json::json!({
+            "e": "24hrTicker",
+            "E": 1739394753778_u64,
+            "s": "ETHUSDT",
+            "p": "65.52",
+            "P": "2.499",
+            "w": "2650.12",
+            "x": "2621.85",
+            "c": "2687.37",
+            "Q": "0.0152",
+            "b": "2687.36",
+            "B": "12.5031",
+            "a": "2687.37",
+            "A": "3.2011",
+            "o": "2621.85",
+            "h": "2695.87",
+            "l": "2548.00",
+            "v": "412093.1200",
+            "q": "1092103412.50",
+            "O": 1739308353778_u64,
+            "C": 1739394753778_u64,
+            "F": 1902710335,
+            "L": 1904013021,
+            "n": 1302687
+        });
+
+        let message: BinanceMessage = serde_json::from_value(json).unwrap();
+        match message {
+            BinanceMessage::Ticker(ticker) => {
+                assert_eq!(ticker.symbol, BinanceSymbol::EthUsdt);
+                assert_eq!(ticker.event_time.to_string(), "2025-02-12T21:12:33.778Z");
+                assert_eq!(ticker.last_price, dec!(2687.37));
+                assert_eq!(ticker.best_bid, dec!(2687.36));
+                assert_eq!(ticker.best_bid_qty, dec!(12.5031));
+                assert_eq!(ticker.best_ask, dec!(2687.37));
+                assert_eq!(ticker.best_ask_qty, dec!(3.2011));
+                assert_eq!(ticker.volume, dec!(412093.1200));
+                assert_eq!(ticker.open_time.to_string(), "2025-02-11T21:12:33.778Z");
+                assert_eq!(ticker.trade_count, 1302687);
+            },
+            _ => panic!("Expected BinanceMessage::Ticker"),
+        }
+    }
+
+    #[test]
+    fn test_binance_book_ticker_message_deserialize() {
+        let json = serde_json::json!({
+            "u": 400900217,
+            "s": "BNBUSDT",
+            "b": "25.35190000",
+            "B": "31.21000000",
+            "a": "25.36520000",
+            "A": "40.66000000"
+        });
+
+        let message: BinanceMessage = serde_json::from_value(json).unwrap();
+        match message {
+            BinanceMessage::BookTicker(book) => {
+                assert_eq!(book.update_id, 400900217);
+                assert_eq!(book.symbol, BinanceSymbol::BnbUsdt);
+                assert_eq!(book.best_bid, dec!(25.3519));
+                assert_eq!(book.best_ask_qty, dec!(40.66));
+            },
+            _ => panic!("Expected BinanceMessage::BookTicker"),
+        }
+    }
+
+    #[test]
+    fn test_binance_response_deserialize() {
+        let json = serde_json::json!({ "result": null, "id": 7 });
+
+        let message: BinanceMessage = serde_json::from_value(json).unwrap();
+        match message {
+            BinanceMessage::Response(response) => {
+                assert_eq!(response.id, 7);
+                assert!(response.result.is_none());
+            },
+            _ => panic!("Expected BinanceMessage::Response"),
+        }
+    }
+}
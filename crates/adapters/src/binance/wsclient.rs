@@ -0,0 +1,206 @@
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc,
+};
+
+use sikkara_core::{AppError, AppResult};
+use sikkara_wsclient::WsCallback;
+use tokio::sync::{broadcast, mpsc};
+use tokio_tungstenite::tungstenite::{Message, Utf8Bytes};
+use tracing::{error, info, warn};
+
+use crate::binance::{
+    BinanceMessage, BinanceRequest, BinanceRequestMethod, BinanceStream, BinanceSymbol,
+};
+
+/// Client of the Binance Spot websocket streams, e.g.
+/// `wss://stream.binance.com:9443/ws`.
+///
+/// The client writes requests to the connection, the messages received are
+/// parsed and broadcast by its [`BinanceWsCallback`].
+#[derive(Debug, Clone)]
+pub struct BinanceWsClient {
+    ws_url: String,
+    sender: mpsc::Sender<Message>,
+    message_broadcaster: broadcast::Sender<BinanceMessage>,
+    /// Identifier of the next request
+    next_id: Arc<AtomicU64>,
+}
+
+impl BinanceWsClient {
+    pub fn new(
+        ws_url: String,
+        sender: mpsc::Sender<Message>,
+        message_broadcaster: broadcast::Sender<BinanceMessage>,
+    ) -> Self {
+        BinanceWsClient {
+            ws_url,
+            sender,
+            message_broadcaster,
+            next_id: Arc::new(AtomicU64::new(1)),
+        }
+    }
+
+    /// Returns the callback of the connection, to be handed to its consumer.
+    pub fn callback(&self) -> BinanceWsCallback {
+        BinanceWsCallback {
+            ws_url: self.ws_url.clone(),
+            sender: self.sender.clone(),
+            message_broadcaster: self.message_broadcaster.clone(),
+        }
+    }
+
+    pub fn subscribe(
+        &self,
+        symbols: Vec<BinanceSymbol>,
+        streams: Vec<BinanceStream>,
+    ) -> AppResult<broadcast::Receiver<BinanceMessage>> {
+        self.request(BinanceRequestMethod::Subscribe, &symbols, &streams)?;
+        Ok(self.message_broadcaster.subscribe())
+    }
+
+    pub fn unsubscribe(
+        &self,
+        symbols: Vec<BinanceSymbol>,
+        streams: Vec<BinanceStream>,
+    ) -> AppResult<()> {
+        self.request(BinanceRequestMethod::Unsubscribe, &symbols, &streams)
+    }
+
+    pub fn ws_url(&self) -> &str { &self.ws_url }
+
+    pub fn write(&self, message: Message) -> AppResult<()> { write(&self.sender, message) }
+
+    pub fn close(&self) -> AppResult<()> { self.write(Message::Close(None)) }
+
+    /// Writes a request for every stream of every symbol.
+    fn request(
+        &self,
+        method: BinanceRequestMethod,
+        symbols: &[BinanceSymbol],
+        streams: &[BinanceStream],
+    ) -> AppResult<()> {
+        let params = symbols
+            .iter()
+            .flat_map(|symbol| streams.iter().map(move |stream| stream.name(symbol)))
+            .collect();
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let request = BinanceRequest { method, params, id };
+        let message = serde_json::to_string(&request)?;
+
+        self.write(Message::Text(Utf8Bytes::from(&message)))
+    }
+}
+
+fn write(sender: &mpsc::Sender<Message>, message: Message) -> AppResult<()> {
+    match sender.try_send(message) {
+        Ok(_) => Ok(()),
+        Err(e) => {
+            Err(AppError::WebSocketError(format!("failed to send message to websocket: {}", e))
+                .into())
+        },
+    }
+}
+
+/// Callback of the connection of a [`BinanceWsClient`], broadcasting the
+/// messages received to its subscribers.
+#[derive(Debug, Clone)]
+pub struct BinanceWsCallback {
+    ws_url: String,
+    sender: mpsc::Sender<Message>,
+    message_broadcaster: broadcast::Sender<BinanceMessage>,
+}
+
+#[async_trait::async_trait]
+impl WsCallback for BinanceWsCallback {
+    async fn on_connect(&mut self, timestamp: jiff::Timestamp) -> AppResult<()> {
+        info!("Connected to Binance WebSocket at {}", self.ws_url);
+        Ok(())
+    }
+
+    async fn on_message(&mut self, message: Message, receive_at: jiff::Timestamp) -> AppResult<()> {
+        match message {
+            Message::Text(text) => {
+                let binance_message: BinanceMessage = match serde_json::from_str(&text) {
+                    Ok(msg) => msg,
+                    Err(e) => {
+                        error!("Failed to parse Binance message: {}", e);
+                        return Err(AppError::WebSocketError(format!(
+                            "Failed to parse Binance message: {}",
+                            e
+                        ))
+                        .into());
+                    },
+                };
+                self.message_broadcaster
+                    .send(binance_message)
+                    .map_err(|e| {
+                        AppError::WebSocketError(format!("Failed to broadcast message: {}", e))
+                    })?;
+            },
+            Message::Close(_) => {
+                info!("WebSocket connection closed by remote peer");
+                return self.on_disconnect();
+            },
+
+            // Binance closes connections not answering its pings within a minute
+            Message::Ping(ping) => {
+                write(&self.sender, Message::Pong(ping))?;
+            },
+
+            _ => {
+                warn!("Received unsupported message type: {:?}", message);
+            },
+        };
+        Ok(())
+    }
+
+    fn on_disconnect(&mut self) -> AppResult<()> {
+        info!("WebSocket connection closed or lost");
+        Ok(())
+    }
+
+    fn on_heartbeat(&mut self) -> AppResult<()> {
+        info!("Heartbeat check for Binance WebSocket connection");
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_subscribe_writes_a_request_per_call() {
+        let (sender, mut receiver) = mpsc::channel(4);
+        let (broadcaster, _) = broadcast::channel(4);
+        let client = BinanceWsClient::new("wss://binance".to_string(), sender, broadcaster);
+
+        client
+            .subscribe(
+                vec![BinanceSymbol::EthUsdt, BinanceSymbol::BtcUsdt],
+                vec![BinanceStream::Ticker],
+            )
+            .unwrap();
+        client
+            .unsubscribe(vec![BinanceSymbol::BtcUsdt], vec![BinanceStream::Ticker])
+            .unwrap();
+
+        let request = |message: Option<Message>| match message {
+            Some(Message::Text(text)) => serde_json::from_str::<serde_json::Value>(&text).unwrap(),
+            other => panic!("expected a text message, got {:?}", other),
+        };
+        assert_eq!(
+            request(receiver.try_recv().ok()),
+            serde_json::json!({
+                "method": "SUBSCRIBE",
+                "params": ["ethusdt@ticker", "btcusdt@ticker"],
+                "id": 1
+            })
+        );
+        assert_eq!(
+            request(receiver.try_recv().ok()),
+            serde_json::json!({ "method": "UNSUBSCRIBE", "params": ["btcusdt@ticker"], "id": 2 })
+        );
+    }
+}
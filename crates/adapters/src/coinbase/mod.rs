@@ -2,9 +2,19 @@
 mod models;
 #[allow(unused)]
 pub use models::{
-    CoinbaseChannelMessage, CoinbaseHeartbeatMessage, CoinbaseMessage, CoinbaseRequest,
-    CoinbaseRequestType, CoinbaseResponse, CoinbaseSymbol, CoinbaseTickerMessage,
+    CandleGranularity, CoinbaseCandleMessage, CoinbaseCandlesEvent, CoinbaseCandlesMessage,
+    CoinbaseChannelMessage, CoinbaseChannelMode, CoinbaseErrorMessage, CoinbaseHeartbeatMessage,
+    CoinbaseLevel2Batch, CoinbaseLevel2Message, CoinbaseLevel2Snapshot, CoinbaseMatchMessage,
+    CoinbaseMessage, CoinbaseOrderMessage, CoinbaseProductStatus, CoinbaseRequest,
+    CoinbaseRequestAuth, CoinbaseRequestType, CoinbaseResponse, CoinbaseStatusMessage,
+    CoinbaseSymbol, CoinbaseTickerMessage, CoinbaseUserMatchMessage, CoinbaseUserMessage, Side,
+};
+
+mod trade;
+pub use trade::{
+    sign_request, CoinbaseAccount, CoinbaseAmount, CoinbaseOrder, CoinbaseTradeClient,
+    CreateOrderRequest, OrderConfiguration, OrderSide, OrderStatus,
 };
 
 mod wsclient;
-pub use wsclient::CoinbaseWsClient;
+pub use wsclient::{CoinbaseCredentials, CoinbaseWsClient};
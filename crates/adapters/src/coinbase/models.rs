@@ -7,6 +7,49 @@ pub struct CoinbaseRequest {
     pub request_type: CoinbaseRequestType,
     pub product_ids: Vec<CoinbaseSymbol>,
     pub channels: Vec<String>,
+    /// Signature of authenticated subscriptions, omitted otherwise
+    #[serde(flatten)]
+    pub auth: Option<CoinbaseRequestAuth>,
+}
+
+/// Fields signing a [`CoinbaseRequest`], see `CoinbaseCredentials::sign`.
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CoinbaseRequestAuth {
+    pub signature: String,
+    pub key: String,
+    pub passphrase: String,
+    /// Unix timestamp in seconds the signature was computed at
+    pub timestamp: String,
+}
+
+impl std::fmt::Debug for CoinbaseRequestAuth {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CoinbaseRequestAuth")
+            .field("key", &self.key)
+            .field("timestamp", &self.timestamp)
+            .finish_non_exhaustive()
+    }
+}
+
+/// How often tickers are pushed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CoinbaseChannelMode {
+    /// Every trade, on the `ticker` channel
+    #[default]
+    Realtime,
+    /// Every 5 seconds, on the `ticker_batch` channel
+    Batched,
+}
+
+impl CoinbaseChannelMode {
+    /// Returns the channel pushing the tickers.
+    pub fn ticker_channel(&self) -> &'static str {
+        match self {
+            CoinbaseChannelMode::Realtime => "ticker",
+            CoinbaseChannelMode::Batched => "ticker_batch",
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -20,6 +63,9 @@ pub enum CoinbaseRequestType {
 #[derive(Debug, Clone, Deserialize)]
 #[serde(untagged)]
 pub enum CoinbaseMessage {
+    /// Tried first, the `match` messages of the `user` channel only differ
+    /// from the public ones by the user they belong to
+    User(CoinbaseUserMessage),
     ChannelMessage(CoinbaseChannelMessage),
     Response(CoinbaseResponse),
 }
@@ -27,8 +73,41 @@ pub enum CoinbaseMessage {
 #[derive(Debug, Clone, Deserialize)]
 #[serde(tag = "type", rename_all = "lowercase")]
 pub enum CoinbaseChannelMessage {
+    /// Ticker of the `ticker` channel, or of the `ticker_batch` channel with
+    /// the same payload
+    #[serde(alias = "ticker_batch")]
     Ticker(CoinbaseTickerMessage),
     Heartbeat(CoinbaseHeartbeatMessage),
+    /// Trade of the `matches` channel, the most recent one being sent as
+    /// `last_match` on subscription
+    #[serde(rename = "match", alias = "last_match")]
+    Match(CoinbaseMatchMessage),
+    Status(CoinbaseStatusMessage),
+    /// Order book of the `level2_batch` channel, tagged `snapshot` or
+    /// `l2update`
+    #[serde(untagged)]
+    Level2(CoinbaseLevel2Message),
+    /// Candles of the `candles` channel, in an envelope without a `type`
+    #[serde(untagged)]
+    Candles(CoinbaseCandlesMessage),
+}
+
+impl CoinbaseChannelMessage {
+    /// Returns the product the message is about, none for the status of all
+    /// products.
+    pub fn product_id(&self) -> Option<String> {
+        match self {
+            CoinbaseChannelMessage::Ticker(ticker) => Some(ticker.product_id.to_string()),
+            CoinbaseChannelMessage::Heartbeat(heartbeat) => Some(heartbeat.product_id.clone()),
+            CoinbaseChannelMessage::Match(trade) => Some(trade.product_id.to_string()),
+            CoinbaseChannelMessage::Status(_) => None,
+            CoinbaseChannelMessage::Level2(level2) => Some(level2.product_id().to_string()),
+            CoinbaseChannelMessage::Candles(message) => message
+                .candles()
+                .next()
+                .map(|candle| candle.product_id.to_string()),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -52,6 +131,247 @@ pub struct CoinbaseTickerMessage {
     pub last_size: Decimal,
 }
 
+/// Trade between a maker and a taker order.
+#[derive(Debug, Clone, Deserialize)]
+pub struct CoinbaseMatchMessage {
+    pub trade_id: u64,
+    pub maker_order_id: String,
+    pub taker_order_id: String,
+    /// Side of the maker order, the taker traded the other side
+    pub side: Side,
+    pub size: Decimal,
+    pub price: Decimal,
+    pub product_id: CoinbaseSymbol,
+    pub sequence: u64,
+    #[serde(with = "sikkara_core::timestamp_with_tz_serializer")]
+    pub time: jiff::Timestamp,
+}
+
+/// Message of the `level2_batch` channel, the whole book once subscribed and
+/// then the changes of its levels, batched every 50 milliseconds.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum CoinbaseLevel2Message {
+    Snapshot(CoinbaseLevel2Snapshot),
+    #[serde(rename = "l2update")]
+    Update(CoinbaseLevel2Batch),
+}
+
+impl CoinbaseLevel2Message {
+    pub fn product_id(&self) -> &CoinbaseSymbol {
+        match self {
+            CoinbaseLevel2Message::Snapshot(snapshot) => &snapshot.product_id,
+            CoinbaseLevel2Message::Update(batch) => &batch.product_id,
+        }
+    }
+}
+
+/// Price and size of every level of the book.
+#[derive(Debug, Clone, Deserialize)]
+pub struct CoinbaseLevel2Snapshot {
+    pub product_id: CoinbaseSymbol,
+    /// Bid levels from the best
+    pub bids: Vec<(Decimal, Decimal)>,
+    /// Ask levels from the best
+    pub asks: Vec<(Decimal, Decimal)>,
+}
+
+/// Levels of the book changed since the previous batch.
+#[derive(Debug, Clone, Deserialize)]
+pub struct CoinbaseLevel2Batch {
+    pub product_id: CoinbaseSymbol,
+    /// Side, price and new size of every changed level, a size of zero
+    /// removing the level
+    pub changes: Vec<(Side, Decimal, Decimal)>,
+    #[serde(with = "sikkara_core::timestamp_with_tz_serializer")]
+    pub time: jiff::Timestamp,
+}
+
+/// Envelope of the `candles` channel, the candles of the subscribed products
+/// once subscribed and then their updates.
+#[derive(Debug, Clone, Deserialize)]
+pub struct CoinbaseCandlesMessage {
+    pub channel: String,
+    #[serde(with = "sikkara_core::timestamp_with_tz_serializer")]
+    pub timestamp: jiff::Timestamp,
+    pub sequence_num: u64,
+    pub events: Vec<CoinbaseCandlesEvent>,
+}
+
+impl CoinbaseCandlesMessage {
+    /// Returns the candles of all the events, in order.
+    pub fn candles(&self) -> impl Iterator<Item = &CoinbaseCandleMessage> {
+        self.events.iter().flat_map(|event| event.candles.iter())
+    }
+}
+
+/// Candles of a [`CoinbaseCandlesMessage`], `snapshot` or `update` depending
+/// on `type`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct CoinbaseCandlesEvent {
+    #[serde(rename = "type")]
+    pub event_type: String,
+    pub candles: Vec<CoinbaseCandleMessage>,
+}
+
+/// OHLCV bar of a product, updated until the next one starts.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct CoinbaseCandleMessage {
+    pub product_id: CoinbaseSymbol,
+    /// Unix timestamp in seconds the bar starts at
+    pub start: Decimal,
+    pub open: Decimal,
+    pub high: Decimal,
+    pub low: Decimal,
+    pub close: Decimal,
+    /// Traded size in the base asset
+    pub volume: Decimal,
+}
+
+/// Time span of a candle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CandleGranularity {
+    #[serde(rename = "ONE_MINUTE")]
+    OneMinute,
+    #[serde(rename = "FIVE_MINUTE")]
+    FiveMinutes,
+    #[serde(rename = "FIFTEEN_MINUTE")]
+    FifteenMinutes,
+    #[serde(rename = "THIRTY_MINUTE")]
+    ThirtyMinutes,
+    #[serde(rename = "ONE_HOUR")]
+    OneHour,
+    #[serde(rename = "TWO_HOUR")]
+    TwoHours,
+    #[serde(rename = "SIX_HOUR")]
+    SixHours,
+    #[serde(rename = "ONE_DAY")]
+    OneDay,
+}
+
+impl CandleGranularity {
+    pub fn duration(&self) -> std::time::Duration {
+        let minutes = match self {
+            CandleGranularity::OneMinute => 1,
+            CandleGranularity::FiveMinutes => 5,
+            CandleGranularity::FifteenMinutes => 15,
+            CandleGranularity::ThirtyMinutes => 30,
+            CandleGranularity::OneHour => 60,
+            CandleGranularity::TwoHours => 2 * 60,
+            CandleGranularity::SixHours => 6 * 60,
+            CandleGranularity::OneDay => 24 * 60,
+        };
+        std::time::Duration::from_secs(minutes * 60)
+    }
+}
+
+/// Message of the authenticated `user` channel, reporting the lifecycle of our
+/// own orders. Every message carries the user and profile it belongs to.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum CoinbaseUserMessage {
+    /// The order was accepted by the matching engine
+    Received(CoinbaseOrderMessage),
+    /// The order rests on the book
+    Open(CoinbaseOrderMessage),
+    /// The order left the book, filled or canceled
+    Done(CoinbaseOrderMessage),
+    /// The order was filled, partially or fully
+    Match(CoinbaseUserMatchMessage),
+    /// The size or price of the resting order changed
+    Change(CoinbaseOrderMessage),
+}
+
+/// Lifecycle update of one of our orders, the fields set depending on its type.
+#[derive(Debug, Clone, Deserialize)]
+pub struct CoinbaseOrderMessage {
+    pub user_id: String,
+    pub profile_id: String,
+    pub order_id: String,
+    pub product_id: CoinbaseSymbol,
+    pub side: Side,
+    /// Limit price, none for market orders
+    #[serde(default)]
+    pub price: Option<Decimal>,
+    /// Size of a received order, none for market orders given in funds
+    #[serde(default)]
+    pub size: Option<Decimal>,
+    /// Size left to fill of an open, done or changed order
+    #[serde(default)]
+    pub remaining_size: Option<Decimal>,
+    /// Size of a changed order
+    #[serde(default)]
+    pub new_size: Option<Decimal>,
+    /// Why an order is done, `filled` or `canceled`, or changed, e.g. `STP`
+    #[serde(default)]
+    pub reason: Option<String>,
+    pub sequence: u64,
+    #[serde(with = "sikkara_core::timestamp_with_tz_serializer")]
+    pub time: jiff::Timestamp,
+}
+
+/// Fill of one of our orders, against another order of the book.
+#[derive(Debug, Clone, Deserialize)]
+pub struct CoinbaseUserMatchMessage {
+    pub user_id: String,
+    pub profile_id: String,
+    #[serde(flatten)]
+    pub trade: CoinbaseMatchMessage,
+    /// Our user if our order was the taker
+    #[serde(default)]
+    pub taker_user_id: Option<String>,
+    #[serde(default)]
+    pub taker_fee_rate: Option<Decimal>,
+    #[serde(default)]
+    pub maker_fee_rate: Option<Decimal>,
+}
+
+impl CoinbaseUserMatchMessage {
+    /// Whether our order took liquidity.
+    pub fn is_taker(&self) -> bool { self.taker_user_id.is_some() }
+
+    /// Returns the id of our order.
+    pub fn order_id(&self) -> &str {
+        match self.is_taker() {
+            true => &self.trade.taker_order_id,
+            false => &self.trade.maker_order_id,
+        }
+    }
+
+    /// Returns the side of our order, the match reporting the maker side.
+    pub fn side(&self) -> Side {
+        match (self.is_taker(), self.trade.side) {
+            (false, side) => side,
+            (true, Side::Buy) => Side::Sell,
+            (true, Side::Sell) => Side::Buy,
+        }
+    }
+}
+
+/// Message of the `status` channel, listing the products and their trading
+/// status.
+#[derive(Debug, Clone, Deserialize)]
+pub struct CoinbaseStatusMessage {
+    pub products: Vec<CoinbaseProductStatus>,
+}
+
+/// Status of a product, which is not necessarily one of the [`CoinbaseSymbol`].
+#[derive(Debug, Clone, Deserialize)]
+pub struct CoinbaseProductStatus {
+    pub id: String,
+    pub base_currency: String,
+    pub quote_currency: String,
+    #[serde(default)]
+    pub base_min_size: Option<Decimal>,
+    #[serde(default)]
+    pub base_max_size: Option<Decimal>,
+    /// Trading status, e.g. `online` or `delisted`
+    pub status: String,
+    /// Reason of the status, empty when online
+    #[serde(default)]
+    pub status_message: Option<String>,
+}
+
 #[derive(Debug, Clone, Deserialize)]
 pub struct CoinbaseHeartbeatMessage {
     pub last_trade_id: u64,
@@ -65,6 +385,15 @@ pub struct CoinbaseHeartbeatMessage {
 #[serde(tag = "type", rename_all = "lowercase")]
 pub enum CoinbaseResponse {
     Subscriptions(CoinbaseSubscriptionsResponse),
+    Error(CoinbaseErrorMessage),
+}
+
+/// Error of a request, e.g. a subscription to an unknown product.
+#[derive(Debug, Clone, Deserialize)]
+pub struct CoinbaseErrorMessage {
+    pub message: String,
+    #[serde(default)]
+    pub reason: Option<String>,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -72,20 +401,30 @@ pub struct CoinbaseSubscriptionsResponse {
     pub channels: Vec<CoinbaseSubscription>,
 }
 
+impl CoinbaseSubscriptionsResponse {
+    /// Returns whether the product is subscribed to the channel.
+    pub fn contains(&self, product_id: &str, channel: &str) -> bool {
+        self.channels.iter().any(|subscription| {
+            subscription.name == channel
+                && subscription.product_ids.iter().any(|id| id == product_id)
+        })
+    }
+}
+
 #[derive(Debug, Clone, Deserialize)]
 pub struct CoinbaseSubscription {
     pub name: String,
     pub product_ids: Vec<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum Side {
     Buy,
     Sell,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum CoinbaseSymbol {
     EthUsd,
     BtcUsd,
@@ -139,6 +478,7 @@ mod tests {
             request_type: CoinbaseRequestType::Subscribe,
             product_ids: vec![CoinbaseSymbol::BtcUsd],
             channels: vec!["tickers".to_string()],
+            auth: None,
         };
 
         let serialized = serde_json::to_value(&request).unwrap();
@@ -254,6 +594,7 @@ mod tests {
                 assert_eq!(subscriptions.channels[0].name, "ticker");
                 assert_eq!(subscriptions.channels[0].product_ids, vec!["BTC-USD"]);
             },
+            _ => panic!("Expected CoinbaseResponse::Subscriptions"),
         }
 
         let response: CoinbaseMessage = serde_json::from_value(json).unwrap();
@@ -288,4 +629,340 @@ mod tests {
             _ => panic!("Expected Heartbeat"),
         }
     }
+
+    #[test]
+    fn test_coinbase_match_message_deserialize() {
+        let json = serde_json::json!({
+            "type": "match",
+            "trade_id": 609139974_u64,
+            "maker_order_id": "ac928c66-ca53-498f-9c13-a110027a60e8",
+            "taker_order_id": "132fb6ae-456b-4654-b4e0-d681ac05cea1",
+            "side": "sell",
+            "size": "0.0152",
+            "price": "2687.37",
+            "product_id": "ETH-USD",
+            "sequence": 75193216604_u64,
+            "time": "2025-02-12T21:12:33.912345Z"
+        });
+
+        let message: CoinbaseMessage = serde_json::from_value(json.clone()).unwrap();
+        match message {
+            CoinbaseMessage::ChannelMessage(CoinbaseChannelMessage::Match(trade)) => {
+                assert_eq!(trade.trade_id, 609139974);
+                assert_eq!(trade.maker_order_id, "ac928c66-ca53-498f-9c13-a110027a60e8");
+                assert_eq!(trade.taker_order_id, "132fb6ae-456b-4654-b4e0-d681ac05cea1");
+                assert!(matches!(trade.side, Side::Sell));
+                assert_eq!(trade.size, dec!(0.0152));
+                assert_eq!(trade.price, dec!(2687.37));
+                assert_eq!(trade.product_id, CoinbaseSymbol::EthUsd);
+                assert_eq!(trade.sequence, 75193216604);
+                assert_eq!(trade.time.to_string(), "2025-02-12T21:12:33.912345Z");
+            },
+            _ => panic!("Expected CoinbaseMessage::ChannelMessage with Match"),
+        }
+
+        // The last trade sent on subscription has the same payload
+        let mut json = json;
+        json["type"] = serde_json::json!("last_match");
+        let message: CoinbaseChannelMessage = serde_json::from_value(json).unwrap();
+        assert!(matches!(message, CoinbaseChannelMessage::Match(_)));
+    }
+
+    #[test]
+    fn test_coinbase_status_message_deserialize() {
+        let json = serde_json::json!({
+            "type": "status",
+            "products": [
+                {
+                    "id": "ETH-USD",
+                    "base_currency": "ETH",
+                    "quote_currency": "USD",
+                    "base_min_size": "0.001",
+                    "base_max_size": "10000",
+                    "base_increment": "0.00000001",
+                    "quote_increment": "0.01",
+                    "display_name": "ETH/USD",
+                    "status": "online",
+                    "status_message": "",
+                    "min_market_funds": "1"
+                },
+                {
+                    "id": "XYZ-USD",
+                    "base_currency": "XYZ",
+                    "quote_currency": "USD",
+                    "status": "delisted",
+                    "status_message": "XYZ-USD is delisted"
+                }
+            ],
+            "currencies": []
+        });
+
+        let message: CoinbaseMessage = serde_json::from_value(json).unwrap();
+        match message {
+            CoinbaseMessage::ChannelMessage(CoinbaseChannelMessage::Status(status)) => {
+                assert_eq!(status.products.len(), 2);
+                assert_eq!(status.products[0].id, "ETH-USD");
+                assert_eq!(status.products[0].base_min_size, Some(dec!(0.001)));
+                assert_eq!(status.products[0].base_max_size, Some(dec!(10000)));
+                assert_eq!(status.products[0].status, "online");
+                assert_eq!(status.products[1].base_min_size, None);
+                assert_eq!(status.products[1].status, "delisted");
+                assert_eq!(
+                    status.products[1].status_message.as_deref(),
+                    Some("XYZ-USD is delisted")
+                );
+            },
+            _ => panic!("Expected CoinbaseMessage::ChannelMessage with Status"),
+        }
+    }
+
+    #[test]
+    fn test_coinbase_level2_messages_deserialize() {
+        let snapshot = serde_json::json!({
+            "type": "snapshot",
+            "product_id": "ETH-USD",
+            "bids": [["2686.83", "2.01571863"], ["2686.5", "0.5"]],
+            "asks": [["2687.37", "0.03375599"]]
+        });
+        let message: CoinbaseMessage = serde_json::from_value(snapshot).unwrap();
+        match message {
+            CoinbaseMessage::ChannelMessage(CoinbaseChannelMessage::Level2(
+                CoinbaseLevel2Message::Snapshot(snapshot),
+            )) => {
+                assert_eq!(snapshot.product_id, CoinbaseSymbol::EthUsd);
+                assert_eq!(snapshot.bids[1], (dec!(2686.5), dec!(0.5)));
+                assert_eq!(snapshot.asks, vec![(dec!(2687.37), dec!(0.03375599))]);
+            },
+            _ => panic!("Expected CoinbaseMessage::ChannelMessage with a Level2 snapshot"),
+        }
+
+        let update = serde_json::json!({
+            "type": "l2update",
+            "product_id": "ETH-USD",
+            "changes": [["buy", "2686.90", "1.5"], ["sell", "2687.37", "0"]],
+            "time": "2025-02-12T21:12:33.912345Z"
+        });
+        let message: CoinbaseMessage = serde_json::from_value(update).unwrap();
+        match message {
+            CoinbaseMessage::ChannelMessage(CoinbaseChannelMessage::Level2(
+                CoinbaseLevel2Message::Update(batch),
+            )) => {
+                assert_eq!(
+                    batch.changes,
+                    vec![
+                        (Side::Buy, dec!(2686.90), dec!(1.5)),
+                        (Side::Sell, dec!(2687.37), Decimal::ZERO)
+                    ]
+                );
+                assert_eq!(batch.time, "2025-02-12T21:12:33.912345Z".parse().unwrap());
+            },
+            _ => panic!("Expected CoinbaseMessage::ChannelMessage with a Level2 update"),
+        }
+    }
+
+    #[test]
+    fn test_coinbase_candles_message_deserialize() {
+        let json = serde_json::json!({
+            "channel": "candles",
+            "client_id": "",
+            "timestamp": "2025-02-12T21:15:35.39625135Z",
+            "sequence_num": 0,
+            "events": [
+                {
+                    "type": "snapshot",
+                    "candles": [
+                        {
+                            "start": "1739394900",
+                            "high": "2695.87",
+                            "low": "2686.5",
+                            "open": "2687.37",
+                            "close": "2690.12",
+                            "volume": "132.96498967",
+                            "product_id": "ETH-USD"
+                        }
+                    ]
+                }
+            ]
+        });
+        let message: CoinbaseMessage = serde_json::from_value(json).unwrap();
+        let CoinbaseMessage::ChannelMessage(message) = message else {
+            panic!("Expected CoinbaseMessage::ChannelMessage");
+        };
+        assert_eq!(message.product_id(), Some("ETH-USD".to_string()));
+        let CoinbaseChannelMessage::Candles(candles) = message else {
+            panic!("Expected CoinbaseChannelMessage::Candles");
+        };
+        assert_eq!(candles.sequence_num, 0);
+        assert_eq!(candles.events[0].event_type, "snapshot");
+        assert_eq!(
+            candles.candles().collect::<Vec<_>>(),
+            vec![&CoinbaseCandleMessage {
+                product_id: CoinbaseSymbol::EthUsd,
+                start: dec!(1739394900),
+                open: dec!(2687.37),
+                high: dec!(2695.87),
+                low: dec!(2686.5),
+                close: dec!(2690.12),
+                volume: dec!(132.96498967),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_candle_granularity_serialize() {
+        assert_eq!(
+            serde_json::to_value(CandleGranularity::FiveMinutes).unwrap(),
+            serde_json::json!("FIVE_MINUTE")
+        );
+        assert_eq!(
+            serde_json::from_value::<CandleGranularity>(serde_json::json!("ONE_DAY")).unwrap(),
+            CandleGranularity::OneDay
+        );
+        assert_eq!(CandleGranularity::OneHour.duration().as_secs(), 3600);
+    }
+
+    #[test]
+    fn test_coinbase_error_message_deserialize() {
+        let json = serde_json::json!({
+            "type": "error",
+            "message": "Failed to subscribe",
+            "reason": "XYZ-USD is not a valid product"
+        });
+
+        let message: CoinbaseMessage = serde_json::from_value(json).unwrap();
+        match message {
+            CoinbaseMessage::Response(CoinbaseResponse::Error(error)) => {
+                assert_eq!(error.message, "Failed to subscribe");
+                assert_eq!(error.reason.as_deref(), Some("XYZ-USD is not a valid product"));
+            },
+            _ => panic!("Expected CoinbaseMessage::Response with Error"),
+        }
+    }
+
+    /// Lifecycle of a limit buy on the `user` channel, partially filled as
+    /// taker then canceled.
+    fn user_fixtures() -> Vec<serde_json::Value> {
+        let order = serde_json::json!({
+            "user_id": "5844eceecf7e803e259d0365",
+            "profile_id": "765d1549-9660-4be2-97d4-fa2d65fa3352",
+            "order_id": "d50ec984-77a8-460a-b958-66f114b0de9b",
+            "product_id": "ETH-USD",
+            "side": "buy",
+            "sequence": 75193216610_u64,
+            "time": "2025-02-12T21:12:34.000001Z"
+        });
+        let with = |fields: serde_json::Value| {
+            let mut message = order.clone();
+            message
+                .as_object_mut()
+                .unwrap()
+                .extend(fields.as_object().unwrap().clone());
+            message
+        };
+        vec![
+            with(serde_json::json!({
+                "type": "received", "order_type": "limit", "size": "1.5", "price": "2687.00",
+                "client_oid": ""
+            })),
+            serde_json::json!({
+                "type": "match",
+                "trade_id": 609139980_u64,
+                "maker_order_id": "ac928c66-ca53-498f-9c13-a110027a60e8",
+                "taker_order_id": "d50ec984-77a8-460a-b958-66f114b0de9b",
+                "side": "sell",
+                "size": "0.5",
+                "price": "2686.90",
+                "product_id": "ETH-USD",
+                "sequence": 75193216611_u64,
+                "time": "2025-02-12T21:12:34.000002Z",
+                "user_id": "5844eceecf7e803e259d0365",
+                "profile_id": "765d1549-9660-4be2-97d4-fa2d65fa3352",
+                "taker_user_id": "5844eceecf7e803e259d0365",
+                "taker_profile_id": "765d1549-9660-4be2-97d4-fa2d65fa3352",
+                "taker_fee_rate": "0.006"
+            }),
+            with(serde_json::json!({
+                "type": "open", "price": "2687.00", "remaining_size": "1.0"
+            })),
+            with(serde_json::json!({
+                "type": "change", "reason": "STP", "price": "2687.00", "new_size": "0.8",
+                "old_size": "1.0"
+            })),
+            with(serde_json::json!({
+                "type": "done", "price": "2687.00", "remaining_size": "0.8", "reason": "canceled"
+            })),
+        ]
+    }
+
+    #[test]
+    fn test_coinbase_user_messages_deserialize() {
+        let messages = user_fixtures()
+            .into_iter()
+            .map(|json| match serde_json::from_value(json).unwrap() {
+                CoinbaseMessage::User(message) => message,
+                other => panic!("Expected CoinbaseMessage::User, got {:?}", other),
+            })
+            .collect::<Vec<_>>();
+
+        let CoinbaseUserMessage::Received(received) = &messages[0] else {
+            panic!("Expected Received");
+        };
+        assert_eq!(received.order_id, "d50ec984-77a8-460a-b958-66f114b0de9b");
+        assert_eq!(received.size, Some(dec!(1.5)));
+        assert_eq!(received.price, Some(dec!(2687.00)));
+        assert_eq!(received.side, Side::Buy);
+
+        let CoinbaseUserMessage::Match(fill) = &messages[1] else {
+            panic!("Expected Match");
+        };
+        assert!(fill.is_taker());
+        assert_eq!(fill.order_id(), "d50ec984-77a8-460a-b958-66f114b0de9b");
+        assert_eq!(fill.side(), Side::Buy);
+        assert_eq!(fill.trade.size, dec!(0.5));
+        assert_eq!(fill.trade.price, dec!(2686.90));
+        assert_eq!(fill.taker_fee_rate, Some(dec!(0.006)));
+
+        let CoinbaseUserMessage::Open(open) = &messages[2] else {
+            panic!("Expected Open");
+        };
+        assert_eq!(open.remaining_size, Some(dec!(1.0)));
+
+        let CoinbaseUserMessage::Change(change) = &messages[3] else {
+            panic!("Expected Change");
+        };
+        assert_eq!(change.new_size, Some(dec!(0.8)));
+        assert_eq!(change.reason.as_deref(), Some("STP"));
+
+        let CoinbaseUserMessage::Done(done) = &messages[4] else {
+            panic!("Expected Done");
+        };
+        assert_eq!(done.reason.as_deref(), Some("canceled"));
+        assert_eq!(done.time.to_string(), "2025-02-12T21:12:34.000001Z");
+    }
+
+    #[test]
+    fn test_coinbase_maker_fill_is_told_apart_from_public_match() {
+        let mut json = user_fixtures().remove(1);
+        let object = json.as_object_mut().unwrap();
+        object.remove("taker_user_id");
+        object.remove("taker_profile_id");
+        object.insert("maker_user_id".to_string(), object["user_id"].clone());
+        let message: CoinbaseMessage = serde_json::from_value(json.clone()).unwrap();
+        let CoinbaseMessage::User(CoinbaseUserMessage::Match(fill)) = message else {
+            panic!("Expected a user Match");
+        };
+        assert!(!fill.is_taker());
+        assert_eq!(fill.order_id(), "ac928c66-ca53-498f-9c13-a110027a60e8");
+        assert_eq!(fill.side(), Side::Sell);
+
+        // Without the user, the same payload is a public match
+        let object = json.as_object_mut().unwrap();
+        object.remove("user_id");
+        object.remove("profile_id");
+        let message: CoinbaseMessage = serde_json::from_value(json).unwrap();
+        assert!(matches!(
+            message,
+            CoinbaseMessage::ChannelMessage(CoinbaseChannelMessage::Match(_))
+        ));
+    }
 }
@@ -0,0 +1,619 @@
+//! Coinbase Advanced Trade REST client placing and tracking orders.
+//!
+//! Every request is authenticated with the `CB-ACCESS-*` headers, the
+//! signature being the hex encoded HMAC-SHA256 of
+//! `timestamp + method + path + body` keyed with the API secret.
+
+use std::{fmt, sync::Arc};
+
+use hmac::{Hmac, Mac};
+use reqwest::Method;
+use rust_decimal::Decimal;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use sha2::Sha256;
+use sikkara_core::{AppError, AppResult, Clock, Secret, SystemClock};
+
+use super::CoinbaseSymbol;
+
+const ORDERS_PATH: &str = "/api/v3/brokerage/orders";
+const ACCOUNTS_PATH: &str = "/api/v3/brokerage/accounts";
+
+/// Signs a request, returning the `CB-ACCESS-SIGN` header value.
+///
+/// `path` excludes the query string, `body` is empty for requests without
+/// one.
+pub fn sign_request(
+    secret: &Secret,
+    timestamp: i64,
+    method: &str,
+    path: &str,
+    body: &str,
+) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.expose().as_bytes())
+        .expect("HMAC accepts keys of any length");
+    mac.update(format!("{}{}{}{}", timestamp, method, path, body).as_bytes());
+    hex::encode(mac.finalize().into_bytes())
+}
+
+/// Side of an order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum OrderSide {
+    Buy,
+    Sell,
+}
+
+/// Type and time in force of an order, sizes are in the base asset.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OrderConfiguration {
+    /// Market order, immediate or cancel
+    MarketMarketIoc { base_size: Decimal },
+    /// Limit order, immediate or cancel
+    SorLimitIoc { base_size: Decimal, limit_price: Decimal },
+    /// Limit order, good until cancelled
+    LimitLimitGtc {
+        base_size: Decimal,
+        limit_price: Decimal,
+        #[serde(default)]
+        post_only: bool,
+    },
+}
+
+/// Request placing an order.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CreateOrderRequest {
+    /// Unique id of the order, placing an order twice with the same id has no
+    /// effect
+    pub client_order_id: String,
+    pub product_id: CoinbaseSymbol,
+    pub side: OrderSide,
+    pub order_configuration: OrderConfiguration,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct CreateOrderResponse {
+    success: bool,
+    success_response: Option<CreateOrderSuccess>,
+    error_response: Option<CreateOrderError>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct CreateOrderSuccess {
+    order_id: String,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+struct CreateOrderError {
+    error: String,
+    message: String,
+    preview_failure_reason: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct CancelOrdersResponse {
+    results: Vec<CancelOrderResult>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct CancelOrderResult {
+    success: bool,
+    #[serde(default)]
+    failure_reason: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct GetOrderResponse {
+    order: CoinbaseOrder,
+}
+
+/// Lifecycle state of an order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum OrderStatus {
+    Pending,
+    Open,
+    Filled,
+    Cancelled,
+    Expired,
+    Failed,
+    #[serde(other)]
+    Unknown,
+}
+
+impl OrderStatus {
+    /// Whether the order can no longer be filled.
+    pub fn is_final(&self) -> bool {
+        matches!(
+            self,
+            OrderStatus::Filled
+                | OrderStatus::Cancelled
+                | OrderStatus::Expired
+                | OrderStatus::Failed
+        )
+    }
+}
+
+/// An order as reported by Coinbase.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct CoinbaseOrder {
+    pub order_id: String,
+    pub client_order_id: String,
+    pub product_id: CoinbaseSymbol,
+    pub side: OrderSide,
+    pub status: OrderStatus,
+    /// Filled size in the base asset
+    #[serde(default)]
+    pub filled_size: Decimal,
+    /// Average fill price, zero until filled
+    #[serde(default, deserialize_with = "decimal_or_empty")]
+    pub average_filled_price: Decimal,
+    /// Fees paid in the quote asset
+    #[serde(default)]
+    pub total_fees: Decimal,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct ListAccountsResponse {
+    accounts: Vec<CoinbaseAccount>,
+}
+
+/// An amount of a currency.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct CoinbaseAmount {
+    pub value: Decimal,
+    pub currency: String,
+}
+
+/// A currency account of the portfolio.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct CoinbaseAccount {
+    pub currency: String,
+    /// Amount available for trading
+    pub available_balance: CoinbaseAmount,
+    /// Amount on hold, e.g. reserved by open orders
+    pub hold: CoinbaseAmount,
+}
+
+impl CoinbaseAccount {
+    /// Total amount held, available or on hold.
+    pub fn total(&self) -> Decimal { self.available_balance.value + self.hold.value }
+}
+
+/// Coinbase reports missing decimals as empty strings.
+fn decimal_or_empty<'de, D>(deserializer: D) -> Result<Decimal, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let value = String::deserialize(deserializer)?;
+    if value.is_empty() {
+        return Ok(Decimal::ZERO);
+    }
+    value.parse().map_err(serde::de::Error::custom)
+}
+
+/// Authenticated client of the Coinbase Advanced Trade REST API.
+#[derive(Clone)]
+pub struct CoinbaseTradeClient {
+    client: reqwest::Client,
+    api_url: String,
+    api_key: String,
+    api_secret: Secret,
+    clock: Arc<dyn Clock>,
+}
+
+impl fmt::Debug for CoinbaseTradeClient {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CoinbaseTradeClient")
+            .field("api_url", &self.api_url)
+            .field("api_key", &self.api_key)
+            .finish_non_exhaustive()
+    }
+}
+
+impl CoinbaseTradeClient {
+    pub fn new(api_url: &str, api_key: String, api_secret: Secret) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            api_url: api_url.trim_end_matches('/').to_string(),
+            api_key,
+            api_secret,
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// Uses the given clock for the request timestamps.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Places an order, returning its id.
+    pub async fn place_order(&self, request: &CreateOrderRequest) -> AppResult<String> {
+        let body = serde_json::to_string(request)
+            .map_err(|e| AppError::SerializationError(e.to_string()))?;
+        let response: CreateOrderResponse =
+            self.send(Method::POST, ORDERS_PATH, Some(body)).await?;
+        match response {
+            CreateOrderResponse { success: true, success_response: Some(success), .. } => {
+                Ok(success.order_id)
+            },
+            CreateOrderResponse { error_response, .. } => {
+                let error = error_response.unwrap_or_default();
+                Err(AppError::HttpError(format!(
+                    "coinbase rejected order {}: {} {} {}",
+                    request.client_order_id,
+                    error.error,
+                    error.message,
+                    error.preview_failure_reason
+                ))
+                .into())
+            },
+        }
+    }
+
+    /// Places a limit order, immediate or cancel unless `good_until_cancelled`.
+    pub async fn place_limit_order(
+        &self,
+        client_order_id: String,
+        product_id: CoinbaseSymbol,
+        side: OrderSide,
+        base_size: Decimal,
+        limit_price: Decimal,
+        good_until_cancelled: bool,
+    ) -> AppResult<String> {
+        let order_configuration = if good_until_cancelled {
+            OrderConfiguration::LimitLimitGtc { base_size, limit_price, post_only: false }
+        } else {
+            OrderConfiguration::SorLimitIoc { base_size, limit_price }
+        };
+        self.place_order(&CreateOrderRequest {
+            client_order_id,
+            product_id,
+            side,
+            order_configuration,
+        })
+        .await
+    }
+
+    /// Places a market order, immediate or cancel.
+    pub async fn place_market_order(
+        &self,
+        client_order_id: String,
+        product_id: CoinbaseSymbol,
+        side: OrderSide,
+        base_size: Decimal,
+    ) -> AppResult<String> {
+        self.place_order(&CreateOrderRequest {
+            client_order_id,
+            product_id,
+            side,
+            order_configuration: OrderConfiguration::MarketMarketIoc { base_size },
+        })
+        .await
+    }
+
+    /// Cancels an open order.
+    pub async fn cancel_order(&self, order_id: &str) -> AppResult<()> {
+        let body = serde_json::json!({ "order_ids": [order_id] }).to_string();
+        let path = format!("{}/batch_cancel", ORDERS_PATH);
+        let response: CancelOrdersResponse = self.send(Method::POST, &path, Some(body)).await?;
+        match response.results.first() {
+            Some(CancelOrderResult { success: true, .. }) => Ok(()),
+            result => Err(AppError::HttpError(format!(
+                "coinbase failed to cancel order {}: {}",
+                order_id,
+                result
+                    .map(|r| r.failure_reason.as_str())
+                    .unwrap_or("no result")
+            ))
+            .into()),
+        }
+    }
+
+    /// Returns the current state of an order.
+    pub async fn get_order(&self, order_id: &str) -> AppResult<CoinbaseOrder> {
+        let path = format!("{}/historical/{}", ORDERS_PATH, order_id);
+        let response: GetOrderResponse = self.send(Method::GET, &path, None).await?;
+        Ok(response.order)
+    }
+
+    /// Returns the currency accounts of the portfolio.
+    pub async fn list_accounts(&self) -> AppResult<Vec<CoinbaseAccount>> {
+        let response: ListAccountsResponse = self.send(Method::GET, ACCOUNTS_PATH, None).await?;
+        Ok(response.accounts)
+    }
+
+    async fn send<T: DeserializeOwned>(
+        &self,
+        method: Method,
+        path: &str,
+        body: Option<String>,
+    ) -> AppResult<T> {
+        let timestamp = self.clock.now().as_second();
+        let signature = sign_request(
+            &self.api_secret,
+            timestamp,
+            method.as_str(),
+            path,
+            body.as_deref().unwrap_or_default(),
+        );
+        let mut request = self
+            .client
+            .request(method.clone(), format!("{}{}", self.api_url, path))
+            .header("CB-ACCESS-KEY", &self.api_key)
+            .header("CB-ACCESS-SIGN", signature)
+            .header("CB-ACCESS-TIMESTAMP", timestamp.to_string());
+        if let Some(body) = body {
+            request = request
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .body(body);
+        }
+
+        let response = request.send().await.map_err(|e| {
+            AppError::HttpError(format!("coinbase {} {} failed: {}", method, path, e))
+        })?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(AppError::HttpError(format!(
+                "coinbase {} {} returned status {}: {}",
+                method, path, status, body
+            ))
+            .into());
+        }
+        response.json().await.map_err(|e| {
+            AppError::HttpError(format!(
+                "coinbase {} {} returned invalid response: {}",
+                method, path, e
+            ))
+            .into()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal_macros::dec;
+    use serde_json::json;
+    use sikkara_core::MockClock;
+    use wiremock::{
+        matchers::{body_json, header, method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    use super::*;
+
+    const TIMESTAMP: i64 = 1739404800;
+
+    fn client(server: &MockServer) -> CoinbaseTradeClient {
+        let clock = MockClock::new(jiff::Timestamp::from_second(TIMESTAMP).unwrap());
+        CoinbaseTradeClient::new(
+            &server.uri(),
+            "test-api-key".to_string(),
+            Secret::new("test-api-secret"),
+        )
+        .with_clock(Arc::new(clock))
+    }
+
+    fn sell_request() -> CreateOrderRequest {
+        CreateOrderRequest {
+            client_order_id: "sikarra-1".to_string(),
+            product_id: CoinbaseSymbol::EthUsd,
+            side: OrderSide::Sell,
+            order_configuration: OrderConfiguration::SorLimitIoc {
+                base_size: dec!(0.5),
+                limit_price: dec!(2520.00),
+            },
+        }
+    }
+
+    #[test]
+    fn test_request_signature() {
+        let secret = Secret::new("test-api-secret");
+        let body = serde_json::to_string(&sell_request()).unwrap();
+        assert_eq!(
+            sign_request(&secret, TIMESTAMP, "POST", ORDERS_PATH, &body),
+            "f7114b0ca5e3ff0a0729533310f5f07ddc66afc2e4f5a7c5c7d581fbfeb78f30"
+        );
+        assert_eq!(
+            sign_request(
+                &secret,
+                TIMESTAMP,
+                "GET",
+                "/api/v3/brokerage/orders/historical/11111-000000-000000",
+                ""
+            ),
+            "fc231a9bd02d5240e78ce540ccebc6627a18660e4f04985815ca04f1bc98c036"
+        );
+    }
+
+    #[test]
+    fn test_order_request_serialization() {
+        assert_eq!(
+            serde_json::to_string(&sell_request()).unwrap(),
+            r#"{"client_order_id":"sikarra-1","product_id":"ETH-USD","side":"SELL","order_configuration":{"sor_limit_ioc":{"base_size":"0.5","limit_price":"2520.00"}}}"#
+        );
+
+        let market = OrderConfiguration::MarketMarketIoc { base_size: dec!(1.25) };
+        assert_eq!(
+            serde_json::to_value(&market).unwrap(),
+            json!({ "market_market_ioc": { "base_size": "1.25" } })
+        );
+    }
+
+    #[test]
+    fn test_order_deserialization() {
+        let order: CoinbaseOrder = serde_json::from_value(json!({
+            "order_id": "11111-000000-000000",
+            "client_order_id": "sikarra-1",
+            "product_id": "ETH-USD",
+            "side": "SELL",
+            "status": "OPEN",
+            "filled_size": "0",
+            "average_filled_price": "",
+            "total_fees": "0",
+            "time_in_force": "IMMEDIATE_OR_CANCEL"
+        }))
+        .unwrap();
+        assert_eq!(order.status, OrderStatus::Open);
+        assert_eq!(order.average_filled_price, Decimal::ZERO);
+        assert!(!order.status.is_final());
+
+        let status: OrderStatus = serde_json::from_value(json!("QUEUED")).unwrap();
+        assert_eq!(status, OrderStatus::Unknown);
+    }
+
+    #[test]
+    fn test_debug_does_not_show_secret() {
+        let client = CoinbaseTradeClient::new(
+            "https://api.coinbase.com",
+            "test-api-key".to_string(),
+            Secret::new("test-api-secret"),
+        );
+        assert!(!format!("{:?}", client).contains("test-api-secret"));
+    }
+
+    #[tokio::test]
+    async fn test_order_flow() {
+        let server = MockServer::start().await;
+        let client = client(&server);
+
+        Mock::given(method("POST"))
+            .and(path(ORDERS_PATH))
+            .and(header("CB-ACCESS-KEY", "test-api-key"))
+            .and(header("CB-ACCESS-TIMESTAMP", "1739404800"))
+            .and(header(
+                "CB-ACCESS-SIGN",
+                "f7114b0ca5e3ff0a0729533310f5f07ddc66afc2e4f5a7c5c7d581fbfeb78f30",
+            ))
+            .and(body_json(json!({
+                "client_order_id": "sikarra-1",
+                "product_id": "ETH-USD",
+                "side": "SELL",
+                "order_configuration": {
+                    "sor_limit_ioc": { "base_size": "0.5", "limit_price": "2520.00" }
+                }
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "success": true,
+                "success_response": {
+                    "order_id": "11111-000000-000000",
+                    "product_id": "ETH-USD",
+                    "side": "SELL",
+                    "client_order_id": "sikarra-1"
+                }
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api/v3/brokerage/orders/historical/11111-000000-000000"))
+            .and(header(
+                "CB-ACCESS-SIGN",
+                "fc231a9bd02d5240e78ce540ccebc6627a18660e4f04985815ca04f1bc98c036",
+            ))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "order": {
+                    "order_id": "11111-000000-000000",
+                    "client_order_id": "sikarra-1",
+                    "product_id": "ETH-USD",
+                    "side": "SELL",
+                    "status": "FILLED",
+                    "filled_size": "0.5",
+                    "average_filled_price": "2520.4",
+                    "total_fees": "1.512"
+                }
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/api/v3/brokerage/orders/batch_cancel"))
+            .and(body_json(json!({ "order_ids": ["11111-000000-000000"] })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "results": [{
+                    "success": false,
+                    "failure_reason": "UNKNOWN_CANCEL_ORDER",
+                    "order_id": "11111-000000-000000"
+                }]
+            })))
+            .mount(&server)
+            .await;
+
+        let order_id = client
+            .place_limit_order(
+                "sikarra-1".to_string(),
+                CoinbaseSymbol::EthUsd,
+                OrderSide::Sell,
+                dec!(0.5),
+                dec!(2520.00),
+                false,
+            )
+            .await
+            .unwrap();
+        assert_eq!(order_id, "11111-000000-000000");
+
+        let order = client.get_order(&order_id).await.unwrap();
+        assert_eq!(order.status, OrderStatus::Filled);
+        assert_eq!(order.filled_size, dec!(0.5));
+        assert_eq!(order.average_filled_price, dec!(2520.4));
+
+        // A filled order can no longer be cancelled
+        let error = client.cancel_order(&order_id).await.unwrap_err();
+        assert!(error.to_string().contains("UNKNOWN_CANCEL_ORDER"));
+    }
+
+    #[tokio::test]
+    async fn test_list_accounts() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path(ACCOUNTS_PATH))
+            .and(header("CB-ACCESS-KEY", "test-api-key"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "accounts": [{
+                    "uuid": "8bfc20d7-f7c6-4422-bf07-8243ca4169fe",
+                    "name": "ETH Wallet",
+                    "currency": "ETH",
+                    "available_balance": { "value": "1.25", "currency": "ETH" },
+                    "hold": { "value": "0.5", "currency": "ETH" },
+                    "active": true,
+                    "type": "ACCOUNT_TYPE_CRYPTO"
+                }],
+                "has_next": false,
+                "cursor": "",
+                "size": 1
+            })))
+            .mount(&server)
+            .await;
+
+        let accounts = client(&server).list_accounts().await.unwrap();
+        assert_eq!(accounts.len(), 1);
+        assert_eq!(accounts[0].currency, "ETH");
+        assert_eq!(accounts[0].available_balance.value, dec!(1.25));
+        assert_eq!(accounts[0].total(), dec!(1.75));
+    }
+
+    #[tokio::test]
+    async fn test_rejected_order() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path(ORDERS_PATH))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "success": false,
+                "error_response": {
+                    "error": "INSUFFICIENT_FUND",
+                    "message": "Insufficient balance in source account",
+                    "preview_failure_reason": "PREVIEW_INSUFFICIENT_FUND"
+                }
+            })))
+            .mount(&server)
+            .await;
+
+        let error = client(&server)
+            .place_order(&sell_request())
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("INSUFFICIENT_FUND"));
+    }
+}
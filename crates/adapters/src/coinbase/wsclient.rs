@@ -1,59 +1,374 @@
-use sikkara_core::{AppError, AppResult};
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex, MutexGuard,
+    },
+    time::Duration,
+};
+
+use base64::{prelude::BASE64_STANDARD, Engine};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use sikkara_core::{AppError, AppResult, Secret};
 use sikkara_wsclient::WsCallback;
 use tokio::sync::{broadcast, mpsc};
 use tokio_tungstenite::tungstenite::{Message, Utf8Bytes};
 use tracing::{debug, error, info, warn};
 
 use crate::coinbase::{
-    models::CoinbaseSymbol, CoinbaseMessage, CoinbaseRequest, CoinbaseRequestType,
+    models::CoinbaseSymbol, CandleGranularity, CoinbaseCandleMessage, CoinbaseChannelMessage,
+    CoinbaseChannelMode, CoinbaseMessage, CoinbaseRequest, CoinbaseRequestAuth,
+    CoinbaseRequestType, CoinbaseResponse, CoinbaseUserMessage,
 };
 
+/// Number of messages buffered for the receivers of each product
+const PRODUCT_CHANNEL_CAPACITY: usize = 100;
+
+/// Channel of the order book, whose changes are batched every 50 milliseconds
+const LEVEL2_CHANNEL: &str = "level2_batch";
+
+/// Channel of the OHLCV candles, which only pushes five minute candles
+const CANDLES_CHANNEL: &str = "candles";
+
+/// Credentials signing the subscriptions of a [`CoinbaseWsClient`], required
+/// by the `user` channel and granting higher rate limits.
+#[derive(Debug, Clone)]
+pub struct CoinbaseCredentials {
+    pub api_key: String,
+    /// Base64 encoded API secret
+    pub api_secret: Secret,
+    pub passphrase: Secret,
+}
+
+impl CoinbaseCredentials {
+    /// Signs a request at `timestamp`, in seconds: the signature is the base64
+    /// encoded HMAC-SHA256 of `timestamp + "GET" + "/users/self/verify"` keyed
+    /// by the decoded secret.
+    pub fn sign(&self, timestamp: i64) -> AppResult<CoinbaseRequestAuth> {
+        let secret = BASE64_STANDARD
+            .decode(self.api_secret.expose())
+            .map_err(|_| {
+                AppError::ConfigError("coinbase api secret is not base64 encoded".to_string())
+            })?;
+        let mut mac =
+            Hmac::<Sha256>::new_from_slice(&secret).expect("HMAC accepts keys of any length");
+        mac.update(format!("{}GET/users/self/verify", timestamp).as_bytes());
+
+        Ok(CoinbaseRequestAuth {
+            signature: BASE64_STANDARD.encode(mac.finalize().into_bytes()),
+            key: self.api_key.clone(),
+            passphrase: self.passphrase.expose().to_string(),
+            timestamp: timestamp.to_string(),
+        })
+    }
+}
+
+/// Client of the Coinbase websocket feed.
+///
+/// The server forgets subscriptions when the connection drops, so the client
+/// tracks them, sends them on the first connection and again on every
+/// reconnection. Its clones share the same subscriptions.
+///
+/// The messages of each product are broadcast to the receivers of that
+/// product only, see [`Self::subscribe`], so that a busy product neither
+/// floods nor lags the receivers of the others. The messages of no product,
+/// e.g. subscription responses, are broadcast through `message_broadcaster`
+/// and those of the `user` and `candles` channels separately, see
+/// [`Self::subscribe_user_events`] and [`Self::subscribe_candles`].
+///
+/// Messages are broadcast behind an [`Arc`], so that a message is shared
+/// rather than cloned for each receiver, see `benches/broadcast.rs`.
 #[derive(Debug, Clone)]
 pub struct CoinbaseWsClient {
     ws_url: String,
     sender: mpsc::Sender<Message>,
-    message_broadcaster: broadcast::Sender<CoinbaseMessage>,
+    message_broadcaster: broadcast::Sender<Arc<CoinbaseMessage>>,
+    /// Broadcasters of the messages of each product, by product id
+    product_broadcasters: Arc<Mutex<HashMap<String, broadcast::Sender<Arc<CoinbaseMessage>>>>>,
+    user_broadcaster: broadcast::Sender<CoinbaseUserMessage>,
+    candle_broadcaster: broadcast::Sender<CoinbaseCandleMessage>,
+    subscriptions: Arc<Mutex<Subscriptions>>,
+    /// Messages received while nobody was subscribed
+    dropped_messages: Arc<AtomicU64>,
+    /// Credentials signing every request if authenticated
+    credentials: Option<CoinbaseCredentials>,
+    /// Channel the tickers are subscribed on
+    channel_mode: CoinbaseChannelMode,
+}
+
+/// Subscriptions of a [`CoinbaseWsClient`] and whether they are live.
+#[derive(Debug, Default)]
+struct Subscriptions {
+    /// Requests are only written while connected, they are sent on connect
+    /// otherwise
+    connected: bool,
+    /// Whether a connection was established before, later connections being
+    /// reconnections
+    connected_before: bool,
+    /// Product and channel pairs, in the order they were subscribed
+    entries: Vec<(CoinbaseSymbol, String)>,
+}
+
+impl Subscriptions {
+    /// Returns a subscribe request per channel, for all its products.
+    fn requests(&self) -> Vec<CoinbaseRequest> {
+        let mut requests: Vec<CoinbaseRequest> = Vec::new();
+        for (product_id, channel) in &self.entries {
+            match requests
+                .iter_mut()
+                .find(|request| request.channels[0] == *channel)
+            {
+                Some(request) => request.product_ids.push(product_id.clone()),
+                None => requests.push(CoinbaseRequest {
+                    request_type: CoinbaseRequestType::Subscribe,
+                    product_ids: vec![product_id.clone()],
+                    channels: vec![channel.clone()],
+                    auth: None,
+                }),
+            }
+        }
+        requests
+    }
 }
 
 impl CoinbaseWsClient {
     pub fn new(
         ws_url: String,
         sender: mpsc::Sender<Message>,
-        message_broadcaster: broadcast::Sender<CoinbaseMessage>,
+        message_broadcaster: broadcast::Sender<Arc<CoinbaseMessage>>,
     ) -> Self {
-        CoinbaseWsClient { ws_url, sender, message_broadcaster }
+        CoinbaseWsClient {
+            ws_url,
+            sender,
+            message_broadcaster,
+            product_broadcasters: Arc::new(Mutex::new(HashMap::new())),
+            user_broadcaster: broadcast::channel(100).0,
+            candle_broadcaster: broadcast::channel(100).0,
+            subscriptions: Arc::new(Mutex::new(Subscriptions::default())),
+            dropped_messages: Arc::new(AtomicU64::new(0)),
+            credentials: None,
+            channel_mode: CoinbaseChannelMode::default(),
+        }
     }
 
+    /// Creates a client signing its subscriptions with `credentials`.
+    pub fn new_with_auth(
+        ws_url: String,
+        sender: mpsc::Sender<Message>,
+        message_broadcaster: broadcast::Sender<Arc<CoinbaseMessage>>,
+        credentials: CoinbaseCredentials,
+    ) -> Self {
+        CoinbaseWsClient {
+            credentials: Some(credentials),
+            ..CoinbaseWsClient::new(ws_url, sender, message_broadcaster)
+        }
+    }
+
+    /// Subscribes the tickers on the channel of `channel_mode`, e.g. batched
+    /// to reduce the message volume of busy products.
+    pub fn with_channel_mode(mut self, channel_mode: CoinbaseChannelMode) -> Self {
+        self.channel_mode = channel_mode;
+        self
+    }
+
+    pub fn channel_mode(&self) -> CoinbaseChannelMode { self.channel_mode }
+
+    /// Subscribes the product to the channels and returns a receiver of the
+    /// messages of this product, on all its channels.
     pub fn subscribe(
         &self,
-        product_ids: Vec<CoinbaseSymbol>,
+        product_id: CoinbaseSymbol,
+        channels: Vec<String>,
+    ) -> AppResult<broadcast::Receiver<Arc<CoinbaseMessage>>> {
+        let (receiver, _) = self.subscribe_channels(product_id, channels)?;
+        Ok(receiver)
+    }
+
+    /// Subscribes the product to the channels like [`Self::subscribe`], also
+    /// returning the channels the product was not subscribed to before.
+    fn subscribe_channels(
+        &self,
+        product_id: CoinbaseSymbol,
         channels: Vec<String>,
-    ) -> AppResult<broadcast::Receiver<CoinbaseMessage>> {
-        let request =
-            CoinbaseRequest { request_type: CoinbaseRequestType::Subscribe, product_ids, channels };
-        let message = serde_json::to_string(&request)?;
+    ) -> AppResult<(broadcast::Receiver<Arc<CoinbaseMessage>>, Vec<String>)> {
+        let mut subscriptions = self.subscriptions();
+        let mut added = Vec::new();
+        for channel in &channels {
+            let entry = (product_id.clone(), channel.clone());
+            if !subscriptions.entries.contains(&entry) {
+                subscriptions.entries.push(entry);
+                added.push(channel.clone());
+            }
+        }
+
+        let receiver = self
+            .product_broadcasters()
+            .entry(product_id.to_string())
+            .or_insert_with(|| broadcast::channel(PRODUCT_CHANNEL_CAPACITY).0)
+            .subscribe();
+        if subscriptions.connected {
+            let request = CoinbaseRequest {
+                request_type: CoinbaseRequestType::Subscribe,
+                product_ids: vec![product_id],
+                channels,
+                auth: None,
+            };
+            self.write_request(&request)?;
+        }
+        Ok((receiver, added))
+    }
+
+    /// Subscribes the product to the channels like [`Self::subscribe`], then
+    /// waits for the server to confirm the subscription within `timeout`.
+    ///
+    /// Fails if the server answers with an error, e.g. for an unknown product,
+    /// or does not confirm in time, in which case the channels this call
+    /// subscribed are dropped rather than sent again on every reconnection,
+    /// those subscribed before being kept. Any error answered while
+    /// waiting fails the subscription, errors not telling the request they are
+    /// about.
+    pub async fn subscribe_confirmed(
+        &self,
+        product_id: CoinbaseSymbol,
+        channels: Vec<String>,
+        timeout: Duration,
+    ) -> AppResult<broadcast::Receiver<Arc<CoinbaseMessage>>> {
+        // Listen before subscribing, so that the confirmation cannot be missed
+        let mut responses = self.message_broadcaster.subscribe();
+        let (receiver, added) = self.subscribe_channels(product_id.clone(), channels.clone())?;
+
+        let product = product_id.to_string();
+        let confirmation = async {
+            loop {
+                let message = match responses.recv().await {
+                    Ok(message) => message,
+                    Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(broadcast::error::RecvError::Closed) => {
+                        return Err(AppError::WebSocketError(
+                            "coinbase client closed before confirming the subscription".to_string(),
+                        ));
+                    },
+                };
+                match message.as_ref() {
+                    CoinbaseMessage::Response(CoinbaseResponse::Subscriptions(subscriptions))
+                        if channels
+                            .iter()
+                            .all(|channel| subscriptions.contains(&product, channel)) =>
+                    {
+                        return Ok(());
+                    },
+                    CoinbaseMessage::Response(CoinbaseResponse::Error(error)) => {
+                        return Err(AppError::WebSocketError(format!(
+                            "coinbase rejected the subscription of {} to {:?}: {}, reason: {}",
+                            product,
+                            channels,
+                            error.message,
+                            error.reason.as_deref().unwrap_or("unknown")
+                        )));
+                    },
+                    _ => {},
+                }
+            }
+        };
+        let result = match tokio::time::timeout(timeout, confirmation).await {
+            Ok(result) => result,
+            Err(_) => Err(AppError::WebSocketError(format!(
+                "coinbase did not confirm the subscription of {} to {:?} within {:?}",
+                product, channels, timeout
+            ))),
+        };
+        if let Err(e) = result {
+            self.unsubscribe(vec![product_id], added)?;
+            return Err(e.into());
+        }
+        Ok(receiver)
+    }
+
+    /// Subscribes to the `user` channel of the products, which reports the
+    /// lifecycle of our own orders and requires an authenticated client.
+    pub fn subscribe_user_events(
+        &self,
+        product_ids: Vec<CoinbaseSymbol>,
+    ) -> AppResult<broadcast::Receiver<CoinbaseUserMessage>> {
+        if self.credentials.is_none() {
+            return Err(AppError::ConfigError(
+                "the coinbase user channel requires credentials".to_string(),
+            )
+            .into());
+        }
+        for product_id in product_ids {
+            self.subscribe(product_id, vec!["user".to_string()])?;
+        }
+        Ok(self.user_broadcaster.subscribe())
+    }
 
-        self.write(Message::Text(Utf8Bytes::from(&message)));
-        Ok(self.message_broadcaster.subscribe())
+    /// Subscribes to the order book of the product and returns a receiver of
+    /// the messages of this product, the book being sent whole first and then
+    /// its changes, see [`crate::coinbase::CoinbaseLevel2Message`].
+    pub fn subscribe_order_book(
+        &self,
+        product_id: CoinbaseSymbol,
+    ) -> AppResult<broadcast::Receiver<Arc<CoinbaseMessage>>> {
+        self.subscribe(product_id, vec![LEVEL2_CHANNEL.to_string()])
     }
 
+    /// Subscribes to the OHLCV candles of the products and returns a receiver
+    /// of their candles, one at a time.
+    ///
+    /// The `candles` channel only pushes five minute candles, so any other
+    /// granularity is rejected.
+    pub fn subscribe_candles(
+        &self,
+        product_ids: Vec<CoinbaseSymbol>,
+        granularity: CandleGranularity,
+    ) -> AppResult<broadcast::Receiver<CoinbaseCandleMessage>> {
+        if granularity != CandleGranularity::FiveMinutes {
+            return Err(AppError::ConfigError(format!(
+                "the coinbase candles channel only pushes five minute candles, not {:?}",
+                granularity
+            ))
+            .into());
+        }
+        for product_id in product_ids {
+            self.subscribe(product_id, vec![CANDLES_CHANNEL.to_string()])?;
+        }
+        Ok(self.candle_broadcaster.subscribe())
+    }
+
+    /// Unsubscribes the products from the channels, which is a no-op for those
+    /// not subscribed.
     pub fn unsubscribe(
         &self,
         product_ids: Vec<CoinbaseSymbol>,
         channels: Vec<String>,
     ) -> AppResult<()> {
+        let mut subscriptions = self.subscriptions();
+        let count = subscriptions.entries.len();
+        subscriptions.entries.retain(|(product_id, channel)| {
+            !(product_ids.contains(product_id) && channels.contains(channel))
+        });
+
+        // Nothing to tell the server if nothing was subscribed or it already
+        // forgot the subscriptions
+        if subscriptions.entries.len() == count || !subscriptions.connected {
+            return Ok(());
+        }
         let request = CoinbaseRequest {
             request_type: CoinbaseRequestType::Unsubscribe,
             product_ids,
             channels,
+            auth: None,
         };
-        let message = serde_json::to_string(&request)?;
-
-        self.write(Message::Text(Utf8Bytes::from(&message)))
+        self.write_request(&request)
     }
 
     pub fn ws_url(&self) -> &str { &self.ws_url }
 
+    /// Returns the number of messages dropped because nobody was subscribed,
+    /// e.g. during startup or while a collector restarts.
+    pub fn dropped_messages(&self) -> u64 { self.dropped_messages.load(Ordering::Relaxed) }
+
     pub fn write(&self, message: Message) -> AppResult<()> {
         match self.sender.try_send(message) {
             Ok(_) => Ok(()),
@@ -65,13 +380,82 @@ impl CoinbaseWsClient {
     }
 
     pub fn close(&self) -> AppResult<()> { self.write(Message::Close(None)) }
+
+    fn write_request(&self, request: &CoinbaseRequest) -> AppResult<()> {
+        let message = match &self.credentials {
+            Some(credentials) => {
+                // Signed when written, the server rejects stale timestamps
+                let mut request = request.clone();
+                request.auth = Some(credentials.sign(jiff::Timestamp::now().as_second())?);
+                serde_json::to_string(&request)?
+            },
+            None => serde_json::to_string(request)?,
+        };
+        self.write(Message::Text(Utf8Bytes::from(&message)))
+    }
+
+    /// Writes the subscribe requests of all the tracked subscriptions.
+    fn resubscribe(&self, subscriptions: &Subscriptions) -> AppResult<()> {
+        for request in subscriptions.requests() {
+            debug!("subscribing to {:?} on {:?}", request.channels, request.product_ids);
+            self.write_request(&request)?;
+        }
+        Ok(())
+    }
+
+    fn subscriptions(&self) -> MutexGuard<'_, Subscriptions> {
+        // The subscriptions stay consistent even if a holder panicked
+        self.subscriptions.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn product_broadcasters(
+        &self,
+    ) -> MutexGuard<'_, HashMap<String, broadcast::Sender<Arc<CoinbaseMessage>>>> {
+        self.product_broadcasters
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Broadcasts a message to the receivers of its product, or to those of
+    /// all messages if it is about no product. Returns whether anybody
+    /// received it.
+    fn broadcast(&self, message: CoinbaseMessage) -> bool {
+        let product_id = match &message {
+            CoinbaseMessage::ChannelMessage(message) => message.product_id(),
+            _ => None,
+        };
+        let message = Arc::new(message);
+        match product_id {
+            Some(product_id) => {
+                let broadcaster = self.product_broadcasters().get(&product_id).cloned();
+                broadcaster.is_some_and(|broadcaster| broadcaster.send(message).is_ok())
+            },
+            None => self.message_broadcaster.send(message).is_ok(),
+        }
+    }
 }
 
 #[async_trait::async_trait]
 impl WsCallback for CoinbaseWsClient {
     async fn on_connect(&mut self, timestamp: jiff::Timestamp) -> AppResult<()> {
         info!("Connected to Coinbase WebSocket at {}", self.ws_url);
-        Ok(())
+
+        // Flush the subscriptions made before the first connection, they are
+        // sent again on reconnect otherwise
+        let mut subscriptions = self.subscriptions();
+        subscriptions.connected = true;
+        if subscriptions.connected_before {
+            return Ok(());
+        }
+        subscriptions.connected_before = true;
+        self.resubscribe(&subscriptions)
+    }
+
+    async fn on_reconnect(&mut self, attempt: u32, timestamp: jiff::Timestamp) -> AppResult<()> {
+        // The server forgot the subscriptions of the previous connection
+        info!("Reconnected to Coinbase WebSocket after {} attempts, subscribing again", attempt);
+        let subscriptions = self.subscriptions();
+        self.resubscribe(&subscriptions)
     }
 
     async fn on_message(&mut self, message: Message, receive_at: jiff::Timestamp) -> AppResult<()> {
@@ -88,11 +472,31 @@ impl WsCallback for CoinbaseWsClient {
                         .into());
                     },
                 };
-                self.message_broadcaster
-                    .send(coinbase_message.clone())
-                    .map_err(|e| {
-                        AppError::WebSocketError(format!("Failed to broadcast message: {}", e))
-                    })?;
+                // Errors are about a subscription rather than a product, so no
+                // product receiver sees them
+                if let CoinbaseMessage::Response(CoinbaseResponse::Error(error)) = &coinbase_message
+                {
+                    error!(
+                        "Coinbase error: {}, reason: {}",
+                        error.message,
+                        error.reason.as_deref().unwrap_or("unknown")
+                    );
+                }
+                // Sending only fails without receivers, which is not a reason
+                // to drop the connection
+                let sent = match coinbase_message {
+                    CoinbaseMessage::User(message) => self.user_broadcaster.send(message).is_ok(),
+                    CoinbaseMessage::ChannelMessage(CoinbaseChannelMessage::Candles(message)) => {
+                        message.candles().fold(false, |sent, candle| {
+                            self.candle_broadcaster.send(candle.clone()).is_ok() || sent
+                        })
+                    },
+                    message => self.broadcast(message),
+                };
+                if !sent {
+                    self.dropped_messages.fetch_add(1, Ordering::Relaxed);
+                    debug!("Dropped Coinbase message, nobody is subscribed");
+                }
             },
             Message::Close(_) => {
                 info!("WebSocket connection closed by remote peer");
@@ -112,11 +516,586 @@ impl WsCallback for CoinbaseWsClient {
 
     fn on_disconnect(&mut self) -> AppResult<()> {
         info!("WebSocket connection closed or lost");
+        self.subscriptions().connected = false;
         Ok(())
     }
 
     fn on_heartbeat(&mut self) -> AppResult<()> {
-        info!("Heartbeat check for Coinbase WebSocket connection");
+        // The consumer pings the server, nothing to do at the protocol level
+        debug!("Heartbeat check for Coinbase WebSocket connection");
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use futures::{SinkExt, StreamExt};
+    use sikkara_core::ConstantBackoff;
+    use sikkara_wsclient::WsConsumer;
+    use tokio::net::TcpListener;
+    use tokio_util::sync::CancellationToken;
+
+    use super::*;
+
+    fn new_client(ws_url: &str) -> (CoinbaseWsClient, mpsc::Receiver<Message>) {
+        let (sender, receiver) = mpsc::channel(16);
+        let (broadcaster, _) = broadcast::channel(16);
+        (CoinbaseWsClient::new(ws_url.to_string(), sender, broadcaster), receiver)
+    }
+
+    fn request(message: Option<Message>) -> serde_json::Value {
+        match message {
+            Some(Message::Text(text)) => serde_json::from_str(&text).unwrap(),
+            other => panic!("expected a text message, got {:?}", other),
+        }
+    }
+
+    fn ticker(trade_id: u64) -> String {
+        serde_json::json!({
+            "type": "ticker",
+            "sequence": 75193216603_u64,
+            "product_id": "ETH-USD",
+            "price": "2687.37",
+            "open_24h": "2621.85",
+            "volume_24h": "132964.98967648",
+            "low_24h": "2548",
+            "high_24h": "2695.87",
+            "volume_30d": "5204346.20541330",
+            "best_bid": "2686.83",
+            "best_bid_size": "2.01571863",
+            "best_ask": "2687.37",
+            "best_ask_size": "0.03375599",
+            "side": "buy",
+            "time": "2025-02-12T21:12:33.778451Z",
+            "trade_id": trade_id,
+            "last_size": "0.0007456"
+        })
+        .to_string()
+    }
+
+    #[tokio::test]
+    async fn test_subscriptions_are_sent_on_connect_and_reconnect() {
+        let (mut client, mut receiver) = new_client("wss://coinbase");
+
+        // Subscriptions made while disconnected are queued
+        for product_id in [CoinbaseSymbol::EthUsd, CoinbaseSymbol::BtcUsd] {
+            client
+                .subscribe(product_id, vec!["ticker".to_string()])
+                .unwrap();
+        }
+        client
+            .subscribe(CoinbaseSymbol::EthUsd, vec!["heartbeat".to_string()])
+            .unwrap();
+        client
+            .unsubscribe(vec![CoinbaseSymbol::BtcUsd], vec!["ticker".to_string()])
+            .unwrap();
+        assert!(receiver.try_recv().is_err());
+
+        client.on_connect(jiff::Timestamp::now()).await.unwrap();
+        assert_eq!(
+            request(receiver.try_recv().ok()),
+            serde_json::json!({"type": "subscribe", "product_ids": ["ETH-USD"], "channels": ["ticker"]})
+        );
+        assert_eq!(
+            request(receiver.try_recv().ok()),
+            serde_json::json!({"type": "subscribe", "product_ids": ["ETH-USD"], "channels": ["heartbeat"]})
+        );
+        assert!(receiver.try_recv().is_err());
+
+        // Subscriptions made while connected are sent right away
+        client
+            .subscribe(CoinbaseSymbol::BtcUsd, vec!["ticker".to_string()])
+            .unwrap();
+        assert_eq!(
+            request(receiver.try_recv().ok()),
+            serde_json::json!({"type": "subscribe", "product_ids": ["BTC-USD"], "channels": ["ticker"]})
+        );
+
+        // And all of them are sent again after a reconnect, including those made
+        // while disconnected
+        client.on_disconnect().unwrap();
+        client
+            .subscribe(CoinbaseSymbol::EthUsdt, vec!["ticker".to_string()])
+            .unwrap();
+        client.on_connect(jiff::Timestamp::now()).await.unwrap();
+        assert!(receiver.try_recv().is_err());
+        client
+            .on_reconnect(1, jiff::Timestamp::now())
+            .await
+            .unwrap();
+        assert_eq!(
+            request(receiver.try_recv().ok()),
+            serde_json::json!({
+                "type": "subscribe",
+                "product_ids": ["ETH-USD", "BTC-USD", "ETH-USDT"],
+                "channels": ["ticker"]
+            })
+        );
+        assert_eq!(request(receiver.try_recv().ok())["channels"], serde_json::json!(["heartbeat"]));
+    }
+
+    fn credentials() -> CoinbaseCredentials {
+        CoinbaseCredentials {
+            api_key: "test-api-key".to_string(),
+            // "sikarra-test-websocket-secret-0123456789"
+            api_secret: Secret::new("c2lrYXJyYS10ZXN0LXdlYnNvY2tldC1zZWNyZXQtMDEyMzQ1Njc4OQ=="),
+            passphrase: Secret::new("test-passphrase"),
+        }
+    }
+
+    #[test]
+    fn test_credentials_sign_the_verify_path() {
+        let auth = credentials().sign(1739394753).unwrap();
+        assert_eq!(auth.signature, "wB7a1RrZLSctOHyrIjzHmSmE8Z379/0/3Ehx9r4q4hQ=");
+        assert_eq!(auth.key, "test-api-key");
+        assert_eq!(auth.passphrase, "test-passphrase");
+        assert_eq!(auth.timestamp, "1739394753");
+        assert!(!format!("{:?}", auth).contains("test-passphrase"));
+
+        let invalid =
+            CoinbaseCredentials { api_secret: Secret::new("not base64!"), ..credentials() };
+        assert!(invalid.sign(1739394753).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_authenticated_subscriptions_are_signed() {
+        let (sender, mut receiver) = mpsc::channel(4);
+        let (broadcaster, _) = broadcast::channel(4);
+        let mut client = CoinbaseWsClient::new_with_auth(
+            "wss://coinbase".to_string(),
+            sender,
+            broadcaster,
+            credentials(),
+        );
+        client
+            .subscribe(CoinbaseSymbol::EthUsd, vec!["user".to_string()])
+            .unwrap();
+        client.on_connect(jiff::Timestamp::now()).await.unwrap();
+
+        let request = request(receiver.try_recv().ok());
+        assert_eq!(request["type"], "subscribe");
+        assert_eq!(request["channels"], serde_json::json!(["user"]));
+        assert_eq!(request["key"], "test-api-key");
+        assert_eq!(request["passphrase"], "test-passphrase");
+        let timestamp: i64 = request["timestamp"].as_str().unwrap().parse().unwrap();
+        assert_eq!(request["signature"], credentials().sign(timestamp).unwrap().signature.as_str());
+
+        // Unauthenticated requests carry none of the fields
+        let (mut client, mut receiver) = new_client("wss://coinbase");
+        client.on_connect(jiff::Timestamp::now()).await.unwrap();
+        client
+            .subscribe(CoinbaseSymbol::EthUsd, vec!["ticker".to_string()])
+            .unwrap();
+        assert_eq!(
+            request(receiver.try_recv().ok()),
+            serde_json::json!({"type": "subscribe", "product_ids": ["ETH-USD"], "channels": ["ticker"]})
+        );
+    }
+
+    #[tokio::test]
+    async fn test_user_events_are_broadcast_separately() {
+        let (mut client, _) = new_client("wss://coinbase");
+        assert!(client
+            .subscribe_user_events(vec![CoinbaseSymbol::EthUsd])
+            .is_err());
+
+        let (sender, mut receiver) = mpsc::channel(4);
+        let (broadcaster, _) = broadcast::channel(4);
+        let mut client = CoinbaseWsClient::new_with_auth(
+            "wss://coinbase".to_string(),
+            sender,
+            broadcaster,
+            credentials(),
+        );
+        client.on_connect(jiff::Timestamp::now()).await.unwrap();
+        let mut user_events = client
+            .subscribe_user_events(vec![CoinbaseSymbol::EthUsd])
+            .unwrap();
+        let mut messages = client.message_broadcaster.subscribe();
+        assert_eq!(request(receiver.try_recv().ok())["channels"], serde_json::json!(["user"]));
+
+        let done = serde_json::json!({
+            "type": "done",
+            "user_id": "5844eceecf7e803e259d0365",
+            "profile_id": "765d1549-9660-4be2-97d4-fa2d65fa3352",
+            "order_id": "d50ec984-77a8-460a-b958-66f114b0de9b",
+            "product_id": "ETH-USD",
+            "side": "buy",
+            "reason": "filled",
+            "remaining_size": "0",
+            "sequence": 75193216612_u64,
+            "time": "2025-02-12T21:12:34.000003Z"
+        });
+        client
+            .on_message(Message::Text(Utf8Bytes::from(done.to_string())), jiff::Timestamp::now())
+            .await
+            .unwrap();
+        assert!(matches!(user_events.try_recv(), Ok(CoinbaseUserMessage::Done(_))));
+        assert!(messages.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_messages_without_subscribers_are_dropped() {
+        let (mut client, _receiver) = new_client("wss://coinbase");
+
+        let message = Message::Text(Utf8Bytes::from(ticker(1)));
+        assert!(client
+            .on_message(message, jiff::Timestamp::now())
+            .await
+            .is_ok());
+        assert_eq!(client.dropped_messages(), 1);
+
+        let mut messages = client
+            .subscribe(CoinbaseSymbol::EthUsd, vec!["ticker".to_string()])
+            .unwrap();
+        let message = Message::Text(Utf8Bytes::from(ticker(2)));
+        client
+            .on_message(message, jiff::Timestamp::now())
+            .await
+            .unwrap();
+        assert!(messages.try_recv().is_ok());
+        assert_eq!(client.dropped_messages(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_messages_are_broadcast_to_their_product_only() {
+        let (mut client, _receiver) = new_client("wss://coinbase");
+        let mut eth = client
+            .subscribe(CoinbaseSymbol::EthUsd, vec!["ticker".to_string()])
+            .unwrap();
+        let mut btc = client
+            .subscribe(CoinbaseSymbol::BtcUsd, vec!["heartbeat".to_string()])
+            .unwrap();
+        let mut others = client.message_broadcaster.subscribe();
+
+        let heartbeat = serde_json::json!({
+            "type": "heartbeat", "last_trade_id": 1, "product_id": "BTC-USD",
+            "sequence": 1, "time": "2025-02-12T21:12:33.912345Z"
+        });
+        let subscriptions = serde_json::json!({
+            "type": "subscriptions",
+            "channels": [{ "name": "ticker", "product_ids": ["ETH-USD"] }]
+        });
+        for text in [ticker(1), heartbeat.to_string(), subscriptions.to_string()] {
+            client
+                .on_message(Message::Text(Utf8Bytes::from(text)), jiff::Timestamp::now())
+                .await
+                .unwrap();
+        }
+
+        assert!(matches!(
+            eth.try_recv().unwrap().as_ref(),
+            CoinbaseMessage::ChannelMessage(CoinbaseChannelMessage::Ticker(_))
+        ));
+        assert!(eth.try_recv().is_err());
+        assert!(matches!(
+            btc.try_recv().unwrap().as_ref(),
+            CoinbaseMessage::ChannelMessage(CoinbaseChannelMessage::Heartbeat(_))
+        ));
+        assert!(btc.try_recv().is_err());
+        assert!(matches!(others.try_recv().unwrap().as_ref(), CoinbaseMessage::Response(_)));
+        assert_eq!(client.dropped_messages(), 0);
+    }
+
+    /// Subscribes the client confirming within `timeout` in the background,
+    /// then answers with `response` once it listens.
+    async fn subscribe_confirmed_answering(
+        client: &CoinbaseWsClient,
+        timeout: Duration,
+        response: Option<serde_json::Value>,
+    ) -> AppResult<broadcast::Receiver<Arc<CoinbaseMessage>>> {
+        let subscriber = client.clone();
+        let subscription = tokio::spawn(async move {
+            subscriber
+                .subscribe_confirmed(CoinbaseSymbol::EthUsd, vec!["ticker".to_string()], timeout)
+                .await
+        });
+        while client.message_broadcaster.receiver_count() == 0 {
+            tokio::task::yield_now().await;
+        }
+        if let Some(response) = response {
+            let response: CoinbaseMessage = serde_json::from_value(response).unwrap();
+            client.message_broadcaster.send(Arc::new(response)).unwrap();
+        }
+        subscription.await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_subscription_is_confirmed_by_the_subscriptions_response() {
+        let (client, _receiver) = new_client("wss://coinbase");
+        let subscriptions = serde_json::json!({
+            "type": "subscriptions",
+            "channels": [
+                { "name": "heartbeat", "product_ids": ["ETH-USD"] },
+                { "name": "ticker", "product_ids": ["BTC-USD", "ETH-USD"] }
+            ]
+        });
+        let result =
+            subscribe_confirmed_answering(&client, Duration::from_secs(5), Some(subscriptions))
+                .await;
+        assert!(result.is_ok());
+        assert_eq!(
+            client.subscriptions().entries,
+            vec![(CoinbaseSymbol::EthUsd, "ticker".to_string())]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_rejected_subscription_fails_and_is_dropped() {
+        let (client, _receiver) = new_client("wss://coinbase");
+        let error = serde_json::json!({
+            "type": "error",
+            "message": "Failed to subscribe",
+            "reason": "ETH-USD is not a valid product"
+        });
+        let error = subscribe_confirmed_answering(&client, Duration::from_secs(5), Some(error))
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("is not a valid product"));
+        assert!(client.subscriptions().entries.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_failed_confirmation_keeps_earlier_subscriptions() {
+        let (client, mut receiver) = new_client("wss://coinbase");
+        client.subscriptions().connected = true;
+        client
+            .subscribe(CoinbaseSymbol::EthUsd, vec!["ticker".to_string()])
+            .unwrap();
+        assert_eq!(request(receiver.recv().await)["type"], "subscribe");
+
+        let error = subscribe_confirmed_answering(&client, Duration::from_millis(50), None)
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("did not confirm"));
+        // The subscription is requested again, but not unsubscribed
+        assert_eq!(request(receiver.recv().await)["type"], "subscribe");
+        assert!(receiver.try_recv().is_err());
+        assert_eq!(
+            client.subscriptions().entries,
+            vec![(CoinbaseSymbol::EthUsd, "ticker".to_string())]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_unconfirmed_subscription_times_out() {
+        let (client, _receiver) = new_client("wss://coinbase");
+        // Subscriptions of another product do not confirm it
+        let subscriptions = serde_json::json!({
+            "type": "subscriptions",
+            "channels": [{ "name": "ticker", "product_ids": ["BTC-USD"] }]
+        });
+        let error =
+            subscribe_confirmed_answering(&client, Duration::from_millis(50), Some(subscriptions))
+                .await
+                .unwrap_err();
+        assert!(error.to_string().contains("did not confirm"));
+        assert!(client.subscriptions().entries.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_order_book_is_subscribed_on_the_level2_batch_channel() {
+        let (mut client, mut receiver) = new_client("wss://coinbase");
+        client.on_connect(jiff::Timestamp::now()).await.unwrap();
+        let mut book = client.subscribe_order_book(CoinbaseSymbol::EthUsd).unwrap();
+        assert_eq!(
+            request(receiver.try_recv().ok()),
+            serde_json::json!({
+                "type": "subscribe",
+                "product_ids": ["ETH-USD"],
+                "channels": ["level2_batch"]
+            })
+        );
+
+        let update = serde_json::json!({
+            "type": "l2update",
+            "product_id": "ETH-USD",
+            "changes": [["buy", "2686.90", "1.5"]],
+            "time": "2025-02-12T21:12:33.912345Z"
+        });
+        client
+            .on_message(Message::Text(Utf8Bytes::from(update.to_string())), jiff::Timestamp::now())
+            .await
+            .unwrap();
+        assert!(matches!(
+            book.try_recv().unwrap().as_ref(),
+            CoinbaseMessage::ChannelMessage(CoinbaseChannelMessage::Level2(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_candles_are_broadcast_one_at_a_time() {
+        let (mut client, mut receiver) = new_client("wss://coinbase");
+        client.on_connect(jiff::Timestamp::now()).await.unwrap();
+        assert!(client
+            .subscribe_candles(vec![CoinbaseSymbol::EthUsd], CandleGranularity::OneMinute)
+            .is_err());
+        let mut candles = client
+            .subscribe_candles(
+                vec![CoinbaseSymbol::EthUsd, CoinbaseSymbol::BtcUsd],
+                CandleGranularity::FiveMinutes,
+            )
+            .unwrap();
+        for product_id in ["ETH-USD", "BTC-USD"] {
+            assert_eq!(
+                request(receiver.try_recv().ok()),
+                serde_json::json!({
+                    "type": "subscribe",
+                    "product_ids": [product_id],
+                    "channels": ["candles"]
+                })
+            );
+        }
+
+        let candle = |product_id, close| {
+            serde_json::json!({
+                "start": "1739394900",
+                "high": "2695.87",
+                "low": "2686.5",
+                "open": "2687.37",
+                "close": close,
+                "volume": "132.96498967",
+                "product_id": product_id
+            })
+        };
+        let update = serde_json::json!({
+            "channel": "candles",
+            "client_id": "",
+            "timestamp": "2025-02-12T21:15:35.39625135Z",
+            "sequence_num": 1,
+            "events": [{
+                "type": "update",
+                "candles": [candle("ETH-USD", "2690.12"), candle("BTC-USD", "96512.3")]
+            }]
+        });
+        client
+            .on_message(Message::Text(Utf8Bytes::from(update.to_string())), jiff::Timestamp::now())
+            .await
+            .unwrap();
+        assert_eq!(candles.try_recv().unwrap().product_id, CoinbaseSymbol::EthUsd);
+        assert_eq!(candles.try_recv().unwrap().product_id, CoinbaseSymbol::BtcUsd);
+        assert_eq!(client.dropped_messages(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_unsubscribe_writes_a_request_once() {
+        let (mut client, mut receiver) = new_client("wss://coinbase");
+        for product_id in [CoinbaseSymbol::EthUsd, CoinbaseSymbol::BtcUsd] {
+            client
+                .subscribe(product_id, vec!["ticker".to_string()])
+                .unwrap();
+        }
+        client.on_connect(jiff::Timestamp::now()).await.unwrap();
+        receiver.try_recv().unwrap();
+
+        client
+            .unsubscribe(vec![CoinbaseSymbol::BtcUsd], vec!["ticker".to_string()])
+            .unwrap();
+        assert_eq!(
+            request(receiver.try_recv().ok()),
+            serde_json::json!({
+                "type": "unsubscribe",
+                "product_ids": ["BTC-USD"],
+                "channels": ["ticker"]
+            })
+        );
+
+        // Unsubscribing again is a no-op
+        client
+            .unsubscribe(vec![CoinbaseSymbol::BtcUsd], vec!["ticker".to_string()])
+            .unwrap();
+        assert!(receiver.try_recv().is_err());
+
+        // And the product is not subscribed again on reconnect
+        client.on_disconnect().unwrap();
+        client.on_connect(jiff::Timestamp::now()).await.unwrap();
+        client
+            .on_reconnect(1, jiff::Timestamp::now())
+            .await
+            .unwrap();
+        assert_eq!(
+            request(receiver.try_recv().ok())["product_ids"],
+            serde_json::json!(["ETH-USD"])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_tickers_resume_after_reconnect() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let ws_url = format!("ws://{}", listener.local_addr().unwrap());
+
+        // Fake server sending a ticker to each subscription, dropping the first
+        // connection right after
+        let server = tokio::spawn(async move {
+            let mut requests = Vec::new();
+            for trade_id in [1_u64, 2] {
+                let (stream, _) = listener.accept().await.unwrap();
+                let mut ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+                let text = loop {
+                    match ws.next().await {
+                        Some(Ok(Message::Text(text))) => break text,
+                        Some(Ok(_)) => continue,
+                        other => panic!("expected a subscribe request, got {:?}", other),
+                    }
+                };
+                requests.push(serde_json::from_str::<serde_json::Value>(&text).unwrap());
+                ws.send(Message::Text(Utf8Bytes::from(ticker(trade_id))))
+                    .await
+                    .unwrap();
+
+                if trade_id == 2 {
+                    // Keep the second connection until the client closes it
+                    while let Some(Ok(message)) = ws.next().await {
+                        if message.is_close() {
+                            break;
+                        }
+                    }
+                }
+            }
+            requests
+        });
+
+        let (client, receiver) = new_client(&ws_url);
+        let mut messages = client
+            .subscribe(CoinbaseSymbol::EthUsd, vec!["ticker".to_string()])
+            .unwrap();
+        let shutdown = CancellationToken::new();
+        let consumer = WsConsumer::new(
+            ws_url,
+            client.clone(),
+            1000,
+            ConstantBackoff::new(Duration::from_millis(10), Some(5)),
+            receiver,
+        );
+        let stats = consumer.stats_handle();
+        let consumer = consumer.spawn(shutdown.clone());
+
+        for expected_trade_id in [1_u64, 2] {
+            let message = tokio::time::timeout(Duration::from_secs(5), messages.recv())
+                .await
+                .expect("ticker not received")
+                .unwrap();
+            match message.as_ref() {
+                CoinbaseMessage::ChannelMessage(CoinbaseChannelMessage::Ticker(ticker)) => {
+                    assert_eq!(ticker.trade_id, expected_trade_id);
+                },
+                other => panic!("expected a ticker, got {:?}", other),
+            }
+        }
+
+        shutdown.cancel();
+        consumer.await.unwrap().unwrap();
+        let stats = stats.lock().unwrap().clone();
+        assert!(stats.messages_received >= 2);
+        assert!(stats.bytes_received > 0);
+        assert!(stats.last_message_at.is_some());
+        let subscribe = serde_json::json!({
+            "type": "subscribe",
+            "product_ids": ["ETH-USD"],
+            "channels": ["ticker"]
+        });
+        assert_eq!(server.await.unwrap(), vec![subscribe.clone(), subscribe]);
+    }
+}
@@ -0,0 +1,14 @@
+//! ERC20 token bindings
+
+use alloy::sol;
+
+sol! {
+    #[derive(Debug)]
+    #[sol(rpc)]
+    interface IERC20 {
+        function balanceOf(address owner) external view returns (uint256);
+        function decimals() external view returns (uint8);
+        function allowance(address owner, address spender) external view returns (uint256);
+        function approve(address spender, uint256 amount) external returns (bool);
+    }
+}
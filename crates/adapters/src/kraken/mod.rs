@@ -0,0 +1,11 @@
+#[allow(unused)]
+mod models;
+#[allow(unused)]
+pub use models::{
+    KrakenChannelMessage, KrakenMessage, KrakenRequest, KrakenRequestMethod, KrakenResponse,
+    KrakenStatus, KrakenStatusUpdate, KrakenSubscription, KrakenSymbol, KrakenTickerMessage,
+    KrakenTickerUpdate, KrakenUpdateType,
+};
+
+mod wsclient;
+pub use wsclient::KrakenWsClient;
@@ -0,0 +1,269 @@
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+/// Request of the Kraken websocket v2 API, e.g. `{"method": "subscribe",
+/// "params": {"channel": "ticker", "symbol": ["ETH/USD"]}, "req_id": 1}`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KrakenRequest {
+    pub method: KrakenRequestMethod,
+    pub params: KrakenSubscription,
+    /// Identifier echoed back in the response
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub req_id: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum KrakenRequestMethod {
+    Subscribe,
+    Unsubscribe,
+}
+
+/// Channel and symbols of a subscription.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KrakenSubscription {
+    pub channel: String,
+    pub symbol: Vec<KrakenSymbol>,
+}
+
+/// Message received from Kraken, either pushed on a channel or in response to
+/// a [`KrakenRequest`].
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum KrakenMessage {
+    ChannelMessage(KrakenChannelMessage),
+    Response(KrakenResponse),
+}
+
+/// Message pushed on a channel, which is named by its `channel` field.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "channel", rename_all = "lowercase")]
+pub enum KrakenChannelMessage {
+    Ticker(KrakenTickerUpdate),
+    Heartbeat,
+    Status(KrakenStatusUpdate),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum KrakenUpdateType {
+    /// Full state, sent once after subscribing
+    Snapshot,
+    Update,
+}
+
+/// Ticker channel message, carrying the tickers of one or more symbols.
+#[derive(Debug, Clone, Deserialize)]
+pub struct KrakenTickerUpdate {
+    #[serde(rename = "type")]
+    pub update_type: KrakenUpdateType,
+    pub data: Vec<KrakenTickerMessage>,
+}
+
+/// Ticker of a symbol. Prices and quantities are sent as JSON numbers.
+#[derive(Debug, Clone, Deserialize)]
+pub struct KrakenTickerMessage {
+    pub symbol: KrakenSymbol,
+    pub bid: Decimal,
+    pub bid_qty: Decimal,
+    pub ask: Decimal,
+    pub ask_qty: Decimal,
+    /// Price of the last trade
+    pub last: Decimal,
+    /// Traded volume over the last 24 hours in the base asset
+    pub volume: Decimal,
+    /// Volume weighted average price over the last 24 hours
+    pub vwap: Decimal,
+    pub low: Decimal,
+    pub high: Decimal,
+    pub change: Decimal,
+    pub change_pct: Decimal,
+    #[serde(with = "sikkara_core::timestamp_with_tz_serializer")]
+    pub timestamp: jiff::Timestamp,
+}
+
+/// Status channel message, sent on connection and on trading status changes.
+#[derive(Debug, Clone, Deserialize)]
+pub struct KrakenStatusUpdate {
+    pub data: Vec<KrakenStatus>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct KrakenStatus {
+    /// Trading status of the exchange, e.g. `online` or `maintenance`
+    pub system: String,
+    pub api_version: String,
+    /// Identifier of the connection, a large integer
+    pub connection_id: u64,
+    pub version: String,
+}
+
+/// Response to a [`KrakenRequest`].
+#[derive(Debug, Clone, Deserialize)]
+pub struct KrakenResponse {
+    pub method: KrakenRequestMethod,
+    pub success: bool,
+    /// Reason the request failed
+    pub error: Option<String>,
+    pub req_id: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum KrakenSymbol {
+    EthUsd,
+    BtcUsd,
+    EthUsdt,
+}
+
+impl std::fmt::Display for KrakenSymbol {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            KrakenSymbol::EthUsd => write!(f, "ETH/USD"),
+            KrakenSymbol::BtcUsd => write!(f, "BTC/USD"),
+            KrakenSymbol::EthUsdt => write!(f, "ETH/USDT"),
+        }
+    }
+}
+
+impl Serialize for KrakenSymbol {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for KrakenSymbol {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        match s.as_str() {
+            "ETH/USD" => Ok(KrakenSymbol::EthUsd),
+            "BTC/USD" => Ok(KrakenSymbol::BtcUsd),
+            "ETH/USDT" => Ok(KrakenSymbol::EthUsdt),
+            _ => Err(serde::de::Error::unknown_variant(&s, &["ETH/USD", "BTC/USD", "ETH/USDT"])),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal_macros::dec;
+
+    use super::*;
+
+    #[test]
+    fn test_kraken_subscribe_request_serialize() {
+        let request = KrakenRequest {
+            method: KrakenRequestMethod::Subscribe,
+            params: KrakenSubscription {
+                channel: "ticker".to_string(),
+                symbol: vec![KrakenSymbol::EthUsd, KrakenSymbol::BtcUsd],
+            },
+            req_id: Some(1),
+        };
+
+        let serialized = serde_json::to_value(&request).unwrap();
+        assert_eq!(
+            serialized,
+            serde_json::json!({
+                "method": "subscribe",
+                "params": {"channel": "ticker", "symbol": ["ETH/USD", "BTC/USD"]},
+                "req_id": 1
+            })
+        );
+    }
+
+    #[test]
+    fn test_kraken_ticker_message_deserialize() {
+        let json = serde_json::json!({
+            "channel": "ticker",
+            "type": "update",
+            "data": [{
+                "symbol": "ETH/USD",
+                "bid": 2686.83,
+                "bid_qty": 2.01571863,
+                "ask": 2687.37,
+                "ask_qty": 0.03375599,
+                "last": 2687.37,
+                "volume": 132964.98967648,
+                "vwap": 2630.12,
+                "low": 2548.0,
+                "high": 2695.87,
+                "change": 65.52,
+                "change_pct": 2.5,
+                "timestamp": "2025-02-12T21:12:33.778451Z"
+            }]
+        });
+
+        let message: KrakenMessage = serde_json::from_value(json).unwrap();
+        match message {
+            KrakenMessage::ChannelMessage(KrakenChannelMessage::Ticker(update)) => {
+                assert_eq!(update.update_type, KrakenUpdateType::Update);
+                assert_eq!(update.data.len(), 1);
+                let ticker = &update.data[0];
+                assert_eq!(ticker.symbol, KrakenSymbol::EthUsd);
+                assert_eq!(ticker.bid, dec!(2686.83));
+                assert_eq!(ticker.bid_qty, dec!(2.01571863));
+                assert_eq!(ticker.ask, dec!(2687.37));
+                assert_eq!(ticker.last, dec!(2687.37));
+                assert_eq!(ticker.volume, dec!(132964.98967648));
+                assert_eq!(ticker.vwap, dec!(2630.12));
+                assert_eq!(ticker.timestamp.to_string(), "2025-02-12T21:12:33.778451Z");
+            },
+            _ => panic!("Expected KrakenChannelMessage::Ticker"),
+        }
+    }
+
+    #[test]
+    fn test_kraken_heartbeat_and_status_deserialize() {
+        let message: KrakenMessage =
+            serde_json::from_value(serde_json::json!({"channel": "heartbeat"})).unwrap();
+        assert!(matches!(message, KrakenMessage::ChannelMessage(KrakenChannelMessage::Heartbeat)));
+
+        let json = serde_json::json!({
+            "channel": "status",
+            "type": "update",
+            "data": [{
+                "version": "2.0.9",
+                "system": "online",
+                "api_version": "v2",
+                "connection_id": 12393906104898154338_u64
+            }]
+        });
+        let message: KrakenMessage = serde_json::from_value(json).unwrap();
+        match message {
+            KrakenMessage::ChannelMessage(KrakenChannelMessage::Status(status)) => {
+                assert_eq!(status.data[0].system, "online");
+                assert_eq!(status.data[0].connection_id, 12393906104898154338);
+            },
+            _ => panic!("Expected KrakenChannelMessage::Status"),
+        }
+    }
+
+    #[test]
+    fn test_kraken_subscribe_response_deserialize() {
+        let json = serde_json::json!({
+            "method": "subscribe",
+            "result": {"channel": "ticker", "snapshot": true, "symbol": "ETH/USD"},
+            "success": true,
+            "time_in": "2025-02-12T21:12:33.102Z",
+            "time_out": "2025-02-12T21:12:33.104Z",
+            "req_id": 1
+        });
+
+        let message: KrakenMessage = serde_json::from_value(json).unwrap();
+        match message {
+            KrakenMessage::Response(response) => {
+                assert_eq!(response.method, KrakenRequestMethod::Subscribe);
+                assert!(response.success);
+                assert_eq!(response.req_id, Some(1));
+            },
+            _ => panic!("Expected KrakenMessage::Response"),
+        }
+    }
+}
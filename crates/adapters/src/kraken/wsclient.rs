@@ -0,0 +1,134 @@
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc,
+};
+
+use sikkara_core::{AppError, AppResult};
+use sikkara_wsclient::WsCallback;
+use tokio::sync::{broadcast, mpsc};
+use tokio_tungstenite::tungstenite::{Message, Utf8Bytes};
+use tracing::{error, info, warn};
+
+use crate::kraken::{
+    KrakenMessage, KrakenRequest, KrakenRequestMethod, KrakenSubscription, KrakenSymbol,
+};
+
+/// Client of the Kraken websocket v2 API, e.g. `wss://ws.kraken.com/v2`.
+#[derive(Debug, Clone)]
+pub struct KrakenWsClient {
+    ws_url: String,
+    sender: mpsc::Sender<Message>,
+    message_broadcaster: broadcast::Sender<KrakenMessage>,
+    /// Identifier of the next request
+    next_req_id: Arc<AtomicU64>,
+}
+
+impl KrakenWsClient {
+    pub fn new(
+        ws_url: String,
+        sender: mpsc::Sender<Message>,
+        message_broadcaster: broadcast::Sender<KrakenMessage>,
+    ) -> Self {
+        KrakenWsClient {
+            ws_url,
+            sender,
+            message_broadcaster,
+            next_req_id: Arc::new(AtomicU64::new(1)),
+        }
+    }
+
+    pub fn subscribe(
+        &self,
+        symbols: Vec<KrakenSymbol>,
+        channel: String,
+    ) -> AppResult<broadcast::Receiver<KrakenMessage>> {
+        self.request(KrakenRequestMethod::Subscribe, symbols, channel)?;
+        Ok(self.message_broadcaster.subscribe())
+    }
+
+    pub fn unsubscribe(&self, symbols: Vec<KrakenSymbol>, channel: String) -> AppResult<()> {
+        self.request(KrakenRequestMethod::Unsubscribe, symbols, channel)
+    }
+
+    pub fn ws_url(&self) -> &str { &self.ws_url }
+
+    pub fn write(&self, message: Message) -> AppResult<()> {
+        match self.sender.try_send(message) {
+            Ok(_) => Ok(()),
+            Err(e) => {
+                Err(AppError::WebSocketError(format!("failed to send message to websocket: {}", e))
+                    .into())
+            },
+        }
+    }
+
+    pub fn close(&self) -> AppResult<()> { self.write(Message::Close(None)) }
+
+    fn request(
+        &self,
+        method: KrakenRequestMethod,
+        symbol: Vec<KrakenSymbol>,
+        channel: String,
+    ) -> AppResult<()> {
+        let request = KrakenRequest {
+            method,
+            params: KrakenSubscription { channel, symbol },
+            req_id: Some(self.next_req_id.fetch_add(1, Ordering::Relaxed)),
+        };
+        let message = serde_json::to_string(&request)?;
+
+        self.write(Message::Text(Utf8Bytes::from(&message)))
+    }
+}
+
+#[async_trait::async_trait]
+impl WsCallback for KrakenWsClient {
+    async fn on_connect(&mut self, timestamp: jiff::Timestamp) -> AppResult<()> {
+        info!("Connected to Kraken WebSocket at {}", self.ws_url);
+        Ok(())
+    }
+
+    async fn on_message(&mut self, message: Message, receive_at: jiff::Timestamp) -> AppResult<()> {
+        match message {
+            Message::Text(text) => {
+                let kraken_message: KrakenMessage = match serde_json::from_str(&text) {
+                    Ok(msg) => msg,
+                    Err(e) => {
+                        error!("Failed to parse Kraken message: {}", e);
+                        return Err(AppError::WebSocketError(format!(
+                            "Failed to parse Kraken message: {}",
+                            e
+                        ))
+                        .into());
+                    },
+                };
+                self.message_broadcaster.send(kraken_message).map_err(|e| {
+                    AppError::WebSocketError(format!("Failed to broadcast message: {}", e))
+                })?;
+            },
+            Message::Close(_) => {
+                info!("WebSocket connection closed by remote peer");
+                return self.on_disconnect();
+            },
+
+            Message::Ping(ping) => {
+                self.write(Message::Pong(ping))?;
+            },
+
+            _ => {
+                warn!("Received unsupported message type: {:?}", message);
+            },
+        };
+        Ok(())
+    }
+
+    fn on_disconnect(&mut self) -> AppResult<()> {
+        info!("WebSocket connection closed or lost");
+        Ok(())
+    }
+
+    fn on_heartbeat(&mut self) -> AppResult<()> {
+        info!("Heartbeat check for Kraken WebSocket connection");
+        Ok(())
+    }
+}
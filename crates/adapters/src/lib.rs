@@ -1,10 +1,33 @@
 // Define trading adapters here
 
+#[allow(unused)]
+mod binance;
+pub use binance::*;
+
 #[allow(unused)]
 mod coinbase;
 pub use coinbase::*;
 
+#[allow(unused)]
+mod kraken;
+pub use kraken::*;
+
+#[allow(unused)]
+mod okx;
+pub use okx::*;
+
+#[allow(unused)]
+pub mod uniswap_v3;
+#[allow(unused)]
+pub use uniswap_v3::*;
+
 #[allow(unused, clippy::too_many_arguments)]
 pub mod uniswap_v4;
 #[allow(unused)]
 pub use uniswap_v4::*;
+
+mod erc20;
+pub use erc20::IERC20;
+
+pub mod transaction;
+pub use transaction::*;
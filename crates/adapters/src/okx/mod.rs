@@ -0,0 +1,10 @@
+#[allow(unused)]
+mod models;
+#[allow(unused)]
+pub use models::{
+    OkxAction, OkxArg, OkxBookData, OkxChannel, OkxChannelData, OkxChannelMessage, OkxEvent,
+    OkxEventMessage, OkxInstrument, OkxMessage, OkxOperation, OkxSubscribeRequest, OkxTickerData,
+};
+
+mod wsclient;
+pub use wsclient::OkxWsClient;
@@ -0,0 +1,286 @@
+use rust_decimal::Decimal;
+use serde::{Deserialize, Deserializer, Serialize};
+
+/// Request of the OKX websocket v5 API, e.g. `{"op": "subscribe", "args":
+/// [{"channel": "tickers", "instId": "ETH-USDT"}]}`. Public channels require
+/// no login.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OkxSubscribeRequest {
+    pub op: OkxOperation,
+    pub args: Vec<OkxArg>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OkxOperation {
+    Subscribe,
+    Unsubscribe,
+}
+
+/// Channel of an instrument, identifying both subscriptions and the messages
+/// pushed on them.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OkxArg {
+    pub channel: OkxChannel,
+    #[serde(rename = "instId")]
+    pub inst_id: OkxInstrument,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OkxChannel {
+    /// Last traded price and best bid and ask, pushed every 100ms at most
+    Tickers,
+    /// Order book of 400 levels, a snapshot followed by incremental updates
+    Books,
+}
+
+/// Instrument id, e.g. `ETH-USDT`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct OkxInstrument(pub String);
+
+impl OkxInstrument {
+    pub fn new(inst_id: impl Into<String>) -> Self { Self(inst_id.into()) }
+}
+
+impl std::fmt::Display for OkxInstrument {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result { write!(f, "{}", self.0) }
+}
+
+/// Message received from OKX, either pushed on a channel or an event answering
+/// a request.
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum OkxMessage {
+    ChannelMessage(OkxChannelMessage),
+    Event(OkxEventMessage),
+}
+
+/// Message pushed on the channel named by its `arg`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct OkxChannelMessage {
+    pub arg: OkxArg,
+    /// Whether the data is a snapshot or an update, only set on books
+    pub action: Option<OkxAction>,
+    pub data: OkxChannelData,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OkxAction {
+    Snapshot,
+    Update,
+}
+
+/// Data of a channel message, whose layout depends on the channel.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum OkxChannelData {
+    Tickers(Vec<OkxTickerData>),
+    Books(Vec<OkxBookData>),
+}
+
+/// Ticker of an instrument. All numbers are sent as strings.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OkxTickerData {
+    pub inst_type: String,
+    pub inst_id: OkxInstrument,
+    /// Price of the last trade
+    pub last: Decimal,
+    pub last_sz: Decimal,
+    pub ask_px: Decimal,
+    pub ask_sz: Decimal,
+    pub bid_px: Decimal,
+    pub bid_sz: Decimal,
+    #[serde(rename = "open24h")]
+    pub open_24h: Decimal,
+    #[serde(rename = "high24h")]
+    pub high_24h: Decimal,
+    #[serde(rename = "low24h")]
+    pub low_24h: Decimal,
+    /// Traded volume over the last 24 hours in the quote asset
+    #[serde(rename = "volCcy24h")]
+    pub vol_ccy_24h: Decimal,
+    /// Traded volume over the last 24 hours in the base asset
+    #[serde(rename = "vol24h")]
+    pub vol_24h: Decimal,
+    #[serde(deserialize_with = "string_millis")]
+    pub ts: jiff::Timestamp,
+}
+
+/// Order book snapshot or update of an instrument.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OkxBookData {
+    /// Price, size, a deprecated field and the number of orders of each level
+    pub asks: Vec<[Decimal; 4]>,
+    pub bids: Vec<[Decimal; 4]>,
+    #[serde(deserialize_with = "string_millis")]
+    pub ts: jiff::Timestamp,
+    /// CRC32 of the first 25 levels of the book after the update
+    pub checksum: Option<i64>,
+    pub seq_id: Option<i64>,
+    pub prev_seq_id: Option<i64>,
+}
+
+/// Event answering a request, or reporting an error.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OkxEventMessage {
+    pub event: OkxEvent,
+    pub arg: Option<OkxArg>,
+    pub code: Option<String>,
+    pub msg: Option<String>,
+    pub conn_id: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OkxEvent {
+    Subscribe,
+    Unsubscribe,
+    Error,
+}
+
+/// Deserializes a unix timestamp in milliseconds sent as a string.
+fn string_millis<'de, D>(deserializer: D) -> Result<jiff::Timestamp, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let s = String::deserialize(deserializer)?;
+    let millis = s.parse::<i64>().map_err(serde::de::Error::custom)?;
+    jiff::Timestamp::from_millisecond(millis).map_err(serde::de::Error::custom)
+}
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal_macros::dec;
+
+    use super::*;
+
+    #[test]
+    fn test_okx_subscribe_request_serialize() {
+        let request = OkxSubscribeRequest {
+            op: OkxOperation::Subscribe,
+            args: vec![OkxArg {
+                channel: OkxChannel::Tickers,
+                inst_id: OkxInstrument::new("ETH-USDT"),
+            }],
+        };
+
+        let serialized = serde_json::to_value(&request).unwrap();
+        assert_eq!(
+            serialized,
+            serde_json::json!({
+                "op": "subscribe",
+                "args": [{"channel": "tickers", "instId": "ETH-USDT"}]
+            })
+        );
+    }
+
+    #[test]
+    fn test_okx_ticker_message_deserialize() {
+        let json = serde_json::json!({
+            "arg": {"channel": "tickers", "instId": "ETH-USDT"},
+            "data": [{
+                "instType": "SPOT",
+                "instId": "ETH-USDT",
+                "last": "2687.37",
+                "lastSz": "0.0152",
+                "askPx": "2687.38",
+                "askSz": "3.2011",
+                "bidPx": "2687.36",
+                "bidSz": "12.5031",
+                "open24h": "2621.85",
+                "high24h": "2695.87",
+                "low24h": "2548",
+                "volCcy24h": "1092103412.5",
+                "vol24h": "412093.12",
+                "sodUtc0": "2650.1",
+                "sodUtc8": "2640.2",
+                "ts": "1739394753778"
+            }]
+        });
+
+        let message: OkxMessage = serde_json::from_value(json).unwrap();
+        let OkxMessage::ChannelMessage(message) = message else {
+            panic!("Expected OkxMessage::ChannelMessage")
+        };
+        assert_eq!(message.arg.channel, OkxChannel::Tickers);
+        assert_eq!(message.action, None);
+        match message.data {
+            OkxChannelData::Tickers(tickers) => {
+                assert_eq!(tickers[0].inst_id, OkxInstrument::new("ETH-USDT"));
+                assert_eq!(tickers[0].last, dec!(2687.37));
+                assert_eq!(tickers[0].bid_px, dec!(2687.36));
+                assert_eq!(tickers[0].ask_px, dec!(2687.38));
+                assert_eq!(tickers[0].vol_24h, dec!(412093.12));
+                assert_eq!(tickers[0].ts.to_string(), "2025-02-12T21:12:33.778Z");
+            },
+            _ => panic!("Expected OkxChannelData::Tickers"),
+        }
+    }
+
+    #[test]
+    fn test_okx_books_message_deserialize() {
+        let json = serde_json::json!({
+            "arg": {"channel": "books", "instId": "ETH-USDT"},
+            "action": "snapshot",
+            "data": [{
+                "asks": [["2687.38", "3.2011", "0", "4"]],
+                "bids": [["2687.36", "12.5031", "0", "11"]],
+                "ts": "1739394753778",
+                "checksum": -855196043,
+                "prevSeqId": -1,
+                "seqId": 123456
+            }]
+        });
+
+        let message: OkxMessage = serde_json::from_value(json).unwrap();
+        let OkxMessage::ChannelMessage(message) = message else {
+            panic!("Expected OkxMessage::ChannelMessage")
+        };
+        assert_eq!(message.action, Some(OkxAction::Snapshot));
+        match message.data {
+            OkxChannelData::Books(books) => {
+                assert_eq!(books[0].asks[0][0], dec!(2687.38));
+                assert_eq!(books[0].bids[0][1], dec!(12.5031));
+                assert_eq!(books[0].checksum, Some(-855196043));
+                assert_eq!(books[0].seq_id, Some(123456));
+            },
+            _ => panic!("Expected OkxChannelData::Books"),
+        }
+    }
+
+    #[test]
+    fn test_okx_event_message_deserialize() {
+        let json = serde_json::json!({
+            "event": "subscribe",
+            "arg": {"channel": "tickers", "instId": "ETH-USDT"},
+            "connId": "a4d3ae55"
+        });
+        let message: OkxMessage = serde_json::from_value(json).unwrap();
+        assert!(matches!(
+            message,
+            OkxMessage::Event(OkxEventMessage { event: OkxEvent::Subscribe, .. })
+        ));
+
+        let json = serde_json::json!({
+            "event": "error",
+            "code": "60012",
+            "msg": "Invalid request",
+            "connId": "a4d3ae55"
+        });
+        let message: OkxMessage = serde_json::from_value(json).unwrap();
+        match message {
+            OkxMessage::Event(event) => {
+                assert_eq!(event.event, OkxEvent::Error);
+                assert_eq!(event.code.as_deref(), Some("60012"));
+            },
+            _ => panic!("Expected OkxMessage::Event"),
+        }
+    }
+}
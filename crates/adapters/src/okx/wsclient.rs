@@ -0,0 +1,185 @@
+use sikkara_core::{AppError, AppResult};
+use sikkara_wsclient::WsCallback;
+use tokio::sync::{broadcast, mpsc};
+use tokio_tungstenite::tungstenite::{Message, Utf8Bytes};
+use tracing::{error, info, warn};
+
+use crate::okx::{
+    OkxArg, OkxChannel, OkxInstrument, OkxMessage, OkxOperation, OkxSubscribeRequest,
+};
+
+/// Application level keepalive, OKX closes the connections which sent nothing
+/// for 30 seconds and answers with [`PONG`].
+const PING: &str = "ping";
+
+/// Answer of OKX to a [`PING`], which is not JSON.
+const PONG: &str = "pong";
+
+/// Client of the public OKX websocket v5 channels, e.g.
+/// `wss://ws.okx.com:8443/ws/v5/public`.
+///
+/// A ping is sent on every heartbeat of the consumer, whose interval must be
+/// shorter than 30 seconds.
+#[derive(Debug, Clone)]
+pub struct OkxWsClient {
+    ws_url: String,
+    sender: mpsc::Sender<Message>,
+    message_broadcaster: broadcast::Sender<OkxMessage>,
+}
+
+impl OkxWsClient {
+    pub fn new(
+        ws_url: String,
+        sender: mpsc::Sender<Message>,
+        message_broadcaster: broadcast::Sender<OkxMessage>,
+    ) -> Self {
+        OkxWsClient { ws_url, sender, message_broadcaster }
+    }
+
+    pub fn subscribe(
+        &self,
+        instruments: Vec<OkxInstrument>,
+        channel: OkxChannel,
+    ) -> AppResult<broadcast::Receiver<OkxMessage>> {
+        self.request(OkxOperation::Subscribe, instruments, channel)?;
+        Ok(self.message_broadcaster.subscribe())
+    }
+
+    pub fn unsubscribe(
+        &self,
+        instruments: Vec<OkxInstrument>,
+        channel: OkxChannel,
+    ) -> AppResult<()> {
+        self.request(OkxOperation::Unsubscribe, instruments, channel)
+    }
+
+    pub fn ws_url(&self) -> &str { &self.ws_url }
+
+    pub fn write(&self, message: Message) -> AppResult<()> {
+        match self.sender.try_send(message) {
+            Ok(_) => Ok(()),
+            Err(e) => {
+                Err(AppError::WebSocketError(format!("failed to send message to websocket: {}", e))
+                    .into())
+            },
+        }
+    }
+
+    pub fn close(&self) -> AppResult<()> { self.write(Message::Close(None)) }
+
+    fn request(
+        &self,
+        op: OkxOperation,
+        instruments: Vec<OkxInstrument>,
+        channel: OkxChannel,
+    ) -> AppResult<()> {
+        let args = instruments
+            .into_iter()
+            .map(|inst_id| OkxArg { channel, inst_id })
+            .collect();
+        let request = OkxSubscribeRequest { op, args };
+        let message = serde_json::to_string(&request)?;
+
+        self.write(Message::Text(Utf8Bytes::from(&message)))
+    }
+}
+
+#[async_trait::async_trait]
+impl WsCallback for OkxWsClient {
+    async fn on_connect(&mut self, timestamp: jiff::Timestamp) -> AppResult<()> {
+        info!("Connected to OKX WebSocket at {}", self.ws_url);
+        Ok(())
+    }
+
+    async fn on_message(&mut self, message: Message, receive_at: jiff::Timestamp) -> AppResult<()> {
+        match message {
+            Message::Text(text) if text.as_str() == PONG => {},
+            Message::Text(text) => {
+                let okx_message: OkxMessage = match serde_json::from_str(&text) {
+                    Ok(msg) => msg,
+                    Err(e) => {
+                        error!("Failed to parse OKX message: {}", e);
+                        return Err(AppError::WebSocketError(format!(
+                            "Failed to parse OKX message: {}",
+                            e
+                        ))
+                        .into());
+                    },
+                };
+                self.message_broadcaster.send(okx_message).map_err(|e| {
+                    AppError::WebSocketError(format!("Failed to broadcast message: {}", e))
+                })?;
+            },
+            Message::Close(_) => {
+                info!("WebSocket connection closed by remote peer");
+                return self.on_disconnect();
+            },
+
+            Message::Ping(ping) => {
+                self.write(Message::Pong(ping))?;
+            },
+
+            _ => {
+                warn!("Received unsupported message type: {:?}", message);
+            },
+        };
+        Ok(())
+    }
+
+    fn on_disconnect(&mut self) -> AppResult<()> {
+        info!("WebSocket connection closed or lost");
+        Ok(())
+    }
+
+    fn on_heartbeat(&mut self) -> AppResult<()> {
+        // Enqueued like any request, the consumer writes it
+        self.write(Message::Text(Utf8Bytes::from_static(PING)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_client() -> (OkxWsClient, mpsc::Receiver<Message>, broadcast::Receiver<OkxMessage>) {
+        let (sender, receiver) = mpsc::channel(4);
+        let (broadcaster, messages) = broadcast::channel(4);
+        (OkxWsClient::new("wss://okx".to_string(), sender, broadcaster), receiver, messages)
+    }
+
+    #[tokio::test]
+    async fn test_ping_is_enqueued_on_heartbeat() {
+        let (mut client, mut receiver, _messages) = new_client();
+        client.on_heartbeat().unwrap();
+        assert_eq!(receiver.try_recv().unwrap(), Message::Text(Utf8Bytes::from_static("ping")));
+    }
+
+    #[tokio::test]
+    async fn test_pong_is_neither_an_error_nor_broadcast() {
+        let (mut client, _receiver, mut messages) = new_client();
+        client
+            .on_message(Message::Text(Utf8Bytes::from_static("pong")), jiff::Timestamp::now())
+            .await
+            .unwrap();
+        assert!(messages.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_subscribe_writes_a_request_per_instrument() {
+        let (client, mut receiver, _messages) = new_client();
+        client
+            .subscribe(vec![OkxInstrument::new("ETH-USDC")], OkxChannel::Tickers)
+            .unwrap();
+        let Message::Text(request) = receiver.try_recv().unwrap() else {
+            panic!("expected a text frame");
+        };
+        let request: serde_json::Value = serde_json::from_str(request.as_str()).unwrap();
+        assert_eq!(
+            request,
+            serde_json::json!({
+                "op": "subscribe",
+                "args": [{ "channel": "tickers", "instId": "ETH-USDC" }]
+            })
+        );
+    }
+}
@@ -0,0 +1,590 @@
+use std::{
+    fmt, fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+use alloy::{
+    network::TransactionBuilder,
+    primitives::{
+        address,
+        aliases::{U160, U48},
+        Address, U256,
+    },
+    providers::Provider,
+    rpc::types::TransactionRequest,
+    sol,
+    sol_types::SolCall,
+};
+use serde::{Deserialize, Serialize};
+use sikkara_core::{AppError, AppResult};
+use tracing::{debug, info, warn};
+
+use super::{TxSubmitter, Urgency};
+use crate::IERC20;
+
+sol! {
+    #[derive(Debug)]
+    #[sol(rpc)]
+    interface IPermit2 {
+        function allowance(address user, address token, address spender) external view returns (uint160 amount, uint48 expiration, uint48 nonce);
+        function approve(address token, address spender, uint160 amount, uint48 expiration) external;
+    }
+}
+
+/// Canonical Permit2 deployment, at the same address on every chain
+pub const PERMIT2_ADDRESS: Address = address!("0x000000000022D473030F116dDEE9F6B43aC78BA3");
+
+/// Remaining validity below which a Permit2 allowance is renewed
+const PERMIT2_RENEWAL_MARGIN: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Which allowance of a token the execution path depends on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AllowanceKind {
+    /// ERC20 allowance of the signer to Permit2
+    Erc20,
+    /// Permit2 allowance of the signer to the router
+    Permit2,
+}
+
+/// A token swapped through the router and the allowances it needs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TokenApproval {
+    pub token: Address,
+    /// Allowance below which the token is approved again, in the smallest unit
+    /// of the token
+    pub min_allowance: U256,
+    /// Amount approved, in the smallest unit of the token
+    pub cap: U256,
+}
+
+/// An allowance read from the chain, or approved by the manager.
+///
+/// Only unlimited allowances are cached, as every swap spends down the others.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CachedAllowance {
+    pub chain_id: u64,
+    pub owner: Address,
+    pub token: Address,
+    pub spender: Address,
+    pub kind: AllowanceKind,
+    pub amount: U256,
+    /// Unix seconds the allowance expires at, zero if it never does
+    pub expiration: u64,
+}
+
+impl CachedAllowance {
+    /// Whether the allowance is at least `min_allowance` and does not expire
+    /// within the renewal margin of `now`, in unix seconds.
+    fn covers(&self, min_allowance: U256, now: u64) -> bool {
+        self.amount >= min_allowance
+            && (self.expiration == 0 || self.expiration > now + PERMIT2_RENEWAL_MARGIN.as_secs())
+    }
+
+    /// Whether the allowance is not spent down by transfers, that is the
+    /// maximum amount of its kind.
+    fn is_unlimited(&self) -> bool {
+        match self.kind {
+            AllowanceKind::Erc20 => self.amount == U256::MAX,
+            AllowanceKind::Permit2 => self.amount >= U256::from(U160::MAX),
+        }
+    }
+}
+
+/// Checks, and if enabled submits, the token approvals swaps through the
+/// Universal Router depend on.
+///
+/// The router pulls ERC20 inputs through Permit2, so every token must approve
+/// Permit2, which in turn must approve the router. Approving is a policy
+/// decision, so missing allowances are an error listing the exact
+/// transactions to send, unless auto approval is enabled. Approvals are then
+/// submitted for the configured cap and confirmed before returning.
+///
+/// Unlimited allowances found sufficient are cached in a JSON file if one is
+/// configured, so they are not read again on every start until a Permit2
+/// allowance nears its expiration. Limited allowances are spent down by every
+/// swap, so they are always read from the chain.
+///
+/// # Type Parameters
+///
+/// * `P` - The RPC provider type that implements [`alloy::providers::Provider`]
+pub struct ApprovalManager<P>
+where
+    P: Provider + Send + Sync,
+{
+    submitter: Arc<TxSubmitter<P>>,
+    router: Address,
+    permit2: Address,
+    tokens: Vec<TokenApproval>,
+    auto_approve: bool,
+    permit2_expiration: Duration,
+    confirm_timeout: Duration,
+    cache_path: Option<PathBuf>,
+}
+
+impl<P> fmt::Debug for ApprovalManager<P>
+where
+    P: Provider + Send + Sync,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ApprovalManager")
+            .field("submitter", &self.submitter)
+            .field("router", &self.router)
+            .field("permit2", &self.permit2)
+            .field("tokens", &self.tokens)
+            .field("auto_approve", &self.auto_approve)
+            .field("cache_path", &self.cache_path)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<P> ApprovalManager<P>
+where
+    P: Provider + Send + Sync,
+{
+    /// Creates a manager of the approvals of `tokens` to `router` by the
+    /// signer of `submitter`, through the canonical Permit2.
+    pub fn new(
+        submitter: Arc<TxSubmitter<P>>,
+        router: Address,
+        tokens: Vec<TokenApproval>,
+    ) -> Self {
+        Self {
+            submitter,
+            router,
+            permit2: PERMIT2_ADDRESS,
+            tokens,
+            auto_approve: false,
+            permit2_expiration: Duration::from_secs(30 * 24 * 60 * 60),
+            confirm_timeout: Duration::from_secs(120),
+            cache_path: None,
+        }
+    }
+
+    /// Uses the Permit2 deployed at `permit2`.
+    pub fn with_permit2(mut self, permit2: Address) -> Self {
+        self.permit2 = permit2;
+        self
+    }
+
+    /// Submits the missing approvals instead of failing.
+    pub fn with_auto_approve(mut self, auto_approve: bool) -> Self {
+        self.auto_approve = auto_approve;
+        self
+    }
+
+    /// Permit2 allowances are approved for `expiration`, and approvals are
+    /// given `confirm_timeout` to be mined.
+    pub fn with_timeouts(mut self, expiration: Duration, confirm_timeout: Duration) -> Self {
+        self.permit2_expiration = expiration;
+        self.confirm_timeout = confirm_timeout;
+        self
+    }
+
+    /// Caches sufficient unlimited allowances in the JSON file at `path`.
+    pub fn with_cache(mut self, path: impl AsRef<Path>) -> Self {
+        self.cache_path = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Returns the tokens whose approvals are managed.
+    pub fn tokens(&self) -> &[TokenApproval] { &self.tokens }
+
+    /// Ensures every token has sufficient allowances, submitting the missing
+    /// approvals if enabled.
+    ///
+    /// Fails listing the approvals to send if some are missing and auto
+    /// approval is disabled.
+    pub async fn ensure(&self) -> AppResult<()> {
+        let mut cache = self.load_cache();
+        let now = jiff::Timestamp::now().as_second().max(0) as u64;
+        let mut missing = Vec::new();
+        for token in &self.tokens {
+            for kind in [AllowanceKind::Erc20, AllowanceKind::Permit2] {
+                let spender = self.spender(kind);
+                let cached = cache.iter().any(|allowance| {
+                    self.is_entry(allowance, token.token, kind)
+                        && allowance.is_unlimited()
+                        && allowance.covers(token.min_allowance, now)
+                });
+                if cached {
+                    debug!(token = %token.token, %spender, ?kind, "allowance cached");
+                    continue;
+                }
+                let mut allowance = self.allowance(token.token, kind).await?;
+                if !allowance.covers(token.min_allowance, now) {
+                    if !self.auto_approve {
+                        warn!(
+                            token = %token.token,
+                            %spender,
+                            ?kind,
+                            allowance = %allowance.amount,
+                            min_allowance = %token.min_allowance,
+                            "missing token approval"
+                        );
+                        missing.push(self.instructions(token, kind, now));
+                        continue;
+                    }
+                    allowance = self.approve(token, kind, now).await?;
+                }
+                cache.retain(|entry| !self.is_entry(entry, token.token, kind));
+                if allowance.is_unlimited() {
+                    cache.push(allowance);
+                }
+            }
+        }
+        self.save_cache(&cache)?;
+
+        if !missing.is_empty() {
+            return Err(AppError::ConfigError(format!(
+                "missing token approvals of {}, approve them or enable auto_approve:\n{}",
+                self.submitter.address(),
+                missing.join("\n")
+            ))
+            .into());
+        }
+        info!(tokens = self.tokens.len(), router = %self.router, "token approvals in place");
+        Ok(())
+    }
+
+    /// Reads the allowance of a token from the chain.
+    async fn allowance(&self, token: Address, kind: AllowanceKind) -> AppResult<CachedAllowance> {
+        let owner = self.submitter.address();
+        let provider = self.submitter.provider();
+        let (amount, expiration) = match kind {
+            AllowanceKind::Erc20 => {
+                let amount = IERC20::new(token, provider)
+                    .allowance(owner, self.permit2)
+                    .call()
+                    .await?;
+                (amount, 0)
+            },
+            AllowanceKind::Permit2 => {
+                let allowance = IPermit2::new(self.permit2, provider)
+                    .allowance(owner, token, self.router)
+                    .call()
+                    .await?;
+                let expiration = u64::try_from(allowance.expiration).unwrap_or(u64::MAX);
+                (U256::from(allowance.amount), expiration)
+            },
+        };
+        Ok(self.entry(token, kind, amount, expiration))
+    }
+
+    /// Submits the approval of a token for its cap and waits for it to be
+    /// mined.
+    async fn approve(
+        &self,
+        token: &TokenApproval,
+        kind: AllowanceKind,
+        now: u64,
+    ) -> AppResult<CachedAllowance> {
+        let (to, input, amount, expiration) = match kind {
+            AllowanceKind::Erc20 => {
+                let call = IERC20::approveCall { spender: self.permit2, amount: token.cap };
+                (token.token, call.abi_encode(), token.cap, 0)
+            },
+            AllowanceKind::Permit2 => {
+                let amount = U160::saturating_from(token.cap);
+                let expiration = now + self.permit2_expiration.as_secs();
+                let call = IPermit2::approveCall {
+                    token: token.token,
+                    spender: self.router,
+                    amount,
+                    expiration: U48::saturating_from(expiration),
+                };
+                (self.permit2, call.abi_encode(), U256::from(amount), expiration)
+            },
+        };
+        let request = TransactionRequest::default().with_to(to).with_input(input);
+        let pending = self.submitter.submit(request, Urgency::Normal).await?;
+        info!(
+            tx_hash = %pending.hash,
+            token = %token.token,
+            spender = %self.spender(kind),
+            ?kind,
+            cap = %amount,
+            "token approval submitted"
+        );
+        self.submitter
+            .confirm(&pending, self.confirm_timeout)
+            .await?;
+        info!(tx_hash = %pending.hash, token = %token.token, ?kind, "token approval confirmed");
+        Ok(self.entry(token.token, kind, amount, expiration))
+    }
+
+    /// Returns the transaction approving a token, as a `cast` command.
+    fn instructions(&self, token: &TokenApproval, kind: AllowanceKind, now: u64) -> String {
+        match kind {
+            AllowanceKind::Erc20 => format!(
+                "cast send {} \"approve(address,uint256)\" {} {}",
+                token.token, self.permit2, token.cap
+            ),
+            AllowanceKind::Permit2 => format!(
+                "cast send {} \"approve(address,address,uint160,uint48)\" {} {} {} {}",
+                self.permit2,
+                token.token,
+                self.router,
+                U160::saturating_from(token.cap),
+                now + self.permit2_expiration.as_secs()
+            ),
+        }
+    }
+
+    fn spender(&self, kind: AllowanceKind) -> Address {
+        match kind {
+            AllowanceKind::Erc20 => self.permit2,
+            AllowanceKind::Permit2 => self.router,
+        }
+    }
+
+    fn entry(
+        &self,
+        token: Address,
+        kind: AllowanceKind,
+        amount: U256,
+        expiration: u64,
+    ) -> CachedAllowance {
+        CachedAllowance {
+            chain_id: self.submitter.chain_id(),
+            owner: self.submitter.address(),
+            token,
+            spender: self.spender(kind),
+            kind,
+            amount,
+            expiration,
+        }
+    }
+
+    fn is_entry(&self, entry: &CachedAllowance, token: Address, kind: AllowanceKind) -> bool {
+        entry.chain_id == self.submitter.chain_id()
+            && entry.owner == self.submitter.address()
+            && entry.token == token
+            && entry.spender == self.spender(kind)
+            && entry.kind == kind
+    }
+
+    /// Reads the cached allowances, none if there is no cache or it cannot be
+    /// read.
+    fn load_cache(&self) -> Vec<CachedAllowance> {
+        let Some(path) = &self.cache_path else {
+            return Vec::new();
+        };
+        let Ok(content) = fs::read_to_string(path) else {
+            return Vec::new();
+        };
+        serde_json::from_str(&content).unwrap_or_else(|e| {
+            warn!(path = %path.display(), error = %e, "ignoring unreadable approval cache");
+            Vec::new()
+        })
+    }
+
+    fn save_cache(&self, cache: &[CachedAllowance]) -> AppResult<()> {
+        let Some(path) = &self.cache_path else {
+            return Ok(());
+        };
+        if let Some(parent) = path
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+        {
+            fs::create_dir_all(parent)?;
+        }
+        let content = serde_json::to_string_pretty(cache).map_err(|e| {
+            AppError::SerializationError(format!("failed to serialize approval cache: {}", e))
+        })?;
+        fs::write(path, content)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::{
+        primitives::{address, b256, Bytes, TxHash, U64},
+        providers::ProviderBuilder,
+        sol_types::SolValue,
+        transports::mock::Asserter,
+    };
+    use serde_json::json;
+    use sikkara_core::Secret;
+
+    use super::*;
+    use crate::{signer_from_key, FeeCaps, FeeEstimator};
+
+    /// The first well known Anvil development key.
+    const DEV_KEY: &str = "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+    const USDC: Address = address!("0x833589fCD6eDb6E08f4c7C32D4f71b54bdA02913");
+    const ROUTER: Address = address!("0x6fF5693b99212Da76ad316178A184AB56D299b43");
+    const TX_HASH: TxHash =
+        b256!("0x2c6c1d4b32d4a6f7f3e2c95ac46f8d2d4e0bd7b4b2b4c4f1a2a3b9c1d8e7f6a5");
+
+    fn manager(asserter: Asserter) -> ApprovalManager<impl Provider> {
+        let provider = ProviderBuilder::new()
+            .disable_recommended_fillers()
+            .connect_mocked_client(asserter);
+        let fee_caps =
+            FeeCaps { max_fee_per_gas: 100_000_000_000, max_priority_fee_per_gas: 2_000_000_000 };
+        let submitter = TxSubmitter::new(
+            Arc::new(provider),
+            signer_from_key(&Secret::new(DEV_KEY)).unwrap(),
+            8453,
+            FeeEstimator::new(fee_caps),
+        );
+        let usdc = TokenApproval {
+            token: USDC,
+            min_allowance: U256::from(1_000_000_000u64),
+            cap: U256::from(10_000_000_000u64),
+        };
+        ApprovalManager::new(Arc::new(submitter), ROUTER, vec![usdc])
+            .with_timeouts(Duration::from_secs(3600 * 48), Duration::from_secs(5))
+    }
+
+    fn allowance_result(amount: impl Into<U256>, expiration: u64) -> Bytes {
+        (amount.into(), U256::from(expiration), U256::ZERO)
+            .abi_encode()
+            .into()
+    }
+
+    #[tokio::test]
+    async fn test_missing_allowances_fail_with_instructions() {
+        let asserter = Asserter::new();
+        asserter.push_success(&Bytes::from(U256::ZERO.abi_encode()));
+        asserter.push_success(&allowance_result(U256::ZERO, 0));
+
+        let error = manager(asserter.clone()).ensure().await.unwrap_err();
+        let message = error.to_string();
+        assert!(message.contains("missing token approvals"), "{}", message);
+        assert!(message.contains(&format!(
+            "cast send {} \"approve(address,uint256)\" {} 10000000000",
+            USDC, PERMIT2_ADDRESS
+        )));
+        assert!(message.contains(&format!(
+            "cast send {} \"approve(address,address,uint160,uint48)\" {} {} 10000000000",
+            PERMIT2_ADDRESS, USDC, ROUTER
+        )));
+        assert!(asserter.read_q().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_missing_allowance_is_approved() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = dir.path().join("approvals.json");
+        let far_expiration = jiff::Timestamp::now().as_second() as u64 + 3600 * 24 * 7;
+
+        let asserter = Asserter::new();
+        // ERC20 allowance to Permit2 below the minimum
+        asserter.push_success(&Bytes::from(U256::from(5).abi_encode()));
+        // Nonces, latest and pending
+        asserter.push_success(&U64::from(2));
+        asserter.push_success(&U64::from(2));
+        // Fees and gas
+        asserter.push_success(&json!({
+            "oldestBlock": "0x1e8480",
+            "baseFeePerGas": ["0x3b9aca00", "0x3b9aca00"],
+            "gasUsedRatio": [0.5],
+            "reward": [["0x3b9aca00"]]
+        }));
+        asserter.push_success(&U64::from(46_000));
+        asserter.push_success(&TX_HASH);
+        asserter.push_success(&json!({
+            "type": "0x2",
+            "status": "0x1",
+            "transactionHash": TX_HASH,
+            "transactionIndex": "0x0",
+            "blockHash": "0x4e3a3754410177e6937ef1f84bba68ea139e8d1a2258c5f85db9f1cd715a1bdd",
+            "blockNumber": "0x1e8481",
+            "from": "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266",
+            "to": USDC,
+            "cumulativeGasUsed": "0xb3b0",
+            "gasUsed": "0xb3b0",
+            "effectiveGasPrice": "0x77359400",
+            "contractAddress": null,
+            "logs": [],
+            "logsBloom": format!("0x{}", "0".repeat(512))
+        }));
+        // Permit2 allowance to the router already in place
+        asserter.push_success(&allowance_result(U256::from(10_000_000_000u64), far_expiration));
+
+        let manager = manager(asserter.clone())
+            .with_auto_approve(true)
+            .with_cache(&cache);
+        manager.ensure().await.unwrap();
+        assert!(asserter.read_q().is_empty());
+
+        // Limited allowances are spent down by swaps, so they are not cached
+        let cached: Vec<CachedAllowance> =
+            serde_json::from_str(&fs::read_to_string(&cache).unwrap()).unwrap();
+        assert!(cached.is_empty());
+
+        asserter.push_success(&Bytes::from(U256::from(10_000_000_000u64).abi_encode()));
+        asserter.push_success(&allowance_result(U256::from(10_000_000_000u64), far_expiration));
+        manager.ensure().await.unwrap();
+        assert!(asserter.read_q().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_spent_allowances_are_read_again() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = dir.path().join("approvals.json");
+        let far_expiration = jiff::Timestamp::now().as_second() as u64 + 3600 * 24 * 7;
+
+        let asserter = Asserter::new();
+        let manager = manager(asserter.clone()).with_cache(&cache);
+        // Allowances once sufficient, since spent down by swaps
+        let stale = vec![
+            manager.entry(USDC, AllowanceKind::Erc20, U256::from(10_000_000_000u64), 0),
+            manager.entry(
+                USDC,
+                AllowanceKind::Permit2,
+                U256::from(10_000_000_000u64),
+                far_expiration,
+            ),
+        ];
+        manager.save_cache(&stale).unwrap();
+
+        asserter.push_success(&Bytes::from(U256::from(5).abi_encode()));
+        asserter.push_success(&allowance_result(U256::from(5), far_expiration));
+        let error = manager.ensure().await.unwrap_err();
+        assert!(error.to_string().contains("missing token approvals"), "{}", error);
+        assert!(asserter.read_q().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_unlimited_allowances_are_cached_until_expiring() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = dir.path().join("approvals.json");
+        let now = jiff::Timestamp::now().as_second() as u64;
+        let far_expiration = now + 3600 * 24 * 7;
+
+        let asserter = Asserter::new();
+        let manager = manager(asserter.clone()).with_cache(&cache);
+        asserter.push_success(&Bytes::from(U256::MAX.abi_encode()));
+        asserter.push_success(&allowance_result(U256::from(U160::MAX), far_expiration));
+        manager.ensure().await.unwrap();
+        assert!(asserter.read_q().is_empty());
+
+        let cached: Vec<CachedAllowance> =
+            serde_json::from_str(&fs::read_to_string(&cache).unwrap()).unwrap();
+        assert_eq!(cached.len(), 2);
+        assert_eq!(cached[0].kind, AllowanceKind::Erc20);
+        assert_eq!(cached[0].spender, PERMIT2_ADDRESS);
+        assert_eq!(cached[1].kind, AllowanceKind::Permit2);
+        assert_eq!(cached[1].spender, ROUTER);
+
+        // Cached allowances are not read again
+        manager.ensure().await.unwrap();
+        assert!(asserter.read_q().is_empty());
+
+        // Until the Permit2 allowance nears its expiration
+        let expiring = vec![
+            cached[0].clone(),
+            CachedAllowance { expiration: now + 3600, ..cached[1].clone() },
+        ];
+        manager.save_cache(&expiring).unwrap();
+        asserter.push_success(&allowance_result(U256::from(U160::MAX), far_expiration));
+        manager.ensure().await.unwrap();
+        assert!(asserter.read_q().is_empty());
+    }
+}
@@ -0,0 +1,311 @@
+use std::time::Duration;
+
+use alloy::{
+    eips::{eip1559::Eip1559Estimation, BlockNumberOrTag},
+    providers::Provider,
+    rpc::types::FeeHistory,
+};
+use serde::{Deserialize, Serialize};
+use sikkara_core::AppResult;
+
+use super::{bump_fees, FeeCaps};
+
+/// How fast a transaction needs to be included, derived from how perishable
+/// the opportunity it is sent for is.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Urgency {
+    /// Inclusion within a few blocks is fine
+    Slow,
+    #[default]
+    Normal,
+    /// The opportunity is gone within a couple of blocks
+    Urgent,
+}
+
+impl Urgency {
+    /// Picks the urgency of a transaction from the expected lifetime of the
+    /// opportunity it is sent for.
+    pub fn from_lifetime(lifetime: Duration) -> Self {
+        if lifetime < Duration::from_secs(5) {
+            Urgency::Urgent
+        } else if lifetime < Duration::from_secs(60) {
+            Urgency::Normal
+        } else {
+            Urgency::Slow
+        }
+    }
+
+    /// Percentile of the priority fees paid in recent blocks to pay.
+    pub fn reward_percentile(&self) -> f64 {
+        match self {
+            Urgency::Slow => 10.0,
+            Urgency::Normal => 50.0,
+            Urgency::Urgent => 90.0,
+        }
+    }
+
+    /// Headroom over the next base fee, in percent, so that the transaction
+    /// stays includable while the base fee rises.
+    pub fn base_fee_percent(&self) -> u128 {
+        match self {
+            Urgency::Slow => 125,
+            Urgency::Normal => 200,
+            Urgency::Urgent => 300,
+        }
+    }
+}
+
+/// How the fees of a stuck transaction are raised when it is replaced.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EscalationPolicy {
+    /// Fee increase of every replacement, nodes require at least 10%
+    pub bump_percent: u128,
+    /// Number of replacements after which a transaction is given up on
+    pub max_replacements: u32,
+}
+
+impl Default for EscalationPolicy {
+    fn default() -> Self { Self { bump_percent: 15, max_replacements: 3 } }
+}
+
+impl EscalationPolicy {
+    /// Returns the fees of the next replacement of a transaction sent with
+    /// `fees` and already replaced `replacements` times, none once the
+    /// replacements are exhausted or the caps leave no room for a bump.
+    pub fn next(
+        &self,
+        fees: Eip1559Estimation,
+        replacements: u32,
+        caps: &FeeCaps,
+    ) -> Option<Eip1559Estimation> {
+        if replacements >= self.max_replacements {
+            return None;
+        }
+        bump_fees(fees, self.bump_percent, caps).ok()
+    }
+
+    /// Returns the fees of all successive replacements of a transaction first
+    /// sent with `fees`.
+    pub fn sequence(&self, fees: Eip1559Estimation, caps: &FeeCaps) -> Vec<Eip1559Estimation> {
+        let mut sequence = Vec::new();
+        let mut fees = fees;
+        while let Some(next) = self.next(fees, sequence.len() as u32, caps) {
+            sequence.push(next);
+            fees = next;
+        }
+        sequence
+    }
+}
+
+/// Picks the EIP-1559 fees of transactions from the fees paid in recent
+/// blocks, as reported by `eth_feeHistory`.
+///
+/// The priority fee is the median over the last blocks of the reward
+/// percentile of the [`Urgency`], the max fee adds the headroom of the urgency
+/// over the next base fee. Both are bounded by the [`FeeCaps`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FeeEstimator {
+    caps: FeeCaps,
+    block_count: u64,
+    min_priority_fee_per_gas: u128,
+    escalation: EscalationPolicy,
+}
+
+impl FeeEstimator {
+    pub fn new(caps: FeeCaps) -> Self {
+        Self {
+            caps,
+            block_count: 10,
+            min_priority_fee_per_gas: 1_000_000,
+            escalation: EscalationPolicy::default(),
+        }
+    }
+
+    /// Sets the number of recent blocks the fees are picked from.
+    pub fn with_block_count(mut self, block_count: u64) -> Self {
+        self.block_count = block_count.max(1);
+        self
+    }
+
+    /// Sets the lowest priority fee paid, used as well when recent blocks
+    /// paid no priority fees.
+    pub fn with_min_priority_fee_per_gas(mut self, min_priority_fee_per_gas: u128) -> Self {
+        self.min_priority_fee_per_gas = min_priority_fee_per_gas;
+        self
+    }
+
+    pub fn with_escalation(mut self, escalation: EscalationPolicy) -> Self {
+        self.escalation = escalation;
+        self
+    }
+
+    pub fn caps(&self) -> &FeeCaps { &self.caps }
+
+    pub fn escalation(&self) -> &EscalationPolicy { &self.escalation }
+
+    /// Reads the recent fee history and picks the fees for `urgency`.
+    pub async fn estimate<P>(&self, provider: &P, urgency: Urgency) -> AppResult<Eip1559Estimation>
+    where
+        P: Provider,
+    {
+        let history = provider
+            .get_fee_history(
+                self.block_count,
+                BlockNumberOrTag::Latest,
+                &[urgency.reward_percentile()],
+            )
+            .await?;
+        Ok(self.estimate_from_history(&history, urgency))
+    }
+
+    /// Picks the fees for `urgency` from a fee history requested with the
+    /// reward percentile of the urgency.
+    pub fn estimate_from_history(
+        &self,
+        history: &FeeHistory,
+        urgency: Urgency,
+    ) -> Eip1559Estimation {
+        let mut rewards = history
+            .reward
+            .iter()
+            .flatten()
+            .filter_map(|block| block.first().copied())
+            .collect::<Vec<_>>();
+        rewards.sort_unstable();
+        let priority_fee = rewards
+            .get(rewards.len() / 2)
+            .copied()
+            .unwrap_or_default()
+            .max(self.min_priority_fee_per_gas);
+
+        let base_fee = history.next_block_base_fee().unwrap_or_default();
+        let max_fee = base_fee * urgency.base_fee_percent() / 100 + priority_fee;
+        self.caps.apply(Eip1559Estimation {
+            max_fee_per_gas: max_fee,
+            max_priority_fee_per_gas: priority_fee,
+        })
+    }
+
+    /// Returns the fees of the next replacement of a transaction, see
+    /// [`EscalationPolicy::next`].
+    pub fn escalate(
+        &self,
+        fees: Eip1559Estimation,
+        replacements: u32,
+    ) -> Option<Eip1559Estimation> {
+        self.escalation.next(fees, replacements, &self.caps)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GWEI: u128 = 1_000_000_000;
+
+    fn caps() -> FeeCaps {
+        FeeCaps { max_fee_per_gas: 100 * GWEI, max_priority_fee_per_gas: 2 * GWEI }
+    }
+
+    /// A `eth_feeHistory` response over 5 blocks, as returned by a node.
+    fn fee_history(rewards: &str) -> FeeHistory {
+        serde_json::from_str(&format!(
+            r#"{{
+                "oldestBlock": "0x1e8480",
+                "baseFeePerGas": ["0x3b9aca00", "0x3b9aca00", "0x41cdb400", "0x4a817c80", "0x4e3b2920", "0x4a817c80"],
+                "gasUsedRatio": [0.5, 0.9, 0.95, 0.7, 0.3],
+                "reward": {}
+            }}"#,
+            rewards
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_fees_follow_the_urgency() {
+        let estimator = FeeEstimator::new(caps());
+        // Next base fee is 1.25 gwei, the median reward 0.1 gwei
+        let history = fee_history(
+            r#"[["0x5f5e100"], ["0x2faf080"], ["0xbebc200"], ["0x1dcd6500"], ["0x5f5e100"]]"#,
+        );
+
+        let slow = estimator.estimate_from_history(&history, Urgency::Slow);
+        assert_eq!(slow.max_priority_fee_per_gas, GWEI / 10);
+        assert_eq!(slow.max_fee_per_gas, 1_562_500_000 + GWEI / 10);
+
+        let normal = estimator.estimate_from_history(&history, Urgency::Normal);
+        assert_eq!(normal.max_fee_per_gas, 2_500_000_000 + GWEI / 10);
+
+        let urgent = estimator.estimate_from_history(&history, Urgency::Urgent);
+        assert_eq!(urgent.max_fee_per_gas, 3_750_000_000 + GWEI / 10);
+    }
+
+    #[test]
+    fn test_priority_fee_is_the_median_reward() {
+        let estimator = FeeEstimator::new(caps());
+        // 0.05, 0.1, 0.2, 0.5 and 0.1 gwei
+        let history = fee_history(
+            r#"[["0x2faf080"], ["0x5f5e100"], ["0xbebc200"], ["0x1dcd6500"], ["0x5f5e100"]]"#,
+        );
+        let fees = estimator.estimate_from_history(&history, Urgency::Urgent);
+        assert_eq!(fees.max_priority_fee_per_gas, GWEI / 10);
+    }
+
+    #[test]
+    fn test_fees_are_capped() {
+        let estimator = FeeEstimator::new(FeeCaps {
+            max_fee_per_gas: 3 * GWEI,
+            max_priority_fee_per_gas: GWEI / 20,
+        });
+        let history = fee_history(
+            r#"[["0x5f5e100"], ["0x5f5e100"], ["0x5f5e100"], ["0x5f5e100"], ["0x5f5e100"]]"#,
+        );
+        let fees = estimator.estimate_from_history(&history, Urgency::Urgent);
+        assert_eq!(
+            fees,
+            Eip1559Estimation { max_fee_per_gas: 3 * GWEI, max_priority_fee_per_gas: GWEI / 20 }
+        );
+    }
+
+    #[test]
+    fn test_empty_blocks_pay_the_minimum_priority_fee() {
+        let estimator = FeeEstimator::new(caps()).with_min_priority_fee_per_gas(GWEI / 100);
+        let history = fee_history(r#"[["0x0"], ["0x0"], ["0x0"], ["0x0"], ["0x0"]]"#);
+        let fees = estimator.estimate_from_history(&history, Urgency::Normal);
+        assert_eq!(fees.max_priority_fee_per_gas, GWEI / 100);
+    }
+
+    #[test]
+    fn test_escalation_sequence() {
+        let estimator = FeeEstimator::new(caps())
+            .with_escalation(EscalationPolicy { bump_percent: 20, max_replacements: 5 });
+        let initial =
+            Eip1559Estimation { max_fee_per_gas: 50 * GWEI, max_priority_fee_per_gas: GWEI };
+
+        // The caps stop the escalation after 3 bumps
+        let sequence = estimator.escalation().sequence(initial, estimator.caps());
+        let priority_fees = sequence
+            .iter()
+            .map(|fees| fees.max_priority_fee_per_gas)
+            .collect::<Vec<_>>();
+        let max_fees = sequence
+            .iter()
+            .map(|fees| fees.max_fee_per_gas)
+            .collect::<Vec<_>>();
+        assert_eq!(priority_fees, vec![1_200_000_000, 1_440_000_000, 1_728_000_000]);
+        assert_eq!(max_fees, vec![60 * GWEI, 72 * GWEI, 86_400_000_000]);
+
+        // Replacements are bounded as well
+        let policy = EscalationPolicy { bump_percent: 10, max_replacements: 2 };
+        assert_eq!(policy.sequence(initial, &caps()).len(), 2);
+        assert_eq!(estimator.escalate(initial, 5), None);
+    }
+
+    #[test]
+    fn test_urgency_from_opportunity_lifetime() {
+        assert_eq!(Urgency::from_lifetime(Duration::from_secs(2)), Urgency::Urgent);
+        assert_eq!(Urgency::from_lifetime(Duration::from_secs(30)), Urgency::Normal);
+        assert_eq!(Urgency::from_lifetime(Duration::from_secs(600)), Urgency::Slow);
+    }
+}
@@ -0,0 +1,28 @@
+//! Transaction Submission
+//!
+//! This module provides the signing identity of the bot and the submission of
+//! EIP-1559 transactions on its behalf, along with the token approvals its
+//! swaps depend on.
+
+mod approvals;
+pub use approvals::{
+    AllowanceKind, ApprovalManager, CachedAllowance, IPermit2, TokenApproval, PERMIT2_ADDRESS,
+};
+
+mod fees;
+pub use fees::{EscalationPolicy, FeeEstimator, Urgency};
+
+mod nonce;
+pub use nonce::{InFlightTx, NonceManager, NonceSource};
+
+mod receipts;
+pub use receipts::{MinedTx, ReceiptMonitor, ReceiptSource, ReceiptStatus};
+
+mod relay;
+pub use relay::PrivateRelay;
+
+mod submitter;
+pub use submitter::{
+    bump_fees, signer_from_key, signer_from_keystore, FeeCaps, PendingTx, PrivateKeySigner,
+    TxFailureKind, TxSubmitter,
+};
@@ -0,0 +1,379 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    sync::Arc,
+};
+
+use alloy::{
+    primitives::{Address, TxHash},
+    providers::Provider,
+    rpc::types::TransactionRequest,
+};
+use sikkara_core::AppResult;
+use tokio::sync::Mutex;
+use tracing::{info, warn};
+
+/// Source of the transaction counts of an address, i.e. the chain view of its
+/// nonces.
+#[async_trait::async_trait]
+pub trait NonceSource: Send + Sync {
+    /// Returns the number of mined transactions of `address`.
+    async fn mined_nonce(&self, address: Address) -> AppResult<u64>;
+
+    /// Returns the number of mined and pending transactions of `address`.
+    async fn pending_nonce(&self, address: Address) -> AppResult<u64>;
+}
+
+#[async_trait::async_trait]
+impl<P> NonceSource for Arc<P>
+where
+    P: Provider + Send + Sync,
+{
+    async fn mined_nonce(&self, address: Address) -> AppResult<u64> {
+        Ok(self.get_transaction_count(address).latest().await?)
+    }
+
+    async fn pending_nonce(&self, address: Address) -> AppResult<u64> {
+        Ok(self.get_transaction_count(address).pending().await?)
+    }
+}
+
+/// A transaction sent but not known to be mined yet.
+#[derive(Debug, Clone)]
+pub struct InFlightTx {
+    pub hash: TxHash,
+    /// The signed request, kept to replace the transaction
+    pub request: TransactionRequest,
+    pub max_fee_per_gas: u128,
+    pub max_priority_fee_per_gas: u128,
+    /// Number of times the transaction was replaced with higher fees
+    pub replacements: u32,
+    /// Sent through a private relay, so not pending for the node until it is
+    /// included or published
+    pub private: bool,
+}
+
+#[derive(Debug, Default)]
+struct NonceState {
+    /// Next nonce never handed out, none until synced from the chain
+    next: Option<u64>,
+    /// Nonces handed out but never sent, reused before `next`
+    released: BTreeSet<u64>,
+    /// Sent transactions by nonce
+    in_flight: BTreeMap<u64, InFlightTx>,
+    /// Whether the chain state must be read again before the next nonce
+    stale: bool,
+}
+
+/// Hands out the nonces of a single signer to concurrent submissions.
+///
+/// Nonces are handed out sequentially under a lock which is never held while a
+/// transaction is sent, so concurrent submissions neither reuse a nonce nor
+/// wait for each other. The first nonce is read from the pending transaction
+/// count of the signer. A nonce that ends up not being sent is released and
+/// handed out again first, so no gap is left behind.
+///
+/// When the chain disagrees, e.g. after a dropped transaction or a "nonce too
+/// low" error, the manager is marked stale and [`NonceManager::resync`]s
+/// before handing out the next nonce.
+#[derive(Debug)]
+pub struct NonceManager<S> {
+    source: S,
+    address: Address,
+    state: Mutex<NonceState>,
+}
+
+impl<S> NonceManager<S>
+where
+    S: NonceSource,
+{
+    pub fn new(source: S, address: Address) -> Self {
+        Self { source, address, state: Mutex::new(NonceState::default()) }
+    }
+
+    /// Returns the address the nonces are handed out for.
+    pub fn address(&self) -> Address { self.address }
+
+    /// Hands out the next nonce, which must be either [`Self::track`]ed once
+    /// sent or [`Self::release`]d.
+    pub async fn acquire(&self) -> AppResult<u64> {
+        let mut state = self.state.lock().await;
+        if state.next.is_none() || state.stale {
+            self.sync(&mut state).await?;
+        }
+        if let Some(nonce) = state.released.pop_first() {
+            return Ok(nonce);
+        }
+        let nonce = state.next.expect("nonces are synced");
+        state.next = Some(nonce + 1);
+        Ok(nonce)
+    }
+
+    /// Returns a nonce which was not sent, to be handed out again.
+    pub async fn release(&self, nonce: u64) {
+        let mut state = self.state.lock().await;
+        if state.next == Some(nonce + 1) {
+            state.next = Some(nonce);
+        } else {
+            state.released.insert(nonce);
+        }
+    }
+
+    /// Records a transaction sent with the given nonce.
+    pub async fn track(&self, nonce: u64, tx: InFlightTx) {
+        self.state.lock().await.in_flight.insert(nonce, tx);
+    }
+
+    /// Returns the transaction sent with the given nonce, if still in flight.
+    pub async fn in_flight(&self, nonce: u64) -> Option<InFlightTx> {
+        self.state.lock().await.in_flight.get(&nonce).cloned()
+    }
+
+    /// Returns the nonces of all transactions in flight.
+    pub async fn in_flight_nonces(&self) -> Vec<u64> {
+        self.state.lock().await.in_flight.keys().copied().collect()
+    }
+
+    /// Forgets the transactions up to and including `nonce`, once mined.
+    pub async fn confirm(&self, nonce: u64) {
+        let mut state = self.state.lock().await;
+        state.in_flight = state.in_flight.split_off(&(nonce + 1));
+    }
+
+    /// Treats the private transaction with the given nonce as public once it
+    /// was published or the relay gave up on it, and marks the nonces stale.
+    /// The next nonce is then read from the chain first, which hands the nonce
+    /// out again unless the node knows the transaction as pending, so a
+    /// transaction the relay never included leaves no gap behind.
+    pub async fn publish(&self, nonce: u64) {
+        let mut state = self.state.lock().await;
+        if let Some(tx) = state.in_flight.get_mut(&nonce) {
+            tx.private = false;
+        }
+        state.stale = true;
+    }
+
+    /// Marks the nonces stale, e.g. after a "nonce too low" error, so they are
+    /// read from the chain before the next nonce is handed out.
+    pub async fn mark_stale(&self) { self.state.lock().await.stale = true; }
+
+    /// Reads the nonces from the chain, returning the next nonce.
+    pub async fn resync(&self) -> AppResult<u64> {
+        let mut state = self.state.lock().await;
+        self.sync(&mut state).await?;
+        Ok(state.next.expect("nonces are synced"))
+    }
+
+    async fn sync(&self, state: &mut NonceState) -> AppResult<()> {
+        let mined = self.source.mined_nonce(self.address).await?;
+        let pending = self.source.pending_nonce(self.address).await?;
+
+        // Mined transactions are no longer in flight
+        state.in_flight = state.in_flight.split_off(&mined);
+
+        // Transactions the node does not know as pending were dropped, they are
+        // forgotten and their nonces handed out again. Private transactions are
+        // not publicly visible before being included and are kept.
+        let (private, dropped): (BTreeMap<_, _>, BTreeMap<_, _>) = state
+            .in_flight
+            .split_off(&pending)
+            .into_iter()
+            .partition(|(_, tx)| tx.private);
+        state.in_flight.extend(private);
+        if !dropped.is_empty() {
+            warn!(
+                address = %self.address,
+                nonces = ?dropped.keys().collect::<Vec<_>>(),
+                "in flight transactions dropped, reusing their nonces"
+            );
+        }
+        let next = state
+            .in_flight
+            .last_key_value()
+            .map_or(pending, |(nonce, _)| pending.max(nonce + 1));
+        if let Some(previous) = state.next.filter(|previous| *previous != next) {
+            info!(address = %self.address, from = previous, to = next, "nonces resynced");
+        }
+        state.next = Some(next);
+        state.released.clear();
+        state.stale = false;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+
+    use super::*;
+
+    /// A chain whose transaction counts are set by the test, shared by clones.
+    #[derive(Debug, Clone, Default)]
+    struct FakeChain {
+        mined: Arc<AtomicU64>,
+        pending: Arc<AtomicU64>,
+        reads: Arc<AtomicUsize>,
+    }
+
+    impl FakeChain {
+        fn set(&self, mined: u64, pending: u64) {
+            self.mined.store(mined, Ordering::SeqCst);
+            self.pending.store(pending, Ordering::SeqCst);
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl NonceSource for FakeChain {
+        async fn mined_nonce(&self, _address: Address) -> AppResult<u64> {
+            Ok(self.mined.load(Ordering::SeqCst))
+        }
+
+        async fn pending_nonce(&self, _address: Address) -> AppResult<u64> {
+            self.reads.fetch_add(1, Ordering::SeqCst);
+            // Yield so concurrent acquisitions interleave with the sync
+            tokio::task::yield_now().await;
+            Ok(self.pending.load(Ordering::SeqCst))
+        }
+    }
+
+    fn in_flight_tx() -> InFlightTx {
+        InFlightTx {
+            hash: TxHash::ZERO,
+            request: TransactionRequest::default(),
+            max_fee_per_gas: 2_000_000_000,
+            max_priority_fee_per_gas: 1_000_000,
+            replacements: 0,
+            private: false,
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_concurrent_acquisitions_get_distinct_nonces() {
+        let chain = FakeChain::default();
+        chain.set(40, 42);
+        let manager = Arc::new(NonceManager::new(chain.clone(), Address::ZERO));
+
+        let tasks = (0..50).map(|_| {
+            let manager = manager.clone();
+            tokio::spawn(async move { manager.acquire().await.unwrap() })
+        });
+        let mut nonces = futures::future::join_all(tasks)
+            .await
+            .into_iter()
+            .map(|nonce| nonce.unwrap())
+            .collect::<Vec<_>>();
+        nonces.sort();
+
+        assert_eq!(nonces, (42..92).collect::<Vec<_>>());
+        assert_eq!(chain.reads.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_released_nonces_are_reused_first() {
+        let chain = FakeChain::default();
+        let manager = NonceManager::new(chain.clone(), Address::ZERO);
+        for expected in 0..3 {
+            assert_eq!(manager.acquire().await.unwrap(), expected);
+        }
+
+        manager.release(1).await;
+        assert_eq!(manager.acquire().await.unwrap(), 1);
+        assert_eq!(manager.acquire().await.unwrap(), 3);
+
+        // Releasing the latest nonce leaves no gap behind
+        manager.release(3).await;
+        assert_eq!(manager.acquire().await.unwrap(), 3);
+        assert_eq!(chain.reads.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_dropped_transaction_nonce_is_reused_after_resync() {
+        let chain = FakeChain::default();
+        chain.set(5, 5);
+        let manager = NonceManager::new(chain.clone(), Address::ZERO);
+        for expected in 5..8 {
+            let nonce = manager.acquire().await.unwrap();
+            assert_eq!(nonce, expected);
+            manager.track(nonce, in_flight_tx()).await;
+        }
+
+        // 5 is pending, 6 was dropped and 7 is stuck behind it
+        chain.set(5, 6);
+        assert_eq!(manager.resync().await.unwrap(), 6);
+        assert_eq!(manager.in_flight_nonces().await, vec![5]);
+        assert_eq!(manager.acquire().await.unwrap(), 6);
+        assert_eq!(manager.acquire().await.unwrap(), 7);
+
+        // Once mined, 5 is no longer in flight
+        chain.set(6, 8);
+        manager.resync().await.unwrap();
+        assert!(manager.in_flight_nonces().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_private_transactions_are_not_dropped_on_resync() {
+        let chain = FakeChain::default();
+        chain.set(5, 5);
+        let manager = NonceManager::new(chain.clone(), Address::ZERO);
+        assert_eq!(manager.acquire().await.unwrap(), 5);
+        manager
+            .track(5, InFlightTx { private: true, ..in_flight_tx() })
+            .await;
+
+        // The node does not know the private transaction as pending
+        assert_eq!(manager.resync().await.unwrap(), 6);
+        assert_eq!(manager.in_flight_nonces().await, vec![5]);
+        assert_eq!(manager.acquire().await.unwrap(), 6);
+    }
+
+    #[tokio::test]
+    async fn test_published_private_transaction_nonce_is_reused_if_dropped() {
+        let chain = FakeChain::default();
+        chain.set(5, 5);
+        let manager = NonceManager::new(chain.clone(), Address::ZERO);
+        for expected in 5..7 {
+            let nonce = manager.acquire().await.unwrap();
+            assert_eq!(nonce, expected);
+            manager
+                .track(nonce, InFlightTx { private: true, ..in_flight_tx() })
+                .await;
+        }
+
+        // The relay gave up on both, the node only accepted 5 once published
+        manager.publish(5).await;
+        manager.publish(6).await;
+        chain.set(5, 6);
+        assert_eq!(manager.acquire().await.unwrap(), 6);
+        assert_eq!(manager.in_flight_nonces().await, vec![5]);
+        assert!(!manager.in_flight(5).await.unwrap().private);
+        assert_eq!(chain.reads.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_stale_nonces_are_resynced_before_the_next_acquisition() {
+        let chain = FakeChain::default();
+        let manager = NonceManager::new(chain.clone(), Address::ZERO);
+        assert_eq!(manager.acquire().await.unwrap(), 0);
+        manager.track(0, in_flight_tx()).await;
+
+        // Transactions were sent outside of the manager, "nonce too low"
+        chain.set(9, 10);
+        assert_eq!(manager.acquire().await.unwrap(), 1);
+        manager.mark_stale().await;
+        assert_eq!(manager.acquire().await.unwrap(), 10);
+        assert!(manager.in_flight_nonces().await.is_empty());
+        assert_eq!(chain.reads.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_confirmed_transactions_are_no_longer_in_flight() {
+        let manager = NonceManager::new(FakeChain::default(), Address::ZERO);
+        for _ in 0..3 {
+            let nonce = manager.acquire().await.unwrap();
+            manager.track(nonce, in_flight_tx()).await;
+        }
+
+        manager.confirm(1).await;
+        assert_eq!(manager.in_flight_nonces().await, vec![2]);
+        assert!(manager.in_flight(2).await.is_some());
+    }
+}
@@ -0,0 +1,497 @@
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use alloy::{
+    primitives::{Address, Bytes, TxHash},
+    providers::Provider,
+};
+use sikkara_core::AppResult;
+use tokio::sync::{mpsc, Mutex};
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, info, warn};
+
+use super::{NonceSource, PendingTx};
+
+/// Source of the receipts of transactions and of the head of the chain.
+#[async_trait::async_trait]
+pub trait ReceiptSource: NonceSource {
+    /// Returns the number of the latest block.
+    async fn block_number(&self) -> AppResult<u64>;
+
+    /// Returns where a transaction was mined, none if it is not.
+    async fn receipt(&self, hash: TxHash) -> AppResult<Option<MinedTx>>;
+
+    /// Sends a signed transaction to the public mempool.
+    async fn publish(&self, raw: &Bytes) -> AppResult<()>;
+}
+
+#[async_trait::async_trait]
+impl<P> ReceiptSource for Arc<P>
+where
+    P: Provider + Send + Sync,
+{
+    async fn block_number(&self) -> AppResult<u64> { Ok(self.get_block_number().await?) }
+
+    async fn receipt(&self, hash: TxHash) -> AppResult<Option<MinedTx>> {
+        Ok(self
+            .get_transaction_receipt(hash)
+            .await?
+            .map(|receipt| MinedTx {
+                block_number: receipt.block_number.unwrap_or_default(),
+                gas_used: receipt.gas_used,
+                success: receipt.status(),
+            }))
+    }
+
+    async fn publish(&self, raw: &Bytes) -> AppResult<()> {
+        self.send_raw_transaction(raw).await?;
+        Ok(())
+    }
+}
+
+/// Where a transaction was mined.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MinedTx {
+    pub block_number: u64,
+    pub gas_used: u64,
+    /// Whether it succeeded rather than reverted
+    pub success: bool,
+}
+
+/// Progress of a watched transaction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReceiptStatus {
+    /// Mined, with fewer confirmations than required
+    Included { block_number: u64 },
+    /// Mined with the required confirmations
+    Confirmed { block_number: u64, gas_used: u64 },
+    /// Reverted, with the required confirmations
+    Reverted { block_number: u64, gas_used: u64 },
+    /// Its receipt disappeared, the block which included it was reorged out.
+    /// It is watched until mined again.
+    Reorged { block_number: u64 },
+    /// Its nonce was used by another transaction, e.g. a replacement
+    Dropped,
+    /// Not mined within the timeout
+    TimedOut { blocks: u64 },
+}
+
+impl ReceiptStatus {
+    /// Whether the transaction is no longer watched.
+    pub fn is_final(&self) -> bool {
+        !matches!(self, ReceiptStatus::Included { .. } | ReceiptStatus::Reorged { .. })
+    }
+}
+
+#[derive(Debug)]
+struct WatchedTx {
+    pending: PendingTx,
+    /// Head of the chain when the transaction was first polled
+    since_block: Option<u64>,
+    mined: Option<MinedTx>,
+    /// Whether a private transaction was published once the relay gave up
+    published: bool,
+    updates: mpsc::UnboundedSender<ReceiptStatus>,
+}
+
+/// Watches submitted transactions of a single signer across new blocks.
+///
+/// Every new block, the receipt of each watched transaction is read. A mined
+/// transaction is confirmed once `confirmations` blocks, including its own,
+/// are on top of the chain. A receipt disappearing means its block was
+/// reorged out, the transaction is then watched until it is mined again. A
+/// transaction whose nonce was mined without it was dropped, and one not mined
+/// within `timeout_blocks` times out.
+///
+/// Private transactions not included by the relay before their last block are
+/// published.
+///
+/// # Type Parameters
+///
+/// * `S` - The [`ReceiptSource`] the chain is read from
+#[derive(Debug)]
+pub struct ReceiptMonitor<S> {
+    source: S,
+    address: Address,
+    confirmations: u64,
+    timeout_blocks: u64,
+    head: Mutex<Option<u64>>,
+    watched: Mutex<HashMap<TxHash, WatchedTx>>,
+}
+
+impl<S> ReceiptMonitor<S>
+where
+    S: ReceiptSource,
+{
+    pub fn new(source: S, address: Address, confirmations: u64, timeout_blocks: u64) -> Self {
+        Self {
+            source,
+            address,
+            confirmations: confirmations.max(1),
+            timeout_blocks,
+            head: Mutex::new(None),
+            watched: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Watches a submitted transaction, returning the updates of its progress
+    /// which end with a final [`ReceiptStatus`].
+    pub async fn watch(&self, pending: &PendingTx) -> mpsc::UnboundedReceiver<ReceiptStatus> {
+        let (updates, receiver) = mpsc::unbounded_channel();
+        let watched = WatchedTx {
+            pending: pending.clone(),
+            since_block: None,
+            mined: None,
+            published: pending.private_until_block.is_none(),
+            updates,
+        };
+        self.watched.lock().await.insert(pending.hash, watched);
+        receiver
+    }
+
+    /// Returns the hashes of the watched transactions.
+    pub async fn watched(&self) -> Vec<TxHash> {
+        self.watched.lock().await.keys().copied().collect()
+    }
+
+    /// Updates the watched transactions if a new block was produced since the
+    /// last poll.
+    pub async fn poll(&self) -> AppResult<()> {
+        let head = self.source.block_number().await?;
+        {
+            let mut last = self.head.lock().await;
+            if *last == Some(head) {
+                return Ok(());
+            }
+            *last = Some(head);
+        }
+
+        let mut watched = self.watched.lock().await;
+        let mut mined_nonce = None;
+        let mut finished = Vec::new();
+        for (hash, tx) in watched.iter_mut() {
+            let since_block = *tx.since_block.get_or_insert(head);
+            let status = match self.source.receipt(*hash).await? {
+                Some(mined) => self.mined_status(tx, mined, head),
+                None => {
+                    let nonce = match mined_nonce {
+                        Some(nonce) => nonce,
+                        None => *mined_nonce.insert(self.source.mined_nonce(self.address).await?),
+                    };
+                    self.unmined_status(tx, nonce, head, since_block).await?
+                },
+            };
+            let Some(status) = status else {
+                continue;
+            };
+            if status.is_final() {
+                finished.push(*hash);
+            }
+            // The watcher may have given up on the transaction
+            let _ = tx.updates.send(status);
+        }
+        for hash in finished {
+            watched.remove(&hash);
+        }
+        Ok(())
+    }
+
+    fn mined_status(&self, tx: &mut WatchedTx, mined: MinedTx, head: u64) -> Option<ReceiptStatus> {
+        let included = tx.mined.replace(mined) != Some(mined);
+        let confirmations = (head + 1).saturating_sub(mined.block_number);
+        if confirmations >= self.confirmations {
+            let (block_number, gas_used) = (mined.block_number, mined.gas_used);
+            info!(tx_hash = %tx.pending.hash, block_number, confirmations, "transaction confirmed");
+            return Some(if mined.success {
+                ReceiptStatus::Confirmed { block_number, gas_used }
+            } else {
+                ReceiptStatus::Reverted { block_number, gas_used }
+            });
+        }
+        let block_number = mined.block_number;
+        included.then(|| {
+            debug!(tx_hash = %tx.pending.hash, block_number, "transaction included");
+            ReceiptStatus::Included { block_number }
+        })
+    }
+
+    async fn unmined_status(
+        &self,
+        tx: &mut WatchedTx,
+        mined_nonce: u64,
+        head: u64,
+        since_block: u64,
+    ) -> AppResult<Option<ReceiptStatus>> {
+        if let Some(mined) = tx.mined.take() {
+            warn!(
+                tx_hash = %tx.pending.hash,
+                block_number = mined.block_number,
+                "transaction receipt disappeared, reorged out"
+            );
+            return Ok(Some(ReceiptStatus::Reorged { block_number: mined.block_number }));
+        }
+        if mined_nonce > tx.pending.nonce {
+            // Mined between both reads rather than dropped
+            if let Some(mined) = self.source.receipt(tx.pending.hash).await? {
+                return Ok(self.mined_status(tx, mined, head));
+            }
+            warn!(
+                tx_hash = %tx.pending.hash,
+                nonce = tx.pending.nonce,
+                mined_nonce,
+                "transaction dropped, its nonce was used by another transaction"
+            );
+            return Ok(Some(ReceiptStatus::Dropped));
+        }
+        if let Some(until) = tx.pending.private_until_block.filter(|_| !tx.published) {
+            if head > until {
+                warn!(
+                    tx_hash = %tx.pending.hash,
+                    until,
+                    "transaction not included by the private relay, publishing it"
+                );
+                // Fails if it was included or published in the meantime
+                if let Err(e) = self.source.publish(&tx.pending.raw).await {
+                    warn!(tx_hash = %tx.pending.hash, error = %e, "failed to publish transaction");
+                }
+                tx.published = true;
+            }
+        }
+        let blocks = head.saturating_sub(since_block);
+        if blocks >= self.timeout_blocks {
+            warn!(tx_hash = %tx.pending.hash, blocks, "transaction timed out");
+            return Ok(Some(ReceiptStatus::TimedOut { blocks }));
+        }
+        Ok(None)
+    }
+}
+
+impl<S> ReceiptMonitor<S>
+where
+    S: ReceiptSource + 'static,
+{
+    /// Polls for new blocks every `poll_interval` until `shutdown` is
+    /// cancelled.
+    pub fn spawn(
+        self: Arc<Self>,
+        poll_interval: Duration,
+        shutdown: CancellationToken,
+    ) -> tokio::task::JoinHandle<AppResult<()>> {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(poll_interval);
+            loop {
+                tokio::select! {
+                    _ = shutdown.cancelled() => return Ok(()),
+                    _ = interval.tick() => {
+                        if let Err(e) = self.poll().await {
+                            warn!(address = %self.address, error = %e, "failed to poll receipts");
+                        }
+                    },
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex as StdMutex;
+
+    use alloy::primitives::b256;
+
+    use super::*;
+
+    const TX_HASH: TxHash =
+        b256!("0x2c6c1d4b32d4a6f7f3e2c95ac46f8d2d4e0bd7b4b2b4c4f1a2a3b9c1d8e7f6a5");
+
+    /// A chain whose head, receipts and mined nonce are scripted by the test,
+    /// shared by clones.
+    #[derive(Debug, Clone, Default)]
+    struct ScriptedChain {
+        state: Arc<StdMutex<ChainState>>,
+    }
+
+    #[derive(Debug, Default)]
+    struct ChainState {
+        head: u64,
+        mined_nonce: u64,
+        receipts: HashMap<TxHash, MinedTx>,
+        published: Vec<Bytes>,
+    }
+
+    impl ScriptedChain {
+        fn advance(&self, head: u64) { self.state.lock().unwrap().head = head; }
+
+        fn mine(&self, hash: TxHash, block_number: u64, success: bool) {
+            let mut state = self.state.lock().unwrap();
+            state
+                .receipts
+                .insert(hash, MinedTx { block_number, gas_used: 180_000, success });
+            state.mined_nonce += 1;
+        }
+
+        fn reorg(&self, hash: TxHash) {
+            let mut state = self.state.lock().unwrap();
+            state.receipts.remove(&hash);
+            state.mined_nonce -= 1;
+        }
+
+        fn set_mined_nonce(&self, nonce: u64) { self.state.lock().unwrap().mined_nonce = nonce; }
+    }
+
+    #[async_trait::async_trait]
+    impl NonceSource for ScriptedChain {
+        async fn mined_nonce(&self, _address: Address) -> AppResult<u64> {
+            Ok(self.state.lock().unwrap().mined_nonce)
+        }
+
+        async fn pending_nonce(&self, _address: Address) -> AppResult<u64> {
+            Ok(self.state.lock().unwrap().mined_nonce)
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl ReceiptSource for ScriptedChain {
+        async fn block_number(&self) -> AppResult<u64> { Ok(self.state.lock().unwrap().head) }
+
+        async fn receipt(&self, hash: TxHash) -> AppResult<Option<MinedTx>> {
+            Ok(self.state.lock().unwrap().receipts.get(&hash).copied())
+        }
+
+        async fn publish(&self, raw: &Bytes) -> AppResult<()> {
+            self.state.lock().unwrap().published.push(raw.clone());
+            Ok(())
+        }
+    }
+
+    fn pending_tx(nonce: u64) -> PendingTx {
+        PendingTx {
+            hash: TX_HASH,
+            nonce,
+            gas_limit: 200_000,
+            max_fee_per_gas: 2_000_000_000,
+            max_priority_fee_per_gas: 1_000_000,
+            raw: Bytes::from_static(&[0x02]),
+            private_until_block: None,
+        }
+    }
+
+    async fn step(
+        chain: &ScriptedChain,
+        monitor: &ReceiptMonitor<ScriptedChain>,
+        head: u64,
+    ) -> AppResult<()> {
+        chain.advance(head);
+        monitor.poll().await
+    }
+
+    #[tokio::test]
+    async fn test_transaction_is_confirmed_after_confirmation_depth() {
+        let chain = ScriptedChain::default();
+        chain.set_mined_nonce(7);
+        let monitor = ReceiptMonitor::new(chain.clone(), Address::ZERO, 3, 20);
+        let mut updates = monitor.watch(&pending_tx(7)).await;
+
+        step(&chain, &monitor, 100).await.unwrap();
+        chain.mine(TX_HASH, 101, true);
+        step(&chain, &monitor, 101).await.unwrap();
+        assert_eq!(updates.recv().await, Some(ReceiptStatus::Included { block_number: 101 }));
+
+        // Polling the same head again is a no-op
+        step(&chain, &monitor, 101).await.unwrap();
+        step(&chain, &monitor, 102).await.unwrap();
+        assert!(updates.try_recv().is_err());
+
+        step(&chain, &monitor, 103).await.unwrap();
+        assert_eq!(
+            updates.recv().await,
+            Some(ReceiptStatus::Confirmed { block_number: 101, gas_used: 180_000 })
+        );
+        assert!(monitor.watched().await.is_empty());
+        assert_eq!(updates.recv().await, None);
+    }
+
+    #[tokio::test]
+    async fn test_reverted_transaction_is_reported_once_confirmed() {
+        let chain = ScriptedChain::default();
+        let monitor = ReceiptMonitor::new(chain.clone(), Address::ZERO, 1, 20);
+        let mut updates = monitor.watch(&pending_tx(0)).await;
+
+        chain.mine(TX_HASH, 5, false);
+        step(&chain, &monitor, 5).await.unwrap();
+        assert_eq!(
+            updates.recv().await,
+            Some(ReceiptStatus::Reverted { block_number: 5, gas_used: 180_000 })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_transaction_whose_nonce_was_used_is_dropped() {
+        let chain = ScriptedChain::default();
+        chain.set_mined_nonce(7);
+        let monitor = ReceiptMonitor::new(chain.clone(), Address::ZERO, 2, 20);
+        let mut updates = monitor.watch(&pending_tx(7)).await;
+
+        step(&chain, &monitor, 100).await.unwrap();
+        // Another transaction with the same nonce is mined
+        chain.set_mined_nonce(8);
+        step(&chain, &monitor, 101).await.unwrap();
+        assert_eq!(updates.recv().await, Some(ReceiptStatus::Dropped));
+        assert!(monitor.watched().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_reorged_transaction_is_watched_until_mined_again() {
+        let chain = ScriptedChain::default();
+        let monitor = ReceiptMonitor::new(chain.clone(), Address::ZERO, 2, 20);
+        let mut updates = monitor.watch(&pending_tx(0)).await;
+
+        chain.mine(TX_HASH, 10, true);
+        step(&chain, &monitor, 10).await.unwrap();
+        assert_eq!(updates.recv().await, Some(ReceiptStatus::Included { block_number: 10 }));
+
+        // The block including the transaction is reorged out
+        chain.reorg(TX_HASH);
+        step(&chain, &monitor, 11).await.unwrap();
+        assert_eq!(updates.recv().await, Some(ReceiptStatus::Reorged { block_number: 10 }));
+        assert_eq!(monitor.watched().await, vec![TX_HASH]);
+
+        // Mined again, its confirmations count from its new block
+        chain.mine(TX_HASH, 12, true);
+        step(&chain, &monitor, 12).await.unwrap();
+        assert_eq!(updates.recv().await, Some(ReceiptStatus::Included { block_number: 12 }));
+        step(&chain, &monitor, 13).await.unwrap();
+        assert_eq!(
+            updates.recv().await,
+            Some(ReceiptStatus::Confirmed { block_number: 12, gas_used: 180_000 })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_unmined_transaction_times_out() {
+        let chain = ScriptedChain::default();
+        let monitor = ReceiptMonitor::new(chain.clone(), Address::ZERO, 1, 3);
+        let mut updates = monitor.watch(&pending_tx(0)).await;
+
+        for head in 50..53 {
+            step(&chain, &monitor, head).await.unwrap();
+        }
+        assert!(updates.try_recv().is_err());
+        step(&chain, &monitor, 53).await.unwrap();
+        assert_eq!(updates.recv().await, Some(ReceiptStatus::TimedOut { blocks: 3 }));
+    }
+
+    #[tokio::test]
+    async fn test_private_transaction_is_published_after_its_last_block() {
+        let chain = ScriptedChain::default();
+        let monitor = ReceiptMonitor::new(chain.clone(), Address::ZERO, 1, 20);
+        let pending = PendingTx { private_until_block: Some(101), ..pending_tx(0) };
+        let _updates = monitor.watch(&pending).await;
+
+        step(&chain, &monitor, 100).await.unwrap();
+        step(&chain, &monitor, 101).await.unwrap();
+        assert!(chain.state.lock().unwrap().published.is_empty());
+        step(&chain, &monitor, 102).await.unwrap();
+        step(&chain, &monitor, 103).await.unwrap();
+        assert_eq!(chain.state.lock().unwrap().published, vec![pending.raw]);
+    }
+}
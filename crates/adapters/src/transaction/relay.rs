@@ -0,0 +1,217 @@
+use std::{fmt, time::Duration};
+
+use alloy::{
+    primitives::keccak256,
+    signers::{local::PrivateKeySigner, SignerSync},
+};
+use serde::Deserialize;
+use serde_json::json;
+use sikkara_core::{AppError, AppResult};
+
+/// Header authenticating requests to Flashbots style relays
+const SIGNATURE_HEADER: &str = "X-Flashbots-Signature";
+
+#[derive(Debug, Deserialize)]
+struct RelayResponse {
+    error: Option<RelayError>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RelayError {
+    message: String,
+}
+
+/// Client of a Flashbots style relay, submitting transactions privately with
+/// `eth_sendPrivateTransaction` so they never show up in the public mempool
+/// before being included.
+///
+/// Requests are signed by the auth signer, if any, which the relay uses as
+/// the reputation of the sender. [`fmt::Debug`] only shows its address.
+#[derive(Clone)]
+pub struct PrivateRelay {
+    client: reqwest::Client,
+    url: String,
+    auth: Option<PrivateKeySigner>,
+    timeout: Duration,
+}
+
+impl fmt::Debug for PrivateRelay {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PrivateRelay")
+            .field("url", &self.url)
+            .field("auth", &self.auth.as_ref().map(|signer| signer.address()))
+            .field("timeout", &self.timeout)
+            .finish()
+    }
+}
+
+impl PrivateRelay {
+    pub fn new(url: &str) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            url: url.to_string(),
+            auth: None,
+            timeout: Duration::from_secs(5),
+        }
+    }
+
+    /// Signs the requests with `signer`.
+    pub fn with_auth_signer(mut self, signer: PrivateKeySigner) -> Self {
+        self.auth = Some(signer);
+        self
+    }
+
+    /// Fails requests not answered within `timeout`.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn url(&self) -> &str { &self.url }
+
+    /// Submits a signed transaction, which the relay tries to include up to
+    /// block `max_block_number`.
+    pub async fn send_private_transaction(
+        &self,
+        raw: &[u8],
+        max_block_number: u64,
+    ) -> AppResult<()> {
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "eth_sendPrivateTransaction",
+            "params": [{
+                "tx": format!("0x{}", hex::encode(raw)),
+                "maxBlockNumber": format!("{:#x}", max_block_number),
+                "preferences": { "fast": true }
+            }]
+        })
+        .to_string();
+        let mut request = self
+            .client
+            .post(&self.url)
+            .timeout(self.timeout)
+            .header(reqwest::header::CONTENT_TYPE, "application/json");
+        if let Some(signer) = &self.auth {
+            request = request.header(SIGNATURE_HEADER, sign_body(signer, &body)?);
+        }
+
+        let response = request.body(body).send().await.map_err(|e| {
+            let reason = if e.is_timeout() { "timed out".to_string() } else { e.to_string() };
+            AppError::HttpError(format!("relay {} request failed: {}", self.url, reason))
+        })?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(AppError::HttpError(format!(
+                "relay {} returned status {}: {}",
+                self.url, status, body
+            ))
+            .into());
+        }
+        let response: RelayResponse = response.json().await.map_err(|e| {
+            AppError::HttpError(format!("relay {} returned invalid response: {}", self.url, e))
+        })?;
+        match response.error {
+            Some(error) => Err(AppError::TransactionError(format!(
+                "relay {} rejected transaction: {}",
+                self.url, error.message
+            ))
+            .into()),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Signs a request body the way Flashbots relays expect, i.e. the EIP-191
+/// signature of the hex encoded body hash, prefixed by the signer address.
+fn sign_body(signer: &PrivateKeySigner, body: &str) -> AppResult<String> {
+    let message = format!("{:?}", keccak256(body.as_bytes()));
+    let signature = signer
+        .sign_message_sync(message.as_bytes())
+        .map_err(|e| AppError::TransactionError(format!("failed to sign relay request: {}", e)))?;
+    Ok(format!("{}:0x{}", signer.address(), hex::encode(signature.as_bytes())))
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::Signature;
+    use wiremock::{
+        matchers::{body_partial_json, header_exists, method},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    use super::*;
+
+    /// The first well known Anvil development key.
+    const DEV_KEY: &str = "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+
+    #[test]
+    fn test_body_signature_recovers_to_the_signer() {
+        let signer = DEV_KEY.parse::<PrivateKeySigner>().unwrap();
+        let header = sign_body(&signer, r#"{"id":1}"#).unwrap();
+        let (address, signature) = header.split_once(':').unwrap();
+        assert_eq!(address, signer.address().to_string());
+
+        let signature =
+            Signature::try_from(hex::decode(&signature[2..]).unwrap().as_slice()).unwrap();
+        let message = format!("{:?}", keccak256(br#"{"id":1}"#));
+        assert_eq!(signature.recover_address_from_msg(message).unwrap(), signer.address());
+    }
+
+    #[tokio::test]
+    async fn test_accepted_and_rejected_transactions() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(header_exists(SIGNATURE_HEADER))
+            .and(body_partial_json(json!({
+                "method": "eth_sendPrivateTransaction",
+                "params": [{ "tx": "0x02f8", "maxBlockNumber": "0x1e8483" }]
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "result": "0x2c6c1d4b32d4a6f7f3e2c95ac46f8d2d4e0bd7b4b2b4c4f1a2a3b9c1d8e7f6a5"
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "error": { "code": -32000, "message": "bundle simulation failed" }
+            })))
+            .mount(&server)
+            .await;
+
+        let relay = PrivateRelay::new(&server.uri()).with_auth_signer(DEV_KEY.parse().unwrap());
+        relay
+            .send_private_transaction(&[0x02, 0xf8], 2_000_003)
+            .await
+            .unwrap();
+
+        let error = relay
+            .send_private_transaction(&[0x02, 0xf9], 2_000_003)
+            .await
+            .unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("rejected transaction: bundle simulation failed"));
+    }
+
+    #[tokio::test]
+    async fn test_unanswered_request_times_out() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_secs(2)))
+            .mount(&server)
+            .await;
+
+        let relay = PrivateRelay::new(&server.uri()).with_timeout(Duration::from_millis(100));
+        let error = relay
+            .send_private_transaction(&[0x02, 0xf8], 2_000_003)
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("timed out"), "{}", error);
+    }
+}
@@ -0,0 +1,828 @@
+use std::{
+    fmt,
+    path::Path,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+pub use alloy::signers::local::PrivateKeySigner;
+use alloy::{
+    consensus::TxEnvelope,
+    eips::{eip1559::Eip1559Estimation, eip2718::Encodable2718},
+    network::{EthereumWallet, TransactionBuilder},
+    primitives::{Address, Bytes, TxHash},
+    providers::Provider,
+    rpc::types::{TransactionReceipt, TransactionRequest},
+    transports::TransportError,
+};
+use serde::Serialize;
+use sikkara_core::{AppError, AppResult, Secret};
+use tracing::{info, warn};
+
+use super::{FeeEstimator, InFlightTx, NonceManager, PrivateRelay, Urgency};
+
+/// Reads a signer from a hex encoded private key.
+///
+/// The error never contains the key.
+pub fn signer_from_key(key: &Secret) -> AppResult<PrivateKeySigner> {
+    key.expose()
+        .trim()
+        .parse::<PrivateKeySigner>()
+        .map_err(|_| AppError::ConfigError("invalid signer private key".to_string()).into())
+}
+
+/// Decrypts a signer from an encrypted JSON keystore file.
+pub fn signer_from_keystore(
+    path: impl AsRef<Path>,
+    password: &Secret,
+) -> AppResult<PrivateKeySigner> {
+    let path = path.as_ref();
+    PrivateKeySigner::decrypt_keystore(path, password.expose()).map_err(|e| {
+        AppError::ConfigError(format!("failed to decrypt keystore {}: {}", path.display(), e))
+            .into()
+    })
+}
+
+/// Upper bounds of the fees paid per gas, in wei.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FeeCaps {
+    pub max_fee_per_gas: u128,
+    pub max_priority_fee_per_gas: u128,
+}
+
+impl FeeCaps {
+    /// Caps the estimated fees, keeping the priority fee within the max fee.
+    pub fn apply(&self, estimate: Eip1559Estimation) -> Eip1559Estimation {
+        let max_fee_per_gas = estimate.max_fee_per_gas.min(self.max_fee_per_gas);
+        let max_priority_fee_per_gas = estimate
+            .max_priority_fee_per_gas
+            .min(self.max_priority_fee_per_gas)
+            .min(max_fee_per_gas);
+        Eip1559Estimation { max_fee_per_gas, max_priority_fee_per_gas }
+    }
+}
+
+/// Bumps the fees of a transaction being replaced by `bump_percent`, which
+/// nodes require to be at least 10%, keeping them within the caps.
+///
+/// Returns an error if the caps leave no room for the bump.
+pub fn bump_fees(
+    fees: Eip1559Estimation,
+    bump_percent: u128,
+    caps: &FeeCaps,
+) -> AppResult<Eip1559Estimation> {
+    let bump = |fee: u128| fee + (fee * bump_percent.max(10)).div_ceil(100);
+    let bumped = Eip1559Estimation {
+        max_fee_per_gas: bump(fees.max_fee_per_gas),
+        max_priority_fee_per_gas: bump(fees.max_priority_fee_per_gas),
+    };
+    if caps.apply(bumped) != bumped {
+        return Err(AppError::TransactionError(format!(
+            "fee bump to {} / {} wei exceeds the caps {} / {} wei",
+            bumped.max_fee_per_gas,
+            bumped.max_priority_fee_per_gas,
+            caps.max_fee_per_gas,
+            caps.max_priority_fee_per_gas
+        ))
+        .into());
+    }
+    Ok(bumped)
+}
+
+/// Interval at which the receipt of a submitted transaction is polled
+const RECEIPT_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Why a transaction failed, telling failed trades apart from transactions
+/// that never made it on chain.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TxFailureKind {
+    /// Reverted, in simulation or on chain
+    Revert,
+    /// Not mined in time
+    Timeout,
+    /// Rejected for paying too little fees
+    FeeTooLow,
+    /// Its nonce was used by another transaction
+    Dropped,
+    Other,
+}
+
+impl TxFailureKind {
+    /// Classifies a transaction error from its message.
+    pub fn classify(error: &impl fmt::Display) -> Self {
+        let message = error.to_string().to_lowercase();
+        if message.contains("revert") {
+            TxFailureKind::Revert
+        } else if message.contains("timed out") || message.contains("timeout") {
+            TxFailureKind::Timeout
+        } else if message.contains("dropped") {
+            TxFailureKind::Dropped
+        } else if ["fee too low", "underpriced", "less than block base fee"]
+            .iter()
+            .any(|pattern| message.contains(pattern))
+        {
+            TxFailureKind::FeeTooLow
+        } else {
+            TxFailureKind::Other
+        }
+    }
+}
+
+/// A submitted transaction, with the nonce and fees it was signed with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PendingTx {
+    pub hash: TxHash,
+    pub nonce: u64,
+    pub gas_limit: u64,
+    pub max_fee_per_gas: u128,
+    pub max_priority_fee_per_gas: u128,
+    /// The signed transaction
+    pub raw: Bytes,
+    /// Last block the private relay tries to include the transaction in, none
+    /// if it was sent publicly
+    pub private_until_block: Option<u64>,
+}
+
+/// Error of a send, telling whether the nonce of the transaction may have
+/// been used.
+#[derive(Debug)]
+enum SendError {
+    /// The transaction failed before being broadcast, or the node or relay
+    /// rejected it, so its nonce is unused
+    NotSent(anyhow::Error),
+    /// The node or relay may have accepted the transaction, e.g. it timed
+    /// out after receiving it
+    Unknown(anyhow::Error),
+}
+
+impl SendError {
+    /// Classifies an error of broadcasting a transaction, which was only not
+    /// sent if the node or relay answered with a rejection.
+    fn broadcast(error: anyhow::Error) -> Self {
+        let rejected = match error.downcast_ref::<TransportError>() {
+            // The node already knowing the transaction accepted it earlier
+            Some(error) => error.as_error_resp().is_some_and(|response| {
+                let message = response.message.to_lowercase();
+                !message.contains("already known") && !message.contains("known transaction")
+            }),
+            None => matches!(error.downcast_ref::<AppError>(), Some(AppError::TransactionError(_))),
+        };
+        if rejected {
+            SendError::NotSent(error)
+        } else {
+            SendError::Unknown(error)
+        }
+    }
+
+    fn into_inner(self) -> anyhow::Error {
+        match self {
+            SendError::NotSent(error) | SendError::Unknown(error) => error,
+        }
+    }
+}
+
+/// Private submission of transactions through a relay.
+#[derive(Debug, Clone)]
+struct PrivateSubmission {
+    relay: PrivateRelay,
+    /// Blocks the relay is given to include a transaction before it is
+    /// published
+    fallback_after_blocks: u64,
+}
+
+/// Builds, signs and submits EIP-1559 transactions from a single signer.
+///
+/// Every send takes its nonce from a [`NonceManager`], so concurrent
+/// submissions never reuse a nonce. Transactions are sent to the public
+/// mempool, or privately through a relay if one is configured. The signer is
+/// never formatted, [`fmt::Debug`] only shows its address.
+///
+/// # Type Parameters
+///
+/// * `P` - The RPC provider type that implements [`alloy::providers::Provider`]
+pub struct TxSubmitter<P>
+where
+    P: Provider + Send + Sync,
+{
+    provider: Arc<P>,
+    wallet: EthereumWallet,
+    address: Address,
+    chain_id: u64,
+    fees: FeeEstimator,
+    nonces: NonceManager<Arc<P>>,
+    private: Option<PrivateSubmission>,
+}
+
+impl<P> fmt::Debug for TxSubmitter<P>
+where
+    P: Provider + Send + Sync,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TxSubmitter")
+            .field("address", &self.address)
+            .field("chain_id", &self.chain_id)
+            .field("fees", &self.fees)
+            .field("private", &self.private)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<P> TxSubmitter<P>
+where
+    P: Provider + Send + Sync,
+{
+    /// Creates a submitter signing with `signer` for the chain `chain_id`,
+    /// paying the fees picked by `fees`.
+    pub fn new(
+        provider: Arc<P>,
+        signer: PrivateKeySigner,
+        chain_id: u64,
+        fees: FeeEstimator,
+    ) -> Self {
+        let address = signer.address();
+        let nonces = NonceManager::new(provider.clone(), address);
+        Self {
+            provider,
+            wallet: EthereumWallet::from(signer),
+            address,
+            chain_id,
+            fees,
+            nonces,
+            private: None,
+        }
+    }
+
+    /// Sends transactions privately through `relay`, publishing those it did
+    /// not include within `fallback_after_blocks` blocks.
+    pub fn with_private_relay(mut self, relay: PrivateRelay, fallback_after_blocks: u64) -> Self {
+        self.private = Some(PrivateSubmission { relay, fallback_after_blocks });
+        self
+    }
+
+    /// Returns the address transactions are sent from.
+    pub fn address(&self) -> Address { self.address }
+
+    /// Returns the chain transactions are signed for.
+    pub fn chain_id(&self) -> u64 { self.chain_id }
+
+    /// Returns the provider transactions are submitted through.
+    pub fn provider(&self) -> &Arc<P> { &self.provider }
+
+    /// Returns the nonces of the signer.
+    pub fn nonces(&self) -> &NonceManager<Arc<P>> { &self.nonces }
+
+    /// Fills the sender, chain id, nonce, fees for `urgency` and, unless
+    /// already set, the estimated gas limit of a transaction.
+    pub async fn prepare(
+        &self,
+        request: TransactionRequest,
+        nonce: u64,
+        urgency: Urgency,
+    ) -> AppResult<TransactionRequest> {
+        let fees = self.fees.estimate(self.provider.as_ref(), urgency).await?;
+        let request = request
+            .with_from(self.address)
+            .with_chain_id(self.chain_id)
+            .with_nonce(nonce)
+            .with_max_fee_per_gas(fees.max_fee_per_gas)
+            .with_max_priority_fee_per_gas(fees.max_priority_fee_per_gas);
+        if request.gas_limit().is_some() {
+            return Ok(request);
+        }
+        let gas_limit = self.provider.estimate_gas(request.clone()).await?;
+        Ok(request.with_gas_limit(gas_limit))
+    }
+
+    /// Simulates a transaction from the signer with `eth_call`, failing if it
+    /// reverts.
+    pub async fn simulate(&self, request: &TransactionRequest) -> AppResult<()> {
+        self.provider
+            .call(request.clone().with_from(self.address))
+            .await?;
+        Ok(())
+    }
+
+    /// Waits for a submitted transaction to be mined, failing if it reverted
+    /// or was not mined within `timeout`.
+    ///
+    /// A private transaction is not publicly visible until it is included, it
+    /// is only published once the relay gave up on it, or when the wait times
+    /// out first. Its nonce is then read from the node again, so that it is
+    /// reused if the published transaction is dropped.
+    pub async fn confirm(
+        &self,
+        pending: &PendingTx,
+        timeout: Duration,
+    ) -> AppResult<TransactionReceipt> {
+        let started_at = Instant::now();
+        let mut published = pending.private_until_block.is_none();
+        loop {
+            if let Some(receipt) = self.provider.get_transaction_receipt(pending.hash).await? {
+                self.nonces.confirm(pending.nonce).await;
+                if !receipt.status() {
+                    return Err(AppError::TransactionError(format!(
+                        "transaction {} reverted in block {}",
+                        pending.hash,
+                        receipt.block_number.unwrap_or_default()
+                    ))
+                    .into());
+                }
+                return Ok(receipt);
+            }
+            if let Some(until) = pending.private_until_block.filter(|_| !published) {
+                if self.provider.get_block_number().await? > until {
+                    warn!(
+                        tx_hash = %pending.hash,
+                        until,
+                        "transaction not included by the private relay, publishing it"
+                    );
+                    self.publish(pending).await;
+                    published = true;
+                }
+            }
+            if started_at.elapsed() >= timeout {
+                if !published {
+                    warn!(tx_hash = %pending.hash, "private transaction timed out, publishing it");
+                    self.publish(pending).await;
+                }
+                return Err(AppError::TransactionError(format!(
+                    "transaction {} timed out, not mined within {:?}",
+                    pending.hash, timeout
+                ))
+                .into());
+            }
+            tokio::time::sleep(RECEIPT_POLL_INTERVAL).await;
+        }
+    }
+
+    /// Publishes a private transaction, handing its nonce back to the nonce
+    /// manager as a public one.
+    async fn publish(&self, pending: &PendingTx) {
+        // Fails if it was included or published in the meantime
+        if let Err(e) = self.provider.send_raw_transaction(&pending.raw).await {
+            warn!(tx_hash = %pending.hash, error = %e, "failed to publish transaction");
+        }
+        self.nonces.publish(pending.nonce).await;
+    }
+
+    /// Signs a prepared transaction.
+    pub async fn sign(&self, request: TransactionRequest) -> AppResult<TxEnvelope> {
+        request
+            .build(&self.wallet)
+            .await
+            .map_err(|e| AppError::TransactionError(format!("failed to sign: {}", e)).into())
+    }
+
+    /// Prepares, signs and submits a transaction with the next nonce of the
+    /// signer, returning without waiting for it to be included.
+    ///
+    /// On a "nonce too low" error the nonces are resynced from the chain and
+    /// the transaction is submitted once more.
+    pub async fn submit(
+        &self,
+        request: TransactionRequest,
+        urgency: Urgency,
+    ) -> AppResult<PendingTx> {
+        match self.submit_once(request.clone(), urgency).await {
+            Err(e) if is_nonce_too_low(&e) => {
+                warn!(from = %self.address, error = %e, "nonce too low, resyncing nonces");
+                self.nonces.mark_stale().await;
+                self.submit_once(request, urgency).await
+            },
+            result => result,
+        }
+    }
+
+    /// Replaces the in flight transaction with the given nonce, e.g. when it is
+    /// stuck, by the same transaction with fees escalated by the
+    /// [`super::EscalationPolicy`] of the submitter.
+    pub async fn replace(&self, nonce: u64) -> AppResult<PendingTx> {
+        let in_flight = self.nonces.in_flight(nonce).await.ok_or_else(|| {
+            AppError::TransactionError(format!("no transaction in flight with nonce {}", nonce))
+        })?;
+        let fees = Eip1559Estimation {
+            max_fee_per_gas: in_flight.max_fee_per_gas,
+            max_priority_fee_per_gas: in_flight.max_priority_fee_per_gas,
+        };
+        let fees = self
+            .fees
+            .escalate(fees, in_flight.replacements)
+            .ok_or_else(|| {
+                AppError::TransactionError(format!(
+                    "transaction with nonce {} can no longer be replaced after {} replacements",
+                    nonce, in_flight.replacements
+                ))
+            })?;
+        let request = in_flight
+            .request
+            .with_max_fee_per_gas(fees.max_fee_per_gas)
+            .with_max_priority_fee_per_gas(fees.max_priority_fee_per_gas);
+        let pending = self
+            .send(request, nonce, in_flight.replacements + 1)
+            .await
+            .map_err(SendError::into_inner)?;
+        info!(
+            tx_hash = %pending.hash,
+            replaced = %in_flight.hash,
+            nonce,
+            replacements = in_flight.replacements + 1,
+            max_fee_per_gas = pending.max_fee_per_gas,
+            max_priority_fee_per_gas = pending.max_priority_fee_per_gas,
+            "transaction replaced"
+        );
+        Ok(pending)
+    }
+
+    async fn submit_once(
+        &self,
+        request: TransactionRequest,
+        urgency: Urgency,
+    ) -> AppResult<PendingTx> {
+        let nonce = self.nonces.acquire().await?;
+        let sent = match self.prepare(request, nonce, urgency).await {
+            Ok(request) => self.send(request, nonce, 0).await,
+            Err(e) => Err(SendError::NotSent(e)),
+        };
+        let pending = match sent {
+            Ok(pending) => pending,
+            Err(SendError::NotSent(e)) => {
+                self.nonces.release(nonce).await;
+                return Err(e);
+            },
+            // Reusing the nonce could replace the transaction, the node tells
+            // whether it was used
+            Err(SendError::Unknown(e)) => {
+                warn!(
+                    from = %self.address,
+                    nonce,
+                    error = %e,
+                    "transaction may have been sent, resyncing nonces"
+                );
+                self.nonces.mark_stale().await;
+                return Err(e);
+            },
+        };
+        info!(
+            tx_hash = %pending.hash,
+            from = %self.address,
+            nonce,
+            ?urgency,
+            gas_limit = pending.gas_limit,
+            max_fee_per_gas = pending.max_fee_per_gas,
+            max_priority_fee_per_gas = pending.max_priority_fee_per_gas,
+            "transaction submitted"
+        );
+        Ok(pending)
+    }
+
+    /// Signs and sends a prepared transaction, tracking it as in flight.
+    async fn send(
+        &self,
+        request: TransactionRequest,
+        nonce: u64,
+        replacements: u32,
+    ) -> Result<PendingTx, SendError> {
+        let (gas_limit, max_fee_per_gas, max_priority_fee_per_gas) = (
+            request.gas_limit().unwrap_or_default(),
+            request.max_fee_per_gas().unwrap_or_default(),
+            request.max_priority_fee_per_gas().unwrap_or_default(),
+        );
+        let envelope = self
+            .sign(request.clone())
+            .await
+            .map_err(SendError::NotSent)?;
+        let raw = Bytes::from(envelope.encoded_2718());
+        let (hash, private_until_block) = match &self.private {
+            Some(private) => {
+                let block_number = self
+                    .provider
+                    .get_block_number()
+                    .await
+                    .map_err(|e| SendError::NotSent(e.into()))?;
+                let until = block_number + private.fallback_after_blocks;
+                private
+                    .relay
+                    .send_private_transaction(&raw, until)
+                    .await
+                    .map_err(SendError::broadcast)?;
+                (*envelope.tx_hash(), Some(until))
+            },
+            None => {
+                let sent = self
+                    .provider
+                    .send_raw_transaction(&raw)
+                    .await
+                    .map_err(|e| SendError::broadcast(e.into()))?;
+                (*sent.tx_hash(), None)
+            },
+        };
+        let pending = PendingTx {
+            hash,
+            nonce,
+            gas_limit,
+            max_fee_per_gas,
+            max_priority_fee_per_gas,
+            raw,
+            private_until_block,
+        };
+        let in_flight = InFlightTx {
+            hash: pending.hash,
+            request,
+            max_fee_per_gas,
+            max_priority_fee_per_gas,
+            replacements,
+            private: private_until_block.is_some(),
+        };
+        self.nonces.track(nonce, in_flight).await;
+        Ok(pending)
+    }
+}
+
+fn is_nonce_too_low(error: &impl fmt::Display) -> bool {
+    error.to_string().to_lowercase().contains("nonce too low")
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::{
+        consensus::{transaction::SignerRecoverable, Transaction},
+        node_bindings::Anvil,
+        primitives::{keccak256, U256, U64},
+        providers::ProviderBuilder,
+        transports::mock::Asserter,
+    };
+    use serde_json::{json, Value};
+    use wiremock::{
+        matchers::{body_partial_json, method},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    use super::*;
+
+    /// The first well known Anvil development key.
+    const DEV_KEY: &str = "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+    const DEV_ADDRESS: &str = "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266";
+
+    fn submitter(url: &str, chain_id: u64) -> TxSubmitter<impl Provider> {
+        let provider = ProviderBuilder::new().connect_http(url.parse().unwrap());
+        let signer = signer_from_key(&Secret::new(DEV_KEY)).unwrap();
+        let fee_caps =
+            FeeCaps { max_fee_per_gas: 100_000_000_000, max_priority_fee_per_gas: 2_000_000_000 };
+        TxSubmitter::new(Arc::new(provider), signer, chain_id, FeeEstimator::new(fee_caps))
+    }
+
+    #[test]
+    fn test_invalid_key_is_not_echoed() {
+        let key = Secret::new("0xnot-a-private-key");
+        let error = signer_from_key(&key).unwrap_err();
+        assert_eq!(error.to_string(), "Configuration error: invalid signer private key");
+    }
+
+    #[test]
+    fn test_debug_does_not_show_key() {
+        let submitter = submitter("http://127.0.0.1:1", 8453);
+        let debug = format!("{:?}", submitter);
+        assert!(debug.contains(&format!("{:?}", submitter.address())));
+        assert!(!debug.contains(&DEV_KEY[2..]));
+    }
+
+    #[test]
+    fn test_fee_caps_bound_the_estimate() {
+        let caps = FeeCaps { max_fee_per_gas: 50, max_priority_fee_per_gas: 10 };
+        let capped =
+            caps.apply(Eip1559Estimation { max_fee_per_gas: 80, max_priority_fee_per_gas: 20 });
+        assert_eq!(capped, Eip1559Estimation { max_fee_per_gas: 50, max_priority_fee_per_gas: 10 });
+
+        let capped =
+            caps.apply(Eip1559Estimation { max_fee_per_gas: 5, max_priority_fee_per_gas: 8 });
+        assert_eq!(capped, Eip1559Estimation { max_fee_per_gas: 5, max_priority_fee_per_gas: 5 });
+    }
+
+    #[test]
+    fn test_failure_classification() {
+        let cases = [
+            (
+                "server returned an error response: execution reverted: V4TooLittleReceived",
+                TxFailureKind::Revert,
+            ),
+            ("transaction 0x01 reverted in block 42", TxFailureKind::Revert),
+            ("transaction 0x01 timed out, not mined within 30s", TxFailureKind::Timeout),
+            ("transaction 0x01 dropped, its nonce 7 was used", TxFailureKind::Dropped),
+            ("replacement transaction underpriced", TxFailureKind::FeeTooLow),
+            ("max fee per gas less than block base fee", TxFailureKind::FeeTooLow),
+            ("insufficient funds for gas * price + value", TxFailureKind::Other),
+        ];
+        for (message, kind) in cases {
+            assert_eq!(TxFailureKind::classify(&message), kind, "{}", message);
+        }
+    }
+
+    #[test]
+    fn test_replacement_fees_are_bumped_within_caps() {
+        let caps = FeeCaps { max_fee_per_gas: 100, max_priority_fee_per_gas: 20 };
+        let fees = Eip1559Estimation { max_fee_per_gas: 50, max_priority_fee_per_gas: 9 };
+
+        // Bumps below the 10% nodes require are raised to it, rounding up
+        let bumped = bump_fees(fees, 5, &caps).unwrap();
+        assert_eq!(bumped, Eip1559Estimation { max_fee_per_gas: 55, max_priority_fee_per_gas: 10 });
+
+        let bumped = bump_fees(fees, 100, &caps).unwrap();
+        assert_eq!(
+            bumped,
+            Eip1559Estimation { max_fee_per_gas: 100, max_priority_fee_per_gas: 18 }
+        );
+
+        let error = bump_fees(fees, 150, &caps).unwrap_err();
+        assert!(error.to_string().contains("exceeds the caps"));
+    }
+
+    #[tokio::test]
+    async fn test_prepared_transaction_is_signed_by_signer() {
+        let submitter = submitter("http://127.0.0.1:1", 8453);
+        let request = TransactionRequest::default()
+            .with_from(submitter.address())
+            .with_to(Address::ZERO)
+            .with_value(U256::from(1))
+            .with_chain_id(8453)
+            .with_nonce(7)
+            .with_gas_limit(21_000)
+            .with_max_fee_per_gas(2_000_000_000)
+            .with_max_priority_fee_per_gas(1_000_000);
+
+        let envelope = submitter.sign(request).await.unwrap();
+        assert!(envelope.is_eip1559());
+        assert_eq!(envelope.recover_signer().unwrap(), DEV_ADDRESS.parse::<Address>().unwrap());
+        assert_eq!(envelope.chain_id(), Some(8453));
+        assert_eq!(envelope.nonce(), 7);
+        assert_eq!(envelope.max_fee_per_gas(), 2_000_000_000);
+    }
+
+    #[tokio::test]
+    #[ignore = "requires anvil on PATH"]
+    async fn test_transactions_are_submitted_to_anvil() {
+        let anvil = Anvil::new().spawn();
+        let submitter = submitter(anvil.endpoint().as_str(), anvil.chain_id());
+        let provider = ProviderBuilder::new().connect_http(anvil.endpoint_url());
+
+        for expected_nonce in 0..2 {
+            let request = TransactionRequest::default()
+                .with_to(anvil.addresses()[1])
+                .with_value(U256::from(1));
+            let pending = submitter.submit(request, Urgency::Normal).await.unwrap();
+            assert_eq!(pending.nonce, expected_nonce);
+            assert!(pending.max_priority_fee_per_gas <= pending.max_fee_per_gas);
+            assert!(pending.max_fee_per_gas <= 100_000_000_000);
+
+            let tx = provider
+                .get_transaction_by_hash(pending.hash)
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(tx.inner.signer(), submitter.address());
+            assert_eq!(tx.inner.nonce(), expected_nonce);
+        }
+        assert_eq!(submitter.nonces().in_flight_nonces().await, vec![0, 1]);
+    }
+
+    fn mocked_submitter(asserter: &Asserter) -> TxSubmitter<impl Provider> {
+        let provider = ProviderBuilder::new()
+            .disable_recommended_fillers()
+            .connect_mocked_client(asserter.clone());
+        let fee_caps =
+            FeeCaps { max_fee_per_gas: 100_000_000_000, max_priority_fee_per_gas: 2_000_000_000 };
+        TxSubmitter::new(
+            Arc::new(provider),
+            signer_from_key(&Secret::new(DEV_KEY)).unwrap(),
+            8453,
+            FeeEstimator::new(fee_caps),
+        )
+    }
+
+    fn push_fee_history(asserter: &Asserter) {
+        asserter.push_success(&json!({
+            "oldestBlock": "0x1e8480",
+            "baseFeePerGas": ["0x3b9aca00", "0x3b9aca00"],
+            "gasUsedRatio": [0.5],
+            "reward": [["0x3b9aca00"]]
+        }));
+    }
+
+    fn transfer() -> TransactionRequest {
+        TransactionRequest::default()
+            .with_to(Address::ZERO)
+            .with_value(U256::from(1))
+            .with_gas_limit(21_000)
+    }
+
+    #[tokio::test]
+    async fn test_rejected_transaction_releases_its_nonce() {
+        let asserter = Asserter::new();
+        // Nonces, latest and pending
+        asserter.push_success(&U64::from(3));
+        asserter.push_success(&U64::from(3));
+        push_fee_history(&asserter);
+        asserter.push_failure_msg("insufficient funds for gas * price + value");
+        let submitter = mocked_submitter(&asserter);
+        let error = submitter
+            .submit(transfer(), Urgency::Normal)
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("insufficient funds"), "{}", error);
+
+        // The nonce is handed out again without reading the chain
+        push_fee_history(&asserter);
+        asserter.push_success(&TxHash::ZERO);
+        let pending = submitter.submit(transfer(), Urgency::Normal).await.unwrap();
+        assert_eq!(pending.nonce, 3);
+        assert!(asserter.read_q().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_unanswered_transaction_resyncs_its_nonce() {
+        let asserter = Asserter::new();
+        asserter.push_success(&U64::from(3));
+        asserter.push_success(&U64::from(3));
+        push_fee_history(&asserter);
+        // No response to the send, which the node may have accepted
+        let submitter = mocked_submitter(&asserter);
+        assert!(submitter.submit(transfer(), Urgency::Normal).await.is_err());
+
+        // The node knows it as pending, so its nonce is not reused
+        asserter.push_success(&U64::from(3));
+        asserter.push_success(&U64::from(4));
+        push_fee_history(&asserter);
+        asserter.push_success(&TxHash::ZERO);
+        let pending = submitter.submit(transfer(), Urgency::Normal).await.unwrap();
+        assert_eq!(pending.nonce, 4);
+        assert!(asserter.read_q().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_private_transaction_is_published_after_fallback() {
+        let relay = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(body_partial_json(json!({
+                "method": "eth_sendPrivateTransaction",
+                "params": [{ "maxBlockNumber": "0x1e8482" }]
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "result": "0x2c6c1d4b32d4a6f7f3e2c95ac46f8d2d4e0bd7b4b2b4c4f1a2a3b9c1d8e7f6a5"
+            })))
+            .expect(1)
+            .mount(&relay)
+            .await;
+
+        let asserter = Asserter::new();
+        // Nonces, latest and pending
+        asserter.push_success(&U64::from(3));
+        asserter.push_success(&U64::from(3));
+        asserter.push_success(&json!({
+            "oldestBlock": "0x1e8480",
+            "baseFeePerGas": ["0x3b9aca00", "0x3b9aca00"],
+            "gasUsedRatio": [0.5],
+            "reward": [["0x3b9aca00"]]
+        }));
+        // Block the relay is given two blocks from
+        asserter.push_success(&U64::from(2_000_000));
+        // Not included by the relay, published once past its last block
+        asserter.push_success(&Value::Null);
+        asserter.push_success(&U64::from(2_000_003));
+        asserter.push_success(&TxHash::ZERO);
+        asserter.push_success(&Value::Null);
+
+        let provider = ProviderBuilder::new()
+            .disable_recommended_fillers()
+            .connect_mocked_client(asserter.clone());
+        let fee_caps =
+            FeeCaps { max_fee_per_gas: 100_000_000_000, max_priority_fee_per_gas: 2_000_000_000 };
+        let submitter = TxSubmitter::new(
+            Arc::new(provider),
+            signer_from_key(&Secret::new(DEV_KEY)).unwrap(),
+            8453,
+            FeeEstimator::new(fee_caps),
+        )
+        .with_private_relay(PrivateRelay::new(&relay.uri()), 2);
+
+        let request = TransactionRequest::default()
+            .with_to(Address::ZERO)
+            .with_value(U256::from(1))
+            .with_gas_limit(21_000);
+        let pending = submitter.submit(request, Urgency::Normal).await.unwrap();
+        assert_eq!(pending.nonce, 3);
+        assert_eq!(pending.private_until_block, Some(2_000_002));
+        assert_eq!(pending.hash, keccak256(&pending.raw));
+        assert!(submitter.nonces().in_flight(3).await.unwrap().private);
+
+        let error = submitter
+            .confirm(&pending, Duration::from_millis(500))
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("timed out"), "{}", error);
+        assert!(asserter.read_q().is_empty());
+        // Its nonce is read from the node before the next submission
+        assert!(!submitter.nonces().in_flight(3).await.unwrap().private);
+    }
+}
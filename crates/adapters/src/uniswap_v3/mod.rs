@@ -0,0 +1,5 @@
+mod state;
+pub use state::{IUniswapV3Pool, PoolSlotDataStreamV3, UniswapV3StateViewManager};
+
+mod models;
+pub use models::PoolSlotDataV3;
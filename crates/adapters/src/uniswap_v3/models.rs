@@ -0,0 +1,117 @@
+//! Uniswap V3 Pool Data Models
+//!
+//! This module defines the state of a Uniswap V3 pool as read from its
+//! `slot0()`. The spot price is derived from `sqrtPriceX96` exactly as for
+//! Uniswap V4 pools, see [`SpotPrice`], whereas the fees are encoded
+//! differently: the LP fee is an immutable of the pool and the protocol fee is
+//! a fraction of it for each token.
+
+use alloy::primitives::{
+    aliases::{I24, U24},
+    U160,
+};
+
+use crate::uniswap_v4::SpotPrice;
+
+/// Represents the state of a Uniswap V3 pool at a specific point in time.
+#[derive(Debug, Clone)]
+pub struct PoolSlotDataV3 {
+    /// The square root of the price scaled by 2^96.
+    pub sqrt_price_x96: U160,
+
+    /// The current tick of the pool, `price = 1.0001^tick`.
+    pub tick: i32,
+
+    /// Index of the last written price observation
+    pub observation_index: u16,
+
+    /// Number of price observations stored by the pool
+    pub observation_cardinality: u16,
+
+    /// Protocol fee of both tokens, the denominator of the share of the LP
+    /// fee for token 0 in the lower and for token 1 in the upper 4 bits.
+    pub fee_protocol: u8,
+
+    /// Whether the pool is unlocked, i.e. not in the middle of a swap
+    pub unlocked: bool,
+
+    /// Fee charged on swaps in hundredths of a basis point, e.g. 500 for
+    /// 0.05%, fixed when the pool is created.
+    pub fee: U24,
+
+    /// The calculated spot price
+    pub spot_price: SpotPrice,
+
+    /// Time the state was fetched
+    pub timestamp: jiff::Timestamp,
+}
+
+impl PoolSlotDataV3 {
+    /// Creates a new [`PoolSlotDataV3`] instance with calculated spot price.
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        sqrt_price_x96: U160,
+        tick: I24,
+        observation_index: u16,
+        observation_cardinality: u16,
+        fee_protocol: u8,
+        unlocked: bool,
+        fee: U24,
+        token_0_decimals: u8,
+        token_1_decimals: u8,
+        invert: bool,
+    ) -> Self {
+        let spot_price = SpotPrice::new_from_sqrt_ratio_x96(
+            sqrt_price_x96,
+            token_0_decimals,
+            token_1_decimals,
+            invert,
+        );
+        Self {
+            sqrt_price_x96,
+            tick: tick.as_i32(),
+            observation_index,
+            observation_cardinality,
+            fee_protocol,
+            unlocked,
+            fee,
+            spot_price,
+            timestamp: jiff::Timestamp::now(),
+        }
+    }
+
+    /// Returns the denominator of the share of the LP fee taken by the
+    /// protocol on swaps of token 0, 0 if the protocol fee is off.
+    pub fn protocol_fee_0(&self) -> u8 { self.fee_protocol % 16 }
+
+    /// Returns the denominator of the share of the LP fee taken by the
+    /// protocol on swaps of token 1, 0 if the protocol fee is off.
+    pub fn protocol_fee_1(&self) -> u8 { self.fee_protocol >> 4 }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spot_price_matches_v4() {
+        let data = PoolSlotDataV3::new(
+            U160::from(3961408125713216879677197u128),
+            I24::try_from(-198080).unwrap(),
+            12,
+            100,
+            0x64,
+            true,
+            U24::from(500),
+            18,
+            6,
+            true,
+        );
+        assert_eq!(data.tick, -198080);
+        assert_eq!(data.fee, U24::from(500));
+        assert_eq!(data.spot_price.to_fixed(2, None), "2500.00");
+        // 1/4 of the fee on token 0 and 1/6 on token 1
+        assert_eq!(data.protocol_fee_0(), 4);
+        assert_eq!(data.protocol_fee_1(), 6);
+    }
+}
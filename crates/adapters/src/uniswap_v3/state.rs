@@ -0,0 +1,334 @@
+//! Uniswap V3 State Management
+//!
+//! This module provides functionality to watch Uniswap V3 pool state by
+//! polling, like [`crate::UniswapV4StateViewManager`] does for Uniswap V4.
+//! Uniswap V3 pools are contracts of their own, so pools are addressed by their
+//! contract address rather than by their id.
+
+use std::{
+    future::IntoFuture,
+    pin::Pin,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use alloy::{
+    primitives::{aliases::U24, Address},
+    sol,
+};
+use futures::{stream, Stream};
+use sikkara_core::{metrics::RpcObserver, AppResult, BackoffStrategy, ExponentialBackoff};
+use tokio::time::{interval, sleep};
+use tracing::{error, warn};
+
+use crate::uniswap_v3::models::PoolSlotDataV3;
+
+sol! {
+    #[derive(Debug)]
+    #[sol(rpc)]
+    interface IUniswapV3Pool {
+        function slot0() external view returns (
+            uint160 sqrtPriceX96,
+            int24 tick,
+            uint16 observationIndex,
+            uint16 observationCardinality,
+            uint16 observationCardinalityNext,
+            uint8 feeProtocol,
+            bool unlocked
+        );
+        function fee() external view returns (uint24);
+    }
+}
+
+/// A stream that emits [`PoolSlotDataV3`] whenever pool state is fetched, or
+/// the error ending it.
+pub type PoolSlotDataStreamV3 = Pin<Box<dyn Stream<Item = AppResult<PoolSlotDataV3>> + Send>>;
+
+/// Manager for watching Uniswap V3 pool state changes.
+///
+/// # Type Parameters
+///
+/// * `P` - The RPC provider type that implements [`alloy::providers::Provider`]
+pub struct UniswapV3StateViewManager<P>
+where
+    P: alloy::providers::Provider + Send + Sync,
+{
+    /// The RPC provider for blockchain interactions
+    provider: Arc<P>,
+    /// Observer of the RPC calls, if instrumented
+    observer: Option<Arc<dyn RpcObserver>>,
+    /// Delays between the retries of a failed poll of a watched pool
+    retry_backoff: ExponentialBackoff,
+}
+
+impl<P> Clone for UniswapV3StateViewManager<P>
+where
+    P: alloy::providers::Provider + Send + Sync,
+{
+    fn clone(&self) -> Self {
+        Self {
+            provider: self.provider.clone(),
+            observer: self.observer.clone(),
+            retry_backoff: self.retry_backoff.clone(),
+        }
+    }
+}
+
+impl<P> UniswapV3StateViewManager<P>
+where
+    P: alloy::providers::Provider + Send + Sync + 'static,
+{
+    /// Creates a new state view manager reading pools through `provider`.
+    pub fn new(provider: Arc<P>) -> Self {
+        Self {
+            provider,
+            observer: None,
+            retry_backoff: ExponentialBackoff::from_durations(
+                Some(5),
+                Duration::from_millis(500),
+                Duration::from_secs(8),
+                2,
+            ),
+        }
+    }
+
+    /// Reports the duration and outcome of every RPC call to the given
+    /// observer.
+    pub fn with_observer(mut self, observer: Arc<dyn RpcObserver>) -> Self {
+        self.observer = Some(observer);
+        self
+    }
+
+    /// Retries a failed poll of [`Self::watch_pool`] after the delays of
+    /// `backoff`, ending the stream once it runs out of retries. By default a
+    /// poll is retried 5 times, from 500ms up to 8s apart.
+    pub fn with_retry_backoff(mut self, backoff: ExponentialBackoff) -> Self {
+        self.retry_backoff = backoff;
+        self
+    }
+
+    /// Fetches the fee of the pool at `pool`, which is fixed when the pool is
+    /// created.
+    pub async fn fetch_fee(&self, pool: Address) -> AppResult<U24> {
+        let contract = IUniswapV3Pool::new(pool, &self.provider);
+        Ok(self.observe("fee", contract.fee().call()).await?)
+    }
+
+    /// Fetches the current `slot0` state of the pool at `pool`, whose fee is
+    /// `fee`, see [`Self::fetch_fee`].
+    pub async fn fetch_slot0(
+        &self,
+        pool: Address,
+        fee: U24,
+        token_0_decimals: u8,
+        token_1_decimals: u8,
+        invert: bool,
+    ) -> AppResult<PoolSlotDataV3> {
+        let contract = IUniswapV3Pool::new(pool, &self.provider);
+        let slot = self.observe("slot0", contract.slot0().call()).await?;
+        Ok(PoolSlotDataV3::new(
+            slot.sqrtPriceX96,
+            slot.tick,
+            slot.observationIndex,
+            slot.observationCardinality,
+            slot.feeProtocol,
+            slot.unlocked,
+            fee,
+            token_0_decimals,
+            token_1_decimals,
+            invert,
+        ))
+    }
+
+    /// Fetches the state of a pool for a watch stream, fetching its fee on the
+    /// first successful poll only.
+    ///
+    /// A failed fetch is retried after the next delay of `backoff`, which
+    /// starts over after a success, and its error returned once the backoff
+    /// runs out of retries.
+    async fn poll_slot0(
+        &self,
+        pool: Address,
+        fee: &mut Option<U24>,
+        token_0_decimals: u8,
+        token_1_decimals: u8,
+        invert: bool,
+        backoff: &mut ExponentialBackoff,
+    ) -> AppResult<PoolSlotDataV3> {
+        loop {
+            let data = async {
+                let fee = match *fee {
+                    Some(fee) => fee,
+                    None => *fee.insert(self.fetch_fee(pool).await?),
+                };
+                self.fetch_slot0(pool, fee, token_0_decimals, token_1_decimals, invert)
+                    .await
+            }
+            .await;
+            match data {
+                Ok(data) => {
+                    backoff.reset();
+                    return Ok(data);
+                },
+                Err(e) => match backoff.next_delay() {
+                    Some(delay) => {
+                        warn!(
+                            pool = %pool,
+                            error = %e,
+                            attempt = backoff.get_iteration_count(),
+                            "Failed to fetch pool state from contract, retrying in {:?}",
+                            delay
+                        );
+                        sleep(delay).await;
+                    },
+                    None => {
+                        error!(
+                            pool = %pool,
+                            error = %e,
+                            "Failed to fetch pool state from contract, giving up"
+                        );
+                        return Err(e);
+                    },
+                },
+            }
+        }
+    }
+
+    /// Awaits an RPC call, reporting its duration and outcome to the observer.
+    async fn observe<T, E>(
+        &self,
+        method: &'static str,
+        call: impl IntoFuture<Output = Result<T, E>>,
+    ) -> Result<T, E> {
+        let started_at = Instant::now();
+        let result = call.await;
+        if let Some(observer) = &self.observer {
+            observer.observe(method, started_at.elapsed(), result.is_ok());
+        }
+        result
+    }
+
+    /// Creates a stream polling the state of the pool at `pool` every
+    /// `poll_interval`. A failed poll is retried according to the
+    /// [retry backoff](Self::with_retry_backoff), and once it runs out of
+    /// retries the stream emits the error and ends.
+    pub fn watch_pool(
+        &self,
+        pool: Address,
+        poll_interval: Duration,
+        token_0_decimals: u8,
+        token_1_decimals: u8,
+        invert: bool,
+    ) -> PoolSlotDataStreamV3 {
+        let state = (self.clone(), interval(poll_interval), None, self.retry_backoff.clone());
+
+        let stream = stream::unfold(Some(state), move |state| async move {
+            let (manager, mut timer, mut fee, mut backoff) = state?;
+
+            timer.tick().await;
+
+            // End the stream with the error once the retries are exhausted
+            match manager
+                .poll_slot0(
+                    pool,
+                    &mut fee,
+                    token_0_decimals,
+                    token_1_decimals,
+                    invert,
+                    &mut backoff,
+                )
+                .await
+            {
+                Ok(data) => Some((Ok(data), Some((manager, timer, fee, backoff)))),
+                Err(e) => Some((Err(e), None)),
+            }
+        });
+
+        Box::pin(stream)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::{providers::ProviderBuilder, transports::mock::Asserter};
+    use futures::StreamExt;
+
+    use super::*;
+
+    /// Backoff retrying a failed poll `retries` times right away.
+    fn immediate_retries(retries: u32) -> ExponentialBackoff {
+        ExponentialBackoff::from_durations(Some(retries), Duration::ZERO, Duration::ZERO, 1)
+    }
+
+    /// `slot0` result at a sqrtPriceX96 of 2^96 and tick 0, ABI encoded.
+    const SLOT0_RESULT: &str = "0x\
+        0000000000000000000000000000000000000001000000000000000000000000\
+        0000000000000000000000000000000000000000000000000000000000000000\
+        0000000000000000000000000000000000000000000000000000000000000007\
+        0000000000000000000000000000000000000000000000000000000000000064\
+        0000000000000000000000000000000000000000000000000000000000000064\
+        0000000000000000000000000000000000000000000000000000000000000044\
+        0000000000000000000000000000000000000000000000000000000000000001";
+
+    #[tokio::test]
+    async fn test_watch_pool_decodes_slot0_and_fee() {
+        let asserter = Asserter::new();
+        // The fee is only read on the first poll
+        asserter.push_success(&format!("0x{:064x}", 3000));
+        asserter.push_success(&SLOT0_RESULT);
+        asserter.push_success(&SLOT0_RESULT);
+        let provider = ProviderBuilder::new()
+            .disable_recommended_fillers()
+            .connect_mocked_client(asserter);
+        let manager = UniswapV3StateViewManager::new(Arc::new(provider))
+            .with_retry_backoff(immediate_retries(0));
+
+        let mut stream = manager.watch_pool(Address::ZERO, Duration::from_millis(1), 6, 6, false);
+        let data = stream.next().await.unwrap().unwrap();
+        assert_eq!(data.tick, 0);
+        assert_eq!(data.observation_index, 7);
+        assert_eq!(data.observation_cardinality, 100);
+        assert_eq!(data.protocol_fee_0(), 4);
+        assert_eq!(data.protocol_fee_1(), 4);
+        assert!(data.unlocked);
+        assert_eq!(data.fee.to::<u32>(), 3000);
+        assert_eq!(data.spot_price.to_fixed(2, None), "1.00");
+
+        let data = stream.next().await.unwrap().unwrap();
+        assert_eq!(data.fee.to::<u32>(), 3000);
+
+        // The stream ends with the error once the pool can no longer be read
+        assert!(stream.next().await.unwrap().is_err());
+        assert!(stream.next().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_watch_pool_retries_failed_polls() {
+        let asserter = Asserter::new();
+        // The first two polls fail and the third succeeds
+        asserter.push_failure_msg("header not found");
+        asserter.push_failure_msg("header not found");
+        asserter.push_success(&format!("0x{:064x}", 500));
+        asserter.push_success(&SLOT0_RESULT);
+        let provider = ProviderBuilder::new()
+            .disable_recommended_fillers()
+            .connect_mocked_client(asserter.clone());
+        let manager = UniswapV3StateViewManager::new(Arc::new(provider))
+            .with_retry_backoff(immediate_retries(2));
+        let mut stream = manager.watch_pool(Address::ZERO, Duration::from_millis(1), 6, 6, false);
+
+        // Two failures in a row are within the retries
+        let data = stream.next().await.unwrap().unwrap();
+        assert_eq!(data.fee.to::<u32>(), 500);
+        assert!(asserter.read_q().is_empty());
+
+        // but a third ends the stream with its error, the retries starting over
+        // after the success
+        for _ in 0..3 {
+            asserter.push_failure_msg("header not found");
+        }
+        assert!(stream.next().await.unwrap().is_err());
+        assert!(stream.next().await.is_none());
+        assert!(asserter.read_q().is_empty());
+    }
+}
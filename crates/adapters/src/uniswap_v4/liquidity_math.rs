@@ -0,0 +1,198 @@
+//! Liquidity math of concentrated liquidity positions
+//!
+//! Port of the `LiquidityAmounts` library of the Uniswap periphery contracts:
+//! the liquidity that amounts of the currencies provide to a price range, and
+//! the amounts of the currencies a liquidity is worth, at the current price of
+//! the pool. Prices are square root prices in Q64.96 as in the pool state, and
+//! amounts are in the smallest unit of their currency. Intermediate products
+//! are computed over 512 bits, and results round down as on chain.
+//!
+//! Below the range a position holds currency 0 only, above it currency 1
+//! only, and both in between.
+
+use alloy::primitives::{U160, U256, U512};
+use sikkara_core::{AppError, AppResult};
+
+/// Returns the bounds of a range sorted, failing if the range is empty.
+fn sorted_range(sqrt_price_a_x96: U160, sqrt_price_b_x96: U160) -> AppResult<(U512, U512)> {
+    let (lower, upper) = match sqrt_price_a_x96 < sqrt_price_b_x96 {
+        true => (sqrt_price_a_x96, sqrt_price_b_x96),
+        false => (sqrt_price_b_x96, sqrt_price_a_x96),
+    };
+    if lower.is_zero() || lower == upper {
+        return Err(AppError::IntegrityError(format!(
+            "no liquidity can be provided between {} and {}",
+            lower, upper
+        ))
+        .into());
+    }
+    Ok((U512::from(lower), U512::from(upper)))
+}
+
+fn to_liquidity(liquidity: U512) -> AppResult<u128> {
+    u128::try_from(liquidity).map_err(|_| {
+        AppError::IntegrityError(format!("liquidity {} overflows 128 bits", liquidity)).into()
+    })
+}
+
+/// Returns the liquidity `amount_0` of currency 0 provides between the two
+/// square root prices: `Δx · √Pa · √Pb / (√Pb - √Pa)`.
+pub fn liquidity_for_amount_0(
+    sqrt_price_a_x96: U160,
+    sqrt_price_b_x96: U160,
+    amount_0: U256,
+) -> AppResult<u128> {
+    let (lower, upper) = sorted_range(sqrt_price_a_x96, sqrt_price_b_x96)?;
+    let intermediate = (lower * upper) >> 96;
+    to_liquidity(U512::from(amount_0) * intermediate / (upper - lower))
+}
+
+/// Returns the liquidity `amount_1` of currency 1 provides between the two
+/// square root prices: `Δy / (√Pb - √Pa)`.
+pub fn liquidity_for_amount_1(
+    sqrt_price_a_x96: U160,
+    sqrt_price_b_x96: U160,
+    amount_1: U256,
+) -> AppResult<u128> {
+    let (lower, upper) = sorted_range(sqrt_price_a_x96, sqrt_price_b_x96)?;
+    to_liquidity((U512::from(amount_1) << 96) / (upper - lower))
+}
+
+/// Returns the most liquidity `amount_0` of currency 0 and `amount_1` of
+/// currency 1 provide between the two square root prices, at the current
+/// square root price of the pool.
+pub fn liquidity_for_amounts(
+    sqrt_price_x96: U160,
+    sqrt_price_a_x96: U160,
+    sqrt_price_b_x96: U160,
+    amount_0: U256,
+    amount_1: U256,
+) -> AppResult<u128> {
+    let (lower, upper) = match sqrt_price_a_x96 < sqrt_price_b_x96 {
+        true => (sqrt_price_a_x96, sqrt_price_b_x96),
+        false => (sqrt_price_b_x96, sqrt_price_a_x96),
+    };
+    if sqrt_price_x96 <= lower {
+        liquidity_for_amount_0(lower, upper, amount_0)
+    } else if sqrt_price_x96 < upper {
+        let liquidity_0 = liquidity_for_amount_0(sqrt_price_x96, upper, amount_0)?;
+        let liquidity_1 = liquidity_for_amount_1(lower, sqrt_price_x96, amount_1)?;
+        Ok(liquidity_0.min(liquidity_1))
+    } else {
+        liquidity_for_amount_1(lower, upper, amount_1)
+    }
+}
+
+/// Returns the amount of currency 0 `liquidity` is worth between the two
+/// square root prices: `L · (√Pb - √Pa) / (√Pa · √Pb)`.
+pub fn amount_0_for_liquidity(
+    sqrt_price_a_x96: U160,
+    sqrt_price_b_x96: U160,
+    liquidity: u128,
+) -> AppResult<U256> {
+    let (lower, upper) = sorted_range(sqrt_price_a_x96, sqrt_price_b_x96)?;
+    let amount = (U512::from(liquidity) << 96) * (upper - lower) / upper / lower;
+    Ok(amount.saturating_to())
+}
+
+/// Returns the amount of currency 1 `liquidity` is worth between the two
+/// square root prices: `L · (√Pb - √Pa)`.
+pub fn amount_1_for_liquidity(
+    sqrt_price_a_x96: U160,
+    sqrt_price_b_x96: U160,
+    liquidity: u128,
+) -> AppResult<U256> {
+    let (lower, upper) = sorted_range(sqrt_price_a_x96, sqrt_price_b_x96)?;
+    let amount = (U512::from(liquidity) * (upper - lower)) >> 96;
+    Ok(amount.saturating_to())
+}
+
+/// Returns the amounts of currency 0 and currency 1 `liquidity` is worth
+/// between the two square root prices, at the current square root price of
+/// the pool.
+pub fn amounts_for_liquidity(
+    sqrt_price_x96: U160,
+    sqrt_price_a_x96: U160,
+    sqrt_price_b_x96: U160,
+    liquidity: u128,
+) -> AppResult<(U256, U256)> {
+    let (lower, upper) = match sqrt_price_a_x96 < sqrt_price_b_x96 {
+        true => (sqrt_price_a_x96, sqrt_price_b_x96),
+        false => (sqrt_price_b_x96, sqrt_price_a_x96),
+    };
+    if sqrt_price_x96 <= lower {
+        Ok((amount_0_for_liquidity(lower, upper, liquidity)?, U256::ZERO))
+    } else if sqrt_price_x96 < upper {
+        Ok((
+            amount_0_for_liquidity(sqrt_price_x96, upper, liquidity)?,
+            amount_1_for_liquidity(lower, sqrt_price_x96, liquidity)?,
+        ))
+    } else {
+        Ok((U256::ZERO, amount_1_for_liquidity(lower, upper, liquidity)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Square root prices of the reference tests of the Uniswap periphery,
+    // encodePriceSqrt(reserve1, reserve0) of 1:1, with a range from 100:110 to
+    // 110:100 and prices of 99:110 below and 111:100 above it
+    const SQRT_PRICE_1_1: U160 = U160::from_limbs([0, 1 << 32, 0]);
+
+    fn sqrt_price(value: &str) -> U160 { value.parse().unwrap() }
+
+    fn range() -> (U160, U160) {
+        (sqrt_price("75541088972021052632782079082"), sqrt_price("83095197869223157896060286990"))
+    }
+
+    #[test]
+    fn test_liquidity_for_amounts_matches_the_reference() {
+        let (lower, upper) = range();
+        let liquidity = |price| {
+            liquidity_for_amounts(price, lower, upper, U256::from(100), U256::from(200)).unwrap()
+        };
+        // Inside the range, the scarcer currency bounds the liquidity
+        assert_eq!(liquidity(SQRT_PRICE_1_1), 2148);
+        // Below the range currency 0 only is deposited, above it currency 1 only
+        assert_eq!(liquidity(sqrt_price("75162434512514379355924140470")), 1048);
+        assert_eq!(liquidity(sqrt_price("83472048772503575395058907992")), 2097);
+        // down to the bounds
+        assert_eq!(liquidity(lower), 1048);
+        assert_eq!(liquidity(upper), 2097);
+        // whatever the order of the bounds
+        assert_eq!(
+            liquidity_for_amounts(SQRT_PRICE_1_1, upper, lower, U256::from(100), U256::from(200))
+                .unwrap(),
+            2148
+        );
+    }
+
+    #[test]
+    fn test_amounts_for_liquidity_matches_the_reference() {
+        let (lower, upper) = range();
+        let amounts = |price, liquidity| amounts_for_liquidity(price, lower, upper, liquidity);
+        assert_eq!(amounts(SQRT_PRICE_1_1, 2148).unwrap(), (U256::from(99), U256::from(99)));
+        assert_eq!(
+            amounts(sqrt_price("75162434512514379355924140470"), 1048).unwrap(),
+            (U256::from(99), U256::ZERO)
+        );
+        assert_eq!(
+            amounts(sqrt_price("83472048772503575395058907992"), 2097).unwrap(),
+            (U256::ZERO, U256::from(199))
+        );
+        assert_eq!(amounts(lower, 1048).unwrap(), (U256::from(99), U256::ZERO));
+        assert_eq!(amounts(upper, 2097).unwrap(), (U256::ZERO, U256::from(199)));
+    }
+
+    #[test]
+    fn test_empty_ranges_provide_no_liquidity() {
+        let (lower, _) = range();
+        assert!(liquidity_for_amount_0(lower, lower, U256::from(100)).is_err());
+        assert!(liquidity_for_amount_1(U160::ZERO, lower, U256::from(100)).is_err());
+        assert!(amounts_for_liquidity(SQRT_PRICE_1_1, lower, lower, 1).is_err());
+        // Liquidity beyond 128 bits cannot be held by a position
+        assert!(liquidity_for_amount_1(lower, lower + U160::from(1), U256::MAX).is_err());
+    }
+}
@@ -1,4 +1,27 @@
 mod state;
-pub use state::UniswapV4StateViewManager;
+pub use state::{PoolSlotDataStream, PoolSlotSink, UniswapV4StateViewManager, MULTICALL3_ADDRESS};
 
 mod models;
+pub use models::{
+    estimate_max_trade_within_slippage, price_impact_bps, price_to_sqrt_price_x96, price_to_tick,
+    sqrt_price_x96_at_tick, tick_to_price, PoolSlotData, PoolSlotRecord, SpotPrice, MAX_TICK,
+    MIN_TICK,
+};
+
+mod liquidity_math;
+pub use liquidity_math::{
+    amount_0_for_liquidity, amount_1_for_liquidity, amounts_for_liquidity, liquidity_for_amount_0,
+    liquidity_for_amount_1, liquidity_for_amounts,
+};
+
+mod watcher;
+pub use watcher::{UniswapV4MultiPoolWatcher, WatchedPool};
+
+mod quoter;
+pub use quoter::{QuotedPool, UniswapV4Quoter};
+
+mod router;
+pub use router::{
+    min_amount_out, ExactInSingleSwap, ExactInputSingleParams, IUniversalRouter, PoolKey,
+    UniversalRouter,
+};
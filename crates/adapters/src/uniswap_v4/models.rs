@@ -3,7 +3,9 @@
 //! This module defines the core data structures for representing Uniswap V4
 //! pool state. It provides types for pool slot data including price
 //! calculations and conversions between different price representations (tick,
-//! sqrtPriceX96, decimal price).
+//! sqrtPriceX96, decimal price). Pool slot data serializes to a compact
+//! [`PoolSlotRecord`], from which it can be reconstructed including its spot
+//! price.
 
 use core::num;
 
@@ -19,10 +21,20 @@ use fastnum::{
     decimal::{Context, RoundingMode},
     i512, D512, I512, U512,
 };
-use rust_decimal::Decimal;
+use rust_decimal::{
+    prelude::{FromPrimitive, ToPrimitive},
+    Decimal,
+};
+use serde::{Deserialize, Serialize};
+use sikkara_core::{AppError, AppResult};
 
 pub const Q192: I512 = I512::from_bits(U512::from_digits([0, 0, 0, 1, 0, 0, 0, 0]));
 
+/// Lowest tick of a Uniswap pool, at a price of about 2^-128
+pub const MIN_TICK: i32 = -887272;
+/// Highest tick of a Uniswap pool, at a price of about 2^128
+pub const MAX_TICK: i32 = 887272;
+
 /// Represents the complete state data for a Uniswap V4 pool at a specific point
 /// in time.
 ///
@@ -37,7 +49,8 @@ pub const Q192: I512 = I512::from_bits(U512::from_digits([0, 0, 0, 1, 0, 0, 0, 0
 /// - `sqrt_price_x96`: The raw square root price scaled by 2^96 (from contract)
 /// - `tick`: The current tick (logarithmic price representation)
 /// - `spot_price`: Human-readable decimal price (calculated)
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(into = "PoolSlotRecord", try_from = "PoolSlotRecord")]
 pub struct PoolSlotData {
     /// The square root of the price scaled by 2^96.
     pub sqrt_price_x96: U160,
@@ -64,6 +77,24 @@ pub struct PoolSlotData {
 
     /// The calculated spot price
     pub spot_price: SpotPrice,
+
+    /// In range liquidity of the pool, if fetched
+    pub liquidity: Option<u128>,
+
+    /// Block the state was read at, if pinned to a block
+    pub block_number: Option<u64>,
+
+    /// Time the state was fetched
+    pub timestamp: jiff::Timestamp,
+
+    /// Decimals of token 0, used to derive the spot price
+    pub token_0_decimals: u8,
+
+    /// Decimals of token 1, used to derive the spot price
+    pub token_1_decimals: u8,
+
+    /// Whether the spot price is inverted
+    pub invert: bool,
 }
 
 impl PoolSlotData {
@@ -87,7 +118,318 @@ impl PoolSlotData {
             token_1_decimals,
             invert,
         );
-        Self { sqrt_price_x96, tick: tick.as_i32(), protocol_fee, lp_fee, spot_price }
+        Self {
+            sqrt_price_x96,
+            tick: tick.as_i32(),
+            protocol_fee,
+            lp_fee,
+            spot_price,
+            liquidity: None,
+            block_number: None,
+            timestamp: jiff::Timestamp::now(),
+            token_0_decimals,
+            token_1_decimals,
+            invert,
+        }
+    }
+
+    /// Sets the block the state was read at and the liquidity of the pool at
+    /// that block.
+    pub fn at_block(mut self, block_number: u64, liquidity: u128) -> Self {
+        self.block_number = Some(block_number);
+        self.liquidity = Some(liquidity);
+        self
+    }
+
+    /// Sets the in range liquidity of the pool.
+    pub fn with_liquidity(mut self, liquidity: u128) -> Self {
+        self.liquidity = Some(liquidity);
+        self
+    }
+
+    /// Returns the price impact in basis points of selling `amount_token0`
+    /// whole units of token 0 into the pool, see [`price_impact_bps`]. None if
+    /// the liquidity was not fetched.
+    pub fn price_impact_for_amount(&self, amount_token0: Decimal) -> Option<u32> {
+        let scale = Decimal::from_i128_with_scale(10i128.pow(self.token_0_decimals as u32), 0);
+        let amount = amount_token0.checked_mul(scale)?;
+        Some(price_impact_bps(amount, self.liquidity?, self.sqrt_price_x96))
+    }
+
+    /// Returns the most whole units of token 0 that can be sold into the pool
+    /// without moving its price by more than `max_slippage_bps`, see
+    /// [`estimate_max_trade_within_slippage`]. None if the liquidity was not
+    /// fetched.
+    pub fn max_trade_within_slippage(&self, max_slippage_bps: u32) -> Option<Decimal> {
+        let amount = estimate_max_trade_within_slippage(
+            self.liquidity?,
+            self.sqrt_price_x96,
+            max_slippage_bps,
+        );
+        let scale = Decimal::from_i128_with_scale(10i128.pow(self.token_0_decimals as u32), 0);
+        amount.checked_div(scale)
+    }
+
+    /// Estimates swapping `amount_in` whole units of token 0 for token 1 if
+    /// `zero_for_one`, or of token 1 for token 0 otherwise, returning the
+    /// amount received in whole units and the spot price after the swap, in
+    /// the orientation of [`Self::spot_price`].
+    ///
+    /// The swap moves the price along the constant product curve of the in
+    /// range liquidity, after the LP fee, and stops at `sqrt_price_limit` if
+    /// given. Crossing into the next tick range is ignored, so the output of
+    /// swaps large enough to cross one is overestimated.
+    pub fn estimate_swap_output(
+        &self,
+        amount_in: Decimal,
+        zero_for_one: bool,
+        sqrt_price_limit: Option<U160>,
+    ) -> AppResult<(Decimal, Decimal)> {
+        let liquidity = match self.liquidity {
+            Some(liquidity) if liquidity > 0 => liquidity as f64,
+            Some(_) => return Err(AppError::IntegrityError("pool has no liquidity".into()).into()),
+            None => return Err(AppError::IntegrityError("pool liquidity is unknown".into()).into()),
+        };
+        if amount_in.is_sign_negative() {
+            return Err(
+                AppError::IntegrityError(format!("negative amount in {}", amount_in)).into()
+            );
+        }
+        let (decimals_in, decimals_out) = match zero_for_one {
+            true => (self.token_0_decimals, self.token_1_decimals),
+            false => (self.token_1_decimals, self.token_0_decimals),
+        };
+        let fee_rate = self.lp_fee.to::<u32>() as f64 / 1_000_000.0;
+        let amount_in = amount_in.to_f64().unwrap_or_default()
+            * 10f64.powi(decimals_in as i32)
+            * (1.0 - fee_rate);
+
+        // Token 0 in lowers √P to L·√P / (L + Δx·√P), token 1 in raises it by Δy / L
+        let q96 = 2f64.powi(96);
+        let sqrt_price = f64::from(self.sqrt_price_x96) / q96;
+        let mut new_sqrt_price = match zero_for_one {
+            true => liquidity * sqrt_price / (liquidity + amount_in * sqrt_price),
+            false => sqrt_price + amount_in / liquidity,
+        };
+        if let Some(limit) = sqrt_price_limit {
+            let limit = f64::from(limit) / q96;
+            if (zero_for_one && limit >= sqrt_price) || (!zero_for_one && limit <= sqrt_price) {
+                return Err(AppError::IntegrityError(format!(
+                    "sqrt price limit {} is on the wrong side of the price",
+                    limit
+                ))
+                .into());
+            }
+            new_sqrt_price = match zero_for_one {
+                true => new_sqrt_price.max(limit),
+                false => new_sqrt_price.min(limit),
+            };
+        }
+        let amount_out = match zero_for_one {
+            true => liquidity * (sqrt_price - new_sqrt_price),
+            false => liquidity * (1.0 / sqrt_price - 1.0 / new_sqrt_price),
+        } / 10f64.powi(decimals_out as i32);
+
+        // The spot price follows the raw price if inverted, its inverse otherwise
+        let spot_price = self
+            .spot_price
+            .to_fixed(18, None)
+            .parse::<f64>()
+            .unwrap_or_default();
+        let ratio = (new_sqrt_price / sqrt_price).powi(2);
+        let new_price = if self.invert { spot_price * ratio } else { spot_price / ratio };
+
+        let to_decimal = |value: f64| {
+            Decimal::from_f64(value).ok_or_else(|| {
+                AppError::IntegrityError(format!("{} is out of the decimal range", value))
+            })
+        };
+        Ok((to_decimal(amount_out)?.round_dp(decimals_out as u32), to_decimal(new_price)?))
+    }
+}
+
+/// Returns the price impact in basis points of selling `trade_amount_token0`
+/// of token 0, in its smallest unit, into a pool of in range `liquidity` at
+/// `sqrt_price_x96`.
+///
+/// Within a tick range, selling `Δx` of token 0 moves the square root price
+/// from `√P` to `L·√P / (L + Δx·√P)`, so the price drops by
+/// `1 - (L / (L + Δx·√P))²`. Crossing into the next tick range is ignored, so
+/// the impact of trades large enough to cross one is underestimated.
+pub fn price_impact_bps(
+    trade_amount_token0: Decimal,
+    liquidity: u128,
+    sqrt_price_x96: U160,
+) -> u32 {
+    let amount = trade_amount_token0.to_f64().unwrap_or_default();
+    if amount <= 0.0 {
+        return 0;
+    }
+    if liquidity == 0 {
+        return 10_000;
+    }
+    let liquidity = liquidity as f64;
+    let sqrt_price = f64::from(sqrt_price_x96) / 2f64.powi(96);
+    let ratio = liquidity / (liquidity + amount * sqrt_price);
+    ((1.0 - ratio * ratio) * 10_000.0).round() as u32
+}
+
+/// Returns the most token 0, in its smallest unit, that can be sold into a
+/// pool of in range `liquidity` at `sqrt_price_x96` without moving its price
+/// by more than `max_slippage_bps`, the inverse of [`price_impact_bps`].
+///
+/// Solving `1 - (L / (L + Δx·√P))² = s` gives `Δx = L·(1 / √(1 - s) - 1) / √P`.
+/// Crossing into the next tick range is ignored, so the amount is
+/// overestimated for pools whose liquidity thins out next to the price.
+pub fn estimate_max_trade_within_slippage(
+    liquidity: u128,
+    sqrt_price_x96: U160,
+    max_slippage_bps: u32,
+) -> Decimal {
+    if max_slippage_bps >= 10_000 {
+        return Decimal::MAX;
+    }
+    let sqrt_price = f64::from(sqrt_price_x96) / 2f64.powi(96);
+    if liquidity == 0 || sqrt_price <= 0.0 {
+        return Decimal::ZERO;
+    }
+    let ratio = (1.0 - max_slippage_bps as f64 / 10_000.0).sqrt();
+    let amount = liquidity as f64 * (1.0 / ratio - 1.0) / sqrt_price;
+    Decimal::from_f64(amount.floor()).unwrap_or(Decimal::MAX)
+}
+
+/// Returns the highest tick at or below `price`, the whole units of token 1
+/// per whole unit of token 0, clamped to [`MIN_TICK`] and [`MAX_TICK`].
+///
+/// A tick is worth `1.0001^tick` of the smallest unit of token 1 per smallest
+/// unit of token 0, so the tick of a price is `log(price · 10^(d1 - d0)) /
+/// log(1.0001)`, rounded down.
+pub fn price_to_tick(price: Decimal, token_0_decimals: u8, token_1_decimals: u8) -> AppResult<i32> {
+    let price = price
+        .to_f64()
+        .filter(|price| *price > 0.0)
+        .ok_or_else(|| AppError::IntegrityError(format!("no tick has a price of {}", price)))?;
+    let raw_price = price * 10f64.powi(token_1_decimals as i32 - token_0_decimals as i32);
+    let tick = raw_price.ln() / 1.0001f64.ln();
+    // Prices of a tick converted back and forth land a hair off the tick
+    let tick = match (tick - tick.round()).abs() < 1e-6 {
+        true => tick.round(),
+        false => tick.floor(),
+    };
+    Ok(tick.clamp(MIN_TICK as f64, MAX_TICK as f64) as i32)
+}
+
+/// Returns the price at `tick` in whole units of token 1 per whole unit of
+/// token 0, the inverse of [`price_to_tick`].
+pub fn tick_to_price(tick: i32, token_0_decimals: u8, token_1_decimals: u8) -> AppResult<Decimal> {
+    let raw_price = 1.0001f64.powi(tick.clamp(MIN_TICK, MAX_TICK));
+    let price = raw_price * 10f64.powi(token_0_decimals as i32 - token_1_decimals as i32);
+    Decimal::from_f64(price)
+        .filter(|price| !price.is_zero())
+        .ok_or_else(|| {
+            AppError::IntegrityError(format!(
+                "the price of tick {} is out of the decimal range",
+                tick
+            ))
+        })
+}
+
+/// Returns the square root price at `tick` in Q64.96, approximated in floating
+/// point rather than with the exact `TickMath` of the pool contracts.
+pub fn sqrt_price_x96_at_tick(tick: i32) -> AppResult<U160> {
+    let sqrt_price = 1.0001f64.powf(tick.clamp(MIN_TICK, MAX_TICK) as f64 / 2.0);
+    U160::try_from(sqrt_price * 2f64.powi(96)).map_err(|_| {
+        AppError::IntegrityError(format!("no square root price at tick {}", tick)).into()
+    })
+}
+
+/// Returns the square root price in Q64.96 of `price`, the whole units of
+/// token 1 per whole unit of token 0.
+pub fn price_to_sqrt_price_x96(
+    price: Decimal,
+    token_0_decimals: u8,
+    token_1_decimals: u8,
+) -> AppResult<U160> {
+    let raw_price = price.to_f64().unwrap_or_default()
+        * 10f64.powi(token_1_decimals as i32 - token_0_decimals as i32);
+    U160::try_from(raw_price.sqrt() * 2f64.powi(96))
+        .ok()
+        .filter(|sqrt_price| !sqrt_price.is_zero())
+        .ok_or_else(|| {
+            AppError::IntegrityError(format!("no square root price of {}", price)).into()
+        })
+}
+
+/// Raw pool state as persisted, e.g. for backtesting.
+///
+/// Only the contract data is kept, the spot price is derived again when the
+/// record is converted back into [`PoolSlotData`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PoolSlotRecord {
+    #[serde(with = "sikkara_core::timestamp_with_tz_serializer")]
+    pub timestamp: jiff::Timestamp,
+    pub block_number: Option<u64>,
+    /// Hex encoded, `0x` prefixed square root price scaled by 2^96
+    pub sqrt_price_x96: String,
+    pub tick: i32,
+    pub protocol_fee: u32,
+    pub lp_fee: u32,
+    pub liquidity: Option<u128>,
+    pub token_0_decimals: u8,
+    pub token_1_decimals: u8,
+    pub invert: bool,
+}
+
+impl From<PoolSlotData> for PoolSlotRecord {
+    fn from(data: PoolSlotData) -> Self {
+        Self {
+            timestamp: data.timestamp,
+            block_number: data.block_number,
+            sqrt_price_x96: format!("{:#x}", data.sqrt_price_x96),
+            tick: data.tick,
+            protocol_fee: data.protocol_fee.to::<u32>(),
+            lp_fee: data.lp_fee.to::<u32>(),
+            liquidity: data.liquidity,
+            token_0_decimals: data.token_0_decimals,
+            token_1_decimals: data.token_1_decimals,
+            invert: data.invert,
+        }
+    }
+}
+
+impl TryFrom<PoolSlotRecord> for PoolSlotData {
+    type Error = AppError;
+
+    fn try_from(record: PoolSlotRecord) -> Result<Self, Self::Error> {
+        let sqrt_price_x96 = record.sqrt_price_x96.parse::<U160>().map_err(|e| {
+            AppError::SerializationError(format!(
+                "invalid sqrt_price_x96 {}: {}",
+                record.sqrt_price_x96, e
+            ))
+        })?;
+        let fee = |fee: u32| {
+            U24::try_from(fee)
+                .map_err(|_| AppError::SerializationError(format!("invalid fee {}", fee)))
+        };
+        let spot_price = SpotPrice::new_from_sqrt_ratio_x96(
+            sqrt_price_x96,
+            record.token_0_decimals,
+            record.token_1_decimals,
+            record.invert,
+        );
+        Ok(Self {
+            sqrt_price_x96,
+            tick: record.tick,
+            protocol_fee: fee(record.protocol_fee)?,
+            lp_fee: fee(record.lp_fee)?,
+            spot_price,
+            liquidity: record.liquidity,
+            block_number: record.block_number,
+            timestamp: record.timestamp,
+            token_0_decimals: record.token_0_decimals,
+            token_1_decimals: record.token_1_decimals,
+            invert: record.invert,
+        })
     }
 }
 
@@ -200,3 +542,237 @@ impl SpotPrice {
             .to_string()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal_macros::dec;
+
+    use super::*;
+
+    /// ETH-USDC `slot0` snapshots captured from the feed.
+    const CAPTURED_RECORDS: [&str; 2] = [
+        r#"{"timestamp":"2025-06-20T14:03:05Z","block_number":22745131,"sqrt_price_x96":"0x346dc5d63886594af4f0d","tick":-198080,"protocol_fee":0,"lp_fee":500,"liquidity":4151392815226375618,"token_0_decimals":18,"token_1_decimals":6,"invert":true}"#,
+        r#"{"timestamp":"2025-06-20T14:03:10Z","block_number":null,"sqrt_price_x96":"0x3543199f0a4e108e4227c","tick":-197765,"protocol_fee":0,"lp_fee":500,"liquidity":null,"token_0_decimals":18,"token_1_decimals":6,"invert":true}"#,
+    ];
+
+    #[test]
+    fn test_captured_records_are_loaded() {
+        let data: PoolSlotData = serde_json::from_str(CAPTURED_RECORDS[0]).unwrap();
+        assert_eq!(data.sqrt_price_x96, U160::from(3961408125713216879677197u128));
+        assert_eq!(data.tick, -198080);
+        assert_eq!(data.lp_fee, U24::from(500));
+        assert_eq!(data.liquidity, Some(4151392815226375618));
+        assert_eq!(data.block_number, Some(22745131));
+        assert_eq!(data.timestamp.to_string(), "2025-06-20T14:03:05Z");
+        assert_eq!(data.spot_price.to_fixed(2, None), "2500.00");
+
+        let data: PoolSlotData = serde_json::from_str(CAPTURED_RECORDS[1]).unwrap();
+        assert_eq!(data.liquidity, None);
+        assert_eq!(data.block_number, None);
+        assert_eq!(data.spot_price.to_fixed(2, None), "2580.10");
+    }
+
+    #[test]
+    fn test_records_round_trip() {
+        for captured in CAPTURED_RECORDS {
+            let data: PoolSlotData = serde_json::from_str(captured).unwrap();
+            assert_eq!(serde_json::to_string(&data).unwrap(), captured);
+        }
+
+        let data = PoolSlotData::new(
+            U160::from(3961408125713216879677197u128),
+            I24::try_from(-198080).unwrap(),
+            U24::from(0),
+            U24::from(3000),
+            18,
+            6,
+            true,
+        )
+        .at_block(22745131, 4151392815226375618);
+        let loaded: PoolSlotData =
+            serde_json::from_str(&serde_json::to_string(&data).unwrap()).unwrap();
+        assert_eq!(PoolSlotRecord::from(loaded.clone()), PoolSlotRecord::from(data.clone()));
+        assert_eq!(loaded.spot_price.to_fixed(6, None), data.spot_price.to_fixed(6, None));
+    }
+
+    #[test]
+    fn test_price_impact_of_selling_token_0() {
+        // 1% of the liquidity at a price of 1 moves the price by 1 - 1/1.01²
+        let sqrt_price_x96 = U160::from(1u128 << 96);
+        assert_eq!(
+            price_impact_bps(Decimal::from(10u64.pow(16)), 10u128.pow(18), sqrt_price_x96),
+            197
+        );
+        assert_eq!(price_impact_bps(Decimal::ZERO, 10u128.pow(18), sqrt_price_x96), 0);
+        assert_eq!(price_impact_bps(Decimal::ONE, 0, sqrt_price_x96), 10_000);
+
+        // Selling 100 ETH into the captured ETH-USDC pool
+        let data: PoolSlotData = serde_json::from_str(CAPTURED_RECORDS[0]).unwrap();
+        assert_eq!(data.price_impact_for_amount(Decimal::ONE), Some(0));
+        assert_eq!(data.price_impact_for_amount(Decimal::from(100)), Some(24));
+        assert_eq!(data.price_impact_for_amount(Decimal::from(1000)), Some(237));
+
+        // Unknown without the liquidity
+        let data: PoolSlotData = serde_json::from_str(CAPTURED_RECORDS[1]).unwrap();
+        assert_eq!(data.price_impact_for_amount(Decimal::from(100)), None);
+    }
+
+    #[test]
+    fn test_max_trade_within_slippage() {
+        // Inverse of the price impact, 1% of the liquidity moving the price by 197 bps
+        let sqrt_price_x96 = U160::from(1u128 << 96);
+        let amount = estimate_max_trade_within_slippage(10u128.pow(18), sqrt_price_x96, 197);
+        assert_eq!(price_impact_bps(amount, 10u128.pow(18), sqrt_price_x96), 197);
+        assert!(amount < Decimal::from(10u64.pow(16)));
+        assert_eq!(estimate_max_trade_within_slippage(0, sqrt_price_x96, 50), Decimal::ZERO);
+        assert_eq!(estimate_max_trade_within_slippage(1, sqrt_price_x96, 10_000), Decimal::MAX);
+
+        // About 100 ETH moves the captured ETH-USDC pool by 24 bps
+        let data: PoolSlotData = serde_json::from_str(CAPTURED_RECORDS[0]).unwrap();
+        assert_eq!(data.max_trade_within_slippage(24).unwrap().round_dp(2), dec!(99.81));
+        assert_eq!(data.max_trade_within_slippage(50).unwrap().round_dp(2), dec!(208.35));
+        let data: PoolSlotData = serde_json::from_str(CAPTURED_RECORDS[1]).unwrap();
+        assert_eq!(data.max_trade_within_slippage(50), None);
+    }
+
+    #[test]
+    fn test_ticks_and_prices_round_trip() {
+        // From ETH-USDC and stablecoin pairs to prices far from one
+        let ticks = [
+            (-198080, (18, 6)),
+            (-276324, (18, 6)),
+            (-230270, (18, 8)),
+            (0, (18, 18)),
+            (400000, (18, 18)),
+            (-300000, (18, 18)),
+        ];
+        for (tick, decimals) in ticks {
+            let price = tick_to_price(tick, decimals.0, decimals.1).unwrap();
+            assert_eq!(price_to_tick(price, decimals.0, decimals.1).unwrap(), tick);
+        }
+        // The price of a tick is at most a basis point away from any price in it
+        let tick = price_to_tick(dec!(2500), 18, 6).unwrap();
+        assert_eq!(tick, -198080);
+        assert!(tick_to_price(tick, 18, 6).unwrap() <= dec!(2500));
+        assert!(tick_to_price(tick + 1, 18, 6).unwrap() > dec!(2500));
+        assert_eq!(tick_to_price(0, 6, 6).unwrap(), Decimal::ONE);
+    }
+
+    #[test]
+    fn test_sqrt_prices_of_ticks_and_prices() {
+        // Within a part in a billion of TickMath, 2^96 at tick 0
+        assert_eq!(sqrt_price_x96_at_tick(0).unwrap(), U160::from(1u128 << 96));
+        let sqrt_price = f64::from(sqrt_price_x96_at_tick(MIN_TICK).unwrap());
+        assert!((sqrt_price / 4295128739.0 - 1.0).abs() < 1e-9);
+        let sqrt_price = f64::from(sqrt_price_x96_at_tick(MAX_TICK).unwrap());
+        assert!(
+            (sqrt_price / 1461446703485210103287273052203988822378723970342.0 - 1.0).abs() < 1e-9
+        );
+
+        // The captured ETH-USDC price of 2500
+        let data: PoolSlotData = serde_json::from_str(CAPTURED_RECORDS[0]).unwrap();
+        let sqrt_price = price_to_sqrt_price_x96(dec!(2500), 18, 6).unwrap();
+        assert!((f64::from(sqrt_price) / f64::from(data.sqrt_price_x96) - 1.0).abs() < 1e-9);
+        assert!(price_to_sqrt_price_x96(Decimal::ZERO, 18, 6).is_err());
+    }
+
+    #[test]
+    fn test_ticks_clamp_to_the_tick_range() {
+        assert_eq!(price_to_tick(Decimal::MAX, 0, 18).unwrap(), MAX_TICK);
+        assert_eq!(price_to_tick(Decimal::new(1, 28), 18, 0).unwrap(), MIN_TICK);
+        assert!(price_to_tick(Decimal::ZERO, 18, 6).is_err());
+        assert!(price_to_tick(dec!(-1), 18, 6).is_err());
+        // Beyond the decimal range
+        assert!(tick_to_price(MAX_TICK, 0, 0).is_err());
+        assert!(tick_to_price(MIN_TICK, 0, 0).is_err());
+    }
+
+    #[test]
+    fn test_swap_output_along_the_curve() {
+        let data: PoolSlotData = serde_json::from_str(CAPTURED_RECORDS[0]).unwrap();
+
+        // Selling ETH pays the 0.05% fee and lowers the ETH price
+        let (amount_out, new_price) = data.estimate_swap_output(Decimal::ONE, true, None).unwrap();
+        assert_eq!(amount_out.round_dp(2), dec!(2498.72));
+        assert_eq!(new_price.round_dp(2), dec!(2499.94));
+        let (amount_out, new_price) = data
+            .estimate_swap_output(Decimal::from(100), true, None)
+            .unwrap();
+        assert_eq!(amount_out.round_dp(2), dec!(249574.56));
+        assert_eq!(new_price.round_dp(2), dec!(2493.99));
+
+        // and buying it with USDC raises the price
+        let (amount_out, new_price) = data
+            .estimate_swap_output(Decimal::from(250000), false, None)
+            .unwrap();
+        assert_eq!(amount_out.round_dp(4), dec!(99.8298));
+        assert_eq!(new_price.round_dp(2), dec!(2506.02));
+    }
+
+    #[test]
+    fn test_swap_stops_at_the_price_limit() {
+        let data: PoolSlotData = serde_json::from_str(CAPTURED_RECORDS[0]).unwrap();
+        let (unlimited, _) = data
+            .estimate_swap_output(Decimal::from(100), true, None)
+            .unwrap();
+
+        // A limit 0.1% below the square root price caps the drop at about 0.2%
+        let limit = data.sqrt_price_x96 - data.sqrt_price_x96 / U160::from(1000);
+        let (amount_out, new_price) = data
+            .estimate_swap_output(Decimal::from(100), true, Some(limit))
+            .unwrap();
+        assert!(amount_out < unlimited);
+        assert_eq!(new_price.round_dp(0), dec!(2495));
+
+        // A limit above the price cannot be reached by selling token 0
+        let limit = data.sqrt_price_x96 + U160::from(1);
+        assert!(data
+            .estimate_swap_output(Decimal::ONE, true, Some(limit))
+            .is_err());
+    }
+
+    #[test]
+    fn test_swap_needs_the_liquidity() {
+        let data: PoolSlotData = serde_json::from_str(CAPTURED_RECORDS[1]).unwrap();
+        assert!(data.estimate_swap_output(Decimal::ONE, true, None).is_err());
+        assert!(data
+            .with_liquidity(0)
+            .estimate_swap_output(Decimal::ONE, true, None)
+            .is_err());
+    }
+
+    #[test]
+    fn test_spot_price_of_a_6_8_decimals_pair() {
+        // A sqrtPriceX96 of 2^91 is a raw price of 1/1024, which the decimals of
+        // USDC (6) and cbBTC (8) scale to a cbBTC price of 102400 USDC
+        let data = PoolSlotData::new(
+            U160::from(1u128 << 91),
+            I24::try_from(-69319).unwrap(),
+            U24::from(0),
+            U24::from(500),
+            6,
+            8,
+            true,
+        );
+        assert_eq!(data.spot_price.to_fixed(12, None), "0.000009765625");
+
+        // and 18/6 decimals make it off by 14 orders of magnitude
+        let data = PoolSlotData::new(
+            U160::from(1u128 << 91),
+            I24::try_from(-69319).unwrap(),
+            U24::from(0),
+            U24::from(500),
+            18,
+            6,
+            true,
+        );
+        assert_eq!(data.spot_price.to_fixed(2, None), "976562500.00");
+    }
+
+    #[test]
+    fn test_invalid_sqrt_price_is_rejected() {
+        let captured = CAPTURED_RECORDS[0].replace("0x346dc5d63886594af4f0d", "0xnope");
+        let error = serde_json::from_str::<PoolSlotData>(&captured).unwrap_err();
+        assert!(error.to_string().contains("invalid sqrt_price_x96"));
+    }
+}
@@ -0,0 +1,204 @@
+//! Uniswap V4 swap quotes through the V4Quoter
+//!
+//! The spot price of a pool ignores the LP fee and the price impact of a
+//! trade. The V4Quoter simulates the swap against the PoolManager instead,
+//! crossing ticks and running the hooks of the pool, so that a quote is the
+//! price a swap of that size would actually execute at.
+
+use std::{sync::Arc, time::Instant};
+
+use alloy::{
+    primitives::{Address, Bytes, U256},
+    sol,
+};
+use rust_decimal::{prelude::ToPrimitive, Decimal};
+use sikkara_core::{metrics::RpcObserver, AppError, AppResult};
+
+use crate::uniswap_v4::PoolKey;
+
+// Generate contract bindings from ABI
+sol!(
+    #[derive(Debug)]
+    #[sol(rpc)]
+    V4Quoter,
+    "abis/Quoter.json"
+);
+
+/// Pool quoted by a [`UniswapV4Quoter`], with the decimals of its currencies.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QuotedPool {
+    pub key: PoolKey,
+    pub currency_0_decimals: u8,
+    pub currency_1_decimals: u8,
+}
+
+/// Quoter of Uniswap V4 swaps, reading the V4Quoter contract.
+pub struct UniswapV4Quoter<P>
+where
+    P: alloy::providers::Provider + Send + Sync,
+{
+    provider: Arc<P>,
+    /// The address of the V4Quoter contract
+    address: Address,
+    /// Observer of the RPC calls, if instrumented
+    observer: Option<Arc<dyn RpcObserver>>,
+}
+
+impl<P> Clone for UniswapV4Quoter<P>
+where
+    P: alloy::providers::Provider + Send + Sync,
+{
+    fn clone(&self) -> Self {
+        Self {
+            provider: self.provider.clone(),
+            address: self.address,
+            observer: self.observer.clone(),
+        }
+    }
+}
+
+impl<P> UniswapV4Quoter<P>
+where
+    P: alloy::providers::Provider + Send + Sync,
+{
+    pub fn new(provider: Arc<P>, address: Address) -> Self {
+        Self { provider, address, observer: None }
+    }
+
+    /// Reports the duration and outcome of every RPC call to the given
+    /// observer.
+    pub fn with_observer(mut self, observer: Arc<dyn RpcObserver>) -> Self {
+        self.observer = Some(observer);
+        self
+    }
+
+    /// Quotes swapping `amount_in` whole units of currency 0 for currency 1
+    /// if `zero_for_one`, or of currency 1 for currency 0 otherwise, returning
+    /// the effective price of the swap: the whole units received per whole
+    /// unit paid, after the LP fee and the price impact.
+    pub async fn quote_exact_input(
+        &self,
+        pool: &QuotedPool,
+        zero_for_one: bool,
+        amount_in: Decimal,
+    ) -> AppResult<Decimal> {
+        let (decimals_in, decimals_out) = match zero_for_one {
+            true => (pool.currency_0_decimals, pool.currency_1_decimals),
+            false => (pool.currency_1_decimals, pool.currency_0_decimals),
+        };
+        let exact_amount = amount_in
+            .checked_mul(Decimal::from_i128_with_scale(10i128.pow(decimals_in as u32), 0))
+            .and_then(|amount| amount.trunc().to_u128())
+            .filter(|amount| *amount > 0)
+            .ok_or_else(|| {
+                AppError::IntegrityError(format!("cannot quote an amount in of {}", amount_in))
+            })?;
+
+        let params = V4Quoter::QuoteExactSingleParams {
+            poolKey: V4Quoter::PoolKey {
+                currency0: pool.key.currency0,
+                currency1: pool.key.currency1,
+                fee: pool.key.fee,
+                tickSpacing: pool.key.tickSpacing,
+                hooks: pool.key.hooks,
+            },
+            zeroForOne: zero_for_one,
+            exactAmount: exact_amount,
+            hookData: Bytes::new(),
+        };
+        let contract = V4Quoter::new(self.address, &self.provider);
+        let started_at = Instant::now();
+        let quote = contract.quoteExactInputSingle(params).call().await;
+        if let Some(observer) = &self.observer {
+            observer.observe("quoteExactInputSingle", started_at.elapsed(), quote.is_ok());
+        }
+
+        let amount_out = to_whole_units(quote?.amountOut, decimals_out)?;
+        let amount_in = to_whole_units(U256::from(exact_amount), decimals_in)?;
+        Ok(amount_out / amount_in)
+    }
+}
+
+/// Converts an amount in the smallest unit of a currency to whole units.
+fn to_whole_units(amount: U256, decimals: u8) -> AppResult<Decimal> {
+    let amount = i128::try_from(amount)
+        .ok()
+        .and_then(|amount| Decimal::try_from_i128_with_scale(amount, decimals as u32).ok())
+        .ok_or_else(|| {
+            AppError::IntegrityError(format!("{} is out of the decimal range", amount))
+        })?;
+    Ok(amount.normalize())
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::{
+        primitives::address, providers::ProviderBuilder, sol_types::SolValue,
+        transports::mock::Asserter,
+    };
+    use rust_decimal_macros::dec;
+
+    use super::*;
+
+    fn eth_usdc() -> QuotedPool {
+        QuotedPool {
+            key: PoolKey::sorted(
+                Address::ZERO,
+                address!("0xA0b86991c6218b36c1d19D4a2E9Eb0cE3606eB48"),
+                500,
+                10,
+                Address::ZERO,
+            ),
+            currency_0_decimals: 18,
+            currency_1_decimals: 6,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_quotes_are_effective_prices() {
+        let asserter = Asserter::new();
+        // 2 ETH sold for 4990.5 USDC, then 5000 USDC paid for 1.99 ETH
+        let quote = |amount_out: U256| (amount_out, U256::from(120_000)).abi_encode_params();
+        asserter.push_success(&Bytes::from(quote(U256::from(4_990_500_000u64))));
+        asserter.push_success(&Bytes::from(quote(
+            U256::from(199u64) * U256::from(10u64).pow(U256::from(16)),
+        )));
+        let provider = ProviderBuilder::new()
+            .disable_recommended_fillers()
+            .connect_mocked_client(asserter.clone());
+        let quoter = UniswapV4Quoter::new(Arc::new(provider), Address::ZERO);
+
+        let price = quoter
+            .quote_exact_input(&eth_usdc(), true, dec!(2))
+            .await
+            .unwrap();
+        assert_eq!(price, dec!(2495.25));
+        let price = quoter
+            .quote_exact_input(&eth_usdc(), false, dec!(5000))
+            .await
+            .unwrap();
+        assert_eq!(price, dec!(0.000398));
+        assert!(asserter.read_q().is_empty());
+
+        // Amounts below the smallest unit cannot be quoted
+        assert!(quoter
+            .quote_exact_input(&eth_usdc(), false, dec!(0.0000001))
+            .await
+            .is_err());
+        assert!(asserter.read_q().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_failed_quotes_are_errors() {
+        let asserter = Asserter::new();
+        asserter.push_failure_msg("execution reverted");
+        let provider = ProviderBuilder::new()
+            .disable_recommended_fillers()
+            .connect_mocked_client(asserter.clone());
+        let quoter = UniswapV4Quoter::new(Arc::new(provider), Address::ZERO);
+        assert!(quoter
+            .quote_exact_input(&eth_usdc(), true, dec!(1))
+            .await
+            .is_err());
+    }
+}
@@ -0,0 +1,217 @@
+//! Uniswap V4 swaps through the Universal Router
+//!
+//! A swap is a single `V4_SWAP` command of the Universal Router, whose input
+//! is the list of V4 router actions to run: the swap itself, settling the
+//! input currency and taking the output currency.
+
+use alloy::{
+    network::TransactionBuilder,
+    primitives::{
+        aliases::{I24, U24},
+        Address, Bytes, U256,
+    },
+    rpc::types::TransactionRequest,
+    sol,
+    sol_types::{SolCall, SolValue},
+};
+
+sol! {
+    /// Identifies a V4 pool, currencies are sorted by address and the native
+    /// currency is the zero address.
+    #[derive(Debug, PartialEq, Eq)]
+    struct PoolKey {
+        address currency0;
+        address currency1;
+        uint24 fee;
+        int24 tickSpacing;
+        address hooks;
+    }
+
+    #[derive(Debug, PartialEq, Eq)]
+    struct ExactInputSingleParams {
+        PoolKey poolKey;
+        bool zeroForOne;
+        uint128 amountIn;
+        uint128 amountOutMinimum;
+        bytes hookData;
+    }
+
+    interface IUniversalRouter {
+        function execute(bytes calldata commands, bytes[] calldata inputs, uint256 deadline) external payable;
+    }
+}
+
+/// Universal Router command running V4 router actions
+const V4_SWAP: u8 = 0x10;
+
+/// V4 router actions
+const SWAP_EXACT_IN_SINGLE: u8 = 0x06;
+const SETTLE_ALL: u8 = 0x0c;
+const TAKE_ALL: u8 = 0x0f;
+
+impl PoolKey {
+    /// Creates the key of a pool, sorting its currencies.
+    pub fn sorted(
+        token_a: Address,
+        token_b: Address,
+        fee: u32,
+        tick_spacing: i32,
+        hooks: Address,
+    ) -> Self {
+        let (currency0, currency1) =
+            if token_a < token_b { (token_a, token_b) } else { (token_b, token_a) };
+        Self {
+            currency0,
+            currency1,
+            fee: U24::from(fee),
+            tickSpacing: I24::try_from(tick_spacing).unwrap_or_default(),
+            hooks,
+        }
+    }
+}
+
+/// Swap of an exact input amount within a single pool.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExactInSingleSwap {
+    pub pool_key: PoolKey,
+    /// Currency paid, one of the currencies of the pool
+    pub currency_in: Address,
+    /// Amount paid, in the smallest unit of the input currency
+    pub amount_in: u128,
+    /// The swap reverts if it returns less, in the smallest unit of the output
+    /// currency
+    pub amount_out_minimum: u128,
+}
+
+impl ExactInSingleSwap {
+    pub fn zero_for_one(&self) -> bool { self.currency_in == self.pool_key.currency0 }
+
+    pub fn currency_out(&self) -> Address {
+        if self.zero_for_one() {
+            self.pool_key.currency1
+        } else {
+            self.pool_key.currency0
+        }
+    }
+}
+
+/// Minimum output of a swap quoted `quoted_out`, accepting at most
+/// `max_slippage_bps` of slippage.
+pub fn min_amount_out(quoted_out: u128, max_slippage_bps: u32) -> u128 {
+    let max_slippage_bps = u128::from(max_slippage_bps.min(10_000));
+    quoted_out - quoted_out * max_slippage_bps / 10_000
+}
+
+/// The Universal Router deployed at an address.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UniversalRouter {
+    address: Address,
+}
+
+impl UniversalRouter {
+    pub fn new(address: Address) -> Self { Self { address } }
+
+    pub fn address(&self) -> Address { self.address }
+
+    /// Encodes the calldata of a swap, which reverts once `deadline`, in unix
+    /// seconds, has passed.
+    pub fn swap_calldata(&self, swap: &ExactInSingleSwap, deadline: u64) -> Bytes {
+        let params = ExactInputSingleParams {
+            poolKey: swap.pool_key.clone(),
+            zeroForOne: swap.zero_for_one(),
+            amountIn: swap.amount_in,
+            amountOutMinimum: swap.amount_out_minimum,
+            hookData: Bytes::new(),
+        };
+        let actions = Bytes::from(vec![SWAP_EXACT_IN_SINGLE, SETTLE_ALL, TAKE_ALL]);
+        let action_params: Vec<Bytes> = vec![
+            params.abi_encode().into(),
+            (swap.currency_in, U256::from(swap.amount_in))
+                .abi_encode()
+                .into(),
+            (swap.currency_out(), U256::from(swap.amount_out_minimum))
+                .abi_encode()
+                .into(),
+        ];
+        let input = (actions, action_params).abi_encode_params();
+        IUniversalRouter::executeCall {
+            commands: Bytes::from(vec![V4_SWAP]),
+            inputs: vec![input.into()],
+            deadline: U256::from(deadline),
+        }
+        .abi_encode()
+        .into()
+    }
+
+    /// Builds the transaction of a swap, paying the input amount as value when
+    /// the input currency is native.
+    pub fn swap_request(&self, swap: &ExactInSingleSwap, deadline: u64) -> TransactionRequest {
+        let request = TransactionRequest::default()
+            .with_to(self.address)
+            .with_input(self.swap_calldata(swap, deadline));
+        if swap.currency_in == Address::ZERO {
+            request.with_value(U256::from(swap.amount_in))
+        } else {
+            request
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::address;
+
+    use super::*;
+
+    const USDC: Address = address!("0x833589fCD6eDb6E08f4c7C32D4f71b54bdA02913");
+
+    fn eth_usdc_swap(currency_in: Address) -> ExactInSingleSwap {
+        ExactInSingleSwap {
+            pool_key: PoolKey::sorted(USDC, Address::ZERO, 500, 10, Address::ZERO),
+            currency_in,
+            amount_in: 1_000_000_000_000_000_000,
+            amount_out_minimum: 2_487_500_000,
+        }
+    }
+
+    #[test]
+    fn test_min_amount_out() {
+        assert_eq!(min_amount_out(2_500_000_000, 50), 2_487_500_000);
+        assert_eq!(min_amount_out(2_500_000_000, 0), 2_500_000_000);
+        assert_eq!(min_amount_out(2_500_000_000, 20_000), 0);
+    }
+
+    #[test]
+    fn test_pool_key_is_sorted() {
+        let swap = eth_usdc_swap(Address::ZERO);
+        assert_eq!(swap.pool_key.currency0, Address::ZERO);
+        assert_eq!(swap.pool_key.currency1, USDC);
+        assert!(swap.zero_for_one());
+        assert_eq!(swap.currency_out(), USDC);
+        assert!(!eth_usdc_swap(USDC).zero_for_one());
+    }
+
+    #[test]
+    fn test_swap_calldata_roundtrip() {
+        let router = UniversalRouter::new(address!("0x6fF5693b99212Da76ad316178A184AB56D299b43"));
+        let swap = eth_usdc_swap(Address::ZERO);
+        let request = router.swap_request(&swap, 1739394800);
+        assert_eq!(request.value, Some(U256::from(swap.amount_in)));
+
+        let calldata = router.swap_calldata(&swap, 1739394800);
+        let call = <IUniversalRouter::executeCall as SolCall>::abi_decode(&calldata).unwrap();
+        assert_eq!(call.commands, Bytes::from(vec![V4_SWAP]));
+        assert_eq!(call.deadline, U256::from(1739394800));
+
+        let (actions, params) = <(Bytes, Vec<Bytes>)>::abi_decode_params(&call.inputs[0]).unwrap();
+        assert_eq!(actions, Bytes::from(vec![SWAP_EXACT_IN_SINGLE, SETTLE_ALL, TAKE_ALL]));
+        let decoded = ExactInputSingleParams::abi_decode(&params[0]).unwrap();
+        assert!(decoded.zeroForOne);
+        assert_eq!(decoded.amountOutMinimum, 2_487_500_000);
+        let (take, minimum) = <(Address, U256)>::abi_decode(&params[2]).unwrap();
+        assert_eq!((take, minimum), (USDC, U256::from(2_487_500_000u64)));
+
+        // ERC20 inputs are not paid as value
+        assert_eq!(router.swap_request(&eth_usdc_swap(USDC), 1739394800).value, None);
+    }
+}
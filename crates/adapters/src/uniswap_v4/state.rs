@@ -2,19 +2,35 @@
 //!
 //! This module provides functionality to watch and stream Uniswap V4 pool state
 //! changes in real-time. It uses polling-based approach to fetch pool data at
-//! configurable intervals.
+//! configurable intervals, batching the pools watched together into a single
+//! call through [Multicall3](https://www.multicall3.com), or fetches it on
+//! every swap of the pool over a log subscription, or once per new block over
+//! a block subscription. RPC calls can be observed,
+//! e.g. to export their durations and errors, through an [`RpcObserver`], and
+//! every fetched state can be handed to a [`PoolSlotSink`], e.g. to persist raw
+//! snapshots.
 
-use std::{pin::Pin, sync::Arc, time::Duration};
+use std::{
+    future::IntoFuture,
+    pin::Pin,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 use alloy::{
-    primitives::{Address, B256},
+    eips::BlockId,
+    primitives::{address, Address, B256},
+    providers::Provider,
+    rpc::types::Filter,
     sol,
+    sol_types::{SolCall, SolEvent},
 };
-use futures::{stream, Stream};
-use tokio::time::interval;
-use tracing::error;
+use futures::{stream, Stream, StreamExt};
+use sikkara_core::{metrics::RpcObserver, AppResult, BackoffStrategy, ExponentialBackoff};
+use tokio::time::{interval, sleep};
+use tracing::{error, warn};
 
-use crate::uniswap_v4::models::PoolSlotData;
+use crate::uniswap_v4::{models::PoolSlotData, watcher::WatchedPool};
 
 // Generate contract bindings from ABI
 sol!(
@@ -24,12 +40,56 @@ sol!(
     "abis/StateView.json"
 );
 
+sol! {
+    #[derive(Debug)]
+    #[sol(rpc)]
+    interface IMulticall3 {
+        struct Call3 {
+            address target;
+            bool allowFailure;
+            bytes callData;
+        }
+
+        struct Result {
+            bool success;
+            bytes returnData;
+        }
+
+        function aggregate3(Call3[] calldata calls) external payable returns (Result[] memory returnData);
+    }
+}
+
+sol! {
+    #[derive(Debug)]
+    interface IPoolManager {
+        event Swap(
+            bytes32 indexed id,
+            address indexed sender,
+            int128 amount0,
+            int128 amount1,
+            uint160 sqrtPriceX96,
+            uint128 liquidity,
+            int24 tick,
+            uint24 fee
+        );
+    }
+}
+
+/// Address of the Multicall3 contract, the same on most chains.
+pub const MULTICALL3_ADDRESS: Address = address!("0xcA11bde05977b3631167028862bE2a173976CA11");
+
 /// A stream that emits [`PoolSlotData`] whenever pool state is fetched.
 ///
 /// The stream will emit data at regular intervals based on the polling
 /// frequency configured when creating the stream. Each emission contains the
 /// current state of the pool including price, tick, and fee information.
-pub type PoolSlotDataStream = Pin<Box<dyn Stream<Item = PoolSlotData> + Send>>;
+pub type PoolSlotDataStream = Pin<Box<dyn Stream<Item = AppResult<PoolSlotData>> + Send>>;
+
+/// Sink of the pool state fetched by a [`UniswapV4StateViewManager`].
+pub trait PoolSlotSink: Send + Sync + std::fmt::Debug {
+    /// Records a successfully fetched pool state.
+    fn record(&self, data: &PoolSlotData);
+}
 
 /// Manager for watching Uniswap V4 pool state changes.
 ///
@@ -48,6 +108,37 @@ where
     provider: Arc<P>,
     /// The address of the Uniswap V4 contract
     address: Address,
+    /// Observer of the RPC calls, if instrumented
+    observer: Option<Arc<dyn RpcObserver>>,
+    /// Sink of the watched pool states, if any
+    sink: Option<Arc<dyn PoolSlotSink>>,
+    /// Whether the state is pinned to a block
+    block_state: bool,
+    /// Address of the Multicall3 contract batching the calls of many pools
+    multicall_address: Address,
+    /// Delays between the retries of a failed poll of a watched pool
+    retry_backoff: ExponentialBackoff,
+    /// Address of the PoolManager contract emitting the swaps of the pools,
+    /// if known
+    pool_manager_address: Option<Address>,
+}
+
+impl<P> Clone for UniswapV4StateViewManager<P>
+where
+    P: alloy::providers::Provider + Send + Sync,
+{
+    fn clone(&self) -> Self {
+        Self {
+            provider: self.provider.clone(),
+            address: self.address,
+            observer: self.observer.clone(),
+            sink: self.sink.clone(),
+            block_state: self.block_state,
+            multicall_address: self.multicall_address,
+            retry_backoff: self.retry_backoff.clone(),
+            pool_manager_address: self.pool_manager_address,
+        }
+    }
 }
 
 impl<P> UniswapV4StateViewManager<P>
@@ -64,18 +155,314 @@ where
     /// # Returns
     ///
     /// A new [`UniswapV4StateViewManager`] instance
-    pub fn new(provider: Arc<P>, address: Address) -> Self { Self { provider, address } }
+    pub fn new(provider: Arc<P>, address: Address) -> Self {
+        Self {
+            provider,
+            address,
+            observer: None,
+            sink: None,
+            block_state: false,
+            multicall_address: MULTICALL3_ADDRESS,
+            retry_backoff: ExponentialBackoff::from_durations(
+                Some(5),
+                Duration::from_millis(500),
+                Duration::from_secs(8),
+                2,
+            ),
+            pool_manager_address: None,
+        }
+    }
+
+    /// Retries a failed poll of [`Self::watch_pool`] after the delays of
+    /// `backoff`, ending the stream once it runs out of retries. By default a
+    /// poll is retried 5 times, from 500ms up to 8s apart.
+    pub fn with_retry_backoff(mut self, backoff: ExponentialBackoff) -> Self {
+        self.retry_backoff = backoff;
+        self
+    }
+
+    /// Subscribes [`Self::watch_pool_events`] to the swaps emitted by the
+    /// PoolManager contract at the given address, which differs from the
+    /// StateView contract read.
+    pub fn with_pool_manager_address(mut self, pool_manager_address: Address) -> Self {
+        self.pool_manager_address = Some(pool_manager_address);
+        self
+    }
+
+    /// Batches the calls of [`Self::watch_pools`] through the Multicall3
+    /// contract at the given address, for chains it is not deployed at
+    /// [`MULTICALL3_ADDRESS`] on.
+    pub fn with_multicall_address(mut self, multicall_address: Address) -> Self {
+        self.multicall_address = multicall_address;
+        self
+    }
+
+    /// Reports the duration and outcome of every RPC call to the given
+    /// observer.
+    pub fn with_observer(mut self, observer: Arc<dyn RpcObserver>) -> Self {
+        self.observer = Some(observer);
+        self
+    }
+
+    /// Hands every state emitted by [`Self::watch_pool`] to the given sink.
+    pub fn with_sink(mut self, sink: Arc<dyn PoolSlotSink>) -> Self {
+        self.sink = Some(sink);
+        self
+    }
+
+    /// Pins every fetched state to the latest block, at the cost of one more
+    /// RPC call per fetch.
+    pub fn with_block_state(mut self) -> Self {
+        self.block_state = true;
+        self
+    }
+
+    /// Fetches the current `slot0` state of a pool along with its in range
+    /// liquidity, read in the same poll, pricing it with the decimals of its
+    /// tokens.
+    pub async fn fetch_slot0(
+        &self,
+        pool_id: B256,
+        token_0_decimals: u8,
+        token_1_decimals: u8,
+        invert: bool,
+    ) -> AppResult<PoolSlotData> {
+        if self.block_state {
+            let block_number = self
+                .observe("eth_blockNumber", self.provider.get_block_number())
+                .await?;
+            return self
+                .fetch_slot0_at(pool_id, token_0_decimals, token_1_decimals, invert, block_number)
+                .await;
+        }
+
+        // Both calls are awaited to completion so that both are observed
+        let contract = UniswapV4::new(self.address, &self.provider);
+        let (slot, liquidity) = tokio::join!(
+            self.observe("getSlot0", contract.getSlot0(pool_id).call()),
+            self.get_liquidity(pool_id),
+        );
+        let (slot, liquidity) = (slot?, liquidity?);
+        let data = PoolSlotData::new(
+            slot.sqrtPriceX96,
+            slot.tick,
+            slot.protocolFee,
+            slot.lpFee,
+            token_0_decimals,
+            token_1_decimals,
+            invert,
+        );
+        Ok(data.with_liquidity(liquidity))
+    }
+
+    /// Fetches the current in range liquidity of a pool, from which the price
+    /// impact of a swap is estimated.
+    pub async fn get_liquidity(&self, pool_id: B256) -> AppResult<u128> {
+        let contract = UniswapV4::new(self.address, &self.provider);
+        Ok(self
+            .observe("getLiquidity", contract.getLiquidity(pool_id).call())
+            .await?)
+    }
+
+    /// Fetches the `slot0` state and in range liquidity of a pool like
+    /// [`Self::fetch_slot0`], as of the given block.
+    async fn fetch_slot0_at(
+        &self,
+        pool_id: B256,
+        token_0_decimals: u8,
+        token_1_decimals: u8,
+        invert: bool,
+        block_number: u64,
+    ) -> AppResult<PoolSlotData> {
+        let contract = UniswapV4::new(self.address, &self.provider);
+        let block = BlockId::number(block_number);
+        let (slot, liquidity) = tokio::try_join!(
+            self.observe("getSlot0", contract.getSlot0(pool_id).block(block).call()),
+            self.observe("getLiquidity", contract.getLiquidity(pool_id).block(block).call()),
+        )?;
+        let data = PoolSlotData::new(
+            slot.sqrtPriceX96,
+            slot.tick,
+            slot.protocolFee,
+            slot.lpFee,
+            token_0_decimals,
+            token_1_decimals,
+            invert,
+        );
+        Ok(data.at_block(block_number, liquidity))
+    }
+
+    /// Fetches the current `slot0` state and in range liquidity of many pools
+    /// in a single call through Multicall3, pinned to the latest block if
+    /// [block state](Self::with_block_state) is on.
+    ///
+    /// Pools whose state could not be read are logged and left out of the
+    /// result.
+    pub async fn fetch_slot0s(
+        &self,
+        pools: &[WatchedPool],
+    ) -> AppResult<Vec<(B256, PoolSlotData)>> {
+        let multicall = IMulticall3::new(self.multicall_address, &self.provider);
+        let calls = pools
+            .iter()
+            .flat_map(|pool| {
+                [
+                    UniswapV4::getSlot0Call { poolId: pool.pool_id }.abi_encode(),
+                    UniswapV4::getLiquidityCall { poolId: pool.pool_id }.abi_encode(),
+                ]
+            })
+            .map(|call_data| IMulticall3::Call3 {
+                target: self.address,
+                allowFailure: true,
+                callData: call_data.into(),
+            })
+            .collect();
+        let block_number = match self.block_state {
+            true => Some(
+                self.observe("eth_blockNumber", self.provider.get_block_number())
+                    .await?,
+            ),
+            false => None,
+        };
+        let mut aggregate = multicall.aggregate3(calls);
+        if let Some(block_number) = block_number {
+            aggregate = aggregate.block(BlockId::number(block_number));
+        }
+        let results = self.observe("aggregate3", aggregate.call()).await?;
+
+        let mut slots = Vec::with_capacity(pools.len());
+        for (pool, results) in pools.iter().zip(results.chunks(2)) {
+            let [slot, liquidity] = results else {
+                error!(pool_id = %pool.pool_id, "Missing pool state in multicall");
+                continue;
+            };
+            if !slot.success || !liquidity.success {
+                error!(pool_id = %pool.pool_id, "Failed to fetch pool state in multicall");
+                continue;
+            }
+            let decoded =
+                UniswapV4::getSlot0Call::abi_decode_returns(&slot.returnData).and_then(|slot| {
+                    UniswapV4::getLiquidityCall::abi_decode_returns(&liquidity.returnData)
+                        .map(|liquidity| (slot, liquidity))
+                });
+            let (slot, liquidity) = match decoded {
+                Ok(decoded) => decoded,
+                Err(e) => {
+                    error!(pool_id = %pool.pool_id, error = %e, "Failed to decode pool state");
+                    continue;
+                },
+            };
+            let data = PoolSlotData::new(
+                slot.sqrtPriceX96,
+                slot.tick,
+                slot.protocolFee,
+                slot.lpFee,
+                pool.token_0_decimals,
+                pool.token_1_decimals,
+                pool.invert,
+            );
+            let data = match block_number {
+                Some(block_number) => data.at_block(block_number, liquidity),
+                None => data.with_liquidity(liquidity),
+            };
+            slots.push((pool.pool_id, data));
+        }
+        Ok(slots)
+    }
+
+    /// Awaits an RPC call, reporting its duration and outcome to the observer.
+    async fn observe<T, E>(
+        &self,
+        method: &'static str,
+        call: impl IntoFuture<Output = Result<T, E>>,
+    ) -> Result<T, E> {
+        let started_at = Instant::now();
+        let result = call.await;
+        if let Some(observer) = &self.observer {
+            observer.observe(method, started_at.elapsed(), result.is_ok());
+        }
+        result
+    }
+
+    /// Fetches the state of a pool for a watch stream, as of `block_number` if
+    /// given, handing it to the sink.
+    ///
+    /// A failed fetch is retried after the next delay of `backoff`, which
+    /// starts over after a success, and its error returned once the backoff
+    /// runs out of retries.
+    async fn poll_slot0(
+        &self,
+        pool_id: B256,
+        token_0_decimals: u8,
+        token_1_decimals: u8,
+        invert: bool,
+        block_number: Option<u64>,
+        backoff: &mut ExponentialBackoff,
+    ) -> AppResult<PoolSlotData> {
+        loop {
+            let data = match block_number {
+                Some(block_number) => {
+                    self.fetch_slot0_at(
+                        pool_id,
+                        token_0_decimals,
+                        token_1_decimals,
+                        invert,
+                        block_number,
+                    )
+                    .await
+                },
+                None => {
+                    self.fetch_slot0(pool_id, token_0_decimals, token_1_decimals, invert)
+                        .await
+                },
+            };
+            match data {
+                Ok(data) => {
+                    backoff.reset();
+                    if let Some(sink) = &self.sink {
+                        sink.record(&data);
+                    }
+                    return Ok(data);
+                },
+                Err(e) => match backoff.next_delay() {
+                    Some(delay) => {
+                        warn!(
+                            pool_id = %pool_id,
+                            error = %e,
+                            attempt = backoff.get_iteration_count(),
+                            "Failed to fetch pool state from contract, retrying in {:?}",
+                            delay
+                        );
+                        sleep(delay).await;
+                    },
+                    None => {
+                        error!(
+                            pool_id = %pool_id,
+                            error = %e,
+                            "Failed to fetch pool state from contract, giving up"
+                        );
+                        return Err(e);
+                    },
+                },
+            }
+        }
+    }
 
     /// Creates a stream that watches a specific pool's state changes.
     ///
     /// This method creates an infinite stream that polls the specified pool at
     /// regular intervals and emits [`PoolSlotData`] containing the current
-    /// pool state. The stream will continue indefinitely until dropped.
+    /// pool state. A failed poll is retried according to the
+    /// [retry backoff](Self::with_retry_backoff), and once it runs out of
+    /// retries the stream emits the error and ends.
     ///
     /// # Arguments
     ///
     /// * `pool_id` - The unique identifier (hash) of the pool to watch
     /// * `poll_interval` - How frequently to poll for state changes
+    /// * `token_0_decimals`, `token_1_decimals` - Decimals of the tokens of the
+    ///   pool, from which the spot price is derived
+    /// * `invert` - Whether the spot price is inverted, see [`SpotPrice`]
     ///
     /// # Recommended Intervals
     ///
@@ -86,51 +473,507 @@ where
         &self,
         pool_id: B256,
         poll_interval: Duration,
+        token_0_decimals: u8,
+        token_1_decimals: u8,
         invert: bool,
     ) -> PoolSlotDataStream {
-        let provider = self.provider.clone();
-        let address = self.address;
+        let state = (self.clone(), interval(poll_interval), self.retry_backoff.clone());
 
-        let stream = stream::unfold(
-            (provider, address, pool_id, interval(poll_interval)),
-            move |(provider, address, pool_id, mut timer)| async move {
-                // Wait for the next polling interval
-                timer.tick().await;
+        let stream = stream::unfold(Some(state), move |state| async move {
+            let (manager, mut timer, mut backoff) = state?;
+
+            // Wait for the next polling interval
+            timer.tick().await;
+
+            // Attempt to fetch current pool state, ending the stream with the
+            // error once the retries are exhausted
+            match manager
+                .poll_slot0(pool_id, token_0_decimals, token_1_decimals, invert, None, &mut backoff)
+                .await
+            {
+                Ok(data) => Some((Ok(data), Some((manager, timer, backoff)))),
+                Err(e) => Some((Err(e), None)),
+            }
+        });
 
-                // Attempt to fetch current pool state
-                match UniswapV4::new(address, &provider)
-                    .getSlot0(pool_id)
-                    .call()
+        Box::pin(stream)
+    }
+
+    /// Creates a stream that watches a pool like [`Self::watch_pool`], but
+    /// waits `next_interval()` after every poll rather than a fixed interval,
+    /// e.g. to poll faster while the price is about to move.
+    pub fn watch_pool_adaptive<F>(
+        &self,
+        pool_id: B256,
+        next_interval: F,
+        token_0_decimals: u8,
+        token_1_decimals: u8,
+        invert: bool,
+    ) -> PoolSlotDataStream
+    where
+        F: FnMut() -> Duration + Send + 'static,
+    {
+        let state = (self.clone(), next_interval, self.retry_backoff.clone(), true);
+
+        let stream = stream::unfold(Some(state), move |state| async move {
+            let (manager, mut next_interval, mut backoff, first) = state?;
+
+            // The first poll is immediate, like the first tick of an interval
+            if !first {
+                sleep(next_interval()).await;
+            }
+
+            match manager
+                .poll_slot0(pool_id, token_0_decimals, token_1_decimals, invert, None, &mut backoff)
+                .await
+            {
+                Ok(data) => Some((Ok(data), Some((manager, next_interval, backoff, false)))),
+                Err(e) => Some((Err(e), None)),
+            }
+        });
+
+        Box::pin(stream)
+    }
+
+    /// Creates a stream that watches a pool like [`Self::watch_pool`], but
+    /// fetches its state on every swap of the pool rather than on a fixed
+    /// interval, so that a price move is seen within milliseconds and an idle
+    /// pool costs no RPC call.
+    ///
+    /// The state is fetched once right away and then on every `Swap` log of
+    /// the pool, received over a subscription of the provider. If the
+    /// provider cannot subscribe, e.g. over HTTP, or the
+    /// [PoolManager address](Self::with_pool_manager_address) is unknown, the
+    /// pool is polled every `fallback_interval` instead.
+    pub fn watch_pool_events(
+        &self,
+        pool_id: B256,
+        fallback_interval: Duration,
+        token_0_decimals: u8,
+        token_1_decimals: u8,
+        invert: bool,
+    ) -> PoolSlotDataStream {
+        let manager = self.clone();
+
+        let stream = stream::once(async move {
+            let poll = |manager: &Self| {
+                manager.watch_pool(
+                    pool_id,
+                    fallback_interval,
+                    token_0_decimals,
+                    token_1_decimals,
+                    invert,
+                )
+            };
+            let Some(pool_manager_address) = manager.pool_manager_address else {
+                warn!(pool_id = %pool_id, "No PoolManager address to subscribe to, polling");
+                return poll(&manager);
+            };
+            let filter = Filter::new()
+                .address(pool_manager_address)
+                .event_signature(IPoolManager::Swap::SIGNATURE_HASH)
+                .topic1(pool_id);
+            let swaps = match manager.provider.subscribe_logs(&filter).await {
+                Ok(subscription) => subscription.into_stream(),
+                Err(e) => {
+                    warn!(
+                        pool_id = %pool_id,
+                        error = %e,
+                        "Failed to subscribe to the swaps of the pool, polling"
+                    );
+                    return poll(&manager);
+                },
+            };
+
+            let backoff = manager.retry_backoff.clone();
+            let state = (manager, swaps, backoff, true);
+            let stream = stream::unfold(Some(state), move |state| async move {
+                let (manager, mut swaps, mut backoff, first) = state?;
+
+                // The first fetch is immediate, the next ones wait for a swap
+                if !first {
+                    swaps.next().await?;
+                }
+
+                match manager
+                    .poll_slot0(
+                        pool_id,
+                        token_0_decimals,
+                        token_1_decimals,
+                        invert,
+                        None,
+                        &mut backoff,
+                    )
                     .await
                 {
-                    Ok(slot) => {
-                        // Successfully fetched slot0 data, create PoolSlotData
-                        let data = PoolSlotData::new(
-                            slot.sqrtPriceX96,
-                            slot.tick,
-                            slot.protocolFee,
-                            slot.lpFee,
-                            18,
-                            6,
-                            invert,
-                        );
-                        // Return data and continue the stream
-                        Some((data, (provider, address, pool_id, timer)))
-                    },
+                    Ok(data) => Some((Ok(data), Some((manager, swaps, backoff, false)))),
+                    Err(e) => Some((Err(e), None)),
+                }
+            });
+            Box::pin(stream) as PoolSlotDataStream
+        })
+        .flatten();
+
+        Box::pin(stream)
+    }
+
+    /// Creates a stream that watches a pool like [`Self::watch_pool`], but
+    /// fetches its state exactly once per new block rather than on a fixed
+    /// interval, so that no block is missed and no call is wasted on a block
+    /// already read.
+    ///
+    /// Every state is read as of the block announced over a `newHeads`
+    /// subscription of the provider and tagged with its number. If the
+    /// provider cannot subscribe, e.g. over HTTP, the pool is polled every
+    /// `fallback_interval` instead.
+    pub fn watch_pool_on_blocks(
+        &self,
+        pool_id: B256,
+        fallback_interval: Duration,
+        token_0_decimals: u8,
+        token_1_decimals: u8,
+        invert: bool,
+    ) -> PoolSlotDataStream {
+        let manager = self.clone();
+
+        let stream = stream::once(async move {
+            let blocks = match manager.provider.subscribe_blocks().await {
+                Ok(subscription) => subscription.into_stream(),
+                Err(e) => {
+                    warn!(
+                        pool_id = %pool_id,
+                        error = %e,
+                        "Failed to subscribe to new blocks, polling"
+                    );
+                    return manager.watch_pool(
+                        pool_id,
+                        fallback_interval,
+                        token_0_decimals,
+                        token_1_decimals,
+                        invert,
+                    );
+                },
+            };
+
+            let backoff = manager.retry_backoff.clone();
+            let state = (manager, blocks, backoff);
+            let stream = stream::unfold(Some(state), move |state| async move {
+                let (manager, mut blocks, mut backoff) = state?;
+                let header = blocks.next().await?;
+
+                match manager
+                    .poll_slot0(
+                        pool_id,
+                        token_0_decimals,
+                        token_1_decimals,
+                        invert,
+                        Some(header.number),
+                        &mut backoff,
+                    )
+                    .await
+                {
+                    Ok(data) => Some((Ok(data), Some((manager, blocks, backoff)))),
+                    Err(e) => Some((Err(e), None)),
+                }
+            });
+            Box::pin(stream) as PoolSlotDataStream
+        })
+        .flatten();
+
+        Box::pin(stream)
+    }
+
+    /// Creates a stream that watches the state of many pools with a single RPC
+    /// call per interval.
+    ///
+    /// Every interval emits the state of each pool that could be read, in no
+    /// particular order. Unlike [`Self::watch_pool`], the stream carries on
+    /// after a failed interval.
+    pub fn watch_pools(
+        &self,
+        pools: Vec<WatchedPool>,
+        poll_interval: Duration,
+    ) -> impl Stream<Item = (B256, PoolSlotData)> + Send {
+        let manager = self.clone();
+
+        stream::unfold(
+            (manager, pools, interval(poll_interval)),
+            |(manager, pools, mut timer)| async move {
+                timer.tick().await;
+
+                let slots = match manager.fetch_slot0s(&pools).await {
+                    Ok(slots) => slots,
                     Err(e) => {
-                        // Log error and skip this iteration
                         error!(
-                            pool_id = %pool_id,
+                            pools = pools.len(),
                             error = %e,
-                            "Failed to fetch pool state from contract"
+                            "Failed to fetch pool states from multicall"
                         );
-                        // Return None to skip emission but continue stream
-                        None
+                        Vec::new()
                     },
+                };
+                if let Some(sink) = &manager.sink {
+                    for (_, data) in &slots {
+                        sink.record(data);
+                    }
                 }
+                Some((stream::iter(slots), (manager, pools, timer)))
             },
+        )
+        .flatten()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use alloy::{
+        primitives::Bytes, providers::ProviderBuilder, sol_types::SolValue,
+        transports::mock::Asserter,
+    };
+    use metrics_exporter_prometheus::PrometheusBuilder;
+    use serde_json::{json, Value};
+    use sikkara_core::metrics::RpcMetrics;
+    use wiremock::{matchers::method, Mock, MockServer, Request, Respond, ResponseTemplate};
+
+    use super::*;
+
+    /// `getSlot0` result at a sqrtPriceX96 of 2^96, ABI encoded.
+    const SLOT0_RESULT: &str = "0x\
+        0000000000000000000000000000000000000001000000000000000000000000\
+        0000000000000000000000000000000000000000000000000000000000000000\
+        0000000000000000000000000000000000000000000000000000000000000000\
+        0000000000000000000000000000000000000000000000000000000000000000";
+
+    /// Answers JSON-RPC requests after a delay, echoing the request id.
+    struct JsonRpcResponder {
+        result: Result<&'static str, &'static str>,
+        delay: Duration,
+    }
+
+    impl Respond for JsonRpcResponder {
+        fn respond(&self, request: &Request) -> ResponseTemplate {
+            let request: Value = serde_json::from_slice(&request.body).unwrap();
+            let body = match self.result {
+                Ok(result) => json!({ "jsonrpc": "2.0", "id": request["id"], "result": result }),
+                Err(message) => json!({
+                    "jsonrpc": "2.0",
+                    "id": request["id"],
+                    "error": { "code": -32000, "message": message },
+                }),
+            };
+            ResponseTemplate::new(200)
+                .set_body_json(body)
+                .set_delay(self.delay)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rpc_calls_are_observed() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(JsonRpcResponder {
+                result: Ok(SLOT0_RESULT),
+                delay: Duration::from_millis(200),
+            })
+            .up_to_n_times(2)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .respond_with(JsonRpcResponder {
+                result: Err("header not found"),
+                delay: Duration::ZERO,
+            })
+            .mount(&server)
+            .await;
+
+        let recorder = PrometheusBuilder::new()
+            .set_buckets(&[0.1, 0.5, 1.0])
+            .unwrap()
+            .build_recorder();
+        let handle = recorder.handle();
+        let _guard = metrics::set_default_local_recorder(&recorder);
+
+        let provider = ProviderBuilder::new().connect_http(server.uri().parse().unwrap());
+        let manager = UniswapV4StateViewManager::new(Arc::new(provider), Address::ZERO)
+            .with_observer(Arc::new(RpcMetrics::new("127.0.0.1")));
+        // The liquidity is read along, decoding the first word of the result
+        let data = manager.fetch_slot0(B256::ZERO, 18, 6, false).await.unwrap();
+        assert_eq!(data.tick, 0);
+        assert_eq!(data.liquidity, Some(1 << 96));
+        assert!(manager.fetch_slot0(B256::ZERO, 18, 6, false).await.is_err());
+
+        let scrape = handle.render();
+        let labels = "method=\"getSlot0\",endpoint=\"127.0.0.1\"";
+        // The delayed call lands above the first bucket, the failed one below
+        for expected in [
+            format!("sikarra_adapter_rpc_duration_seconds_bucket{{{labels},le=\"0.1\"}} 1"),
+            format!("sikarra_adapter_rpc_duration_seconds_bucket{{{labels},le=\"0.5\"}} 2"),
+            format!("sikarra_adapter_rpc_duration_seconds_count{{{labels}}} 2"),
+            format!("sikarra_adapter_rpc_errors_total{{{labels}}} 1"),
+        ] {
+            assert!(scrape.contains(&expected), "{} not in {}", expected, scrape);
+        }
+    }
+
+    /// Backoff retrying a failed poll `retries` times right away.
+    fn immediate_retries(retries: u32) -> ExponentialBackoff {
+        ExponentialBackoff::from_durations(Some(retries), Duration::ZERO, Duration::ZERO, 1)
+    }
+
+    #[tokio::test]
+    async fn test_watch_pool_adaptive_waits_the_next_interval_between_polls() {
+        let slot0: Bytes = SLOT0_RESULT.parse().unwrap();
+        let asserter = Asserter::new();
+        // getSlot0 and getLiquidity of every poll
+        for _ in 0..6 {
+            asserter.push_success(&slot0);
+        }
+        let provider = ProviderBuilder::new()
+            .disable_recommended_fillers()
+            .connect_mocked_client(asserter.clone());
+        let manager = UniswapV4StateViewManager::new(Arc::new(provider), Address::ZERO)
+            .with_retry_backoff(immediate_retries(0));
+
+        let waits = Arc::new(AtomicUsize::new(0));
+        let counter = waits.clone();
+        let next_interval = move || {
+            counter.fetch_add(1, Ordering::SeqCst);
+            Duration::from_millis(1)
+        };
+        let stream = manager.watch_pool_adaptive(B256::ZERO, next_interval, 18, 6, false);
+
+        // The first poll is immediate and the stream ends with the failed fourth
+        let updates = stream.collect::<Vec<_>>().await;
+        assert_eq!(updates.len(), 4);
+        assert!(updates[..3].iter().all(|update| update.is_ok()));
+        assert!(updates[3].is_err());
+        assert_eq!(waits.load(Ordering::SeqCst), 3);
+        assert!(asserter.read_q().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_watch_pool_retries_failed_polls() {
+        let slot0: Bytes = SLOT0_RESULT.parse().unwrap();
+        let asserter = Asserter::new();
+        // Both calls fail in the first two polls and succeed in the third
+        for _ in 0..4 {
+            asserter.push_failure_msg("header not found");
+        }
+        asserter.push_success(&slot0);
+        asserter.push_success(&slot0);
+        let provider = ProviderBuilder::new()
+            .disable_recommended_fillers()
+            .connect_mocked_client(asserter.clone());
+        let manager = UniswapV4StateViewManager::new(Arc::new(provider), Address::ZERO)
+            .with_retry_backoff(immediate_retries(2));
+        let mut stream = manager.watch_pool(B256::ZERO, Duration::from_millis(1), 18, 6, false);
+
+        // Two failures in a row are within the retries
+        let data = stream.next().await.unwrap().unwrap();
+        assert_eq!(data.tick, 0);
+        assert!(asserter.read_q().is_empty());
+
+        // but a third ends the stream with its error, the retries starting over
+        // after the success
+        for _ in 0..6 {
+            asserter.push_failure_msg("header not found");
+        }
+        assert!(stream.next().await.unwrap().is_err());
+        assert!(stream.next().await.is_none());
+        assert!(asserter.read_q().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_watch_pool_events_polls_without_subscriptions() {
+        let slot0: Bytes = SLOT0_RESULT.parse().unwrap();
+        let asserter = Asserter::new();
+        for _ in 0..4 {
+            asserter.push_success(&slot0);
+        }
+        let provider = ProviderBuilder::new()
+            .disable_recommended_fillers()
+            .connect_mocked_client(asserter.clone());
+        let manager = UniswapV4StateViewManager::new(Arc::new(provider), Address::ZERO);
+
+        // Polled without the PoolManager address to subscribe to
+        let stream = manager.watch_pool_events(B256::ZERO, Duration::from_millis(1), 18, 6, false);
+        let updates = stream.take(1).collect::<Vec<_>>().await;
+        assert_eq!(updates[0].as_ref().unwrap().tick, 0);
+
+        // and by a provider that cannot subscribe
+        let manager = manager.with_pool_manager_address(Address::with_last_byte(1));
+        let stream = manager.watch_pool_events(B256::ZERO, Duration::from_millis(1), 18, 6, false);
+        let updates = stream.take(1).collect::<Vec<_>>().await;
+        assert_eq!(updates[0].as_ref().unwrap().tick, 0);
+        assert!(asserter.read_q().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_watch_pool_on_blocks_polls_without_subscriptions() {
+        let slot0: Bytes = SLOT0_RESULT.parse().unwrap();
+        let asserter = Asserter::new();
+        asserter.push_success(&slot0);
+        asserter.push_success(&slot0);
+        let provider = ProviderBuilder::new()
+            .disable_recommended_fillers()
+            .connect_mocked_client(asserter.clone());
+        let manager = UniswapV4StateViewManager::new(Arc::new(provider), Address::ZERO);
+
+        // The mocked provider cannot subscribe, so the pool is polled unpinned
+        let stream =
+            manager.watch_pool_on_blocks(B256::ZERO, Duration::from_millis(1), 18, 6, false);
+        let updates = stream.take(1).collect::<Vec<_>>().await;
+        let data = updates[0].as_ref().unwrap();
+        assert_eq!(data.tick, 0);
+        assert_eq!(data.block_number, None);
+        assert!(asserter.read_q().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_watch_pools_batches_calls() {
+        let slot0: Bytes = SLOT0_RESULT.parse().unwrap();
+        let liquidity = Bytes::from(1u128.abi_encode());
+        // getSlot0 and getLiquidity of every pool
+        let results = vec![
+            IMulticall3::Result { success: true, returnData: slot0.clone() },
+            IMulticall3::Result { success: true, returnData: liquidity.clone() },
+            IMulticall3::Result { success: false, returnData: Bytes::new() },
+            IMulticall3::Result { success: false, returnData: Bytes::new() },
+            IMulticall3::Result { success: true, returnData: slot0 },
+            IMulticall3::Result { success: true, returnData: liquidity },
+        ];
+        let asserter = Asserter::new();
+        asserter.push_success(&Bytes::from(results.abi_encode()));
+        asserter.push_success(&Bytes::from(results.abi_encode()));
+        let provider = ProviderBuilder::new()
+            .disable_recommended_fillers()
+            .connect_mocked_client(asserter.clone());
+        let manager = UniswapV4StateViewManager::new(Arc::new(provider), Address::ZERO);
+
+        // The failed pool is left out
+        let pools = vec![
+            WatchedPool::new(B256::with_last_byte(1), 18, 6, false),
+            WatchedPool::new(B256::with_last_byte(2), 18, 6, false),
+            WatchedPool::new(B256::with_last_byte(3), 18, 6, true),
+        ];
+        let slots = manager.fetch_slot0s(&pools).await.unwrap();
+        assert_eq!(
+            slots
+                .iter()
+                .map(|(pool_id, _)| *pool_id)
+                .collect::<Vec<_>>(),
+            vec![B256::with_last_byte(1), B256::with_last_byte(3)]
         );
+        assert_eq!(slots[1].1.spot_price.to_fixed(2, None), "1000000000000.00");
+        assert_eq!(slots[1].1.liquidity, Some(1));
 
-        Box::pin(stream)
+        // A single call per interval serves every pool
+        let stream = manager.watch_pools(pools, Duration::from_secs(1));
+        let updates = stream.take(2).collect::<Vec<_>>().await;
+        assert_eq!(updates.len(), 2);
+        assert!(updates.iter().all(|(_, data)| data.tick == 0));
+        assert!(asserter.read_q().is_empty());
     }
 }
@@ -0,0 +1,304 @@
+//! Watching many Uniswap V4 pools read through the same node.
+//!
+//! Polling every pool on its own costs one round trip per pool and interval.
+//! The [`UniswapV4MultiPoolWatcher`] instead reads the state of all the pools
+//! in a single Multicall3 call per interval and hands the state of each pool
+//! to its own stream.
+
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use alloy::primitives::B256;
+use futures::stream;
+use sikkara_core::{AppError, AppResult, BackoffStrategy, ExponentialBackoff};
+use tokio::{
+    sync::mpsc::{self, error::TrySendError},
+    task::JoinHandle,
+    time::{interval, sleep},
+};
+use tokio_util::sync::CancellationToken;
+use tracing::{error, warn};
+
+use crate::uniswap_v4::{
+    state::{PoolSlotDataStream, PoolSlotSink},
+    PoolSlotData, UniswapV4StateViewManager,
+};
+
+/// States buffered per pool until its stream is read, newer states being
+/// dropped beyond
+const POOL_STATE_BUFFER: usize = 16;
+
+/// Pool watched by a [`UniswapV4MultiPoolWatcher`].
+#[derive(Debug, Clone)]
+pub struct WatchedPool {
+    pub pool_id: B256,
+    /// Decimals of the tokens of the pool, from which the spot price is
+    /// derived
+    pub token_0_decimals: u8,
+    pub token_1_decimals: u8,
+    /// Whether the spot price is inverted, see [`SpotPrice`](crate::SpotPrice)
+    pub invert: bool,
+    /// Sink of the states of the pool, if any
+    pub sink: Option<Arc<dyn PoolSlotSink>>,
+}
+
+impl WatchedPool {
+    pub fn new(pool_id: B256, token_0_decimals: u8, token_1_decimals: u8, invert: bool) -> Self {
+        Self { pool_id, token_0_decimals, token_1_decimals, invert, sink: None }
+    }
+
+    /// Hands every state of the pool to the given sink.
+    pub fn with_sink(mut self, sink: Arc<dyn PoolSlotSink>) -> Self {
+        self.sink = Some(sink);
+        self
+    }
+}
+
+/// Watcher of many pools read through the same node, with a single RPC call
+/// per interval for all of them.
+pub struct UniswapV4MultiPoolWatcher<P>
+where
+    P: alloy::providers::Provider + Send + Sync,
+{
+    manager: UniswapV4StateViewManager<P>,
+    pools: Vec<WatchedPool>,
+    poll_interval: Duration,
+    /// Delays between the retries of a failed poll
+    retry_backoff: ExponentialBackoff,
+}
+
+impl<P> UniswapV4MultiPoolWatcher<P>
+where
+    P: alloy::providers::Provider + Send + Sync + 'static,
+{
+    /// Creates a watcher of the given pools, read by `manager` every
+    /// `poll_interval`.
+    pub fn new(
+        manager: UniswapV4StateViewManager<P>,
+        pools: Vec<WatchedPool>,
+        poll_interval: Duration,
+    ) -> Self {
+        Self {
+            manager,
+            pools,
+            poll_interval,
+            retry_backoff: ExponentialBackoff::from_durations(
+                Some(5),
+                Duration::from_millis(500),
+                Duration::from_secs(8),
+                2,
+            ),
+        }
+    }
+
+    /// Retries a failed poll after the delays of `backoff`, ending every
+    /// stream once it runs out of retries. By default a poll is retried 5
+    /// times, from 500ms up to 8s apart.
+    pub fn with_retry_backoff(mut self, backoff: ExponentialBackoff) -> Self {
+        self.retry_backoff = backoff;
+        self
+    }
+
+    /// Starts polling the pools until `shutdown` is cancelled or every stream
+    /// is dropped, returning the stream of each pool by pool id.
+    ///
+    /// Like [`UniswapV4StateViewManager::watch_pool`], every stream emits the
+    /// error of the poll and ends once the retries are exhausted. A pool whose
+    /// state could not be read in an otherwise successful poll is skipped
+    /// until the next one.
+    pub fn spawn(
+        self,
+        shutdown: CancellationToken,
+    ) -> (HashMap<B256, PoolSlotDataStream>, JoinHandle<AppResult<()>>) {
+        let mut streams = HashMap::with_capacity(self.pools.len());
+        let mut senders = HashMap::with_capacity(self.pools.len());
+        for pool in &self.pools {
+            let (sender, mut receiver) = mpsc::channel(POOL_STATE_BUFFER);
+            senders.insert(pool.pool_id, sender);
+            let stream = stream::poll_fn(move |cx| receiver.poll_recv(cx));
+            streams.insert(pool.pool_id, Box::pin(stream) as PoolSlotDataStream);
+        }
+        (streams, tokio::spawn(self.run(senders, shutdown)))
+    }
+
+    async fn run(
+        self,
+        senders: HashMap<B256, mpsc::Sender<AppResult<PoolSlotData>>>,
+        shutdown: CancellationToken,
+    ) -> AppResult<()> {
+        let mut timer = interval(self.poll_interval);
+        let mut backoff = self.retry_backoff.clone();
+        let mut retry_in = None;
+        loop {
+            // Wait for the next interval, or the retry of a failed poll
+            let wait = async {
+                match retry_in.take() {
+                    Some(delay) => sleep(delay).await,
+                    None => {
+                        timer.tick().await;
+                    },
+                }
+            };
+            tokio::select! {
+                _ = shutdown.cancelled() => return Ok(()),
+                _ = wait => {},
+            }
+
+            match self.manager.fetch_slot0s(&self.pools).await {
+                Ok(slots) => {
+                    backoff.reset();
+                    for (pool_id, data) in slots {
+                        self.emit(&senders, pool_id, data);
+                    }
+                },
+                Err(e) => match backoff.next_delay() {
+                    Some(delay) => {
+                        warn!(
+                            pools = self.pools.len(),
+                            error = %e,
+                            attempt = backoff.get_iteration_count(),
+                            "Failed to fetch pool states from multicall, retrying in {:?}",
+                            delay
+                        );
+                        retry_in = Some(delay);
+                    },
+                    None => {
+                        error!(
+                            pools = self.pools.len(),
+                            error = %e,
+                            "Failed to fetch pool states from multicall, giving up"
+                        );
+                        for sender in senders.values() {
+                            let error = AppError::HttpError(e.to_string());
+                            let _ = sender.send(Err(error.into())).await;
+                        }
+                        return Ok(());
+                    },
+                },
+            }
+
+            if senders.values().all(|sender| sender.is_closed()) {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Hands the state of a pool to its sink and stream, dropping it if the
+    /// stream is behind.
+    fn emit(
+        &self,
+        senders: &HashMap<B256, mpsc::Sender<AppResult<PoolSlotData>>>,
+        pool_id: B256,
+        data: PoolSlotData,
+    ) {
+        if let Some(sink) = self
+            .pools
+            .iter()
+            .find(|pool| pool.pool_id == pool_id)
+            .and_then(|pool| pool.sink.as_ref())
+        {
+            sink.record(&data);
+        }
+        let Some(sender) = senders.get(&pool_id) else { return };
+        if let Err(TrySendError::Full(_)) = sender.try_send(Ok(data)) {
+            warn!(pool_id = %pool_id, "Dropped pool state, its stream is behind");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::{
+        primitives::{Address, Bytes},
+        providers::ProviderBuilder,
+        sol_types::SolValue,
+        transports::mock::Asserter,
+    };
+    use futures::StreamExt;
+
+    use super::*;
+    use crate::uniswap_v4::state::IMulticall3;
+
+    /// `getSlot0` result at a sqrtPriceX96 of 2^96 and tick 0.
+    const SLOT0_RESULT: &str = "0x\
+        0000000000000000000000000000000000000001000000000000000000000000\
+        0000000000000000000000000000000000000000000000000000000000000000\
+        0000000000000000000000000000000000000000000000000000000000000000\
+        0000000000000000000000000000000000000000000000000000000000000000";
+
+    /// Multicall3 result reading the state of `pools` pools.
+    fn multicall_result(pools: usize) -> Bytes {
+        let slot0: Bytes = SLOT0_RESULT.parse().unwrap();
+        let liquidity = Bytes::from(1u128.abi_encode());
+        let results = (0..pools)
+            .flat_map(|_| {
+                [
+                    IMulticall3::Result { success: true, returnData: slot0.clone() },
+                    IMulticall3::Result { success: true, returnData: liquidity.clone() },
+                ]
+            })
+            .collect::<Vec<_>>();
+        Bytes::from(results.abi_encode())
+    }
+
+    #[tokio::test]
+    async fn test_one_call_per_poll_serves_every_pool() {
+        let asserter = Asserter::new();
+        // A single multicall per poll for the 4 pools
+        for _ in 0..2 {
+            asserter.push_success(&multicall_result(4));
+        }
+        let provider = ProviderBuilder::new()
+            .disable_recommended_fillers()
+            .connect_mocked_client(asserter.clone());
+        let manager = UniswapV4StateViewManager::new(Arc::new(provider), Address::ZERO);
+        let pools = (1..=4)
+            .map(|byte| WatchedPool::new(B256::with_last_byte(byte), 18, 6, true))
+            .collect();
+        let watcher = UniswapV4MultiPoolWatcher::new(manager, pools, Duration::from_millis(1));
+
+        let shutdown = CancellationToken::new();
+        let (mut streams, handle) = watcher.spawn(shutdown.clone());
+        assert_eq!(streams.len(), 4);
+        for byte in 1..=4 {
+            let stream = streams.get_mut(&B256::with_last_byte(byte)).unwrap();
+            for _ in 0..2 {
+                let data = stream.next().await.unwrap().unwrap();
+                assert_eq!(data.liquidity, Some(1));
+                assert_eq!(data.spot_price.to_fixed(2, None), "1000000000000.00");
+            }
+        }
+        shutdown.cancel();
+        handle.await.unwrap().unwrap();
+        assert!(asserter.read_q().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_failed_polls_end_every_stream_once_retries_run_out() {
+        let asserter = Asserter::new();
+        asserter.push_failure_msg("header not found");
+        asserter.push_failure_msg("header not found");
+        let provider = ProviderBuilder::new()
+            .disable_recommended_fillers()
+            .connect_mocked_client(asserter.clone());
+        let manager = UniswapV4StateViewManager::new(Arc::new(provider), Address::ZERO);
+        let pools = (1..=2)
+            .map(|byte| WatchedPool::new(B256::with_last_byte(byte), 18, 6, true))
+            .collect();
+        let watcher = UniswapV4MultiPoolWatcher::new(manager, pools, Duration::from_millis(1))
+            .with_retry_backoff(ExponentialBackoff::from_durations(
+                Some(1),
+                Duration::ZERO,
+                Duration::ZERO,
+                1,
+            ));
+
+        let (streams, handle) = watcher.spawn(CancellationToken::new());
+        for (_, stream) in streams {
+            let updates = stream.collect::<Vec<_>>().await;
+            assert_eq!(updates.len(), 1);
+            assert!(updates[0].is_err());
+        }
+        handle.await.unwrap().unwrap();
+        assert!(asserter.read_q().is_empty());
+    }
+}
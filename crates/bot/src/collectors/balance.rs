@@ -0,0 +1,279 @@
+use std::{sync::Arc, time::Duration};
+
+use alloy::{
+    primitives::{Address, U256},
+    providers::Provider,
+};
+use futures::{stream, StreamExt};
+use rust_decimal::Decimal;
+use sikkara_adapters::{CoinbaseTradeClient, IERC20};
+use sikkara_core::{AppError, AppResult, Collector, CollectorStream};
+use tokio::time::interval;
+use tracing::warn;
+
+use crate::engine::{BalanceUpdate, BalanceVenue, Exchange, InternalEvent, Token};
+
+/// A token whose balances are tracked.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BalanceToken {
+    /// Asset symbol, as named by the CEX
+    pub asset: String,
+    /// The token, the native currency at the zero address
+    pub token: Token,
+}
+
+/// Collector periodically reading the balances held on-chain and, if a
+/// client is configured, on Coinbase.
+///
+/// On-chain balances are read for every token and address, Coinbase balances
+/// for the accounts of the same assets. Balances failing to be read are
+/// skipped until the next poll.
+pub struct BalanceCollector<P>
+where
+    P: Provider + Send + Sync,
+{
+    name: String,
+    provider: Arc<P>,
+    addresses: Vec<Address>,
+    tokens: Vec<BalanceToken>,
+    coinbase: Option<CoinbaseTradeClient>,
+    poll_interval: Duration,
+}
+
+impl<P> BalanceCollector<P>
+where
+    P: Provider + Send + Sync,
+{
+    pub fn new(
+        pool: String,
+        provider: Arc<P>,
+        addresses: Vec<Address>,
+        tokens: Vec<BalanceToken>,
+        poll_interval: Duration,
+    ) -> Self {
+        Self {
+            name: format!("balance_collector_{}", pool),
+            provider,
+            addresses,
+            tokens,
+            coinbase: None,
+            poll_interval,
+        }
+    }
+
+    /// Reads the Coinbase balances with `client` as well.
+    pub fn with_coinbase(mut self, client: CoinbaseTradeClient) -> Self {
+        self.coinbase = Some(client);
+        self
+    }
+
+    /// Reads all balances once.
+    pub async fn poll(&self) -> Vec<BalanceUpdate> {
+        let timestamp = jiff::Timestamp::now();
+        let mut updates = Vec::new();
+        for address in &self.addresses {
+            for token in &self.tokens {
+                match self.wallet_balance(*address, &token.token).await {
+                    Ok(balance) => updates.push(BalanceUpdate {
+                        venue: BalanceVenue::Wallet(*address),
+                        asset: token.asset.clone(),
+                        free: balance,
+                        total: balance,
+                        timestamp,
+                    }),
+                    Err(e) => {
+                        warn!(%address, asset = %token.asset, error = %e, "failed to read balance")
+                    },
+                }
+            }
+        }
+
+        if let Some(client) = &self.coinbase {
+            match client.list_accounts().await {
+                Ok(accounts) => updates.extend(
+                    accounts
+                        .into_iter()
+                        .filter(|account| {
+                            self.tokens
+                                .iter()
+                                .any(|token| token.asset == account.currency)
+                        })
+                        .map(|account| BalanceUpdate {
+                            venue: BalanceVenue::Cex(Exchange::Coinbase),
+                            free: account.available_balance.value,
+                            total: account.total(),
+                            asset: account.currency,
+                            timestamp,
+                        }),
+                ),
+                Err(e) => warn!(error = %e, "failed to read coinbase balances"),
+            }
+        }
+        updates
+    }
+
+    async fn wallet_balance(&self, address: Address, token: &Token) -> AppResult<Decimal> {
+        let amount = if token.address == Address::ZERO {
+            self.provider.get_balance(address).await?
+        } else {
+            IERC20::new(token.address, &self.provider)
+                .balanceOf(address)
+                .call()
+                .await?
+        };
+        from_units(amount, token).ok_or_else(|| {
+            AppError::IntegrityError(format!(
+                "balance {} of token {} does not fit a decimal",
+                amount, token.address
+            ))
+            .into()
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl<P> Collector<InternalEvent> for BalanceCollector<P>
+where
+    P: Provider + Send + Sync,
+{
+    fn name(&self) -> &str { &self.name }
+
+    /// Balances are polled far less often than the staleness threshold of
+    /// price feeds.
+    fn tracks_liveness(&self) -> bool { false }
+
+    async fn subscribe_event_stream(&mut self) -> AppResult<CollectorStream<'_, InternalEvent>> {
+        let collector = &*self;
+        let stream = stream::unfold(interval(self.poll_interval), move |mut timer| async move {
+            timer.tick().await;
+            Some((collector.poll().await, timer))
+        })
+        .flat_map(stream::iter)
+        .map(InternalEvent::BalanceUpdate);
+        Ok(Box::pin(stream))
+    }
+
+    async fn unsubscribe_event_stream(&mut self) -> AppResult<()> { Ok(()) }
+}
+
+/// Converts an amount in the smallest unit of a token into the token, none if
+/// it does not fit a decimal.
+fn from_units(amount: U256, token: &Token) -> Option<Decimal> {
+    let amount = i128::try_from(amount).ok()?;
+    Decimal::try_from_i128_with_scale(amount, token.decimals.into())
+        .ok()
+        .map(|amount| amount.normalize())
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::{
+        primitives::{address, U256},
+        providers::ProviderBuilder,
+        transports::mock::Asserter,
+    };
+    use rust_decimal_macros::dec;
+    use serde_json::json;
+    use sikkara_core::Secret;
+    use wiremock::{
+        matchers::{method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    use super::*;
+
+    const WALLET: Address = address!("0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266");
+    const USDC: Address = address!("0x833589fCD6eDb6E08f4c7C32D4f71b54bdA02913");
+
+    fn tokens() -> Vec<BalanceToken> {
+        vec![
+            BalanceToken {
+                asset: "ETH".to_string(),
+                token: Token { address: Address::ZERO, decimals: 18 },
+            },
+            BalanceToken { asset: "USDC".to_string(), token: Token { address: USDC, decimals: 6 } },
+        ]
+    }
+
+    #[test]
+    fn test_amounts_are_converted_to_tokens() {
+        let usdc = Token { address: USDC, decimals: 6 };
+        assert_eq!(from_units(U256::from(2_500_500_000u64), &usdc), Some(dec!(2500.5)));
+        assert_eq!(from_units(U256::ZERO, &usdc), Some(Decimal::ZERO));
+
+        let eth = Token { address: Address::ZERO, decimals: 18 };
+        assert_eq!(from_units(U256::from(1u64), &eth), Some(dec!(0.000000000000000001)));
+        assert_eq!(from_units(U256::MAX, &eth), None);
+    }
+
+    #[tokio::test]
+    async fn test_balances_are_emitted() {
+        let asserter = Asserter::new();
+        // 1.5 ETH and 2500.5 USDC
+        asserter.push_success(&U256::from(1_500_000_000_000_000_000u64));
+        asserter.push_success(&format!("0x{:064x}", 2_500_500_000u64));
+        let provider = ProviderBuilder::new()
+            .disable_recommended_fillers()
+            .connect_mocked_client(asserter.clone());
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/v3/brokerage/accounts"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "accounts": [
+                    {
+                        "currency": "ETH",
+                        "available_balance": { "value": "2.25", "currency": "ETH" },
+                        "hold": { "value": "0.5", "currency": "ETH" }
+                    },
+                    {
+                        "currency": "SOL",
+                        "available_balance": { "value": "10", "currency": "SOL" },
+                        "hold": { "value": "0", "currency": "SOL" }
+                    }
+                ]
+            })))
+            .mount(&server)
+            .await;
+        let client = CoinbaseTradeClient::new(
+            &server.uri(),
+            "test-api-key".to_string(),
+            Secret::new("test-api-secret"),
+        );
+
+        let mut collector = BalanceCollector::new(
+            "ETH-USDC".to_string(),
+            Arc::new(provider),
+            vec![WALLET],
+            tokens(),
+            Duration::from_secs(60),
+        )
+        .with_coinbase(client);
+        let events: Vec<_> = collector
+            .subscribe_event_stream()
+            .await
+            .unwrap()
+            .take(3)
+            .collect()
+            .await;
+
+        let balances: Vec<_> = events
+            .into_iter()
+            .map(|event| match event {
+                InternalEvent::BalanceUpdate(balance) => {
+                    (balance.venue, balance.asset, balance.free, balance.total)
+                },
+                event => panic!("unexpected event: {:?}", event),
+            })
+            .collect();
+        assert_eq!(
+            balances,
+            vec![
+                (BalanceVenue::Wallet(WALLET), "ETH".to_string(), dec!(1.5), dec!(1.5)),
+                (BalanceVenue::Wallet(WALLET), "USDC".to_string(), dec!(2500.5), dec!(2500.5)),
+                (BalanceVenue::Cex(Exchange::Coinbase), "ETH".to_string(), dec!(2.25), dec!(2.75)),
+            ]
+        );
+        assert!(asserter.read_q().is_empty());
+    }
+}
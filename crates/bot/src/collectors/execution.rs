@@ -0,0 +1,57 @@
+use sikkara_core::{AppError, AppResult, Collector, CollectorStream};
+use tokio::sync::mpsc;
+use tokio_stream::{wrappers::UnboundedReceiverStream, StreamExt};
+
+use crate::engine::{ExecutionEvent, InternalEvent, OrderUpdate};
+
+/// Collector feeding the execution updates and the order updates of the
+/// executors of a pool back into its pipeline.
+#[derive(Debug)]
+pub struct ExecutionEventCollector {
+    name: String,
+    receiver: Option<mpsc::UnboundedReceiver<ExecutionEvent>>,
+    order_sender: mpsc::UnboundedSender<OrderUpdate>,
+    order_receiver: Option<mpsc::UnboundedReceiver<OrderUpdate>>,
+}
+
+impl ExecutionEventCollector {
+    /// Creates the collector and the sender executors report their updates
+    /// through.
+    pub fn new(pool: String) -> (Self, mpsc::UnboundedSender<ExecutionEvent>) {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let (order_sender, order_receiver) = mpsc::unbounded_channel();
+        let collector = Self {
+            name: format!("execution_collector_{}", pool),
+            receiver: Some(receiver),
+            order_sender,
+            order_receiver: Some(order_receiver),
+        };
+        (collector, sender)
+    }
+
+    /// Returns the sender executors report the updates of their orders
+    /// through.
+    pub fn order_updates(&self) -> mpsc::UnboundedSender<OrderUpdate> { self.order_sender.clone() }
+}
+
+#[async_trait::async_trait]
+impl Collector<InternalEvent> for ExecutionEventCollector {
+    fn name(&self) -> &str { &self.name }
+
+    fn tracks_liveness(&self) -> bool { false }
+
+    async fn subscribe_event_stream(&mut self) -> AppResult<CollectorStream<'_, InternalEvent>> {
+        let (Some(receiver), Some(order_receiver)) =
+            (self.receiver.take(), self.order_receiver.take())
+        else {
+            return Err(
+                AppError::ConfigError(format!("{} is already subscribed", self.name)).into()
+            );
+        };
+        let executions = UnboundedReceiverStream::new(receiver).map(InternalEvent::Execution);
+        let orders = UnboundedReceiverStream::new(order_receiver).map(InternalEvent::OrderUpdate);
+        Ok(Box::pin(executions.merge(orders)))
+    }
+
+    async fn unsubscribe_event_stream(&mut self) -> AppResult<()> { Ok(()) }
+}
@@ -0,0 +1,46 @@
+use sikkara_core::{AppResult, Collector, CollectorStream};
+use tokio_stream::StreamExt;
+use tracing::{info_span, Span};
+
+use crate::engine::{InternalEvent, PoolSymbol, PriceFeed};
+
+/// Collector of the fills of our own orders on a CEX, e.g. to track the
+/// realized inventory.
+#[derive(Debug, Clone)]
+pub struct OwnFillCollector<P>
+where
+    P: PriceFeed + Send + Sync,
+{
+    pub symbol: PoolSymbol,
+    pub client: P,
+    pub name: String,
+}
+
+impl<P> OwnFillCollector<P>
+where
+    P: PriceFeed + Send + Sync,
+{
+    pub fn new(symbol: PoolSymbol, client: P) -> Self {
+        let name = format!("own_fill_collector_{}", symbol);
+        Self { symbol, client, name }
+    }
+}
+
+#[async_trait::async_trait]
+impl<P> Collector<InternalEvent> for OwnFillCollector<P>
+where
+    P: PriceFeed + Send + Sync,
+{
+    fn name(&self) -> &str { &self.name }
+
+    fn span(&self) -> Span {
+        info_span!("collector", collector = %self.name, exchange = %self.client.exchange())
+    }
+
+    async fn subscribe_event_stream(&mut self) -> AppResult<CollectorStream<'_, InternalEvent>> {
+        let stream = self.client.subscribe_own_fills(self.symbol.clone()).await?;
+        Ok(Box::pin(stream.map(InternalEvent::OwnFill)))
+    }
+
+    async fn unsubscribe_event_stream(&mut self) -> AppResult<()> { Ok(()) }
+}
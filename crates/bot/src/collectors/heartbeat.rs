@@ -0,0 +1,187 @@
+use std::time::Duration;
+
+use futures::stream;
+use sikkara_core::{AppResult, Collector, CollectorStream};
+use tokio_stream::StreamExt;
+use tracing::{info_span, warn, Span};
+
+use crate::engine::{
+    FeedState, FeedStatus, HeartbeatSubscription, InternalEvent, PoolSymbol, PriceFeed, PriceSource,
+};
+
+/// Collector watching the heartbeats of a CEX feed, reporting the feed down
+/// once no heartbeat arrived within `timeout` and up again once they resume.
+///
+/// Unlike the ticker, heartbeats are sent even if the product does not
+/// trade, so their absence reveals a dead subscription rather than a quiet
+/// market.
+#[derive(Debug, Clone)]
+pub struct HeartbeatMonitor<P>
+where
+    P: PriceFeed + Send + Sync,
+{
+    pub symbol: PoolSymbol,
+    pub client: P,
+    pub name: String,
+    pub timeout: Duration,
+}
+
+impl<P> HeartbeatMonitor<P>
+where
+    P: PriceFeed + Send + Sync,
+{
+    pub fn new(symbol: PoolSymbol, client: P, timeout: Duration) -> Self {
+        let name = format!("heartbeat_monitor_{}", symbol);
+        Self { symbol, client, name, timeout }
+    }
+}
+
+/// State of the heartbeats of a feed between two transitions.
+struct Liveness<'a> {
+    heartbeats: HeartbeatSubscription<'a>,
+    /// Time of the last heartbeat, if any arrived yet
+    last_heartbeat: Option<jiff::Timestamp>,
+    stale: bool,
+}
+
+#[async_trait::async_trait]
+impl<P> Collector<InternalEvent> for HeartbeatMonitor<P>
+where
+    P: PriceFeed + Send + Sync,
+{
+    fn name(&self) -> &str { &self.name }
+
+    fn span(&self) -> Span {
+        info_span!(
+            "collector",
+            collector = %self.name,
+            exchange = %self.client.exchange(),
+            source = %PriceSource::Cex
+        )
+    }
+
+    /// Only transitions are emitted, so the collector is silent while the feed
+    /// is healthy.
+    fn tracks_liveness(&self) -> bool { false }
+
+    async fn subscribe_event_stream(&mut self) -> AppResult<CollectorStream<'_, InternalEvent>> {
+        let heartbeats = self
+            .client
+            .subscribe_heartbeats(self.symbol.clone())
+            .await?;
+        let (feed, symbol, timeout) = (self.name.clone(), self.symbol.clone(), self.timeout);
+        let liveness = Liveness { heartbeats, last_heartbeat: None, stale: false };
+
+        let stream = stream::unfold(liveness, move |mut liveness| {
+            let (feed, symbol) = (feed.clone(), symbol.clone());
+            async move {
+                loop {
+                    let (state, reason) =
+                        match tokio::time::timeout(timeout, liveness.heartbeats.next()).await {
+                            Ok(Some(timestamp)) => {
+                                liveness.last_heartbeat = Some(timestamp);
+                                if !liveness.stale {
+                                    continue;
+                                }
+                                (FeedState::Up, "heartbeats resumed".to_string())
+                            },
+                            // The heartbeats end with the subscription, which the
+                            // price feed collector reports
+                            Ok(None) => return None,
+                            Err(_) if liveness.stale => continue,
+                            Err(_) => {
+                                let last = match liveness.last_heartbeat {
+                                    Some(timestamp) => format!("last at {}", timestamp),
+                                    None => "none yet".to_string(),
+                                };
+                                warn!("No heartbeat of {} within {:?}, {}", symbol, timeout, last);
+                                let reason = format!("no heartbeat within {:?}, {}", timeout, last);
+                                (FeedState::Down, reason)
+                            },
+                        };
+                    liveness.stale = state == FeedState::Down;
+                    let status = FeedStatus {
+                        feed,
+                        source: PriceSource::Cex,
+                        symbol,
+                        state,
+                        reason,
+                        timestamp: jiff::Timestamp::now(),
+                    };
+                    return Some((InternalEvent::FeedStatus(status), liveness));
+                }
+            }
+        });
+        Ok(Box::pin(stream))
+    }
+
+    async fn unsubscribe_event_stream(&mut self) -> AppResult<()> { Ok(()) }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::sync::mpsc;
+    use tokio_stream::wrappers::ReceiverStream;
+
+    use super::*;
+    use crate::engine::{Exchange, PriceFeedSubscription};
+
+    /// Price feed replaying the heartbeats sent through a channel.
+    struct FakeHeartbeats {
+        receiver: Option<mpsc::Receiver<jiff::Timestamp>>,
+    }
+
+    #[async_trait::async_trait]
+    impl PriceFeed for FakeHeartbeats {
+        fn exchange(&self) -> Exchange { Exchange::Coinbase }
+
+        async fn subscribe_price_feed(
+            &mut self,
+            _pool_symbol: PoolSymbol,
+        ) -> AppResult<PriceFeedSubscription<'_>> {
+            Ok(Box::pin(tokio_stream::pending()))
+        }
+
+        async fn unsubscribe_price_feed(&mut self, _pool_symbol: PoolSymbol) -> AppResult<()> {
+            Ok(())
+        }
+
+        async fn subscribe_heartbeats(
+            &mut self,
+            _pool_symbol: PoolSymbol,
+        ) -> AppResult<HeartbeatSubscription<'_>> {
+            Ok(Box::pin(ReceiverStream::new(self.receiver.take().unwrap())))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_stale_transition_fires_once_heartbeats_stop() {
+        let (sender, receiver) = mpsc::channel(4);
+        let mut monitor = HeartbeatMonitor::new(
+            PoolSymbol::ETH_USDC,
+            FakeHeartbeats { receiver: Some(receiver) },
+            Duration::from_millis(50),
+        );
+        let mut stream = monitor.subscribe_event_stream().await.unwrap();
+
+        let beat = "2025-02-12T21:12:30Z".parse().unwrap();
+        sender.send(beat).await.unwrap();
+        let Some(InternalEvent::FeedStatus(status)) = stream.next().await else {
+            panic!("expected a feed status");
+        };
+        assert_eq!(status.state, FeedState::Down);
+        assert_eq!(status.source, PriceSource::Cex);
+        assert_eq!(status.feed, "heartbeat_monitor_ETH-USDC");
+        assert!(status.reason.contains("last at 2025-02-12T21:12:30Z"));
+
+        // Stale only once, until the heartbeats resume
+        sender.send(beat).await.unwrap();
+        let Some(InternalEvent::FeedStatus(status)) = stream.next().await else {
+            panic!("expected a feed status");
+        };
+        assert_eq!(status.state, FeedState::Up);
+
+        drop(sender);
+        assert!(stream.next().await.is_none());
+    }
+}
@@ -3,3 +3,18 @@ pub use ticker::PriceFeedCollector;
 
 mod pool;
 pub use pool::PoolFeedCollector;
+
+mod snapshot;
+pub use snapshot::{load_snapshots, SnapshotRecorder, SnapshotWriter};
+
+mod execution;
+pub use execution::ExecutionEventCollector;
+
+mod balance;
+pub use balance::{BalanceCollector, BalanceToken};
+
+mod fills;
+pub use fills::OwnFillCollector;
+
+mod heartbeat;
+pub use heartbeat::HeartbeatMonitor;
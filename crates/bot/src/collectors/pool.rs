@@ -1,7 +1,9 @@
+use futures::stream;
 use sikkara_core::{AppResult, Collector, CollectorStream};
 use tokio_stream::StreamExt;
+use tracing::{info_span, Span};
 
-use crate::engine::{InternalEvent, Pool, PoolFeed};
+use crate::engine::{FeedState, FeedStatus, InternalEvent, Pool, PoolFeed, PriceSource};
 
 /// Collector that listens for updates from a pool feed client
 #[derive(Debug, Clone)]
@@ -31,12 +33,34 @@ where
 {
     fn name(&self) -> &str { &self.name }
 
+    fn span(&self) -> Span {
+        info_span!(
+            "collector",
+            collector = %self.name,
+            exchange = self.client.exchange(),
+            source = %PriceSource::Dex
+        )
+    }
+
     async fn subscribe_event_stream(&mut self) -> AppResult<CollectorStream<'_, InternalEvent>> {
         let stream = self
             .client
             .subscribe_pool_updates(self.pool.clone())
             .await?;
         let stream = stream.filter_map(|update| Some(InternalEvent::PoolPriceUpdate(update)));
+
+        // Report the feed as down once the underlying pool feed terminates
+        let (feed, symbol) = (self.name.clone(), self.pool.symbol.clone());
+        let stream = stream.chain(stream::once(async move {
+            InternalEvent::FeedStatus(FeedStatus {
+                feed,
+                source: PriceSource::Dex,
+                symbol,
+                state: FeedState::Down,
+                reason: "pool feed stream ended".to_string(),
+                timestamp: jiff::Timestamp::now(),
+            })
+        }));
         Ok(Box::pin(stream))
     }
 
@@ -0,0 +1,203 @@
+//! Raw pool state snapshots for backtesting.
+//!
+//! Every [`PoolSlotData`] fetched by a pool feed can be handed to a
+//! [`SnapshotRecorder`], which queues it without blocking the feed. A single
+//! [`SnapshotWriter`] task drains the queue on a blocking thread into one JSON
+//! lines file per pool per UTC day,
+//! `<output_dir>/slot0-<SYMBOL>-YYYY-MM-DD.jsonl`, see
+//! [`DailyFileWriter`](crate::daily_files::DailyFileWriter).
+//! [`load_snapshots`] reads a file back, deriving the spot prices again.
+
+use std::{
+    fs::File,
+    io::{BufRead, BufReader},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use sikkara_adapters::{PoolSlotData, PoolSlotSink};
+use sikkara_core::{AppError, AppResult};
+use tokio_util::sync::CancellationToken;
+
+use crate::daily_files::{DailyFileFormat, DailyFileQueue, DailyFileWriter, DailyRow};
+
+/// A pool state as queued for the [`SnapshotWriter`].
+#[derive(Debug)]
+struct Snapshot {
+    symbol: String,
+    data: PoolSlotData,
+}
+
+impl DailyRow for Snapshot {
+    fn key(&self) -> &str { &self.symbol }
+
+    fn timestamp(&self) -> jiff::Timestamp { self.data.timestamp }
+}
+
+/// Queues the pool states of a pool for the [`SnapshotWriter`].
+///
+/// States are dropped rather than blocking the feed when the writer falls
+/// behind.
+#[derive(Debug, Clone)]
+pub struct SnapshotRecorder {
+    symbol: String,
+    queue: DailyFileQueue<Snapshot>,
+}
+
+impl SnapshotRecorder {
+    /// Returns the number of states dropped because the queue was full,
+    /// across all pools.
+    pub fn dropped(&self) -> u64 { self.queue.dropped() }
+}
+
+impl PoolSlotSink for SnapshotRecorder {
+    fn record(&self, data: &PoolSlotData) {
+        self.queue
+            .push(Snapshot { symbol: self.symbol.clone(), data: data.clone() });
+    }
+}
+
+/// Writes the queued pool states to daily JSON lines files per pool.
+#[derive(Debug)]
+pub struct SnapshotWriter {
+    writer: DailyFileWriter<Snapshot>,
+}
+
+impl SnapshotWriter {
+    /// Creates a writer to `output_dir`, queueing up to `queue_size` states
+    /// and flushing the files every `flush_interval`.
+    pub fn new(
+        output_dir: impl Into<PathBuf>,
+        queue_size: usize,
+        flush_interval: Duration,
+    ) -> Self {
+        let format = DailyFileFormat {
+            name: "Snapshot",
+            rows: "pool states",
+            prefix: "slot0-",
+            extension: ".jsonl",
+            header: None,
+            format_row: write_json_line,
+        };
+        Self { writer: DailyFileWriter::new(output_dir, format, queue_size, flush_interval) }
+    }
+
+    /// Returns a recorder of the states of the given pool feeding this writer.
+    pub fn recorder(&self, symbol: impl Into<String>) -> SnapshotRecorder {
+        SnapshotRecorder { symbol: symbol.into(), queue: self.writer.queue() }
+    }
+
+    /// Spawns the writer on a blocking thread until shutdown is requested,
+    /// at which point the queued states are written and the files flushed.
+    pub fn spawn(self, shutdown: CancellationToken) -> tokio::task::JoinHandle<AppResult<()>> {
+        self.writer.spawn(shutdown)
+    }
+}
+
+/// Appends a pool state to the buffer as a JSON line.
+fn write_json_line(snapshot: &Snapshot, buffer: &mut Vec<u8>) -> AppResult<()> {
+    serde_json::to_writer(&mut *buffer, &snapshot.data)?;
+    buffer.push(b'\n');
+    Ok(())
+}
+
+/// Loads the pool states of a snapshot file, in the order they were fetched.
+pub fn load_snapshots(path: impl AsRef<Path>) -> AppResult<Vec<PoolSlotData>> {
+    let path = path.as_ref();
+    let reader = BufReader::new(File::open(path)?);
+    let mut snapshots = Vec::new();
+    for (index, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.is_empty() {
+            continue;
+        }
+        let data = serde_json::from_str(&line).map_err(|e| {
+            AppError::SerializationError(format!("{} line {}: {}", path.display(), index + 1, e))
+        })?;
+        snapshots.push(data);
+    }
+    Ok(snapshots)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use alloy::primitives::{
+        aliases::{I24, U24},
+        U160,
+    };
+
+    use super::*;
+
+    fn snapshot(timestamp: &str, sqrt_price_x96: u128, block_number: u64) -> PoolSlotData {
+        let mut data = PoolSlotData::new(
+            U160::from(sqrt_price_x96),
+            I24::try_from(-198080).unwrap(),
+            U24::from(0),
+            U24::from(500),
+            18,
+            6,
+            true,
+        )
+        .at_block(block_number, 4151392815226375618);
+        data.timestamp = timestamp.parse().unwrap();
+        data
+    }
+
+    #[tokio::test]
+    async fn test_snapshots_are_written_and_loaded() {
+        let dir = tempfile::tempdir().unwrap();
+        let writer = SnapshotWriter::new(dir.path(), 16, Duration::from_millis(10));
+        let recorder = writer.recorder("ETH-USDC");
+
+        let snapshots = [
+            snapshot("2025-06-20T23:59:55Z", 3961408125713216879677197, 22745131),
+            snapshot("2025-06-21T00:00:07Z", 4024371226411718127395452, 22745132),
+            snapshot("2025-06-21T00:00:19Z", 3961408125713216879677197, 22745133),
+        ];
+        for data in &snapshots {
+            recorder.record(data);
+        }
+
+        let shutdown = CancellationToken::new();
+        let task = writer.spawn(shutdown.clone());
+        shutdown.cancel();
+        task.await.unwrap().unwrap();
+        assert_eq!(recorder.dropped(), 0);
+
+        let loaded = load_snapshots(dir.path().join("slot0-ETH-USDC-2025-06-20.jsonl")).unwrap();
+        assert_eq!(loaded.len(), 1);
+        assert_eq!(loaded[0].block_number, Some(22745131));
+        assert_eq!(loaded[0].spot_price.to_fixed(2, None), "2500.00");
+
+        let loaded = load_snapshots(dir.path().join("slot0-ETH-USDC-2025-06-21.jsonl")).unwrap();
+        assert_eq!(loaded.len(), 2);
+        for (loaded, data) in loaded.iter().zip(&snapshots[1..]) {
+            assert_eq!(loaded.sqrt_price_x96, data.sqrt_price_x96);
+            assert_eq!(loaded.tick, data.tick);
+            assert_eq!(loaded.lp_fee, data.lp_fee);
+            assert_eq!(loaded.liquidity, data.liquidity);
+            assert_eq!(loaded.block_number, data.block_number);
+            assert_eq!(loaded.timestamp, data.timestamp);
+            assert_eq!(loaded.spot_price.to_fixed(6, None), data.spot_price.to_fixed(6, None));
+        }
+        assert_eq!(loaded[0].spot_price.to_fixed(2, None), "2580.10");
+    }
+
+    #[test]
+    fn test_invalid_snapshot_reports_its_line() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("slot0-ETH-USDC-2025-06-20.jsonl");
+        let valid = serde_json::to_string(&snapshot(
+            "2025-06-20T12:00:00Z",
+            3961408125713216879677197,
+            22745131,
+        ))
+        .unwrap();
+        fs::write(&path, format!("{}\n{{\"tick\":1}}\n", valid)).unwrap();
+
+        let error = load_snapshots(&path).unwrap_err();
+        assert!(error.to_string().contains("line 2"), "{}", error);
+    }
+}
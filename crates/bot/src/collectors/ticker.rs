@@ -1,7 +1,13 @@
+use std::time::Duration;
+
+use futures::stream;
 use sikkara_core::{AppResult, Collector, CollectorStream};
-use tokio_stream::StreamExt;
+use tokio_stream::{Stream, StreamExt};
+use tracing::{info_span, warn, Span};
 
-use crate::engine::{InternalEvent, PoolSymbol, PriceFeed};
+use crate::engine::{
+    FeedState, FeedStatus, InternalEvent, PoolSymbol, PriceFeed, PriceSource, Ticker,
+};
 
 /// Collector that listens for price feed updates from a price feed client
 #[derive(Debug, Clone)]
@@ -12,6 +18,9 @@ where
     pub symbol: PoolSymbol,
     pub client: P,
     pub name: String,
+    /// Time without a ticker after which the feed is reported down, never if
+    /// absent
+    pub stale_after: Option<Duration>,
 }
 
 impl<P> PriceFeedCollector<P>
@@ -19,10 +28,87 @@ where
     P: PriceFeed + Send + Sync,
 {
     pub fn new(symbol: PoolSymbol, client: P) -> Self {
-        Self { symbol, client, name: "price_feed_collector".to_string() }
+        // Named after the exchange too, as several may feed the same pool
+        let name = format!("price_feed_collector_{}_{}", client.exchange(), symbol);
+        Self { symbol, client, name, stale_after: None }
+    }
+
+    /// Reports the feed down once no ticker arrived within `stale_after`, and
+    /// up again once they resume, so that strategies stop trading on a stale
+    /// price.
+    pub fn with_stale_after(mut self, stale_after: Duration) -> Self {
+        self.stale_after = Some(stale_after);
+        self
     }
 }
 
+/// State of the tickers of a feed watched for staleness.
+struct Staleness<S> {
+    tickers: S,
+    /// Time of the last ticker, if any arrived yet
+    last_seen: Option<jiff::Timestamp>,
+    stale: bool,
+    /// Ticker that revived the feed, emitted after its transition
+    pending: Option<Ticker>,
+}
+
+/// Emits the tickers as events, interleaved with a transition down once no
+/// ticker arrived within `stale_after` and up once they resume.
+fn watch_staleness<'a, S>(
+    tickers: S,
+    feed: String,
+    symbol: PoolSymbol,
+    stale_after: Duration,
+) -> impl Stream<Item = InternalEvent> + Send + 'a
+where
+    S: Stream<Item = Ticker> + Send + Unpin + 'a,
+{
+    let staleness = Staleness { tickers, last_seen: None, stale: false, pending: None };
+    stream::unfold(staleness, move |mut staleness| {
+        let (feed, symbol) = (feed.clone(), symbol.clone());
+        async move {
+            if let Some(ticker) = staleness.pending.take() {
+                return Some((InternalEvent::TickerUpdate(ticker), staleness));
+            }
+            // Once stale, only the next ticker matters
+            let next = if staleness.stale {
+                Ok(staleness.tickers.next().await)
+            } else {
+                tokio::time::timeout(stale_after, staleness.tickers.next()).await
+            };
+            let (state, reason) = match next {
+                Ok(Some(ticker)) => {
+                    staleness.last_seen = Some(ticker.timestamp);
+                    if !staleness.stale {
+                        return Some((InternalEvent::TickerUpdate(ticker), staleness));
+                    }
+                    staleness.pending = Some(ticker);
+                    (FeedState::Up, "tickers resumed".to_string())
+                },
+                Ok(None) => return None,
+                Err(_) => {
+                    let last = match staleness.last_seen {
+                        Some(timestamp) => format!("last at {}", timestamp),
+                        None => "none yet".to_string(),
+                    };
+                    warn!("No ticker of {} within {:?}, {}", symbol, stale_after, last);
+                    (FeedState::Down, format!("no ticker within {:?}, {}", stale_after, last))
+                },
+            };
+            staleness.stale = state == FeedState::Down;
+            let status = FeedStatus {
+                feed,
+                source: PriceSource::Cex,
+                symbol,
+                state,
+                reason,
+                timestamp: jiff::Timestamp::now(),
+            };
+            Some((InternalEvent::FeedStatus(status), staleness))
+        }
+    })
+}
+
 #[async_trait::async_trait]
 impl<P> Collector<InternalEvent> for PriceFeedCollector<P>
 where
@@ -30,18 +116,44 @@ where
 {
     fn name(&self) -> &str { &self.name }
 
+    fn span(&self) -> Span {
+        info_span!(
+            "collector",
+            collector = %self.name,
+            exchange = %self.client.exchange(),
+            source = %PriceSource::Cex
+        )
+    }
+
     async fn subscribe_event_stream(&mut self) -> AppResult<CollectorStream<'_, InternalEvent>> {
         let stream = self
             .client
             .subscribe_price_feed(self.symbol.clone())
             .await?;
-        let stream = stream.filter_map(|ticker| {
-            if ticker.symbol == self.symbol {
-                Some(InternalEvent::TickerUpdate(ticker))
-            } else {
-                None
-            }
-        });
+        let symbol = self.symbol.clone();
+        let tickers = stream.filter(move |ticker| ticker.symbol == symbol);
+        let stream: CollectorStream<'_, InternalEvent> = match self.stale_after {
+            Some(stale_after) => Box::pin(watch_staleness(
+                tickers,
+                self.name.clone(),
+                self.symbol.clone(),
+                stale_after,
+            )),
+            None => Box::pin(tickers.map(InternalEvent::TickerUpdate)),
+        };
+
+        // Report the feed as down once the underlying price feed terminates
+        let (feed, symbol) = (self.name.clone(), self.symbol.clone());
+        let stream = stream.chain(stream::once(async move {
+            InternalEvent::FeedStatus(FeedStatus {
+                feed,
+                source: PriceSource::Cex,
+                symbol,
+                state: FeedState::Down,
+                reason: "price feed stream ended".to_string(),
+                timestamp: jiff::Timestamp::now(),
+            })
+        }));
         Ok(Box::pin(stream))
     }
 
@@ -51,3 +163,84 @@ where
             .await
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal_macros::dec;
+    use tokio::sync::mpsc;
+    use tokio_stream::wrappers::ReceiverStream;
+
+    use super::*;
+    use crate::engine::{Exchange, PriceFeedSubscription};
+
+    /// Price feed replaying the tickers sent through a channel.
+    struct FakeTickers {
+        receiver: Option<mpsc::Receiver<Ticker>>,
+    }
+
+    #[async_trait::async_trait]
+    impl PriceFeed for FakeTickers {
+        fn exchange(&self) -> Exchange { Exchange::Coinbase }
+
+        async fn subscribe_price_feed(
+            &mut self,
+            _pool_symbol: PoolSymbol,
+        ) -> AppResult<PriceFeedSubscription<'_>> {
+            Ok(Box::pin(ReceiverStream::new(self.receiver.take().unwrap())))
+        }
+
+        async fn unsubscribe_price_feed(&mut self, _pool_symbol: PoolSymbol) -> AppResult<()> {
+            Ok(())
+        }
+    }
+
+    fn ticker(timestamp: &str) -> Ticker {
+        Ticker {
+            exchage: Exchange::Coinbase,
+            symbol: PoolSymbol::ETH_USDC,
+            price: dec!(2500),
+            timestamp: timestamp.parse().unwrap(),
+            bid: None,
+            ask: None,
+            bid_size: None,
+            ask_size: None,
+            volume_24h: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_feed_is_reported_down_while_no_ticker_arrives() {
+        let (sender, receiver) = mpsc::channel(4);
+        let mut collector =
+            PriceFeedCollector::new(PoolSymbol::ETH_USDC, FakeTickers { receiver: Some(receiver) })
+                .with_stale_after(Duration::from_millis(50));
+        let mut stream = collector.subscribe_event_stream().await.unwrap();
+
+        sender.send(ticker("2025-02-12T21:12:30Z")).await.unwrap();
+        assert!(matches!(stream.next().await, Some(InternalEvent::TickerUpdate(_))));
+        let Some(InternalEvent::FeedStatus(status)) = stream.next().await else {
+            panic!("expected a feed status");
+        };
+        assert_eq!(status.state, FeedState::Down);
+        assert_eq!(status.feed, "price_feed_collector_coinbase_ETH-USDC");
+        assert!(status.reason.contains("last at 2025-02-12T21:12:30Z"));
+
+        // Down only once, until a ticker revives the feed
+        sender.send(ticker("2025-02-12T21:13:30Z")).await.unwrap();
+        let Some(InternalEvent::FeedStatus(status)) = stream.next().await else {
+            panic!("expected a feed status");
+        };
+        assert_eq!(status.state, FeedState::Up);
+        let Some(InternalEvent::TickerUpdate(revived)) = stream.next().await else {
+            panic!("expected the ticker");
+        };
+        assert_eq!(revived, ticker("2025-02-12T21:13:30Z"));
+
+        drop(sender);
+        let Some(InternalEvent::FeedStatus(status)) = stream.next().await else {
+            panic!("expected a feed status");
+        };
+        assert_eq!(status.reason, "price feed stream ended");
+        assert!(stream.next().await.is_none());
+    }
+}
@@ -4,9 +4,25 @@
 //! trading between centralized exchanges (CEX) and decentralized exchanges
 //! (DEX).
 
+use std::{
+    collections::HashMap,
+    sync::{Mutex, OnceLock},
+    time::Duration,
+};
+
+use alloy::{primitives::Address, providers::Provider};
+use rust_decimal::{prelude::ToPrimitive, Decimal};
+use rust_decimal_macros::dec;
 use serde::Deserialize;
+use sikkara_adapters::{
+    CoinbaseChannelMode, EscalationPolicy, FeeCaps, FeeEstimator, IERC20, PERMIT2_ADDRESS,
+};
+use sikkara_core::{AppError, AppResult, ExponentialBackoff};
 
-use crate::engine::PoolSymbol;
+use crate::{
+    engine::{LegOrdering, PoolSymbol},
+    executors::AlertType,
+};
 
 /// Main configuration for  trading operations.
 ///
@@ -16,17 +32,130 @@ use crate::engine::PoolSymbol;
 pub struct BotConfig {
     /// List of DEX pools to monitor for arbitrage opportunities
     pub pools: Vec<PoolConfig>,
-    /// Centralized exchange configuration for price feeds
-    pub cex: CexConfig,
+    /// Centralized exchange configurations for price feeds, a single one
+    /// accepted in place of a list
+    #[serde(deserialize_with = "one_or_many_cex")]
+    pub cex: Vec<CexConfig>,
+    /// Optional median price across the CEX feeds, replacing the price of
+    /// each exchange
+    pub price_aggregation: Option<AggregatedPriceFeedConfig>,
     /// Market making strategy parameters
     pub market_making: MarketMakingConfig,
+    /// Optional WebSocket endpoint rebroadcasting internal events and actions
+    pub event_stream: Option<EventStreamConfig>,
+    /// Optional webhook alerting for opportunities and feed outages
+    pub alerts: Option<AlertConfig>,
+    /// Optional hourly summary reports
+    pub reports: Option<ReportConfig>,
+    /// Optional append-only audit log of opportunities and suppressions
+    pub audit: Option<AuditConfig>,
+    /// Optional raw pool state snapshots for backtesting
+    pub snapshots: Option<SnapshotConfig>,
+    /// Optional transaction execution
+    pub execution: Option<ExecutionConfig>,
+    /// Optional on-chain and CEX balance tracking
+    pub balances: Option<BalanceConfig>,
+    /// Optional HTTP health and halt endpoints
+    pub health: Option<HealthConfig>,
+    /// Optional risk limits, enforced across all pools
+    pub risk: Option<RiskConfig>,
+    /// Optional trading fees, deducted from the profit of opportunities
+    pub fees: Option<FeeConfig>,
+    /// Number of prices kept in the in-memory price history of each pool
+    #[serde(default = "default_price_history_capacity")]
+    pub price_history_capacity: usize,
+    /// Collector liveness tracking
+    #[serde(default)]
+    pub liveness: LivenessConfig,
+    /// Log the trades the strategy would make and account them in a paper
+    /// ledger, which cannot be combined with live execution
+    #[serde(default)]
+    pub paper_trading: bool,
 }
 
+fn default_price_history_capacity() -> usize { 10_000 }
+
+impl BotConfig {
+    /// Checks what deserialization cannot: that the addresses of the pools
+    /// are checksummed addresses, that the market making parameters are
+    /// within their bounds, and that the fee caps of the execution are valid
+    /// amounts of wei.
+    pub fn validate(&self) -> AppResult<()> {
+        for pool in &self.pools {
+            pool.validate()?;
+        }
+        if let Some(execution) = &self.execution {
+            execution.validate()?;
+        }
+        self.market_making.validate()
+    }
+
+    /// Names the settings of a `reloaded` configuration which differ from
+    /// this one but only apply on a restart. A reload applies the spreads,
+    /// thresholds, factors, gas and capital of the market making parameters
+    /// only, and of the other sections the pools and CEX connections are
+    /// compared.
+    pub fn restart_required_changes(&self, reloaded: &BotConfig) -> Vec<&'static str> {
+        let (current, new) = (&self.market_making, &reloaded.market_making);
+        [
+            ("pools", self.pools != reloaded.pools),
+            ("cex", self.cex != reloaded.cex),
+            ("market_making.volatility_window", current.volatility_window != new.volatility_window),
+            ("market_making.use_twap", current.use_twap != new.use_twap),
+            ("market_making.twap_window_secs", current.twap_window_secs != new.twap_window_secs),
+            ("market_making.dex_ema_alpha", current.dex_ema_alpha != new.dex_ema_alpha),
+            ("market_making.export", current.export != new.export),
+            ("market_making.fill_simulation", current.fill_simulation != new.fill_simulation),
+        ]
+        .into_iter()
+        .filter_map(|(setting, changed)| changed.then_some(setting))
+        .collect()
+    }
+}
+
+/// Parses a checksummed address, naming what it is the address of on failure.
+fn checksummed_address(address: &str, what: &str) -> AppResult<Address> {
+    Address::parse_checksummed(address, None).map_err(|e| {
+        AppError::ConfigError(format!("invalid {} address {}: {}", what, address, e)).into()
+    })
+}
+
+/// Configuration of the collector liveness tracking.
+///
+/// A collector is considered down once its last event is older than
+/// `staleness_threshold_secs`. The last event age and the cumulative uptime
+/// and downtime are exported as metrics every `update_interval_secs`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct LivenessConfig {
+    #[serde(default = "default_staleness_threshold_secs")]
+    pub staleness_threshold_secs: u64,
+    #[serde(default = "default_liveness_update_interval_secs")]
+    pub update_interval_secs: u64,
+    /// Seconds without a ticker after which a CEX price feed is reported
+    /// down, pausing the strategies on its stale price, never if absent
+    #[serde(default)]
+    pub price_stale_after_secs: Option<u64>,
+}
+
+impl Default for LivenessConfig {
+    fn default() -> Self {
+        Self {
+            staleness_threshold_secs: default_staleness_threshold_secs(),
+            update_interval_secs: default_liveness_update_interval_secs(),
+            price_stale_after_secs: None,
+        }
+    }
+}
+
+fn default_staleness_threshold_secs() -> u64 { 30 }
+
+fn default_liveness_update_interval_secs() -> u64 { 5 }
+
 /// Configuration for a decentralized exchange pool.
 ///
 /// Represents a trading pool on a DEX that can be monitored for arbitrage
 /// opportunities against centralized exchange prices.
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize)]
 #[serde(tag = "dex", rename = "lowercase")]
 pub enum PoolConfig {
     /// Uniswap V4 pool configuration
@@ -52,46 +181,211 @@ pub enum PoolConfig {
         hook_address: Option<String>,
         /// Scaling factor for the pool, used for price calculations
         scaling: u8,
+        /// Interval the pool state is polled at, in milliseconds
+        #[serde(default = "default_pool_poll_interval_ms")]
+        poll_interval_ms: u64,
+        /// Interval the pool state is polled at while the CEX and DEX prices
+        /// diverge by half the arbitrage threshold or more, in milliseconds.
+        /// The pool is polled every `poll_interval_ms` throughout if absent
+        #[serde(default)]
+        fast_poll_interval_ms: Option<u64>,
+        /// What triggers a read of the pool state, a timer by default
+        #[serde(default)]
+        trigger: PoolTrigger,
+        /// Size of the base asset quoted on every read of the pool state, so
+        /// that the CEX price is compared to the prices a trade of that size
+        /// would execute at. Not quoted if absent
+        #[serde(default)]
+        quote_size: Option<Decimal>,
+        /// Contract address of the V4Quoter, required with `quote_size`
+        #[serde(default)]
+        quoter_address: Option<String>,
+    },
+    /// Uniswap V3 pool configuration, watched for prices only
+    #[serde(rename = "uniswapv3")]
+    UniswapV3 {
+        /// Contract address of the Uniswap V3 pool itself
+        address: String,
+        /// Trading pair symbol for this pool
+        symbol: PoolSymbol,
+        /// Token 0, meaning the first token decimal places
+        token_0: TokenConfig,
+        /// Token 1, meaning the second token decimal places
+        token_1: TokenConfig,
+        /// Fee tier of the pool, e.g. 500 for 0.05%
+        fee_tier: u32,
+        /// Node Url
+        node_url: String,
+        /// Scaling factor for the pool, used for price calculations
+        scaling: u8,
+        /// Interval the pool state is polled at, in milliseconds
+        #[serde(default = "default_pool_poll_interval_ms")]
+        poll_interval_ms: u64,
     },
 }
 
+/// What triggers a read of the state of a pool.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PoolTrigger {
+    /// Every `poll_interval_ms`, faster while the prices diverge if
+    /// `fast_poll_interval_ms` is set
+    #[default]
+    Timer,
+    /// Once per new block, announced over a subscription of the node, which
+    /// needs a websocket `node_url`. The pool is polled every
+    /// `poll_interval_ms` if the node cannot subscribe
+    Block,
+}
+
 /// Represnts Token configuration in a trading pool.
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
 pub struct TokenConfig {
     /// Address of the token contract
     pub address: String,
-    /// Number of decimal places for the token
+    /// Number of decimal places for the token, read from the token contract
+    /// if 0
     pub decimals: u8,
 }
 
+/// Decimals read from token contracts by address, fetched once per process.
+static TOKEN_DECIMALS: OnceLock<Mutex<HashMap<Address, u8>>> = OnceLock::new();
+
+impl TokenConfig {
+    /// Returns true if the decimals have to be read from the token contract,
+    /// which is requested by configuring them as 0.
+    pub fn auto_detect_decimals(&self) -> bool { self.decimals == 0 }
+
+    /// Reads the decimals of an ERC-20 token from its contract, the zero
+    /// address standing for the native currency with 18 decimals. The result
+    /// is cached for the lifetime of the process.
+    pub async fn fetch_decimals<P: Provider>(provider: &P, address: Address) -> AppResult<u8> {
+        if address == Address::ZERO {
+            return Ok(18);
+        }
+        let cache = TOKEN_DECIMALS.get_or_init(Default::default);
+        if let Some(decimals) = cache.lock().unwrap().get(&address) {
+            return Ok(*decimals);
+        }
+
+        let decimals = IERC20::new(address, provider).decimals().call().await?;
+        cache.lock().unwrap().insert(address, decimals);
+        Ok(decimals)
+    }
+}
+
+fn default_pool_poll_interval_ms() -> u64 { 5000 }
+
 impl PoolConfig {
+    /// Checks that the addresses of the pool, its tokens and its hook and
+    /// quoter if any are checksummed addresses.
+    pub fn validate(&self) -> AppResult<()> {
+        let symbol = self.symbol();
+        checksummed_address(self.address(), &format!("{} pool", symbol))?;
+        let (token_0, token_1) = match self {
+            PoolConfig::UniswapV4 { token_0, token_1, .. }
+            | PoolConfig::UniswapV3 { token_0, token_1, .. } => (token_0, token_1),
+        };
+        checksummed_address(&token_0.address, &format!("{} token 0", symbol))?;
+        checksummed_address(&token_1.address, &format!("{} token 1", symbol))?;
+        if let PoolConfig::UniswapV4 { hook_address, quoter_address, .. } = self {
+            if let Some(hook_address) = hook_address {
+                checksummed_address(hook_address, &format!("{} hook", symbol))?;
+            }
+            if let Some(quoter_address) = quoter_address {
+                checksummed_address(quoter_address, &format!("{} quoter", symbol))?;
+            }
+        }
+        Ok(())
+    }
+
     /// Returns the contract address of the pool.
     pub fn address(&self) -> &str {
         match self {
-            PoolConfig::UniswapV4 { address, .. } => address,
+            PoolConfig::UniswapV4 { address, .. } | PoolConfig::UniswapV3 { address, .. } => {
+                address
+            },
         }
     }
 
     /// Returns a reference to the trading pair symbol.
     pub fn symbol(&self) -> &PoolSymbol {
         match self {
-            PoolConfig::UniswapV4 { symbol, .. } => symbol,
+            PoolConfig::UniswapV4 { symbol, .. } | PoolConfig::UniswapV3 { symbol, .. } => symbol,
         }
     }
 
     /// Returns an owned copy of the trading pair symbol.
-    pub fn symbol_owned(&self) -> PoolSymbol {
+    pub fn symbol_owned(&self) -> PoolSymbol { self.symbol().clone() }
+
+    /// Returns the fee tier of the pool, in hundredths of a basis point.
+    pub fn fee_tier(&self) -> u32 {
         match self {
-            PoolConfig::UniswapV4 { symbol, .. } => symbol.clone(),
+            PoolConfig::UniswapV4 { fee_tier, .. } | PoolConfig::UniswapV3 { fee_tier, .. } => {
+                *fee_tier
+            },
         }
     }
+
+    /// Returns the interval the pool state is polled at.
+    pub fn poll_interval(&self) -> Duration {
+        match self {
+            PoolConfig::UniswapV4 { poll_interval_ms, .. }
+            | PoolConfig::UniswapV3 { poll_interval_ms, .. } => {
+                Duration::from_millis(*poll_interval_ms)
+            },
+        }
+    }
+
+    /// Returns the interval the pool state is polled at while an arbitrage
+    /// opportunity is building up, if polled adaptively.
+    pub fn fast_poll_interval(&self) -> Option<Duration> {
+        match self {
+            PoolConfig::UniswapV4 { fast_poll_interval_ms, .. } => {
+                fast_poll_interval_ms.map(Duration::from_millis)
+            },
+            PoolConfig::UniswapV3 { .. } => None,
+        }
+    }
+
+    /// Returns what triggers a read of the pool state, always a timer for
+    /// Uniswap V3 pools.
+    pub fn trigger(&self) -> PoolTrigger {
+        match self {
+            PoolConfig::UniswapV4 { trigger, .. } => *trigger,
+            PoolConfig::UniswapV3 { .. } => PoolTrigger::Timer,
+        }
+    }
+
+    /// Returns the size of the base asset the pool is quoted for along with
+    /// the address of the V4Quoter, if quoted.
+    ///
+    /// Fails if a quote size is set without the address of the quoter.
+    pub fn quote(&self) -> AppResult<Option<(Decimal, &str)>> {
+        match self {
+            PoolConfig::UniswapV4 { quote_size: Some(size), quoter_address, symbol, .. } => {
+                let address = quoter_address.as_deref().ok_or_else(|| {
+                    AppError::ConfigError(format!(
+                        "pool {} has a quote size but no quoter address",
+                        symbol
+                    ))
+                })?;
+                Ok(Some((*size, address)))
+            },
+            _ => Ok(None),
+        }
+    }
+
+    /// Returns true if opportunities on the pool can be executed, which is
+    /// only supported for Uniswap V4 pools.
+    pub fn supports_execution(&self) -> bool { matches!(self, PoolConfig::UniswapV4 { .. }) }
 }
 
 /// Configuration for centralized exchange connections.
 ///
 /// Defines which centralized exchange to connect to and how to establish
 /// the connection for receiving real-time price feeds.
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
 #[serde(tag = "exchange", rename = "lowercase")]
 pub enum CexConfig {
     /// Coinbase Pro WebSocket configuration
@@ -99,9 +393,154 @@ pub enum CexConfig {
     Coinbase {
         /// WebSocket URL for Coinbase Pro price feeds
         ws_url: String,
+        /// Keep reconnecting the websocket forever instead of giving up after
+        /// the default number of retries, for long-lived bots
+        #[serde(default)]
+        unlimited_reconnects: bool,
+        /// Backoff between reconnection attempts of the websocket
+        #[serde(default)]
+        reconnect: ReconnectConfig,
+        /// Credentials signing the subscriptions, unauthenticated if absent
+        #[serde(default)]
+        auth: Option<CoinbaseWsAuthConfig>,
+        /// Ticker channel to subscribe, `realtime` for every trade or
+        /// `batched` to reduce the message volume
+        #[serde(default)]
+        channel_mode: CoinbaseChannelMode,
+        /// Seconds without a heartbeat after which the feed is reported down
+        #[serde(default = "default_heartbeat_timeout_secs")]
+        heartbeat_timeout_secs: u64,
+    },
+    /// Binance Spot WebSocket configuration
+    #[serde(rename = "binance")]
+    Binance {
+        /// WebSocket URL for Binance Spot streams
+        ws_url: String,
+        /// Keep reconnecting the websocket forever instead of giving up after
+        /// the default number of retries, for long-lived bots
+        #[serde(default)]
+        unlimited_reconnects: bool,
+        /// Backoff between reconnection attempts of the websocket
+        #[serde(default)]
+        reconnect: ReconnectConfig,
     },
 }
 
+fn default_heartbeat_timeout_secs() -> u64 { 15 }
+
+/// Reads either a single CEX configuration or a non-empty list of them.
+fn one_or_many_cex<'de, D>(deserializer: D) -> Result<Vec<CexConfig>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(CexConfig),
+        Many(Vec<CexConfig>),
+    }
+
+    let configs = match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::One(config) => vec![config],
+        OneOrMany::Many(configs) => configs,
+    };
+    if configs.is_empty() {
+        return Err(serde::de::Error::custom("at least one CEX must be configured"));
+    }
+    Ok(configs)
+}
+
+/// Configuration of the median price across the CEX feeds, see
+/// [`crate::engine::AggregatedPriceFeed`].
+#[derive(Debug, Clone, Deserialize)]
+pub struct AggregatedPriceFeedConfig {
+    /// Number of exchanges that must have priced the pair before the median
+    /// is streamed
+    #[serde(default = "default_aggregation_min_sources")]
+    pub min_sources: usize,
+}
+
+fn default_aggregation_min_sources() -> usize { 2 }
+
+/// Credentials of the Coinbase Exchange websocket feed.
+///
+/// The API key, secret and passphrase are read from the environment variables
+/// they name.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct CoinbaseWsAuthConfig {
+    #[serde(default = "default_coinbase_ws_api_key_env")]
+    pub api_key_env: String,
+    #[serde(default = "default_coinbase_ws_api_secret_env")]
+    pub api_secret_env: String,
+    #[serde(default = "default_coinbase_ws_passphrase_env")]
+    pub passphrase_env: String,
+}
+
+fn default_coinbase_ws_api_key_env() -> String { "COINBASE_WS_API_KEY".to_string() }
+
+fn default_coinbase_ws_api_secret_env() -> String { "COINBASE_WS_API_SECRET".to_string() }
+
+fn default_coinbase_ws_passphrase_env() -> String { "COINBASE_WS_PASSPHRASE".to_string() }
+
+/// Backoff between reconnection attempts of a websocket, defaulting to 10
+/// retries doubling from 1 up to 60 seconds.
+///
+/// # Fields
+/// - `max_retries`: Number of reconnection attempts before giving up, ignored
+///   with unlimited reconnects.
+/// - `min_delay_secs` / `max_delay_secs`: Bounds of the delay between attempts.
+/// - `factor`: Factor the delay is multiplied by after each attempt.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct ReconnectConfig {
+    #[serde(default = "default_reconnect_max_retries")]
+    pub max_retries: u32,
+    #[serde(default = "default_reconnect_min_delay_secs")]
+    pub min_delay_secs: u32,
+    #[serde(default = "default_reconnect_max_delay_secs")]
+    pub max_delay_secs: u32,
+    #[serde(default = "default_reconnect_factor")]
+    pub factor: u32,
+}
+
+impl Default for ReconnectConfig {
+    fn default() -> Self {
+        Self {
+            max_retries: default_reconnect_max_retries(),
+            min_delay_secs: default_reconnect_min_delay_secs(),
+            max_delay_secs: default_reconnect_max_delay_secs(),
+            factor: default_reconnect_factor(),
+        }
+    }
+}
+
+impl ReconnectConfig {
+    /// Returns the backoff between reconnection attempts, which never runs out
+    /// of retries if `unlimited`.
+    pub fn backoff(&self, unlimited: bool) -> ExponentialBackoff {
+        match unlimited {
+            true => ExponentialBackoff::unlimited(
+                Duration::from_secs(self.min_delay_secs.into()),
+                Duration::from_secs(self.max_delay_secs.into()),
+                self.factor,
+            ),
+            false => ExponentialBackoff::new(
+                self.max_retries,
+                self.min_delay_secs,
+                self.max_delay_secs,
+                self.factor,
+            ),
+        }
+    }
+}
+
+fn default_reconnect_max_retries() -> u32 { 10 }
+
+fn default_reconnect_min_delay_secs() -> u32 { 1 }
+
+fn default_reconnect_max_delay_secs() -> u32 { 60 }
+
+fn default_reconnect_factor() -> u32 { 2 }
+
 /// Configuration for market making strategy parameters.
 ///
 /// # Fields
@@ -115,6 +554,30 @@ pub enum CexConfig {
 ///   cost calculations
 /// - `arbitrage_threshold_bps`: The threshold in basis points for triggering
 ///   during arbitrage opportunities.
+/// - `gas_units_per_trade`: Gas used by a trade placing the range.
+/// - `token_price_usd`: Price of the native token paying for gas, the gas cost
+///   is left out of the spreads unless set.
+/// - `volatility_window`: Number of CEX price samples the volatility is
+///   measured over.
+/// - `volatility_threshold`: Coefficient of variation of these samples above
+///   which the market is volatile and both spreads are widened.
+/// - `volatility_widen_factor`: Factor both spreads are widened by while the
+///   market is volatile, the arbitrage widen factor if absent.
+/// - `use_twap`: Use the time-weighted average of the CEX prices as the fair
+///   value rather than the last tick.
+/// - `twap_window_secs`: Window the CEX prices are averaged over.
+/// - `dex_ema_alpha`: Smoothing factor of the exponential moving average of the
+///   pool prices detecting arbitrage, the last pool price if absent.
+/// - `export`: Optional CSV export of every simulated range.
+/// - `acceptable_price_impact_bps`: Largest move of the pool price that trading
+///   the recommended size may cause, beyond which opportunities are suppressed.
+/// - `capital_usd`: Capital deposited in the simulated ranges, whose amounts
+///   are left out unless set.
+/// - `fill_simulation`: Optional simulated fills of the ranges by the CEX
+///   price.
+/// - `inventory_skew_bps`: Largest shift of the spreads towards the side
+///   rebalancing the tracked balances, reached once a single asset is held. The
+///   spreads are not skewed unless set and balances are tracked.
 #[derive(Debug, Clone, Deserialize)]
 pub struct MarketMakingConfig {
     pub base_spread_bps: u32,
@@ -124,10 +587,704 @@ pub struct MarketMakingConfig {
     pub arbitrage_tighten_factor: rust_decimal::Decimal,
     pub arbitrage_widen_factor: rust_decimal::Decimal,
     pub arbitrage_threshold_bps: u32,
+    #[serde(default = "default_gas_units_per_trade")]
+    pub gas_units_per_trade: u64,
+    #[serde(default)]
+    pub token_price_usd: rust_decimal::Decimal,
+    #[serde(default = "default_volatility_window")]
+    pub volatility_window: usize,
+    #[serde(default = "default_volatility_threshold")]
+    pub volatility_threshold: rust_decimal::Decimal,
+    #[serde(default)]
+    pub volatility_widen_factor: Option<rust_decimal::Decimal>,
+    #[serde(default)]
+    pub use_twap: bool,
+    #[serde(default = "default_twap_window_secs")]
+    pub twap_window_secs: u64,
+    #[serde(default)]
+    pub dex_ema_alpha: Option<rust_decimal::Decimal>,
+    #[serde(default)]
+    pub export: Option<SimulationExportConfig>,
+    #[serde(default = "default_acceptable_price_impact_bps")]
+    pub acceptable_price_impact_bps: u32,
+    #[serde(default)]
+    pub capital_usd: rust_decimal::Decimal,
+    #[serde(default)]
+    pub fill_simulation: Option<FillSimulationConfig>,
+    #[serde(default)]
+    pub inventory_skew_bps: u32,
+}
+
+fn default_gas_units_per_trade() -> u64 { 150_000 }
+
+fn default_acceptable_price_impact_bps() -> u32 { 50 }
+
+impl MarketMakingConfig {
+    /// Checks that the base spread is within the minimum and maximum spreads,
+    /// that the arbitrage and volatility factors tighten and widen the
+    /// spreads, and that the gas price is positive.
+    pub fn validate(&self) -> AppResult<()> {
+        if self.min_spread_bps > self.base_spread_bps || self.base_spread_bps > self.max_spread_bps
+        {
+            return Err(AppError::ConfigError(format!(
+                "the base spread of {} bps is not within the minimum spread of {} bps and the \
+                 maximum spread of {} bps",
+                self.base_spread_bps, self.min_spread_bps, self.max_spread_bps
+            ))
+            .into());
+        }
+        if self.arbitrage_tighten_factor >= Decimal::ONE
+            || self.arbitrage_widen_factor <= Decimal::ONE
+        {
+            return Err(AppError::ConfigError(format!(
+                "the arbitrage tighten factor {} must be below 1 and the widen factor {} above",
+                self.arbitrage_tighten_factor, self.arbitrage_widen_factor
+            ))
+            .into());
+        }
+        if let Some(factor) = self.volatility_widen_factor.filter(|f| *f <= Decimal::ONE) {
+            return Err(AppError::ConfigError(format!(
+                "the volatility widen factor {} must be above 1",
+                factor
+            ))
+            .into());
+        }
+        if self.gas_price <= Decimal::ZERO {
+            return Err(AppError::ConfigError(format!(
+                "the gas price must be positive, got {}",
+                self.gas_price
+            ))
+            .into());
+        }
+        if let Some(fills) = &self.fill_simulation {
+            if fills.clip_size <= Decimal::ZERO
+                || fills.max_inventory <= Decimal::ZERO
+                || fills.summary_interval == 0
+            {
+                return Err(AppError::ConfigError(format!(
+                    "the simulated fills need a positive clip size, maximum inventory and summary \
+                     interval, got {}, {} and {}",
+                    fills.clip_size, fills.max_inventory, fills.summary_interval
+                ))
+                .into());
+            }
+        }
+        Ok(())
+    }
+
+    /// Cost in USD of the gas of a trade, zero unless `token_price_usd` is set.
+    pub fn gas_cost_usd(&self) -> rust_decimal::Decimal {
+        let gas_price = self.gas_price * Decimal::new(1, 9); // gwei to native token
+        gas_price * Decimal::from(self.gas_units_per_trade) * self.token_price_usd
+    }
+}
+
+fn default_volatility_window() -> usize { 20 }
+
+fn default_volatility_threshold() -> rust_decimal::Decimal { dec!(0.02) }
+
+fn default_twap_window_secs() -> u64 { 60 }
+
+/// Configuration for the CSV export of the market making simulation.
+///
+/// When present, every simulated range is appended to
+/// `<output_dir>/mm-<SYMBOL>-YYYY-MM-DD.csv`, rotated daily.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct SimulationExportConfig {
+    /// Directory the CSV files are written to
+    #[serde(default = "default_export_output_dir")]
+    pub output_dir: String,
+    /// Number of ranges queued for the writer before ranges are dropped
+    #[serde(default = "default_export_queue_size")]
+    pub queue_size: usize,
+    /// Interval at which the CSV files are flushed
+    #[serde(default = "default_export_flush_interval_secs")]
+    pub flush_interval_secs: u64,
+}
+
+fn default_export_output_dir() -> String { "simulation".to_string() }
+
+fn default_export_queue_size() -> usize { 4096 }
+
+fn default_export_flush_interval_secs() -> u64 { 5 }
+
+/// Configuration for the simulated fills of the market making ranges.
+///
+/// When present, a CEX price at or beyond the bid or the ask of the last range
+/// fills it by `clip_size`, and the resulting position is logged every
+/// `summary_interval` price updates.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct FillSimulationConfig {
+    /// Size of every fill, in the base asset
+    pub clip_size: Decimal,
+    /// Largest long or short inventory in the base asset, fills being cut to
+    /// stay within it
+    pub max_inventory: Decimal,
+    /// Number of price updates between two position summaries
+    #[serde(default = "default_fill_summary_interval")]
+    pub summary_interval: u64,
+}
+
+fn default_fill_summary_interval() -> u64 { 100 }
+
+/// Configuration for the external event stream endpoint.
+///
+/// When present, the bot serves every internal event and action as JSON on
+/// `ws://<listen_addr>/ws/events`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct EventStreamConfig {
+    /// Socket address to listen on, e.g. `127.0.0.1:9001`
+    pub listen_addr: String,
+    /// Number of messages buffered per client before messages are dropped
+    #[serde(default = "default_client_queue_size")]
+    pub client_queue_size: usize,
+}
+
+fn default_client_queue_size() -> usize { 1024 }
+
+/// Configuration for the health endpoint.
+///
+/// When present, the bot serves its health on `http://<listen_addr>/health`
+/// and lets operators clear a trading halt with `DELETE /api/halt`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct HealthConfig {
+    /// Socket address to listen on, e.g. `127.0.0.1:9002`
+    pub listen_addr: String,
+}
+
+/// Configuration of the risk limits.
+///
+/// The profit and loss of every UTC day, realized and unrealized, is tracked
+/// across all pools. Trading halts once the loss of the day exceeds
+/// `max_daily_loss`, until an operator clears the halt.
+#[derive(Debug, Clone, Deserialize)]
+pub struct RiskConfig {
+    /// Loss in the quote asset tolerated per day
+    pub max_daily_loss: Decimal,
+    /// Hour of the day, UTC, the daily profit and loss resets at
+    #[serde(default)]
+    pub day_boundary_hour: u8,
+}
+
+/// Configuration of the trading fees.
+///
+/// An arbitrage round trip pays the CEX taker fee, the LP fee of the pool
+/// given by its fee tier, and the gas of the swap priced from the market
+/// making configuration. Opportunities are only signalled once their profit
+/// net of these fees exceeds `min_profit_usd`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct FeeConfig {
+    /// Taker fee of the CEX in basis points, e.g. 60 for 0.6%
+    pub cex_taker_bps: Decimal,
+    /// Net profit in USD an opportunity must exceed
+    #[serde(default)]
+    pub min_profit_usd: Decimal,
+}
+
+/// Configuration for the hourly summary reports.
+///
+/// When present, a JSON report per hour is written to
+/// `<output_dir>/YYYY-MM-DDTHH.json`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ReportConfig {
+    /// Directory the reports are written to
+    #[serde(default = "default_report_output_dir")]
+    pub output_dir: String,
+}
+
+fn default_report_output_dir() -> String { "reports".to_string() }
+
+/// Configuration for the opportunity audit log.
+///
+/// When present, every opportunity and suppression decision is appended to
+/// `<output_dir>/opportunities-YYYY-MM-DD.jsonl`, rotated daily.
+#[derive(Debug, Clone, Deserialize)]
+pub struct AuditConfig {
+    /// Directory the audit log is written to
+    #[serde(default = "default_audit_output_dir")]
+    pub output_dir: String,
+}
+
+fn default_audit_output_dir() -> String { "audit".to_string() }
+
+/// Configuration for the raw pool state snapshots.
+///
+/// When present, every fetched `slot0` state is pinned to its block, includes
+/// the pool liquidity and is appended to
+/// `<output_dir>/slot0-<SYMBOL>-YYYY-MM-DD.jsonl`, rotated daily.
+#[derive(Debug, Clone, Deserialize)]
+pub struct SnapshotConfig {
+    /// Directory the snapshots are written to
+    #[serde(default = "default_snapshot_output_dir")]
+    pub output_dir: String,
+    /// Number of states queued for the writer before states are dropped
+    #[serde(default = "default_snapshot_queue_size")]
+    pub queue_size: usize,
+    /// Interval at which the snapshot files are flushed
+    #[serde(default = "default_snapshot_flush_interval_secs")]
+    pub flush_interval_secs: u64,
+}
+
+fn default_snapshot_output_dir() -> String { "snapshots".to_string() }
+
+fn default_snapshot_queue_size() -> usize { 1024 }
+
+fn default_snapshot_flush_interval_secs() -> u64 { 5 }
+
+/// Configuration of the balance tracking.
+///
+/// The balances of `tokens` held by `addresses` are read from `rpc_url`
+/// every `poll_interval_secs`, a token at the zero address being the native
+/// currency. The Coinbase balances of the same assets are read as well when
+/// coinbase credentials are configured for execution.
+#[derive(Debug, Clone, Deserialize)]
+pub struct BalanceConfig {
+    pub rpc_url: String,
+    pub addresses: Vec<String>,
+    pub tokens: Vec<BalanceTokenConfig>,
+    #[serde(default = "default_balance_poll_interval_secs")]
+    pub poll_interval_secs: u64,
+}
+
+fn default_balance_poll_interval_secs() -> u64 { 30 }
+
+/// A token whose balances are tracked.
+#[derive(Debug, Clone, Deserialize)]
+pub struct BalanceTokenConfig {
+    /// Asset symbol, as named by the CEX
+    pub asset: String,
+    #[serde(flatten)]
+    pub token: TokenConfig,
+}
+
+/// Configuration of the transaction execution.
+///
+/// Transactions are signed by `signer` for the chain `chain_id` and submitted
+/// through `rpc_url`. In [`ExecutionMode::Live`] the bot refuses to start
+/// without an explicitly configured signer.
+///
+/// # Fields
+/// - `max_fee_per_gas_gwei` / `max_priority_fee_per_gas_gwei`: Caps of the
+///   estimated fees, in gwei.
+/// - `trade_size`: Size traded on each leg of an opportunity, in the base
+///   asset. No orders are placed while it is zero.
+/// - `coinbase`: Credentials placing the CEX leg, required in
+///   [`ExecutionMode::Live`].
+#[derive(Debug, Clone, Deserialize)]
+pub struct ExecutionConfig {
+    #[serde(default)]
+    pub mode: ExecutionMode,
+    pub signer: Option<SignerConfig>,
+    pub chain_id: u64,
+    pub rpc_url: String,
+    #[serde(default = "default_max_fee_per_gas_gwei")]
+    pub max_fee_per_gas_gwei: Decimal,
+    #[serde(default = "default_max_priority_fee_per_gas_gwei")]
+    pub max_priority_fee_per_gas_gwei: Decimal,
+    /// Number of recent blocks the fees are picked from
+    #[serde(default = "default_fee_history_blocks")]
+    pub fee_history_blocks: u64,
+    /// Fee increase of every replacement of a stuck transaction, at least 10
+    #[serde(default = "default_fee_bump_percent")]
+    pub fee_bump_percent: u128,
+    #[serde(default = "default_max_replacements")]
+    pub max_replacements: u32,
+    /// How signed transactions reach the chain
+    #[serde(default)]
+    pub submission: SubmissionConfig,
+    #[serde(default)]
+    pub trade_size: Decimal,
+    pub coinbase: Option<CoinbaseTradeConfig>,
+    pub dex: Option<DexExecutionConfig>,
+    /// Order the two legs of opportunities are executed in
+    #[serde(default)]
+    pub leg_ordering: LegOrdering,
+    /// Time the CEX leg is given to fill before the plan is aborted
+    #[serde(default = "default_cex_leg_timeout_secs")]
+    pub cex_leg_timeout_secs: u64,
+    /// Time the DEX leg is given to be mined before the plan is aborted,
+    /// longer than the swaps may take to be confirmed or time out
+    #[serde(default = "default_dex_leg_timeout_secs")]
+    pub dex_leg_timeout_secs: u64,
+    /// Size of the base asset earlier opportunities may leave unhedged before
+    /// new ones are blocked, unlimited if unset
+    pub max_unhedged_size: Option<Decimal>,
+    /// Age the oldest quote of an opportunity may reach before it expires
+    /// instead of being executed, unlimited if unset
+    pub max_opportunity_age_ms: Option<u64>,
+    /// Spread the latest prices must still offer in the direction of an
+    /// opportunity before it is executed, not re-validated if unset
+    pub revalidate_min_spread_bps: Option<Decimal>,
+    /// Evaluation of dry run opportunities against the prices which followed
+    #[serde(default)]
+    pub hindsight: HindsightConfig,
+    /// Token approvals of the DEX leg, checked at startup in live mode
+    pub approvals: Option<ApprovalConfig>,
+    /// Submits the missing token approvals at startup instead of failing
+    #[serde(default)]
+    pub auto_approve: bool,
+}
+
+fn default_max_fee_per_gas_gwei() -> Decimal { dec!(100) }
+
+fn default_max_priority_fee_per_gas_gwei() -> Decimal { dec!(2) }
+
+fn default_fee_history_blocks() -> u64 { 10 }
+
+fn default_fee_bump_percent() -> u128 { 15 }
+
+fn default_max_replacements() -> u32 { 3 }
+
+fn default_cex_leg_timeout_secs() -> u64 { 10 }
+
+fn default_dex_leg_timeout_secs() -> u64 { 90 }
+
+impl ExecutionConfig {
+    /// Checks that the fee caps are valid amounts of wei, and that the legs
+    /// are given time to fill and the DEX leg longer than its swaps may take
+    /// to be confirmed or time out.
+    pub fn validate(&self) -> AppResult<()> {
+        self.fee_caps()?;
+        if self.cex_leg_timeout_secs == 0 {
+            return Err(
+                AppError::ConfigError("the cex leg timeout must be positive".to_string()).into()
+            );
+        }
+        if let Some(dex) = &self.dex {
+            let window = dex.swap_window();
+            if Duration::from_secs(self.dex_leg_timeout_secs) <= window {
+                return Err(AppError::ConfigError(format!(
+                    "the dex leg timeout of {}s must exceed the {:?} its swaps may take to be \
+                     confirmed or time out",
+                    self.dex_leg_timeout_secs, window
+                ))
+                .into());
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the fee caps in wei, failing for a negative cap or one too large
+    /// to be converted to wei rather than capping the fees at 0.
+    pub fn fee_caps(&self) -> AppResult<FeeCaps> {
+        let wei = |name: &str, gwei: Decimal| {
+            gwei.checked_mul(dec!(1_000_000_000))
+                .and_then(|wei| wei.to_u128())
+                .ok_or_else(|| AppError::ConfigError(format!("invalid {} of {} gwei", name, gwei)))
+        };
+        Ok(FeeCaps {
+            max_fee_per_gas: wei("max_fee_per_gas_gwei", self.max_fee_per_gas_gwei)?,
+            max_priority_fee_per_gas: wei(
+                "max_priority_fee_per_gas_gwei",
+                self.max_priority_fee_per_gas_gwei,
+            )?,
+        })
+    }
+
+    /// Returns the estimator picking the fees of submitted transactions.
+    pub fn fee_estimator(&self) -> AppResult<FeeEstimator> {
+        Ok(FeeEstimator::new(self.fee_caps()?)
+            .with_block_count(self.fee_history_blocks)
+            .with_escalation(EscalationPolicy {
+                bump_percent: self.fee_bump_percent,
+                max_replacements: self.max_replacements,
+            }))
+    }
+}
+
+/// Credentials of the Coinbase Advanced Trade API.
+///
+/// The API key and secret are read from the environment variables they name.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct CoinbaseTradeConfig {
+    #[serde(default = "default_coinbase_api_url")]
+    pub api_url: String,
+    #[serde(default = "default_coinbase_api_key_env")]
+    pub api_key_env: String,
+    #[serde(default = "default_coinbase_api_secret_env")]
+    pub api_secret_env: String,
+}
+
+fn default_coinbase_api_url() -> String { "https://api.coinbase.com".to_string() }
+
+fn default_coinbase_api_key_env() -> String { "COINBASE_API_KEY".to_string() }
+
+fn default_coinbase_api_secret_env() -> String { "COINBASE_API_SECRET".to_string() }
+
+/// Swaps of the DEX leg of opportunities through the Universal Router.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct DexExecutionConfig {
+    pub router_address: String,
+    /// Maximum slippage accepted over the DEX price of an opportunity
+    #[serde(default = "default_max_slippage_bps")]
+    pub max_slippage_bps: u32,
+    /// Swaps revert once this long has passed since they were built
+    #[serde(default = "default_swap_deadline_secs")]
+    pub deadline_secs: u64,
+    /// Blocks, including its own, on top of a swap before it is confirmed
+    #[serde(default = "default_swap_confirmations")]
+    pub confirmations: u64,
+    /// Swaps not mined within this many blocks are reported as timed out
+    #[serde(default = "default_swap_confirm_timeout_blocks")]
+    pub confirm_timeout_blocks: u64,
+    /// Interval at which the receipts of submitted swaps are polled
+    #[serde(default = "default_receipt_poll_interval_ms")]
+    pub receipt_poll_interval_ms: u64,
+    /// Time between two blocks of the chain
+    #[serde(default = "default_block_time_ms")]
+    pub block_time_ms: u64,
+}
+
+fn default_max_slippage_bps() -> u32 { 50 }
+
+fn default_block_time_ms() -> u64 { 2000 }
+
+impl DexExecutionConfig {
+    /// Returns the longest a swap may take to be confirmed, mined by its
+    /// deadline, or to time out, plus a poll of its receipt.
+    pub fn swap_window(&self) -> Duration {
+        let block_time = Duration::from_millis(self.block_time_ms);
+        let confirmed =
+            Duration::from_secs(self.deadline_secs) + block_time * self.confirmations as u32;
+        let timed_out = block_time * self.confirm_timeout_blocks as u32;
+        confirmed.max(timed_out) + Duration::from_millis(self.receipt_poll_interval_ms)
+    }
+}
+
+/// Token approvals the swaps of the DEX leg depend on.
+///
+/// The Universal Router pulls ERC20 inputs through Permit2, so every token
+/// must approve Permit2, which in turn must approve the router. Missing
+/// approvals fail the startup with the transactions to send, unless
+/// `auto_approve` is enabled, in which case they are submitted for `cap` and
+/// confirmed before the bot starts. Unlimited allowances are cached in
+/// `cache_path`, so they are not checked on every start, as swaps never spend
+/// them.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ApprovalConfig {
+    pub tokens: Vec<ApprovalTokenConfig>,
+    #[serde(default = "default_permit2_address")]
+    pub permit2_address: String,
+    /// Validity of the Permit2 allowances approved
+    #[serde(default = "default_permit2_expiration_secs")]
+    pub permit2_expiration_secs: u64,
+    /// Approvals not mined within this long fail the startup
+    #[serde(default = "default_approval_confirm_timeout_secs")]
+    pub confirm_timeout_secs: u64,
+    #[serde(default = "default_approval_cache_path")]
+    pub cache_path: String,
+}
+
+fn default_permit2_address() -> String { PERMIT2_ADDRESS.to_string() }
+
+fn default_permit2_expiration_secs() -> u64 { 30 * 24 * 60 * 60 }
+
+fn default_approval_confirm_timeout_secs() -> u64 { 120 }
+
+fn default_approval_cache_path() -> String { "approvals.json".to_string() }
+
+/// A token swapped through the router.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ApprovalTokenConfig {
+    #[serde(flatten)]
+    pub token: TokenConfig,
+    /// Amount approved, in the token
+    pub cap: Decimal,
+    /// Allowance below which the token is approved again, defaults to the cap
+    pub min_allowance: Option<Decimal>,
+}
+
+fn default_swap_deadline_secs() -> u64 { 30 }
+
+fn default_swap_confirmations() -> u64 { 2 }
+
+fn default_swap_confirm_timeout_blocks() -> u64 { 30 }
+
+fn default_receipt_poll_interval_ms() -> u64 { 1000 }
+
+/// Whether transactions are actually submitted.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExecutionMode {
+    /// Opportunities are only simulated
+    #[default]
+    DryRun,
+    /// Transactions are signed and submitted
+    Live,
+}
+
+/// Signing identity of the bot.
+///
+/// Key material is never part of the configuration file, the private key or
+/// the keystore password are read from the environment variables they name.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SignerConfig {
+    /// Hex encoded private key
+    PrivateKey {
+        /// Environment variable holding the private key
+        #[serde(default = "default_signer_key_env")]
+        key_env: String,
+    },
+    /// Encrypted JSON keystore file
+    Keystore {
+        /// Path of the keystore file
+        path: String,
+        /// Environment variable holding the keystore password
+        #[serde(default = "default_keystore_password_env")]
+        password_env: String,
+    },
+}
+
+/// Backend signed transactions are submitted through.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(tag = "backend", rename_all = "snake_case")]
+pub enum SubmissionConfig {
+    /// Public mempool of the execution RPC
+    #[default]
+    Public,
+    /// Flashbots style relay, keeping transactions out of the public mempool
+    /// until they are included
+    PrivateRelay {
+        relay_url: String,
+        /// Blocks the relay is given to include a transaction before it is
+        /// published
+        #[serde(default = "default_fallback_after_blocks")]
+        fallback_after_blocks: u64,
+        /// Environment variable holding the key signing relay requests,
+        /// defaults to the transaction signer
+        auth_key_env: Option<String>,
+    },
+}
+
+fn default_fallback_after_blocks() -> u64 { 3 }
+
+/// Evaluation of opportunities executed on paper.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct HindsightConfig {
+    /// Time both legs are assumed to take to execute
+    #[serde(default = "default_hindsight_latency_ms")]
+    pub latency_ms: u64,
+    /// Time after detection opportunities are evaluated, watching whether
+    /// they revert in the meantime
+    #[serde(default = "default_hindsight_window_secs")]
+    pub window_secs: u64,
+}
+
+impl Default for HindsightConfig {
+    fn default() -> Self {
+        Self {
+            latency_ms: default_hindsight_latency_ms(),
+            window_secs: default_hindsight_window_secs(),
+        }
+    }
+}
+
+fn default_hindsight_latency_ms() -> u64 { 500 }
+
+fn default_hindsight_window_secs() -> u64 { 10 }
+
+fn default_signer_key_env() -> String { "SIKARRA_SIGNER_KEY".to_string() }
+
+fn default_keystore_password_env() -> String { "SIKARRA_KEYSTORE_PASSWORD".to_string() }
+
+/// Configuration for webhook alerting.
+///
+/// # Fields
+/// - `webhooks`: Webhooks alerts are delivered to.
+/// - `telegram`: Optional Telegram chat alerts are mirrored to, which also
+///   accepts `/status` and `/mute` commands.
+/// - `min_opportunity_net_bps`: Minimum net profit in basis points for an
+///   opportunity to be alerted.
+/// - `rate_limit_max_alerts`: Maximum number of alerts of the same type within
+///   `rate_limit_period_secs`, shared across all pools.
+/// - `max_retries`: Number of retries on server errors, 0 disables retries.
+/// - `retry_min_delay_secs` / `retry_max_delay_secs`: Bounds of the exponential
+///   backoff between retries.
+#[derive(Debug, Clone, Deserialize)]
+pub struct AlertConfig {
+    #[serde(default)]
+    pub webhooks: Vec<WebhookConfig>,
+    pub telegram: Option<TelegramConfig>,
+    #[serde(default = "default_min_opportunity_net_bps")]
+    pub min_opportunity_net_bps: Decimal,
+    #[serde(default = "default_rate_limit_max_alerts")]
+    pub rate_limit_max_alerts: usize,
+    #[serde(default = "default_rate_limit_period_secs")]
+    pub rate_limit_period_secs: u64,
+    #[serde(default = "default_max_retries")]
+    pub max_retries: u8,
+    #[serde(default = "default_retry_min_delay_secs")]
+    pub retry_min_delay_secs: u32,
+    #[serde(default = "default_retry_max_delay_secs")]
+    pub retry_max_delay_secs: u32,
+}
+
+fn default_min_opportunity_net_bps() -> Decimal { dec!(50) }
+
+fn default_rate_limit_max_alerts() -> usize { 5 }
+
+fn default_rate_limit_period_secs() -> u64 { 300 }
+
+fn default_max_retries() -> u8 { 3 }
+
+fn default_retry_min_delay_secs() -> u32 { 1 }
+
+fn default_retry_max_delay_secs() -> u32 { 30 }
+
+/// A webhook alerts are POSTed to.
+#[derive(Debug, Clone, Deserialize)]
+pub struct WebhookConfig {
+    /// Webhook URL
+    pub url: String,
+    /// Payload format expected by the webhook
+    #[serde(default)]
+    pub format: WebhookFormat,
+    /// Alert types delivered to this webhook, all types if omitted
+    pub events: Option<Vec<AlertType>>,
+}
+
+/// A Telegram chat alerts are mirrored to.
+///
+/// The bot token is a secret and is therefore read from the environment
+/// variable named by `bot_token_env` rather than from the configuration file.
+#[derive(Debug, Clone, Deserialize)]
+pub struct TelegramConfig {
+    /// Environment variable holding the bot token
+    #[serde(default = "default_telegram_bot_token_env")]
+    pub bot_token_env: String,
+    /// Chat alerts are sent to, commands from other chats are ignored
+    pub chat_id: i64,
+    /// Base URL of the Telegram Bot API
+    #[serde(default = "default_telegram_api_url")]
+    pub api_url: String,
+    /// Long polling timeout of `getUpdates` requests
+    #[serde(default = "default_telegram_poll_timeout_secs")]
+    pub poll_timeout_secs: u64,
+}
+
+fn default_telegram_bot_token_env() -> String { "TELEGRAM_BOT_TOKEN".to_string() }
+
+fn default_telegram_api_url() -> String { "https://api.telegram.org".to_string() }
+
+fn default_telegram_poll_timeout_secs() -> u64 { 30 }
+
+/// Payload format of a webhook.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WebhookFormat {
+    /// Structured JSON payload with a `text` summary and the full `alert`
+    #[default]
+    Json,
+    /// Slack incoming webhook payload
+    Slack,
+    /// Discord webhook payload
+    Discord,
 }
 
 #[cfg(test)]
 mod tests {
+    use alloy::{primitives::address, providers::ProviderBuilder, transports::mock::Asserter};
     use serde_json::json;
 
     use super::*;
@@ -182,17 +1339,48 @@ mod tests {
             hook_address,
             tick_spacing,
             scaling,
-        } = &config.pools[0];
+            poll_interval_ms,
+            fast_poll_interval_ms,
+            trigger,
+            quote_size,
+            quoter_address,
+        } = &config.pools[0]
+        else {
+            panic!("expected a Uniswap V4 pool");
+        };
         assert_eq!(address, "0x1234567890abcdef1234567890abcdef12345678");
-        assert_eq!(*symbol, PoolSymbol::EthUsdc);
+        assert_eq!(*symbol, PoolSymbol::ETH_USDC);
         assert_eq!(token_0.decimals, 18);
         assert_eq!(token_1.decimals, 6);
         assert_eq!(*fee_tier, 500);
         assert_eq!(*tick_spacing, 10);
         assert_eq!(node_url, "https://mainnet.infura.io/v3/YOUR_INFURA_PROJECT_ID");
         assert_eq!(*scaling, 2);
-        let CexConfig::Coinbase { ws_url } = &config.cex;
+        assert_eq!(*poll_interval_ms, 5000);
+        assert_eq!(*fast_poll_interval_ms, None);
+        assert_eq!(*trigger, PoolTrigger::Timer);
+        assert_eq!((*quote_size, quoter_address.as_deref()), (None, None));
+        assert!(config.pools[0].quote().unwrap().is_none());
+        let CexConfig::Coinbase {
+            ws_url,
+            unlimited_reconnects,
+            reconnect,
+            auth,
+            channel_mode,
+            heartbeat_timeout_secs,
+        } = &config.cex[0]
+        else {
+            panic!("expected a Coinbase feed");
+        };
         assert_eq!(ws_url, "wss://ws-feed.pro.coinbase.com");
+        assert!(!unlimited_reconnects);
+        assert_eq!(*reconnect, ReconnectConfig::default());
+        assert!(auth.is_none());
+        assert_eq!(*channel_mode, CoinbaseChannelMode::Realtime);
+        assert_eq!(*heartbeat_timeout_secs, 15);
+        assert_eq!(config.cex.len(), 1);
+        assert!(config.price_aggregation.is_none());
+        assert!(!config.paper_trading);
         let market_making = config.market_making;
         assert_eq!(market_making.base_spread_bps, 50);
         assert_eq!(market_making.max_spread_bps, 100);
@@ -201,5 +1389,665 @@ mod tests {
         assert_eq!(market_making.arbitrage_threshold_bps, 100);
         assert_eq!(market_making.arbitrage_tighten_factor.to_string(), "0.7");
         assert_eq!(market_making.arbitrage_widen_factor.to_string(), "1.3");
+        assert_eq!(market_making.gas_units_per_trade, 150_000);
+        assert!(market_making.token_price_usd.is_zero());
+        assert_eq!(market_making.volatility_window, 20);
+        assert_eq!(market_making.volatility_threshold.to_string(), "0.02");
+        assert!(market_making.volatility_widen_factor.is_none());
+        assert!(!market_making.use_twap);
+        assert_eq!(market_making.twap_window_secs, 60);
+        assert!(market_making.dex_ema_alpha.is_none());
+        assert_eq!(market_making.acceptable_price_impact_bps, 50);
+        assert!(market_making.export.is_none());
+        assert!(market_making.fill_simulation.is_none());
+        assert!(config.event_stream.is_none());
+        assert!(config.alerts.is_none());
+        assert!(config.reports.is_none());
+        assert!(config.audit.is_none());
+        assert!(config.snapshots.is_none());
+        assert!(config.execution.is_none());
+        assert!(config.balances.is_none());
+        assert!(config.health.is_none());
+        assert!(config.risk.is_none());
+        assert_eq!(config.price_history_capacity, 10_000);
+        assert_eq!(config.liveness.staleness_threshold_secs, 30);
+        assert_eq!(config.liveness.update_interval_secs, 5);
+        assert_eq!(config.liveness.price_stale_after_secs, None);
+    }
+
+    #[tokio::test]
+    async fn token_decimals_are_fetched_once() {
+        let asserter = Asserter::new();
+        asserter.push_success(&format!("0x{:064x}", 6));
+        let provider = ProviderBuilder::new()
+            .disable_recommended_fillers()
+            .connect_mocked_client(asserter.clone());
+        let usdc = address!("0x833589fCD6eDb6E08f4c7C32D4f71b54bdA02913");
+
+        assert_eq!(TokenConfig::fetch_decimals(&provider, usdc).await.unwrap(), 6);
+        // Served from the cache, the provider has no response left
+        assert_eq!(TokenConfig::fetch_decimals(&provider, usdc).await.unwrap(), 6);
+        assert_eq!(
+            TokenConfig::fetch_decimals(&provider, Address::ZERO)
+                .await
+                .unwrap(),
+            18
+        );
+
+        let config: TokenConfig = serde_json::from_value(
+            json!({ "address": "0x833589fCD6eDb6E08f4c7C32D4f71b54bdA02913", "decimals": 0 }),
+        )
+        .unwrap();
+        assert!(config.auto_detect_decimals());
+    }
+
+    #[test]
+    fn uniswap_v3_pool_config_deserialization() {
+        let config: PoolConfig = serde_json::from_value(json!({
+            "dex": "uniswapv3",
+            "address": "0x88e6A0c2dDD26FEEb64F039a2c41296FcB3f5640",
+            "symbol": "ETH-USDC",
+            "token_0": { "address": "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48", "decimals": 6 },
+            "token_1": { "address": "0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2", "decimals": 18 },
+            "fee_tier": 500,
+            "scaling": 2,
+            "node_url": "https://mainnet.infura.io/v3/YOUR_INFURA_PROJECT_ID",
+            "poll_interval_ms": 1000
+        }))
+        .unwrap();
+        assert_eq!(config.address(), "0x88e6A0c2dDD26FEEb64F039a2c41296FcB3f5640");
+        assert_eq!(*config.symbol(), PoolSymbol::ETH_USDC);
+        assert!(!config.supports_execution());
+        assert_eq!(config.poll_interval(), Duration::from_secs(1));
+        assert_eq!(config.fast_poll_interval(), None);
+        assert_eq!(config.trigger(), PoolTrigger::Timer);
+        let PoolConfig::UniswapV3 { fee_tier, token_1, .. } = &config else {
+            panic!("expected a Uniswap V3 pool");
+        };
+        assert_eq!(*fee_tier, 500);
+        assert_eq!(token_1.decimals, 18);
+    }
+
+    #[test]
+    fn pool_symbol_deserialization() {
+        let symbol: PoolSymbol = serde_json::from_value(json!("ETH-USDC")).unwrap();
+        assert_eq!(symbol, PoolSymbol::ETH_USDC);
+        // The symbol cbBTC was configured under before following the base asset
+        let symbol: PoolSymbol = serde_json::from_value(json!("USDC-cbBTC")).unwrap();
+        assert_eq!(symbol, PoolSymbol::USDC_CBBTC);
+        assert_eq!(symbol.base_asset(), "BTC");
+
+        // Any pair can be configured
+        let symbol: PoolSymbol = serde_json::from_value(json!("WBTC-DAI")).unwrap();
+        assert_eq!(symbol, PoolSymbol::new("WBTC-DAI"));
+        assert_eq!((symbol.base_asset(), symbol.quote_asset()), ("WBTC", "DAI"));
+        assert_eq!(serde_json::to_value(&symbol).unwrap(), json!("WBTC-DAI"));
+
+        for invalid in ["ETHUSDC", "-USDC", "ETH-"] {
+            assert!(serde_json::from_value::<PoolSymbol>(json!(invalid)).is_err());
+        }
+    }
+
+    #[test]
+    fn block_triggered_pool_config_deserialization() {
+        let config: PoolConfig = serde_json::from_value(json!({
+            "dex": "uniswapv4",
+            "address": "0x1234567890abcdef1234567890abcdef12345678",
+            "symbol": "ETH-USDC",
+            "token_0": { "address": "0x0000000000000000000000000000000000000000", "decimals": 18 },
+            "token_1": { "address": "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48", "decimals": 6 },
+            "fee_tier": 500,
+            "tick_spacing": 10,
+            "scaling": 2,
+            "node_url": "wss://mainnet.infura.io/ws/v3/YOUR_INFURA_PROJECT_ID",
+            "trigger": "block"
+        }))
+        .unwrap();
+        assert_eq!(config.trigger(), PoolTrigger::Block);
+        // The fallback interval if the node cannot subscribe
+        assert_eq!(config.poll_interval(), Duration::from_secs(5));
+    }
+
+    #[test]
+    fn quoted_pool_config_deserialization() {
+        let pool = |quoter_address: Option<&str>| {
+            serde_json::from_value::<PoolConfig>(json!({
+                "dex": "uniswapv4",
+                "address": "0x1234567890abcdef1234567890abcdef12345678",
+                "symbol": "ETH-USDC",
+                "token_0": { "address": "0x0000000000000000000000000000000000000000", "decimals": 18 },
+                "token_1": { "address": "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48", "decimals": 6 },
+                "fee_tier": 500,
+                "tick_spacing": 10,
+                "scaling": 2,
+                "node_url": "https://mainnet.infura.io/v3/YOUR_INFURA_PROJECT_ID",
+                "quote_size": "2.5",
+                "quoter_address": quoter_address
+            }))
+            .unwrap()
+        };
+
+        let quoter = "0x52F0E24D1c21C8A0cB1e5a5dD6198556BD9E1203";
+        assert_eq!(pool(Some(quoter)).quote().unwrap(), Some((dec!(2.5), quoter)));
+        // The quoter is needed to quote the size
+        assert!(pool(None).quote().is_err());
+    }
+
+    #[test]
+    fn cex_auth_config_deserialization() {
+        let config: CexConfig = serde_json::from_value(json!({
+            "exchange": "coinbase",
+            "ws_url": "wss://ws-feed.exchange.coinbase.com",
+            "auth": { "passphrase_env": "SIKARRA_COINBASE_PASSPHRASE" }
+        }))
+        .unwrap();
+        let CexConfig::Coinbase { auth, .. } = &config else {
+            panic!("expected a Coinbase feed");
+        };
+        assert_eq!(
+            *auth,
+            Some(CoinbaseWsAuthConfig {
+                api_key_env: "COINBASE_WS_API_KEY".to_string(),
+                api_secret_env: "COINBASE_WS_API_SECRET".to_string(),
+                passphrase_env: "SIKARRA_COINBASE_PASSPHRASE".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn cex_channel_mode_deserialization() {
+        let config: CexConfig = serde_json::from_value(json!({
+            "exchange": "coinbase",
+            "ws_url": "wss://ws-feed.exchange.coinbase.com",
+            "channel_mode": "batched"
+        }))
+        .unwrap();
+        let CexConfig::Coinbase { channel_mode, .. } = &config else {
+            panic!("expected a Coinbase feed");
+        };
+        assert_eq!(*channel_mode, CoinbaseChannelMode::Batched);
+        assert_eq!(channel_mode.ticker_channel(), "ticker_batch");
+    }
+
+    #[test]
+    fn cex_binance_config_deserialization() {
+        let config: CexConfig = serde_json::from_value(json!({
+            "exchange": "binance",
+            "ws_url": "wss://stream.binance.com:9443/ws",
+            "unlimited_reconnects": true
+        }))
+        .unwrap();
+        let CexConfig::Binance { ws_url, unlimited_reconnects, reconnect } = &config else {
+            panic!("expected a Binance feed");
+        };
+        assert_eq!(ws_url, "wss://stream.binance.com:9443/ws");
+        assert!(unlimited_reconnects);
+        assert_eq!(*reconnect, ReconnectConfig::default());
+    }
+
+    #[test]
+    fn config_validation() {
+        let config = |pool: serde_json::Value, market_making: serde_json::Value| {
+            let mut json = json!({
+                "pools": [{
+                    "dex": "uniswapv4",
+                    "address": "0xA3c0c9b65baD0b08107Aa264b0f3dB444b867A71",
+                    "symbol": "ETH-USDC",
+                    "token_0": {
+                        "address": "0x4200000000000000000000000000000000000006",
+                        "decimals": 18
+                    },
+                    "token_1": {
+                        "address": "0x833589fCD6eDb6E08f4c7C32D4f71b54bdA02913",
+                        "decimals": 6
+                    },
+                    "fee_tier": 500,
+                    "node_url": "https://mainnet.base.org",
+                    "tick_spacing": 10,
+                    "scaling": 2
+                }],
+                "cex": { "exchange": "coinbase", "ws_url": "wss://ws-feed.exchange.coinbase.com" },
+                "market_making": {
+                    "base_spread_bps": 50,
+                    "max_spread_bps": 100,
+                    "min_spread_bps": 10,
+                    "gas_price": "0.5",
+                    "arbitrage_threshold_bps": 10,
+                    "arbitrage_tighten_factor": "0.7",
+                    "arbitrage_widen_factor": "1.3"
+                }
+            });
+            for (key, value) in pool.as_object().unwrap() {
+                json["pools"][0][key] = value.clone();
+            }
+            for (key, value) in market_making.as_object().unwrap() {
+                json["market_making"][key] = value.clone();
+            }
+            serde_json::from_value::<BotConfig>(json).unwrap()
+        };
+        config(json!({}), json!({})).validate().unwrap();
+        // Equal spreads are within bounds
+        config(json!({}), json!({ "min_spread_bps": 50, "max_spread_bps": 50 }))
+            .validate()
+            .unwrap();
+
+        let invalid_pools = [
+            json!({ "address": "0x1234" }),
+            // A single letter off the checksum
+            json!({ "address": "0xa3c0c9b65baD0b08107Aa264b0f3dB444b867A71" }),
+            json!({ "token_0": { "address": "not an address", "decimals": 18 } }),
+            // A digit short
+            json!({
+                "token_1": { "address": "0x833589fCD6eDb6E08f4c7C32D4f71b54bdA0291", "decimals": 6 }
+            }),
+            json!({ "hook_address": "0x833589FCD6eDb6E08f4c7C32D4f71b54bdA02913" }),
+            json!({ "quoter_address": "" }),
+        ];
+        for pool in invalid_pools {
+            let error = config(pool.clone(), json!({})).validate().unwrap_err();
+            assert!(error.to_string().contains("invalid"), "{}: {}", pool, error);
+        }
+
+        let invalid_market_making = [
+            json!({ "min_spread_bps": 60 }),
+            json!({ "max_spread_bps": 40 }),
+            json!({ "arbitrage_tighten_factor": "1" }),
+            json!({ "arbitrage_tighten_factor": "1.1" }),
+            json!({ "arbitrage_widen_factor": "1" }),
+            json!({ "arbitrage_widen_factor": "0.9" }),
+            json!({ "volatility_widen_factor": "1" }),
+            json!({ "gas_price": "0" }),
+            json!({ "gas_price": "-0.5" }),
+            json!({ "fill_simulation": { "clip_size": "0", "max_inventory": "2" } }),
+            json!({ "fill_simulation": { "clip_size": "1", "max_inventory": "-2" } }),
+            json!({
+                "fill_simulation": { "clip_size": "1", "max_inventory": "2", "summary_interval": 0 }
+            }),
+        ];
+        for market_making in invalid_market_making {
+            assert!(
+                config(json!({}), market_making.clone()).validate().is_err(),
+                "{}",
+                market_making
+            );
+        }
+    }
+
+    #[test]
+    fn config_reload_changes() {
+        let config = |pools: serde_json::Value, ws_url: &str, market_making: serde_json::Value| {
+            let mut json = json!({
+                "pools": pools,
+                "cex": { "exchange": "coinbase", "ws_url": ws_url },
+                "market_making": {
+                    "base_spread_bps": 50,
+                    "max_spread_bps": 100,
+                    "min_spread_bps": 10,
+                    "gas_price": "0.5",
+                    "arbitrage_threshold_bps": 10,
+                    "arbitrage_tighten_factor": "0.7",
+                    "arbitrage_widen_factor": "1.3"
+                }
+            });
+            for (key, value) in market_making.as_object().unwrap() {
+                json["market_making"][key] = value.clone();
+            }
+            serde_json::from_value::<BotConfig>(json).unwrap()
+        };
+        let ws_url = "wss://ws-feed.exchange.coinbase.com";
+        let current = config(json!([]), ws_url, json!({}));
+        assert!(current.restart_required_changes(&current).is_empty());
+
+        // Spreads and thresholds apply on a reload
+        let reloaded =
+            config(json!([]), ws_url, json!({ "base_spread_bps": 60, "max_spread_bps": 120 }));
+        assert!(current.restart_required_changes(&reloaded).is_empty());
+
+        let pool = json!({
+            "dex": "uniswapv3",
+            "address": "0xd0b53D9277642d899DF5C87A3966A349A798F224",
+            "symbol": "ETH-USDC",
+            "token_0": { "address": "0x4200000000000000000000000000000000000006", "decimals": 18 },
+            "token_1": { "address": "0x833589fCD6eDb6E08f4c7C32D4f71b54bdA02913", "decimals": 6 },
+            "fee_tier": 500,
+            "node_url": "https://mainnet.base.org",
+            "scaling": 2
+        });
+        let reloaded = config(
+            json!([pool]),
+            "wss://advanced-trade-ws.coinbase.com",
+            json!({ "base_spread_bps": 60, "use_twap": true }),
+        );
+        assert_eq!(
+            current.restart_required_changes(&reloaded),
+            ["pools", "cex", "market_making.use_twap"]
+        );
+    }
+
+    #[test]
+    fn cex_list_config_deserialization() {
+        let config = |cex| {
+            serde_json::from_value::<BotConfig>(json!({
+                "pools": [],
+                "cex": cex,
+                "market_making": {
+                    "base_spread_bps": 50,
+                    "max_spread_bps": 100,
+                    "min_spread_bps": 10,
+                    "gas_price": "0.5",
+                    "arbitrage_threshold_bps": 100,
+                    "arbitrage_tighten_factor": "0.7",
+                    "arbitrage_widen_factor": "1.3"
+                }
+            }))
+        };
+
+        let cex = config(json!([
+            { "exchange": "coinbase", "ws_url": "wss://ws-feed.exchange.coinbase.com" },
+            { "exchange": "binance", "ws_url": "wss://stream.binance.com:9443/ws" }
+        ]))
+        .unwrap()
+        .cex;
+        assert_eq!(cex.len(), 2);
+        assert!(matches!(cex[0], CexConfig::Coinbase { .. }));
+        assert!(matches!(cex[1], CexConfig::Binance { .. }));
+
+        let cex =
+            config(json!({ "exchange": "binance", "ws_url": "wss://stream.binance.com:9443/ws" }))
+                .unwrap()
+                .cex;
+        assert_eq!(cex.len(), 1);
+        assert!(matches!(cex[0], CexConfig::Binance { .. }));
+
+        assert!(config(json!([])).is_err());
+    }
+
+    #[test]
+    fn aggregated_price_feed_config_deserialization() {
+        let config: AggregatedPriceFeedConfig = serde_json::from_value(json!({})).unwrap();
+        assert_eq!(config.min_sources, 2);
+        let config: AggregatedPriceFeedConfig =
+            serde_json::from_value(json!({ "min_sources": 3 })).unwrap();
+        assert_eq!(config.min_sources, 3);
+    }
+
+    #[test]
+    fn cex_reconnect_config_deserialization() {
+        let config: CexConfig = serde_json::from_value(json!({
+            "exchange": "coinbase",
+            "ws_url": "wss://coinbase-proxy.internal",
+            "reconnect": { "max_retries": 3, "min_delay_secs": 5, "max_delay_secs": 300 }
+        }))
+        .unwrap();
+        let CexConfig::Coinbase { reconnect, .. } = &config else {
+            panic!("expected a Coinbase feed");
+        };
+        assert_eq!(
+            *reconnect,
+            ReconnectConfig { max_retries: 3, min_delay_secs: 5, max_delay_secs: 300, factor: 2 }
+        );
+        let delays = reconnect.backoff(false).collect::<Vec<_>>();
+        assert_eq!(delays.len(), 3);
+        assert_eq!(delays[0], Duration::from_secs(5));
+
+        let config: CexConfig = serde_json::from_value(json!({
+            "exchange": "coinbase",
+            "ws_url": "wss://ws-feed.pro.coinbase.com"
+        }))
+        .unwrap();
+        let CexConfig::Coinbase { reconnect, .. } = &config else {
+            panic!("expected a Coinbase feed");
+        };
+        assert_eq!(
+            *reconnect,
+            ReconnectConfig { max_retries: 10, min_delay_secs: 1, max_delay_secs: 60, factor: 2 }
+        );
+        assert!(reconnect.backoff(true).is_unlimited());
+    }
+
+    #[test]
+    fn balance_config_deserialization() {
+        let config: BalanceConfig = serde_json::from_value(json!({
+            "rpc_url": "https://mainnet.base.org",
+            "addresses": ["0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266"],
+            "tokens": [
+                {
+                    "asset": "ETH",
+                    "address": "0x0000000000000000000000000000000000000000",
+                    "decimals": 18
+                },
+                {
+                    "asset": "USDC",
+                    "address": "0x833589fCD6eDb6E08f4c7C32D4f71b54bdA02913",
+                    "decimals": 6
+                }
+            ]
+        }))
+        .unwrap();
+
+        assert_eq!(config.addresses.len(), 1);
+        assert_eq!(config.tokens[1].asset, "USDC");
+        assert_eq!(config.tokens[1].token.decimals, 6);
+        assert_eq!(config.poll_interval_secs, 30);
+    }
+
+    #[test]
+    fn risk_config_deserialization() {
+        let config: RiskConfig =
+            serde_json::from_value(json!({ "max_daily_loss": "250.5" })).unwrap();
+        assert_eq!(config.max_daily_loss, dec!(250.5));
+        assert_eq!(config.day_boundary_hour, 0);
+    }
+
+    #[test]
+    fn fee_config_deserialization() {
+        let config: FeeConfig = serde_json::from_value(json!({ "cex_taker_bps": "60" })).unwrap();
+        assert_eq!(config.cex_taker_bps, dec!(60));
+        assert!(config.min_profit_usd.is_zero());
+    }
+
+    #[test]
+    fn alert_config_deserialization() {
+        let config: AlertConfig = serde_json::from_value(json!({
+            "webhooks": [
+                { "url": "https://hooks.slack.com/services/T/B/X", "format": "slack" },
+                {
+                    "url": "https://discord.com/api/webhooks/1/x",
+                    "format": "discord",
+                    "events": ["feed_down", "kill_switch"]
+                }
+            ],
+            "min_opportunity_net_bps": "25",
+            "rate_limit_max_alerts": 2
+        }))
+        .unwrap();
+
+        assert_eq!(config.webhooks.len(), 2);
+        assert_eq!(config.webhooks[0].format, WebhookFormat::Slack);
+        assert!(config.webhooks[0].events.is_none());
+        assert_eq!(config.webhooks[1].format, WebhookFormat::Discord);
+        assert_eq!(
+            config.webhooks[1].events,
+            Some(vec![AlertType::FeedDown, AlertType::KillSwitch])
+        );
+        assert_eq!(config.min_opportunity_net_bps, dec!(25));
+        assert_eq!(config.rate_limit_max_alerts, 2);
+        assert_eq!(config.rate_limit_period_secs, 300);
+        assert_eq!(config.max_retries, 3);
+        assert!(config.telegram.is_none());
+    }
+
+    #[test]
+    fn telegram_alert_config_deserialization() {
+        let config: AlertConfig =
+            serde_json::from_value(json!({ "telegram": { "chat_id": -1001234 } })).unwrap();
+
+        assert!(config.webhooks.is_empty());
+        let telegram = config.telegram.unwrap();
+        assert_eq!(telegram.chat_id, -1001234);
+        assert_eq!(telegram.bot_token_env, "TELEGRAM_BOT_TOKEN");
+        assert_eq!(telegram.api_url, "https://api.telegram.org");
+        assert_eq!(telegram.poll_timeout_secs, 30);
+    }
+
+    #[test]
+    fn report_config_deserialization() {
+        let config: ReportConfig = serde_json::from_value(json!({})).unwrap();
+        assert_eq!(config.output_dir, "reports");
+    }
+
+    #[test]
+    fn simulation_export_config_deserialization() {
+        let config: SimulationExportConfig = serde_json::from_value(json!({})).unwrap();
+        assert_eq!(config.output_dir, "simulation");
+        assert_eq!(config.queue_size, 4096);
+        assert_eq!(config.flush_interval_secs, 5);
+    }
+
+    #[test]
+    fn fill_simulation_config_deserialization() {
+        let config: FillSimulationConfig =
+            serde_json::from_value(json!({ "clip_size": "0.5", "max_inventory": "2" })).unwrap();
+        assert_eq!(config.clip_size, dec!(0.5));
+        assert_eq!(config.max_inventory, dec!(2));
+        assert_eq!(config.summary_interval, 100);
+    }
+
+    #[test]
+    fn snapshot_config_deserialization() {
+        let config: SnapshotConfig =
+            serde_json::from_value(json!({ "output_dir": "data/slot0" })).unwrap();
+        assert_eq!(config.output_dir, "data/slot0");
+        assert_eq!(config.queue_size, 1024);
+        assert_eq!(config.flush_interval_secs, 5);
+    }
+
+    #[test]
+    fn execution_config_deserialization() {
+        let config: ExecutionConfig = serde_json::from_value(json!({
+            "chain_id": 8453,
+            "rpc_url": "https://mainnet.base.org"
+        }))
+        .unwrap();
+        assert_eq!(config.mode, ExecutionMode::DryRun);
+        assert!(config.signer.is_none());
+        assert_eq!(
+            config.fee_caps().unwrap(),
+            FeeCaps { max_fee_per_gas: 100_000_000_000, max_priority_fee_per_gas: 2_000_000_000 }
+        );
+        assert_eq!(
+            config.fee_estimator().unwrap().escalation(),
+            &EscalationPolicy { bump_percent: 15, max_replacements: 3 }
+        );
+        assert_eq!(config.trade_size, Decimal::ZERO);
+
+        let config: ExecutionConfig = serde_json::from_value(json!({
+            "mode": "live",
+            "signer": { "type": "private_key" },
+            "chain_id": 8453,
+            "rpc_url": "https://mainnet.base.org",
+            "max_fee_per_gas_gwei": "0.5",
+            "max_priority_fee_per_gas_gwei": "0.001",
+            "fee_bump_percent": 25,
+            "max_replacements": 1,
+            "trade_size": "0.5",
+            "coinbase": {}
+        }))
+        .unwrap();
+        assert_eq!(config.mode, ExecutionMode::Live);
+        assert_eq!(
+            config.signer,
+            Some(SignerConfig::PrivateKey { key_env: "SIKARRA_SIGNER_KEY".to_string() })
+        );
+        assert_eq!(
+            config.fee_caps().unwrap(),
+            FeeCaps { max_fee_per_gas: 500_000_000, max_priority_fee_per_gas: 1_000_000 }
+        );
+        assert_eq!(
+            config.fee_estimator().unwrap().escalation(),
+            &EscalationPolicy { bump_percent: 25, max_replacements: 1 }
+        );
+        assert_eq!(config.trade_size, dec!(0.5));
+        assert_eq!(
+            config.coinbase,
+            Some(CoinbaseTradeConfig {
+                api_url: "https://api.coinbase.com".to_string(),
+                api_key_env: "COINBASE_API_KEY".to_string(),
+                api_secret_env: "COINBASE_API_SECRET".to_string(),
+            })
+        );
+
+        let signer: SignerConfig = serde_json::from_value(json!({
+            "type": "keystore",
+            "path": "keys/bot.json"
+        }))
+        .unwrap();
+        assert_eq!(
+            signer,
+            SignerConfig::Keystore {
+                path: "keys/bot.json".to_string(),
+                password_env: "SIKARRA_KEYSTORE_PASSWORD".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn invalid_fee_caps_are_rejected() {
+        let invalid_caps = [
+            json!({ "max_fee_per_gas_gwei": "-1" }),
+            json!({ "max_priority_fee_per_gas_gwei": "-0.001" }),
+            // Overflows when converted to wei
+            json!({ "max_fee_per_gas_gwei": "100000000000000000000000000" }),
+        ];
+        for caps in invalid_caps {
+            let mut json = json!({ "chain_id": 8453, "rpc_url": "https://mainnet.base.org" });
+            for (key, value) in caps.as_object().unwrap() {
+                json[key] = value.clone();
+            }
+            let config: ExecutionConfig = serde_json::from_value(json).unwrap();
+            let error = config.validate().unwrap_err();
+            assert!(error.to_string().contains("gwei"), "{}: {}", caps, error);
+            assert!(config.fee_estimator().is_err());
+        }
+    }
+
+    #[test]
+    fn leg_timeouts_must_exceed_the_swap_window() {
+        let config = |cex_timeout: u64, dex_timeout: u64| -> ExecutionConfig {
+            serde_json::from_value(json!({
+                "chain_id": 8453,
+                "rpc_url": "https://mainnet.base.org",
+                "cex_leg_timeout_secs": cex_timeout,
+                "dex_leg_timeout_secs": dex_timeout,
+                "dex": { "router_address": "0x6fF5693b99212Da76ad316178A184AB56D299b43" }
+            }))
+            .unwrap()
+        };
+        // Swaps time out after 30 blocks of 2s, their receipts polled every 1s
+        assert_eq!(config(10, 90).dex.unwrap().swap_window(), Duration::from_secs(61));
+        assert!(config(10, 90).validate().is_ok());
+
+        let error = config(10, 61).validate().unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Configuration error: the dex leg timeout of 61s must exceed the 61s its swaps may \
+             take to be confirmed or time out"
+        );
+        assert!(config(0, 90).validate().is_err());
+    }
+
+    #[test]
+    fn event_stream_config_deserialization() {
+        let config: EventStreamConfig =
+            serde_json::from_value(json!({ "listen_addr": "127.0.0.1:9001" })).unwrap();
+        assert_eq!(config.listen_addr, "127.0.0.1:9001");
+        assert_eq!(config.client_queue_size, 1024);
+
+        let config: EventStreamConfig = serde_json::from_value(json!({
+            "listen_addr": "0.0.0.0:9001",
+            "client_queue_size": 16
+        }))
+        .unwrap();
+        assert_eq!(config.client_queue_size, 16);
     }
 }
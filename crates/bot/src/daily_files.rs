@@ -0,0 +1,270 @@
+//! Daily files written off the async runtime.
+//!
+//! A [`DailyFileWriter`] drains a bounded queue of rows on a blocking thread
+//! into one file per key, e.g. per symbol, per UTC day,
+//! `<output_dir>/<prefix><KEY>-YYYY-MM-DD<extension>`. The files are flushed
+//! once the flush interval has elapsed, even under steady traffic, and on
+//! shutdown. How rows are written is up to the [`DailyFileFormat`].
+
+use std::{
+    collections::HashMap,
+    fs::{self, File, OpenOptions},
+    io::{BufWriter, Write},
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        mpsc::{self, Receiver, RecvTimeoutError, SyncSender, TrySendError},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use sikkara_core::AppResult;
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+
+/// A row written to the file of its key on the day of its timestamp.
+pub(crate) trait DailyRow: Send + 'static {
+    /// Key of the files the row is written to, e.g. its symbol
+    fn key(&self) -> &str;
+
+    /// Time of the row, picking the UTC day of its file
+    fn timestamp(&self) -> jiff::Timestamp;
+}
+
+/// How the rows of a [`DailyFileWriter`] are written and its files named.
+#[derive(Debug)]
+pub(crate) struct DailyFileFormat<T> {
+    /// What the files hold, in logs, e.g. "Snapshot"
+    pub name: &'static str,
+    /// What the rows are, in logs, e.g. "pool states"
+    pub rows: &'static str,
+    pub prefix: &'static str,
+    pub extension: &'static str,
+    /// Line written first to new files, not to those appended to after a
+    /// restart
+    pub header: Option<&'static str>,
+    /// Appends a row to the buffer as written to the files, ending with a
+    /// newline
+    pub format_row: fn(&T, &mut Vec<u8>) -> AppResult<()>,
+}
+
+impl<T> DailyFileFormat<T> {
+    /// Returns the file name of a key on the given day, e.g.
+    /// `slot0-ETH-USDC-2025-02-12.jsonl`.
+    pub fn file_name(&self, key: &str, day: jiff::civil::Date) -> String {
+        format!("{}{}-{}{}", self.prefix, key, day.strftime("%Y-%m-%d"), self.extension)
+    }
+}
+
+/// Queues rows for a [`DailyFileWriter`].
+///
+/// Cloning is cheap, all clones feed the same writer. Rows are dropped rather
+/// than blocking the caller when the writer falls behind.
+#[derive(Debug)]
+pub(crate) struct DailyFileQueue<T> {
+    name: &'static str,
+    rows: &'static str,
+    sender: SyncSender<T>,
+    dropped: Arc<AtomicU64>,
+}
+
+impl<T> Clone for DailyFileQueue<T> {
+    fn clone(&self) -> Self {
+        Self {
+            name: self.name,
+            rows: self.rows,
+            sender: self.sender.clone(),
+            dropped: self.dropped.clone(),
+        }
+    }
+}
+
+impl<T> DailyFileQueue<T> {
+    /// Queues a row, dropping it if the queue is full.
+    pub fn push(&self, row: T) {
+        match self.sender.try_send(row) {
+            Ok(()) => {},
+            Err(TrySendError::Full(_)) => {
+                let dropped = self.dropped.fetch_add(1, Ordering::Relaxed) + 1;
+                if dropped.is_power_of_two() {
+                    warn!("{} queue full, {} {} dropped so far", self.name, dropped, self.rows);
+                }
+            },
+            Err(TrySendError::Disconnected(_)) => {},
+        }
+    }
+
+    /// Returns the number of rows dropped because the queue was full.
+    pub fn dropped(&self) -> u64 { self.dropped.load(Ordering::Relaxed) }
+}
+
+/// Writes the queued rows to daily files per key.
+#[derive(Debug)]
+pub(crate) struct DailyFileWriter<T> {
+    output_dir: PathBuf,
+    format: DailyFileFormat<T>,
+    flush_interval: Duration,
+    queue: DailyFileQueue<T>,
+    receiver: Receiver<T>,
+}
+
+impl<T: DailyRow> DailyFileWriter<T> {
+    /// Creates a writer to `output_dir`, queueing up to `queue_size` rows and
+    /// flushing the files every `flush_interval`.
+    pub fn new(
+        output_dir: impl Into<PathBuf>,
+        format: DailyFileFormat<T>,
+        queue_size: usize,
+        flush_interval: Duration,
+    ) -> Self {
+        let (sender, receiver) = mpsc::sync_channel(queue_size);
+        let queue = DailyFileQueue {
+            name: format.name,
+            rows: format.rows,
+            sender,
+            dropped: Arc::new(AtomicU64::new(0)),
+        };
+        Self { output_dir: output_dir.into(), format, flush_interval, queue, receiver }
+    }
+
+    /// Returns a queue feeding this writer.
+    pub fn queue(&self) -> DailyFileQueue<T> { self.queue.clone() }
+
+    /// Spawns the writer on a blocking thread until shutdown is requested,
+    /// at which point the queued rows are written and the files flushed.
+    pub fn spawn(self, shutdown: CancellationToken) -> tokio::task::JoinHandle<AppResult<()>> {
+        let Self { output_dir, format, flush_interval, receiver, .. } = self;
+        tokio::task::spawn_blocking(move || {
+            fs::create_dir_all(&output_dir)?;
+            let mut files =
+                DailyFiles { output_dir, format, files: HashMap::new(), buffer: Vec::new() };
+            let mut last_flush = Instant::now();
+            loop {
+                match receiver.recv_timeout(flush_interval) {
+                    Ok(row) => files.write(&row)?,
+                    Err(RecvTimeoutError::Timeout) => {},
+                    Err(RecvTimeoutError::Disconnected) => break,
+                }
+                // Rows arriving faster than the interval never time out
+                if last_flush.elapsed() >= flush_interval {
+                    files.flush()?;
+                    last_flush = Instant::now();
+                }
+                if shutdown.is_cancelled() {
+                    break;
+                }
+            }
+            for row in receiver.try_iter() {
+                files.write(&row)?;
+            }
+            files.flush()
+        })
+    }
+}
+
+/// The file currently written for each key.
+struct DailyFiles<T> {
+    output_dir: PathBuf,
+    format: DailyFileFormat<T>,
+    files: HashMap<String, (jiff::civil::Date, BufWriter<File>)>,
+    /// Row being formatted, reused across rows
+    buffer: Vec<u8>,
+}
+
+impl<T: DailyRow> DailyFiles<T> {
+    fn write(&mut self, row: &T) -> AppResult<()> {
+        let key = row.key();
+        let day = row.timestamp().to_zoned(jiff::tz::TimeZone::UTC).date();
+        let rotate = self
+            .files
+            .get(key)
+            .is_none_or(|(current, _)| *current != day);
+        if rotate {
+            let path = self.output_dir.join(self.format.file_name(key, day));
+            let file = OpenOptions::new().create(true).append(true).open(&path)?;
+            // Only write the header to new files, appending after a restart
+            let is_new = file.metadata()?.len() == 0;
+            let mut writer = BufWriter::new(file);
+            if let Some(header) = self.format.header.filter(|_| is_new) {
+                writer.write_all(header.as_bytes())?;
+                writer.write_all(b"\n")?;
+            }
+            info!("🗂️ {} of {} rotated to {}", self.format.name, key, path.display());
+            if let Some((_, mut previous)) = self.files.insert(key.to_string(), (day, writer)) {
+                previous.flush()?;
+            }
+        }
+
+        self.buffer.clear();
+        (self.format.format_row)(row, &mut self.buffer)?;
+        let (_, writer) = self.files.get_mut(key).expect("file was just opened");
+        writer.write_all(&self.buffer)?;
+        Ok(())
+    }
+
+    fn flush(&mut self) -> AppResult<()> {
+        for (_, writer) in self.files.values_mut() {
+            writer.flush()?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Line {
+        key: String,
+        timestamp: jiff::Timestamp,
+        value: u32,
+    }
+
+    impl DailyRow for Line {
+        fn key(&self) -> &str { &self.key }
+
+        fn timestamp(&self) -> jiff::Timestamp { self.timestamp }
+    }
+
+    fn format() -> DailyFileFormat<Line> {
+        DailyFileFormat {
+            name: "Test export",
+            rows: "lines",
+            prefix: "test-",
+            extension: ".csv",
+            header: Some("key,value"),
+            format_row: |line, buffer| {
+                writeln!(buffer, "{},{}", line.key, line.value)?;
+                Ok(())
+            },
+        }
+    }
+
+    async fn write_lines(dir: &std::path::Path, values: &[u32]) {
+        let writer = DailyFileWriter::new(dir, format(), 16, Duration::from_millis(10));
+        let queue = writer.queue();
+        for value in values {
+            queue.push(Line {
+                key: "ETH-USDC".to_string(),
+                timestamp: "2025-02-12T12:00:00Z".parse().unwrap(),
+                value: *value,
+            });
+        }
+        let shutdown = CancellationToken::new();
+        let task = writer.spawn(shutdown.clone());
+        shutdown.cancel();
+        task.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_header_is_only_written_to_new_files() {
+        let dir = tempfile::tempdir().unwrap();
+        write_lines(dir.path(), &[1, 2]).await;
+        // A restart appends to the file of the day
+        write_lines(dir.path(), &[3]).await;
+
+        let content = fs::read_to_string(dir.path().join("test-ETH-USDC-2025-02-12.csv")).unwrap();
+        assert_eq!(content, "key,value\nETH-USDC,1\nETH-USDC,2\nETH-USDC,3\n");
+    }
+}
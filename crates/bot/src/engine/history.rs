@@ -0,0 +1,304 @@
+//! In-memory price history of a symbol.
+//!
+//! A [`PriceHistory`] is a fixed-capacity ring buffer of CEX and DEX prices,
+//! appended in O(1) and evicting the oldest point once full. It is shared
+//! through a [`PriceHistoryHandle`], which the arbitrage engine of the pool
+//! writes to, and read through [`PriceHistoryReader`]s handed out to
+//! strategies and other consumers.
+
+use std::{
+    collections::{BTreeMap, VecDeque},
+    sync::{Arc, RwLock, RwLockReadGuard},
+    time::Duration,
+};
+
+use rust_decimal::Decimal;
+
+use crate::engine::{InternalEvent, PriceSource};
+
+/// A single price observation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PricePoint {
+    pub timestamp: jiff::Timestamp,
+    pub source: PriceSource,
+    pub price: Decimal,
+}
+
+/// Open, high, low and close prices within a time bucket.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Candle {
+    /// Start of the bucket
+    pub start: jiff::Timestamp,
+    pub open: Decimal,
+    pub high: Decimal,
+    pub low: Decimal,
+    pub close: Decimal,
+    /// Number of prices within the bucket
+    pub count: usize,
+}
+
+/// Fixed-capacity ring buffer of the prices of a symbol.
+#[derive(Debug, Clone)]
+pub struct PriceHistory {
+    capacity: usize,
+    points: VecDeque<PricePoint>,
+    latest_cex: Option<PricePoint>,
+    latest_dex: Option<PricePoint>,
+}
+
+impl PriceHistory {
+    /// Creates an empty history holding at most `capacity` prices.
+    pub fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        Self {
+            capacity,
+            points: VecDeque::with_capacity(capacity),
+            latest_cex: None,
+            latest_dex: None,
+        }
+    }
+
+    /// Appends a price, evicting the oldest one if the history is full.
+    pub fn push(&mut self, point: PricePoint) {
+        if self.points.len() == self.capacity {
+            self.points.pop_front();
+        }
+        self.points.push_back(point);
+        match point.source {
+            PriceSource::Cex => self.latest_cex = Some(point),
+            PriceSource::Dex => self.latest_dex = Some(point),
+        }
+    }
+
+    /// Appends the price carried by an event, if any.
+    pub fn record(&mut self, event: &InternalEvent) {
+        match event {
+            InternalEvent::TickerUpdate(ticker) => self.push(PricePoint {
+                timestamp: ticker.timestamp,
+                source: PriceSource::Cex,
+                price: ticker.price,
+            }),
+            InternalEvent::PoolPriceUpdate(update) => self.push(PricePoint {
+                timestamp: update.timestamp,
+                source: PriceSource::Dex,
+                price: update.price,
+            }),
+            InternalEvent::FeedStatus(_)
+            | InternalEvent::Execution(_)
+            | InternalEvent::BalanceUpdate(_)
+            | InternalEvent::OrderUpdate(_)
+            | InternalEvent::OwnFill(_) => {},
+        }
+    }
+
+    /// Returns the number of prices held.
+    pub fn len(&self) -> usize { self.points.len() }
+
+    /// Returns true if no price was recorded yet.
+    pub fn is_empty(&self) -> bool { self.points.is_empty() }
+
+    /// Returns the latest price of the given source.
+    pub fn latest(&self, source: PriceSource) -> Option<PricePoint> {
+        match source {
+            PriceSource::Cex => self.latest_cex,
+            PriceSource::Dex => self.latest_dex,
+        }
+    }
+
+    /// Returns all prices observed at or after `since`, oldest first.
+    pub fn range(&self, since: jiff::Timestamp) -> Vec<PricePoint> {
+        self.points
+            .iter()
+            .filter(|point| point.timestamp >= since)
+            .copied()
+            .collect()
+    }
+
+    /// Aggregates the prices of a source into candles of `bucket` length,
+    /// aligned to the unix epoch and ordered by time.
+    pub fn ohlc(&self, source: PriceSource, bucket: Duration) -> Vec<Candle> {
+        let bucket_millis = (bucket.as_millis() as i64).max(1);
+        let mut candles: BTreeMap<i64, Candle> = BTreeMap::new();
+        for point in self.points.iter().filter(|point| point.source == source) {
+            let start = point.timestamp.as_millisecond().div_euclid(bucket_millis) * bucket_millis;
+            candles
+                .entry(start)
+                .and_modify(|candle| {
+                    candle.high = candle.high.max(point.price);
+                    candle.low = candle.low.min(point.price);
+                    candle.close = point.price;
+                    candle.count += 1;
+                })
+                .or_insert(Candle {
+                    start: jiff::Timestamp::from_millisecond(start)
+                        .expect("bucket start is in range"),
+                    open: point.price,
+                    high: point.price,
+                    low: point.price,
+                    close: point.price,
+                    count: 1,
+                });
+        }
+        candles.into_values().collect()
+    }
+
+    /// Returns the simple returns between consecutive prices of a source
+    /// within `window` of its latest price, oldest first. Their dispersion is a
+    /// measure of the recent volatility.
+    pub fn returns(&self, source: PriceSource, window: Duration) -> Vec<Decimal> {
+        let Some(latest) = self.latest(source) else { return Vec::new() };
+        let Ok(since) = latest.timestamp.checked_sub(window) else { return Vec::new() };
+        let prices = self
+            .points
+            .iter()
+            .filter(|point| point.source == source && point.timestamp >= since)
+            .map(|point| point.price)
+            .collect::<Vec<_>>();
+        prices
+            .windows(2)
+            .filter(|pair| !pair[0].is_zero())
+            .map(|pair| (pair[1] - pair[0]) / pair[0])
+            .collect()
+    }
+}
+
+/// Shared, writable handle to the price history of a symbol.
+///
+/// Cloning is cheap, all clones share the same history.
+#[derive(Debug, Clone)]
+pub struct PriceHistoryHandle {
+    history: Arc<RwLock<PriceHistory>>,
+}
+
+impl PriceHistoryHandle {
+    pub fn new(capacity: usize) -> Self {
+        Self { history: Arc::new(RwLock::new(PriceHistory::new(capacity))) }
+    }
+
+    /// Appends the price carried by an event, if any.
+    pub fn record(&self, event: &InternalEvent) {
+        self.history
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .record(event);
+    }
+
+    /// Returns a read-only handle to the history.
+    pub fn reader(&self) -> PriceHistoryReader {
+        PriceHistoryReader { history: self.history.clone() }
+    }
+}
+
+/// Shared, read-only handle to the price history of a symbol.
+#[derive(Debug, Clone)]
+pub struct PriceHistoryReader {
+    history: Arc<RwLock<PriceHistory>>,
+}
+
+impl PriceHistoryReader {
+    /// Locks the history for reading. Keep the guard short lived, the
+    /// arbitrage engine cannot record prices while it is held.
+    pub fn read(&self) -> RwLockReadGuard<'_, PriceHistory> {
+        self.history
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal_macros::dec;
+
+    use super::*;
+
+    fn at(secs: i64) -> jiff::Timestamp {
+        jiff::Timestamp::from_second(1_739_394_000 + secs).unwrap()
+    }
+
+    fn point(secs: i64, source: PriceSource, price: Decimal) -> PricePoint {
+        PricePoint { timestamp: at(secs), source, price }
+    }
+
+    #[test]
+    fn test_capacity_evicts_oldest_prices() {
+        let mut history = PriceHistory::new(3);
+        for secs in 0..5 {
+            history.push(point(secs, PriceSource::Cex, Decimal::from(secs)));
+        }
+
+        assert_eq!(history.len(), 3);
+        let prices = history
+            .range(at(0))
+            .iter()
+            .map(|p| p.price)
+            .collect::<Vec<_>>();
+        assert_eq!(prices, vec![dec!(2), dec!(3), dec!(4)]);
+    }
+
+    #[test]
+    fn test_latest_and_range() {
+        let mut history = PriceHistory::new(10);
+        assert_eq!(history.latest(PriceSource::Cex), None);
+
+        history.push(point(0, PriceSource::Cex, dec!(2500)));
+        history.push(point(1, PriceSource::Dex, dec!(2501)));
+        history.push(point(2, PriceSource::Cex, dec!(2502)));
+
+        assert_eq!(history.latest(PriceSource::Cex), Some(point(2, PriceSource::Cex, dec!(2502))));
+        assert_eq!(history.latest(PriceSource::Dex), Some(point(1, PriceSource::Dex, dec!(2501))));
+        assert_eq!(history.range(at(1)).len(), 2);
+        assert!(history.range(at(3)).is_empty());
+    }
+
+    #[test]
+    fn test_ohlc_bucket_aggregation() {
+        let mut history = PriceHistory::new(10);
+        history.push(point(0, PriceSource::Cex, dec!(100)));
+        history.push(point(20, PriceSource::Cex, dec!(105)));
+        history.push(point(30, PriceSource::Dex, dec!(500)));
+        history.push(point(40, PriceSource::Cex, dec!(98)));
+        history.push(point(59, PriceSource::Cex, dec!(101)));
+        history.push(point(61, PriceSource::Cex, dec!(102)));
+
+        let candles = history.ohlc(PriceSource::Cex, Duration::from_secs(60));
+        assert_eq!(
+            candles,
+            vec![
+                Candle {
+                    start: at(0),
+                    open: dec!(100),
+                    high: dec!(105),
+                    low: dec!(98),
+                    close: dec!(101),
+                    count: 4,
+                },
+                Candle {
+                    start: at(60),
+                    open: dec!(102),
+                    high: dec!(102),
+                    low: dec!(102),
+                    close: dec!(102),
+                    count: 1,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_returns_within_window() {
+        let mut history = PriceHistory::new(10);
+        history.push(point(0, PriceSource::Dex, dec!(50)));
+        history.push(point(10, PriceSource::Dex, dec!(100)));
+        history.push(point(20, PriceSource::Cex, dec!(1)));
+        history.push(point(30, PriceSource::Dex, dec!(110)));
+        history.push(point(40, PriceSource::Dex, dec!(99)));
+
+        assert_eq!(
+            history.returns(PriceSource::Dex, Duration::from_secs(30)),
+            vec![dec!(0.1), dec!(-0.1)]
+        );
+        assert!(history
+            .returns(PriceSource::Cex, Duration::from_secs(30))
+            .is_empty());
+    }
+}
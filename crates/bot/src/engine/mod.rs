@@ -6,21 +6,36 @@
 //! opportunities and generate appropriate trading actions.
 
 use sikkara_core::{AppResult, Engine};
-use tracing::{debug, info};
+use tracing::{debug, info, warn};
 
 mod models;
 pub use models::{
-    Exchange, InternalAction, InternalEvent, MarketCondition, MarketMakingRange, Pool,
-    PoolPriceUpdate, PoolSymbol, Ticker,
+    ArbitrageDirection, ArbitrageOpportunity, BalanceUpdate, BalanceVenue, Exchange,
+    ExecutionEvent, ExecutionStatus, FeedState, FeedStatus, InternalAction, InternalEvent, Leg,
+    LegIntent, LegOrdering, MarketCondition, MarketMakingRange, OrderBook, OrderState, OrderUpdate,
+    OwnFill, Pool, PoolPriceUpdate, PoolSymbol, PriceSource, SuppressedOpportunity,
+    SuppressionReason, Ticker, Token, Trade,
 };
 
 mod price_feed;
-pub use price_feed::{PriceFeed, PriceFeedSubscription};
+pub use price_feed::{
+    median_price, AggregatedPriceFeed, HeartbeatSubscription, OrderBookFeed, OrderBookSubscription,
+    OwnFillSubscription, PriceFeed, PriceFeedSubscription, TradeSubscription,
+};
 
 mod pool;
-pub use pool::{PoolFeed, PoolUpdateStream};
+pub use pool::{
+    DivergenceTracker, PollPolicy, PoolFeed, PoolUpdateStream, UniswapV3PoolFeed,
+    UniswapV4PoolFeed, UniswapV4WatchedPoolFeed,
+};
+
+mod history;
+pub use history::{Candle, PriceHistory, PriceHistoryHandle, PriceHistoryReader, PricePoint};
 
-use crate::strategy::BotStrategy;
+mod twap;
+pub use twap::TwapCalculator;
+
+use crate::{positions::BalanceBook, strategy::BotStrategy};
 
 /// Core arbitrage trading engine that processes market events and executes
 /// strategies. Note this engine is designed to be per pool/pair, meaning it
@@ -44,6 +59,10 @@ where
     strategy: S,
     /// The trading pool/pair this engine is monitoring (e.g., "ETH-USDT")
     pool: String,
+    /// Price history of the pool, fed before the strategy sees an event
+    history: Option<PriceHistoryHandle>,
+    /// Balances held, fed before the strategy sees an event
+    balances: Option<BalanceBook>,
 }
 
 impl<S> ArbitrageEngine<S>
@@ -65,7 +84,19 @@ where
     /// strategy and pool.
     pub fn new(strategy: S, pool: String) -> Self {
         let name = format!("arbitrage_engine_{}", pool);
-        Self { strategy, pool, name }
+        Self { strategy, pool, name, history: None, balances: None }
+    }
+
+    /// Records the prices of all processed events into the given history.
+    pub fn with_price_history(mut self, history: PriceHistoryHandle) -> Self {
+        self.history = Some(history);
+        self
+    }
+
+    /// Records the balance updates into the given book.
+    pub fn with_balances(mut self, balances: BalanceBook) -> Self {
+        self.balances = Some(balances);
+        self
     }
 
     /// Gets the trading pool this engine is monitoring.
@@ -90,6 +121,18 @@ where
     ///
     /// Returns a mutable reference to the configured arbitrage strategy.
     pub fn strategy_mut(&mut self) -> &mut S { &mut self.strategy }
+
+    /// Hands an event to the strategy if it is about one of the symbols the
+    /// strategy handles.
+    fn dispatch(&mut self, event: InternalEvent) -> Option<InternalAction> {
+        if let Some(symbol) = event.symbol() {
+            if !self.strategy.symbols().contains(symbol) {
+                debug!(%symbol, "event not dispatched to the strategy");
+                return None;
+            }
+        }
+        self.strategy.handle_internal_event(event)
+    }
 }
 
 #[async_trait::async_trait]
@@ -120,6 +163,9 @@ where
     /// Returns `Ok(Some(action))` if a trading action should be taken,
     /// `Ok(None)` if no action is needed, or an error if processing fails.
     async fn process_event(&mut self, event: InternalEvent) -> AppResult<Option<InternalAction>> {
+        if let Some(history) = &self.history {
+            history.record(&event);
+        }
         match event {
             InternalEvent::TickerUpdate(ticker) => {
                 debug!(
@@ -128,9 +174,7 @@ where
                     price = %ticker.price,
                 );
 
-                self.strategy
-                    .handle_internal_event(InternalEvent::TickerUpdate(ticker));
-                Ok(None)
+                Ok(self.dispatch(InternalEvent::TickerUpdate(ticker)))
             },
             InternalEvent::PoolPriceUpdate(update) => {
                 debug!(
@@ -138,10 +182,141 @@ where
                     symbol = %update.symbol,
                     price = %update.price,
                 );
-                self.strategy
-                    .handle_internal_event(InternalEvent::PoolPriceUpdate(update));
+                Ok(self.dispatch(InternalEvent::PoolPriceUpdate(update)))
+            },
+            InternalEvent::FeedStatus(status) => {
+                warn!(
+                    feed = %status.feed,
+                    symbol = %status.symbol,
+                    state = ?status.state,
+                    reason = %status.reason,
+                    "feed status changed"
+                );
+                Ok(self.dispatch(InternalEvent::FeedStatus(status)))
+            },
+            InternalEvent::Execution(execution) => {
+                info!(
+                    correlation_id = execution.correlation_id,
+                    symbol = %execution.symbol,
+                    status = ?execution.status,
+                    "execution update"
+                );
+                Ok(None)
+            },
+            InternalEvent::BalanceUpdate(balance) => {
+                debug!(
+                    venue = %balance.venue,
+                    asset = %balance.asset,
+                    free = %balance.free,
+                    total = %balance.total,
+                    "balance update"
+                );
+                if let Some(balances) = &self.balances {
+                    balances.record(&balance);
+                }
+                Ok(self.dispatch(InternalEvent::BalanceUpdate(balance)))
+            },
+            InternalEvent::OrderUpdate(order) => {
+                info!(
+                    correlation_id = order.correlation_id,
+                    venue = %order.venue,
+                    order_id = %order.order_id,
+                    state = ?order.state,
+                    filled = %order.filled,
+                    "order update"
+                );
                 Ok(None)
             },
+            InternalEvent::OwnFill(fill) => {
+                info!(
+                    exchange = %fill.exchange,
+                    symbol = %fill.symbol,
+                    order_id = %fill.order_id,
+                    side = ?fill.side,
+                    price = %fill.price,
+                    size = %fill.size,
+                    "own fill"
+                );
+                Ok(self.dispatch(InternalEvent::OwnFill(fill)))
+            },
+        }
+    }
+
+    async fn on_stop(&mut self) -> AppResult<()> {
+        self.strategy.on_stop();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal_macros::dec;
+
+    use super::*;
+
+    /// Strategy recording the symbols of the events it was handed.
+    struct RecordingStrategy {
+        symbols: Vec<PoolSymbol>,
+        handled: Vec<PoolSymbol>,
+    }
+
+    impl BotStrategy for RecordingStrategy {
+        fn symbols(&self) -> Vec<PoolSymbol> { self.symbols.clone() }
+
+        fn handle_internal_event(&mut self, event: InternalEvent) -> Option<InternalAction> {
+            self.handled.extend(event.symbol().cloned());
+            None
         }
     }
+
+    fn ticker(symbol: PoolSymbol) -> InternalEvent {
+        InternalEvent::TickerUpdate(Ticker {
+            exchage: Exchange::Coinbase,
+            symbol,
+            price: dec!(2500),
+            timestamp: jiff::Timestamp::UNIX_EPOCH,
+            bid: None,
+            ask: None,
+            bid_size: None,
+            ask_size: None,
+            volume_24h: None,
+        })
+    }
+
+    #[tokio::test]
+    async fn test_events_are_dispatched_for_the_strategy_symbols_only() {
+        let strategy = RecordingStrategy {
+            symbols: vec![PoolSymbol::ETH_USDC, PoolSymbol::ETH_USDT],
+            handled: Vec::new(),
+        };
+        let mut engine = ArbitrageEngine::new(strategy, "ETH-USDC".to_string());
+
+        for symbol in [PoolSymbol::ETH_USDC, PoolSymbol::USDC_CBBTC, PoolSymbol::ETH_USDT] {
+            engine.process_event(ticker(symbol)).await.unwrap();
+        }
+        assert_eq!(engine.strategy().handled, vec![PoolSymbol::ETH_USDC, PoolSymbol::ETH_USDT]);
+    }
+
+    #[tokio::test]
+    async fn test_balance_updates_are_recorded() {
+        let strategy =
+            RecordingStrategy { symbols: vec![PoolSymbol::ETH_USDC], handled: Vec::new() };
+        let balances = BalanceBook::new();
+        let mut engine =
+            ArbitrageEngine::new(strategy, "ETH-USDC".to_string()).with_balances(balances.clone());
+
+        let balance = BalanceUpdate {
+            venue: BalanceVenue::Cex(Exchange::Coinbase),
+            asset: "ETH".to_string(),
+            free: dec!(0.4),
+            total: dec!(1),
+            timestamp: jiff::Timestamp::UNIX_EPOCH,
+        };
+        engine
+            .process_event(InternalEvent::BalanceUpdate(balance))
+            .await
+            .unwrap();
+        assert_eq!(balances.held("ETH"), Some(dec!(1)));
+        assert_eq!(balances.free(Leg::Cex, "ETH"), Some(dec!(0.4)));
+    }
 }
@@ -1,32 +1,192 @@
 //! Core data models for arbitrage trading operations.
 
-use alloy::primitives::{keccak256, Address, B256};
+use std::{borrow::Cow, collections::BTreeMap, time::Duration};
+
+use alloy::primitives::{keccak256, Address, TxHash, B256};
 use rust_decimal::Decimal;
-use serde::Deserialize;
-use sikkara_adapters::CoinbaseSymbol;
+use serde::{Deserialize, Serialize};
+use sikkara_adapters::{
+    BinanceSymbol, CoinbaseSymbol, KrakenSymbol, OkxInstrument, OrderSide, PoolSlotData,
+    TxFailureKind,
+};
+use sikkara_core::AppError;
 
 use crate::config::TokenConfig;
 
 /// Real-time price data from an exchange.
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
 pub struct Ticker {
+    #[serde(rename = "exchange")]
     pub exchage: Exchange,
     pub symbol: PoolSymbol,
+    /// Price of the last trade
     pub price: Decimal,
+    #[serde(with = "sikkara_core::timestamp_with_tz_serializer")]
     pub timestamp: jiff::Timestamp,
+    /// Best bid and ask, with their sizes in the base asset, if the exchange
+    /// reports them with its ticker
+    pub bid: Option<Decimal>,
+    pub ask: Option<Decimal>,
+    pub bid_size: Option<Decimal>,
+    pub ask_size: Option<Decimal>,
+    /// Traded volume of the base asset over the last 24 hours, if reported
+    pub volume_24h: Option<Decimal>,
+}
+
+/// Trade executed on an exchange.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Trade {
+    pub exchange: Exchange,
+    pub symbol: PoolSymbol,
+    pub trade_id: u64,
+    pub price: Decimal,
+    /// Traded size in the base asset
+    pub size: Decimal,
+    /// Side of the taker, buy if the trade lifted an offer
+    pub taker_side: OrderSide,
+    #[serde(with = "sikkara_core::timestamp_with_tz_serializer")]
+    pub timestamp: jiff::Timestamp,
+}
+
+/// Fill of one of our own orders, reported by the exchange, from which the
+/// realized inventory follows.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct OwnFill {
+    pub exchange: Exchange,
+    pub symbol: PoolSymbol,
+    pub order_id: String,
+    pub trade_id: u64,
+    pub side: OrderSide,
+    pub price: Decimal,
+    /// Filled size in the base asset
+    pub size: Decimal,
+    /// Whether the order took liquidity
+    pub taker: bool,
+    #[serde(with = "sikkara_core::timestamp_with_tz_serializer")]
+    pub timestamp: jiff::Timestamp,
+}
+
+/// Order book of a trading pair on an exchange, kept up to date from the
+/// changes of its levels.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OrderBook {
+    pub exchange: Exchange,
+    pub symbol: PoolSymbol,
+    /// Size of every bid level, by price
+    bids: BTreeMap<Decimal, Decimal>,
+    /// Size of every ask level, by price
+    asks: BTreeMap<Decimal, Decimal>,
+    /// Time of the last change, none for a snapshot not changed yet
+    pub timestamp: Option<jiff::Timestamp>,
+}
+
+impl OrderBook {
+    pub fn new(exchange: Exchange, symbol: PoolSymbol) -> Self {
+        Self { exchange, symbol, bids: BTreeMap::new(), asks: BTreeMap::new(), timestamp: None }
+    }
+
+    /// Replaces all levels with the given price and size pairs.
+    pub fn apply_snapshot(
+        &mut self,
+        bids: impl IntoIterator<Item = (Decimal, Decimal)>,
+        asks: impl IntoIterator<Item = (Decimal, Decimal)>,
+    ) {
+        self.bids = bids
+            .into_iter()
+            .filter(|(_, size)| !size.is_zero())
+            .collect();
+        self.asks = asks
+            .into_iter()
+            .filter(|(_, size)| !size.is_zero())
+            .collect();
+        self.timestamp = None;
+    }
+
+    /// Sets the size of a level, removing it if the size is zero.
+    pub fn apply_change(&mut self, side: OrderSide, price: Decimal, size: Decimal) {
+        let levels = match side {
+            OrderSide::Buy => &mut self.bids,
+            OrderSide::Sell => &mut self.asks,
+        };
+        if size.is_zero() {
+            levels.remove(&price);
+        } else {
+            levels.insert(price, size);
+        }
+    }
+
+    /// Returns the price and size of the highest bid.
+    pub fn best_bid(&self) -> Option<(Decimal, Decimal)> {
+        self.bids
+            .last_key_value()
+            .map(|(price, size)| (*price, *size))
+    }
+
+    /// Returns the price and size of the lowest ask.
+    pub fn best_ask(&self) -> Option<(Decimal, Decimal)> {
+        self.asks
+            .first_key_value()
+            .map(|(price, size)| (*price, *size))
+    }
+
+    /// Returns the price halfway between the best bid and ask.
+    pub fn mid_price(&self) -> Option<Decimal> {
+        let ((bid, _), (ask, _)) = (self.best_bid()?, self.best_ask()?);
+        Some((bid + ask) / Decimal::TWO)
+    }
+
+    /// Returns the bid levels from the highest.
+    pub fn bids(&self) -> impl Iterator<Item = (&Decimal, &Decimal)> { self.bids.iter().rev() }
+
+    /// Returns the ask levels from the lowest.
+    pub fn asks(&self) -> impl Iterator<Item = (&Decimal, &Decimal)> { self.asks.iter() }
 }
 
 /// Price update from a pool
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct PoolPriceUpdate {
     pub symbol: PoolSymbol,
     pub price: Decimal,
+    /// Time the pool state was observed
+    #[serde(with = "sikkara_core::timestamp_with_tz_serializer")]
+    pub timestamp: jiff::Timestamp,
+    /// Block the pool state was read at, if pinned to a block
+    pub block_number: Option<u64>,
+    /// State the price was derived from, if the feed provides it, from which
+    /// the price impact of a swap is estimated
+    #[serde(skip)]
+    pub state: Option<PoolSlotData>,
+    /// Prices buying and selling the quote size of the pool would execute at,
+    /// after the LP fee and the price impact, if the pool is quoted
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub effective_buy_price: Option<Decimal>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub effective_sell_price: Option<Decimal>,
 }
 
 /// Supported cryptocurrency exchanges.
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
 pub enum Exchange {
     Coinbase,
+    Binance,
+    Kraken,
+    Okx,
+    /// Median of the prices of several exchanges, see
+    /// [`crate::engine::AggregatedPriceFeed`]
+    Aggregated,
+}
+
+impl std::fmt::Display for Exchange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Exchange::Coinbase => write!(f, "coinbase"),
+            Exchange::Binance => write!(f, "binance"),
+            Exchange::Kraken => write!(f, "kraken"),
+            Exchange::Okx => write!(f, "okx"),
+            Exchange::Aggregated => write!(f, "aggregated"),
+        }
+    }
 }
 
 /// Represents a trading pool configuration for arbitrage opportunities.
@@ -39,6 +199,8 @@ pub struct Pool {
     pub tick_spacing: i32,
     pub hook: Address,
     pub scaling: u8,
+    /// Interval the pool state is polled at
+    pub poll_interval: Duration,
 }
 
 impl Pool {
@@ -78,34 +240,44 @@ impl From<&TokenConfig> for Token {
     }
 }
 
-/// Trading pair symbols for arbitrage opportunities.
-#[allow(clippy::upper_case_acronyms)]
-#[derive(Default, Debug, Clone, PartialEq, Eq)]
-pub enum PoolSymbol {
-    #[default]
-    EthUsdc,
-    EthUsdt,
-    UsdcCbbtc,
-}
+/// Trading pair symbol of a pool, the base asset first, e.g. `ETH-USDC`.
+///
+/// Any pair can be configured, the symbols of the pairs traded so far being
+/// kept as constants.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PoolSymbol(Cow<'static, str>);
 
 impl PoolSymbol {
-    pub fn get_base_asset(&self) -> String {
-        match self {
-            PoolSymbol::EthUsdc => "ETH".to_string(),
-            PoolSymbol::EthUsdt => "ETH".to_string(),
-            PoolSymbol::UsdcCbbtc => "BTC".to_string(),
-        }
+    pub const ETH_USDC: PoolSymbol = PoolSymbol::new("ETH-USDC");
+    pub const ETH_USDT: PoolSymbol = PoolSymbol::new("ETH-USDT");
+    /// cbBTC against USDC, quoted as BTC
+    pub const USDC_CBBTC: PoolSymbol = PoolSymbol::new("BTC-USDC");
+
+    pub const fn new(symbol: &'static str) -> Self { Self(Cow::Borrowed(symbol)) }
+
+    pub fn as_str(&self) -> &str { &self.0 }
+
+    /// Returns the base asset, before the `-`.
+    pub fn base_asset(&self) -> &str {
+        self.0
+            .split_once('-')
+            .map_or(self.as_str(), |(base, _)| base)
     }
+
+    /// Returns the quote asset, after the `-`.
+    pub fn quote_asset(&self) -> &str { self.0.split_once('-').map_or("", |(_, quote)| quote) }
+}
+
+impl Default for PoolSymbol {
+    fn default() -> Self { PoolSymbol::ETH_USDC }
+}
+
+impl From<String> for PoolSymbol {
+    fn from(symbol: String) -> Self { Self(Cow::Owned(symbol)) }
 }
 
 impl std::fmt::Display for PoolSymbol {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            PoolSymbol::EthUsdc => write!(f, "ETH-USDC"),
-            PoolSymbol::EthUsdt => write!(f, "ETH-USDT"),
-            PoolSymbol::UsdcCbbtc => write!(f, "BTC-USDC"),
-        }
-    }
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result { write!(f, "{}", self.0) }
 }
 
 impl<'de> Deserialize<'de> for PoolSymbol {
@@ -114,39 +286,106 @@ impl<'de> Deserialize<'de> for PoolSymbol {
         D: serde::Deserializer<'de>,
     {
         let s: String = Deserialize::deserialize(deserializer)?;
-        match s.as_str() {
-            "ETH-USDC" => Ok(PoolSymbol::EthUsdc),
-            "ETH-USDT" => Ok(PoolSymbol::EthUsdt),
-            "USDC-cbBTC" => Ok(PoolSymbol::UsdcCbbtc),
-            _ => Err(serde::de::Error::custom(format!("Unknown PoolSymbol: {}", s))),
+        match s.split_once('-') {
+            // Configured before its symbol followed the base asset
+            _ if s == "USDC-cbBTC" => Ok(PoolSymbol::USDC_CBBTC),
+            Some((base, quote)) if !base.is_empty() && !quote.is_empty() => Ok(PoolSymbol::from(s)),
+            _ => Err(serde::de::Error::custom(format!(
+                "Invalid PoolSymbol: {}, expected BASE-QUOTE",
+                s
+            ))),
         }
     }
 }
 
+impl Serialize for PoolSymbol {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
 /// Convert from Coinbase symbol to internal pool symbol.
 impl From<CoinbaseSymbol> for PoolSymbol {
     fn from(symbol: CoinbaseSymbol) -> Self {
         match symbol {
-            CoinbaseSymbol::EthUsd => PoolSymbol::EthUsdc,
-            CoinbaseSymbol::BtcUsd => PoolSymbol::UsdcCbbtc,
-            CoinbaseSymbol::EthUsdt => PoolSymbol::EthUsdt,
+            CoinbaseSymbol::EthUsd => PoolSymbol::ETH_USDC,
+            CoinbaseSymbol::BtcUsd => PoolSymbol::USDC_CBBTC,
+            CoinbaseSymbol::EthUsdt => PoolSymbol::ETH_USDT,
         }
     }
 }
 
-/// Convert from internal pool symbol to Coinbase symbol.
-impl From<PoolSymbol> for CoinbaseSymbol {
-    fn from(symbol: PoolSymbol) -> Self {
+/// Convert from internal pool symbol to Coinbase symbol, failing for pairs
+/// Coinbase does not list.
+impl TryFrom<PoolSymbol> for CoinbaseSymbol {
+    type Error = AppError;
+
+    fn try_from(symbol: PoolSymbol) -> Result<Self, Self::Error> {
+        match symbol.as_str() {
+            "ETH-USDC" => Ok(CoinbaseSymbol::EthUsd),
+            "ETH-USDT" => Ok(CoinbaseSymbol::EthUsdt),
+            "BTC-USDC" => Ok(CoinbaseSymbol::BtcUsd),
+            _ => Err(AppError::ConfigError(format!("no Coinbase product for pool {}", symbol))),
+        }
+    }
+}
+
+/// Convert from internal pool symbol to the Binance symbol quoting it, USDT
+/// standing in for USDC, failing for pairs Binance does not list.
+impl TryFrom<PoolSymbol> for BinanceSymbol {
+    type Error = AppError;
+
+    fn try_from(symbol: PoolSymbol) -> Result<Self, Self::Error> {
+        match symbol.as_str() {
+            "ETH-USDC" | "ETH-USDT" => Ok(BinanceSymbol::EthUsdt),
+            "BTC-USDC" => Ok(BinanceSymbol::BtcUsdt),
+            _ => Err(AppError::ConfigError(format!("no Binance symbol for pool {}", symbol))),
+        }
+    }
+}
+
+/// Convert from internal pool symbol to the Kraken symbol quoting it, USD
+/// standing in for USDC, failing for pairs Kraken does not list.
+impl TryFrom<PoolSymbol> for KrakenSymbol {
+    type Error = AppError;
+
+    fn try_from(symbol: PoolSymbol) -> Result<Self, Self::Error> {
+        match symbol.as_str() {
+            "ETH-USDC" => Ok(KrakenSymbol::EthUsd),
+            "ETH-USDT" => Ok(KrakenSymbol::EthUsdt),
+            "BTC-USDC" => Ok(KrakenSymbol::BtcUsd),
+            _ => Err(AppError::ConfigError(format!("no Kraken symbol for pool {}", symbol))),
+        }
+    }
+}
+
+/// Convert from internal pool symbol to the OKX spot instrument quoting it,
+/// which is named like the pool.
+impl From<PoolSymbol> for OkxInstrument {
+    fn from(symbol: PoolSymbol) -> Self { OkxInstrument::new(symbol.as_str()) }
+}
+
+/// Convert from Binance symbol to internal pool symbol, failing for symbols
+/// without a pool.
+impl TryFrom<BinanceSymbol> for PoolSymbol {
+    type Error = AppError;
+
+    fn try_from(symbol: BinanceSymbol) -> Result<Self, Self::Error> {
         match symbol {
-            PoolSymbol::EthUsdc => CoinbaseSymbol::EthUsd,
-            PoolSymbol::EthUsdt => CoinbaseSymbol::EthUsdt,
-            PoolSymbol::UsdcCbbtc => CoinbaseSymbol::BtcUsd,
+            BinanceSymbol::EthUsdt => Ok(PoolSymbol::ETH_USDT),
+            BinanceSymbol::BtcUsdt => Ok(PoolSymbol::USDC_CBBTC),
+            BinanceSymbol::BnbUsdt => {
+                Err(AppError::ConfigError(format!("no pool trades Binance symbol {}", symbol)))
+            },
         }
     }
 }
 
 /// Represents a market-making range for a trading pair.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct MarketMakingRange {
     pub symbol: PoolSymbol,
     pub fair_value: Decimal,
@@ -155,25 +394,374 @@ pub struct MarketMakingRange {
     pub bid_spread_bps: u32,
     pub ask_spread_bps: u32,
     pub total_range_width: Decimal,
+    /// Cost of the transaction placing the range, in the quote asset
+    pub gas_cost: Decimal,
+    /// Trade size in the base asset whose spread capture covers the gas cost,
+    /// none if the range has no width
+    pub break_even_size: Option<Decimal>,
+    /// Ticks bounding the range on the pool, rounded outward to its tick
+    /// spacing, none unless the tick grid of the pool is known
+    pub tick_lower: Option<i32>,
+    pub tick_upper: Option<i32>,
+    /// Amounts of the base (token 0) and quote (token 1) assets depositing
+    /// the simulated capital between the ticks takes at the pool price
+    pub amount_token0: Option<Decimal>,
+    pub amount_token1: Option<Decimal>,
     pub reasoning: String,
     pub market_condition: MarketCondition,
 }
 
 /// Represents the current market condition for trading strategies.
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
 pub enum MarketCondition {
     Normal,
     Volatile,
     Arbitrage,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+/// Direction of an arbitrage between the CEX and the DEX.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ArbitrageDirection {
+    /// DEX price is below the CEX price: buy on the DEX and sell on the CEX
+    BuyDexSellCex,
+    /// CEX price is below the DEX price: buy on the CEX and sell on the DEX
+    BuyCexSellDex,
+}
+
+impl ArbitrageDirection {
+    /// Returns the direction trading both legs the other way around.
+    pub fn opposite(&self) -> Self {
+        match self {
+            ArbitrageDirection::BuyDexSellCex => ArbitrageDirection::BuyCexSellDex,
+            ArbitrageDirection::BuyCexSellDex => ArbitrageDirection::BuyDexSellCex,
+        }
+    }
+}
+
+/// An arbitrage opportunity detected between CEX and DEX prices.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ArbitrageOpportunity {
+    pub symbol: PoolSymbol,
+    pub direction: ArbitrageDirection,
+    pub cex_price: Decimal,
+    pub dex_price: Decimal,
+    /// Expected profit in basis points of the CEX price after known costs
+    pub net_bps: Decimal,
+    /// Size to trade on each leg in the base asset, zero if no size is
+    /// configured
+    pub recommended_size: Decimal,
+    #[serde(with = "sikkara_core::timestamp_with_tz_serializer")]
+    pub detected_at: jiff::Timestamp,
+    /// Time of the CEX quote the opportunity was priced from
+    #[serde(with = "sikkara_core::timestamp_with_tz_serializer")]
+    pub cex_quote_at: jiff::Timestamp,
+    /// Time of the DEX quote the opportunity was priced from
+    #[serde(with = "sikkara_core::timestamp_with_tz_serializer")]
+    pub dex_quote_at: jiff::Timestamp,
+}
+
+impl ArbitrageOpportunity {
+    /// Returns how old the oldest quote of the opportunity is at `now`, zero
+    /// if a quote is timestamped in the future.
+    pub fn quote_age(&self, now: jiff::Timestamp) -> Duration {
+        let oldest = self.cex_quote_at.min(self.dex_quote_at);
+        Duration::try_from(now.duration_since(oldest)).unwrap_or_default()
+    }
+}
+
+/// Reason an arbitrage opportunity was not acted upon.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SuppressionReason {
+    /// A feed the opportunity was priced from is down, so its price is stale
+    FeedDown { source: PriceSource },
+    /// Trading the recommended size would move the pool price too far
+    PriceImpact { impact_bps: u32 },
+}
+
+impl std::fmt::Display for SuppressionReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SuppressionReason::FeedDown { source } => write!(f, "{} feed down", source),
+            SuppressionReason::PriceImpact { impact_bps } => {
+                write!(f, "price impact of {} bps", impact_bps)
+            },
+        }
+    }
+}
+
+/// An arbitrage opportunity detected but suppressed by the strategy.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SuppressedOpportunity {
+    pub opportunity: ArbitrageOpportunity,
+    pub reason: SuppressionReason,
+}
+
+/// Side of the market a price or feed belongs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PriceSource {
+    Cex,
+    Dex,
+}
+
+impl std::fmt::Display for PriceSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PriceSource::Cex => write!(f, "cex"),
+            PriceSource::Dex => write!(f, "dex"),
+        }
+    }
+}
+
+/// Health state of a market data feed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FeedState {
+    Up,
+    Down,
+}
+
+/// Transition of a market data feed to a new health state.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FeedStatus {
+    /// Name of the collector owning the feed
+    pub feed: String,
+    pub source: PriceSource,
+    pub symbol: PoolSymbol,
+    pub state: FeedState,
+    pub reason: String,
+    #[serde(with = "sikkara_core::timestamp_with_tz_serializer")]
+    pub timestamp: jiff::Timestamp,
+}
+
+/// One of the two legs of an arbitrage.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Leg {
+    Cex,
+    Dex,
+}
+
+impl std::fmt::Display for Leg {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Leg::Cex => write!(f, "cex"),
+            Leg::Dex => write!(f, "dex"),
+        }
+    }
+}
+
+/// Order the legs of an opportunity are executed in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LegOrdering {
+    /// The DEX leg, which may revert, first and the CEX leg once it is mined
+    #[default]
+    DexFirst,
+    /// The CEX leg first and the DEX leg once it is filled
+    CexFirst,
+    /// Both legs at once
+    Simultaneous,
+}
+
+/// Why a leg is traded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LegIntent {
+    /// Trades the leg of the opportunity
+    Open,
+    /// Trades the leg back, offsetting a fill the other leg could not match
+    Unwind,
+}
+
+/// Progress of the execution of an opportunity, from its plan down to the
+/// transactions of its DEX leg.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum ExecutionStatus {
+    Planned {
+        ordering: LegOrdering,
+    },
+    /// A risk check refused to execute a leg
+    Blocked {
+        leg: Leg,
+        reason: String,
+    },
+    LegStarted {
+        leg: Leg,
+        intent: LegIntent,
+    },
+    LegFilled {
+        leg: Leg,
+        intent: LegIntent,
+        /// Filled size in the base asset
+        size: Decimal,
+        /// Order id or transaction hash of the fill
+        reference: String,
+    },
+    LegFailed {
+        leg: Leg,
+        intent: LegIntent,
+        reason: String,
+    },
+    /// Both legs filled the same size
+    Completed {
+        size: Decimal,
+    },
+    /// The plan stopped early, after unwinding what was filled
+    Aborted {
+        reason: String,
+    },
+    /// The opportunity was too old or no longer priced by the latest quotes
+    /// when its execution started, nothing was traded
+    Expired {
+        /// Age of the oldest quote of the opportunity
+        age_ms: u64,
+        reason: String,
+    },
+    Submitted {
+        tx_hash: TxHash,
+        nonce: u64,
+    },
+    Confirmed {
+        tx_hash: TxHash,
+        block_number: u64,
+        gas_used: u64,
+    },
+    /// The block which included the transaction was reorged out, it is
+    /// watched until mined again
+    Reorged {
+        tx_hash: TxHash,
+        block_number: u64,
+    },
+    /// The transaction hash is unknown if it failed before being submitted
+    Failed {
+        tx_hash: Option<TxHash>,
+        kind: TxFailureKind,
+        reason: String,
+    },
+}
+
+/// Update of the execution of an opportunity, fed back into the pipeline by
+/// the executor.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ExecutionEvent {
+    /// Correlation id of the action being executed
+    pub correlation_id: Option<u64>,
+    pub symbol: PoolSymbol,
+    pub status: ExecutionStatus,
+    #[serde(with = "sikkara_core::timestamp_with_tz_serializer")]
+    pub timestamp: jiff::Timestamp,
+}
+
+/// Lifecycle state of an order, a CEX order or a DEX transaction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OrderState {
+    Submitted,
+    PartiallyFilled,
+    Filled,
+    /// Cancelled or expired, possibly after a partial fill
+    Cancelled,
+    Failed,
+}
+
+impl OrderState {
+    /// Whether the order can no longer be filled.
+    pub fn is_final(&self) -> bool {
+        matches!(self, OrderState::Filled | OrderState::Cancelled | OrderState::Failed)
+    }
+}
+
+/// Update of an order placed for one leg of an opportunity.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct OrderUpdate {
+    pub venue: Leg,
+    /// Correlation id of the opportunity the order was placed for, none if
+    /// unknown
+    pub correlation_id: Option<u64>,
+    pub symbol: PoolSymbol,
+    /// Order id, or transaction hash of DEX orders
+    pub order_id: String,
+    pub side: OrderSide,
+    pub state: OrderState,
+    /// Cumulative filled size in the base asset
+    pub filled: Decimal,
+    /// Average fill price, none until filled or if unknown
+    pub avg_price: Option<Decimal>,
+    #[serde(with = "sikkara_core::timestamp_with_tz_serializer")]
+    pub timestamp: jiff::Timestamp,
+}
+
+/// Where a balance is held.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BalanceVenue {
+    /// On-chain address
+    Wallet(Address),
+    /// Account on a centralized exchange
+    Cex(Exchange),
+}
+
+impl std::fmt::Display for BalanceVenue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BalanceVenue::Wallet(address) => write!(f, "wallet {}", address),
+            BalanceVenue::Cex(exchange) => write!(f, "{}", exchange),
+        }
+    }
+}
+
+/// Balance of an asset held on a venue.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BalanceUpdate {
+    pub venue: BalanceVenue,
+    pub asset: String,
+    /// Amount available for trading
+    pub free: Decimal,
+    /// Amount held, including any amount on hold
+    pub total: Decimal,
+    #[serde(with = "sikkara_core::timestamp_with_tz_serializer")]
+    pub timestamp: jiff::Timestamp,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
 pub enum InternalAction {
-    Opportunity,
+    Opportunity(ArbitrageOpportunity),
+    Suppressed(SuppressedOpportunity),
+    /// Halts trading across all pools until an operator clears it
+    Halt {
+        reason: String,
+    },
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
 pub enum InternalEvent {
     TickerUpdate(Ticker),
     PoolPriceUpdate(PoolPriceUpdate),
+    FeedStatus(FeedStatus),
+    Execution(ExecutionEvent),
+    BalanceUpdate(BalanceUpdate),
+    OrderUpdate(OrderUpdate),
+    OwnFill(OwnFill),
+}
+
+impl InternalEvent {
+    /// Returns the symbol the event is about, if it is about a single one.
+    pub fn symbol(&self) -> Option<&PoolSymbol> {
+        match self {
+            InternalEvent::TickerUpdate(ticker) => Some(&ticker.symbol),
+            InternalEvent::PoolPriceUpdate(update) => Some(&update.symbol),
+            InternalEvent::FeedStatus(status) => Some(&status.symbol),
+            InternalEvent::Execution(execution) => Some(&execution.symbol),
+            InternalEvent::OrderUpdate(order) => Some(&order.symbol),
+            InternalEvent::OwnFill(fill) => Some(&fill.symbol),
+            InternalEvent::BalanceUpdate(_) => None,
+        }
+    }
 }
@@ -1,8 +1,16 @@
-use std::{pin::Pin, time::Duration};
+use std::{
+    pin::Pin,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
 
+use alloy::primitives::Address;
 use rust_decimal::Decimal;
-use sikkara_adapters::UniswapV4StateViewManager;
-use sikkara_core::AppResult;
+use sikkara_adapters::{
+    PoolKey, PoolSlotDataStream, QuotedPool, UniswapV3StateViewManager, UniswapV4Quoter,
+    UniswapV4StateViewManager,
+};
+use sikkara_core::{AppError, AppResult};
 use tokio_stream::StreamExt;
 
 use crate::engine::{Pool, PoolPriceUpdate, PoolSymbol};
@@ -14,6 +22,9 @@ pub type PoolUpdateStream<'a> =
 /// Trait for subscribing to and managing updates for trading pools.
 #[async_trait::async_trait]
 pub trait PoolFeed {
+    /// Returns the name of the exchange the pools are on, e.g. `uniswap_v4`.
+    fn exchange(&self) -> &'static str;
+
     /// Subscribe to pool updates for a specific trading pair.
     ///    /// Creates a stream that yields updates for the specified trading
     /// pair.
@@ -34,20 +45,304 @@ pub trait PoolFeed {
     async fn unsubscribe_pool_updates(&mut self, pool_symbol: PoolSymbol) -> AppResult<()>;
 }
 
+/// Last divergence between the CEX and DEX prices of a pool, recorded by the
+/// strategy and read by the pool feed to adapt its polling. Its clones share
+/// the same divergence.
+#[derive(Debug, Clone, Default)]
+pub struct DivergenceTracker {
+    last_bps: Arc<Mutex<Option<Decimal>>>,
+}
+
+impl DivergenceTracker {
+    pub fn new() -> Self { Self::default() }
+
+    /// Records the divergence between the prices, in basis points of the CEX
+    /// price.
+    pub fn record(&self, cex_price: Decimal, dex_price: Decimal) {
+        if cex_price.is_zero() {
+            return;
+        }
+        let bps = (cex_price - dex_price).abs() / cex_price * Decimal::new(10000, 0);
+        *self.last_bps.lock().unwrap_or_else(|e| e.into_inner()) = Some(bps);
+    }
+
+    /// Returns the last divergence in basis points, none until both prices
+    /// were seen.
+    pub fn last_bps(&self) -> Option<Decimal> {
+        *self.last_bps.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// How often the state of a pool is polled.
+#[derive(Debug, Clone, Default)]
+pub enum PollPolicy {
+    /// Every `poll_interval` of the pool
+    #[default]
+    Fixed,
+    /// Every `fast_interval` while the last divergence is at least half of
+    /// `threshold_bps`, so that an opportunity building up is caught early,
+    /// and every `poll_interval` of the pool otherwise
+    Adaptive { fast_interval: Duration, threshold_bps: Decimal, divergence: DivergenceTracker },
+    /// Once per new block, and every `poll_interval` of the pool if the node
+    /// cannot subscribe to new blocks
+    Blocks,
+}
+
+impl PollPolicy {
+    /// Returns the interval until the next poll of a pool polled every
+    /// `slow_interval` when quiet.
+    pub fn interval(&self, slow_interval: Duration) -> Duration {
+        match self {
+            PollPolicy::Fixed | PollPolicy::Blocks => slow_interval,
+            PollPolicy::Adaptive { fast_interval, threshold_bps, divergence } => {
+                match divergence.last_bps() {
+                    Some(bps) if bps * Decimal::new(2, 0) >= *threshold_bps => *fast_interval,
+                    _ => slow_interval,
+                }
+            },
+        }
+    }
+}
+
+/// Feed of a Uniswap V4 pool, polled according to its [`PollPolicy`].
+pub struct UniswapV4PoolFeed<P>
+where
+    P: alloy::providers::Provider + Send + Sync,
+{
+    manager: UniswapV4StateViewManager<P>,
+    poll_policy: PollPolicy,
+    /// Quoter of the effective prices of the pool and the size of the base
+    /// asset quoted, if quoted
+    quote: Option<(UniswapV4Quoter<P>, Decimal)>,
+}
+
+impl<P> UniswapV4PoolFeed<P>
+where
+    P: alloy::providers::Provider + Send + Sync,
+{
+    pub fn new(manager: UniswapV4StateViewManager<P>) -> Self {
+        Self { manager, poll_policy: PollPolicy::default(), quote: None }
+    }
+
+    pub fn with_poll_policy(mut self, poll_policy: PollPolicy) -> Self {
+        self.poll_policy = poll_policy;
+        self
+    }
+
+    /// Quotes buying and selling `quote_size` of the base asset on every read
+    /// of the pool state, for the effective prices of the update.
+    pub fn with_quoter(mut self, quoter: UniswapV4Quoter<P>, quote_size: Decimal) -> Self {
+        self.quote = Some((quoter, quote_size));
+        self
+    }
+}
+
 #[async_trait::async_trait]
-impl<P> PoolFeed for UniswapV4StateViewManager<P>
+impl<P> PoolFeed for UniswapV4PoolFeed<P>
 where
     P: alloy::providers::Provider + Send + Sync + 'static,
 {
+    fn exchange(&self) -> &'static str { "uniswap_v4" }
+
     async fn subscribe_pool_updates(&mut self, pool: Pool) -> AppResult<PoolUpdateStream<'_>> {
         let pool_id = pool.compute_pool_id();
+        let (decimals_0, decimals_1) = (pool.token_0.decimals, pool.token_1.decimals);
+        let invert = pool.token_0.address < pool.token_1.address;
+        let stream = match &self.poll_policy {
+            PollPolicy::Fixed => {
+                self.manager
+                    .watch_pool(pool_id, pool.poll_interval, decimals_0, decimals_1, invert)
+            },
+            PollPolicy::Blocks => self.manager.watch_pool_on_blocks(
+                pool_id,
+                pool.poll_interval,
+                decimals_0,
+                decimals_1,
+                invert,
+            ),
+            policy => {
+                let (policy, slow_interval) = (policy.clone(), pool.poll_interval);
+                self.manager.watch_pool_adaptive(
+                    pool_id,
+                    move || policy.interval(slow_interval),
+                    decimals_0,
+                    decimals_1,
+                    invert,
+                )
+            },
+        };
+
+        let Some((quoter, quote_size)) = self.quote.clone() else {
+            return Ok(price_updates(pool, stream));
+        };
+        // The currencies of the pool are sorted by address, the base asset
+        // first if inverted
+        let (decimals_0, decimals_1) =
+            if invert { (decimals_0, decimals_1) } else { (decimals_1, decimals_0) };
+        let quoted_pool = QuotedPool {
+            key: PoolKey::sorted(
+                pool.token_0.address,
+                pool.token_1.address,
+                pool.fee_tier,
+                pool.tick_spacing,
+                pool.hook,
+            ),
+            currency_0_decimals: decimals_0,
+            currency_1_decimals: decimals_1,
+        };
+        let stream = futures::StreamExt::then(price_updates(pool, stream), move |update| {
+            let (quoter, quoted_pool) = (quoter.clone(), quoted_pool.clone());
+            async move { with_effective_prices(update, &quoter, &quoted_pool, quote_size, invert).await }
+        });
+        Ok(Box::pin(stream))
+    }
+
+    async fn unsubscribe_pool_updates(&mut self, _pool_symbol: PoolSymbol) -> AppResult<()> {
+        // Unsubscription logic if needed, currently a no-op
+        Ok(())
+    }
+}
+
+/// Feed of a Uniswap V4 pool watched along with other pools of the same node
+/// by a [`UniswapV4MultiPoolWatcher`](sikkara_adapters::UniswapV4MultiPoolWatcher),
+/// which polls the pool on its behalf. The stream of the pool can only be
+/// subscribed to once.
+pub struct UniswapV4WatchedPoolFeed {
+    stream: Option<PoolSlotDataStream>,
+}
+
+impl UniswapV4WatchedPoolFeed {
+    pub fn new(stream: PoolSlotDataStream) -> Self { Self { stream: Some(stream) } }
+}
+
+#[async_trait::async_trait]
+impl PoolFeed for UniswapV4WatchedPoolFeed {
+    fn exchange(&self) -> &'static str { "uniswap_v4" }
+
+    async fn subscribe_pool_updates(&mut self, pool: Pool) -> AppResult<PoolUpdateStream<'_>> {
+        let stream = self.stream.take().ok_or_else(|| {
+            AppError::ConfigError(format!("pool {} is already subscribed to", pool.symbol))
+        })?;
+        Ok(price_updates(pool, stream))
+    }
+
+    async fn unsubscribe_pool_updates(&mut self, _pool_symbol: PoolSymbol) -> AppResult<()> {
+        Ok(())
+    }
+}
+
+/// Maps the states of a Uniswap V4 pool to its price updates, ending once
+/// the state could not be polled despite the retries.
+fn price_updates(pool: Pool, stream: PoolSlotDataStream) -> PoolUpdateStream<'static> {
+    let symbol = pool.symbol.clone();
+    let failed_symbol = symbol.clone();
+    let stream = stream.map_while(move |pool_slot_data| match pool_slot_data {
+        Ok(pool_slot_data) => Some(pool_slot_data),
+        Err(e) => {
+            tracing::error!("Stopped watching pool {}: {}", failed_symbol, e);
+            None
+        },
+    });
+    let stream = stream.filter_map(move |pool_slot_data| {
+        let price = pool_slot_data.spot_price.to_fixed(pool.scaling, None);
+        let price = match Decimal::from_str_exact(&price) {
+            Ok(p) => p,
+            Err(e) => {
+                tracing::error!("Failed to parse price: {}", e);
+                return None;
+            },
+        };
+        let pool_price_update = PoolPriceUpdate {
+            symbol: symbol.clone(),
+            price,
+            timestamp: pool_slot_data.timestamp,
+            block_number: pool_slot_data.block_number,
+            state: Some(pool_slot_data),
+            effective_buy_price: None,
+            effective_sell_price: None,
+        };
+        Some(pool_price_update)
+    });
+    Box::pin(stream)
+}
+
+/// Quotes selling and buying `quote_size` of the base asset on a pool, the
+/// currency 0 of the pool if `base_is_currency_0`, setting the effective
+/// prices of `update`. A price whose quote failed is left unset.
+async fn with_effective_prices<P>(
+    mut update: PoolPriceUpdate,
+    quoter: &UniswapV4Quoter<P>,
+    pool: &QuotedPool,
+    quote_size: Decimal,
+    base_is_currency_0: bool,
+) -> PoolPriceUpdate
+where
+    P: alloy::providers::Provider + Send + Sync,
+{
+    // Buying pays the quote asset for about the quote size at the pool price
+    let (sell, buy) = tokio::join!(
+        quoter.quote_exact_input(pool, base_is_currency_0, quote_size),
+        quoter.quote_exact_input(pool, !base_is_currency_0, quote_size * update.price),
+    );
+    match sell {
+        Ok(price) => update.effective_sell_price = Some(price),
+        Err(e) => tracing::warn!("Failed to quote selling on pool {}: {}", update.symbol, e),
+    }
+    // The quote is of the base asset received per quote asset paid
+    match buy {
+        Ok(price) if !price.is_zero() => update.effective_buy_price = Some(Decimal::ONE / price),
+        Ok(_) => tracing::warn!("Buying on pool {} is quoted nothing", update.symbol),
+        Err(e) => tracing::warn!("Failed to quote buying on pool {}: {}", update.symbol, e),
+    }
+    update
+}
+
+/// Feed of a Uniswap V3 pool, which is read at its own contract address
+/// rather than identified by the [`Pool`].
+pub struct UniswapV3PoolFeed<P>
+where
+    P: alloy::providers::Provider + Send + Sync,
+{
+    manager: UniswapV3StateViewManager<P>,
+    address: Address,
+}
+
+impl<P> UniswapV3PoolFeed<P>
+where
+    P: alloy::providers::Provider + Send + Sync,
+{
+    pub fn new(manager: UniswapV3StateViewManager<P>, address: Address) -> Self {
+        Self { manager, address }
+    }
+}
+
+#[async_trait::async_trait]
+impl<P> PoolFeed for UniswapV3PoolFeed<P>
+where
+    P: alloy::providers::Provider + Send + Sync + 'static,
+{
+    fn exchange(&self) -> &'static str { "uniswap_v3" }
+
+    async fn subscribe_pool_updates(&mut self, pool: Pool) -> AppResult<PoolUpdateStream<'_>> {
         let symbol = pool.symbol.clone();
-        let stream = self.watch_pool(
-            pool_id,
-            Duration::from_secs(5), // Polling interval
+        let stream = self.manager.watch_pool(
+            self.address,
+            pool.poll_interval,
+            pool.token_0.decimals,
+            pool.token_1.decimals,
             pool.token_0.address < pool.token_1.address,
         );
 
+        // End the updates once the state could not be polled despite the
+        // retries
+        let failed_symbol = symbol.clone();
+        let stream = stream.map_while(move |pool_slot_data| match pool_slot_data {
+            Ok(pool_slot_data) => Some(pool_slot_data),
+            Err(e) => {
+                tracing::error!("Stopped watching pool {}: {}", failed_symbol, e);
+                None
+            },
+        });
         let stream = stream.filter_map(move |pool_slot_data| {
             let price = pool_slot_data.spot_price.to_fixed(pool.scaling, None);
             let price = match Decimal::from_str_exact(&price) {
@@ -57,14 +352,190 @@ where
                     return None;
                 },
             };
-            let pool_price_update = PoolPriceUpdate { symbol: symbol.clone(), price };
-            Some(pool_price_update)
+            Some(PoolPriceUpdate {
+                symbol: symbol.clone(),
+                price,
+                timestamp: pool_slot_data.timestamp,
+                block_number: None,
+                state: None,
+                effective_buy_price: None,
+                effective_sell_price: None,
+            })
         });
         Ok(Box::pin(stream))
     }
 
     async fn unsubscribe_pool_updates(&mut self, _pool_symbol: PoolSymbol) -> AppResult<()> {
-        // Unsubscription logic if needed, currently a no-op
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use alloy::{
+        primitives::{
+            aliases::{I24, U24},
+            Bytes, U160, U256, U64,
+        },
+        providers::ProviderBuilder,
+        sol_types::SolValue,
+        transports::mock::Asserter,
+    };
+    use rust_decimal_macros::dec;
+    use sikkara_adapters::PoolSlotData;
+
+    use super::*;
+    use crate::engine::Token;
+
+    /// `getSlot0` result at a sqrtPriceX96 of 2^96, ABI encoded.
+    const SLOT0_RESULT: &str = "0x\
+        0000000000000000000000000000000000000001000000000000000000000000\
+        0000000000000000000000000000000000000000000000000000000000000000\
+        0000000000000000000000000000000000000000000000000000000000000000\
+        0000000000000000000000000000000000000000000000000000000000000000";
+
+    fn adaptive(divergence: DivergenceTracker) -> PollPolicy {
+        PollPolicy::Adaptive {
+            fast_interval: Duration::from_millis(500),
+            threshold_bps: dec!(50),
+            divergence,
+        }
+    }
+
+    #[test]
+    fn test_divergence_is_in_basis_points_of_the_cex_price() {
+        let divergence = DivergenceTracker::new();
+        assert_eq!(divergence.last_bps(), None);
+        divergence.record(dec!(2000), dec!(1990));
+        assert_eq!(divergence.last_bps(), Some(dec!(50)));
+        // Shared by its clones, whichever price is higher
+        divergence.clone().record(dec!(2000), dec!(2004));
+        assert_eq!(divergence.last_bps(), Some(dec!(20)));
+    }
+
+    #[test]
+    fn test_adaptive_policy_polls_fast_near_the_threshold() {
+        let slow = Duration::from_secs(5);
+        assert_eq!(PollPolicy::Fixed.interval(slow), slow);
+
+        let divergence = DivergenceTracker::new();
+        let policy = adaptive(divergence.clone());
+        // Slow until a divergence is known
+        assert_eq!(policy.interval(slow), slow);
+        divergence.record(dec!(2000), dec!(1995.2));
+        assert_eq!(policy.interval(slow), slow);
+        // Fast from half of the threshold on
+        divergence.record(dec!(2000), dec!(1995));
+        assert_eq!(policy.interval(slow), Duration::from_millis(500));
+        divergence.record(dec!(2000), dec!(1980));
+        assert_eq!(policy.interval(slow), Duration::from_millis(500));
+        // and slow again once the prices converge
+        divergence.record(dec!(2000), dec!(2000));
+        assert_eq!(policy.interval(slow), slow);
+    }
+
+    #[tokio::test]
+    async fn test_pool_updates_carry_the_block_and_time_of_the_state() {
+        let slot0: Bytes = SLOT0_RESULT.parse().unwrap();
+        let asserter = Asserter::new();
+        asserter.push_success(&U64::from(22745131));
+        asserter.push_success(&slot0);
+        asserter.push_success(&slot0);
+        let provider = ProviderBuilder::new()
+            .disable_recommended_fillers()
+            .connect_mocked_client(asserter.clone());
+        let manager =
+            UniswapV4StateViewManager::new(Arc::new(provider), Address::ZERO).with_block_state();
+        let pool = Pool {
+            symbol: PoolSymbol::ETH_USDC,
+            token_0: Token { address: Address::with_last_byte(1), decimals: 18 },
+            token_1: Token { address: Address::with_last_byte(2), decimals: 6 },
+            fee_tier: 500,
+            tick_spacing: 10,
+            hook: Address::ZERO,
+            scaling: 2,
+            poll_interval: Duration::from_secs(5),
+        };
+
+        let before = jiff::Timestamp::now();
+        let mut feed = UniswapV4PoolFeed::new(manager);
+        let mut updates = feed.subscribe_pool_updates(pool).await.unwrap();
+        let update = updates.next().await.unwrap();
+        assert_eq!(update.symbol, PoolSymbol::ETH_USDC);
+        assert_eq!(update.price, dec!(1000000000000));
+        assert_eq!(update.block_number, Some(22745131));
+        assert!(update.timestamp >= before && update.timestamp <= jiff::Timestamp::now());
+        assert!(asserter.read_q().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_watched_pool_feed_is_subscribed_to_once() {
+        let data = PoolSlotData::new(
+            U160::from(1u128 << 96),
+            I24::ZERO,
+            U24::from(0),
+            U24::from(500),
+            18,
+            6,
+            true,
+        );
+        let stream = futures::stream::iter([Ok(data)]);
+        let pool = Pool {
+            symbol: PoolSymbol::ETH_USDC,
+            token_0: Token { address: Address::with_last_byte(1), decimals: 18 },
+            token_1: Token { address: Address::with_last_byte(2), decimals: 6 },
+            fee_tier: 500,
+            tick_spacing: 10,
+            hook: Address::ZERO,
+            scaling: 2,
+            poll_interval: Duration::from_secs(5),
+        };
+
+        let mut feed = UniswapV4WatchedPoolFeed::new(Box::pin(stream));
+        let updates = feed.subscribe_pool_updates(pool.clone()).await.unwrap();
+        let updates = updates.collect::<Vec<_>>().await;
+        assert_eq!(updates.len(), 1);
+        assert_eq!(updates[0].price, dec!(1000000000000));
+        assert!(feed.subscribe_pool_updates(pool).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_quoted_pool_updates_carry_effective_prices() {
+        let slot0: Bytes = SLOT0_RESULT.parse().unwrap();
+        let quote =
+            |amount_out: U256| Bytes::from((amount_out, U256::from(120_000)).abi_encode_params());
+        let asserter = Asserter::new();
+        // getSlot0 and getLiquidity of the read
+        asserter.push_success(&slot0);
+        asserter.push_success(&slot0);
+        // 2 ETH sold for 4990.5 USDC, and 1.6 ETH bought for 2e12 USDC at the
+        // pool price of 1e12
+        asserter.push_success(&quote(U256::from(4_990_500_000u64)));
+        asserter.push_success(&quote(U256::from(16u64) * U256::from(10u64).pow(U256::from(17))));
+        let provider = Arc::new(
+            ProviderBuilder::new()
+                .disable_recommended_fillers()
+                .connect_mocked_client(asserter.clone()),
+        );
+        let manager = UniswapV4StateViewManager::new(provider.clone(), Address::ZERO);
+        let quoter = UniswapV4Quoter::new(provider, Address::ZERO);
+        let pool = Pool {
+            symbol: PoolSymbol::ETH_USDC,
+            token_0: Token { address: Address::with_last_byte(1), decimals: 18 },
+            token_1: Token { address: Address::with_last_byte(2), decimals: 6 },
+            fee_tier: 500,
+            tick_spacing: 10,
+            hook: Address::ZERO,
+            scaling: 2,
+            poll_interval: Duration::from_secs(5),
+        };
+
+        let mut feed = UniswapV4PoolFeed::new(manager).with_quoter(quoter, dec!(2));
+        let mut updates = feed.subscribe_pool_updates(pool).await.unwrap();
+        let update = updates.next().await.unwrap();
+        assert_eq!(update.price, dec!(1000000000000));
+        assert_eq!(update.effective_sell_price, Some(dec!(2495.25)));
+        assert_eq!(update.effective_buy_price, Some(dec!(1250000000000)));
+        assert!(asserter.read_q().is_empty());
+    }
+}
@@ -3,14 +3,28 @@
 //! This module provides a unified interface for subscribing to price feeds from
 //! various cryptocurrency exchanges. It handles message processing, filtering,
 //! and conversion to standardized ticker formats.
-use std::pin::Pin;
+use std::{collections::HashMap, pin::Pin, sync::Arc, time::Duration};
 
-use sikkara_adapters::{CoinbaseChannelMessage, CoinbaseMessage, CoinbaseWsClient};
-use sikkara_core::AppResult;
+use rust_decimal::Decimal;
+use sikkara_adapters::{
+    BinanceMessage, BinanceStream, BinanceSymbol, BinanceWsClient, CoinbaseChannelMessage,
+    CoinbaseLevel2Message, CoinbaseMatchMessage, CoinbaseMessage, CoinbaseResponse, CoinbaseSymbol,
+    CoinbaseUserMatchMessage, CoinbaseUserMessage, CoinbaseWsClient, KrakenChannelMessage,
+    KrakenMessage, KrakenSymbol, KrakenWsClient, OkxChannel, OkxChannelData, OkxInstrument,
+    OkxMessage, OkxWsClient, OrderSide, Side,
+};
+use sikkara_core::{AppError, AppResult};
 use tokio_stream::{wrappers::BroadcastStream, StreamExt};
 use tracing::{debug, error, info, warn};
 
-use crate::engine::{Exchange, PoolSymbol, Ticker};
+use crate::{
+    config::AggregatedPriceFeedConfig,
+    engine::{Exchange, OrderBook, OwnFill, PoolSymbol, Ticker, Trade},
+};
+
+/// Time the CEX has to confirm the subscription of a price feed, which covers
+/// connecting if the feed is subscribed before the connection is up.
+const SUBSCRIPTION_CONFIRMATION_TIMEOUT: Duration = Duration::from_secs(10);
 
 /// A pinned stream that yields ticker data for price feeds.
 ///
@@ -18,6 +32,21 @@ use crate::engine::{Exchange, PoolSymbol, Ticker};
 /// consumed to receive real-time price information from exchanges.
 pub type PriceFeedSubscription<'a> = Pin<Box<dyn tokio_stream::Stream<Item = Ticker> + Send + 'a>>;
 
+/// A pinned stream that yields the trades of a trading pair.
+pub type TradeSubscription<'a> = Pin<Box<dyn tokio_stream::Stream<Item = Trade> + Send + 'a>>;
+
+/// A pinned stream that yields the fills of our own orders on a trading pair.
+pub type OwnFillSubscription<'a> = Pin<Box<dyn tokio_stream::Stream<Item = OwnFill> + Send + 'a>>;
+
+/// A pinned stream that yields the time of every heartbeat of a trading pair.
+pub type HeartbeatSubscription<'a> =
+    Pin<Box<dyn tokio_stream::Stream<Item = jiff::Timestamp> + Send + 'a>>;
+
+/// A pinned stream that yields the order book of a trading pair after every
+/// change.
+pub type OrderBookSubscription<'a> =
+    Pin<Box<dyn tokio_stream::Stream<Item = OrderBook> + Send + 'a>>;
+
 /// Trait for subscribing to and managing price feeds from cryptocurrency
 /// exchanges.
 ///
@@ -27,6 +56,9 @@ pub type PriceFeedSubscription<'a> = Pin<Box<dyn tokio_stream::Stream<Item = Tic
 /// internal ticker representation.
 #[async_trait::async_trait]
 pub trait PriceFeed {
+    /// Returns the exchange the prices are streamed from.
+    fn exchange(&self) -> Exchange;
+
     /// Subscribe to price feed for a specific trading pair.
     ///
     /// Creates a WebSocket subscription to receive real-time price updates
@@ -51,6 +83,71 @@ pub trait PriceFeed {
     ///
     /// * `pool_symbol` - The trading pair symbol to unsubscribe from
     async fn unsubscribe_price_feed(&mut self, pool_symbol: PoolSymbol) -> AppResult<()>;
+
+    /// Subscribe to the trades of a specific trading pair.
+    ///
+    /// The returned stream yields every trade executed on the exchange, e.g.
+    /// to compute volume weighted prices. Exchanges not streaming trades
+    /// return an error.
+    ///
+    /// # Parameters
+    ///
+    /// * `pool_symbol` - The trading pair symbol to subscribe to
+    async fn subscribe_trades(
+        &mut self,
+        _pool_symbol: PoolSymbol,
+    ) -> AppResult<TradeSubscription<'_>> {
+        Err(AppError::ConfigError(format!("{} does not stream trades", self.exchange())).into())
+    }
+
+    /// Subscribe to the fills of our own orders on a specific trading pair.
+    ///
+    /// Exchanges not streaming the fills of our orders, or not authenticated
+    /// to, return an error.
+    ///
+    /// # Parameters
+    ///
+    /// * `pool_symbol` - The trading pair symbol to subscribe to
+    async fn subscribe_own_fills(
+        &mut self,
+        _pool_symbol: PoolSymbol,
+    ) -> AppResult<OwnFillSubscription<'_>> {
+        Err(AppError::ConfigError(format!("{} does not stream own fills", self.exchange())).into())
+    }
+
+    /// Subscribe to the heartbeats of a specific trading pair.
+    ///
+    /// The exchange sends heartbeats at a fixed interval as long as the
+    /// subscription is alive, so their absence reveals a dead feed. Exchanges
+    /// not sending heartbeats return an error.
+    ///
+    /// # Parameters
+    ///
+    /// * `pool_symbol` - The trading pair symbol to subscribe to
+    async fn subscribe_heartbeats(
+        &mut self,
+        _pool_symbol: PoolSymbol,
+    ) -> AppResult<HeartbeatSubscription<'_>> {
+        Err(AppError::ConfigError(format!("{} does not send heartbeats", self.exchange())).into())
+    }
+}
+
+/// Trait for subscribing to the order books of cryptocurrency exchanges, the
+/// counterpart of [`PriceFeed`] for the depth of the market.
+#[async_trait::async_trait]
+pub trait OrderBookFeed {
+    /// Subscribe to the order book of a specific trading pair.
+    ///
+    /// The returned stream yields the whole book once it is known and again
+    /// after every batch of changes.
+    ///
+    /// # Parameters
+    ///
+    /// * `pool_symbol` - The trading pair symbol to subscribe to
+    async fn subscribe_order_book_feed(
+        &mut self,
+        pool_symbol: PoolSymbol,
+    ) -> AppResult<OrderBookSubscription<'_>>;
 }
 
 /// Helper struct to process Coinbase WebSocket messages.
@@ -70,10 +167,10 @@ impl CoinbaseMessageProcessor {
     /// Creates a filtered stream that converts Coinbase messages to Ticker
     /// objects.
     fn create_ticker_stream(
-        receiver: tokio::sync::broadcast::Receiver<CoinbaseMessage>,
+        receiver: tokio::sync::broadcast::Receiver<Arc<CoinbaseMessage>>,
     ) -> impl tokio_stream::Stream<Item = Ticker> {
         BroadcastStream::new(receiver).filter_map(|result| match result {
-            Ok(message) => Self::process_coinbase_message(message),
+            Ok(message) => Self::process_coinbase_message(&message),
             Err(e) => {
                 Self::handle_stream_error(e);
                 None
@@ -84,13 +181,23 @@ impl CoinbaseMessageProcessor {
     /// Processes a Coinbase WebSocket message and extracts ticker data.
     ///
     /// Handles the top-level message types from Coinbase WebSocket API:
-    /// - Channel messages (ticker, heartbeat)
-    /// - Response messages (subscription confirmations)
-    fn process_coinbase_message(message: CoinbaseMessage) -> Option<Ticker> {
+    /// - Channel messages (ticker, heartbeat, status)
+    /// - Response messages (subscription confirmations and errors)
+    fn process_coinbase_message(message: &CoinbaseMessage) -> Option<Ticker> {
         match message {
             CoinbaseMessage::ChannelMessage(channel_msg) => {
                 Self::process_channel_message(channel_msg)
             },
+            // Broadcast separately by the client
+            CoinbaseMessage::User(_) => None,
+            CoinbaseMessage::Response(CoinbaseResponse::Error(error)) => {
+                error!(
+                    "Coinbase error: {}, reason: {}",
+                    error.message,
+                    error.reason.as_deref().unwrap_or("unknown")
+                );
+                None
+            },
             CoinbaseMessage::Response(response) => {
                 debug!("Received subscription response: {:?}", response);
                 None
@@ -103,7 +210,7 @@ impl CoinbaseMessageProcessor {
     /// Handles different types of channel messages from Coinbase:
     /// - Ticker messages containing price and volume data
     /// - Heartbeat messages for connection health monitoring
-    fn process_channel_message(message: CoinbaseChannelMessage) -> Option<Ticker> {
+    fn process_channel_message(message: &CoinbaseChannelMessage) -> Option<Ticker> {
         match message {
             CoinbaseChannelMessage::Ticker(ticker_data) => {
                 Some(Self::convert_to_ticker(ticker_data))
@@ -112,20 +219,187 @@ impl CoinbaseMessageProcessor {
                 debug!("Received heartbeat for product: {}", heartbeat.product_id);
                 None
             },
+            CoinbaseChannelMessage::Match(_)
+            | CoinbaseChannelMessage::Level2(_)
+            | CoinbaseChannelMessage::Candles(_) => None,
+            CoinbaseChannelMessage::Status(status) => {
+                for product in &status.products {
+                    debug!("Coinbase product {} is {}", product.id, product.status);
+                }
+                None
+            },
+        }
+    }
+
+    /// Creates a filtered stream converting the matches of the Coinbase
+    /// product of `receiver` to Trade objects.
+    fn create_trade_stream(
+        receiver: tokio::sync::broadcast::Receiver<Arc<CoinbaseMessage>>,
+    ) -> impl tokio_stream::Stream<Item = Trade> {
+        BroadcastStream::new(receiver).filter_map(|result| match result {
+            Ok(message) => match message.as_ref() {
+                CoinbaseMessage::ChannelMessage(CoinbaseChannelMessage::Match(trade)) => {
+                    Some(Self::convert_to_trade(trade))
+                },
+                _ => None,
+            },
+            Err(e) => {
+                Self::handle_stream_error(e);
+                None
+            },
+        })
+    }
+
+    /// Creates a filtered stream yielding the time of the heartbeats of the
+    /// Coinbase product of `receiver`.
+    fn create_heartbeat_stream(
+        receiver: tokio::sync::broadcast::Receiver<Arc<CoinbaseMessage>>,
+    ) -> impl tokio_stream::Stream<Item = jiff::Timestamp> {
+        BroadcastStream::new(receiver).filter_map(|result| match result {
+            Ok(message) => match message.as_ref() {
+                CoinbaseMessage::ChannelMessage(CoinbaseChannelMessage::Heartbeat(heartbeat)) => {
+                    Some(heartbeat.time)
+                },
+                _ => None,
+            },
+            Err(e) => {
+                Self::handle_stream_error(e);
+                None
+            },
+        })
+    }
+
+    /// Creates a stream maintaining the order book of the Coinbase product of
+    /// `receiver` from its snapshot and changes, yielding it after each.
+    ///
+    /// Changes received before the snapshot are ignored, the snapshot
+    /// superseding them.
+    fn create_order_book_stream(
+        receiver: tokio::sync::broadcast::Receiver<Arc<CoinbaseMessage>>,
+        symbol: PoolSymbol,
+    ) -> impl tokio_stream::Stream<Item = OrderBook> {
+        let mut book = OrderBook::new(Exchange::Coinbase, symbol);
+        let mut snapshotted = false;
+        BroadcastStream::new(receiver).filter_map(move |result| match result {
+            Ok(message) => match message.as_ref() {
+                CoinbaseMessage::ChannelMessage(CoinbaseChannelMessage::Level2(level2)) => {
+                    match level2 {
+                        CoinbaseLevel2Message::Snapshot(snapshot) => {
+                            book.apply_snapshot(
+                                snapshot.bids.iter().copied(),
+                                snapshot.asks.iter().copied(),
+                            );
+                            snapshotted = true;
+                        },
+                        CoinbaseLevel2Message::Update(_) if !snapshotted => return None,
+                        CoinbaseLevel2Message::Update(batch) => {
+                            for (side, price, size) in &batch.changes {
+                                let side = match side {
+                                    Side::Buy => OrderSide::Buy,
+                                    Side::Sell => OrderSide::Sell,
+                                };
+                                book.apply_change(side, *price, *size);
+                            }
+                            book.timestamp = Some(batch.time);
+                        },
+                    }
+                    Some(book.clone())
+                },
+                _ => None,
+            },
+            Err(e) => {
+                // The book misses the changes lost, until the next snapshot
+                Self::handle_stream_error(e);
+                None
+            },
+        })
+    }
+
+    /// Converts a Coinbase match to our internal Trade model, Coinbase
+    /// reporting the side of the maker.
+    fn convert_to_trade(trade: &CoinbaseMatchMessage) -> Trade {
+        Trade {
+            exchange: Exchange::Coinbase,
+            symbol: trade.product_id.clone().into(),
+            trade_id: trade.trade_id,
+            price: trade.price,
+            size: trade.size,
+            taker_side: match trade.side {
+                Side::Buy => OrderSide::Sell,
+                Side::Sell => OrderSide::Buy,
+            },
+            timestamp: trade.time,
+        }
+    }
+
+    /// Creates a filtered stream converting the fills of our orders on a
+    /// Coinbase product to OwnFill objects, the rest of their lifecycle being
+    /// logged.
+    fn create_own_fill_stream(
+        receiver: tokio::sync::broadcast::Receiver<CoinbaseUserMessage>,
+        product_id: CoinbaseSymbol,
+    ) -> impl tokio_stream::Stream<Item = OwnFill> {
+        BroadcastStream::new(receiver).filter_map(move |result| match result {
+            Ok(CoinbaseUserMessage::Match(fill)) if fill.trade.product_id == product_id => {
+                Some(Self::convert_to_own_fill(fill))
+            },
+            Ok(CoinbaseUserMessage::Match(_)) => None,
+            Ok(
+                CoinbaseUserMessage::Received(order)
+                | CoinbaseUserMessage::Open(order)
+                | CoinbaseUserMessage::Done(order)
+                | CoinbaseUserMessage::Change(order),
+            ) => {
+                debug!(
+                    order_id = %order.order_id,
+                    product_id = %order.product_id,
+                    reason = order.reason.as_deref().unwrap_or(""),
+                    "Coinbase order update"
+                );
+                None
+            },
+            Err(e) => {
+                Self::handle_stream_error(e);
+                None
+            },
+        })
+    }
+
+    /// Converts a fill of one of our Coinbase orders to our internal OwnFill
+    /// model.
+    fn convert_to_own_fill(fill: CoinbaseUserMatchMessage) -> OwnFill {
+        OwnFill {
+            exchange: Exchange::Coinbase,
+            symbol: fill.trade.product_id.clone().into(),
+            order_id: fill.order_id().to_string(),
+            trade_id: fill.trade.trade_id,
+            side: match fill.side() {
+                Side::Buy => OrderSide::Buy,
+                Side::Sell => OrderSide::Sell,
+            },
+            price: fill.trade.price,
+            size: fill.trade.size,
+            taker: fill.is_taker(),
+            timestamp: fill.trade.time,
         }
     }
 
     /// Converts Coinbase ticker data to our internal Ticker model.
     ///
     /// Maps Coinbase-specific ticker fields to our standardized ticker format,
-    /// extracting the essential price and timing information needed for
-    /// arbitrage analysis.
-    fn convert_to_ticker(coinbase_ticker: sikkara_adapters::CoinbaseTickerMessage) -> Ticker {
+    /// extracting the price and timing information needed for arbitrage
+    /// analysis along with the top of the book and the daily volume.
+    fn convert_to_ticker(coinbase_ticker: &sikkara_adapters::CoinbaseTickerMessage) -> Ticker {
         Ticker {
-            symbol: coinbase_ticker.product_id.into(),
+            symbol: coinbase_ticker.product_id.clone().into(),
             price: coinbase_ticker.price,
             exchage: Exchange::Coinbase,
             timestamp: coinbase_ticker.time,
+            bid: Some(coinbase_ticker.best_bid),
+            ask: Some(coinbase_ticker.best_ask),
+            bid_size: Some(coinbase_ticker.best_bid_size),
+            ask_size: Some(coinbase_ticker.best_ask_size),
+            volume_24h: Some(coinbase_ticker.volume_24h),
         }
     }
 
@@ -145,22 +419,707 @@ impl CoinbaseMessageProcessor {
 
 #[async_trait::async_trait]
 impl PriceFeed for CoinbaseWsClient {
+    fn exchange(&self) -> Exchange { Exchange::Coinbase }
+
     async fn subscribe_price_feed(
         &mut self,
         pool_symbol: PoolSymbol,
     ) -> AppResult<PriceFeedSubscription<'_>> {
-        let product_ids = vec![pool_symbol.into()];
-        let channels = vec!["ticker".to_string()];
+        // Heartbeats reveal a dead subscription, see `subscribe_heartbeats`
+        let channels =
+            vec![self.channel_mode().ticker_channel().to_string(), "heartbeat".to_string()];
 
-        let receiver = self.subscribe(product_ids, channels)?;
+        // Confirmed, so that a product Coinbase does not list fails at startup
+        // rather than never ticking
+        let receiver = self
+            .subscribe_confirmed(
+                pool_symbol.try_into()?,
+                channels,
+                SUBSCRIPTION_CONFIRMATION_TIMEOUT,
+            )
+            .await?;
         let stream = CoinbaseMessageProcessor::create_ticker_stream(receiver);
 
         Ok(Box::pin(stream))
     }
 
     async fn unsubscribe_price_feed(&mut self, pool_symbol: PoolSymbol) -> AppResult<()> {
-        let product_ids = vec![pool_symbol.into()];
-        let channels = vec!["ticker".to_string()];
+        let product_ids = vec![pool_symbol.try_into()?];
+        // Heartbeats reveal a dead subscription, see `subscribe_heartbeats`
+        let channels =
+            vec![self.channel_mode().ticker_channel().to_string(), "heartbeat".to_string()];
         self.unsubscribe(product_ids, channels)
     }
+
+    async fn subscribe_trades(
+        &mut self,
+        pool_symbol: PoolSymbol,
+    ) -> AppResult<TradeSubscription<'_>> {
+        let receiver = self.subscribe(pool_symbol.try_into()?, vec!["matches".to_string()])?;
+        let stream = CoinbaseMessageProcessor::create_trade_stream(receiver);
+
+        Ok(Box::pin(stream))
+    }
+
+    async fn subscribe_own_fills(
+        &mut self,
+        pool_symbol: PoolSymbol,
+    ) -> AppResult<OwnFillSubscription<'_>> {
+        let product_id = CoinbaseSymbol::try_from(pool_symbol)?;
+        let receiver = self.subscribe_user_events(vec![product_id.clone()])?;
+        let stream = CoinbaseMessageProcessor::create_own_fill_stream(receiver, product_id);
+
+        Ok(Box::pin(stream))
+    }
+
+    async fn subscribe_heartbeats(
+        &mut self,
+        pool_symbol: PoolSymbol,
+    ) -> AppResult<HeartbeatSubscription<'_>> {
+        let receiver = self.subscribe(pool_symbol.try_into()?, vec!["heartbeat".to_string()])?;
+        let stream = CoinbaseMessageProcessor::create_heartbeat_stream(receiver);
+
+        Ok(Box::pin(stream))
+    }
+}
+
+#[async_trait::async_trait]
+impl OrderBookFeed for CoinbaseWsClient {
+    async fn subscribe_order_book_feed(
+        &mut self,
+        pool_symbol: PoolSymbol,
+    ) -> AppResult<OrderBookSubscription<'_>> {
+        let receiver = self.subscribe_order_book(pool_symbol.clone().into())?;
+        let stream = CoinbaseMessageProcessor::create_order_book_stream(receiver, pool_symbol);
+
+        Ok(Box::pin(stream))
+    }
+}
+
+/// Helper struct to process Binance WebSocket messages.
+///
+/// Binance quotes the pairs of several pools with the same symbol, e.g. ETH
+/// against USDT for both ETH-USDC and ETH-USDT, so its tickers are filtered
+/// by Binance symbol and reported for the pool subscribed to.
+struct BinanceMessageProcessor;
+
+impl BinanceMessageProcessor {
+    /// Creates a filtered stream converting the ticker messages of a Binance
+    /// symbol to Ticker objects of the pool.
+    fn create_ticker_stream(
+        receiver: tokio::sync::broadcast::Receiver<BinanceMessage>,
+        symbol: BinanceSymbol,
+        pool_symbol: PoolSymbol,
+    ) -> impl tokio_stream::Stream<Item = Ticker> {
+        BroadcastStream::new(receiver).filter_map(move |result| match result {
+            Ok(BinanceMessage::Ticker(ticker)) if ticker.symbol == symbol => Some(Ticker {
+                symbol: pool_symbol.clone(),
+                price: ticker.last_price,
+                exchage: Exchange::Binance,
+                timestamp: ticker.event_time,
+                bid: None,
+                ask: None,
+                bid_size: None,
+                ask_size: None,
+                volume_24h: None,
+            }),
+            Ok(BinanceMessage::Response(response)) => {
+                debug!("Received Binance response: {:?}", response);
+                None
+            },
+            Ok(_) => None,
+            Err(e) => {
+                CoinbaseMessageProcessor::handle_stream_error(e);
+                None
+            },
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl PriceFeed for BinanceWsClient {
+    fn exchange(&self) -> Exchange { Exchange::Binance }
+
+    async fn subscribe_price_feed(
+        &mut self,
+        pool_symbol: PoolSymbol,
+    ) -> AppResult<PriceFeedSubscription<'_>> {
+        let symbol = BinanceSymbol::try_from(pool_symbol.clone())?;
+        let receiver = self.subscribe(vec![symbol], vec![BinanceStream::Ticker])?;
+        let stream = BinanceMessageProcessor::create_ticker_stream(receiver, symbol, pool_symbol);
+
+        Ok(Box::pin(stream))
+    }
+
+    async fn unsubscribe_price_feed(&mut self, pool_symbol: PoolSymbol) -> AppResult<()> {
+        self.unsubscribe(vec![pool_symbol.try_into()?], vec![BinanceStream::Ticker])
+    }
+}
+
+/// Helper struct to process Kraken WebSocket messages.
+///
+/// Kraken ticker messages carry a list of tickers, the one of the subscribed
+/// symbol is reported for the pool subscribed to.
+struct KrakenMessageProcessor;
+
+impl KrakenMessageProcessor {
+    /// Creates a filtered stream converting the tickers of a Kraken symbol to
+    /// Ticker objects of the pool.
+    fn create_ticker_stream(
+        receiver: tokio::sync::broadcast::Receiver<KrakenMessage>,
+        symbol: KrakenSymbol,
+        pool_symbol: PoolSymbol,
+    ) -> impl tokio_stream::Stream<Item = Ticker> {
+        BroadcastStream::new(receiver).filter_map(move |result| match result {
+            Ok(KrakenMessage::ChannelMessage(KrakenChannelMessage::Ticker(update))) => update
+                .data
+                .into_iter()
+                .find(|ticker| ticker.symbol == symbol)
+                .map(|ticker| Ticker {
+                    symbol: pool_symbol.clone(),
+                    price: ticker.last,
+                    exchage: Exchange::Kraken,
+                    timestamp: ticker.timestamp,
+                    bid: None,
+                    ask: None,
+                    bid_size: None,
+                    ask_size: None,
+                    volume_24h: None,
+                }),
+            Ok(KrakenMessage::Response(response)) => {
+                debug!("Received Kraken response: {:?}", response);
+                None
+            },
+            Ok(_) => None,
+            Err(e) => {
+                CoinbaseMessageProcessor::handle_stream_error(e);
+                None
+            },
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl PriceFeed for KrakenWsClient {
+    fn exchange(&self) -> Exchange { Exchange::Kraken }
+
+    async fn subscribe_price_feed(
+        &mut self,
+        pool_symbol: PoolSymbol,
+    ) -> AppResult<PriceFeedSubscription<'_>> {
+        let symbol = KrakenSymbol::try_from(pool_symbol.clone())?;
+        let receiver = self.subscribe(vec![symbol], "ticker".to_string())?;
+        let stream = KrakenMessageProcessor::create_ticker_stream(receiver, symbol, pool_symbol);
+
+        Ok(Box::pin(stream))
+    }
+
+    async fn unsubscribe_price_feed(&mut self, pool_symbol: PoolSymbol) -> AppResult<()> {
+        self.unsubscribe(vec![pool_symbol.try_into()?], "ticker".to_string())
+    }
+}
+
+/// Helper struct to process OKX WebSocket messages.
+///
+/// OKX ticker messages carry a list of tickers, the one of the subscribed
+/// instrument is reported for the pool subscribed to.
+struct OkxMessageProcessor;
+
+impl OkxMessageProcessor {
+    /// Creates a filtered stream converting the tickers of an OKX instrument
+    /// to Ticker objects of the pool.
+    fn create_ticker_stream(
+        receiver: tokio::sync::broadcast::Receiver<OkxMessage>,
+        instrument: OkxInstrument,
+        pool_symbol: PoolSymbol,
+    ) -> impl tokio_stream::Stream<Item = Ticker> {
+        BroadcastStream::new(receiver).filter_map(move |result| match result {
+            Ok(OkxMessage::ChannelMessage(message)) => match message.data {
+                OkxChannelData::Tickers(tickers) => tickers
+                    .into_iter()
+                    .find(|ticker| ticker.inst_id == instrument)
+                    .map(|ticker| Ticker {
+                        symbol: pool_symbol.clone(),
+                        price: ticker.last,
+                        exchage: Exchange::Okx,
+                        timestamp: ticker.ts,
+                        bid: None,
+                        ask: None,
+                        bid_size: None,
+                        ask_size: None,
+                        volume_24h: None,
+                    }),
+                OkxChannelData::Books(_) => None,
+            },
+            Ok(OkxMessage::Event(event)) => {
+                debug!("Received OKX event: {:?}", event);
+                None
+            },
+            Err(e) => {
+                CoinbaseMessageProcessor::handle_stream_error(e);
+                None
+            },
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl PriceFeed for OkxWsClient {
+    fn exchange(&self) -> Exchange { Exchange::Okx }
+
+    async fn subscribe_price_feed(
+        &mut self,
+        pool_symbol: PoolSymbol,
+    ) -> AppResult<PriceFeedSubscription<'_>> {
+        let instrument = OkxInstrument::from(pool_symbol.clone());
+        let receiver = self.subscribe(vec![instrument.clone()], OkxChannel::Tickers)?;
+        let stream = OkxMessageProcessor::create_ticker_stream(receiver, instrument, pool_symbol);
+
+        Ok(Box::pin(stream))
+    }
+
+    async fn unsubscribe_price_feed(&mut self, pool_symbol: PoolSymbol) -> AppResult<()> {
+        self.unsubscribe(vec![pool_symbol.into()], OkxChannel::Tickers)
+    }
+}
+
+/// Price feed of the median price of several exchanges, which a single
+/// exchange cannot move on its own.
+///
+/// The tickers of all the feeds are merged, each tagged with the exchange of
+/// its feed. After every ticker, the median of the last price of each exchange
+/// is streamed as a ticker of [`Exchange::Aggregated`], once at least
+/// `min_sources` exchanges priced the pair.
+pub struct AggregatedPriceFeed {
+    feeds: Vec<Box<dyn PriceFeed + Send + Sync>>,
+    min_sources: usize,
+}
+
+impl AggregatedPriceFeed {
+    pub fn new(
+        feeds: Vec<Box<dyn PriceFeed + Send + Sync>>,
+        config: &AggregatedPriceFeedConfig,
+    ) -> Self {
+        Self { feeds, min_sources: config.min_sources }
+    }
+}
+
+/// Returns the median of the prices, the mean of the middle two for an even
+/// number of prices.
+pub fn median_price(prices: &[Decimal]) -> Option<Decimal> {
+    let mut sorted = prices.to_vec();
+    sorted.sort();
+    let middle = sorted.len() / 2;
+    match sorted.len() {
+        0 => None,
+        len if len % 2 == 0 => Some((sorted[middle - 1] + sorted[middle]) / Decimal::new(2, 0)),
+        _ => Some(sorted[middle]),
+    }
+}
+
+#[async_trait::async_trait]
+impl PriceFeed for AggregatedPriceFeed {
+    fn exchange(&self) -> Exchange { Exchange::Aggregated }
+
+    async fn subscribe_price_feed(
+        &mut self,
+        pool_symbol: PoolSymbol,
+    ) -> AppResult<PriceFeedSubscription<'_>> {
+        let mut streams: Vec<PriceFeedSubscription<'_>> = Vec::with_capacity(self.feeds.len());
+        for feed in self.feeds.iter_mut() {
+            let exchange = feed.exchange();
+            let stream = feed.subscribe_price_feed(pool_symbol.clone()).await?;
+            streams.push(Box::pin(stream.map(move |mut ticker| {
+                ticker.exchage = exchange.clone();
+                ticker
+            })));
+        }
+        let Some(merged) = streams
+            .into_iter()
+            .reduce(|merged, stream| Box::pin(merged.merge(stream)))
+        else {
+            return Err(AppError::ConfigError(
+                "an aggregated price feed needs at least one feed".to_string(),
+            )
+            .into());
+        };
+
+        let min_sources = self.min_sources;
+        let mut last_prices = HashMap::new();
+        let stream = merged.filter_map(move |ticker| {
+            last_prices.insert(ticker.exchage.clone(), ticker.price);
+            if last_prices.len() < min_sources {
+                return None;
+            }
+            let prices = last_prices.values().copied().collect::<Vec<_>>();
+            Some(Ticker {
+                exchage: Exchange::Aggregated,
+                symbol: ticker.symbol,
+                price: median_price(&prices)?,
+                timestamp: ticker.timestamp,
+                bid: None,
+                ask: None,
+                bid_size: None,
+                ask_size: None,
+                volume_24h: None,
+            })
+        });
+        Ok(Box::pin(stream))
+    }
+
+    async fn unsubscribe_price_feed(&mut self, pool_symbol: PoolSymbol) -> AppResult<()> {
+        for feed in self.feeds.iter_mut() {
+            feed.unsubscribe_price_feed(pool_symbol.clone()).await?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal_macros::dec;
+    use sikkara_adapters::CoinbaseCredentials;
+    use sikkara_core::Secret;
+    use sikkara_wsclient::WsCallback;
+    use tokio::sync::{broadcast, mpsc};
+    use tokio_tungstenite::tungstenite::{Message, Utf8Bytes};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_coinbase_matches_are_streamed_as_trades() {
+        let (sender, _receiver) = mpsc::channel(4);
+        let (broadcaster, _) = broadcast::channel(4);
+        let mut client = CoinbaseWsClient::new("wss://coinbase".to_string(), sender, broadcaster);
+        let mut feed = client.clone();
+        let mut stream = feed.subscribe_trades(PoolSymbol::ETH_USDC).await.unwrap();
+
+        let trade = |product_id: &str, trade_id: u64, side: &str| {
+            serde_json::json!({
+                "type": "match", "trade_id": trade_id, "sequence": 1,
+                "maker_order_id": "ac928c66-ca53-498f-9c13-a110027a60e8",
+                "taker_order_id": "132fb6ae-456b-4654-b4e0-d681ac05cea1",
+                "side": side, "size": "0.0152", "price": "2687.37",
+                "product_id": product_id, "time": "2025-02-12T21:12:33.912345Z"
+            })
+        };
+        let heartbeat = serde_json::json!({
+            "type": "heartbeat", "last_trade_id": 1, "product_id": "ETH-USD",
+            "sequence": 1, "time": "2025-02-12T21:12:33.912345Z"
+        });
+        for json in [trade("BTC-USD", 1, "sell"), heartbeat, trade("ETH-USD", 2, "sell")] {
+            let message = Message::Text(Utf8Bytes::from(json.to_string()));
+            client
+                .on_message(message, jiff::Timestamp::now())
+                .await
+                .unwrap();
+        }
+
+        let received = stream.next().await.unwrap();
+        assert_eq!(
+            received,
+            Trade {
+                exchange: Exchange::Coinbase,
+                symbol: PoolSymbol::ETH_USDC,
+                trade_id: 2,
+                price: dec!(2687.37),
+                size: dec!(0.0152),
+                taker_side: OrderSide::Buy,
+                timestamp: "2025-02-12T21:12:33.912345Z".parse().unwrap(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_coinbase_ticker_and_ticker_batch_messages_are_tickers() {
+        let ticker = |kind: &str| {
+            serde_json::from_value::<CoinbaseMessage>(serde_json::json!({
+                "type": kind, "sequence": 1, "product_id": "ETH-USD", "price": "2687.37",
+                "open_24h": "2600", "volume_24h": "1000", "low_24h": "2550",
+                "high_24h": "2700", "volume_30d": "30000", "best_bid": "2687.36",
+                "best_bid_size": "1.5", "best_ask": "2687.38", "best_ask_size": "2.5",
+                "side": "buy", "time": "2025-02-12T21:12:33.912345Z", "trade_id": 1,
+                "last_size": "0.0152"
+            }))
+            .unwrap()
+        };
+
+        for kind in ["ticker", "ticker_batch"] {
+            let received = CoinbaseMessageProcessor::process_coinbase_message(&ticker(kind));
+            let received = received.unwrap_or_else(|| panic!("{kind} is not a ticker"));
+            assert_eq!(received.symbol, PoolSymbol::ETH_USDC);
+            assert_eq!(received.price, dec!(2687.37));
+            // The top of the book and the daily volume are carried along
+            assert_eq!((received.bid, received.ask), (Some(dec!(2687.36)), Some(dec!(2687.38))));
+            assert_eq!((received.bid_size, received.ask_size), (Some(dec!(1.5)), Some(dec!(2.5))));
+            assert_eq!(received.volume_24h, Some(dec!(1000)));
+        }
+    }
+
+    #[test]
+    fn test_coinbase_status_and_error_messages_are_not_tickers() {
+        let message =
+            |json: serde_json::Value| serde_json::from_value::<CoinbaseMessage>(json).unwrap();
+        let status = message(serde_json::json!({
+            "type": "status",
+            "products": [{
+                "id": "ETH-USD", "base_currency": "ETH", "quote_currency": "USD",
+                "status": "online", "status_message": ""
+            }],
+            "currencies": []
+        }));
+        let error = message(serde_json::json!({
+            "type": "error",
+            "message": "Failed to subscribe",
+            "reason": "XYZ-USD is not a valid product"
+        }));
+
+        assert!(CoinbaseMessageProcessor::process_coinbase_message(&status).is_none());
+        assert!(CoinbaseMessageProcessor::process_coinbase_message(&error).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_coinbase_order_book_is_maintained_from_its_changes() {
+        let (sender, _receiver) = mpsc::channel(4);
+        let (broadcaster, _) = broadcast::channel(4);
+        let mut client = CoinbaseWsClient::new("wss://coinbase".to_string(), sender, broadcaster);
+        let mut feed = client.clone();
+        let mut stream = feed
+            .subscribe_order_book_feed(PoolSymbol::ETH_USDC)
+            .await
+            .unwrap();
+
+        let update = |changes: serde_json::Value| {
+            serde_json::json!({
+                "type": "l2update", "product_id": "ETH-USD", "changes": changes,
+                "time": "2025-02-12T21:12:33.912345Z"
+            })
+        };
+        let snapshot = serde_json::json!({
+            "type": "snapshot", "product_id": "ETH-USD",
+            "bids": [["2686.83", "2"], ["2686.5", "0.5"]],
+            "asks": [["2687.37", "0.03"], ["2688", "1"]]
+        });
+        let changes = update(serde_json::json!([
+            ["buy", "2686.90", "1.5"],
+            ["sell", "2687.37", "0"],
+            ["buy", "2686.5", "0"]
+        ]));
+        // The change preceding the snapshot is superseded by it
+        for json in [update(serde_json::json!([["buy", "2690", "1"]])), snapshot, changes] {
+            let message = Message::Text(Utf8Bytes::from(json.to_string()));
+            client
+                .on_message(message, jiff::Timestamp::now())
+                .await
+                .unwrap();
+        }
+
+        let book = stream.next().await.unwrap();
+        assert_eq!(book.best_bid(), Some((dec!(2686.83), dec!(2))));
+        assert_eq!(book.best_ask(), Some((dec!(2687.37), dec!(0.03))));
+        assert_eq!(book.timestamp, None);
+
+        let book = stream.next().await.unwrap();
+        assert_eq!(book.symbol, PoolSymbol::ETH_USDC);
+        assert_eq!(book.best_bid(), Some((dec!(2686.90), dec!(1.5))));
+        assert_eq!(book.best_ask(), Some((dec!(2688), dec!(1))));
+        assert_eq!(book.mid_price(), Some(dec!(2687.45)));
+        assert_eq!(
+            book.bids().map(|(price, _)| *price).collect::<Vec<_>>(),
+            vec![dec!(2686.90), dec!(2686.83)]
+        );
+        assert_eq!(book.timestamp, Some("2025-02-12T21:12:33.912345Z".parse().unwrap()));
+    }
+
+    #[tokio::test]
+    async fn test_coinbase_own_matches_are_streamed_as_fills() {
+        let (sender, _receiver) = mpsc::channel(4);
+        let (broadcaster, _) = broadcast::channel(4);
+        let credentials = CoinbaseCredentials {
+            api_key: "test-api-key".to_string(),
+            api_secret: Secret::new("c2lrYXJyYQ=="),
+            passphrase: Secret::new("test-passphrase"),
+        };
+        let mut client = CoinbaseWsClient::new_with_auth(
+            "wss://coinbase".to_string(),
+            sender,
+            broadcaster,
+            credentials,
+        );
+        let mut callback = client.clone();
+        let mut stream = client
+            .subscribe_own_fills(PoolSymbol::ETH_USDC)
+            .await
+            .unwrap();
+
+        let user = serde_json::json!({
+            "user_id": "5844eceecf7e803e259d0365",
+            "profile_id": "765d1549-9660-4be2-97d4-fa2d65fa3352",
+        });
+        let fill = |product_id: &str, trade_id: u64| {
+            serde_json::json!({
+                "type": "match", "trade_id": trade_id, "sequence": 1,
+                "maker_order_id": "ac928c66-ca53-498f-9c13-a110027a60e8",
+                "taker_order_id": "d50ec984-77a8-460a-b958-66f114b0de9b",
+                "side": "buy", "size": "0.5", "price": "2686.90",
+                "product_id": product_id, "time": "2025-02-12T21:12:34.000002Z",
+                "user_id": user["user_id"], "profile_id": user["profile_id"],
+                "maker_user_id": user["user_id"], "maker_profile_id": user["profile_id"]
+            })
+        };
+        let done = serde_json::json!({
+            "type": "done", "order_id": "ac928c66-ca53-498f-9c13-a110027a60e8",
+            "product_id": "ETH-USD", "side": "buy", "reason": "filled", "sequence": 2,
+            "time": "2025-02-12T21:12:34.000003Z",
+            "user_id": user["user_id"], "profile_id": user["profile_id"]
+        });
+        for message in [fill("BTC-USD", 1), done, fill("ETH-USD", 2)] {
+            callback
+                .on_message(
+                    Message::Text(Utf8Bytes::from(message.to_string())),
+                    jiff::Timestamp::now(),
+                )
+                .await
+                .unwrap();
+        }
+
+        let received = stream.next().await.unwrap();
+        assert_eq!(
+            received,
+            OwnFill {
+                exchange: Exchange::Coinbase,
+                symbol: PoolSymbol::ETH_USDC,
+                order_id: "ac928c66-ca53-498f-9c13-a110027a60e8".to_string(),
+                trade_id: 2,
+                side: OrderSide::Buy,
+                price: dec!(2686.90),
+                size: dec!(0.5),
+                taker: false,
+                timestamp: "2025-02-12T21:12:34.000002Z".parse().unwrap(),
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_own_fills_require_credentials() {
+        let (sender, _receiver) = mpsc::channel(4);
+        let (broadcaster, _) = broadcast::channel(4);
+        let mut client = CoinbaseWsClient::new("wss://coinbase".to_string(), sender, broadcaster);
+        assert!(client
+            .subscribe_own_fills(PoolSymbol::ETH_USDC)
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_trades_are_not_streamed_by_every_exchange() {
+        let (sender, _receiver) = mpsc::channel(4);
+        let (broadcaster, _) = broadcast::channel(4);
+        let mut client = BinanceWsClient::new("wss://binance".to_string(), sender, broadcaster);
+        assert!(client.subscribe_trades(PoolSymbol::ETH_USDC).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_binance_tickers_are_reported_for_the_subscribed_pool() {
+        let (sender, _receiver) = mpsc::channel(4);
+        let (broadcaster, _) = broadcast::channel(4);
+        let mut client =
+            BinanceWsClient::new("wss://binance".to_string(), sender, broadcaster.clone());
+        let mut stream = client
+            .subscribe_price_feed(PoolSymbol::ETH_USDC)
+            .await
+            .unwrap();
+
+        let ticker = |symbol: &str, price: &str| {
+            serde_json::from_value::<BinanceMessage>(serde_json::json!({
+                "e": "24hrTicker", "E": 1739394753778_u64, "s": symbol,
+                "p": "0", "P": "0", "w": "0", "c": price, "Q": "0",
+                "b": "0", "B": "0", "a": "0", "A": "0", "o": "0", "h": "0", "l": "0",
+                "v": "0", "q": "0", "O": 1739308353778_u64, "C": 1739394753778_u64,
+                "F": 0, "L": 0, "n": 0
+            }))
+            .unwrap()
+        };
+        broadcaster.send(ticker("BTCUSDT", "97000.5")).unwrap();
+        broadcaster.send(ticker("ETHUSDT", "2687.37")).unwrap();
+
+        let received = stream.next().await.unwrap();
+        assert_eq!(received.symbol, PoolSymbol::ETH_USDC);
+        assert_eq!(received.exchage, Exchange::Binance);
+        assert_eq!(received.price, dec!(2687.37));
+        assert_eq!(received.timestamp.to_string(), "2025-02-12T21:12:33.778Z");
+    }
+
+    /// Price feed replaying the tickers sent through a channel.
+    struct FakeFeed {
+        exchange: Exchange,
+        receiver: Option<mpsc::Receiver<Ticker>>,
+    }
+
+    #[async_trait::async_trait]
+    impl PriceFeed for FakeFeed {
+        fn exchange(&self) -> Exchange { self.exchange.clone() }
+
+        async fn subscribe_price_feed(
+            &mut self,
+            _pool_symbol: PoolSymbol,
+        ) -> AppResult<PriceFeedSubscription<'_>> {
+            let receiver = self.receiver.take().unwrap();
+            Ok(Box::pin(tokio_stream::wrappers::ReceiverStream::new(receiver)))
+        }
+
+        async fn unsubscribe_price_feed(&mut self, _pool_symbol: PoolSymbol) -> AppResult<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_median_price() {
+        assert_eq!(median_price(&[]), None);
+        assert_eq!(median_price(&[dec!(2600), dec!(2500), dec!(2510)]), Some(dec!(2510)));
+        assert_eq!(median_price(&[dec!(2600), dec!(2500)]), Some(dec!(2550)));
+    }
+
+    #[tokio::test]
+    async fn test_aggregated_feed_streams_the_median_once_quorate() {
+        let mut senders = Vec::new();
+        let mut feeds: Vec<Box<dyn PriceFeed + Send + Sync>> = Vec::new();
+        for exchange in [Exchange::Coinbase, Exchange::Binance, Exchange::Okx] {
+            let (sender, receiver) = mpsc::channel(4);
+            senders.push(sender);
+            feeds.push(Box::new(FakeFeed { exchange, receiver: Some(receiver) }));
+        }
+        let config = AggregatedPriceFeedConfig { min_sources: 2 };
+        let mut feed = AggregatedPriceFeed::new(feeds, &config);
+        assert_eq!(feed.exchange(), Exchange::Aggregated);
+        let mut stream = feed
+            .subscribe_price_feed(PoolSymbol::ETH_USDC)
+            .await
+            .unwrap();
+
+        // The tickers of the feeds are all tagged Coinbase, the aggregate
+        // telling them apart by their feed
+        let ticker = |price| Ticker {
+            exchage: Exchange::Coinbase,
+            symbol: PoolSymbol::ETH_USDC,
+            price,
+            timestamp: "2025-02-12T21:12:33Z".parse().unwrap(),
+            bid: None,
+            ask: None,
+            bid_size: None,
+            ask_size: None,
+            volume_24h: None,
+        };
+        senders[0].send(ticker(dec!(2500))).await.unwrap();
+        senders[1].send(ticker(dec!(2510))).await.unwrap();
+        let median = stream.next().await.unwrap();
+        assert_eq!(median.exchage, Exchange::Aggregated);
+        assert_eq!(median.price, dec!(2505));
+
+        // An outlier moves the median to the next price only
+        senders[2].send(ticker(dec!(2600))).await.unwrap();
+        assert_eq!(stream.next().await.unwrap().price, dec!(2510));
+        senders[0].send(ticker(dec!(2520))).await.unwrap();
+        assert_eq!(stream.next().await.unwrap().price, dec!(2520));
+    }
 }
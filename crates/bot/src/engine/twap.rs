@@ -0,0 +1,68 @@
+//! Time-weighted average price of a feed.
+//!
+//! A [`TwapCalculator`] keeps the prices of the last `window` and averages
+//! them, smoothing out the noise of the individual ticks of a CEX feed.
+
+use std::{collections::VecDeque, time::Duration};
+
+use rust_decimal::Decimal;
+
+/// Average of the prices within a sliding time window.
+#[derive(Debug, Clone)]
+pub struct TwapCalculator {
+    window: Duration,
+    samples: VecDeque<(jiff::Timestamp, Decimal)>,
+}
+
+impl TwapCalculator {
+    /// Creates a calculator averaging the prices of the last `window`.
+    pub fn new(window: Duration) -> Self { Self { window, samples: VecDeque::new() } }
+
+    /// Appends a price, evicting the prices older than `window` before
+    /// `timestamp`.
+    pub fn push(&mut self, price: Decimal, timestamp: jiff::Timestamp) {
+        self.samples.push_back((timestamp, price));
+        let Ok(since) = timestamp.checked_sub(self.window) else { return };
+        while self.samples.front().is_some_and(|(at, _)| *at < since) {
+            self.samples.pop_front();
+        }
+    }
+
+    /// Returns the arithmetic mean of the prices within the window, if any.
+    pub fn twap(&self) -> Option<Decimal> {
+        if self.samples.is_empty() {
+            return None;
+        }
+        let sum = self.samples.iter().map(|(_, price)| price).sum::<Decimal>();
+        Some(sum / Decimal::from(self.samples.len()))
+    }
+
+    /// Returns the number of prices within the window.
+    pub fn sample_count(&self) -> usize { self.samples.len() }
+}
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal_macros::dec;
+
+    use super::*;
+
+    fn at(second: i64) -> jiff::Timestamp { jiff::Timestamp::from_second(second).unwrap() }
+
+    #[test]
+    fn test_twap_of_the_window() {
+        let mut twap = TwapCalculator::new(Duration::from_secs(30));
+        assert_eq!(twap.twap(), None);
+
+        twap.push(dec!(2400), at(0));
+        twap.push(dec!(2500), at(10));
+        twap.push(dec!(2600), at(30));
+        assert_eq!(twap.sample_count(), 3);
+        assert_eq!(twap.twap(), Some(dec!(2500)));
+
+        // The price at 0 falls out of the window
+        twap.push(dec!(2700), at(31));
+        assert_eq!(twap.sample_count(), 3);
+        assert_eq!(twap.twap(), Some(dec!(2600)));
+    }
+}
@@ -0,0 +1,177 @@
+//! Fan-out hub distributing serialized events to external stream clients.
+//!
+//! Every connected client owns a bounded queue. Publishing never waits on a
+//! client: when a queue is full the message is dropped and counted, so a slow
+//! or stuck consumer can never backpressure the engines feeding the hub.
+
+use std::{
+    collections::HashSet,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use serde::Serialize;
+use tokio::sync::mpsc::{self, error::TrySendError};
+use tracing::{debug, error};
+
+/// Serialized message as delivered to stream clients.
+pub type StreamPayload = Arc<str>;
+
+/// Kind of message forwarded to stream clients.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StreamKind {
+    Event,
+    Action,
+}
+
+/// Wire format of every message sent to stream clients.
+#[derive(Serialize)]
+struct StreamEnvelope<'a> {
+    kind: StreamKind,
+    data: &'a serde_json::Value,
+}
+
+/// A client registered with the hub.
+struct StreamClient {
+    id: u64,
+    filter: Option<HashSet<String>>,
+    sender: mpsc::Sender<StreamPayload>,
+    dropped: Arc<AtomicU64>,
+}
+
+impl StreamClient {
+    /// Returns true if the client is interested in messages of the given type.
+    fn accepts(&self, message_type: &str) -> bool {
+        self.filter
+            .as_ref()
+            .is_none_or(|types| types.contains(message_type))
+    }
+}
+
+struct HubInner {
+    clients: Mutex<Vec<StreamClient>>,
+    next_id: AtomicU64,
+    client_queue_size: usize,
+    dropped: AtomicU64,
+}
+
+/// Shared handle to the fan-out hub. Cloning is cheap and all clones publish
+/// to the same set of clients.
+#[derive(Clone)]
+pub struct EventStreamHub {
+    inner: Arc<HubInner>,
+}
+
+impl std::fmt::Debug for EventStreamHub {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EventStreamHub")
+            .field("clients", &self.client_count())
+            .field("client_queue_size", &self.inner.client_queue_size)
+            .field("dropped", &self.dropped_messages())
+            .finish()
+    }
+}
+
+/// Receiving side of a client registration.
+pub struct StreamSubscription {
+    pub id: u64,
+    pub receiver: mpsc::Receiver<StreamPayload>,
+    dropped: Arc<AtomicU64>,
+}
+
+impl StreamSubscription {
+    /// Number of messages dropped for this client because its queue was full.
+    pub fn dropped_messages(&self) -> u64 { self.dropped.load(Ordering::Relaxed) }
+}
+
+impl EventStreamHub {
+    /// Creates a new hub where every client can buffer up to
+    /// `client_queue_size` messages.
+    pub fn new(client_queue_size: usize) -> Self {
+        Self {
+            inner: Arc::new(HubInner {
+                clients: Mutex::new(Vec::new()),
+                next_id: AtomicU64::new(0),
+                client_queue_size: client_queue_size.max(1),
+                dropped: AtomicU64::new(0),
+            }),
+        }
+    }
+
+    /// Registers a new client, optionally restricted to the given message
+    /// types (e.g. `ticker_update`, `opportunity`).
+    pub fn register(&self, filter: Option<HashSet<String>>) -> StreamSubscription {
+        let (sender, receiver) = mpsc::channel(self.inner.client_queue_size);
+        let id = self.inner.next_id.fetch_add(1, Ordering::Relaxed);
+        let dropped = Arc::new(AtomicU64::new(0));
+        let client = StreamClient { id, filter, sender, dropped: dropped.clone() };
+        self.lock_clients().push(client);
+        StreamSubscription { id, receiver, dropped }
+    }
+
+    /// Removes a client from the hub.
+    pub fn unregister(&self, id: u64) { self.lock_clients().retain(|client| client.id != id); }
+
+    /// Number of currently connected clients.
+    pub fn client_count(&self) -> usize { self.lock_clients().len() }
+
+    /// Total number of messages dropped across all clients.
+    pub fn dropped_messages(&self) -> u64 { self.inner.dropped.load(Ordering::Relaxed) }
+
+    /// Serializes the message once and offers it to every interested client
+    /// without waiting.
+    pub fn publish<T: Serialize>(&self, kind: StreamKind, message: &T) {
+        if self.client_count() == 0 {
+            return;
+        }
+
+        let data = match serde_json::to_value(message) {
+            Ok(data) => data,
+            Err(e) => {
+                error!("failed to serialize stream message: {}", e);
+                return;
+            },
+        };
+        let message_type = data
+            .get("type")
+            .and_then(|t| t.as_str())
+            .unwrap_or_default()
+            .to_string();
+        let payload: StreamPayload =
+            match serde_json::to_string(&StreamEnvelope { kind, data: &data }) {
+                Ok(payload) => payload.into(),
+                Err(e) => {
+                    error!("failed to serialize stream envelope: {}", e);
+                    return;
+                },
+            };
+
+        self.lock_clients().retain(|client| {
+            if !client.accepts(&message_type) {
+                return true;
+            }
+            match client.sender.try_send(payload.clone()) {
+                Ok(_) => true,
+                Err(TrySendError::Full(_)) => {
+                    client.dropped.fetch_add(1, Ordering::Relaxed);
+                    self.inner.dropped.fetch_add(1, Ordering::Relaxed);
+                    true
+                },
+                Err(TrySendError::Closed(_)) => {
+                    debug!("stream client {} disconnected, removing", client.id);
+                    false
+                },
+            }
+        });
+    }
+
+    fn lock_clients(&self) -> std::sync::MutexGuard<'_, Vec<StreamClient>> {
+        self.inner
+            .clients
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
@@ -0,0 +1,227 @@
+//! Rebroadcast of internal events and actions to external consumers.
+//!
+//! An [`EventStreamPublisher`] is registered with every per-pool
+//! [`sikkara_core::EngineRunner`] both as an engine (to observe events) and as
+//! an executor (to observe actions). It forwards everything to a shared
+//! [`EventStreamHub`], which the [`EventStreamServer`] exposes over WebSocket.
+
+use sikkara_core::{AppResult, Engine, Executor};
+
+mod hub;
+pub use hub::{EventStreamHub, StreamKind, StreamPayload, StreamSubscription};
+
+mod server;
+pub use server::{EventStreamServer, EVENT_STREAM_PATH};
+
+use crate::engine::{InternalAction, InternalEvent};
+
+/// Forwards events and actions of a runner into the event stream hub.
+#[derive(Debug, Clone)]
+pub struct EventStreamPublisher {
+    name: String,
+    hub: EventStreamHub,
+}
+
+impl EventStreamPublisher {
+    pub fn new(pool: String, hub: EventStreamHub) -> Self {
+        Self { name: format!("event_stream_publisher_{}", pool), hub }
+    }
+}
+
+#[async_trait::async_trait]
+impl Engine<InternalEvent, InternalAction> for EventStreamPublisher {
+    fn id(&self) -> &str { &self.name }
+
+    async fn process_event(&mut self, event: InternalEvent) -> AppResult<Option<InternalAction>> {
+        self.hub.publish(StreamKind::Event, &event);
+        Ok(None)
+    }
+}
+
+#[async_trait::async_trait]
+impl Executor<InternalAction> for EventStreamPublisher {
+    fn id(&self) -> &str { &self.name }
+
+    async fn execute_actions(&mut self, actions: Vec<InternalAction>) -> AppResult<()> {
+        for action in &actions {
+            self.hub.publish(StreamKind::Action, action);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use futures::StreamExt;
+    use rust_decimal_macros::dec;
+    use tokio_tungstenite::{connect_async, tungstenite::Message};
+    use tokio_util::sync::CancellationToken;
+
+    use super::*;
+    use crate::engine::{
+        ArbitrageDirection, ArbitrageOpportunity, Exchange, PoolPriceUpdate, PoolSymbol, Ticker,
+    };
+
+    fn ticker_event() -> InternalEvent {
+        InternalEvent::TickerUpdate(Ticker {
+            exchage: Exchange::Coinbase,
+            symbol: PoolSymbol::ETH_USDC,
+            price: dec!(2500.5),
+            timestamp: "2025-02-12T21:12:33.778451Z".parse().unwrap(),
+            bid: None,
+            ask: None,
+            bid_size: None,
+            ask_size: None,
+            volume_24h: None,
+        })
+    }
+
+    fn pool_event() -> InternalEvent {
+        InternalEvent::PoolPriceUpdate(PoolPriceUpdate {
+            symbol: PoolSymbol::ETH_USDC,
+            price: dec!(2501.25),
+            timestamp: "2025-02-12T21:12:34Z".parse().unwrap(),
+            block_number: None,
+            state: None,
+            effective_buy_price: None,
+            effective_sell_price: None,
+        })
+    }
+
+    fn opportunity() -> ArbitrageOpportunity {
+        ArbitrageOpportunity {
+            symbol: PoolSymbol::ETH_USDC,
+            direction: ArbitrageDirection::BuyCexSellDex,
+            cex_price: dec!(2500.5),
+            dex_price: dec!(2501.25),
+            net_bps: dec!(3),
+            recommended_size: dec!(0.5),
+            detected_at: "2025-02-12T21:12:34Z".parse().unwrap(),
+            cex_quote_at: "2025-02-12T21:12:34Z".parse().unwrap(),
+            dex_quote_at: "2025-02-12T21:12:34Z".parse().unwrap(),
+        }
+    }
+
+    async fn wait_for_clients(hub: &EventStreamHub, count: usize) {
+        for _ in 0..100 {
+            if hub.client_count() == count {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("expected {} clients, found {}", count, hub.client_count());
+    }
+
+    #[tokio::test]
+    async fn test_client_receives_filtered_events_and_actions() {
+        let hub = EventStreamHub::new(16);
+        let server = EventStreamServer::bind("127.0.0.1:0", hub.clone())
+            .await
+            .unwrap();
+        let addr = server.local_addr().unwrap();
+        let shutdown = CancellationToken::new();
+        let handle = server.spawn(shutdown.clone());
+
+        let url = format!("ws://{}{}?types=pool_price_update,opportunity", addr, EVENT_STREAM_PATH);
+        let (mut client, _) = connect_async(url).await.unwrap();
+        wait_for_clients(&hub, 1).await;
+
+        let mut publisher = EventStreamPublisher::new("ETH-USDC".to_string(), hub.clone());
+        publisher.process_event(ticker_event()).await.unwrap();
+        publisher.process_event(pool_event()).await.unwrap();
+        publisher
+            .execute_actions(vec![InternalAction::Opportunity(opportunity())])
+            .await
+            .unwrap();
+
+        let Some(Ok(Message::Text(text))) = client.next().await else { panic!("expected text") };
+        let value: serde_json::Value = serde_json::from_str(&text).unwrap();
+        assert_eq!(
+            value,
+            serde_json::json!({
+                "kind": "event",
+                "data": {
+                    "type": "pool_price_update",
+                    "symbol": "ETH-USDC",
+                    "price": "2501.25",
+                    "timestamp": "2025-02-12T21:12:34Z",
+                    "block_number": null
+                }
+            })
+        );
+
+        let Some(Ok(Message::Text(text))) = client.next().await else { panic!("expected text") };
+        let value: serde_json::Value = serde_json::from_str(&text).unwrap();
+        assert_eq!(
+            value,
+            serde_json::json!({
+                "kind": "action",
+                "data": {
+                    "type": "opportunity",
+                    "symbol": "ETH-USDC",
+                    "direction": "buy_cex_sell_dex",
+                    "cex_price": "2500.5",
+                    "dex_price": "2501.25",
+                    "net_bps": "3",
+                    "recommended_size": "0.5",
+                    "detected_at": "2025-02-12T21:12:34Z",
+                    "cex_quote_at": "2025-02-12T21:12:34Z",
+                    "dex_quote_at": "2025-02-12T21:12:34Z"
+                }
+            })
+        );
+
+        drop(client);
+        wait_for_clients(&hub, 0).await;
+        shutdown.cancel();
+        handle.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_unknown_path_is_rejected() {
+        let hub = EventStreamHub::new(16);
+        let server = EventStreamServer::bind("127.0.0.1:0", hub.clone())
+            .await
+            .unwrap();
+        let addr = server.local_addr().unwrap();
+        let shutdown = CancellationToken::new();
+        let handle = server.spawn(shutdown.clone());
+
+        assert!(connect_async(format!("ws://{}/ws/other", addr))
+            .await
+            .is_err());
+        assert_eq!(hub.client_count(), 0);
+
+        shutdown.cancel();
+        handle.await.unwrap().unwrap();
+    }
+
+    #[test]
+    fn test_slow_client_drops_and_counts() {
+        let hub = EventStreamHub::new(2);
+        let mut slow = hub.register(None);
+
+        for _ in 0..5 {
+            hub.publish(StreamKind::Event, &pool_event());
+        }
+
+        assert_eq!(slow.dropped_messages(), 3);
+        assert_eq!(hub.dropped_messages(), 3);
+        assert!(slow.receiver.try_recv().is_ok());
+        assert!(slow.receiver.try_recv().is_ok());
+        assert!(slow.receiver.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_closed_client_is_removed_on_publish() {
+        let hub = EventStreamHub::new(2);
+        let subscription = hub.register(None);
+        assert_eq!(hub.client_count(), 1);
+
+        drop(subscription);
+        hub.publish(StreamKind::Event, &ticker_event());
+        assert_eq!(hub.client_count(), 0);
+    }
+}
@@ -0,0 +1,148 @@
+//! WebSocket server exposing the event stream hub at `/ws/events`.
+//!
+//! Clients may restrict the message types they receive with a comma separated
+//! `types` query parameter, e.g. `/ws/events?types=ticker_update,opportunity`.
+
+use std::{collections::HashSet, net::SocketAddr};
+
+use futures::{SinkExt, StreamExt};
+use sikkara_core::{AppError, AppResult};
+use tokio::net::{TcpListener, TcpStream};
+use tokio_tungstenite::{
+    accept_hdr_async,
+    tungstenite::{
+        handshake::server::{ErrorResponse, Request, Response},
+        http::StatusCode,
+        Message,
+    },
+};
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, error, info, warn};
+
+use crate::event_stream::EventStreamHub;
+
+/// Path on which the event stream is served.
+pub const EVENT_STREAM_PATH: &str = "/ws/events";
+
+/// WebSocket server accepting external event stream consumers.
+#[derive(Debug)]
+pub struct EventStreamServer {
+    listener: TcpListener,
+    hub: EventStreamHub,
+}
+
+impl EventStreamServer {
+    /// Binds the server to the given socket address.
+    pub async fn bind(addr: &str, hub: EventStreamHub) -> AppResult<Self> {
+        let listener = TcpListener::bind(addr).await.map_err(|e| {
+            AppError::WebSocketError(format!("failed to bind event stream to {}: {}", addr, e))
+        })?;
+        Ok(Self { listener, hub })
+    }
+
+    /// Returns the address the server is listening on.
+    pub fn local_addr(&self) -> AppResult<SocketAddr> { Ok(self.listener.local_addr()?) }
+
+    /// Accepts connections until shutdown is requested.
+    pub async fn run(self, shutdown: CancellationToken) -> AppResult<()> {
+        info!("event stream listening on ws://{}{}", self.local_addr()?, EVENT_STREAM_PATH);
+        loop {
+            tokio::select! {
+                _ = shutdown.cancelled() => {
+                    info!("event stream server received shutdown signal, exiting");
+                    return Ok(());
+                }
+                accepted = self.listener.accept() => match accepted {
+                    Ok((stream, peer)) => {
+                        let hub = self.hub.clone();
+                        let client_shutdown = shutdown.child_token();
+                        tokio::spawn(async move {
+                            if let Err(e) = handle_client(stream, peer, hub, client_shutdown).await {
+                                warn!("event stream client {} failed: {}", peer, e);
+                            }
+                        });
+                    },
+                    Err(e) => error!("failed to accept event stream connection: {}", e),
+                }
+            }
+        }
+    }
+
+    /// Spawns the server on the tokio runtime.
+    pub fn spawn(self, shutdown: CancellationToken) -> tokio::task::JoinHandle<AppResult<()>> {
+        tokio::spawn(async move { self.run(shutdown).await })
+    }
+}
+
+async fn handle_client(
+    stream: TcpStream,
+    peer: SocketAddr,
+    hub: EventStreamHub,
+    shutdown: CancellationToken,
+) -> AppResult<()> {
+    let mut query = None;
+    // The error response type is dictated by tungstenite's handshake callback.
+    #[allow(clippy::result_large_err)]
+    let callback = |request: &Request, response: Response| {
+        if request.uri().path() != EVENT_STREAM_PATH {
+            let mut not_found = ErrorResponse::new(Some("not found".to_string()));
+            *not_found.status_mut() = StatusCode::NOT_FOUND;
+            return Err(not_found);
+        }
+        query = request.uri().query().map(str::to_string);
+        Ok(response)
+    };
+    let mut ws_stream = accept_hdr_async(stream, callback)
+        .await
+        .map_err(|e| AppError::WebSocketError(format!("handshake failed: {}", e)))?;
+
+    let filter = query.as_deref().and_then(parse_type_filter);
+    info!("event stream client {} connected with filter {:?}", peer, filter);
+    let mut subscription = hub.register(filter);
+
+    let result = loop {
+        tokio::select! {
+            _ = shutdown.cancelled() => {
+                let _ = ws_stream.send(Message::Close(None)).await;
+                break Ok(());
+            }
+            payload = subscription.receiver.recv() => match payload {
+                Some(payload) => {
+                    if let Err(e) = ws_stream.send(Message::Text(payload.as_ref().into())).await {
+                        break Err(AppError::WebSocketError(format!("failed to send: {}", e)).into());
+                    }
+                },
+                None => break Ok(()),
+            },
+            incoming = ws_stream.next() => match incoming {
+                Some(Ok(Message::Close(_))) | None => break Ok(()),
+                Some(Ok(message)) => debug!("ignoring message from event stream client {}: {:?}", peer, message),
+                Some(Err(e)) => break Err(AppError::WebSocketError(format!("stream error: {}", e)).into()),
+            }
+        }
+    };
+
+    hub.unregister(subscription.id);
+    info!(
+        "event stream client {} disconnected, {} messages dropped",
+        peer,
+        subscription.dropped_messages()
+    );
+    result
+}
+
+/// Parses the `types` query parameter into a set of message types.
+fn parse_type_filter(query: &str) -> Option<HashSet<String>> {
+    query
+        .split('&')
+        .filter_map(|pair| pair.strip_prefix("types="))
+        .map(|types| {
+            types
+                .split(',')
+                .map(str::trim)
+                .filter(|t| !t.is_empty())
+                .map(str::to_string)
+                .collect::<HashSet<_>>()
+        })
+        .find(|types| !types.is_empty())
+}
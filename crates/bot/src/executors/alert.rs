@@ -0,0 +1,528 @@
+//! Webhook alerting for opportunities, feed outages and kill-switch trips.
+//!
+//! The [`AlertExecutor`] observes actions (opportunities) as an executor and
+//! events (feed status transitions) as an engine, turns the relevant ones into
+//! [`Alert`]s and hands them to a shared [`AlertDispatcher`]. The dispatcher
+//! rate limits alerts per [`AlertType`] across all pools and POSTs them to the
+//! configured webhooks, retrying with exponential backoff on server errors.
+//! Alerts are mirrored to Telegram when configured, where they can also be
+//! muted for a while.
+
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sikkara_core::{
+    AppError, AppResult, BackoffStrategy, Engine, Executor, ExponentialBackoff, RateLimiter,
+};
+use tracing::{debug, error, info, warn};
+
+use crate::{
+    config::{AlertConfig, WebhookConfig, WebhookFormat},
+    engine::{
+        ArbitrageDirection, ArbitrageOpportunity, FeedState, FeedStatus, InternalAction,
+        InternalEvent,
+    },
+    executors::{StatusBoard, TelegramClient},
+};
+
+/// Type of an alert, used for webhook filtering and rate limiting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertType {
+    Opportunity,
+    FeedDown,
+    KillSwitch,
+}
+
+/// An alert delivered to the configured webhooks.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Alert {
+    Opportunity(ArbitrageOpportunity),
+    FeedDown(FeedStatus),
+    KillSwitch { reason: String },
+}
+
+impl Alert {
+    /// Returns the type of this alert.
+    pub fn alert_type(&self) -> AlertType {
+        match self {
+            Alert::Opportunity(_) => AlertType::Opportunity,
+            Alert::FeedDown(_) => AlertType::FeedDown,
+            Alert::KillSwitch { .. } => AlertType::KillSwitch,
+        }
+    }
+
+    /// Returns a human readable one line summary of the alert.
+    pub fn summary(&self) -> String {
+        match self {
+            Alert::Opportunity(opportunity) => {
+                let (buy, buy_price, sell, sell_price) = match opportunity.direction {
+                    ArbitrageDirection::BuyDexSellCex => {
+                        ("DEX", opportunity.dex_price, "CEX", opportunity.cex_price)
+                    },
+                    ArbitrageDirection::BuyCexSellDex => {
+                        ("CEX", opportunity.cex_price, "DEX", opportunity.dex_price)
+                    },
+                };
+                format!(
+                    "🚀 Arbitrage opportunity on {}: buy {} ${:.2} → sell {} ${:.2} ({:.2} bps net)",
+                    opportunity.symbol, buy, buy_price, sell, sell_price, opportunity.net_bps
+                )
+            },
+            Alert::FeedDown(status) => {
+                format!("🔴 Feed {} for {} is down: {}", status.feed, status.symbol, status.reason)
+            },
+            Alert::KillSwitch { reason } => format!("🛑 Kill switch tripped: {}", reason),
+        }
+    }
+}
+
+/// Rate limits alerts and delivers them to the configured webhooks and
+/// Telegram chat.
+///
+/// Cloning is cheap, all clones share the same rate limiter, mute state, status
+/// board and HTTP client.
+#[derive(Debug, Clone)]
+pub struct AlertDispatcher {
+    config: Arc<AlertConfig>,
+    client: reqwest::Client,
+    rate_limiter: Arc<Mutex<RateLimiter<AlertType>>>,
+    telegram: Option<TelegramClient>,
+    muted_until: Arc<Mutex<Option<Instant>>>,
+    status: Arc<Mutex<StatusBoard>>,
+}
+
+impl AlertDispatcher {
+    pub fn new(config: AlertConfig) -> Self {
+        let rate_limiter = RateLimiter::new(
+            config.rate_limit_max_alerts,
+            Duration::from_secs(config.rate_limit_period_secs),
+        );
+        Self {
+            config: Arc::new(config),
+            client: reqwest::Client::new(),
+            rate_limiter: Arc::new(Mutex::new(rate_limiter)),
+            telegram: None,
+            muted_until: Arc::new(Mutex::new(None)),
+            status: Arc::new(Mutex::new(StatusBoard::default())),
+        }
+    }
+
+    /// Mirrors all alerts to the chat of the given Telegram client.
+    pub fn with_telegram(mut self, telegram: TelegramClient) -> Self {
+        self.telegram = Some(telegram);
+        self
+    }
+
+    /// Suppresses all alerts but kill-switch trips for the given duration.
+    pub fn mute(&self, duration: Duration) {
+        *self
+            .muted_until
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(Instant::now() + duration);
+    }
+
+    /// Lifts a previous mute.
+    pub fn unmute(&self) {
+        *self
+            .muted_until
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = None;
+    }
+
+    /// Returns true if alerts are currently muted.
+    pub fn is_muted(&self) -> bool {
+        self.muted_until
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .is_some_and(|until| Instant::now() < until)
+    }
+
+    /// Records an event on the status board.
+    pub fn record_event(&self, event: &InternalEvent) {
+        self.status
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .record(event);
+    }
+
+    /// Returns a human readable report of feed health and last prices.
+    pub fn status_report(&self) -> String {
+        self.status
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .render(jiff::Timestamp::now())
+    }
+
+    /// Returns true if an opportunity is severe enough to be alerted.
+    pub fn is_alertable(&self, opportunity: &ArbitrageOpportunity) -> bool {
+        opportunity.net_bps >= self.config.min_opportunity_net_bps
+    }
+
+    /// Delivers the alert to every webhook subscribed to its type and to
+    /// Telegram, unless alerts are muted or the alert type is currently rate
+    /// limited.
+    pub async fn dispatch(&self, alert: Alert) {
+        let alert_type = alert.alert_type();
+        if alert_type != AlertType::KillSwitch && self.is_muted() {
+            debug!("alerts muted, dropping {:?} alert", alert_type);
+            return;
+        }
+        let allowed = self
+            .rate_limiter
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .try_acquire(alert_type);
+        if !allowed {
+            debug!("alert of type {:?} rate limited, dropping", alert_type);
+            return;
+        }
+
+        for webhook in &self.config.webhooks {
+            if !webhook
+                .events
+                .as_ref()
+                .is_none_or(|events| events.contains(&alert_type))
+            {
+                continue;
+            }
+            let body = Self::payload(webhook.format, &alert);
+            match self.deliver(webhook, &body).await {
+                Ok(_) => info!("delivered {:?} alert to webhook", alert_type),
+                Err(e) => error!("failed to deliver {:?} alert to webhook: {}", alert_type, e),
+            }
+        }
+
+        if let Some(telegram) = &self.telegram {
+            match telegram.send_message(&alert.summary()).await {
+                Ok(_) => info!("delivered {:?} alert to telegram", alert_type),
+                Err(e) => error!("failed to deliver {:?} alert to telegram: {}", alert_type, e),
+            }
+        }
+    }
+
+    /// Builds the request body for the given webhook format.
+    fn payload(format: WebhookFormat, alert: &Alert) -> serde_json::Value {
+        match format {
+            WebhookFormat::Json => json!({ "text": alert.summary(), "alert": alert }),
+            WebhookFormat::Slack => json!({ "text": alert.summary() }),
+            WebhookFormat::Discord => json!({ "content": alert.summary() }),
+        }
+    }
+
+    /// POSTs the body to the webhook, retrying on server and transport errors.
+    async fn deliver(&self, webhook: &WebhookConfig, body: &serde_json::Value) -> AppResult<()> {
+        let mut backoff = ExponentialBackoff::new(
+            self.config.max_retries.into(),
+            self.config.retry_min_delay_secs,
+            self.config.retry_max_delay_secs,
+            2,
+        );
+        loop {
+            let failure = match self.client.post(&webhook.url).json(body).send().await {
+                Ok(response) if response.status().is_success() => return Ok(()),
+                Ok(response) if response.status().is_server_error() => {
+                    format!("server error {}", response.status())
+                },
+                Ok(response) => {
+                    return Err(AppError::HttpError(format!(
+                        "webhook rejected alert with status {}",
+                        response.status()
+                    ))
+                    .into());
+                },
+                Err(e) => format!("request failed: {}", e),
+            };
+
+            match backoff.next_delay() {
+                Some(delay) => {
+                    warn!("webhook delivery failed with {}, retrying in {:?}", failure, delay);
+                    tokio::time::sleep(delay).await;
+                },
+                None => {
+                    return Err(AppError::HttpError(format!(
+                        "webhook delivery failed after {} retries: {}",
+                        backoff.get_iteration_count(),
+                        failure
+                    ))
+                    .into());
+                },
+            }
+        }
+    }
+}
+
+/// Turns opportunities and feed outages of a runner into alerts, and keeps the
+/// status board up to date.
+#[derive(Debug, Clone)]
+pub struct AlertExecutor {
+    name: String,
+    dispatcher: AlertDispatcher,
+}
+
+impl AlertExecutor {
+    pub fn new(pool: String, dispatcher: AlertDispatcher) -> Self {
+        Self { name: format!("alert_executor_{}", pool), dispatcher }
+    }
+}
+
+#[async_trait::async_trait]
+impl Executor<InternalAction> for AlertExecutor {
+    fn id(&self) -> &str { &self.name }
+
+    async fn execute_actions(&mut self, actions: Vec<InternalAction>) -> AppResult<()> {
+        for action in actions {
+            match action {
+                InternalAction::Opportunity(opportunity) => {
+                    if self.dispatcher.is_alertable(&opportunity) {
+                        self.dispatcher
+                            .dispatch(Alert::Opportunity(opportunity))
+                            .await;
+                    }
+                },
+                InternalAction::Suppressed(_) => {},
+                // Alerted once by the halt state tripping
+                InternalAction::Halt { .. } => {},
+            }
+        }
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl Engine<InternalEvent, InternalAction> for AlertExecutor {
+    fn id(&self) -> &str { &self.name }
+
+    async fn process_event(&mut self, event: InternalEvent) -> AppResult<Option<InternalAction>> {
+        self.dispatcher.record_event(&event);
+        if let InternalEvent::FeedStatus(status) = event {
+            if status.state == FeedState::Down {
+                self.dispatcher.dispatch(Alert::FeedDown(status)).await;
+            }
+        }
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal::Decimal;
+    use rust_decimal_macros::dec;
+    use wiremock::{
+        matchers::{body_json, method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    use super::*;
+    use crate::engine::{PoolSymbol, PriceSource};
+
+    fn config(webhooks: Vec<WebhookConfig>) -> AlertConfig {
+        AlertConfig {
+            webhooks,
+            telegram: None,
+            min_opportunity_net_bps: dec!(50),
+            rate_limit_max_alerts: 10,
+            rate_limit_period_secs: 60,
+            max_retries: 3,
+            retry_min_delay_secs: 0,
+            retry_max_delay_secs: 0,
+        }
+    }
+
+    fn webhook(server: &MockServer, format: WebhookFormat) -> WebhookConfig {
+        WebhookConfig { url: format!("{}/hook", server.uri()), format, events: None }
+    }
+
+    fn opportunity(net_bps: Decimal) -> InternalAction {
+        InternalAction::Opportunity(ArbitrageOpportunity {
+            symbol: PoolSymbol::ETH_USDC,
+            direction: ArbitrageDirection::BuyDexSellCex,
+            cex_price: dec!(2510),
+            dex_price: dec!(2500),
+            net_bps,
+            recommended_size: Decimal::ZERO,
+            detected_at: "2025-02-12T21:12:33Z".parse().unwrap(),
+            cex_quote_at: "2025-02-12T21:12:33Z".parse().unwrap(),
+            dex_quote_at: "2025-02-12T21:12:33Z".parse().unwrap(),
+        })
+    }
+
+    fn feed_down() -> InternalEvent {
+        InternalEvent::FeedStatus(FeedStatus {
+            feed: "price_feed_collector".to_string(),
+            source: PriceSource::Cex,
+            symbol: PoolSymbol::ETH_USDC,
+            state: FeedState::Down,
+            reason: "price feed stream ended".to_string(),
+            timestamp: "2025-02-12T21:12:33Z".parse().unwrap(),
+        })
+    }
+
+    #[tokio::test]
+    async fn test_opportunity_alert_payload() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/hook"))
+            .and(body_json(json!({
+                "text": "🚀 Arbitrage opportunity on ETH-USDC: buy DEX $2500.00 → sell CEX $2510.00 (60.00 bps net)",
+                "alert": {
+                    "type": "opportunity",
+                    "symbol": "ETH-USDC",
+                    "direction": "buy_dex_sell_cex",
+                    "cex_price": "2510",
+                    "dex_price": "2500",
+                    "net_bps": "60",
+                    "recommended_size": "0",
+                    "detected_at": "2025-02-12T21:12:33Z",
+                    "cex_quote_at": "2025-02-12T21:12:33Z",
+                    "dex_quote_at": "2025-02-12T21:12:33Z"
+                }
+            })))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let dispatcher = AlertDispatcher::new(config(vec![webhook(&server, WebhookFormat::Json)]));
+        let mut executor = AlertExecutor::new("ETH-USDC".to_string(), dispatcher);
+        executor
+            .execute_actions(vec![opportunity(dec!(60)), opportunity(dec!(10))])
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_feed_down_alert_slack_and_discord_formats() {
+        let server = MockServer::start().await;
+        let text = "🔴 Feed price_feed_collector for ETH-USDC is down: price feed stream ended";
+        Mock::given(body_json(json!({ "text": text })))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(body_json(json!({ "content": text })))
+            .respond_with(ResponseTemplate::new(204))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let dispatcher = AlertDispatcher::new(config(vec![
+            webhook(&server, WebhookFormat::Slack),
+            webhook(&server, WebhookFormat::Discord),
+        ]));
+        let mut executor = AlertExecutor::new("ETH-USDC".to_string(), dispatcher);
+        executor.process_event(feed_down()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_alerts_are_rate_limited_per_type() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(2)
+            .mount(&server)
+            .await;
+
+        let mut config = config(vec![webhook(&server, WebhookFormat::Json)]);
+        config.rate_limit_max_alerts = 1;
+        let dispatcher = AlertDispatcher::new(config);
+
+        // Clones share the rate limiter, as executors of different pools do
+        let mut first = AlertExecutor::new("ETH-USDC".to_string(), dispatcher.clone());
+        let mut second = AlertExecutor::new("ETH-USDT".to_string(), dispatcher);
+        first
+            .execute_actions(vec![opportunity(dec!(60))])
+            .await
+            .unwrap();
+        second
+            .execute_actions(vec![opportunity(dec!(70))])
+            .await
+            .unwrap();
+        first.process_event(feed_down()).await.unwrap();
+        second.process_event(feed_down()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_server_errors_are_retried() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(503))
+            .up_to_n_times(2)
+            .expect(2)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let dispatcher = AlertDispatcher::new(config(vec![webhook(&server, WebhookFormat::Json)]));
+        let hook = webhook(&server, WebhookFormat::Json);
+        assert!(dispatcher.deliver(&hook, &json!({})).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_client_errors_and_exhausted_retries_fail() {
+        let server = MockServer::start().await;
+        Mock::given(path("/bad"))
+            .respond_with(ResponseTemplate::new(400))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(path("/down"))
+            .respond_with(ResponseTemplate::new(500))
+            .expect(4)
+            .mount(&server)
+            .await;
+
+        let dispatcher = AlertDispatcher::new(config(vec![]));
+        let bad = WebhookConfig {
+            url: format!("{}/bad", server.uri()),
+            format: WebhookFormat::Json,
+            events: None,
+        };
+        let down = WebhookConfig { url: format!("{}/down", server.uri()), ..bad.clone() };
+        assert!(dispatcher.deliver(&bad, &json!({})).await.is_err());
+        assert!(dispatcher.deliver(&down, &json!({})).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_webhook_event_filters() {
+        let server = MockServer::start().await;
+        Mock::given(path("/feeds"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(path("/all"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(2)
+            .mount(&server)
+            .await;
+
+        let dispatcher = AlertDispatcher::new(config(vec![
+            WebhookConfig {
+                url: format!("{}/feeds", server.uri()),
+                format: WebhookFormat::Json,
+                events: Some(vec![AlertType::FeedDown]),
+            },
+            WebhookConfig {
+                url: format!("{}/all", server.uri()),
+                format: WebhookFormat::Json,
+                events: None,
+            },
+        ]));
+        let mut executor = AlertExecutor::new("ETH-USDC".to_string(), dispatcher);
+        executor
+            .execute_actions(vec![opportunity(dec!(60))])
+            .await
+            .unwrap();
+        executor.process_event(feed_down()).await.unwrap();
+    }
+}
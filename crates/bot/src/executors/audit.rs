@@ -0,0 +1,467 @@
+//! Append-only audit log of detected and suppressed opportunities and of the
+//! execution of opportunities.
+//!
+//! Records are appended as JSON lines to one file per UTC day,
+//! `<output_dir>/opportunities-YYYY-MM-DD.jsonl`. Every record carries a
+//! monotonically increasing `id`, the `schema_version` and a SHA-256
+//! `checksum` over the checksum of the previous record and its own content, so
+//! records are chained across files and restarts. [`verify_audit_log`] replays
+//! a file and validates the chain, detecting modified, removed or reordered
+//! records.
+
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sikkara_core::{AppError, AppResult, Clock, Engine, Executor};
+use tracing::info;
+
+use crate::{
+    engine::{
+        ArbitrageOpportunity, ExecutionEvent, InternalAction, InternalEvent, SuppressedOpportunity,
+    },
+    executors::HindsightEvaluation,
+};
+
+/// Version of the record schema, bumped on incompatible changes.
+pub const AUDIT_SCHEMA_VERSION: u32 = 1;
+
+/// Previous checksum of the very first record.
+pub const GENESIS_CHECKSUM: &str =
+    "0000000000000000000000000000000000000000000000000000000000000000";
+
+const FILE_PREFIX: &str = "opportunities-";
+const FILE_EXTENSION: &str = ".jsonl";
+
+/// A decision recorded in the audit log.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum AuditEntry {
+    Opportunity(ArbitrageOpportunity),
+    Suppression(SuppressedOpportunity),
+    Execution(ExecutionEvent),
+    Halt {
+        reason: String,
+    },
+    /// Outcome of a dry run opportunity had it been executed
+    Hindsight(HindsightEvaluation),
+}
+
+/// A record as written, without its checksum. The checksum is computed over
+/// the previous checksum followed by the serialized record.
+#[derive(Serialize)]
+struct AuditRecord<'a> {
+    id: u64,
+    schema_version: u32,
+    #[serde(with = "sikkara_core::timestamp_with_tz_serializer")]
+    recorded_at: jiff::Timestamp,
+    prev_checksum: &'a str,
+    entry: &'a AuditEntry,
+}
+
+/// Chain fields of a record read back from a file.
+#[derive(Deserialize)]
+struct RecordHeader {
+    id: u64,
+    schema_version: u32,
+    prev_checksum: String,
+    checksum: String,
+}
+
+/// Summary of a verified audit log file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuditChain {
+    pub records: u64,
+    pub first_id: Option<u64>,
+    pub last_id: Option<u64>,
+    /// Previous checksum of the first record, linking to the previous file
+    pub prev_checksum: Option<String>,
+    pub last_checksum: Option<String>,
+}
+
+#[derive(Debug)]
+struct AuditLogState {
+    output_dir: PathBuf,
+    next_id: u64,
+    last_checksum: String,
+    /// Day and file records are currently appended to
+    current: Option<(jiff::civil::Date, File)>,
+}
+
+impl AuditLogState {
+    /// Returns the file of the given day, rotating to it if needed.
+    fn file(&mut self, day: jiff::civil::Date) -> AppResult<&mut File> {
+        if self
+            .current
+            .as_ref()
+            .is_none_or(|(current, _)| *current != day)
+        {
+            let path = self.output_dir.join(file_name(day));
+            let file = OpenOptions::new().create(true).append(true).open(&path)?;
+            info!("🗄️ Audit log rotated to {}", path.display());
+            self.current = Some((day, file));
+        }
+        Ok(&mut self.current.as_mut().expect("file was just opened").1)
+    }
+}
+
+/// Append-only, checksum chained audit log.
+///
+/// Cloning is cheap, all clones append to the same chain. Appends are
+/// synchronous so the chain and the files never diverge.
+#[derive(Debug, Clone)]
+pub struct AuditLog {
+    state: Arc<Mutex<AuditLogState>>,
+    clock: Arc<dyn Clock>,
+}
+
+impl AuditLog {
+    /// Opens the audit log in `output_dir`, continuing the chain of the
+    /// latest file found there.
+    pub fn open(output_dir: impl Into<PathBuf>, clock: Arc<dyn Clock>) -> AppResult<Self> {
+        let output_dir = output_dir.into();
+        fs::create_dir_all(&output_dir)?;
+
+        let (next_id, last_checksum) = match latest_file(&output_dir)? {
+            Some(path) => match last_header(&path)? {
+                Some(header) => (header.id + 1, header.checksum),
+                None => (1, GENESIS_CHECKSUM.to_string()),
+            },
+            None => (1, GENESIS_CHECKSUM.to_string()),
+        };
+        let state = AuditLogState { output_dir, next_id, last_checksum, current: None };
+        Ok(Self { state: Arc::new(Mutex::new(state)), clock })
+    }
+
+    /// Appends an entry, returning its record id.
+    pub fn append(&self, entry: &AuditEntry) -> AppResult<u64> {
+        let recorded_at = self.clock.now();
+        let mut state = self
+            .state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        let id = state.next_id;
+        let record = AuditRecord {
+            id,
+            schema_version: AUDIT_SCHEMA_VERSION,
+            recorded_at,
+            prev_checksum: &state.last_checksum,
+            entry,
+        };
+        let body = serde_json::to_string(&record)?;
+        let checksum = checksum(&state.last_checksum, &body);
+        let line = with_checksum(&body, &checksum);
+
+        let file = state.file(recorded_at.to_zoned(jiff::tz::TimeZone::UTC).date())?;
+        file.write_all(line.as_bytes())?;
+        file.write_all(b"\n")?;
+        file.flush()?;
+
+        state.next_id += 1;
+        state.last_checksum = checksum;
+        Ok(id)
+    }
+}
+
+/// Replays an audit log file, validating the schema version, the record ids
+/// and the checksum chain.
+pub fn verify_audit_log(path: impl AsRef<Path>) -> AppResult<AuditChain> {
+    let path = path.as_ref();
+    let reader = BufReader::new(File::open(path)?);
+    let mut chain = AuditChain {
+        records: 0,
+        first_id: None,
+        last_id: None,
+        prev_checksum: None,
+        last_checksum: None,
+    };
+
+    for (index, line) in reader.lines().enumerate() {
+        let line = line?;
+        let line_number = index + 1;
+        let invalid = |reason: String| {
+            AppError::IntegrityError(format!("{}:{}: {}", path.display(), line_number, reason))
+        };
+
+        let header: RecordHeader =
+            serde_json::from_str(&line).map_err(|e| invalid(format!("malformed record: {}", e)))?;
+        if header.schema_version != AUDIT_SCHEMA_VERSION {
+            return Err(invalid(format!("unknown schema version {}", header.schema_version)).into());
+        }
+        if let Some(last_id) = chain.last_id {
+            if header.id != last_id + 1 {
+                return Err(
+                    invalid(format!("expected id {}, found {}", last_id + 1, header.id)).into()
+                );
+            }
+        }
+        if let Some(last_checksum) = &chain.last_checksum {
+            if header.prev_checksum != *last_checksum {
+                return Err(invalid("chain broken, previous checksum mismatch".to_string()).into());
+            }
+        }
+        let body = without_checksum(&line, &header.checksum)
+            .ok_or_else(|| invalid("checksum is not the last field".to_string()))?;
+        if checksum(&header.prev_checksum, &body) != header.checksum {
+            return Err(invalid("checksum mismatch, record was modified".to_string()).into());
+        }
+
+        chain.records += 1;
+        chain.first_id.get_or_insert(header.id);
+        chain.prev_checksum.get_or_insert(header.prev_checksum);
+        chain.last_id = Some(header.id);
+        chain.last_checksum = Some(header.checksum);
+    }
+    Ok(chain)
+}
+
+/// Returns the file name of the given day, e.g.
+/// `opportunities-2025-02-12.jsonl`.
+pub fn file_name(day: jiff::civil::Date) -> String {
+    format!("{}{}{}", FILE_PREFIX, day.strftime("%Y-%m-%d"), FILE_EXTENSION)
+}
+
+fn checksum(prev_checksum: &str, body: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(prev_checksum.as_bytes());
+    hasher.update(body.as_bytes());
+    hex::encode(hasher.finalize())
+}
+
+/// Appends the checksum as the last field of a serialized record.
+fn with_checksum(body: &str, checksum: &str) -> String {
+    format!("{},\"checksum\":\"{}\"}}", &body[..body.len() - 1], checksum)
+}
+
+/// Strips the checksum appended by [`with_checksum`], returning the record
+/// body it was computed over.
+fn without_checksum(line: &str, checksum: &str) -> Option<String> {
+    let suffix = format!(",\"checksum\":\"{}\"}}", checksum);
+    line.strip_suffix(&suffix).map(|body| format!("{}}}", body))
+}
+
+/// Returns the latest audit log file of a directory. File names sort by day.
+fn latest_file(dir: &Path) -> AppResult<Option<PathBuf>> {
+    let mut files = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with(FILE_PREFIX) && name.ends_with(FILE_EXTENSION))
+        })
+        .collect::<Vec<_>>();
+    files.sort();
+    Ok(files.pop())
+}
+
+fn last_header(path: &Path) -> AppResult<Option<RecordHeader>> {
+    let reader = BufReader::new(File::open(path)?);
+    let mut last = None;
+    for line in reader.lines() {
+        let line = line?;
+        if !line.trim().is_empty() {
+            last = Some(line);
+        }
+    }
+    last.map(|line| {
+        serde_json::from_str(&line).map_err(|e| {
+            AppError::IntegrityError(format!(
+                "cannot continue audit chain of {}, malformed last record: {}",
+                path.display(),
+                e
+            ))
+            .into()
+        })
+    })
+    .transpose()
+}
+
+/// Executor appending opportunities and suppression decisions to the audit
+/// log. As an engine it appends the execution events of opportunities.
+#[derive(Debug, Clone)]
+pub struct AuditExecutor {
+    name: String,
+    log: AuditLog,
+}
+
+impl AuditExecutor {
+    pub fn new(pool: String, log: AuditLog) -> Self {
+        Self { name: format!("audit_executor_{}", pool), log }
+    }
+}
+
+#[async_trait::async_trait]
+impl Executor<InternalAction> for AuditExecutor {
+    fn id(&self) -> &str { &self.name }
+
+    async fn execute_actions(&mut self, actions: Vec<InternalAction>) -> AppResult<()> {
+        for action in actions {
+            let entry = match action {
+                InternalAction::Opportunity(opportunity) => AuditEntry::Opportunity(opportunity),
+                InternalAction::Suppressed(suppressed) => AuditEntry::Suppression(suppressed),
+                InternalAction::Halt { reason } => AuditEntry::Halt { reason },
+            };
+            self.log.append(&entry)?;
+        }
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl Engine<InternalEvent, InternalAction> for AuditExecutor {
+    fn id(&self) -> &str { &self.name }
+
+    async fn process_event(&mut self, event: InternalEvent) -> AppResult<Option<InternalAction>> {
+        if let InternalEvent::Execution(execution) = event {
+            self.log.append(&AuditEntry::Execution(execution))?;
+        }
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use rust_decimal_macros::dec;
+    use sikkara_core::MockClock;
+
+    use super::*;
+    use crate::engine::{
+        ArbitrageDirection, ExecutionStatus, Leg, LegIntent, PoolSymbol, PriceSource,
+        SuppressionReason,
+    };
+
+    fn opportunity() -> ArbitrageOpportunity {
+        ArbitrageOpportunity {
+            symbol: PoolSymbol::ETH_USDC,
+            direction: ArbitrageDirection::BuyDexSellCex,
+            cex_price: dec!(2520),
+            dex_price: dec!(2500),
+            net_bps: dec!(79.37),
+            recommended_size: dec!(0.5),
+            detected_at: "2025-02-12T23:59:30Z".parse().unwrap(),
+            cex_quote_at: "2025-02-12T23:59:30Z".parse().unwrap(),
+            dex_quote_at: "2025-02-12T23:59:30Z".parse().unwrap(),
+        }
+    }
+
+    fn suppression() -> AuditEntry {
+        AuditEntry::Suppression(SuppressedOpportunity {
+            opportunity: opportunity(),
+            reason: SuppressionReason::FeedDown { source: PriceSource::Dex },
+        })
+    }
+
+    fn day(date: &str) -> PathBuf { PathBuf::from(file_name(date.parse().unwrap())) }
+
+    #[test]
+    fn test_daily_rotation_continues_chain() {
+        let dir = tempfile::tempdir().unwrap();
+        let clock = MockClock::new("2025-02-12T23:59:30Z".parse().unwrap());
+        let log = AuditLog::open(dir.path(), Arc::new(clock.clone())).unwrap();
+
+        assert_eq!(log.append(&AuditEntry::Opportunity(opportunity())).unwrap(), 1);
+        assert_eq!(log.append(&suppression()).unwrap(), 2);
+        clock.advance(Duration::from_secs(60));
+        assert_eq!(log.append(&AuditEntry::Opportunity(opportunity())).unwrap(), 3);
+
+        let first = verify_audit_log(dir.path().join(day("2025-02-12"))).unwrap();
+        assert_eq!(first.records, 2);
+        assert_eq!(first.first_id, Some(1));
+        assert_eq!(first.prev_checksum.as_deref(), Some(GENESIS_CHECKSUM));
+
+        let second = verify_audit_log(dir.path().join(day("2025-02-13"))).unwrap();
+        assert_eq!(second.records, 1);
+        assert_eq!(second.first_id, Some(3));
+        assert_eq!(second.prev_checksum, first.last_checksum);
+
+        // Reopening continues the ids and the chain of the latest file
+        let log = AuditLog::open(dir.path(), Arc::new(clock.clone())).unwrap();
+        assert_eq!(log.append(&suppression()).unwrap(), 4);
+        let second = verify_audit_log(dir.path().join(day("2025-02-13"))).unwrap();
+        assert_eq!(second.records, 2);
+        assert_eq!(second.last_id, Some(4));
+    }
+
+    #[test]
+    fn test_records_are_versioned_json_lines() {
+        let dir = tempfile::tempdir().unwrap();
+        let clock = MockClock::new("2025-02-12T21:00:00Z".parse().unwrap());
+        let log = AuditLog::open(dir.path(), Arc::new(clock)).unwrap();
+        log.append(&suppression()).unwrap();
+
+        let content = fs::read_to_string(dir.path().join(day("2025-02-12"))).unwrap();
+        let record: serde_json::Value = serde_json::from_str(content.trim()).unwrap();
+        assert_eq!(record["id"], 1);
+        assert_eq!(record["schema_version"], AUDIT_SCHEMA_VERSION);
+        assert_eq!(record["entry"]["kind"], "suppression");
+        assert_eq!(record["entry"]["reason"]["kind"], "feed_down");
+        assert_eq!(record["entry"]["reason"]["source"], "dex");
+        assert_eq!(record["checksum"].as_str().unwrap().len(), 64);
+    }
+
+    #[tokio::test]
+    async fn test_execution_events_are_recorded() {
+        let dir = tempfile::tempdir().unwrap();
+        let clock = MockClock::new("2025-02-12T21:00:00Z".parse().unwrap());
+        let log = AuditLog::open(dir.path(), Arc::new(clock)).unwrap();
+        let mut executor = AuditExecutor::new("ETH-USDC".to_string(), log);
+        let filled = ExecutionEvent {
+            correlation_id: Some(42),
+            symbol: PoolSymbol::ETH_USDC,
+            status: ExecutionStatus::LegFilled {
+                leg: Leg::Cex,
+                intent: LegIntent::Unwind,
+                size: dec!(0.4),
+                reference: "11111-000000-000000".to_string(),
+            },
+            timestamp: "2025-02-12T21:00:01Z".parse().unwrap(),
+        };
+        executor
+            .process_event(InternalEvent::Execution(filled))
+            .await
+            .unwrap();
+
+        let content = fs::read_to_string(dir.path().join(day("2025-02-12"))).unwrap();
+        let record: serde_json::Value = serde_json::from_str(content.trim()).unwrap();
+        assert_eq!(record["entry"]["kind"], "execution");
+        assert_eq!(record["entry"]["correlation_id"], 42);
+        assert_eq!(record["entry"]["status"]["status"], "leg_filled");
+        assert_eq!(record["entry"]["status"]["intent"], "unwind");
+        assert_eq!(record["entry"]["status"]["size"], "0.4");
+    }
+
+    #[test]
+    fn test_tampered_middle_record_is_detected() {
+        let dir = tempfile::tempdir().unwrap();
+        let clock = MockClock::new("2025-02-12T21:00:00Z".parse().unwrap());
+        let log = AuditLog::open(dir.path(), Arc::new(clock)).unwrap();
+        for _ in 0..3 {
+            log.append(&AuditEntry::Opportunity(opportunity())).unwrap();
+        }
+        let path = dir.path().join(day("2025-02-12"));
+        assert_eq!(verify_audit_log(&path).unwrap().records, 3);
+
+        // Inflating the price of the second record breaks its checksum
+        let content = fs::read_to_string(&path).unwrap();
+        let mut lines = content.lines().map(str::to_string).collect::<Vec<_>>();
+        lines[1] = lines[1].replace("\"cex_price\":\"2520\"", "\"cex_price\":\"2620\"");
+        fs::write(&path, lines.join("\n")).unwrap();
+        let error = verify_audit_log(&path).unwrap_err().to_string();
+        assert!(error.contains(":2: checksum mismatch"), "{}", error);
+
+        // Dropping the second record breaks the ids
+        let mut lines = content.lines().collect::<Vec<_>>();
+        lines.remove(1);
+        fs::write(&path, lines.join("\n")).unwrap();
+        let error = verify_audit_log(&path).unwrap_err().to_string();
+        assert!(error.contains(":2: expected id 2, found 3"), "{}", error);
+    }
+}
@@ -0,0 +1,584 @@
+//! Execution of the CEX leg of arbitrage opportunities.
+//!
+//! The [`CexExecutor`] places the CEX leg of every opportunity on Coinbase as
+//! an immediate or cancel limit order at the CEX price the opportunity was
+//! priced from, so it never fills at a worse price.
+//!
+//! Orders are polled until they can no longer be filled, every change being
+//! reported as an [`OrderUpdate`] carrying the correlation id of the
+//! opportunity. The order of a leg timing out is cancelled and polled until
+//! final, so its fills are still reported.
+
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use rust_decimal::Decimal;
+use sikkara_adapters::{
+    CoinbaseOrder, CoinbaseSymbol, CoinbaseTradeClient, OrderSide, OrderStatus,
+};
+use sikkara_core::{current_correlation_id, AppError, AppResult, Executor};
+use tokio::sync::mpsc;
+use tracing::{info, warn};
+
+use crate::{
+    engine::{
+        ArbitrageDirection, ArbitrageOpportunity, InternalAction, Leg, LegIntent, OrderState,
+        OrderUpdate, PoolSymbol,
+    },
+    executors::{LegExecutor, LegFill},
+};
+
+/// Interval at which orders are polled until they can no longer be filled
+const ORDER_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// The CEX leg of an arbitrage opportunity.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CexOrder {
+    /// Derived from the opportunity, so an opportunity is never traded twice
+    pub client_order_id: String,
+    pub product_id: CoinbaseSymbol,
+    pub side: OrderSide,
+    /// Size in the base asset
+    pub size: Decimal,
+    pub limit_price: Decimal,
+}
+
+impl CexOrder {
+    /// Returns the CEX leg of an opportunity, none if no size is recommended
+    /// or Coinbase does not list the pair.
+    pub fn from_opportunity(opportunity: &ArbitrageOpportunity) -> Option<Self> {
+        if opportunity.recommended_size <= Decimal::ZERO {
+            return None;
+        }
+        let product_id = CoinbaseSymbol::try_from(opportunity.symbol.clone()).ok()?;
+        let side = match opportunity.direction {
+            ArbitrageDirection::BuyDexSellCex => OrderSide::Sell,
+            ArbitrageDirection::BuyCexSellDex => OrderSide::Buy,
+        };
+        Some(Self {
+            client_order_id: format!(
+                "sikarra-{}-{}",
+                opportunity.symbol,
+                opportunity.detected_at.as_millisecond()
+            ),
+            product_id,
+            side,
+            size: opportunity.recommended_size,
+            limit_price: opportunity.cex_price,
+        })
+    }
+
+    /// Returns the order trading the leg of an opportunity with the given
+    /// intent, unwinding it in the opposite direction.
+    fn from_leg(opportunity: &ArbitrageOpportunity, intent: LegIntent) -> AppResult<Self> {
+        let order = match intent {
+            LegIntent::Open => Self::from_opportunity(opportunity),
+            LegIntent::Unwind => Self::from_opportunity(&ArbitrageOpportunity {
+                direction: opportunity.direction.opposite(),
+                ..opportunity.clone()
+            })
+            .map(|order| Self {
+                client_order_id: format!("{}-unwind", order.client_order_id),
+                ..order
+            }),
+        };
+        order.ok_or_else(|| AppError::ConfigError("no trade size configured".to_string()).into())
+    }
+}
+
+/// Returns the lifecycle state of a Coinbase order.
+fn order_state(order: &CoinbaseOrder) -> OrderState {
+    match order.status {
+        OrderStatus::Filled => OrderState::Filled,
+        OrderStatus::Cancelled | OrderStatus::Expired => OrderState::Cancelled,
+        OrderStatus::Failed => OrderState::Failed,
+        OrderStatus::Pending | OrderStatus::Open | OrderStatus::Unknown
+            if order.filled_size > Decimal::ZERO =>
+        {
+            OrderState::PartiallyFilled
+        },
+        OrderStatus::Pending | OrderStatus::Open | OrderStatus::Unknown => OrderState::Submitted,
+    }
+}
+
+/// Executor placing the CEX leg of opportunities on Coinbase.
+#[derive(Debug, Clone)]
+pub struct CexExecutor {
+    name: String,
+    client: CoinbaseTradeClient,
+    order_updates: Option<mpsc::UnboundedSender<OrderUpdate>>,
+    /// Client and exchange ids of the last leg order placed, settled if the
+    /// leg times out
+    placed: Arc<Mutex<Option<(String, String)>>>,
+}
+
+impl CexExecutor {
+    pub fn new(pool: String, client: CoinbaseTradeClient) -> Self {
+        Self {
+            name: format!("cex_executor_{}", pool),
+            client,
+            order_updates: None,
+            placed: Arc::default(),
+        }
+    }
+
+    /// Reports the updates of the orders placed through `order_updates`.
+    pub fn with_order_updates(mut self, order_updates: mpsc::UnboundedSender<OrderUpdate>) -> Self {
+        self.order_updates = Some(order_updates);
+        self
+    }
+
+    fn report(
+        &self,
+        symbol: &PoolSymbol,
+        order_id: &str,
+        side: OrderSide,
+        state: OrderState,
+        filled: Decimal,
+        avg_price: Option<Decimal>,
+    ) {
+        let Some(order_updates) = &self.order_updates else {
+            return;
+        };
+        let update = OrderUpdate {
+            venue: Leg::Cex,
+            correlation_id: current_correlation_id(),
+            symbol: symbol.clone(),
+            order_id: order_id.to_string(),
+            side,
+            state,
+            filled,
+            avg_price,
+            timestamp: jiff::Timestamp::now(),
+        };
+        if order_updates.send(update).is_err() {
+            warn!(symbol = %symbol, "order updates are no longer collected");
+        }
+    }
+
+    async fn execute(&self, opportunity: &ArbitrageOpportunity) -> AppResult<()> {
+        let Some(order) = CexOrder::from_opportunity(opportunity) else {
+            warn!(symbol = %opportunity.symbol, "no trade size configured or no Coinbase product, opportunity not executed");
+            return Ok(());
+        };
+        let order_id = self
+            .client
+            .place_limit_order(
+                order.client_order_id.clone(),
+                order.product_id.clone(),
+                order.side,
+                order.size,
+                order.limit_price,
+                false,
+            )
+            .await?;
+        info!(
+            order_id,
+            client_order_id = order.client_order_id,
+            product_id = %order.product_id,
+            side = ?order.side,
+            size = %order.size,
+            limit_price = %order.limit_price,
+            "cex order placed"
+        );
+        self.report(
+            &opportunity.symbol,
+            &order_id,
+            order.side,
+            OrderState::Submitted,
+            Decimal::ZERO,
+            None,
+        );
+        Ok(())
+    }
+
+    /// Polls an order until it can no longer be filled, reporting every change
+    /// of its state or filled size.
+    async fn final_order(&self, symbol: &PoolSymbol, order_id: &str) -> AppResult<CoinbaseOrder> {
+        let mut reported = (OrderState::Submitted, Decimal::ZERO);
+        loop {
+            let order = self.client.get_order(order_id).await?;
+            let state = order_state(&order);
+            if (state, order.filled_size) != reported {
+                reported = (state, order.filled_size);
+                let avg_price =
+                    (order.filled_size > Decimal::ZERO).then_some(order.average_filled_price);
+                self.report(symbol, order_id, order.side, state, order.filled_size, avg_price);
+            }
+            if order.status.is_final() {
+                return Ok(order);
+            }
+            tokio::time::sleep(ORDER_POLL_INTERVAL).await;
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl LegExecutor for CexExecutor {
+    /// Trades the leg with an immediate or cancel limit order, or unwinds it
+    /// with a market order.
+    async fn execute_leg(
+        &self,
+        opportunity: &ArbitrageOpportunity,
+        intent: LegIntent,
+    ) -> AppResult<LegFill> {
+        let order = CexOrder::from_leg(opportunity, intent)?;
+        let order_id = match intent {
+            LegIntent::Open => {
+                self.client
+                    .place_limit_order(
+                        order.client_order_id.clone(),
+                        order.product_id.clone(),
+                        order.side,
+                        order.size,
+                        order.limit_price,
+                        false,
+                    )
+                    .await?
+            },
+            LegIntent::Unwind => {
+                self.client
+                    .place_market_order(
+                        order.client_order_id.clone(),
+                        order.product_id.clone(),
+                        order.side,
+                        order.size,
+                    )
+                    .await?
+            },
+        };
+        *self
+            .placed
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) =
+            Some((order.client_order_id.clone(), order_id.clone()));
+        self.report(
+            &opportunity.symbol,
+            &order_id,
+            order.side,
+            OrderState::Submitted,
+            Decimal::ZERO,
+            None,
+        );
+        let filled = self.final_order(&opportunity.symbol, &order_id).await?;
+        info!(
+            order_id,
+            side = ?order.side,
+            ?intent,
+            size = %order.size,
+            filled_size = %filled.filled_size,
+            "cex leg filled"
+        );
+        Ok(LegFill { leg: Leg::Cex, size: filled.filled_size, reference: order_id })
+    }
+
+    /// Cancels the order of the leg and polls it until final.
+    async fn settle_leg(
+        &self,
+        opportunity: &ArbitrageOpportunity,
+        intent: LegIntent,
+    ) -> AppResult<LegFill> {
+        let order = CexOrder::from_leg(opportunity, intent)?;
+        let placed = self
+            .placed
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone();
+        let order_id = placed
+            .filter(|(client_order_id, _)| *client_order_id == order.client_order_id)
+            .map(|(_, order_id)| order_id)
+            .ok_or_else(|| {
+                AppError::TransactionError(format!(
+                    "order {} is not known to be placed",
+                    order.client_order_id
+                ))
+            })?;
+        // Orders already final can no longer be cancelled
+        if let Err(e) = self.client.cancel_order(&order_id).await {
+            warn!(order_id, error = %e, "failed to cancel cex order");
+        }
+        let settled = self.final_order(&opportunity.symbol, &order_id).await?;
+        info!(
+            order_id,
+            ?intent,
+            filled_size = %settled.filled_size,
+            status = ?settled.status,
+            "cex leg settled"
+        );
+        Ok(LegFill { leg: Leg::Cex, size: settled.filled_size, reference: order_id })
+    }
+}
+
+#[async_trait::async_trait]
+impl Executor<InternalAction> for CexExecutor {
+    fn id(&self) -> &str { &self.name }
+
+    async fn execute_actions(&mut self, actions: Vec<InternalAction>) -> AppResult<()> {
+        for action in actions {
+            match action {
+                InternalAction::Opportunity(opportunity) => self.execute(&opportunity).await?,
+                InternalAction::Suppressed(_) | InternalAction::Halt { .. } => {},
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use rust_decimal_macros::dec;
+    use serde_json::json;
+    use sikkara_core::{MockClock, Secret};
+    use wiremock::{
+        matchers::{body_json, method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    use super::*;
+    use crate::engine::PoolSymbol;
+
+    fn opportunity(direction: ArbitrageDirection, size: Decimal) -> ArbitrageOpportunity {
+        ArbitrageOpportunity {
+            symbol: PoolSymbol::ETH_USDC,
+            direction,
+            cex_price: dec!(2520.5),
+            dex_price: dec!(2500),
+            net_bps: dec!(81.33),
+            recommended_size: size,
+            detected_at: "2025-02-12T21:12:33.250Z".parse().unwrap(),
+            cex_quote_at: "2025-02-12T21:12:33.250Z".parse().unwrap(),
+            dex_quote_at: "2025-02-12T21:12:33.250Z".parse().unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_cex_leg_of_opportunity() {
+        let order =
+            CexOrder::from_opportunity(&opportunity(ArbitrageDirection::BuyDexSellCex, dec!(0.5)))
+                .unwrap();
+        assert_eq!(
+            order,
+            CexOrder {
+                client_order_id: "sikarra-ETH-USDC-1739394753250".to_string(),
+                product_id: CoinbaseSymbol::EthUsd,
+                side: OrderSide::Sell,
+                size: dec!(0.5),
+                limit_price: dec!(2520.5),
+            }
+        );
+
+        let order =
+            CexOrder::from_opportunity(&opportunity(ArbitrageDirection::BuyCexSellDex, dec!(0.5)))
+                .unwrap();
+        assert_eq!(order.side, OrderSide::Buy);
+
+        let no_size = opportunity(ArbitrageDirection::BuyCexSellDex, Decimal::ZERO);
+        assert_eq!(CexOrder::from_opportunity(&no_size), None);
+    }
+
+    #[tokio::test]
+    async fn test_opportunity_places_ioc_limit_order() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/api/v3/brokerage/orders"))
+            .and(body_json(json!({
+                "client_order_id": "sikarra-ETH-USDC-1739394753250",
+                "product_id": "ETH-USD",
+                "side": "SELL",
+                "order_configuration": {
+                    "sor_limit_ioc": { "base_size": "0.5", "limit_price": "2520.5" }
+                }
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "success": true,
+                "success_response": { "order_id": "11111-000000-000000" }
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let clock = MockClock::new("2025-02-12T21:12:34Z".parse().unwrap());
+        let client =
+            CoinbaseTradeClient::new(&server.uri(), "key".to_string(), Secret::new("secret"))
+                .with_clock(Arc::new(clock));
+        let mut executor = CexExecutor::new("ETH-USDC".to_string(), client);
+        executor
+            .execute_actions(vec![
+                InternalAction::Opportunity(opportunity(
+                    ArbitrageDirection::BuyDexSellCex,
+                    dec!(0.5),
+                )),
+                // Unsized opportunities are not traded
+                InternalAction::Opportunity(opportunity(
+                    ArbitrageDirection::BuyDexSellCex,
+                    Decimal::ZERO,
+                )),
+            ])
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_unwind_leg_places_offsetting_market_order() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/api/v3/brokerage/orders"))
+            .and(body_json(json!({
+                "client_order_id": "sikarra-ETH-USDC-1739394753250-unwind",
+                "product_id": "ETH-USD",
+                "side": "BUY",
+                "order_configuration": { "market_market_ioc": { "base_size": "0.4" } }
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "success": true,
+                "success_response": { "order_id": "22222-000000-000000" }
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api/v3/brokerage/orders/historical/22222-000000-000000"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "order": {
+                    "order_id": "22222-000000-000000",
+                    "client_order_id": "sikarra-ETH-USDC-1739394753250-unwind",
+                    "product_id": "ETH-USD",
+                    "side": "BUY",
+                    "status": "FILLED",
+                    "filled_size": "0.4",
+                    "average_filled_price": "2521",
+                    "total_fees": "1.2"
+                }
+            })))
+            .mount(&server)
+            .await;
+
+        let client =
+            CoinbaseTradeClient::new(&server.uri(), "key".to_string(), Secret::new("secret"));
+        let (order_updates, mut orders) = mpsc::unbounded_channel();
+        let executor =
+            CexExecutor::new("ETH-USDC".to_string(), client).with_order_updates(order_updates);
+        // The opportunity sold on the CEX, unwinding buys back what was sold
+        let fill = executor
+            .execute_leg(
+                &opportunity(ArbitrageDirection::BuyDexSellCex, dec!(0.4)),
+                LegIntent::Unwind,
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            fill,
+            LegFill {
+                leg: Leg::Cex,
+                size: dec!(0.4),
+                reference: "22222-000000-000000".to_string()
+            }
+        );
+
+        let submitted = orders.recv().await.unwrap();
+        assert_eq!(
+            (submitted.venue, submitted.side, submitted.state, submitted.filled),
+            (Leg::Cex, OrderSide::Buy, OrderState::Submitted, Decimal::ZERO)
+        );
+        let filled = orders.recv().await.unwrap();
+        assert_eq!(filled.order_id, "22222-000000-000000");
+        assert_eq!(
+            (filled.state, filled.filled, filled.avg_price),
+            (OrderState::Filled, dec!(0.4), Some(dec!(2521)))
+        );
+        assert!(orders.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_timed_out_leg_is_cancelled_and_settled() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/api/v3/brokerage/orders"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "success": true,
+                "success_response": { "order_id": "11111-000000-000000" }
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+        let order = |status: &str, filled_size: &str| {
+            json!({
+                "order": {
+                    "order_id": "11111-000000-000000",
+                    "client_order_id": "sikarra-ETH-USDC-1739394753250",
+                    "product_id": "ETH-USD",
+                    "side": "SELL",
+                    "status": status,
+                    "filled_size": filled_size,
+                    "average_filled_price": "2520.5",
+                    "total_fees": "0.6"
+                }
+            })
+        };
+        Mock::given(method("GET"))
+            .and(path("/api/v3/brokerage/orders/historical/11111-000000-000000"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(order("OPEN", "0")))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/api/v3/brokerage/orders/batch_cancel"))
+            .and(body_json(json!({ "order_ids": ["11111-000000-000000"] })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "results": [{ "success": true, "order_id": "11111-000000-000000" }]
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+        // Filled in part before the cancellation
+        Mock::given(method("GET"))
+            .and(path("/api/v3/brokerage/orders/historical/11111-000000-000000"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(order("CANCELLED", "0.2")))
+            .mount(&server)
+            .await;
+
+        let client =
+            CoinbaseTradeClient::new(&server.uri(), "key".to_string(), Secret::new("secret"));
+        let (order_updates, mut orders) = mpsc::unbounded_channel();
+        let executor =
+            CexExecutor::new("ETH-USDC".to_string(), client).with_order_updates(order_updates);
+        let opportunity = opportunity(ArbitrageDirection::BuyDexSellCex, dec!(0.5));
+        // The order is still open when the leg times out
+        let timed_out = tokio::time::timeout(
+            Duration::from_millis(100),
+            executor.execute_leg(&opportunity, LegIntent::Open),
+        )
+        .await;
+        assert!(timed_out.is_err());
+
+        let fill = executor
+            .settle_leg(&opportunity, LegIntent::Open)
+            .await
+            .unwrap();
+        assert_eq!(
+            fill,
+            LegFill {
+                leg: Leg::Cex,
+                size: dec!(0.2),
+                reference: "11111-000000-000000".to_string()
+            }
+        );
+        // The late fill is reported
+        let mut last = None;
+        while let Ok(update) = orders.try_recv() {
+            last = Some(update);
+        }
+        let last = last.unwrap();
+        assert_eq!((last.state, last.filled), (OrderState::Cancelled, dec!(0.2)));
+
+        // Unwinding it was never placed
+        assert!(executor
+            .settle_leg(&opportunity, LegIntent::Unwind)
+            .await
+            .is_err());
+    }
+}
@@ -0,0 +1,808 @@
+//! Execution of the DEX leg of arbitrage opportunities.
+//!
+//! The [`DexExecutor`] swaps the DEX leg of every opportunity through the
+//! Universal Router. The swap pays an exact input and reverts if it returns
+//! less than the quoted output minus the accepted slippage, or once its
+//! deadline has passed. It is simulated before being submitted, so swaps
+//! which would revert never cost gas. Submitted swaps are watched by a
+//! [`ReceiptMonitor`] until confirmed, dropped or timed out.
+//!
+//! The progress of every swap is fed back into the pipeline as
+//! [`ExecutionEvent`]s carrying the correlation id of the opportunity, and as
+//! [`OrderUpdate`]s if enabled.
+//!
+//! The swap of a leg timing out is settled from its receipt, or once its nonce
+//! was used by another transaction or its deadline has passed, as it can then
+//! no longer fill.
+
+use std::{
+    fmt,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use alloy::{eips::BlockNumberOrTag, primitives::TxHash, providers::Provider};
+use rust_decimal::{prelude::ToPrimitive, Decimal};
+use sikkara_adapters::{
+    min_amount_out, ExactInSingleSwap, OrderSide, PendingTx, PoolKey, ReceiptMonitor,
+    ReceiptStatus, TxFailureKind, TxSubmitter, UniversalRouter, Urgency,
+};
+use sikkara_core::{current_correlation_id, AppError, AppResult, Executor};
+use tokio::sync::mpsc;
+use tracing::{debug, info, warn};
+
+use crate::{
+    engine::{
+        ArbitrageDirection, ArbitrageOpportunity, ExecutionEvent, ExecutionStatus, InternalAction,
+        Leg, LegIntent, OrderState, OrderUpdate, Pool, PoolSymbol, Token,
+    },
+    executors::{LegExecutor, LegFill},
+};
+
+/// Executor swapping the DEX leg of opportunities through the Universal
+/// Router.
+///
+/// Token 0 of the pool is the base asset, priced in token 1.
+pub struct DexExecutor<P>
+where
+    P: Provider + Send + Sync + 'static,
+{
+    name: String,
+    pool: Pool,
+    submitter: Arc<TxSubmitter<P>>,
+    router: UniversalRouter,
+    max_slippage_bps: u32,
+    deadline: Duration,
+    receipts: Arc<ReceiptMonitor<Arc<P>>>,
+    events: mpsc::UnboundedSender<ExecutionEvent>,
+    order_updates: Option<mpsc::UnboundedSender<OrderUpdate>>,
+    /// Last swap submitted for a leg, settled if the leg times out
+    submitted: Mutex<Option<SubmittedSwap>>,
+}
+
+/// A swap submitted for the leg of an opportunity.
+#[derive(Debug, Clone)]
+struct SubmittedSwap {
+    /// Opportunity in the direction swapped
+    opportunity: ArbitrageOpportunity,
+    pending: PendingTx,
+    /// Timestamp after which the swap reverts
+    deadline: u64,
+}
+
+impl<P> DexExecutor<P>
+where
+    P: Provider + Send + Sync + 'static,
+{
+    /// Creates the executor of a pool, reporting the progress of its swaps
+    /// through `events`.
+    ///
+    /// Swaps accept at most `max_slippage_bps` of slippage over the DEX price
+    /// of the opportunity, revert once `deadline` has passed since they were
+    /// built and are watched by `receipts` until they are confirmed or
+    /// failed.
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        pool: Pool,
+        submitter: Arc<TxSubmitter<P>>,
+        router: UniversalRouter,
+        max_slippage_bps: u32,
+        deadline: Duration,
+        receipts: Arc<ReceiptMonitor<Arc<P>>>,
+        events: mpsc::UnboundedSender<ExecutionEvent>,
+    ) -> Self {
+        Self {
+            name: format!("dex_executor_{}", pool.symbol),
+            pool,
+            submitter,
+            router,
+            max_slippage_bps,
+            deadline,
+            receipts,
+            events,
+            order_updates: None,
+            submitted: Mutex::new(None),
+        }
+    }
+
+    /// Also reports the swaps as order updates through `order_updates`.
+    pub fn with_order_updates(mut self, order_updates: mpsc::UnboundedSender<OrderUpdate>) -> Self {
+        self.order_updates = Some(order_updates);
+        self
+    }
+
+    /// Returns the swap of the DEX leg of an opportunity, none if no size is
+    /// recommended or its amounts do not fit the tokens.
+    pub fn swap(&self, opportunity: &ArbitrageOpportunity) -> Option<ExactInSingleSwap> {
+        if opportunity.recommended_size <= Decimal::ZERO {
+            return None;
+        }
+        let (base, quote) = (&self.pool.token_0, &self.pool.token_1);
+        let quote_size = opportunity
+            .recommended_size
+            .checked_mul(opportunity.dex_price)?;
+        let (currency_in, amount_in, quoted_out) = match opportunity.direction {
+            ArbitrageDirection::BuyDexSellCex => (
+                quote.address,
+                to_units(quote_size, quote)?,
+                to_units(opportunity.recommended_size, base)?,
+            ),
+            ArbitrageDirection::BuyCexSellDex => (
+                base.address,
+                to_units(opportunity.recommended_size, base)?,
+                to_units(quote_size, quote)?,
+            ),
+        };
+        Some(ExactInSingleSwap {
+            pool_key: PoolKey::sorted(
+                base.address,
+                quote.address,
+                self.pool.fee_tier,
+                self.pool.tick_spacing,
+                self.pool.hook,
+            ),
+            currency_in,
+            amount_in,
+            amount_out_minimum: min_amount_out(quoted_out, self.max_slippage_bps),
+        })
+    }
+
+    async fn execute(&self, opportunity: &ArbitrageOpportunity) {
+        let Some(swap) = self.swap(opportunity) else {
+            warn!(symbol = %opportunity.symbol, "no trade size configured, opportunity not executed");
+            return;
+        };
+        let report = self.reporter(opportunity);
+        let Ok((pending, _)) = self.submit_swap(opportunity, &swap, &report).await else {
+            return;
+        };
+
+        // Wait for the swap to be mined without holding up the next actions, a
+        // failure is reported as an execution event
+        let receipts = self.receipts.watch(&pending).await;
+        let submitter = self.submitter.clone();
+        tokio::spawn(async move {
+            let _ = confirm_swap(&submitter, &pending, receipts, &report).await;
+        });
+    }
+
+    fn reporter(&self, opportunity: &ArbitrageOpportunity) -> Reporter {
+        let side = match opportunity.direction {
+            ArbitrageDirection::BuyDexSellCex => OrderSide::Buy,
+            ArbitrageDirection::BuyCexSellDex => OrderSide::Sell,
+        };
+        Reporter {
+            correlation_id: current_correlation_id(),
+            symbol: opportunity.symbol.clone(),
+            side,
+            size: opportunity.recommended_size,
+            events: self.events.clone(),
+            order_updates: self.order_updates.clone(),
+        }
+    }
+
+    /// Simulates and submits a swap, reporting whether it was submitted.
+    /// Returns the submitted swap with its deadline.
+    async fn submit_swap(
+        &self,
+        opportunity: &ArbitrageOpportunity,
+        swap: &ExactInSingleSwap,
+        report: &Reporter,
+    ) -> AppResult<(PendingTx, u64)> {
+        let deadline = jiff::Timestamp::now().as_second() as u64 + self.deadline.as_secs();
+        let request = self.router.swap_request(swap, deadline);
+        if let Err(e) = self.submitter.simulate(&request).await {
+            warn!(symbol = %opportunity.symbol, error = %e, "dex swap simulation failed");
+            report.failed(None, &e);
+            return Err(e);
+        }
+        let pending = match self.submitter.submit(request, Urgency::Urgent).await {
+            Ok(pending) => pending,
+            Err(e) => {
+                warn!(symbol = %opportunity.symbol, error = %e, "dex swap submission failed");
+                report.failed(None, &e);
+                return Err(e);
+            },
+        };
+        info!(
+            tx_hash = %pending.hash,
+            symbol = %opportunity.symbol,
+            direction = ?opportunity.direction,
+            amount_in = swap.amount_in,
+            amount_out_minimum = swap.amount_out_minimum,
+            deadline,
+            "dex swap submitted"
+        );
+        report.send(ExecutionStatus::Submitted { tx_hash: pending.hash, nonce: pending.nonce });
+        report.order(pending.hash, OrderState::Submitted);
+        Ok((pending, deadline))
+    }
+
+    /// Returns whether the swap submitted for a leg succeeded, none while it
+    /// may still be mined.
+    async fn settle_swap(
+        &self,
+        swap: &SubmittedSwap,
+        report: &Reporter,
+    ) -> AppResult<Option<bool>> {
+        let provider = self.submitter.provider();
+        let nonces = self.submitter.nonces();
+        let nonce = swap.pending.nonce;
+        // The swap may have been replaced with higher fees
+        let tx_hash = nonces
+            .in_flight(nonce)
+            .await
+            .map_or(swap.pending.hash, |tx| tx.hash);
+        if let Some(receipt) = provider.get_transaction_receipt(tx_hash).await? {
+            nonces.confirm(nonce).await;
+            let block_number = receipt.block_number.unwrap_or_default();
+            if receipt.status() {
+                report.send(ExecutionStatus::Confirmed {
+                    tx_hash,
+                    block_number,
+                    gas_used: receipt.gas_used,
+                });
+                report.order(tx_hash, OrderState::Filled);
+                return Ok(Some(true));
+            }
+            let error = format!("transaction {} reverted in block {}", tx_hash, block_number);
+            report.failed(Some(tx_hash), &AppError::TransactionError(error));
+            return Ok(Some(false));
+        }
+
+        let mined = provider
+            .get_transaction_count(self.submitter.address())
+            .latest()
+            .await?;
+        if mined > nonce {
+            nonces.mark_stale().await;
+            let error = format!(
+                "transaction {} dropped, its nonce {} was used by another transaction",
+                tx_hash, nonce
+            );
+            report.failed(Some(tx_hash), &AppError::TransactionError(error));
+            return Ok(Some(false));
+        }
+        let latest = provider
+            .get_block_by_number(BlockNumberOrTag::Latest)
+            .await?
+            .ok_or_else(|| AppError::TransactionError("no latest block".to_string()))?;
+        if latest.header.timestamp > swap.deadline {
+            // Still holds its nonce, which is freed once it reverted or was
+            // dropped
+            let error =
+                format!("transaction {} not mined before its deadline {}", tx_hash, swap.deadline);
+            report.failed(Some(tx_hash), &AppError::TransactionError(error));
+            return Ok(Some(false));
+        }
+        Ok(None)
+    }
+}
+
+/// Waits for a submitted swap to be confirmed, reporting whether it
+/// succeeded.
+///
+/// The nonces of the signer are resynced once the swap was dropped or timed
+/// out, as the chain no longer agrees with them.
+async fn confirm_swap<P>(
+    submitter: &TxSubmitter<P>,
+    pending: &PendingTx,
+    mut receipts: mpsc::UnboundedReceiver<ReceiptStatus>,
+    report: &Reporter,
+) -> AppResult<()>
+where
+    P: Provider + Send + Sync,
+{
+    let tx_hash = pending.hash;
+    while let Some(status) = receipts.recv().await {
+        let error = match status {
+            ReceiptStatus::Included { block_number } => {
+                debug!(%tx_hash, block_number, "dex swap included");
+                continue;
+            },
+            ReceiptStatus::Reorged { block_number } => {
+                warn!(%tx_hash, block_number, "dex swap reorged out");
+                report.send(ExecutionStatus::Reorged { tx_hash, block_number });
+                continue;
+            },
+            ReceiptStatus::Confirmed { block_number, gas_used } => {
+                submitter.nonces().confirm(pending.nonce).await;
+                info!(%tx_hash, block_number, "dex swap confirmed");
+                report.send(ExecutionStatus::Confirmed { tx_hash, block_number, gas_used });
+                report.order(tx_hash, OrderState::Filled);
+                return Ok(());
+            },
+            ReceiptStatus::Reverted { block_number, .. } => {
+                submitter.nonces().confirm(pending.nonce).await;
+                format!("transaction {} reverted in block {}", tx_hash, block_number)
+            },
+            ReceiptStatus::Dropped => {
+                submitter.nonces().mark_stale().await;
+                format!(
+                    "transaction {} dropped, its nonce {} was used by another transaction",
+                    tx_hash, pending.nonce
+                )
+            },
+            ReceiptStatus::TimedOut { blocks } => {
+                submitter.nonces().mark_stale().await;
+                format!("transaction {} timed out, not mined within {} blocks", tx_hash, blocks)
+            },
+        };
+        let error = AppError::TransactionError(error);
+        warn!(%tx_hash, error = %error, "dex swap failed");
+        report.failed(Some(tx_hash), &error);
+        return Err(error.into());
+    }
+    Err(AppError::TransactionError(format!("transaction {} is no longer watched", tx_hash)).into())
+}
+
+#[async_trait::async_trait]
+impl<P> LegExecutor for DexExecutor<P>
+where
+    P: Provider + Send + Sync + 'static,
+{
+    /// Swaps the leg and waits for the swap to be mined. The fill is the size
+    /// of the opportunity, the swap reverting if it returns less than the
+    /// accepted slippage allows.
+    async fn execute_leg(
+        &self,
+        opportunity: &ArbitrageOpportunity,
+        intent: LegIntent,
+    ) -> AppResult<LegFill> {
+        let opportunity = match intent {
+            LegIntent::Open => opportunity.clone(),
+            LegIntent::Unwind => ArbitrageOpportunity {
+                direction: opportunity.direction.opposite(),
+                ..opportunity.clone()
+            },
+        };
+        let swap = self.swap(&opportunity).ok_or_else(|| {
+            AppError::ConfigError(format!(
+                "no swap of size {} for {}",
+                opportunity.recommended_size, opportunity.symbol
+            ))
+        })?;
+        let report = self.reporter(&opportunity);
+        let (pending, deadline) = self.submit_swap(&opportunity, &swap, &report).await?;
+        let submitted =
+            SubmittedSwap { opportunity: opportunity.clone(), pending: pending.clone(), deadline };
+        *self
+            .submitted
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(submitted);
+        let receipts = self.receipts.watch(&pending).await;
+        confirm_swap(&self.submitter, &pending, receipts, &report).await?;
+        Ok(LegFill {
+            leg: Leg::Dex,
+            size: opportunity.recommended_size,
+            reference: pending.hash.to_string(),
+        })
+    }
+
+    /// Reads the receipt of the swap of the leg, or whether it can no longer
+    /// be mined successfully. Fails while it still can.
+    async fn settle_leg(
+        &self,
+        opportunity: &ArbitrageOpportunity,
+        intent: LegIntent,
+    ) -> AppResult<LegFill> {
+        let opportunity = match intent {
+            LegIntent::Open => opportunity.clone(),
+            LegIntent::Unwind => ArbitrageOpportunity {
+                direction: opportunity.direction.opposite(),
+                ..opportunity.clone()
+            },
+        };
+        let submitted = self
+            .submitted
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone()
+            .filter(|swap| swap.opportunity == opportunity)
+            .ok_or_else(|| {
+                AppError::TransactionError(format!(
+                    "no swap of {} known to be submitted",
+                    opportunity.symbol
+                ))
+            })?;
+        let tx_hash = submitted.pending.hash;
+        let report = self.reporter(&opportunity);
+        let size = match self.settle_swap(&submitted, &report).await? {
+            Some(true) => opportunity.recommended_size,
+            Some(false) => Decimal::ZERO,
+            None => {
+                return Err(AppError::TransactionError(format!(
+                    "transaction {} may still be mined before its deadline {}",
+                    tx_hash, submitted.deadline
+                ))
+                .into());
+            },
+        };
+        info!(%tx_hash, ?intent, %size, "dex leg settled");
+        Ok(LegFill { leg: Leg::Dex, size, reference: tx_hash.to_string() })
+    }
+}
+
+#[async_trait::async_trait]
+impl<P> Executor<InternalAction> for DexExecutor<P>
+where
+    P: Provider + Send + Sync + 'static,
+{
+    fn id(&self) -> &str { &self.name }
+
+    async fn execute_actions(&mut self, actions: Vec<InternalAction>) -> AppResult<()> {
+        for action in actions {
+            match action {
+                InternalAction::Opportunity(opportunity) => self.execute(&opportunity).await,
+                InternalAction::Suppressed(_) | InternalAction::Halt { .. } => {},
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Reports the progress of the swap of a single opportunity.
+#[derive(Clone)]
+struct Reporter {
+    correlation_id: Option<u64>,
+    symbol: PoolSymbol,
+    /// Side of the swap in the base asset
+    side: OrderSide,
+    size: Decimal,
+    events: mpsc::UnboundedSender<ExecutionEvent>,
+    order_updates: Option<mpsc::UnboundedSender<OrderUpdate>>,
+}
+
+impl Reporter {
+    fn send(&self, status: ExecutionStatus) {
+        let event = ExecutionEvent {
+            correlation_id: self.correlation_id,
+            symbol: self.symbol.clone(),
+            status,
+            timestamp: jiff::Timestamp::now(),
+        };
+        if self.events.send(event).is_err() {
+            warn!(symbol = %self.symbol, "execution events are no longer collected");
+        }
+    }
+
+    /// Reports the swap as an order, which fills its whole size once mined
+    /// as it reverts otherwise.
+    fn order(&self, tx_hash: TxHash, state: OrderState) {
+        let Some(order_updates) = &self.order_updates else {
+            return;
+        };
+        let update = OrderUpdate {
+            venue: Leg::Dex,
+            correlation_id: self.correlation_id,
+            symbol: self.symbol.clone(),
+            order_id: tx_hash.to_string(),
+            side: self.side,
+            state,
+            filled: if state == OrderState::Filled { self.size } else { Decimal::ZERO },
+            avg_price: None,
+            timestamp: jiff::Timestamp::now(),
+        };
+        if order_updates.send(update).is_err() {
+            warn!(symbol = %self.symbol, "order updates are no longer collected");
+        }
+    }
+
+    fn failed(&self, tx_hash: Option<TxHash>, error: &impl fmt::Display) {
+        // Swaps failing before being submitted never became an order
+        if let Some(tx_hash) = tx_hash {
+            self.order(tx_hash, OrderState::Failed);
+        }
+        self.send(ExecutionStatus::Failed {
+            tx_hash,
+            kind: TxFailureKind::classify(error),
+            reason: error.to_string(),
+        });
+    }
+}
+
+/// Converts an amount of a token into its smallest unit, none if it does not
+/// fit.
+pub(crate) fn to_units(amount: Decimal, token: &Token) -> Option<u128> {
+    amount
+        .checked_mul(Decimal::from(10u64.checked_pow(token.decimals.into())?))?
+        .trunc()
+        .to_u128()
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::{
+        primitives::{address, b256, Address, U64},
+        providers::ProviderBuilder,
+        transports::mock::Asserter,
+    };
+    use rust_decimal_macros::dec;
+    use serde_json::{json, Value};
+    use sikkara_adapters::{signer_from_key, FeeCaps, FeeEstimator};
+    use sikkara_core::Secret;
+
+    use super::*;
+
+    /// The first well known Anvil development key.
+    const DEV_KEY: &str = "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+    const WETH: Address = address!("0x4200000000000000000000000000000000000006");
+    const USDC: Address = address!("0x833589fCD6eDb6E08f4c7C32D4f71b54bdA02913");
+    const TX_HASH: TxHash =
+        b256!("0x2c6c1d4b32d4a6f7f3e2c95ac46f8d2d4e0bd7b4b2b4c4f1a2a3b9c1d8e7f6a5");
+
+    fn executor(
+        asserter: Asserter,
+    ) -> (DexExecutor<impl Provider>, mpsc::UnboundedReceiver<ExecutionEvent>) {
+        let provider = Arc::new(
+            ProviderBuilder::new()
+                .disable_recommended_fillers()
+                .connect_mocked_client(asserter),
+        );
+        let fee_caps =
+            FeeCaps { max_fee_per_gas: 100_000_000_000, max_priority_fee_per_gas: 2_000_000_000 };
+        let submitter = TxSubmitter::new(
+            provider.clone(),
+            signer_from_key(&Secret::new(DEV_KEY)).unwrap(),
+            8453,
+            FeeEstimator::new(fee_caps),
+        );
+        let receipts = ReceiptMonitor::new(provider, submitter.address(), 1, 10);
+        let pool = Pool {
+            symbol: PoolSymbol::ETH_USDC,
+            token_0: Token { address: WETH, decimals: 18 },
+            token_1: Token { address: USDC, decimals: 6 },
+            fee_tier: 500,
+            tick_spacing: 10,
+            hook: Address::ZERO,
+            scaling: 2,
+            poll_interval: Duration::from_secs(5),
+        };
+        let (sender, events) = mpsc::unbounded_channel();
+        let executor = DexExecutor::new(
+            pool,
+            Arc::new(submitter),
+            UniversalRouter::new(address!("0x6fF5693b99212Da76ad316178A184AB56D299b43")),
+            50,
+            Duration::from_secs(30),
+            Arc::new(receipts),
+            sender,
+        );
+        (executor, events)
+    }
+
+    fn opportunity(direction: ArbitrageDirection) -> ArbitrageOpportunity {
+        ArbitrageOpportunity {
+            symbol: PoolSymbol::ETH_USDC,
+            direction,
+            cex_price: dec!(2520.5),
+            dex_price: dec!(2500),
+            net_bps: dec!(81.33),
+            recommended_size: dec!(0.5),
+            detected_at: "2025-02-12T21:12:33.250Z".parse().unwrap(),
+            cex_quote_at: "2025-02-12T21:12:33.250Z".parse().unwrap(),
+            dex_quote_at: "2025-02-12T21:12:33.250Z".parse().unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_dex_leg_of_opportunity() {
+        let (executor, _) = executor(Asserter::new());
+
+        // Buying on the DEX pays the quote for the base
+        let swap = executor
+            .swap(&opportunity(ArbitrageDirection::BuyDexSellCex))
+            .unwrap();
+        assert_eq!(swap.currency_in, USDC);
+        assert_eq!(swap.amount_in, 1_250_000_000);
+        assert_eq!(swap.amount_out_minimum, 497_500_000_000_000_000);
+        assert_eq!(swap.currency_out(), WETH);
+
+        // Selling on the DEX pays the base for the quote
+        let swap = executor
+            .swap(&opportunity(ArbitrageDirection::BuyCexSellDex))
+            .unwrap();
+        assert_eq!(swap.currency_in, WETH);
+        assert_eq!(swap.amount_in, 500_000_000_000_000_000);
+        assert_eq!(swap.amount_out_minimum, 1_243_750_000);
+
+        let no_size = ArbitrageOpportunity {
+            recommended_size: Decimal::ZERO,
+            ..opportunity(ArbitrageDirection::BuyCexSellDex)
+        };
+        assert_eq!(executor.swap(&no_size), None);
+    }
+
+    #[tokio::test]
+    async fn test_swap_is_submitted_and_confirmed() {
+        let asserter = Asserter::new();
+        // Simulation
+        asserter.push_success(&"0x");
+        // Nonces, latest and pending
+        asserter.push_success(&U64::from(7));
+        asserter.push_success(&U64::from(7));
+        // Fees and gas
+        asserter.push_success(&json!({
+            "oldestBlock": "0x1e8480",
+            "baseFeePerGas": ["0x3b9aca00", "0x3b9aca00"],
+            "gasUsedRatio": [0.5],
+            "reward": [["0x3b9aca00"]]
+        }));
+        asserter.push_success(&U64::from(180_000));
+        asserter.push_success(&TX_HASH);
+        // Receipt polled in the next block
+        asserter.push_success(&U64::from(2_000_001));
+        asserter.push_success(&json!({
+            "type": "0x2",
+            "status": "0x1",
+            "transactionHash": TX_HASH,
+            "transactionIndex": "0x0",
+            "blockHash": "0x4e3a3754410177e6937ef1f84bba68ea139e8d1a2258c5f85db9f1cd715a1bdd",
+            "blockNumber": "0x1e8481",
+            "from": "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266",
+            "to": "0x6fF5693b99212Da76ad316178A184AB56D299b43",
+            "cumulativeGasUsed": "0x2bf20",
+            "gasUsed": "0x2bf20",
+            "effectiveGasPrice": "0x77359400",
+            "contractAddress": null,
+            "logs": [],
+            "logsBloom": format!("0x{}", "0".repeat(512))
+        }));
+
+        let (executor, mut events) = executor(asserter.clone());
+        let (order_updates, mut orders) = mpsc::unbounded_channel();
+        let mut executor = executor.with_order_updates(order_updates);
+        executor
+            .execute_actions(vec![InternalAction::Opportunity(opportunity(
+                ArbitrageDirection::BuyDexSellCex,
+            ))])
+            .await
+            .unwrap();
+        executor.receipts.poll().await.unwrap();
+
+        let submitted = events.recv().await.unwrap();
+        assert_eq!(submitted.symbol, PoolSymbol::ETH_USDC);
+        assert_eq!(submitted.status, ExecutionStatus::Submitted { tx_hash: TX_HASH, nonce: 7 });
+        let confirmed = events.recv().await.unwrap();
+        assert_eq!(
+            confirmed.status,
+            ExecutionStatus::Confirmed {
+                tx_hash: TX_HASH,
+                block_number: 2_000_001,
+                gas_used: 180_000
+            }
+        );
+        assert!(asserter.read_q().is_empty());
+
+        // The swap is reported as a DEX buy of the base asset
+        let submitted = orders.recv().await.unwrap();
+        assert_eq!(
+            (submitted.venue, submitted.side, submitted.state, submitted.filled),
+            (Leg::Dex, OrderSide::Buy, OrderState::Submitted, Decimal::ZERO)
+        );
+        let filled = orders.recv().await.unwrap();
+        assert_eq!(filled.order_id, TX_HASH.to_string());
+        assert_eq!((filled.state, filled.filled), (OrderState::Filled, dec!(0.5)));
+    }
+
+    #[tokio::test]
+    async fn test_reverting_swap_is_not_submitted() {
+        let asserter = Asserter::new();
+        asserter.push_failure_msg("execution reverted: V4TooLittleReceived");
+
+        let (mut executor, mut events) = executor(asserter.clone());
+        executor
+            .execute_actions(vec![InternalAction::Opportunity(opportunity(
+                ArbitrageDirection::BuyCexSellDex,
+            ))])
+            .await
+            .unwrap();
+
+        let ExecutionStatus::Failed { tx_hash, kind, reason } = events.recv().await.unwrap().status
+        else {
+            panic!("expected the swap to fail");
+        };
+        assert_eq!(tx_hash, None);
+        assert_eq!(kind, TxFailureKind::Revert);
+        assert!(reason.contains("V4TooLittleReceived"));
+        // Nothing is sent once the simulation reverted
+        assert!(events.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_dropped_swap_resyncs_nonces() {
+        let asserter = Asserter::new();
+        asserter.push_success(&"0x");
+        asserter.push_success(&U64::from(7));
+        asserter.push_success(&U64::from(7));
+        asserter.push_success(&json!({
+            "oldestBlock": "0x1e8480",
+            "baseFeePerGas": ["0x3b9aca00", "0x3b9aca00"],
+            "gasUsedRatio": [0.5],
+            "reward": [["0x3b9aca00"]]
+        }));
+        asserter.push_success(&U64::from(180_000));
+        asserter.push_success(&TX_HASH);
+        // Not mined, while its nonce was used by another transaction
+        asserter.push_success(&U64::from(2_000_001));
+        asserter.push_success(&Value::Null);
+        asserter.push_success(&U64::from(8));
+        asserter.push_success(&Value::Null);
+
+        let (mut executor, mut events) = executor(asserter.clone());
+        executor
+            .execute_actions(vec![InternalAction::Opportunity(opportunity(
+                ArbitrageDirection::BuyDexSellCex,
+            ))])
+            .await
+            .unwrap();
+        executor.receipts.poll().await.unwrap();
+
+        let submitted = events.recv().await.unwrap();
+        assert_eq!(submitted.status, ExecutionStatus::Submitted { tx_hash: TX_HASH, nonce: 7 });
+        let ExecutionStatus::Failed { tx_hash, kind, .. } = events.recv().await.unwrap().status
+        else {
+            panic!("expected the swap to be dropped");
+        };
+        assert_eq!((tx_hash, kind), (Some(TX_HASH), TxFailureKind::Dropped));
+        assert!(asserter.read_q().is_empty());
+
+        // The nonces are read from the chain again before the next swap
+        asserter.push_success(&U64::from(8));
+        asserter.push_success(&U64::from(8));
+        assert_eq!(executor.submitter.nonces().acquire().await.unwrap(), 8);
+    }
+
+    #[tokio::test]
+    async fn test_timed_out_leg_is_settled_from_its_nonce() {
+        let asserter = Asserter::new();
+        asserter.push_success(&"0x");
+        asserter.push_success(&U64::from(7));
+        asserter.push_success(&U64::from(7));
+        asserter.push_success(&json!({
+            "oldestBlock": "0x1e8480",
+            "baseFeePerGas": ["0x3b9aca00", "0x3b9aca00"],
+            "gasUsedRatio": [0.5],
+            "reward": [["0x3b9aca00"]]
+        }));
+        asserter.push_success(&U64::from(180_000));
+        asserter.push_success(&TX_HASH);
+
+        let (executor, mut events) = executor(asserter.clone());
+        let opportunity = opportunity(ArbitrageDirection::BuyDexSellCex);
+        // No block is produced before the leg times out
+        let timed_out = tokio::time::timeout(
+            Duration::from_millis(100),
+            executor.execute_leg(&opportunity, LegIntent::Open),
+        )
+        .await;
+        assert!(timed_out.is_err());
+        assert!(asserter.read_q().is_empty());
+
+        // Not mined, while its nonce was used by another transaction
+        asserter.push_success(&Value::Null);
+        asserter.push_success(&U64::from(8));
+        let fill = executor
+            .settle_leg(&opportunity, LegIntent::Open)
+            .await
+            .unwrap();
+        assert_eq!(
+            fill,
+            LegFill { leg: Leg::Dex, size: Decimal::ZERO, reference: TX_HASH.to_string() }
+        );
+        assert!(asserter.read_q().is_empty());
+        let submitted = events.recv().await.unwrap();
+        assert_eq!(submitted.status, ExecutionStatus::Submitted { tx_hash: TX_HASH, nonce: 7 });
+        let ExecutionStatus::Failed { tx_hash, kind, .. } = events.recv().await.unwrap().status
+        else {
+            panic!("expected the swap to be dropped");
+        };
+        assert_eq!((tx_hash, kind), (Some(TX_HASH), TxFailureKind::Dropped));
+
+        // Unwinding it was never submitted
+        assert!(executor
+            .settle_leg(&opportunity, LegIntent::Unwind)
+            .await
+            .is_err());
+    }
+}
@@ -0,0 +1,424 @@
+//! Hindsight evaluation of opportunities executed on paper.
+//!
+//! Paper trading assumes opportunities are filled at the quoted prices, which
+//! overstates the profit whenever the DEX price reverts before a transaction
+//! could have been mined. In dry run mode the [`HindsightExecutor`] keeps
+//! every opportunity pending for an evaluation window, then replays the prices
+//! recorded in the price history of the pool since its detection:
+//!
+//! - The legs are assumed to trade at the prices standing once the execution
+//!   latency elapsed, giving the realized profit.
+//! - The first time within the window the spread no longer covers the costs of
+//!   the opportunity is recorded as its reversion.
+//!
+//! Evaluations are appended to the audit log, if any, and accumulated into a
+//! summary of the pool. The executor is also registered as an engine, every
+//! event evaluating the opportunities whose window has elapsed.
+
+use std::{
+    sync::{Arc, Mutex, MutexGuard},
+    time::Duration,
+};
+
+use rust_decimal::Decimal;
+use serde::Serialize;
+use sikkara_core::{AppResult, Clock, Engine, Executor};
+use tracing::info;
+
+use crate::{
+    engine::{
+        ArbitrageDirection, ArbitrageOpportunity, InternalAction, InternalEvent,
+        PriceHistoryReader, PricePoint, PriceSource,
+    },
+    executors::{AuditEntry, AuditLog},
+};
+
+/// Outcome of an opportunity had it been executed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct HindsightEvaluation {
+    pub opportunity: ArbitrageOpportunity,
+    /// CEX price standing once the execution latency elapsed
+    pub realized_cex_price: Decimal,
+    /// DEX price standing once the execution latency elapsed
+    pub realized_dex_price: Decimal,
+    /// Expected profit in basis points of the CEX price at the realized prices
+    pub realized_net_bps: Decimal,
+    /// Profit of the recommended size at the quoted prices, in the quote asset
+    pub quoted_pnl: Decimal,
+    /// Profit of the recommended size at the realized prices, in the quote
+    /// asset
+    pub realized_pnl: Decimal,
+    /// Time after detection the spread stopped covering the costs, none if it
+    /// did not within the window
+    pub reverted_after_ms: Option<i64>,
+}
+
+/// Evaluations of a pool accumulated since start.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct HindsightSummary {
+    pub evaluated: u64,
+    /// Opportunities which reverted within the window
+    pub reverted: u64,
+    pub quoted_pnl: Decimal,
+    pub realized_pnl: Decimal,
+}
+
+impl HindsightSummary {
+    fn record(&mut self, evaluation: &HindsightEvaluation) {
+        self.evaluated += 1;
+        if evaluation.reverted_after_ms.is_some() {
+            self.reverted += 1;
+        }
+        self.quoted_pnl += evaluation.quoted_pnl;
+        self.realized_pnl += evaluation.realized_pnl;
+    }
+}
+
+/// Spread in basis points of the CEX price captured by trading in `direction`,
+/// negative if the prices moved against it.
+fn spread_bps(direction: ArbitrageDirection, cex_price: Decimal, dex_price: Decimal) -> Decimal {
+    if cex_price.is_zero() {
+        return Decimal::ZERO;
+    }
+    let spread = match direction {
+        ArbitrageDirection::BuyDexSellCex => cex_price - dex_price,
+        ArbitrageDirection::BuyCexSellDex => dex_price - cex_price,
+    };
+    spread / cex_price * Decimal::from(10_000)
+}
+
+/// Profit of trading `size` with a net profit of `net_bps` of the CEX price.
+fn pnl(size: Decimal, cex_price: Decimal, net_bps: Decimal) -> Decimal {
+    size * cex_price * net_bps / Decimal::from(10_000)
+}
+
+/// Evaluates an opportunity against the prices observed since its detection,
+/// oldest first, assuming its legs trade once `latency` elapsed.
+pub fn evaluate(
+    opportunity: &ArbitrageOpportunity,
+    points: &[PricePoint],
+    latency: Duration,
+    window: Duration,
+) -> HindsightEvaluation {
+    let detected_at = opportunity.detected_at;
+    let execute_at = detected_at.checked_add(latency).unwrap_or(detected_at);
+    let window_end = detected_at.checked_add(window).unwrap_or(detected_at);
+    // Costs are whatever separates the quoted spread from the net profit
+    let costs_bps = spread_bps(opportunity.direction, opportunity.cex_price, opportunity.dex_price)
+        - opportunity.net_bps;
+
+    let (mut cex_price, mut dex_price) = (opportunity.cex_price, opportunity.dex_price);
+    let (mut realized_cex_price, mut realized_dex_price) = (cex_price, dex_price);
+    let mut reverted_after_ms = None;
+    for point in points
+        .iter()
+        .filter(|point| point.timestamp >= detected_at && point.timestamp <= window_end)
+    {
+        match point.source {
+            PriceSource::Cex => cex_price = point.price,
+            PriceSource::Dex => dex_price = point.price,
+        }
+        if point.timestamp <= execute_at {
+            (realized_cex_price, realized_dex_price) = (cex_price, dex_price);
+        }
+        let net_bps = spread_bps(opportunity.direction, cex_price, dex_price) - costs_bps;
+        if reverted_after_ms.is_none() && net_bps <= Decimal::ZERO {
+            reverted_after_ms =
+                Some(point.timestamp.duration_since(detected_at).as_millis() as i64);
+        }
+    }
+
+    let realized_net_bps =
+        spread_bps(opportunity.direction, realized_cex_price, realized_dex_price) - costs_bps;
+    let size = opportunity.recommended_size;
+    HindsightEvaluation {
+        opportunity: opportunity.clone(),
+        realized_cex_price,
+        realized_dex_price,
+        realized_net_bps: realized_net_bps.round_dp(2),
+        quoted_pnl: pnl(size, opportunity.cex_price, opportunity.net_bps).round_dp(6),
+        realized_pnl: pnl(size, realized_cex_price, realized_net_bps).round_dp(6),
+        reverted_after_ms,
+    }
+}
+
+/// Executor keeping opportunities pending until their evaluation window
+/// elapsed, see the module documentation.
+///
+/// Cloning is cheap, all clones share the pending opportunities and the
+/// summary, so one clone is registered as the executor and another as the
+/// engine.
+#[derive(Debug, Clone)]
+pub struct HindsightExecutor {
+    name: String,
+    history: PriceHistoryReader,
+    clock: Arc<dyn Clock>,
+    latency: Duration,
+    window: Duration,
+    audit: Option<AuditLog>,
+    pending: Arc<Mutex<Vec<ArbitrageOpportunity>>>,
+    summary: Arc<Mutex<HindsightSummary>>,
+}
+
+impl HindsightExecutor {
+    pub fn new(
+        pool: String,
+        history: PriceHistoryReader,
+        clock: Arc<dyn Clock>,
+        latency: Duration,
+        window: Duration,
+    ) -> Self {
+        Self {
+            name: format!("hindsight_executor_{}", pool),
+            history,
+            clock,
+            latency,
+            window: window.max(latency),
+            audit: None,
+            pending: Arc::new(Mutex::new(Vec::new())),
+            summary: Arc::new(Mutex::new(HindsightSummary::default())),
+        }
+    }
+
+    /// Appends every evaluation to the audit log.
+    pub fn with_audit_log(mut self, log: AuditLog) -> Self {
+        self.audit = Some(log);
+        self
+    }
+
+    /// Returns the evaluations accumulated so far.
+    pub fn summary(&self) -> HindsightSummary { lock(&self.summary).clone() }
+
+    /// Evaluates the opportunities whose window has elapsed.
+    pub fn evaluate_due(&self) -> AppResult<Vec<HindsightEvaluation>> {
+        let now = self.clock.now();
+        let due = {
+            let mut pending = lock(&self.pending);
+            let (due, waiting) = pending.drain(..).partition(|opportunity| {
+                opportunity
+                    .detected_at
+                    .checked_add(self.window)
+                    .is_ok_and(|window_end| window_end <= now)
+            });
+            *pending = waiting;
+            due
+        };
+
+        let mut evaluations = Vec::with_capacity(due.len());
+        for opportunity in due {
+            let points = self.history.read().range(opportunity.detected_at);
+            let evaluation = evaluate(&opportunity, &points, self.latency, self.window);
+            if let Some(log) = &self.audit {
+                log.append(&AuditEntry::Hindsight(evaluation.clone()))?;
+            }
+            let summary = {
+                let mut summary = lock(&self.summary);
+                summary.record(&evaluation);
+                summary.clone()
+            };
+            info!(
+                "🔍 HINDSIGHT | Symbol: {} | Net: {} bps realized vs {} bps quoted | PnL: ${} realized vs ${} quoted | Reverted after: {} | Total: ${} realized vs ${} quoted over {} ({} reverted)",
+                opportunity.symbol,
+                evaluation.realized_net_bps,
+                opportunity.net_bps.round_dp(2),
+                evaluation.realized_pnl.round_dp(2),
+                evaluation.quoted_pnl.round_dp(2),
+                evaluation
+                    .reverted_after_ms
+                    .map_or("-".to_string(), |ms| format!("{}ms", ms)),
+                summary.realized_pnl.round_dp(2),
+                summary.quoted_pnl.round_dp(2),
+                summary.evaluated,
+                summary.reverted
+            );
+            evaluations.push(evaluation);
+        }
+        Ok(evaluations)
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+#[async_trait::async_trait]
+impl Executor<InternalAction> for HindsightExecutor {
+    fn id(&self) -> &str { &self.name }
+
+    async fn execute_actions(&mut self, actions: Vec<InternalAction>) -> AppResult<()> {
+        let mut pending = lock(&self.pending);
+        for action in actions {
+            if let InternalAction::Opportunity(opportunity) = action {
+                pending.push(opportunity);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl Engine<InternalEvent, InternalAction> for HindsightExecutor {
+    fn id(&self) -> &str { &self.name }
+
+    async fn process_event(&mut self, _event: InternalEvent) -> AppResult<Option<InternalAction>> {
+        self.evaluate_due()?;
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal_macros::dec;
+    use sikkara_core::MockClock;
+
+    use super::*;
+    use crate::{
+        engine::{PoolPriceUpdate, PoolSymbol, PriceHistoryHandle, Ticker},
+        executors::{audit::file_name, verify_audit_log},
+    };
+
+    const DETECTED_AT: &str = "2025-02-12T21:00:00Z";
+
+    fn opportunity() -> ArbitrageOpportunity {
+        ArbitrageOpportunity {
+            symbol: PoolSymbol::ETH_USDC,
+            direction: ArbitrageDirection::BuyDexSellCex,
+            cex_price: dec!(2520),
+            dex_price: dec!(2500),
+            net_bps: dec!(59.37),
+            recommended_size: dec!(0.5),
+            detected_at: DETECTED_AT.parse().unwrap(),
+            cex_quote_at: DETECTED_AT.parse().unwrap(),
+            dex_quote_at: DETECTED_AT.parse().unwrap(),
+        }
+    }
+
+    /// Price observed `millis` after the detection of the opportunity.
+    fn point(source: PriceSource, millis: i64, price: Decimal) -> PricePoint {
+        let detected_at: jiff::Timestamp = DETECTED_AT.parse().unwrap();
+        PricePoint {
+            timestamp: detected_at
+                .checked_add(jiff::SignedDuration::from_millis(millis))
+                .unwrap(),
+            source,
+            price,
+        }
+    }
+
+    #[test]
+    fn test_persisting_opportunity_realizes_quoted_profit() {
+        let points = [
+            point(PriceSource::Dex, 200, dec!(2500)),
+            point(PriceSource::Cex, 400, dec!(2520)),
+            point(PriceSource::Dex, 4_000, dec!(2505)),
+        ];
+        let evaluation =
+            evaluate(&opportunity(), &points, Duration::from_secs(2), Duration::from_secs(10));
+        assert_eq!(evaluation.realized_dex_price, dec!(2500));
+        assert_eq!(evaluation.realized_net_bps, dec!(59.37));
+        assert_eq!(evaluation.realized_pnl, evaluation.quoted_pnl);
+        assert_eq!(evaluation.quoted_pnl, dec!(7.48062));
+        assert_eq!(evaluation.reverted_after_ms, None);
+    }
+
+    #[test]
+    fn test_reverting_dex_price_loses_the_profit() {
+        // The pool is arbitraged by someone else before we could have traded
+        let points = [
+            point(PriceSource::Dex, 1_000, dec!(2512)),
+            point(PriceSource::Dex, 1_500, dec!(2516)),
+            point(PriceSource::Dex, 3_000, dec!(2500)),
+            // Outside of the window
+            point(PriceSource::Dex, 12_000, dec!(2530)),
+        ];
+        let evaluation =
+            evaluate(&opportunity(), &points, Duration::from_secs(2), Duration::from_secs(10));
+        assert_eq!(evaluation.realized_cex_price, dec!(2520));
+        assert_eq!(evaluation.realized_dex_price, dec!(2516));
+        // 16 bps of spread left, eaten by the 20 bps of costs
+        assert_eq!(evaluation.realized_net_bps, dec!(-4.12));
+        assert!(evaluation.realized_pnl < Decimal::ZERO);
+        assert_eq!(evaluation.reverted_after_ms, Some(1_500));
+    }
+
+    #[tokio::test]
+    async fn test_evaluation_waits_for_the_window() {
+        let dir = tempfile::tempdir().unwrap();
+        let clock = MockClock::new(DETECTED_AT.parse().unwrap());
+        let log = AuditLog::open(dir.path(), Arc::new(clock.clone())).unwrap();
+        let history = PriceHistoryHandle::new(100);
+        let executor = HindsightExecutor::new(
+            "ETH-USDC".to_string(),
+            history.reader(),
+            Arc::new(clock.clone()),
+            Duration::from_secs(2),
+            Duration::from_secs(10),
+        )
+        .with_audit_log(log);
+        let (mut actions, mut events) = (executor.clone(), executor.clone());
+
+        actions
+            .execute_actions(vec![InternalAction::Opportunity(opportunity())])
+            .await
+            .unwrap();
+        for (millis, price) in [(1_000, dec!(2512)), (1_500, dec!(2516))] {
+            let update = InternalEvent::PoolPriceUpdate(PoolPriceUpdate {
+                symbol: PoolSymbol::ETH_USDC,
+                price,
+                timestamp: point(PriceSource::Dex, millis, price).timestamp,
+                block_number: None,
+                state: None,
+                effective_buy_price: None,
+                effective_sell_price: None,
+            });
+            history.record(&update);
+            clock.advance(Duration::from_secs(1));
+            events.process_event(update).await.unwrap();
+        }
+        assert_eq!(executor.summary(), HindsightSummary::default());
+
+        clock.set("2025-02-12T21:00:10Z".parse().unwrap());
+        let ticker = InternalEvent::TickerUpdate(Ticker {
+            exchage: crate::engine::Exchange::Coinbase,
+            symbol: PoolSymbol::ETH_USDC,
+            price: dec!(2520),
+            timestamp: clock.now(),
+            bid: None,
+            ask: None,
+            bid_size: None,
+            ask_size: None,
+            volume_24h: None,
+        });
+        events.process_event(ticker).await.unwrap();
+
+        let summary = executor.summary();
+        assert_eq!(summary.evaluated, 1);
+        assert_eq!(summary.reverted, 1);
+        assert_eq!(summary.quoted_pnl, dec!(7.48062));
+        assert!(summary.realized_pnl < Decimal::ZERO);
+        let path = dir.path().join(file_name("2025-02-12".parse().unwrap()));
+        assert_eq!(verify_audit_log(&path).unwrap().records, 1);
+        assert!(std::fs::read_to_string(&path)
+            .unwrap()
+            .contains(r#""kind":"hindsight""#));
+
+        // Evaluated once only
+        events
+            .process_event(InternalEvent::TickerUpdate(Ticker {
+                exchage: crate::engine::Exchange::Coinbase,
+                symbol: PoolSymbol::ETH_USDC,
+                price: dec!(2520),
+                timestamp: clock.now(),
+                bid: None,
+                ask: None,
+                bid_size: None,
+                ask_size: None,
+                volume_24h: None,
+            }))
+            .await
+            .unwrap();
+        assert_eq!(executor.summary().evaluated, 1);
+    }
+}
@@ -0,0 +1,30 @@
+mod audit;
+pub use audit::{
+    verify_audit_log, AuditChain, AuditEntry, AuditExecutor, AuditLog, AUDIT_SCHEMA_VERSION,
+    GENESIS_CHECKSUM,
+};
+
+mod alert;
+pub use alert::{Alert, AlertDispatcher, AlertExecutor, AlertType};
+
+mod cex;
+pub use cex::{CexExecutor, CexOrder};
+
+mod dex;
+pub use dex::DexExecutor;
+pub(crate) use dex::to_units;
+
+mod planner;
+pub use planner::{ExecutionPlan, ExecutionPlanner, LegExecutor, LegFill, RiskCheck};
+
+mod hindsight;
+pub use hindsight::{HindsightEvaluation, HindsightExecutor, HindsightSummary};
+
+mod paper;
+pub use paper::PaperExecutor;
+
+mod status;
+pub use status::{PriceObservation, StatusBoard, SymbolStatus};
+
+mod telegram;
+pub use telegram::{TelegramClient, TelegramCommand, TelegramCommandHandler};
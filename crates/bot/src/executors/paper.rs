@@ -0,0 +1,68 @@
+use sikkara_core::{AppResult, Executor};
+use tracing::{info, warn};
+
+use crate::{
+    engine::{ArbitrageDirection, ArbitrageOpportunity, InternalAction},
+    executors::CexOrder,
+    positions::DailyPnl,
+};
+
+/// Executor standing in for the live executors in dry run mode, logging the
+/// orders it would have placed instead of placing them.
+#[derive(Debug, Clone)]
+pub struct PaperExecutor {
+    name: String,
+    pnl: Option<DailyPnl>,
+}
+
+impl PaperExecutor {
+    pub fn new(pool: String) -> Self {
+        Self { name: format!("paper_executor_{}", pool), pnl: None }
+    }
+
+    /// Accounts both legs in the daily profit and loss, as if filled at the
+    /// quoted prices.
+    pub fn with_pnl(mut self, pnl: DailyPnl) -> Self {
+        self.pnl = Some(pnl);
+        self
+    }
+
+    fn execute(&self, opportunity: &ArbitrageOpportunity) {
+        let Some(order) = CexOrder::from_opportunity(opportunity) else {
+            warn!(symbol = %opportunity.symbol, "no trade size configured or no Coinbase product, opportunity not executed");
+            return;
+        };
+        info!(
+            client_order_id = order.client_order_id,
+            product_id = %order.product_id,
+            side = ?order.side,
+            size = %order.size,
+            limit_price = %order.limit_price,
+            "paper cex order placed"
+        );
+        if let Some(pnl) = &self.pnl {
+            let symbol = opportunity.symbol.to_string();
+            let size = match opportunity.direction {
+                ArbitrageDirection::BuyDexSellCex => order.size,
+                ArbitrageDirection::BuyCexSellDex => -order.size,
+            };
+            pnl.record_fill(&symbol, size, opportunity.dex_price);
+            pnl.record_fill(&symbol, -size, opportunity.cex_price);
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Executor<InternalAction> for PaperExecutor {
+    fn id(&self) -> &str { &self.name }
+
+    async fn execute_actions(&mut self, actions: Vec<InternalAction>) -> AppResult<()> {
+        for action in actions {
+            match action {
+                InternalAction::Opportunity(opportunity) => self.execute(&opportunity),
+                InternalAction::Suppressed(_) | InternalAction::Halt { .. } => {},
+            }
+        }
+        Ok(())
+    }
+}
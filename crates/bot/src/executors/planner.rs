@@ -0,0 +1,930 @@
+//! Two-leg execution of arbitrage opportunities.
+//!
+//! Executing the legs of an opportunity independently leaves a one-sided
+//! position whenever one of them fails. The [`ExecutionPlanner`] instead runs
+//! both legs of every opportunity according to an [`ExecutionPlan`]:
+//!
+//! - The legs run in the configured [`LegOrdering`], the second leg trading the
+//!   size the first one filled.
+//! - Every leg is bounded by a timeout and preceded by the [`RiskCheck`]s of
+//!   the planner, a refused leg aborts the plan.
+//! - A leg timing out may still be live, so it is settled: stopped and its
+//!   final fill read, before deciding what to unwind.
+//! - Once a leg fails, whatever the legs filled is unwound, i.e. traded back.
+//!   Likewise the excess of a leg filling more than the other. If the final
+//!   state of a leg cannot be read, nothing is unwound and the positions are
+//!   left to the reconciliation.
+//! - An opportunity whose quotes are older than the freshness budget, or which
+//!   the latest prices no longer offer, expires without trading.
+//!
+//! Every step is reported as an [`ExecutionEvent`] carrying the correlation id
+//! of the opportunity, so the audit log captures the whole lifecycle.
+
+use std::{sync::Arc, time::Duration};
+
+use futures::future::join_all;
+use rust_decimal::Decimal;
+use sikkara_core::{
+    current_correlation_id,
+    metrics::{Metric, Subsystem, SymbolLabels},
+    AppResult, Clock, Executor, SystemClock,
+};
+use tokio::sync::mpsc;
+use tracing::{error, info, warn};
+
+use crate::engine::{
+    ArbitrageDirection, ArbitrageOpportunity, ExecutionEvent, ExecutionStatus, InternalAction, Leg,
+    LegIntent, LegOrdering, PriceHistory, PriceHistoryReader, PriceSource,
+};
+
+/// Counter of the opportunities which expired instead of being executed.
+pub const EXPIRED_OPPORTUNITIES_METRIC: Metric<SymbolLabels> =
+    Metric::new(Subsystem::Executor, "expired_opportunities_total");
+
+/// Fill of a leg.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LegFill {
+    pub leg: Leg,
+    /// Filled size in the base asset
+    pub size: Decimal,
+    /// Order id or transaction hash of the fill
+    pub reference: String,
+}
+
+/// Executes one leg of opportunities on behalf of the planner.
+#[async_trait::async_trait]
+pub trait LegExecutor: Send + Sync {
+    /// Trades the leg of an opportunity for its recommended size, waiting for
+    /// it to be filled. Unwinding trades the opposite side.
+    async fn execute_leg(
+        &self,
+        opportunity: &ArbitrageOpportunity,
+        intent: LegIntent,
+    ) -> AppResult<LegFill>;
+
+    /// Stops the leg of an opportunity whose execution timed out from filling
+    /// any further and returns what it filled. Fails if its final state
+    /// cannot be read.
+    async fn settle_leg(
+        &self,
+        opportunity: &ArbitrageOpportunity,
+        intent: LegIntent,
+    ) -> AppResult<LegFill>;
+}
+
+/// Pre-trade check consulted before every leg of a plan. Unwinds only reduce
+/// the exposure and are never checked.
+pub trait RiskCheck: Send + Sync {
+    /// Returns why the leg of an opportunity must not be executed, none if it
+    /// may.
+    fn check(&self, opportunity: &ArbitrageOpportunity, leg: Leg) -> Option<String>;
+}
+
+/// How the legs of an opportunity are executed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExecutionPlan {
+    pub opportunity: ArbitrageOpportunity,
+    pub ordering: LegOrdering,
+    /// Stages run one after the other, the legs of a stage concurrently
+    pub stages: Vec<Vec<Leg>>,
+    pub cex_timeout: Duration,
+    pub dex_timeout: Duration,
+}
+
+impl ExecutionPlan {
+    /// Returns the time a leg is given to fill.
+    pub fn timeout(&self, leg: Leg) -> Duration {
+        match leg {
+            Leg::Cex => self.cex_timeout,
+            Leg::Dex => self.dex_timeout,
+        }
+    }
+}
+
+/// Why a leg failed.
+struct LegFailure {
+    reason: String,
+    /// What the leg filled before it was stopped, unwound with the other
+    /// fills
+    fill: Option<LegFill>,
+    /// Whether the final state of the leg is known
+    settled: bool,
+}
+
+/// Executor running both legs of opportunities, see the module documentation.
+///
+/// Plans run one at a time, so the legs of two opportunities never interleave.
+pub struct ExecutionPlanner {
+    name: String,
+    cex: Arc<dyn LegExecutor>,
+    dex: Arc<dyn LegExecutor>,
+    ordering: LegOrdering,
+    cex_timeout: Duration,
+    dex_timeout: Duration,
+    risk_checks: Vec<Arc<dyn RiskCheck>>,
+    clock: Arc<dyn Clock>,
+    /// Age the oldest quote of an opportunity may reach, unlimited if unset
+    max_opportunity_age: Option<Duration>,
+    /// Latest prices opportunities are re-validated against, with the spread
+    /// in basis points they must still offer
+    revalidation: Option<(PriceHistoryReader, Decimal)>,
+    events: mpsc::UnboundedSender<ExecutionEvent>,
+}
+
+impl ExecutionPlanner {
+    /// Creates the planner of a pool, reporting the steps of its plans through
+    /// `events`.
+    pub fn new(
+        pool: String,
+        cex: Arc<dyn LegExecutor>,
+        dex: Arc<dyn LegExecutor>,
+        events: mpsc::UnboundedSender<ExecutionEvent>,
+    ) -> Self {
+        Self {
+            name: format!("execution_planner_{}", pool),
+            cex,
+            dex,
+            ordering: LegOrdering::default(),
+            cex_timeout: Duration::from_secs(10),
+            dex_timeout: Duration::from_secs(90),
+            risk_checks: Vec::new(),
+            clock: Arc::new(SystemClock),
+            max_opportunity_age: None,
+            revalidation: None,
+            events,
+        }
+    }
+
+    pub fn with_ordering(mut self, ordering: LegOrdering) -> Self {
+        self.ordering = ordering;
+        self
+    }
+
+    pub fn with_leg_timeouts(mut self, cex_timeout: Duration, dex_timeout: Duration) -> Self {
+        self.cex_timeout = cex_timeout;
+        self.dex_timeout = dex_timeout;
+        self
+    }
+
+    pub fn with_risk_check(mut self, risk_check: Arc<dyn RiskCheck>) -> Self {
+        self.risk_checks.push(risk_check);
+        self
+    }
+
+    /// Uses the given clock to measure the age of opportunities.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Expires opportunities whose oldest quote is older than `max_age` when
+    /// their plan starts.
+    pub fn with_max_opportunity_age(mut self, max_age: Duration) -> Self {
+        self.max_opportunity_age = Some(max_age);
+        self
+    }
+
+    /// Expires opportunities for which the latest prices of `history` no
+    /// longer offer a spread of at least `min_spread_bps` in their direction.
+    pub fn with_revalidation(
+        mut self,
+        history: PriceHistoryReader,
+        min_spread_bps: Decimal,
+    ) -> Self {
+        self.revalidation = Some((history, min_spread_bps));
+        self
+    }
+
+    /// Returns the plan of an opportunity, none if no size is recommended.
+    pub fn plan(&self, opportunity: &ArbitrageOpportunity) -> Option<ExecutionPlan> {
+        if opportunity.recommended_size <= Decimal::ZERO {
+            return None;
+        }
+        let stages = match self.ordering {
+            LegOrdering::DexFirst => vec![vec![Leg::Dex], vec![Leg::Cex]],
+            LegOrdering::CexFirst => vec![vec![Leg::Cex], vec![Leg::Dex]],
+            LegOrdering::Simultaneous => vec![vec![Leg::Cex, Leg::Dex]],
+        };
+        Some(ExecutionPlan {
+            opportunity: opportunity.clone(),
+            ordering: self.ordering,
+            stages,
+            cex_timeout: self.cex_timeout,
+            dex_timeout: self.dex_timeout,
+        })
+    }
+
+    /// Runs a plan, returning the size both legs filled.
+    pub async fn run(&self, plan: &ExecutionPlan) -> Decimal {
+        let report = Reporter { correlation_id: current_correlation_id(), events: &self.events };
+        let opportunity = &plan.opportunity;
+        if let Some(status) = self.expired(opportunity) {
+            warn!(symbol = %opportunity.symbol, ?status, "opportunity expired, not executed");
+            EXPIRED_OPPORTUNITIES_METRIC
+                .counter(SymbolLabels { symbol: opportunity.symbol.to_string() })
+                .increment(1);
+            report.send(opportunity, status);
+            return Decimal::ZERO;
+        }
+        report.send(opportunity, ExecutionStatus::Planned { ordering: plan.ordering });
+
+        let mut fills = Vec::new();
+        let mut size = opportunity.recommended_size;
+        for stage in &plan.stages {
+            let sized = ArbitrageOpportunity { recommended_size: size, ..opportunity.clone() };
+            for leg in stage {
+                if let Some(reason) = self.blocked(&sized, *leg) {
+                    report.send(
+                        opportunity,
+                        ExecutionStatus::Blocked { leg: *leg, reason: reason.clone() },
+                    );
+                    self.abort(plan, &report, &fills, format!("{} leg blocked: {}", leg, reason))
+                        .await;
+                    return Decimal::ZERO;
+                }
+            }
+
+            let results = join_all(
+                stage
+                    .iter()
+                    .map(|leg| self.run_leg(plan, &report, *leg, LegIntent::Open, &sized)),
+            )
+            .await;
+            let mut failure = None;
+            let mut settled = true;
+            for result in results {
+                match result {
+                    Ok(fill) => fills.push(fill),
+                    Err(leg_failure) => {
+                        fills.extend(leg_failure.fill);
+                        settled &= leg_failure.settled;
+                        failure = Some(leg_failure.reason);
+                    },
+                }
+            }
+            match failure {
+                Some(reason) if settled => {
+                    self.abort(plan, &report, &fills, reason).await;
+                    return Decimal::ZERO;
+                },
+                Some(reason) => {
+                    // Unwinding could open the very position the unsettled
+                    // leg may have hedged
+                    error!(
+                        symbol = %opportunity.symbol,
+                        %reason,
+                        "leg left unsettled, positions left to the reconciliation"
+                    );
+                    report.send(
+                        opportunity,
+                        ExecutionStatus::Aborted { reason: format!("{}, nothing unwound", reason) },
+                    );
+                    return Decimal::ZERO;
+                },
+                None => {},
+            }
+
+            // Later legs only trade what was filled so far
+            size = fills.iter().map(|fill| fill.size).min().unwrap_or_default();
+            if size <= Decimal::ZERO {
+                self.abort(plan, &report, &fills, "nothing filled".to_string())
+                    .await;
+                return Decimal::ZERO;
+            }
+        }
+
+        // Trade back the excess of a leg filling more than the other
+        for fill in &fills {
+            if fill.size > size {
+                let excess = LegFill { size: fill.size - size, ..fill.clone() };
+                self.unwind(plan, &report, &excess).await;
+            }
+        }
+        info!(symbol = %opportunity.symbol, %size, "opportunity executed");
+        report.send(opportunity, ExecutionStatus::Completed { size });
+        size
+    }
+
+    fn executor(&self, leg: Leg) -> &Arc<dyn LegExecutor> {
+        match leg {
+            Leg::Cex => &self.cex,
+            Leg::Dex => &self.dex,
+        }
+    }
+
+    /// Returns the expired status of an opportunity too old or no longer
+    /// offered by the latest prices, none if it may be executed.
+    fn expired(&self, opportunity: &ArbitrageOpportunity) -> Option<ExecutionStatus> {
+        let age = opportunity.quote_age(self.clock.now());
+        let age_ms = age.as_millis() as u64;
+        if let Some(max_age) = self.max_opportunity_age {
+            if age > max_age {
+                let reason =
+                    format!("quotes are {}ms old, budget is {}ms", age_ms, max_age.as_millis());
+                return Some(ExecutionStatus::Expired { age_ms, reason });
+            }
+        }
+
+        let (history, min_spread_bps) = self.revalidation.as_ref()?;
+        let reason = match latest_spread_bps(&history.read(), opportunity.direction) {
+            None => "no latest prices to re-validate against".to_string(),
+            Some(spread_bps) if spread_bps < *min_spread_bps => format!(
+                "latest prices offer {} bps, below {} bps",
+                spread_bps.round_dp(2),
+                min_spread_bps
+            ),
+            Some(_) => return None,
+        };
+        Some(ExecutionStatus::Expired { age_ms, reason })
+    }
+
+    fn blocked(&self, opportunity: &ArbitrageOpportunity, leg: Leg) -> Option<String> {
+        self.risk_checks
+            .iter()
+            .find_map(|risk_check| risk_check.check(opportunity, leg))
+    }
+
+    /// Runs a leg within its timeout, returning why it failed otherwise.
+    async fn run_leg(
+        &self,
+        plan: &ExecutionPlan,
+        report: &Reporter<'_>,
+        leg: Leg,
+        intent: LegIntent,
+        opportunity: &ArbitrageOpportunity,
+    ) -> Result<LegFill, LegFailure> {
+        report.send(opportunity, ExecutionStatus::LegStarted { leg, intent });
+        let timeout = plan.timeout(leg);
+        let result =
+            tokio::time::timeout(timeout, self.executor(leg).execute_leg(opportunity, intent))
+                .await;
+        let failure = match result {
+            Ok(Ok(fill)) => {
+                report.send(
+                    opportunity,
+                    ExecutionStatus::LegFilled {
+                        leg,
+                        intent,
+                        size: fill.size,
+                        reference: fill.reference.clone(),
+                    },
+                );
+                return Ok(fill);
+            },
+            Ok(Err(e)) => LegFailure {
+                reason: format!("{} leg failed: {}", leg, e),
+                fill: None,
+                settled: true,
+            },
+            Err(_) => self.settle(leg, intent, opportunity, timeout).await,
+        };
+        let reason = &failure.reason;
+        warn!(symbol = %opportunity.symbol, %leg, ?intent, %reason, "leg failed");
+        report
+            .send(opportunity, ExecutionStatus::LegFailed { leg, intent, reason: reason.clone() });
+        Err(failure)
+    }
+
+    /// Settles a leg which timed out, given as long again to do so.
+    async fn settle(
+        &self,
+        leg: Leg,
+        intent: LegIntent,
+        opportunity: &ArbitrageOpportunity,
+        timeout: Duration,
+    ) -> LegFailure {
+        warn!(symbol = %opportunity.symbol, %leg, ?intent, "leg timed out, settling it");
+        let settled =
+            tokio::time::timeout(timeout, self.executor(leg).settle_leg(opportunity, intent)).await;
+        let error = match settled {
+            Ok(Ok(fill)) => {
+                return LegFailure {
+                    reason: format!(
+                        "{} leg timed out after {:?}, having filled {}",
+                        leg, timeout, fill.size
+                    ),
+                    fill: Some(fill),
+                    settled: true,
+                };
+            },
+            Ok(Err(e)) => e.to_string(),
+            Err(_) => format!("not settled within {:?}", timeout),
+        };
+        LegFailure {
+            reason: format!(
+                "{} leg timed out after {:?}, its final state is unknown: {}",
+                leg, timeout, error
+            ),
+            fill: None,
+            settled: false,
+        }
+    }
+
+    /// Unwinds everything filled so far and reports the plan as aborted.
+    async fn abort(
+        &self,
+        plan: &ExecutionPlan,
+        report: &Reporter<'_>,
+        fills: &[LegFill],
+        reason: String,
+    ) {
+        for fill in fills.iter().filter(|fill| fill.size > Decimal::ZERO) {
+            self.unwind(plan, report, fill).await;
+        }
+        warn!(symbol = %plan.opportunity.symbol, %reason, "opportunity execution aborted");
+        report.send(&plan.opportunity, ExecutionStatus::Aborted { reason });
+    }
+
+    async fn unwind(&self, plan: &ExecutionPlan, report: &Reporter<'_>, fill: &LegFill) {
+        let opportunity =
+            ArbitrageOpportunity { recommended_size: fill.size, ..plan.opportunity.clone() };
+        if let Err(failure) = self
+            .run_leg(plan, report, fill.leg, LegIntent::Unwind, &opportunity)
+            .await
+        {
+            error!(
+                symbol = %opportunity.symbol,
+                leg = %fill.leg,
+                size = %fill.size,
+                unwound = ?failure.fill.map(|unwound| unwound.size),
+                "failed to unwind leg, position left open"
+            );
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Executor<InternalAction> for ExecutionPlanner {
+    fn id(&self) -> &str { &self.name }
+
+    async fn execute_actions(&mut self, actions: Vec<InternalAction>) -> AppResult<()> {
+        for action in actions {
+            match action {
+                InternalAction::Opportunity(opportunity) => match self.plan(&opportunity) {
+                    Some(plan) => {
+                        self.run(&plan).await;
+                    },
+                    None => {
+                        warn!(symbol = %opportunity.symbol, "no trade size configured, opportunity not executed")
+                    },
+                },
+                InternalAction::Suppressed(_) | InternalAction::Halt { .. } => {},
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Returns the spread in basis points of the CEX price the latest prices offer
+/// in the given direction, negative if they are crossed the other way.
+fn latest_spread_bps(history: &PriceHistory, direction: ArbitrageDirection) -> Option<Decimal> {
+    let cex = history.latest(PriceSource::Cex)?.price;
+    let dex = history.latest(PriceSource::Dex)?.price;
+    if cex <= Decimal::ZERO {
+        return None;
+    }
+    let spread = match direction {
+        ArbitrageDirection::BuyDexSellCex => cex - dex,
+        ArbitrageDirection::BuyCexSellDex => dex - cex,
+    };
+    Some(spread / cex * Decimal::from(10_000))
+}
+
+/// Reports the steps of the plan of a single opportunity.
+struct Reporter<'a> {
+    correlation_id: Option<u64>,
+    events: &'a mpsc::UnboundedSender<ExecutionEvent>,
+}
+
+impl Reporter<'_> {
+    fn send(&self, opportunity: &ArbitrageOpportunity, status: ExecutionStatus) {
+        let event = ExecutionEvent {
+            correlation_id: self.correlation_id,
+            symbol: opportunity.symbol.clone(),
+            status,
+            timestamp: jiff::Timestamp::now(),
+        };
+        if self.events.send(event).is_err() {
+            warn!(symbol = %opportunity.symbol, "execution events are no longer collected");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::VecDeque, sync::Mutex};
+
+    use rust_decimal_macros::dec;
+    use sikkara_core::{AppError, MockClock};
+
+    use super::*;
+    use crate::engine::{
+        Exchange, InternalEvent, PoolPriceUpdate, PoolSymbol, PriceHistoryHandle, Ticker,
+    };
+
+    /// Leg executor replaying scripted outcomes, recording the legs traded.
+    struct ScriptedLeg {
+        leg: Leg,
+        outcomes: Mutex<VecDeque<Result<Decimal, String>>>,
+        /// Outcome of settling the leg, which never completes opening when
+        /// set
+        settlement: Option<Result<Decimal, String>>,
+        trades: Arc<Mutex<Vec<(Leg, LegIntent, ArbitrageDirection, Decimal)>>>,
+    }
+
+    #[async_trait::async_trait]
+    impl LegExecutor for ScriptedLeg {
+        async fn execute_leg(
+            &self,
+            opportunity: &ArbitrageOpportunity,
+            intent: LegIntent,
+        ) -> AppResult<LegFill> {
+            self.trades.lock().unwrap().push((
+                self.leg,
+                intent,
+                opportunity.direction,
+                opportunity.recommended_size,
+            ));
+            if self.settlement.is_some() && intent == LegIntent::Open {
+                std::future::pending::<()>().await;
+            }
+            let outcome = self
+                .outcomes
+                .lock()
+                .unwrap()
+                .pop_front()
+                .expect("unexpected leg");
+            let size = outcome.map_err(AppError::TransactionError)?;
+            Ok(LegFill { leg: self.leg, size, reference: format!("{}-fill", self.leg) })
+        }
+
+        async fn settle_leg(
+            &self,
+            _opportunity: &ArbitrageOpportunity,
+            _intent: LegIntent,
+        ) -> AppResult<LegFill> {
+            let outcome = self.settlement.clone().expect("unexpected settlement");
+            let size = outcome.map_err(AppError::TransactionError)?;
+            Ok(LegFill { leg: self.leg, size, reference: format!("{}-settled", self.leg) })
+        }
+    }
+
+    struct Harness {
+        planner: ExecutionPlanner,
+        trades: Arc<Mutex<Vec<(Leg, LegIntent, ArbitrageDirection, Decimal)>>>,
+        events: mpsc::UnboundedReceiver<ExecutionEvent>,
+    }
+
+    impl Harness {
+        fn new(
+            ordering: LegOrdering,
+            cex: Vec<Result<Decimal, String>>,
+            dex: Vec<Result<Decimal, String>>,
+        ) -> Self {
+            Self::with_timed_out_leg(ordering, cex, dex, None)
+        }
+
+        /// Harness whose given leg never completes opening, settling with the
+        /// given outcome once timed out after 50ms.
+        fn with_timed_out_leg(
+            ordering: LegOrdering,
+            cex: Vec<Result<Decimal, String>>,
+            dex: Vec<Result<Decimal, String>>,
+            timed_out: Option<(Leg, Result<Decimal, String>)>,
+        ) -> Self {
+            let trades = Arc::new(Mutex::new(Vec::new()));
+            let scripted = |leg, outcomes: Vec<_>| {
+                let settlement = timed_out
+                    .clone()
+                    .filter(|(timed_out, _)| *timed_out == leg)
+                    .map(|(_, settlement)| settlement);
+                Arc::new(ScriptedLeg {
+                    leg,
+                    outcomes: Mutex::new(outcomes.into()),
+                    settlement,
+                    trades: trades.clone(),
+                })
+            };
+            let (sender, events) = mpsc::unbounded_channel();
+            let planner = ExecutionPlanner::new(
+                "ETH-USDC".to_string(),
+                scripted(Leg::Cex, cex),
+                scripted(Leg::Dex, dex),
+                sender,
+            )
+            .with_ordering(ordering)
+            .with_leg_timeouts(Duration::from_millis(50), Duration::from_millis(50));
+            Self { planner, trades, events }
+        }
+
+        fn trades(&self) -> Vec<(Leg, LegIntent, ArbitrageDirection, Decimal)> {
+            self.trades.lock().unwrap().clone()
+        }
+
+        fn statuses(&mut self) -> Vec<ExecutionStatus> {
+            let mut statuses = Vec::new();
+            while let Ok(event) = self.events.try_recv() {
+                statuses.push(event.status);
+            }
+            statuses
+        }
+    }
+
+    fn opportunity() -> ArbitrageOpportunity {
+        ArbitrageOpportunity {
+            symbol: PoolSymbol::ETH_USDC,
+            direction: ArbitrageDirection::BuyDexSellCex,
+            cex_price: dec!(2520.5),
+            dex_price: dec!(2500),
+            net_bps: dec!(81.33),
+            recommended_size: dec!(0.5),
+            detected_at: "2025-02-12T21:12:33.250Z".parse().unwrap(),
+            cex_quote_at: "2025-02-12T21:12:33.250Z".parse().unwrap(),
+            dex_quote_at: "2025-02-12T21:12:33.250Z".parse().unwrap(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_both_legs_are_executed_in_order() {
+        let mut harness =
+            Harness::new(LegOrdering::DexFirst, vec![Ok(dec!(0.5))], vec![Ok(dec!(0.5))]);
+        let plan = harness.planner.plan(&opportunity()).unwrap();
+        assert_eq!(plan.stages, vec![vec![Leg::Dex], vec![Leg::Cex]]);
+
+        assert_eq!(harness.planner.run(&plan).await, dec!(0.5));
+        assert_eq!(
+            harness.trades(),
+            vec![
+                (Leg::Dex, LegIntent::Open, ArbitrageDirection::BuyDexSellCex, dec!(0.5)),
+                (Leg::Cex, LegIntent::Open, ArbitrageDirection::BuyDexSellCex, dec!(0.5)),
+            ]
+        );
+        let statuses = harness.statuses();
+        assert_eq!(
+            statuses.first(),
+            Some(&ExecutionStatus::Planned { ordering: LegOrdering::DexFirst })
+        );
+        assert_eq!(statuses.last(), Some(&ExecutionStatus::Completed { size: dec!(0.5) }));
+    }
+
+    #[tokio::test]
+    async fn test_first_leg_failure_aborts_without_unwinding() {
+        let mut harness = Harness::new(
+            LegOrdering::CexFirst,
+            vec![Err("insufficient funds".to_string())],
+            vec![],
+        );
+        let plan = harness.planner.plan(&opportunity()).unwrap();
+
+        assert_eq!(harness.planner.run(&plan).await, Decimal::ZERO);
+        // The DEX leg never ran
+        assert_eq!(harness.trades().len(), 1);
+        let statuses = harness.statuses();
+        assert!(statuses.contains(&ExecutionStatus::LegFailed {
+            leg: Leg::Cex,
+            intent: LegIntent::Open,
+            reason: "cex leg failed: Transaction error: insufficient funds".to_string()
+        }));
+        assert_eq!(
+            statuses.last(),
+            Some(&ExecutionStatus::Aborted {
+                reason: "cex leg failed: Transaction error: insufficient funds".to_string()
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_second_leg_failure_unwinds_the_first() {
+        let mut harness = Harness::new(
+            LegOrdering::CexFirst,
+            vec![Ok(dec!(0.4)), Ok(dec!(0.4))],
+            vec![Err("execution reverted".to_string())],
+        );
+        let plan = harness.planner.plan(&opportunity()).unwrap();
+
+        assert_eq!(harness.planner.run(&plan).await, Decimal::ZERO);
+        assert_eq!(
+            harness.trades(),
+            vec![
+                (Leg::Cex, LegIntent::Open, ArbitrageDirection::BuyDexSellCex, dec!(0.5)),
+                // The DEX leg trades what the CEX leg filled
+                (Leg::Dex, LegIntent::Open, ArbitrageDirection::BuyDexSellCex, dec!(0.4)),
+                (Leg::Cex, LegIntent::Unwind, ArbitrageDirection::BuyDexSellCex, dec!(0.4)),
+            ]
+        );
+        let statuses = harness.statuses();
+        assert!(statuses.contains(&ExecutionStatus::LegFilled {
+            leg: Leg::Cex,
+            intent: LegIntent::Unwind,
+            size: dec!(0.4),
+            reference: "cex-fill".to_string()
+        }));
+        assert!(matches!(statuses.last(), Some(ExecutionStatus::Aborted { .. })));
+    }
+
+    #[tokio::test]
+    async fn test_partial_fill_of_simultaneous_legs_unwinds_the_excess() {
+        let mut harness = Harness::new(
+            LegOrdering::Simultaneous,
+            vec![Ok(dec!(0.3))],
+            vec![Ok(dec!(0.5)), Ok(dec!(0.2))],
+        );
+        let plan = harness.planner.plan(&opportunity()).unwrap();
+
+        assert_eq!(harness.planner.run(&plan).await, dec!(0.3));
+        assert!(harness.trades().contains(&(
+            Leg::Dex,
+            LegIntent::Unwind,
+            ArbitrageDirection::BuyDexSellCex,
+            dec!(0.2)
+        )));
+    }
+
+    #[tokio::test]
+    async fn test_timed_out_leg_is_settled_before_unwinding() {
+        let mut harness = Harness::with_timed_out_leg(
+            LegOrdering::Simultaneous,
+            vec![Ok(dec!(0.2))],
+            vec![Ok(dec!(0.5)), Ok(dec!(0.5))],
+            Some((Leg::Cex, Ok(dec!(0.2)))),
+        );
+        let plan = harness.planner.plan(&opportunity()).unwrap();
+
+        assert_eq!(harness.planner.run(&plan).await, Decimal::ZERO);
+        // The CEX order filled 0.2 before it was cancelled, both legs are
+        // unwound
+        let trades = harness.trades();
+        assert_eq!(trades.len(), 4);
+        assert!(trades.contains(&(
+            Leg::Cex,
+            LegIntent::Unwind,
+            ArbitrageDirection::BuyDexSellCex,
+            dec!(0.2)
+        )));
+        assert!(trades.contains(&(
+            Leg::Dex,
+            LegIntent::Unwind,
+            ArbitrageDirection::BuyDexSellCex,
+            dec!(0.5)
+        )));
+        assert!(harness.statuses().contains(&ExecutionStatus::LegFailed {
+            leg: Leg::Cex,
+            intent: LegIntent::Open,
+            reason: "cex leg timed out after 50ms, having filled 0.2".to_string()
+        }));
+    }
+
+    #[tokio::test]
+    async fn test_unsettled_leg_leaves_the_others_open() {
+        let mut harness = Harness::with_timed_out_leg(
+            LegOrdering::DexFirst,
+            vec![],
+            vec![],
+            Some((Leg::Dex, Err("swap still pending".to_string()))),
+        );
+        let plan = harness.planner.plan(&opportunity()).unwrap();
+
+        assert_eq!(harness.planner.run(&plan).await, Decimal::ZERO);
+        // Neither the CEX leg nor any unwind ran
+        assert_eq!(
+            harness.trades(),
+            vec![(Leg::Dex, LegIntent::Open, ArbitrageDirection::BuyDexSellCex, dec!(0.5))]
+        );
+        assert_eq!(
+            harness.statuses().last(),
+            Some(&ExecutionStatus::Aborted {
+                reason: "dex leg timed out after 50ms, its final state is unknown: Transaction \
+                         error: swap still pending, nothing unwound"
+                    .to_string()
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_blocked_leg_aborts_the_plan() {
+        struct Halted;
+
+        impl RiskCheck for Halted {
+            fn check(&self, _opportunity: &ArbitrageOpportunity, leg: Leg) -> Option<String> {
+                (leg == Leg::Cex).then(|| "halted".to_string())
+            }
+        }
+
+        let mut harness =
+            Harness::new(LegOrdering::DexFirst, vec![], vec![Ok(dec!(0.5)), Ok(dec!(0.5))]);
+        harness.planner = harness.planner.with_risk_check(Arc::new(Halted));
+        let plan = harness.planner.plan(&opportunity()).unwrap();
+
+        assert_eq!(harness.planner.run(&plan).await, Decimal::ZERO);
+        assert_eq!(
+            harness.trades(),
+            vec![
+                (Leg::Dex, LegIntent::Open, ArbitrageDirection::BuyDexSellCex, dec!(0.5)),
+                (Leg::Dex, LegIntent::Unwind, ArbitrageDirection::BuyDexSellCex, dec!(0.5)),
+            ]
+        );
+        assert!(harness
+            .statuses()
+            .contains(&ExecutionStatus::Blocked { leg: Leg::Cex, reason: "halted".to_string() }));
+    }
+
+    /// Planner of the harness expiring opportunities after 500ms, with the
+    /// clock at the given time.
+    fn with_freshness_budget(harness: Harness, now: &str) -> Harness {
+        let clock = MockClock::new(now.parse().unwrap());
+        let planner = harness
+            .planner
+            .with_clock(Arc::new(clock))
+            .with_max_opportunity_age(Duration::from_millis(500));
+        Harness { planner, ..harness }
+    }
+
+    #[tokio::test]
+    async fn test_fresh_opportunity_is_executed() {
+        let harness = Harness::new(LegOrdering::DexFirst, vec![Ok(dec!(0.5))], vec![Ok(dec!(0.5))]);
+        let mut harness = with_freshness_budget(harness, "2025-02-12T21:12:33.400Z");
+        let plan = harness.planner.plan(&opportunity()).unwrap();
+
+        assert_eq!(harness.planner.run(&plan).await, dec!(0.5));
+        assert_eq!(
+            harness.statuses().last(),
+            Some(&ExecutionStatus::Completed { size: dec!(0.5) })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_opportunity_at_the_budget_is_executed() {
+        let harness = Harness::new(LegOrdering::DexFirst, vec![Ok(dec!(0.5))], vec![Ok(dec!(0.5))]);
+        let mut harness = with_freshness_budget(harness, "2025-02-12T21:12:33.750Z");
+        let plan = harness.planner.plan(&opportunity()).unwrap();
+
+        assert_eq!(harness.planner.run(&plan).await, dec!(0.5));
+        assert!(!harness
+            .statuses()
+            .iter()
+            .any(|status| matches!(status, ExecutionStatus::Expired { .. })));
+    }
+
+    #[tokio::test]
+    async fn test_expired_opportunity_is_not_executed() {
+        let harness = Harness::new(LegOrdering::DexFirst, vec![], vec![]);
+        let mut harness = with_freshness_budget(harness, "2025-02-12T21:12:33.751Z");
+        // The DEX quote is older than the CEX quote the opportunity was
+        // detected on
+        let opportunity = ArbitrageOpportunity {
+            cex_quote_at: "2025-02-12T21:12:33.600Z".parse().unwrap(),
+            ..opportunity()
+        };
+        let plan = harness.planner.plan(&opportunity).unwrap();
+
+        assert_eq!(harness.planner.run(&plan).await, Decimal::ZERO);
+        assert!(harness.trades().is_empty());
+        assert_eq!(
+            harness.statuses(),
+            vec![ExecutionStatus::Expired {
+                age_ms: 501,
+                reason: "quotes are 501ms old, budget is 500ms".to_string()
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_opportunity_no_longer_offered_by_latest_prices_expires() {
+        let history = PriceHistoryHandle::new(16);
+        let timestamp: jiff::Timestamp = "2025-02-12T21:12:33.300Z".parse().unwrap();
+        history.record(&InternalEvent::TickerUpdate(Ticker {
+            exchage: Exchange::Coinbase,
+            symbol: PoolSymbol::ETH_USDC,
+            price: dec!(2501),
+            timestamp,
+            bid: None,
+            ask: None,
+            bid_size: None,
+            ask_size: None,
+            volume_24h: None,
+        }));
+        history.record(&InternalEvent::PoolPriceUpdate(PoolPriceUpdate {
+            symbol: PoolSymbol::ETH_USDC,
+            price: dec!(2500),
+            timestamp,
+            block_number: None,
+            state: None,
+            effective_buy_price: None,
+            effective_sell_price: None,
+        }));
+
+        let mut harness = Harness::new(LegOrdering::DexFirst, vec![], vec![]);
+        harness.planner = harness
+            .planner
+            .with_revalidation(history.reader(), dec!(10));
+        let plan = harness.planner.plan(&opportunity()).unwrap();
+
+        // The CEX price came down, the spread is 4 bps instead of 81
+        assert_eq!(harness.planner.run(&plan).await, Decimal::ZERO);
+        assert!(harness.trades().is_empty());
+        assert!(matches!(
+            harness.statuses().as_slice(),
+            [ExecutionStatus::Expired { reason, .. }] if reason == "latest prices offer 4.00 bps, below 10 bps"
+        ));
+    }
+}
@@ -0,0 +1,166 @@
+use std::collections::BTreeMap;
+
+use rust_decimal::Decimal;
+
+use crate::engine::{FeedState, InternalEvent};
+
+/// Latest observed price of one side of a market.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PriceObservation {
+    pub price: Decimal,
+    pub observed_at: jiff::Timestamp,
+}
+
+/// Health and latest prices of a single symbol.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SymbolStatus {
+    pub cex: Option<PriceObservation>,
+    pub dex: Option<PriceObservation>,
+    /// Feeds currently down, with the reason they went down
+    pub down_feeds: BTreeMap<String, String>,
+}
+
+/// Feed health and last prices per symbol, as observed by the alert executors
+/// of all pools.
+#[derive(Debug, Clone, Default)]
+pub struct StatusBoard {
+    symbols: BTreeMap<String, SymbolStatus>,
+}
+
+impl StatusBoard {
+    /// Records an internal event.
+    pub fn record(&mut self, event: &InternalEvent) {
+        match event {
+            InternalEvent::TickerUpdate(ticker) => {
+                self.symbol_mut(ticker.symbol.to_string()).cex =
+                    Some(PriceObservation { price: ticker.price, observed_at: ticker.timestamp });
+            },
+            InternalEvent::PoolPriceUpdate(update) => {
+                self.symbol_mut(update.symbol.to_string()).dex =
+                    Some(PriceObservation { price: update.price, observed_at: update.timestamp });
+            },
+            InternalEvent::FeedStatus(status) => {
+                let symbol = self.symbol_mut(status.symbol.to_string());
+                match status.state {
+                    FeedState::Up => {
+                        symbol.down_feeds.remove(&status.feed);
+                    },
+                    FeedState::Down => {
+                        symbol
+                            .down_feeds
+                            .insert(status.feed.clone(), status.reason.clone());
+                    },
+                }
+            },
+            InternalEvent::Execution(_)
+            | InternalEvent::BalanceUpdate(_)
+            | InternalEvent::OrderUpdate(_)
+            | InternalEvent::OwnFill(_) => {},
+        }
+    }
+
+    /// Returns the status of a symbol, if any event was recorded for it.
+    pub fn symbol(&self, symbol: &str) -> Option<&SymbolStatus> { self.symbols.get(symbol) }
+
+    /// Renders a human readable report of all symbols as of `now`.
+    pub fn render(&self, now: jiff::Timestamp) -> String {
+        if self.symbols.is_empty() {
+            return "📊 No market data received yet".to_string();
+        }
+
+        let mut report = "📊 Status".to_string();
+        for (symbol, status) in &self.symbols {
+            let feeds = if status.down_feeds.is_empty() {
+                "✅ feeds up".to_string()
+            } else {
+                let down = status
+                    .down_feeds
+                    .iter()
+                    .map(|(feed, reason)| format!("{} ({})", feed, reason))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                format!("🔴 down: {}", down)
+            };
+            report.push_str(&format!(
+                "\n{}: CEX {}, DEX {}, {}",
+                symbol,
+                Self::render_price(status.cex, now),
+                Self::render_price(status.dex, now),
+                feeds
+            ));
+        }
+        report
+    }
+
+    fn render_price(observation: Option<PriceObservation>, now: jiff::Timestamp) -> String {
+        match observation {
+            Some(observation) => {
+                let age_secs = now.duration_since(observation.observed_at).as_secs().max(0);
+                format!("${:.2} ({}s ago)", observation.price.round_dp(2), age_secs)
+            },
+            None => "n/a".to_string(),
+        }
+    }
+
+    fn symbol_mut(&mut self, symbol: String) -> &mut SymbolStatus {
+        self.symbols.entry(symbol).or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal_macros::dec;
+
+    use super::*;
+    use crate::engine::{Exchange, FeedStatus, PoolPriceUpdate, PoolSymbol, PriceSource, Ticker};
+
+    #[test]
+    fn test_records_prices_and_feed_health() {
+        let mut board = StatusBoard::default();
+        let now: jiff::Timestamp = "2025-02-12T21:12:40Z".parse().unwrap();
+        assert_eq!(board.render(now), "📊 No market data received yet");
+
+        board.record(&InternalEvent::TickerUpdate(Ticker {
+            exchage: Exchange::Coinbase,
+            symbol: PoolSymbol::ETH_USDC,
+            price: dec!(2510.458),
+            timestamp: "2025-02-12T21:12:33Z".parse().unwrap(),
+            bid: None,
+            ask: None,
+            bid_size: None,
+            ask_size: None,
+            volume_24h: None,
+        }));
+        board.record(&InternalEvent::PoolPriceUpdate(PoolPriceUpdate {
+            symbol: PoolSymbol::ETH_USDC,
+            price: dec!(2500),
+            timestamp: now,
+            block_number: None,
+            state: None,
+            effective_buy_price: None,
+            effective_sell_price: None,
+        }));
+        assert_eq!(
+            board.render(now),
+            "📊 Status\nETH-USDC: CEX $2510.46 (7s ago), DEX $2500.00 (0s ago), ✅ feeds up"
+        );
+
+        let status = FeedStatus {
+            feed: "price_feed_collector".to_string(),
+            source: PriceSource::Cex,
+            symbol: PoolSymbol::ETH_USDC,
+            state: FeedState::Down,
+            reason: "price feed stream ended".to_string(),
+            timestamp: now,
+        };
+        board.record(&InternalEvent::FeedStatus(status.clone()));
+        assert_eq!(
+            board.render(now),
+            "📊 Status\nETH-USDC: CEX $2510.46 (7s ago), DEX $2500.00 (0s ago), 🔴 down: \
+             price_feed_collector (price feed stream ended)"
+        );
+
+        board.record(&InternalEvent::FeedStatus(FeedStatus { state: FeedState::Up, ..status }));
+        assert!(board.symbol("ETH-USDC").unwrap().down_feeds.is_empty());
+    }
+}
@@ -0,0 +1,495 @@
+//! Telegram notifications and chat commands.
+//!
+//! The [`TelegramClient`] is attached to the [`AlertDispatcher`] to mirror
+//! alerts into a chat. The [`TelegramCommandHandler`] long polls the Bot API
+//! for messages in that chat and answers the following commands:
+//! - `/status`: feed health and last prices per symbol
+//! - `/mute <duration>`: suppresses alerts, e.g. `/mute 30m`
+//! - `/unmute`: lifts a previous mute
+
+use std::time::Duration;
+
+use serde::Deserialize;
+use serde_json::json;
+use sikkara_core::{AppError, AppResult, BackoffStrategy, ExponentialBackoff};
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, info, warn};
+
+use crate::executors::AlertDispatcher;
+
+/// Minimal client of the Telegram Bot API, bound to a single chat.
+#[derive(Clone)]
+pub struct TelegramClient {
+    client: reqwest::Client,
+    /// `{api_url}/bot{token}`, never logged since it contains the token
+    base_url: String,
+    chat_id: i64,
+}
+
+impl std::fmt::Debug for TelegramClient {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TelegramClient")
+            .field("chat_id", &self.chat_id)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Envelope of every Bot API response.
+#[derive(Debug, Deserialize)]
+struct TelegramResponse<T> {
+    ok: bool,
+    result: Option<T>,
+    description: Option<String>,
+}
+
+/// An incoming update, only messages are of interest.
+#[derive(Debug, Clone, Deserialize)]
+pub struct TelegramUpdate {
+    pub update_id: i64,
+    pub message: Option<TelegramMessage>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct TelegramMessage {
+    pub chat: TelegramChat,
+    pub text: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct TelegramChat {
+    pub id: i64,
+}
+
+impl TelegramClient {
+    pub fn new(api_url: &str, bot_token: &str, chat_id: i64) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            base_url: format!("{}/bot{}", api_url.trim_end_matches('/'), bot_token),
+            chat_id,
+        }
+    }
+
+    /// Returns the chat this client is bound to.
+    pub fn chat_id(&self) -> i64 { self.chat_id }
+
+    /// Sends a plain text message to the chat.
+    pub async fn send_message(&self, text: &str) -> AppResult<()> {
+        let body = json!({ "chat_id": self.chat_id, "text": text });
+        let response = self
+            .client
+            .post(format!("{}/sendMessage", self.base_url))
+            .json(&body)
+            .send()
+            .await;
+        Self::parse::<serde_json::Value>("sendMessage", response).await?;
+        Ok(())
+    }
+
+    /// Long polls for updates following `offset`, waiting at most
+    /// `timeout_secs` for new ones.
+    pub async fn get_updates(
+        &self,
+        offset: Option<i64>,
+        timeout_secs: u64,
+    ) -> AppResult<Vec<TelegramUpdate>> {
+        let mut query = vec![("timeout", timeout_secs.to_string())];
+        if let Some(offset) = offset {
+            query.push(("offset", offset.to_string()));
+        }
+        let response = self
+            .client
+            .get(format!("{}/getUpdates", self.base_url))
+            .query(&query)
+            .send()
+            .await;
+        Self::parse("getUpdates", response).await
+    }
+
+    async fn parse<T: serde::de::DeserializeOwned>(
+        method: &str,
+        response: Result<reqwest::Response, reqwest::Error>,
+    ) -> AppResult<T> {
+        // Strip the URL from transport errors, it contains the bot token
+        let response = response.map_err(|e| {
+            AppError::HttpError(format!("telegram {} failed: {}", method, e.without_url()))
+        })?;
+        let status = response.status();
+        let body: TelegramResponse<T> = response.json().await.map_err(|e| {
+            AppError::HttpError(format!(
+                "telegram {} returned invalid response with status {}: {}",
+                method,
+                status,
+                e.without_url()
+            ))
+        })?;
+        match body {
+            TelegramResponse { ok: true, result: Some(result), .. } => Ok(result),
+            TelegramResponse { description, .. } => Err(AppError::HttpError(format!(
+                "telegram {} rejected with status {}: {}",
+                method,
+                status,
+                description.unwrap_or_default()
+            ))
+            .into()),
+        }
+    }
+}
+
+/// A command sent to the bot.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TelegramCommand {
+    Status,
+    Mute(Duration),
+    Unmute,
+    Unknown,
+}
+
+impl TelegramCommand {
+    /// Parses a message text, returns `None` if it is not a command.
+    pub fn parse(text: &str) -> Option<Self> {
+        let mut parts = text.split_whitespace();
+        let command = parts.next()?.strip_prefix('/')?;
+        // Commands in group chats may be addressed as `/status@MyBot`
+        let command = command.split('@').next().unwrap_or_default();
+        let command = match command {
+            "status" => TelegramCommand::Status,
+            "mute" => match parts.next().and_then(parse_duration) {
+                Some(duration) => TelegramCommand::Mute(duration),
+                None => TelegramCommand::Unknown,
+            },
+            "unmute" => TelegramCommand::Unmute,
+            _ => TelegramCommand::Unknown,
+        };
+        Some(command)
+    }
+}
+
+/// Parses durations such as `90s`, `30m`, `2h` or `1d`.
+fn parse_duration(value: &str) -> Option<Duration> {
+    let split = value.len().checked_sub(1)?;
+    let (amount, unit) = value.split_at(split);
+    let amount: u64 = amount.parse().ok()?;
+    let unit_secs = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        _ => return None,
+    };
+    Some(Duration::from_secs(amount.checked_mul(unit_secs)?))
+}
+
+/// Formats a duration as `1h 30m`, dropping seconds beyond the first minute.
+fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    match (secs / 3600, (secs % 3600) / 60) {
+        (0, 0) => format!("{}s", secs),
+        (0, minutes) => format!("{}m", minutes),
+        (hours, 0) => format!("{}h", hours),
+        (hours, minutes) => format!("{}h {}m", hours, minutes),
+    }
+}
+
+/// Long polls the Bot API and answers commands sent to the configured chat.
+#[derive(Debug)]
+pub struct TelegramCommandHandler {
+    client: TelegramClient,
+    dispatcher: AlertDispatcher,
+    poll_timeout_secs: u64,
+    retry_min_delay_secs: u32,
+    retry_max_delay_secs: u32,
+    offset: Option<i64>,
+}
+
+impl TelegramCommandHandler {
+    pub fn new(
+        client: TelegramClient,
+        dispatcher: AlertDispatcher,
+        poll_timeout_secs: u64,
+        retry_min_delay_secs: u32,
+        retry_max_delay_secs: u32,
+    ) -> Self {
+        Self {
+            client,
+            dispatcher,
+            poll_timeout_secs,
+            retry_min_delay_secs,
+            retry_max_delay_secs,
+            offset: None,
+        }
+    }
+
+    /// Fetches one batch of updates and answers the commands in it.
+    pub async fn poll_once(&mut self) -> AppResult<()> {
+        let updates = self
+            .client
+            .get_updates(self.offset, self.poll_timeout_secs)
+            .await?;
+        for update in updates {
+            // Acknowledge the update even if answering fails, so a poisoned
+            // message is not redelivered forever
+            self.offset = Some(update.update_id + 1);
+            let Some(message) = update.message else { continue };
+            if message.chat.id != self.client.chat_id() {
+                warn!("ignoring telegram message from unknown chat {}", message.chat.id);
+                continue;
+            }
+            let Some(command) = message.text.as_deref().and_then(TelegramCommand::parse) else {
+                continue;
+            };
+            debug!("received telegram command {:?}", command);
+            let reply = self.handle(command);
+            self.client.send_message(&reply).await?;
+        }
+        Ok(())
+    }
+
+    /// Executes a command and returns the reply.
+    fn handle(&self, command: TelegramCommand) -> String {
+        match command {
+            TelegramCommand::Status => self.dispatcher.status_report(),
+            TelegramCommand::Mute(duration) => {
+                self.dispatcher.mute(duration);
+                format!("🔇 Alerts muted for {}", format_duration(duration))
+            },
+            TelegramCommand::Unmute => {
+                self.dispatcher.unmute();
+                "🔔 Alerts unmuted".to_string()
+            },
+            TelegramCommand::Unknown => {
+                "Supported commands: /status, /mute <duration> (e.g. 30m, 2h), /unmute".to_string()
+            },
+        }
+    }
+
+    /// Polls until shutdown is requested, backing off on API errors.
+    pub async fn run(mut self, shutdown: CancellationToken) -> AppResult<()> {
+        info!("📨 telegram command handler started for chat {}", self.client.chat_id());
+        let mut backoff = self.backoff();
+        loop {
+            let result = tokio::select! {
+                _ = shutdown.cancelled() => {
+                    info!("telegram command handler received shutdown signal, exiting");
+                    return Ok(());
+                }
+                result = self.poll_once() => result,
+            };
+            match result {
+                Ok(_) => backoff = self.backoff(),
+                Err(e) => {
+                    let delay = backoff
+                        .next_delay()
+                        .unwrap_or(Duration::from_secs(self.retry_max_delay_secs.into()));
+                    warn!("telegram polling failed: {}, retrying in {:?}", e, delay);
+                    tokio::select! {
+                        _ = shutdown.cancelled() => {
+                            info!("telegram command handler received shutdown signal, exiting");
+                            return Ok(());
+                        }
+                        _ = tokio::time::sleep(delay) => {}
+                    }
+                },
+            }
+        }
+    }
+
+    /// Spawns the handler on the tokio runtime.
+    pub fn spawn(self, shutdown: CancellationToken) -> tokio::task::JoinHandle<AppResult<()>> {
+        tokio::spawn(async move { self.run(shutdown).await })
+    }
+
+    /// Backoff between failed polls, retrying forever.
+    fn backoff(&self) -> ExponentialBackoff {
+        ExponentialBackoff::unlimited(
+            Duration::from_secs(self.retry_min_delay_secs.into()),
+            Duration::from_secs(self.retry_max_delay_secs.into()),
+            2,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal_macros::dec;
+    use wiremock::{
+        matchers::{body_json, method, path, query_param},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    use super::*;
+    use crate::{
+        config::AlertConfig,
+        engine::{
+            ArbitrageDirection, ArbitrageOpportunity, InternalEvent, PoolPriceUpdate, PoolSymbol,
+        },
+        executors::Alert,
+    };
+
+    const TOKEN: &str = "123:secret";
+    const CHAT_ID: i64 = 42;
+
+    fn dispatcher(server: &MockServer) -> AlertDispatcher {
+        let config = AlertConfig {
+            webhooks: vec![],
+            telegram: None,
+            min_opportunity_net_bps: dec!(50),
+            rate_limit_max_alerts: 10,
+            rate_limit_period_secs: 60,
+            max_retries: 0,
+            retry_min_delay_secs: 0,
+            retry_max_delay_secs: 0,
+        };
+        AlertDispatcher::new(config).with_telegram(client(server))
+    }
+
+    fn client(server: &MockServer) -> TelegramClient {
+        TelegramClient::new(&server.uri(), TOKEN, CHAT_ID)
+    }
+
+    fn handler(server: &MockServer, dispatcher: AlertDispatcher) -> TelegramCommandHandler {
+        TelegramCommandHandler::new(client(server), dispatcher, 0, 0, 0)
+    }
+
+    fn updates(messages: &[(i64, i64, &str)]) -> serde_json::Value {
+        let result = messages
+            .iter()
+            .map(|(update_id, chat_id, text)| {
+                json!({
+                    "update_id": update_id,
+                    "message": { "message_id": 1, "chat": { "id": chat_id }, "text": text }
+                })
+            })
+            .collect::<Vec<_>>();
+        json!({ "ok": true, "result": result })
+    }
+
+    async fn mount_send_message(server: &MockServer, text: &str) {
+        Mock::given(method("POST"))
+            .and(path(format!("/bot{}/sendMessage", TOKEN)))
+            .and(body_json(json!({ "chat_id": CHAT_ID, "text": text })))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(json!({ "ok": true, "result": {} })),
+            )
+            .expect(1)
+            .mount(server)
+            .await;
+    }
+
+    #[test]
+    fn test_parse_commands() {
+        assert_eq!(TelegramCommand::parse("/status"), Some(TelegramCommand::Status));
+        assert_eq!(TelegramCommand::parse("/status@SikarraBot"), Some(TelegramCommand::Status));
+        assert_eq!(
+            TelegramCommand::parse("/mute 30m"),
+            Some(TelegramCommand::Mute(Duration::from_secs(30 * 60)))
+        );
+        assert_eq!(
+            TelegramCommand::parse("/mute 2h"),
+            Some(TelegramCommand::Mute(Duration::from_secs(2 * 60 * 60)))
+        );
+        assert_eq!(TelegramCommand::parse("/mute"), Some(TelegramCommand::Unknown));
+        assert_eq!(TelegramCommand::parse("/mute soon"), Some(TelegramCommand::Unknown));
+        assert_eq!(TelegramCommand::parse("/unmute"), Some(TelegramCommand::Unmute));
+        assert_eq!(TelegramCommand::parse("hello"), None);
+        assert_eq!(format_duration(Duration::from_secs(90 * 60)), "1h 30m");
+    }
+
+    #[tokio::test]
+    async fn test_alerts_are_mirrored_and_muted() {
+        let server = MockServer::start().await;
+        let alert = Alert::Opportunity(ArbitrageOpportunity {
+            symbol: PoolSymbol::ETH_USDC,
+            direction: ArbitrageDirection::BuyDexSellCex,
+            cex_price: dec!(2510),
+            dex_price: dec!(2500),
+            net_bps: dec!(60),
+            recommended_size: dec!(0),
+            detected_at: "2025-02-12T21:12:33Z".parse().unwrap(),
+            cex_quote_at: "2025-02-12T21:12:33Z".parse().unwrap(),
+            dex_quote_at: "2025-02-12T21:12:33Z".parse().unwrap(),
+        });
+        mount_send_message(&server, &alert.summary()).await;
+
+        let dispatcher = dispatcher(&server);
+        dispatcher.dispatch(alert.clone()).await;
+
+        // Muted alerts are not delivered at all
+        dispatcher.mute(Duration::from_secs(60));
+        dispatcher.dispatch(alert).await;
+    }
+
+    #[tokio::test]
+    async fn test_status_and_mute_commands() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path(format!("/bot{}/getUpdates", TOKEN)))
+            .and(query_param("timeout", "0"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(updates(&[
+                (7, CHAT_ID, "/status"),
+                (8, 1337, "/mute 1h"),
+                (9, CHAT_ID, "/mute 30m"),
+            ])))
+            .expect(1)
+            .mount(&server)
+            .await;
+        let dispatcher = dispatcher(&server);
+        dispatcher.record_event(&InternalEvent::PoolPriceUpdate(PoolPriceUpdate {
+            symbol: PoolSymbol::ETH_USDC,
+            price: dec!(2500),
+            timestamp: jiff::Timestamp::now(),
+            block_number: None,
+            state: None,
+            effective_buy_price: None,
+            effective_sell_price: None,
+        }));
+        mount_send_message(
+            &server,
+            "📊 Status\nETH-USDC: CEX n/a, DEX $2500.00 (0s ago), ✅ feeds up",
+        )
+        .await;
+        mount_send_message(&server, "🔇 Alerts muted for 30m").await;
+
+        let mut handler = handler(&server, dispatcher.clone());
+        handler.poll_once().await.unwrap();
+        assert_eq!(handler.offset, Some(10));
+        assert!(dispatcher.is_muted());
+    }
+
+    #[tokio::test]
+    async fn test_polling_survives_api_errors_until_shutdown() {
+        let server = MockServer::start().await;
+        Mock::given(path(format!("/bot{}/getUpdates", TOKEN)))
+            .respond_with(ResponseTemplate::new(502))
+            .up_to_n_times(2)
+            .expect(2)
+            .mount(&server)
+            .await;
+        Mock::given(path(format!("/bot{}/getUpdates", TOKEN)))
+            .and(query_param("offset", "4"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(updates(&[])))
+            .mount(&server)
+            .await;
+        Mock::given(path(format!("/bot{}/getUpdates", TOKEN)))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(updates(&[(3, CHAT_ID, "/mute 5m")])),
+            )
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        mount_send_message(&server, "🔇 Alerts muted for 5m").await;
+
+        let dispatcher = dispatcher(&server);
+        let shutdown = CancellationToken::new();
+        let handle = handler(&server, dispatcher.clone()).spawn(shutdown.clone());
+        for _ in 0..100 {
+            if dispatcher.is_muted() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert!(dispatcher.is_muted());
+
+        shutdown.cancel();
+        handle.await.unwrap().unwrap();
+    }
+}
@@ -0,0 +1,273 @@
+//! Global trading halt, i.e. the kill switch.
+//!
+//! A [`HaltState`] is shared by the runners of all pools. It is tripped by
+//! [`InternalAction::Halt`] actions, or directly by executors and risk checks,
+//! after which every executor wrapped in a [`HaltGuard`] drops the actions it
+//! is handed and the execution planner refuses further legs. Collectors and
+//! engines keep running, so prices and feed health remain observable while
+//! halted. Only an operator clears a halt, through the `/api/halt` endpoint of
+//! the [`HealthServer`] or by sending `SIGUSR1` to the process.
+
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use serde::Serialize;
+use sikkara_core::{AppResult, Executor};
+use tokio::signal::unix::{signal, SignalKind};
+use tokio_util::sync::CancellationToken;
+use tracing::{error, info, warn};
+
+use crate::{
+    engine::{ArbitrageOpportunity, InternalAction, Leg},
+    executors::{Alert, AlertDispatcher, RiskCheck},
+};
+
+mod server;
+pub use server::{HealthServer, HALT_PATH, HEALTH_PATH};
+
+/// Why and when trading was halted.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Halt {
+    pub reason: String,
+    /// Executor or check which tripped the halt
+    pub source: String,
+    #[serde(with = "sikkara_core::timestamp_with_tz_serializer")]
+    pub tripped_at: jiff::Timestamp,
+}
+
+/// Halt state shared across runners.
+///
+/// Cloning is cheap, all clones share the same state. Tripping an already
+/// halted state keeps the first halt, so concurrent trips from several pools
+/// alert once and report the original reason.
+#[derive(Debug, Clone, Default)]
+pub struct HaltState {
+    halt: Arc<Mutex<Option<Halt>>>,
+    alerts: Option<AlertDispatcher>,
+}
+
+impl HaltState {
+    pub fn new() -> Self { Self::default() }
+
+    /// Dispatches a kill-switch alert whenever the state is tripped.
+    pub fn with_alerts(mut self, dispatcher: AlertDispatcher) -> Self {
+        self.alerts = Some(dispatcher);
+        self
+    }
+
+    /// Halts trading, returning false if it was already halted.
+    pub async fn trip(&self, source: &str, reason: &str) -> bool {
+        let halt = {
+            let mut state = self.lock();
+            if state.is_some() {
+                return false;
+            }
+            let halt = Halt {
+                reason: reason.to_string(),
+                source: source.to_string(),
+                tripped_at: jiff::Timestamp::now(),
+            };
+            *state = Some(halt.clone());
+            halt
+        };
+        error!(source = %halt.source, reason = %halt.reason, "trading halted");
+        if let Some(alerts) = &self.alerts {
+            let reason = format!("{} ({})", halt.reason, halt.source);
+            alerts.dispatch(Alert::KillSwitch { reason }).await;
+        }
+        true
+    }
+
+    /// Resumes trading, returning the halt cleared if any.
+    pub fn clear(&self) -> Option<Halt> {
+        let cleared = self.lock().take();
+        if let Some(halt) = &cleared {
+            warn!(reason = %halt.reason, source = %halt.source, "trading halt cleared");
+        }
+        cleared
+    }
+
+    /// Returns the current halt, none while trading.
+    pub fn current(&self) -> Option<Halt> { self.lock().clone() }
+
+    pub fn is_halted(&self) -> bool { self.lock().is_some() }
+
+    /// Spawns a task clearing the halt whenever the process receives
+    /// `SIGUSR1`.
+    pub fn spawn_signal_handler(
+        &self,
+        shutdown: CancellationToken,
+    ) -> AppResult<tokio::task::JoinHandle<AppResult<()>>> {
+        let mut sigusr1 = signal(SignalKind::user_defined1())?;
+        let state = self.clone();
+        Ok(tokio::spawn(async move {
+            loop {
+                tokio::select! {
+                    _ = shutdown.cancelled() => return Ok(()),
+                    _ = sigusr1.recv() => {
+                        info!("received SIGUSR1, clearing trading halt");
+                        state.clear();
+                    }
+                }
+            }
+        }))
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Option<Halt>> {
+        self.halt
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl RiskCheck for HaltState {
+    fn check(&self, _opportunity: &ArbitrageOpportunity, _leg: Leg) -> Option<String> {
+        self.current()
+            .map(|halt| format!("trading halted: {}", halt.reason))
+    }
+}
+
+/// Executor wrapper tripping the halt state on [`InternalAction::Halt`]
+/// actions and dropping all actions while halted.
+pub struct HaltGuard {
+    name: String,
+    inner: Box<dyn Executor<InternalAction>>,
+    halt: HaltState,
+}
+
+impl HaltGuard {
+    pub fn new(inner: Box<dyn Executor<InternalAction>>, halt: HaltState) -> Self {
+        Self { name: format!("halt_guard_{}", inner.id()), inner, halt }
+    }
+}
+
+#[async_trait::async_trait]
+impl Executor<InternalAction> for HaltGuard {
+    fn id(&self) -> &str { &self.name }
+
+    async fn execute_actions(&mut self, actions: Vec<InternalAction>) -> AppResult<()> {
+        let mut allowed = Vec::with_capacity(actions.len());
+        for action in actions {
+            if let InternalAction::Halt { reason } = &action {
+                self.halt.trip(self.inner.id(), reason).await;
+            }
+            if let Some(halt) = self.halt.current() {
+                warn!(executor = self.inner.id(), reason = %halt.reason, "trading halted, action dropped");
+                continue;
+            }
+            allowed.push(action);
+        }
+        if allowed.is_empty() {
+            return Ok(());
+        }
+        self.inner.execute_actions(allowed).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::future::join_all;
+    use rust_decimal_macros::dec;
+
+    use super::*;
+    use crate::engine::{ArbitrageDirection, PoolSymbol};
+
+    /// Executor recording the actions it is handed.
+    struct Recorder {
+        name: String,
+        actions: Arc<Mutex<Vec<InternalAction>>>,
+    }
+
+    fn recorder(
+        pool: &str,
+    ) -> (Box<dyn Executor<InternalAction>>, Arc<Mutex<Vec<InternalAction>>>) {
+        let actions = Arc::new(Mutex::new(Vec::new()));
+        let recorder = Recorder { name: format!("recorder_{}", pool), actions: actions.clone() };
+        (Box::new(recorder), actions)
+    }
+
+    #[async_trait::async_trait]
+    impl Executor<InternalAction> for Recorder {
+        fn id(&self) -> &str { &self.name }
+
+        async fn execute_actions(&mut self, actions: Vec<InternalAction>) -> AppResult<()> {
+            self.actions.lock().unwrap().extend(actions);
+            Ok(())
+        }
+    }
+
+    fn arbitrage(symbol: PoolSymbol) -> ArbitrageOpportunity {
+        ArbitrageOpportunity {
+            symbol,
+            direction: ArbitrageDirection::BuyDexSellCex,
+            cex_price: dec!(2520.5),
+            dex_price: dec!(2500),
+            net_bps: dec!(81.33),
+            recommended_size: dec!(0.5),
+            detected_at: "2025-02-12T21:12:33.250Z".parse().unwrap(),
+            cex_quote_at: "2025-02-12T21:12:33.250Z".parse().unwrap(),
+            dex_quote_at: "2025-02-12T21:12:33.250Z".parse().unwrap(),
+        }
+    }
+
+    fn opportunity(symbol: PoolSymbol) -> InternalAction {
+        InternalAction::Opportunity(arbitrage(symbol))
+    }
+
+    #[tokio::test]
+    async fn test_halt_from_one_pool_blocks_other_pools() {
+        let halt = HaltState::new();
+        let (eth_usdc, eth_usdc_actions) = recorder("ETH-USDC");
+        let (eth_usdt, eth_usdt_actions) = recorder("ETH-USDT");
+        let mut eth_usdc = HaltGuard::new(eth_usdc, halt.clone());
+        let mut eth_usdt = HaltGuard::new(eth_usdt, halt.clone());
+
+        // The opportunity preceding the halt is still executed
+        eth_usdc
+            .execute_actions(vec![
+                opportunity(PoolSymbol::ETH_USDC),
+                InternalAction::Halt { reason: "daily loss limit reached".to_string() },
+                opportunity(PoolSymbol::ETH_USDC),
+            ])
+            .await
+            .unwrap();
+        assert_eq!(eth_usdc_actions.lock().unwrap().len(), 1);
+
+        eth_usdt
+            .execute_actions(vec![opportunity(PoolSymbol::ETH_USDT)])
+            .await
+            .unwrap();
+        assert!(eth_usdt_actions.lock().unwrap().is_empty());
+
+        let current = halt.current().unwrap();
+        assert_eq!(current.reason, "daily loss limit reached");
+        assert_eq!(current.source, "recorder_ETH-USDC");
+        assert_eq!(
+            halt.check(&arbitrage(PoolSymbol::ETH_USDT), Leg::Cex),
+            Some("trading halted: daily loss limit reached".to_string())
+        );
+
+        // Trading resumes once cleared
+        assert_eq!(halt.clear(), Some(current));
+        eth_usdt
+            .execute_actions(vec![opportunity(PoolSymbol::ETH_USDT)])
+            .await
+            .unwrap();
+        assert_eq!(eth_usdt_actions.lock().unwrap().len(), 1);
+        assert_eq!(halt.clear(), None);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_trips_keep_the_first_halt() {
+        let halt = HaltState::new();
+        let trips = join_all((0..16).map(|pool| {
+            let halt = halt.clone();
+            tokio::spawn(async move { halt.trip(&format!("pool_{}", pool), "tripped").await })
+        }))
+        .await;
+
+        let tripped: Vec<_> = trips.into_iter().map(|trip| trip.unwrap()).collect();
+        assert_eq!(tripped.iter().filter(|tripped| **tripped).count(), 1);
+        let first = tripped.iter().position(|tripped| *tripped).unwrap();
+        assert_eq!(halt.current().unwrap().source, format!("pool_{}", first));
+    }
+}
@@ -0,0 +1,254 @@
+//! HTTP server exposing the health and the halt state of the bot.
+//!
+//! - `GET /health`: whether trading is halted, and why, along with the daily
+//!   profit and loss if tracked
+//! - `GET /api/halt`: the current halt, `null` while trading
+//! - `DELETE /api/halt`: clears the halt, returning the halt cleared
+//!
+//! Every connection serves a single request without a body, which is all
+//! operators and health probes need.
+
+use std::{net::SocketAddr, time::Duration};
+
+use serde_json::json;
+use sikkara_core::{AppError, AppResult};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::{TcpListener, TcpStream},
+};
+use tokio_util::sync::CancellationToken;
+use tracing::{error, info, warn};
+
+use crate::{halt::HaltState, positions::DailyPnl};
+
+/// Path of the health endpoint.
+pub const HEALTH_PATH: &str = "/health";
+
+/// Path of the halt endpoint.
+pub const HALT_PATH: &str = "/api/halt";
+
+/// Time a client is given to send its request.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// HTTP server of the health and halt endpoints.
+#[derive(Debug)]
+pub struct HealthServer {
+    listener: TcpListener,
+    halt: HaltState,
+    pnl: Option<DailyPnl>,
+}
+
+impl HealthServer {
+    /// Binds the server to the given socket address.
+    pub async fn bind(addr: &str, halt: HaltState) -> AppResult<Self> {
+        let listener = TcpListener::bind(addr).await.map_err(|e| {
+            AppError::HttpError(format!("failed to bind health server to {}: {}", addr, e))
+        })?;
+        Ok(Self { listener, halt, pnl: None })
+    }
+
+    /// Reports the daily profit and loss along with the health.
+    pub fn with_pnl(mut self, pnl: DailyPnl) -> Self {
+        self.pnl = Some(pnl);
+        self
+    }
+
+    /// Returns the address the server is listening on.
+    pub fn local_addr(&self) -> AppResult<SocketAddr> { Ok(self.listener.local_addr()?) }
+
+    /// Accepts connections until shutdown is requested.
+    pub async fn run(self, shutdown: CancellationToken) -> AppResult<()> {
+        info!("health server listening on http://{}{}", self.local_addr()?, HEALTH_PATH);
+        loop {
+            tokio::select! {
+                _ = shutdown.cancelled() => {
+                    info!("health server received shutdown signal, exiting");
+                    return Ok(());
+                }
+                accepted = self.listener.accept() => match accepted {
+                    Ok((stream, peer)) => {
+                        let (halt, pnl) = (self.halt.clone(), self.pnl.clone());
+                        tokio::spawn(async move {
+                            if let Err(e) = handle_client(stream, halt, pnl).await {
+                                warn!("health client {} failed: {}", peer, e);
+                            }
+                        });
+                    },
+                    Err(e) => error!("failed to accept health connection: {}", e),
+                }
+            }
+        }
+    }
+
+    /// Spawns the server on the tokio runtime.
+    pub fn spawn(self, shutdown: CancellationToken) -> tokio::task::JoinHandle<AppResult<()>> {
+        tokio::spawn(async move { self.run(shutdown).await })
+    }
+}
+
+async fn handle_client(
+    mut stream: TcpStream,
+    halt: HaltState,
+    pnl: Option<DailyPnl>,
+) -> AppResult<()> {
+    let (reader, mut writer) = stream.split();
+    let mut reader = BufReader::new(reader);
+    let request_line = tokio::time::timeout(REQUEST_TIMEOUT, async {
+        let mut request_line = String::new();
+        reader.read_line(&mut request_line).await?;
+        // Skip the headers, requests carry no body
+        let mut header = String::new();
+        while reader.read_line(&mut header).await? > 0 && !header.trim().is_empty() {
+            header.clear();
+        }
+        AppResult::Ok(request_line)
+    })
+    .await
+    .map_err(|_| AppError::HttpError("request timed out".to_string()))??;
+
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or_default();
+    let path = parts
+        .next()
+        .and_then(|target| target.split('?').next())
+        .unwrap_or_default();
+    let (status, body) = respond(method, path, &halt, pnl.as_ref());
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    );
+    writer.write_all(response.as_bytes()).await?;
+    writer.shutdown().await?;
+    Ok(())
+}
+
+/// Returns the status line and the JSON body answering a request.
+fn respond(
+    method: &str,
+    path: &str,
+    halt: &HaltState,
+    pnl: Option<&DailyPnl>,
+) -> (&'static str, String) {
+    match (method, path) {
+        ("GET", HEALTH_PATH) => {
+            let current = halt.current();
+            let status = if current.is_some() { "halted" } else { "ok" };
+            let mut body = json!({ "status": status, "halt": current });
+            if let Some(pnl) = pnl {
+                body["pnl"] = json!(pnl.snapshot());
+            }
+            ("200 OK", body.to_string())
+        },
+        ("GET", HALT_PATH) => ("200 OK", json!({ "halt": halt.current() }).to_string()),
+        ("DELETE", HALT_PATH) => {
+            info!("trading halt clear requested by operator");
+            ("200 OK", json!({ "cleared": halt.clear() }).to_string())
+        },
+        (_, HEALTH_PATH | HALT_PATH) => {
+            ("405 Method Not Allowed", json!({ "error": "method not allowed" }).to_string())
+        },
+        _ => ("404 Not Found", json!({ "error": "not found" }).to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use reqwest::StatusCode;
+    use rust_decimal_macros::dec;
+    use serde_json::Value;
+    use sikkara_core::MockClock;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_health_and_halt_endpoints() {
+        let halt = HaltState::new();
+        let server = HealthServer::bind("127.0.0.1:0", halt.clone())
+            .await
+            .unwrap();
+        let url = format!("http://{}", server.local_addr().unwrap());
+        let shutdown = CancellationToken::new();
+        let handle = server.spawn(shutdown.clone());
+        let client = reqwest::Client::new();
+
+        let health: Value = client
+            .get(format!("{}{}", url, HEALTH_PATH))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(health, json!({ "status": "ok", "halt": null }));
+
+        halt.trip("risk_manager", "daily loss limit reached").await;
+        let health: Value = client
+            .get(format!("{}{}", url, HEALTH_PATH))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(health["status"], "halted");
+        assert_eq!(health["halt"]["reason"], "daily loss limit reached");
+        assert_eq!(health["halt"]["source"], "risk_manager");
+
+        // Clearing requires an explicit DELETE
+        let response = client
+            .post(format!("{}{}", url, HALT_PATH))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::METHOD_NOT_ALLOWED);
+        assert!(halt.is_halted());
+
+        let cleared: Value = client
+            .delete(format!("{}{}", url, HALT_PATH))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(cleared["cleared"]["reason"], "daily loss limit reached");
+        assert!(!halt.is_halted());
+
+        let response = client.get(format!("{}/metrics", url)).send().await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        shutdown.cancel();
+        handle.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_health_reports_daily_pnl() {
+        let clock = MockClock::new("2025-02-12T21:00:00Z".parse().unwrap());
+        let pnl = DailyPnl::new(dec!(100), 0, Arc::new(clock));
+        pnl.record_fill("ETH-USDC", dec!(1), dec!(2520));
+        pnl.mark("ETH-USDC", dec!(2490));
+        pnl.check();
+
+        let server = HealthServer::bind("127.0.0.1:0", HaltState::new())
+            .await
+            .unwrap()
+            .with_pnl(pnl);
+        let url = format!("http://{}{}", server.local_addr().unwrap(), HEALTH_PATH);
+        let shutdown = CancellationToken::new();
+        let handle = server.spawn(shutdown.clone());
+
+        let health: Value = reqwest::get(url).await.unwrap().json().await.unwrap();
+        assert_eq!(health["status"], "ok");
+        assert_eq!(health["pnl"]["day"], "2025-02-12");
+        assert_eq!(health["pnl"]["total"], "-30");
+        assert_eq!(health["pnl"]["drawdown"], "30");
+        assert_eq!(health["pnl"]["symbols"]["ETH-USDC"]["position"], "1");
+
+        shutdown.cancel();
+        handle.await.unwrap().unwrap();
+    }
+}
@@ -1,19 +1,67 @@
-use std::{env, path::Path};
+use std::{
+    env,
+    io::{self, IsTerminal},
+    path::Path,
+};
 
-use sikkara_core::run;
+use clap::{Args, Parser, Subcommand};
+use sikkara_core::{load_config, run, run_with_reload, ConfigFormat, ConfigReloader};
 
 // Internal module for the arbitrager application
 mod collectors;
 #[allow(unused)]
 mod config;
+mod daily_files;
 #[allow(unused)]
 mod engine;
 #[allow(unused)]
+mod event_stream;
+#[allow(unused)]
+mod executors;
+#[allow(unused)]
+mod halt;
+#[allow(unused)]
+mod positions;
+#[allow(unused)]
+mod report;
+#[allow(unused)]
 mod runner;
 #[allow(unused)]
 mod strategy;
+#[allow(unused)]
+mod tap;
+
+#[derive(Debug, Parser)]
+#[command(about = "CEX/DEX arbitrage monitoring bot")]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Pretty-print the live event stream of the collectors, without running
+    /// any strategy
+    Tap(TapArgs),
+}
+
+#[derive(Debug, Args)]
+struct TapArgs {
+    /// Symbol to tap, e.g. ETH-USDC. Repeat for several symbols, all
+    /// configured pools are tapped if omitted
+    #[arg(long = "symbol")]
+    symbols: Vec<String>,
+    /// Print one JSON object per event
+    #[arg(long)]
+    json: bool,
+    /// Disable colors, which are also disabled when stdout is not a terminal
+    #[arg(long)]
+    no_color: bool,
+}
 
 fn main() {
+    let cli = Cli::parse();
+
     // Read config path from environment variable, with a default fallback
     let config_path = env::var("BOT_CONFIG_PATH").unwrap_or_else(|_| "config/bot.json".to_string());
 
@@ -23,12 +71,29 @@ fn main() {
         eprintln!("Please set BOT_CONFIG_PATH environment variable or ensure the default config file exists");
         std::process::exit(1);
     }
-    let content =
-        std::fs::read_to_string(&config_path).expect("Failed to read the configuration file");
-
-    let params: config::BotConfig =
-        serde_json::from_str(&content).expect("Failed to parse the configuration file");
+    // JSON or TOML, detected from the extension of the file
+    let params: config::BotConfig = load_config(Path::new(&config_path), ConfigFormat::Auto)
+        .expect("Failed to load the configuration file");
 
-    let runner = runner::BotRunner {};
-    run(params, runner);
+    match cli.command {
+        None => {
+            // Reload the market making parameters on SIGHUP
+            let reloader = ConfigReloader::new(&config_path, ConfigFormat::Auto)
+                .with_validation(config::BotConfig::validate);
+            let runner = runner::BotRunner::default().with_config_reloads(reloader.sender());
+            run_with_reload(params, runner, Some(reloader))
+        },
+        Some(Command::Tap(args)) => {
+            // Keep the tapped events readable, logs only report problems
+            if env::var_os("LOG_LEVEL").is_none() {
+                env::set_var("LOG_LEVEL", "warn");
+            }
+            let format = if args.json {
+                tap::TapFormat::Json
+            } else {
+                tap::TapFormat::Pretty { color: !args.no_color && io::stdout().is_terminal() }
+            };
+            run(params, tap::TapRunner::new(args.symbols, format));
+        },
+    }
 }
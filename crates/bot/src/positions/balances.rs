@@ -0,0 +1,202 @@
+//! Balances held on every venue.
+//!
+//! The [`BalanceBook`] of a pool keeps the latest [`BalanceUpdate`] of every
+//! asset on every venue, recorded by the arbitrage engine. The strategy skews
+//! its simulated ranges by the inventory held across all venues, and as a
+//! [`RiskCheck`] the book refuses legs spending more than is free on the venue
+//! they trade on.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock, RwLockReadGuard},
+};
+
+use alloy::primitives::Address;
+use rust_decimal::Decimal;
+
+use crate::{
+    engine::{ArbitrageDirection, ArbitrageOpportunity, BalanceUpdate, BalanceVenue, Leg},
+    executors::RiskCheck,
+};
+
+/// Latest balances of a pool, see the module documentation.
+///
+/// Cloning is cheap, all clones share the same balances, so one clone is
+/// recorded into by the arbitrage engine, another read by the strategy and
+/// another registered as a risk check of the execution planner.
+#[derive(Debug, Clone, Default)]
+pub struct BalanceBook {
+    balances: Arc<RwLock<HashMap<(BalanceVenue, String), BalanceUpdate>>>,
+    /// Wallet swapping the DEX leg, any wallet if unset
+    wallet: Option<Address>,
+}
+
+impl BalanceBook {
+    pub fn new() -> Self { Self::default() }
+
+    /// Checks the DEX legs against the balances of `wallet` only.
+    pub fn with_wallet(mut self, wallet: Address) -> Self {
+        self.wallet = Some(wallet);
+        self
+    }
+
+    /// Records a balance, replacing the previous one of its asset on its
+    /// venue.
+    pub fn record(&self, balance: &BalanceUpdate) {
+        self.balances
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .insert((balance.venue.clone(), balance.asset.clone()), balance.clone());
+    }
+
+    /// Returns the amount of `asset` free for trading on the venue of `leg`,
+    /// none if no balance of it was recorded there.
+    pub fn free(&self, leg: Leg, asset: &str) -> Option<Decimal> {
+        self.read()
+            .values()
+            .filter(|balance| balance.asset == asset && self.trades_on(&balance.venue, leg))
+            .map(|balance| balance.free)
+            .reduce(|total, free| total + free)
+    }
+
+    /// Returns the amount of `asset` held across all venues, none if no
+    /// balance of it was recorded.
+    pub fn held(&self, asset: &str) -> Option<Decimal> {
+        self.read()
+            .values()
+            .filter(|balance| balance.asset == asset)
+            .map(|balance| balance.total)
+            .reduce(|held, total| held + total)
+    }
+
+    fn trades_on(&self, venue: &BalanceVenue, leg: Leg) -> bool {
+        match (venue, leg) {
+            (BalanceVenue::Cex(_), Leg::Cex) => true,
+            (BalanceVenue::Wallet(address), Leg::Dex) => {
+                self.wallet.is_none_or(|wallet| wallet == *address)
+            },
+            _ => false,
+        }
+    }
+
+    fn read(&self) -> RwLockReadGuard<'_, HashMap<(BalanceVenue, String), BalanceUpdate>> {
+        self.balances
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// Returns the asset the leg of an opportunity spends and how much of it, at
+/// the quoted price when buying.
+fn spent(opportunity: &ArbitrageOpportunity, leg: Leg) -> (&str, Decimal) {
+    let symbol = &opportunity.symbol;
+    let size = opportunity.recommended_size;
+    match (opportunity.direction, leg) {
+        (ArbitrageDirection::BuyDexSellCex, Leg::Dex) => {
+            (symbol.quote_asset(), size * opportunity.dex_price)
+        },
+        (ArbitrageDirection::BuyCexSellDex, Leg::Cex) => {
+            (symbol.quote_asset(), size * opportunity.cex_price)
+        },
+        (ArbitrageDirection::BuyDexSellCex, Leg::Cex)
+        | (ArbitrageDirection::BuyCexSellDex, Leg::Dex) => (symbol.base_asset(), size),
+    }
+}
+
+impl RiskCheck for BalanceBook {
+    fn check(&self, opportunity: &ArbitrageOpportunity, leg: Leg) -> Option<String> {
+        let (asset, amount) = spent(opportunity, leg);
+        match self.free(leg, asset) {
+            None => Some(format!("no {} balance known on the {} venue", asset, leg)),
+            Some(free) if free < amount => Some(format!(
+                "{} {} needed, only {} free on the {} venue",
+                amount, asset, free, leg
+            )),
+            Some(_) => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::address;
+    use rust_decimal_macros::dec;
+
+    use super::*;
+    use crate::engine::{Exchange, PoolSymbol};
+
+    const WALLET: Address = address!("0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266");
+    const OTHER_WALLET: Address = address!("0x70997970C51812dc3A010C7d01b50e0d17dc79C8");
+
+    fn balance(venue: BalanceVenue, asset: &str, free: Decimal, total: Decimal) -> BalanceUpdate {
+        BalanceUpdate {
+            venue,
+            asset: asset.to_string(),
+            free,
+            total,
+            timestamp: "2025-02-12T21:00:00Z".parse().unwrap(),
+        }
+    }
+
+    fn opportunity(direction: ArbitrageDirection) -> ArbitrageOpportunity {
+        ArbitrageOpportunity {
+            symbol: PoolSymbol::ETH_USDC,
+            direction,
+            cex_price: dec!(2520),
+            dex_price: dec!(2500),
+            net_bps: dec!(59.37),
+            recommended_size: dec!(0.5),
+            detected_at: "2025-02-12T21:00:00Z".parse().unwrap(),
+            cex_quote_at: "2025-02-12T21:00:00Z".parse().unwrap(),
+            dex_quote_at: "2025-02-12T21:00:00Z".parse().unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_legs_exceeding_the_free_balance_of_their_venue_are_blocked() {
+        let book = BalanceBook::new().with_wallet(WALLET);
+        let coinbase = BalanceVenue::Cex(Exchange::Coinbase);
+        book.record(&balance(coinbase.clone(), "ETH", dec!(0.4), dec!(1)));
+        book.record(&balance(coinbase, "USDC", dec!(5000), dec!(5000)));
+        book.record(&balance(BalanceVenue::Wallet(WALLET), "USDC", dec!(1000), dec!(1000)));
+        // Held by a wallet which does not swap
+        book.record(&balance(BalanceVenue::Wallet(OTHER_WALLET), "ETH", dec!(3), dec!(3)));
+
+        // Buying 0.5 ETH on the pool spends 1250 USDC of the wallet, selling
+        // them on Coinbase 0.5 of the 0.4 ETH free
+        let buy_dex = opportunity(ArbitrageDirection::BuyDexSellCex);
+        assert_eq!(
+            book.check(&buy_dex, Leg::Dex),
+            Some("1250.0 USDC needed, only 1000 free on the dex venue".to_string())
+        );
+        assert_eq!(
+            book.check(&buy_dex, Leg::Cex),
+            Some("0.5 ETH needed, only 0.4 free on the cex venue".to_string())
+        );
+
+        // Buying 0.5 ETH on Coinbase spends 1260 of the 5000 USDC free, the
+        // swapping wallet holds no ETH to sell
+        let buy_cex = opportunity(ArbitrageDirection::BuyCexSellDex);
+        assert_eq!(book.check(&buy_cex, Leg::Cex), None);
+        assert_eq!(
+            book.check(&buy_cex, Leg::Dex),
+            Some("no ETH balance known on the dex venue".to_string())
+        );
+
+        // Balances are replaced by the latest ones of their venue
+        book.record(&balance(BalanceVenue::Wallet(WALLET), "USDC", dec!(1500), dec!(1500)));
+        assert_eq!(book.check(&buy_dex, Leg::Dex), None);
+    }
+
+    #[test]
+    fn test_held_balances_are_summed_across_venues() {
+        let book = BalanceBook::new().with_wallet(WALLET);
+        book.record(&balance(BalanceVenue::Cex(Exchange::Coinbase), "ETH", dec!(0.4), dec!(1)));
+        book.record(&balance(BalanceVenue::Wallet(OTHER_WALLET), "ETH", dec!(3), dec!(3)));
+
+        assert_eq!(book.held("ETH"), Some(dec!(4)));
+        assert_eq!(book.held("USDC"), None);
+        assert_eq!(book.free(Leg::Cex, "ETH"), Some(dec!(0.4)));
+        assert_eq!(book.free(Leg::Dex, "ETH"), None);
+    }
+}
@@ -0,0 +1,534 @@
+//! Reconciliation of order updates into positions.
+//!
+//! The orders of both legs of an opportunity are reported as [`OrderUpdate`]s,
+//! which may arrive out of order and repeat states already seen. The
+//! [`Reconciler`] of a pool matches every update to the opportunity it was
+//! placed for through its correlation id, keeps the furthest state of every
+//! order and applies the newly filled size to the positions of the pool:
+//!
+//! - Updates not advancing the state or the filled size of their order are
+//!   stale and ignored.
+//! - Fills whose correlation id matches no opportunity executed by the pool are
+//!   orphans, e.g. orders placed manually or by a previous run. They still move
+//!   the positions but are flagged.
+//!
+//! As a [`RiskCheck`] the reconciler blocks new legs while earlier
+//! opportunities left more than the tolerated size unhedged. Fills are also
+//! accounted in the [`DailyPnl`] if enabled.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+use rust_decimal::Decimal;
+use serde::Serialize;
+use sikkara_adapters::OrderSide;
+use sikkara_core::{current_correlation_id, AppResult, Engine, Executor};
+use tracing::{debug, info, warn};
+
+use crate::{
+    engine::{ArbitrageOpportunity, InternalAction, InternalEvent, Leg, OrderState, OrderUpdate},
+    executors::RiskCheck,
+};
+
+mod balances;
+pub use balances::BalanceBook;
+
+mod pnl;
+pub use pnl::{
+    DailyPnl, PnlEngine, PnlSnapshot, SymbolPnl, DAILY_DRAWDOWN_METRIC, DAILY_PNL_METRIC,
+};
+
+/// Number of opportunities and orders tracked, the oldest being forgotten.
+const MAX_TRACKED: usize = 1024;
+
+/// Positions of a pool in the base asset, positive when long.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Positions {
+    pub cex: Decimal,
+    pub dex: Decimal,
+    /// Fills matching no opportunity of the pool
+    pub orphan_fills: u64,
+}
+
+impl Positions {
+    /// Returns the position left unhedged across both venues.
+    pub fn net(&self) -> Decimal { self.cex + self.dex }
+
+    fn apply(&mut self, venue: Leg, delta: Decimal) {
+        match venue {
+            Leg::Cex => self.cex += delta,
+            Leg::Dex => self.dex += delta,
+        }
+    }
+}
+
+/// How an order update was reconciled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reconciled {
+    /// The update moved the position of its venue by `delta`
+    Applied { delta: Decimal },
+    /// The update matches no opportunity of the pool, yet moved the position
+    /// of its venue by `delta`
+    Orphan { delta: Decimal },
+    /// The update is older than the state already known
+    Stale,
+}
+
+/// Furthest state known of an order.
+#[derive(Debug, Clone)]
+struct TrackedOrder {
+    state: OrderState,
+    filled: Decimal,
+    avg_price: Option<Decimal>,
+}
+
+/// Progress of an order through its states, final states being terminal.
+fn rank(state: OrderState) -> u8 {
+    match state {
+        OrderState::Submitted => 0,
+        OrderState::PartiallyFilled => 1,
+        OrderState::Filled | OrderState::Cancelled | OrderState::Failed => 2,
+    }
+}
+
+fn signed(side: OrderSide, size: Decimal) -> Decimal {
+    match side {
+        OrderSide::Buy => size,
+        OrderSide::Sell => -size,
+    }
+}
+
+/// Opportunities, orders and positions of a pool.
+#[derive(Debug, Default)]
+pub struct Reconciliation {
+    opportunities: HashMap<u64, ArbitrageOpportunity>,
+    opportunity_ids: VecDeque<u64>,
+    orders: HashMap<String, TrackedOrder>,
+    order_ids: VecDeque<String>,
+    /// Net position of every opportunity across both venues, keyed by
+    /// correlation id
+    exposures: HashMap<Option<u64>, Decimal>,
+    positions: Positions,
+}
+
+impl Reconciliation {
+    /// Records an opportunity executed under a correlation id.
+    pub fn record_opportunity(&mut self, correlation_id: u64, opportunity: ArbitrageOpportunity) {
+        if self
+            .opportunities
+            .insert(correlation_id, opportunity)
+            .is_none()
+        {
+            self.opportunity_ids.push_back(correlation_id);
+        }
+        while self.opportunity_ids.len() > MAX_TRACKED {
+            if let Some(id) = self.opportunity_ids.pop_front() {
+                self.opportunities.remove(&id);
+            }
+        }
+    }
+
+    /// Returns the opportunity an update was placed for, if known.
+    pub fn opportunity(&self, update: &OrderUpdate) -> Option<&ArbitrageOpportunity> {
+        self.opportunities.get(&update.correlation_id?)
+    }
+
+    /// Reconciles an order update, applying its newly filled size.
+    pub fn apply(&mut self, update: &OrderUpdate) -> Reconciled {
+        let previous = match self.orders.get(&update.order_id) {
+            Some(order) => {
+                let advances = update.filled > order.filled
+                    || (update.filled == order.filled && rank(update.state) > rank(order.state));
+                if order.state.is_final() || !advances {
+                    return Reconciled::Stale;
+                }
+                order.filled
+            },
+            None => {
+                self.order_ids.push_back(update.order_id.clone());
+                while self.order_ids.len() > MAX_TRACKED {
+                    if let Some(order_id) = self.order_ids.pop_front() {
+                        self.orders.remove(&order_id);
+                    }
+                }
+                Decimal::ZERO
+            },
+        };
+        self.orders.insert(
+            update.order_id.clone(),
+            TrackedOrder {
+                state: update.state,
+                filled: update.filled,
+                avg_price: update.avg_price,
+            },
+        );
+
+        let delta = signed(update.side, update.filled - previous);
+        self.positions.apply(update.venue, delta);
+        if self.opportunity(update).is_some() {
+            *self.exposures.entry(update.correlation_id).or_default() += delta;
+            return Reconciled::Applied { delta };
+        }
+        *self.exposures.entry(None).or_default() += delta;
+        if !delta.is_zero() {
+            self.positions.orphan_fills += 1;
+        }
+        Reconciled::Orphan { delta }
+    }
+
+    pub fn positions(&self) -> &Positions { &self.positions }
+
+    /// Returns the price of the size an update newly fills, to be called
+    /// before applying it. Fills reporting no price, such as swaps, are
+    /// priced as quoted by their opportunity.
+    pub fn fill_price(&self, update: &OrderUpdate) -> Option<Decimal> {
+        let (filled, avg_price) = self
+            .orders
+            .get(&update.order_id)
+            .map_or((Decimal::ZERO, None), |order| (order.filled, order.avg_price));
+        let size = update.filled - filled;
+        if size <= Decimal::ZERO {
+            return None;
+        }
+        match (update.avg_price, avg_price) {
+            (Some(avg), Some(previous)) => Some((update.filled * avg - filled * previous) / size),
+            (Some(avg), None) => Some(avg),
+            (None, _) => self
+                .opportunity(update)
+                .map(|opportunity| match update.venue {
+                    Leg::Cex => opportunity.cex_price,
+                    Leg::Dex => opportunity.dex_price,
+                }),
+        }
+    }
+
+    /// Returns the position left unhedged by every opportunity but the given
+    /// one, including orphan fills.
+    pub fn unhedged_excluding(&self, correlation_id: Option<u64>) -> Decimal {
+        let own = correlation_id
+            .and_then(|id| self.exposures.get(&Some(id)))
+            .copied()
+            .unwrap_or_default();
+        self.positions.net() - own
+    }
+}
+
+/// Engine and executor reconciling the order updates of a pool, see the
+/// module documentation.
+///
+/// Cloning is cheap, all clones share the same reconciliation, so one clone
+/// is registered as the executor recording opportunities, another as the
+/// engine and another as a risk check of the execution planner.
+#[derive(Debug, Clone)]
+pub struct Reconciler {
+    name: String,
+    reconciliation: Arc<Mutex<Reconciliation>>,
+    max_unhedged: Option<Decimal>,
+    pnl: Option<DailyPnl>,
+}
+
+impl Reconciler {
+    pub fn new(pool: String) -> Self {
+        Self {
+            name: format!("reconciler_{}", pool),
+            reconciliation: Arc::new(Mutex::new(Reconciliation::default())),
+            max_unhedged: None,
+            pnl: None,
+        }
+    }
+
+    /// Blocks new legs while earlier opportunities left more than `size` of
+    /// the base asset unhedged.
+    pub fn with_max_unhedged(mut self, size: Decimal) -> Self {
+        self.max_unhedged = Some(size);
+        self
+    }
+
+    /// Accounts the fills in the daily profit and loss.
+    pub fn with_pnl(mut self, pnl: DailyPnl) -> Self {
+        self.pnl = Some(pnl);
+        self
+    }
+
+    /// Returns the reconciled positions of the pool.
+    pub fn positions(&self) -> Positions { self.lock().positions().clone() }
+
+    fn lock(&self) -> MutexGuard<'_, Reconciliation> {
+        self.reconciliation
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn reconcile(&self, update: &OrderUpdate) {
+        let mut reconciliation = self.lock();
+        let price = reconciliation.fill_price(update);
+        let reconciled = reconciliation.apply(update);
+        if let (Reconciled::Applied { delta } | Reconciled::Orphan { delta }, Some(pnl)) =
+            (reconciled, &self.pnl)
+        {
+            match price {
+                Some(price) => pnl.record_fill(&update.symbol.to_string(), delta, price),
+                None if !delta.is_zero() => {
+                    warn!(order_id = %update.order_id, %delta, "fill without price, not accounted in pnl")
+                },
+                None => {},
+            }
+        }
+        match reconciled {
+            Reconciled::Applied { delta } => {
+                let positions = reconciliation.positions();
+                info!(
+                    correlation_id = update.correlation_id,
+                    venue = %update.venue,
+                    order_id = %update.order_id,
+                    state = ?update.state,
+                    %delta,
+                    cex = %positions.cex,
+                    dex = %positions.dex,
+                    "order reconciled"
+                );
+            },
+            Reconciled::Orphan { delta } if !delta.is_zero() => {
+                warn!(
+                    correlation_id = update.correlation_id,
+                    venue = %update.venue,
+                    order_id = %update.order_id,
+                    %delta,
+                    "orphan fill, matching no opportunity"
+                );
+            },
+            Reconciled::Orphan { .. } | Reconciled::Stale => {
+                debug!(order_id = %update.order_id, state = ?update.state, "order update ignored");
+            },
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Executor<InternalAction> for Reconciler {
+    fn id(&self) -> &str { &self.name }
+
+    async fn execute_actions(&mut self, actions: Vec<InternalAction>) -> AppResult<()> {
+        let Some(correlation_id) = current_correlation_id() else {
+            return Ok(());
+        };
+        let mut reconciliation = self.lock();
+        for action in actions {
+            if let InternalAction::Opportunity(opportunity) = action {
+                reconciliation.record_opportunity(correlation_id, opportunity);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl Engine<InternalEvent, InternalAction> for Reconciler {
+    fn id(&self) -> &str { &self.name }
+
+    async fn process_event(&mut self, event: InternalEvent) -> AppResult<Option<InternalAction>> {
+        if let InternalEvent::OrderUpdate(update) = event {
+            self.reconcile(&update);
+        }
+        Ok(None)
+    }
+}
+
+impl RiskCheck for Reconciler {
+    fn check(&self, _opportunity: &ArbitrageOpportunity, _leg: Leg) -> Option<String> {
+        let max_unhedged = self.max_unhedged?;
+        // The legs of the opportunity being executed hedge each other
+        let unhedged = self.lock().unhedged_excluding(current_correlation_id());
+        (unhedged.abs() > max_unhedged).then(|| {
+            format!("{} left unhedged, more than the {} tolerated", unhedged, max_unhedged)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal_macros::dec;
+    use sikkara_core::MockClock;
+
+    use super::*;
+    use crate::engine::{ArbitrageDirection, PoolSymbol};
+
+    fn opportunity() -> ArbitrageOpportunity {
+        ArbitrageOpportunity {
+            symbol: PoolSymbol::ETH_USDC,
+            direction: ArbitrageDirection::BuyDexSellCex,
+            cex_price: dec!(2520),
+            dex_price: dec!(2500),
+            net_bps: dec!(59.37),
+            recommended_size: dec!(0.5),
+            detected_at: "2025-02-12T21:00:00Z".parse().unwrap(),
+            cex_quote_at: "2025-02-12T21:00:00Z".parse().unwrap(),
+            dex_quote_at: "2025-02-12T21:00:00Z".parse().unwrap(),
+        }
+    }
+
+    fn update(
+        venue: Leg,
+        correlation_id: Option<u64>,
+        order_id: &str,
+        side: OrderSide,
+        state: OrderState,
+        filled: Decimal,
+    ) -> OrderUpdate {
+        OrderUpdate {
+            venue,
+            correlation_id,
+            symbol: PoolSymbol::ETH_USDC,
+            order_id: order_id.to_string(),
+            side,
+            state,
+            filled,
+            avg_price: (filled > Decimal::ZERO).then_some(dec!(2520)),
+            timestamp: "2025-02-12T21:00:01Z".parse().unwrap(),
+        }
+    }
+
+    fn cex_sell(state: OrderState, filled: Decimal) -> OrderUpdate {
+        update(Leg::Cex, Some(7), "11111", OrderSide::Sell, state, filled)
+    }
+
+    #[test]
+    fn test_partial_fills_apply_the_newly_filled_size() {
+        let mut reconciliation = Reconciliation::default();
+        reconciliation.record_opportunity(7, opportunity());
+
+        assert_eq!(
+            reconciliation.apply(&cex_sell(OrderState::Submitted, Decimal::ZERO)),
+            Reconciled::Applied { delta: Decimal::ZERO }
+        );
+        assert_eq!(
+            reconciliation.apply(&cex_sell(OrderState::PartiallyFilled, dec!(0.2))),
+            Reconciled::Applied { delta: dec!(-0.2) }
+        );
+        assert_eq!(
+            reconciliation.apply(&cex_sell(OrderState::Filled, dec!(0.5))),
+            Reconciled::Applied { delta: dec!(-0.3) }
+        );
+        let dex_buy =
+            update(Leg::Dex, Some(7), "0xabc", OrderSide::Buy, OrderState::Filled, dec!(0.5));
+        assert_eq!(reconciliation.apply(&dex_buy), Reconciled::Applied { delta: dec!(0.5) });
+
+        let positions = reconciliation.positions();
+        assert_eq!((positions.cex, positions.dex), (dec!(-0.5), dec!(0.5)));
+        assert_eq!(positions.net(), Decimal::ZERO);
+        assert_eq!(positions.orphan_fills, 0);
+    }
+
+    #[test]
+    fn test_out_of_order_updates_are_stale() {
+        let mut reconciliation = Reconciliation::default();
+        reconciliation.record_opportunity(7, opportunity());
+
+        // The fill overtakes the earlier updates of the order
+        assert_eq!(
+            reconciliation.apply(&cex_sell(OrderState::PartiallyFilled, dec!(0.4))),
+            Reconciled::Applied { delta: dec!(-0.4) }
+        );
+        assert_eq!(
+            reconciliation.apply(&cex_sell(OrderState::Submitted, Decimal::ZERO)),
+            Reconciled::Stale
+        );
+        assert_eq!(
+            reconciliation.apply(&cex_sell(OrderState::PartiallyFilled, dec!(0.2))),
+            Reconciled::Stale
+        );
+        assert_eq!(
+            reconciliation.apply(&cex_sell(OrderState::PartiallyFilled, dec!(0.4))),
+            Reconciled::Stale
+        );
+        // Cancelling the rest of the order fills nothing more
+        assert_eq!(
+            reconciliation.apply(&cex_sell(OrderState::Cancelled, dec!(0.4))),
+            Reconciled::Applied { delta: Decimal::ZERO }
+        );
+        assert_eq!(
+            reconciliation.apply(&cex_sell(OrderState::Filled, dec!(0.5))),
+            Reconciled::Stale
+        );
+        assert_eq!(reconciliation.positions().cex, dec!(-0.4));
+    }
+
+    #[test]
+    fn test_orphan_fills_are_flagged() {
+        let mut reconciliation = Reconciliation::default();
+        reconciliation.record_opportunity(7, opportunity());
+
+        // Unknown correlation id, or none at all
+        let unknown =
+            update(Leg::Cex, Some(8), "22222", OrderSide::Buy, OrderState::Filled, dec!(0.1));
+        assert_eq!(reconciliation.apply(&unknown), Reconciled::Orphan { delta: dec!(0.1) });
+        let manual =
+            update(Leg::Cex, None, "33333", OrderSide::Buy, OrderState::Submitted, Decimal::ZERO);
+        assert_eq!(reconciliation.apply(&manual), Reconciled::Orphan { delta: Decimal::ZERO });
+
+        let positions = reconciliation.positions();
+        assert_eq!(positions.cex, dec!(0.1));
+        assert_eq!(positions.orphan_fills, 1);
+        // The orphan fill is unhedged whatever the opportunity executed
+        assert_eq!(reconciliation.unhedged_excluding(Some(7)), dec!(0.1));
+    }
+
+    #[tokio::test]
+    async fn test_fills_are_accounted_in_pnl() {
+        let clock = MockClock::new("2025-02-12T21:00:01Z".parse().unwrap());
+        let pnl = DailyPnl::new(dec!(100), 0, Arc::new(clock));
+        let mut reconciler = Reconciler::new("ETH-USDC".to_string()).with_pnl(pnl.clone());
+        reconciler.lock().record_opportunity(7, opportunity());
+
+        // Sells 0.2 at 2520 then 0.3 at 2522, averaging 2521.2
+        let partial = cex_sell(OrderState::PartiallyFilled, dec!(0.2));
+        let filled = OrderUpdate {
+            avg_price: Some(dec!(2521.2)),
+            ..cex_sell(OrderState::Filled, dec!(0.5))
+        };
+        // Swaps are priced as quoted
+        let swap = OrderUpdate {
+            avg_price: None,
+            ..update(Leg::Dex, Some(7), "0xabc", OrderSide::Buy, OrderState::Filled, dec!(0.5))
+        };
+        for update in [partial, filled, swap] {
+            reconciler
+                .process_event(InternalEvent::OrderUpdate(update))
+                .await
+                .unwrap();
+        }
+
+        let snapshot = pnl.snapshot();
+        assert_eq!(snapshot.symbols["ETH-USDC"].position, Decimal::ZERO);
+        assert_eq!(snapshot.symbols["ETH-USDC"].realized, dec!(10.6));
+    }
+
+    #[tokio::test]
+    async fn test_unhedged_positions_block_new_legs() {
+        let reconciler = Reconciler::new("ETH-USDC".to_string()).with_max_unhedged(dec!(0.1));
+        let mut engine = reconciler.clone();
+        reconciler.lock().record_opportunity(7, opportunity());
+
+        // The DEX leg of opportunity 7 filled, its CEX leg did not yet
+        let dex_buy =
+            update(Leg::Dex, Some(7), "0xabc", OrderSide::Buy, OrderState::Filled, dec!(0.5));
+        engine
+            .process_event(InternalEvent::OrderUpdate(dex_buy))
+            .await
+            .unwrap();
+        assert_eq!(reconciler.positions().net(), dec!(0.5));
+
+        // Other opportunities are blocked
+        let reason = reconciler.check(&opportunity(), Leg::Dex).unwrap();
+        assert_eq!(reason, "0.5 left unhedged, more than the 0.1 tolerated");
+        assert_eq!(reconciler.lock().unhedged_excluding(Some(7)), Decimal::ZERO);
+
+        engine
+            .process_event(InternalEvent::OrderUpdate(cex_sell(OrderState::Filled, dec!(0.5))))
+            .await
+            .unwrap();
+        assert_eq!(reconciler.check(&opportunity(), Leg::Dex), None);
+    }
+}
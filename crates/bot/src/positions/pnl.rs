@@ -0,0 +1,383 @@
+//! Daily profit and loss, and the daily loss limit.
+//!
+//! The [`DailyPnl`] tracker is shared by all pools. Fills are recorded by the
+//! reconciler when live, or by the paper executor in dry run mode, and
+//! accounted against the net position of their symbol at its average cost:
+//!
+//! - Fills reducing the position realize the difference between their price and
+//!   the average cost.
+//! - The remaining position is marked to the latest CEX price, the unrealized
+//!   profit counting from where it stood when the day started.
+//!
+//! Days start at the configured hour, UTC. The [`PnlEngine`] of every pool
+//! marks the positions and emits a single [`InternalAction::Halt`] per day
+//! once the loss of the day across all symbols exceeds the limit.
+
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+use rust_decimal::{prelude::ToPrimitive, Decimal};
+use serde::{Serialize, Serializer};
+use sikkara_core::{
+    metrics::{Metric, Subsystem, SymbolLabels},
+    AppResult, Clock, Engine,
+};
+use tracing::{error, info};
+
+use crate::engine::{InternalAction, InternalEvent};
+
+/// Gauge of the profit and loss of the day, per symbol and in total.
+pub const DAILY_PNL_METRIC: Metric<SymbolLabels> = Metric::new(Subsystem::Executor, "daily_pnl");
+
+/// Gauge of the drop of the total profit and loss from its peak of the day.
+pub const DAILY_DRAWDOWN_METRIC: Metric<SymbolLabels> =
+    Metric::new(Subsystem::Executor, "daily_drawdown");
+
+/// Symbol label of the metrics summing all symbols.
+const TOTAL_LABEL: &str = "total";
+
+/// Position and profit and loss of a symbol.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct SymbolPnl {
+    /// Net position in the base asset, positive when long
+    pub position: Decimal,
+    /// Average price the position was built at
+    pub avg_cost: Decimal,
+    /// Latest CEX price, none until observed
+    pub mark: Option<Decimal>,
+    /// Profit realized since the start of the day
+    pub realized: Decimal,
+    /// Unrealized profit when the day started
+    #[serde(skip)]
+    unrealized_at_open: Decimal,
+}
+
+impl SymbolPnl {
+    /// Returns the profit of the position at the mark price.
+    pub fn unrealized(&self) -> Decimal {
+        match self.mark {
+            Some(mark) => self.position * (mark - self.avg_cost),
+            None => Decimal::ZERO,
+        }
+    }
+
+    /// Returns the profit, realized and unrealized, since the start of the
+    /// day.
+    pub fn daily(&self) -> Decimal { self.realized + self.unrealized() - self.unrealized_at_open }
+
+    /// Accounts a fill of `size`, negative when selling, at `price`.
+    fn fill(&mut self, size: Decimal, price: Decimal) {
+        if size.is_zero() {
+            return;
+        }
+        let position = self.position + size;
+        if self.position.is_zero() || self.position.is_sign_positive() == size.is_sign_positive() {
+            self.avg_cost =
+                (self.avg_cost * self.position.abs() + price * size.abs()) / position.abs();
+        } else {
+            let closed = size.abs().min(self.position.abs());
+            let direction =
+                if self.position.is_sign_positive() { Decimal::ONE } else { -Decimal::ONE };
+            self.realized += closed * (price - self.avg_cost) * direction;
+            if position.is_zero() {
+                self.avg_cost = Decimal::ZERO;
+            } else if position.is_sign_positive() != self.position.is_sign_positive() {
+                // The fill flipped the position, the rest opening at its price
+                self.avg_cost = price;
+            }
+        }
+        self.position = position;
+    }
+}
+
+/// Profit and loss of the current day across all symbols.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PnlSnapshot {
+    #[serde(serialize_with = "serialize_day")]
+    pub day: Option<jiff::civil::Date>,
+    pub total: Decimal,
+    /// Drop of the total from its peak of the day
+    pub drawdown: Decimal,
+    pub max_daily_loss: Decimal,
+    /// Whether the limit was breached during the day
+    pub breached: bool,
+    pub symbols: BTreeMap<String, SymbolPnl>,
+}
+
+fn serialize_day<S: Serializer>(
+    day: &Option<jiff::civil::Date>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    day.map(|day| day.to_string()).serialize(serializer)
+}
+
+#[derive(Debug, Default)]
+struct PnlBook {
+    day: Option<jiff::civil::Date>,
+    symbols: BTreeMap<String, SymbolPnl>,
+    /// Highest total of the day
+    peak: Decimal,
+    breached: bool,
+}
+
+impl PnlBook {
+    fn total(&self) -> Decimal { self.symbols.values().map(SymbolPnl::daily).sum() }
+
+    /// Starts a new day, keeping the positions.
+    fn roll(&mut self, day: jiff::civil::Date) {
+        if self.day == Some(day) {
+            return;
+        }
+        if let Some(previous) = self.day {
+            info!(day = %previous, pnl = %self.total(), "daily pnl closed");
+        }
+        for symbol in self.symbols.values_mut() {
+            symbol.realized = Decimal::ZERO;
+            symbol.unrealized_at_open = symbol.unrealized();
+        }
+        self.day = Some(day);
+        self.peak = Decimal::ZERO;
+        self.breached = false;
+    }
+}
+
+/// Daily profit and loss tracker shared across pools, see the module
+/// documentation.
+///
+/// Cloning is cheap, all clones share the same book.
+#[derive(Debug, Clone)]
+pub struct DailyPnl {
+    book: Arc<Mutex<PnlBook>>,
+    clock: Arc<dyn Clock>,
+    max_daily_loss: Decimal,
+    day_boundary_hour: u8,
+}
+
+impl DailyPnl {
+    /// Creates a tracker halting once the loss of a day exceeds
+    /// `max_daily_loss`, days starting at `day_boundary_hour` UTC.
+    pub fn new(max_daily_loss: Decimal, day_boundary_hour: u8, clock: Arc<dyn Clock>) -> Self {
+        Self {
+            book: Arc::new(Mutex::new(PnlBook::default())),
+            clock,
+            max_daily_loss: max_daily_loss.abs(),
+            day_boundary_hour: day_boundary_hour % 24,
+        }
+    }
+
+    /// Creates the engine of a pool.
+    pub fn engine(&self, pool: String) -> PnlEngine {
+        PnlEngine { name: format!("pnl_engine_{}", pool), pnl: self.clone() }
+    }
+
+    /// Accounts a fill of `size` of the base asset of `symbol`, negative when
+    /// selling, at `price`.
+    pub fn record_fill(&self, symbol: &str, size: Decimal, price: Decimal) {
+        let mut book = self.lock();
+        let symbol = book.symbols.entry(symbol.to_string()).or_default();
+        symbol.fill(size, price);
+        // Fills mark the position until the next CEX price
+        symbol.mark.get_or_insert(price);
+    }
+
+    /// Marks the position of `symbol` to `price`.
+    pub fn mark(&self, symbol: &str, price: Decimal) {
+        self.lock()
+            .symbols
+            .entry(symbol.to_string())
+            .or_default()
+            .mark = Some(price);
+    }
+
+    /// Returns the reason to halt if the loss of the day just exceeded the
+    /// limit, at most once per day.
+    pub fn check(&self) -> Option<String> {
+        let mut book = self.lock();
+        let total = book.total();
+        book.peak = book.peak.max(total);
+        let drawdown = book.peak - total;
+        for (symbol, pnl) in &book.symbols {
+            DAILY_PNL_METRIC
+                .gauge(SymbolLabels { symbol: symbol.clone() })
+                .set(pnl.daily().to_f64().unwrap_or_default());
+        }
+        let labels = || SymbolLabels { symbol: TOTAL_LABEL.to_string() };
+        DAILY_PNL_METRIC
+            .gauge(labels())
+            .set(total.to_f64().unwrap_or_default());
+        DAILY_DRAWDOWN_METRIC
+            .gauge(labels())
+            .set(drawdown.to_f64().unwrap_or_default());
+
+        if book.breached || total > -self.max_daily_loss {
+            return None;
+        }
+        book.breached = true;
+        let reason = format!(
+            "daily loss limit reached: {} lost, more than the {} tolerated",
+            -total.round_dp(2),
+            self.max_daily_loss
+        );
+        error!(pnl = %total, max_daily_loss = %self.max_daily_loss, "daily loss limit reached");
+        Some(reason)
+    }
+
+    /// Returns the profit and loss of the current day.
+    pub fn snapshot(&self) -> PnlSnapshot {
+        let book = self.lock();
+        let total = book.total();
+        PnlSnapshot {
+            day: book.day,
+            total,
+            drawdown: book.peak.max(total) - total,
+            max_daily_loss: self.max_daily_loss,
+            breached: book.breached,
+            symbols: book.symbols.clone(),
+        }
+    }
+
+    /// Locks the book, first starting a new day if the day boundary was
+    /// crossed.
+    fn lock(&self) -> MutexGuard<'_, PnlBook> {
+        let mut book = self
+            .book
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let shifted = self
+            .clock
+            .now()
+            .checked_sub(jiff::SignedDuration::from_hours(self.day_boundary_hour.into()))
+            .unwrap_or_else(|_| self.clock.now());
+        book.roll(shifted.to_zoned(jiff::tz::TimeZone::UTC).date());
+        book
+    }
+}
+
+/// Engine of a pool marking its positions to the CEX price and halting once
+/// the daily loss limit is exceeded.
+#[derive(Debug, Clone)]
+pub struct PnlEngine {
+    name: String,
+    pnl: DailyPnl,
+}
+
+#[async_trait::async_trait]
+impl Engine<InternalEvent, InternalAction> for PnlEngine {
+    fn id(&self) -> &str { &self.name }
+
+    async fn process_event(&mut self, event: InternalEvent) -> AppResult<Option<InternalAction>> {
+        if let InternalEvent::TickerUpdate(ticker) = &event {
+            self.pnl.mark(&ticker.symbol.to_string(), ticker.price);
+        }
+        Ok(self
+            .pnl
+            .check()
+            .map(|reason| InternalAction::Halt { reason }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use rust_decimal_macros::dec;
+    use sikkara_core::MockClock;
+
+    use super::*;
+    use crate::engine::{Exchange, PoolSymbol, Ticker};
+
+    fn ticker(price: Decimal, clock: &MockClock) -> InternalEvent {
+        InternalEvent::TickerUpdate(Ticker {
+            exchage: Exchange::Coinbase,
+            symbol: PoolSymbol::ETH_USDC,
+            price,
+            timestamp: clock.now(),
+            bid: None,
+            ask: None,
+            bid_size: None,
+            ask_size: None,
+            volume_24h: None,
+        })
+    }
+
+    #[test]
+    fn test_fills_realize_against_the_average_cost() {
+        let mut pnl = SymbolPnl::default();
+        pnl.fill(dec!(0.5), dec!(2500));
+        pnl.fill(dec!(0.5), dec!(2520));
+        assert_eq!((pnl.position, pnl.avg_cost), (dec!(1), dec!(2510)));
+
+        pnl.fill(dec!(-0.4), dec!(2530));
+        assert_eq!(pnl.realized, dec!(8));
+        assert_eq!(pnl.avg_cost, dec!(2510));
+
+        // Selling more than held flips the position short at the fill price
+        pnl.fill(dec!(-1), dec!(2490));
+        assert_eq!(pnl.realized, dec!(-4));
+        assert_eq!((pnl.position, pnl.avg_cost), (dec!(-0.4), dec!(2490)));
+        pnl.mark = Some(dec!(2500));
+        assert_eq!(pnl.unrealized(), dec!(-4));
+    }
+
+    #[tokio::test]
+    async fn test_halts_once_and_resets_next_day() {
+        let clock = MockClock::new("2025-02-12T21:00:00Z".parse().unwrap());
+        let pnl = DailyPnl::new(dec!(100), 0, Arc::new(clock.clone()));
+        let (mut eth, mut btc) =
+            (pnl.engine("ETH-USDC".to_string()), pnl.engine("USDC-cbBTC".to_string()));
+
+        // Hedged fills realize the spread, leaving no position to mark
+        pnl.record_fill("ETH-USDC", dec!(1), dec!(2500));
+        pnl.record_fill("ETH-USDC", dec!(-1), dec!(2520));
+        assert_eq!(eth.process_event(ticker(dec!(2520), &clock)).await.unwrap(), None);
+        assert_eq!(pnl.snapshot().total, dec!(20));
+
+        // An unhedged fill loses as the price drops
+        pnl.record_fill("ETH-USDC", dec!(1), dec!(2520));
+        assert_eq!(eth.process_event(ticker(dec!(2450), &clock)).await.unwrap(), None);
+        let snapshot = pnl.snapshot();
+        assert_eq!(snapshot.total, dec!(-50));
+        assert_eq!(snapshot.drawdown, dec!(70));
+
+        let halt = eth.process_event(ticker(dec!(2390), &clock)).await.unwrap();
+        assert_eq!(
+            halt,
+            Some(InternalAction::Halt {
+                reason: "daily loss limit reached: 110 lost, more than the 100 tolerated"
+                    .to_string()
+            })
+        );
+        // The other pools and further losses do not halt again
+        assert_eq!(btc.process_event(ticker(dec!(2380), &clock)).await.unwrap(), None);
+        assert_eq!(eth.process_event(ticker(dec!(2300), &clock)).await.unwrap(), None);
+        assert!(pnl.snapshot().breached);
+
+        // The next day starts from the position as marked at midnight
+        clock.advance(Duration::from_secs(3 * 3600));
+        assert_eq!(eth.process_event(ticker(dec!(2300), &clock)).await.unwrap(), None);
+        let snapshot = pnl.snapshot();
+        assert_eq!(snapshot.day, Some("2025-02-13".parse().unwrap()));
+        assert_eq!((snapshot.total, snapshot.drawdown), (Decimal::ZERO, Decimal::ZERO));
+        assert!(!snapshot.breached);
+        assert_eq!(snapshot.symbols["ETH-USDC"].position, dec!(1));
+
+        // And halts again once it loses more than the limit itself
+        assert_eq!(eth.process_event(ticker(dec!(2210), &clock)).await.unwrap(), None);
+        assert!(eth
+            .process_event(ticker(dec!(2199), &clock))
+            .await
+            .unwrap()
+            .is_some());
+    }
+
+    #[test]
+    fn test_day_boundary_hour() {
+        let clock = MockClock::new("2025-02-13T05:59:00Z".parse().unwrap());
+        let pnl = DailyPnl::new(dec!(100), 6, Arc::new(clock.clone()));
+        assert_eq!(pnl.snapshot().day, Some("2025-02-12".parse().unwrap()));
+        clock.advance(Duration::from_secs(60));
+        assert_eq!(pnl.snapshot().day, Some("2025-02-13".parse().unwrap()));
+    }
+}
@@ -0,0 +1,463 @@
+//! Hourly summary reports.
+//!
+//! A [`ReportEngine`] is registered with every per-pool
+//! [`sikkara_core::EngineRunner`] alongside the arbitrage engine, both as an
+//! engine (to observe prices and feed health) and as an executor (to observe
+//! opportunities). All of them feed a shared [`HourlyReporter`], which at each
+//! hour boundary writes `<output_dir>/YYYY-MM-DDTHH.json` covering all symbols
+//! and logs a one line digest. A partial report is flushed once the last engine
+//! stops.
+
+use std::{
+    collections::BTreeMap,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
+
+use rust_decimal::Decimal;
+use serde::Serialize;
+use sikkara_core::{AppResult, Clock, Engine, Executor};
+use tracing::{error, info};
+
+mod stats;
+pub use stats::{SymbolReport, SymbolStats};
+
+use crate::{
+    config::MarketMakingConfig,
+    engine::{FeedState, InternalAction, InternalEvent, PoolSymbol, PriceSource},
+    strategy::MarketMakingSimulator,
+};
+
+/// Length of a report period.
+const REPORT_PERIOD_SECS: i64 = 60 * 60;
+
+/// Report over all symbols for a single period.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct HourlyReport {
+    #[serde(with = "sikkara_core::timestamp_with_tz_serializer")]
+    pub period_start: jiff::Timestamp,
+    #[serde(with = "sikkara_core::timestamp_with_tz_serializer")]
+    pub period_end: jiff::Timestamp,
+    /// True if the report was flushed before the end of the hour
+    pub partial: bool,
+    pub symbols: BTreeMap<String, SymbolReport>,
+}
+
+impl HourlyReport {
+    /// Returns the file name of the report, e.g. `2025-02-12T21.json`.
+    pub fn file_name(&self) -> String {
+        format!("{}.json", self.period_start.strftime("%Y-%m-%dT%H"))
+    }
+
+    /// Returns a human readable one line digest of the report.
+    pub fn digest(&self) -> String {
+        let symbols = self
+            .symbols
+            .iter()
+            .map(|(symbol, report)| {
+                format!(
+                    "{}: {} opps (${:.2}), mean spread {} bps, uptime cex {}% dex {}%",
+                    symbol,
+                    report.opportunities.count,
+                    report.opportunities.hypothetical_profit,
+                    report
+                        .spread_bps
+                        .mean_bps
+                        .map_or("n/a".to_string(), |mean| mean.to_string()),
+                    report.feed_uptime_pct.cex,
+                    report.feed_uptime_pct.dex
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("; ");
+        format!("📝 Report {} | {}", self.period_start.strftime("%Y-%m-%dT%H"), symbols)
+    }
+}
+
+#[derive(Debug)]
+struct ReporterState {
+    period_start: jiff::Timestamp,
+    symbols: BTreeMap<String, SymbolStats>,
+    /// Number of engines which have not stopped yet
+    active_engines: usize,
+}
+
+impl ReporterState {
+    /// Closes the current period at `end`, starting the next one at
+    /// `next_start`.
+    fn roll(&mut self, end: jiff::Timestamp, next_start: jiff::Timestamp) -> HourlyReport {
+        let period_start = self.period_start;
+        let symbols = self
+            .symbols
+            .iter_mut()
+            .map(|(symbol, stats)| (symbol.clone(), stats.roll(period_start, end, next_start)))
+            .collect();
+        self.period_start = next_start;
+        HourlyReport { period_start, period_end: end, partial: false, symbols }
+    }
+}
+
+/// Accumulates statistics of all symbols and writes them out hourly.
+///
+/// Cloning is cheap, all clones share the same state.
+#[derive(Debug, Clone)]
+pub struct HourlyReporter {
+    state: Arc<Mutex<ReporterState>>,
+    clock: Arc<dyn Clock>,
+    output_dir: PathBuf,
+}
+
+impl HourlyReporter {
+    pub fn new(output_dir: impl Into<PathBuf>, clock: Arc<dyn Clock>) -> Self {
+        let state = ReporterState {
+            period_start: hour_start(clock.now()),
+            symbols: BTreeMap::new(),
+            active_engines: 0,
+        };
+        Self { state: Arc::new(Mutex::new(state)), clock, output_dir: output_dir.into() }
+    }
+
+    /// Creates the report engine of a pool.
+    pub fn engine(&self, symbol: PoolSymbol, config: MarketMakingConfig) -> ReportEngine {
+        self.lock().active_engines += 1;
+        ReportEngine {
+            name: format!("report_engine_{}", symbol),
+            simulator: MarketMakingSimulator::new(symbol.clone(), config),
+            symbol,
+            reporter: self.clone(),
+            last_cex_price: None,
+            last_dex_price: None,
+        }
+    }
+
+    /// Applies an update to the statistics of a symbol, first writing out the
+    /// report of the previous period if an hour boundary was crossed.
+    async fn update(
+        &self,
+        symbol: &PoolSymbol,
+        update: impl FnOnce(&mut SymbolStats, jiff::Timestamp),
+    ) -> AppResult<()> {
+        let report = {
+            let mut state = self.lock();
+            let now = self.clock.now();
+            let period_end = period_end(state.period_start);
+            let report = (now >= period_end).then(|| state.roll(period_end, hour_start(now)));
+            update(state.symbols.entry(symbol.to_string()).or_default(), now);
+            report
+        };
+        match report {
+            Some(report) => self.write(&report).await,
+            None => Ok(()),
+        }
+    }
+
+    /// Marks an engine as stopped, flushing a partial report once all engines
+    /// have stopped.
+    async fn stop(&self) -> AppResult<()> {
+        let report = {
+            let mut state = self.lock();
+            state.active_engines = state.active_engines.saturating_sub(1);
+            if state.active_engines > 0 {
+                return Ok(());
+            }
+            let now = self.clock.now();
+            let period_end = period_end(state.period_start);
+            if now >= period_end {
+                state.roll(period_end, hour_start(now))
+            } else {
+                HourlyReport { partial: true, ..state.roll(now, now) }
+            }
+        };
+        self.write(&report).await
+    }
+
+    /// Writes the report into the output directory and logs its digest.
+    async fn write(&self, report: &HourlyReport) -> AppResult<()> {
+        let path = self.output_dir.join(report.file_name());
+        tokio::fs::create_dir_all(&self.output_dir).await?;
+        tokio::fs::write(&path, serde_json::to_vec_pretty(report)?).await?;
+        info!("{} | written to {}", report.digest(), path.display());
+        Ok(())
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, ReporterState> {
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// Returns the start of the hour containing `timestamp`.
+fn hour_start(timestamp: jiff::Timestamp) -> jiff::Timestamp {
+    let second = timestamp.as_second().div_euclid(REPORT_PERIOD_SECS) * REPORT_PERIOD_SECS;
+    jiff::Timestamp::from_second(second).expect("hour start is in range")
+}
+
+fn period_end(period_start: jiff::Timestamp) -> jiff::Timestamp {
+    jiff::Timestamp::from_second(period_start.as_second() + REPORT_PERIOD_SECS)
+        .expect("period end is in range")
+}
+
+/// Feeds the prices, feed health and opportunities of a pool into the shared
+/// [`HourlyReporter`].
+#[derive(Debug, Clone)]
+pub struct ReportEngine {
+    name: String,
+    symbol: PoolSymbol,
+    simulator: MarketMakingSimulator,
+    reporter: HourlyReporter,
+    last_cex_price: Option<Decimal>,
+    last_dex_price: Option<Decimal>,
+}
+
+impl ReportEngine {
+    async fn record_price(&mut self, source: PriceSource, price: Decimal) -> AppResult<()> {
+        match source {
+            PriceSource::Cex => {
+                self.last_cex_price = Some(price);
+                self.simulator.record_cex_price(price);
+            },
+            PriceSource::Dex => self.last_dex_price = Some(price),
+        }
+        let paired = self.last_cex_price.zip(self.last_dex_price);
+        let condition = paired.map(|(cex_price, dex_price)| {
+            let spread_bps = (dex_price - cex_price).abs() / cex_price * Decimal::from(10_000);
+            let condition = self
+                .simulator
+                .assess_market_conditions(cex_price, Some(dex_price));
+            (spread_bps, condition)
+        });
+        self.reporter
+            .update(&self.symbol, |stats, now| {
+                stats.record_feed(source, true, now);
+                if let Some((spread_bps, condition)) = condition {
+                    stats.record_spread(spread_bps);
+                    stats.record_condition(condition, now);
+                }
+            })
+            .await
+    }
+}
+
+#[async_trait::async_trait]
+impl Engine<InternalEvent, InternalAction> for ReportEngine {
+    fn id(&self) -> &str { &self.name }
+
+    async fn process_event(&mut self, event: InternalEvent) -> AppResult<Option<InternalAction>> {
+        let result = match event {
+            InternalEvent::TickerUpdate(ticker) => {
+                self.record_price(PriceSource::Cex, ticker.price).await
+            },
+            InternalEvent::PoolPriceUpdate(update) => {
+                self.record_price(PriceSource::Dex, update.price).await
+            },
+            InternalEvent::FeedStatus(status) => {
+                self.reporter
+                    .update(&self.symbol, |stats, now| {
+                        stats.record_feed(status.source, status.state == FeedState::Up, now)
+                    })
+                    .await
+            },
+            InternalEvent::Execution(_)
+            | InternalEvent::BalanceUpdate(_)
+            | InternalEvent::OrderUpdate(_)
+            | InternalEvent::OwnFill(_) => Ok(()),
+        };
+        if let Err(e) = result {
+            error!("failed to write report: {}", e);
+        }
+        Ok(None)
+    }
+
+    async fn on_stop(&mut self) -> AppResult<()> { self.reporter.stop().await }
+}
+
+#[async_trait::async_trait]
+impl Executor<InternalAction> for ReportEngine {
+    fn id(&self) -> &str { &self.name }
+
+    async fn execute_actions(&mut self, actions: Vec<InternalAction>) -> AppResult<()> {
+        for action in actions {
+            match action {
+                InternalAction::Opportunity(opportunity) => {
+                    let profit = (opportunity.cex_price - opportunity.dex_price).abs();
+                    self.reporter
+                        .update(&opportunity.symbol, |stats, _| stats.record_opportunity(profit))
+                        .await?;
+                },
+                InternalAction::Suppressed(_) | InternalAction::Halt { .. } => {},
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{path::Path, time::Duration};
+
+    use rust_decimal_macros::dec;
+    use serde_json::json;
+    use sikkara_core::MockClock;
+
+    use super::*;
+    use crate::engine::{
+        ArbitrageDirection, ArbitrageOpportunity, Exchange, FeedStatus, PoolPriceUpdate, Ticker,
+    };
+
+    fn config() -> MarketMakingConfig {
+        MarketMakingConfig {
+            base_spread_bps: 50,
+            max_spread_bps: 100,
+            min_spread_bps: 10,
+            gas_price: dec!(0.5),
+            arbitrage_tighten_factor: dec!(0.7),
+            arbitrage_widen_factor: dec!(1.3),
+            arbitrage_threshold_bps: 30,
+            gas_units_per_trade: 150_000,
+            token_price_usd: Decimal::ZERO,
+            volatility_window: 20,
+            volatility_threshold: dec!(0.02),
+            volatility_widen_factor: None,
+            use_twap: false,
+            twap_window_secs: 60,
+            dex_ema_alpha: None,
+            export: None,
+            acceptable_price_impact_bps: 50,
+            capital_usd: Decimal::ZERO,
+            fill_simulation: None,
+            inventory_skew_bps: 0,
+        }
+    }
+
+    fn ticker(price: Decimal) -> InternalEvent {
+        InternalEvent::TickerUpdate(Ticker {
+            exchage: Exchange::Coinbase,
+            symbol: PoolSymbol::ETH_USDC,
+            price,
+            timestamp: jiff::Timestamp::UNIX_EPOCH,
+            bid: None,
+            ask: None,
+            bid_size: None,
+            ask_size: None,
+            volume_24h: None,
+        })
+    }
+
+    fn pool_update(price: Decimal) -> InternalEvent {
+        InternalEvent::PoolPriceUpdate(PoolPriceUpdate {
+            symbol: PoolSymbol::ETH_USDC,
+            price,
+            timestamp: jiff::Timestamp::UNIX_EPOCH,
+            block_number: None,
+            state: None,
+            effective_buy_price: None,
+            effective_sell_price: None,
+        })
+    }
+
+    fn opportunity() -> InternalAction {
+        InternalAction::Opportunity(ArbitrageOpportunity {
+            symbol: PoolSymbol::ETH_USDC,
+            direction: ArbitrageDirection::BuyDexSellCex,
+            cex_price: dec!(2510),
+            dex_price: dec!(2500),
+            net_bps: dec!(40),
+            recommended_size: Decimal::ZERO,
+            detected_at: jiff::Timestamp::UNIX_EPOCH,
+            cex_quote_at: jiff::Timestamp::UNIX_EPOCH,
+            dex_quote_at: jiff::Timestamp::UNIX_EPOCH,
+        })
+    }
+
+    fn read_report(dir: &Path, name: &str) -> serde_json::Value {
+        serde_json::from_slice(&std::fs::read(dir.join(name)).unwrap()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_report_is_written_at_hour_boundary() {
+        let dir = tempfile::tempdir().unwrap();
+        let clock = MockClock::new("2025-02-12T21:30:00Z".parse().unwrap());
+        let reporter = HourlyReporter::new(dir.path(), Arc::new(clock.clone()));
+        let mut engine = reporter.engine(PoolSymbol::ETH_USDC, config());
+
+        // Normal market for 10 minutes
+        engine.process_event(ticker(dec!(2500))).await.unwrap();
+        engine.process_event(pool_update(dec!(2501))).await.unwrap();
+        clock.advance(Duration::from_secs(10 * 60));
+
+        // Arbitrage market until the DEX feed goes down 6 minutes later
+        engine.process_event(pool_update(dec!(2510))).await.unwrap();
+        engine.execute_actions(vec![opportunity()]).await.unwrap();
+        clock.advance(Duration::from_secs(6 * 60));
+        engine
+            .process_event(InternalEvent::FeedStatus(FeedStatus {
+                feed: "pool_feed_collector_ETH-USDC".to_string(),
+                source: PriceSource::Dex,
+                symbol: PoolSymbol::ETH_USDC,
+                state: FeedState::Down,
+                reason: "pool feed stream ended".to_string(),
+                timestamp: jiff::Timestamp::UNIX_EPOCH,
+            }))
+            .await
+            .unwrap();
+        assert!(!dir.path().join("2025-02-12T21.json").exists());
+
+        // The first event past the boundary closes the hour
+        clock.set("2025-02-12T22:00:30Z".parse().unwrap());
+        engine.process_event(ticker(dec!(2500))).await.unwrap();
+
+        assert_eq!(
+            read_report(dir.path(), "2025-02-12T21.json"),
+            json!({
+                "period_start": "2025-02-12T21:00:00Z",
+                "period_end": "2025-02-12T22:00:00Z",
+                "partial": false,
+                "symbols": {
+                    "ETH-USDC": {
+                        "spread_bps": {
+                            "samples": 2,
+                            "min_bps": "4",
+                            "max_bps": "40",
+                            "mean_bps": "22",
+                            "buckets": [
+                                { "upper_bps": 1, "count": 0 },
+                                { "upper_bps": 5, "count": 1 },
+                                { "upper_bps": 10, "count": 0 },
+                                { "upper_bps": 25, "count": 0 },
+                                { "upper_bps": 50, "count": 1 },
+                                { "upper_bps": 100, "count": 0 },
+                                { "upper_bps": null, "count": 0 }
+                            ]
+                        },
+                        "opportunities": { "count": 1, "hypothetical_profit": "10" },
+                        "feed_uptime_pct": { "cex": "50", "dex": "26.67" },
+                        "condition_secs": { "normal": 600, "volatile": 0, "arbitrage": 1200 }
+                    }
+                }
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_partial_report_is_flushed_when_last_engine_stops() {
+        let dir = tempfile::tempdir().unwrap();
+        let clock = MockClock::new("2025-02-12T22:00:00Z".parse().unwrap());
+        let reporter = HourlyReporter::new(dir.path(), Arc::new(clock.clone()));
+        let mut first = reporter.engine(PoolSymbol::ETH_USDC, config());
+        let mut second = reporter.engine(PoolSymbol::ETH_USDT, config());
+
+        first.process_event(ticker(dec!(2500))).await.unwrap();
+        clock.advance(Duration::from_secs(15 * 60));
+
+        first.on_stop().await.unwrap();
+        assert!(!dir.path().join("2025-02-12T22.json").exists());
+        second.on_stop().await.unwrap();
+
+        let report = read_report(dir.path(), "2025-02-12T22.json");
+        assert_eq!(report["partial"], json!(true));
+        assert_eq!(report["period_end"], json!("2025-02-12T22:15:00Z"));
+        assert_eq!(report["symbols"]["ETH-USDC"]["feed_uptime_pct"]["cex"], json!("100"));
+        assert_eq!(report["symbols"]["ETH-USDC"]["spread_bps"]["samples"], json!(0));
+    }
+}
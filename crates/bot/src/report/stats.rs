@@ -0,0 +1,240 @@
+use std::{collections::HashMap, time::Duration};
+
+use rust_decimal::Decimal;
+use serde::Serialize;
+
+use crate::engine::{MarketCondition, PriceSource};
+
+/// Inclusive upper bounds of the spread distribution buckets in basis points,
+/// followed by an unbounded bucket.
+const SPREAD_BUCKETS_BPS: [u32; 6] = [1, 5, 10, 25, 50, 100];
+
+/// Distribution of the CEX/DEX spread within a report period.
+#[derive(Debug, Clone, Default)]
+struct SpreadDistribution {
+    samples: u64,
+    sum_bps: Decimal,
+    min_bps: Option<Decimal>,
+    max_bps: Option<Decimal>,
+    buckets: [u64; SPREAD_BUCKETS_BPS.len() + 1],
+}
+
+impl SpreadDistribution {
+    fn record(&mut self, spread_bps: Decimal) {
+        self.samples += 1;
+        self.sum_bps += spread_bps;
+        self.min_bps = Some(self.min_bps.map_or(spread_bps, |min| min.min(spread_bps)));
+        self.max_bps = Some(self.max_bps.map_or(spread_bps, |max| max.max(spread_bps)));
+        let bucket = SPREAD_BUCKETS_BPS
+            .iter()
+            .position(|upper| spread_bps <= Decimal::from(*upper))
+            .unwrap_or(SPREAD_BUCKETS_BPS.len());
+        self.buckets[bucket] += 1;
+    }
+
+    fn report(&self) -> SpreadReport {
+        let mean_bps = match self.samples {
+            0 => None,
+            samples => Some(
+                (self.sum_bps / Decimal::from(samples))
+                    .round_dp(2)
+                    .normalize(),
+            ),
+        };
+        let buckets = self
+            .buckets
+            .iter()
+            .enumerate()
+            .map(|(i, count)| SpreadBucket {
+                upper_bps: SPREAD_BUCKETS_BPS.get(i).copied(),
+                count: *count,
+            })
+            .collect();
+        SpreadReport {
+            samples: self.samples,
+            min_bps: self.min_bps.map(|min| min.round_dp(2).normalize()),
+            max_bps: self.max_bps.map(|max| max.round_dp(2).normalize()),
+            mean_bps,
+            buckets,
+        }
+    }
+}
+
+/// Time spent in each state of a categorical value, e.g. a feed being up or a
+/// market condition.
+#[derive(Debug, Clone)]
+struct StateDurations<S> {
+    current: Option<(S, jiff::Timestamp)>,
+    totals: HashMap<S, Duration>,
+}
+
+impl<S> Default for StateDurations<S> {
+    fn default() -> Self { Self { current: None, totals: HashMap::new() } }
+}
+
+impl<S> StateDurations<S>
+where
+    S: Copy + Eq + std::hash::Hash,
+{
+    /// Enters the given state at `now`, accounting the time spent in the
+    /// previous one.
+    fn enter(&mut self, state: S, now: jiff::Timestamp) {
+        if let Some((current, _)) = self.current {
+            if current == state {
+                return;
+            }
+        }
+        self.close(now);
+        self.current = Some((state, now));
+    }
+
+    /// Accounts the time spent in the current state until `end`, staying in it.
+    fn close(&mut self, end: jiff::Timestamp) {
+        if let Some((state, since)) = self.current.as_mut() {
+            let elapsed = end.duration_since(*since).unsigned_abs();
+            *self.totals.entry(*state).or_default() += elapsed;
+            *since = end;
+        }
+    }
+
+    /// Restarts the current state at `start`, dropping any accounted totals.
+    fn restart(&mut self, start: jiff::Timestamp) {
+        self.totals.clear();
+        if let Some((_, since)) = self.current.as_mut() {
+            *since = start;
+        }
+    }
+
+    fn total(&self, state: S) -> Duration { self.totals.get(&state).copied().unwrap_or_default() }
+}
+
+/// Statistics of a single symbol accumulated over a report period.
+#[derive(Debug, Clone, Default)]
+pub struct SymbolStats {
+    spread: SpreadDistribution,
+    opportunities: u64,
+    hypothetical_profit: Decimal,
+    cex_feed: StateDurations<bool>,
+    dex_feed: StateDurations<bool>,
+    conditions: StateDurations<MarketCondition>,
+}
+
+impl SymbolStats {
+    /// Records the spread between the paired CEX and DEX prices.
+    pub fn record_spread(&mut self, spread_bps: Decimal) { self.spread.record(spread_bps); }
+
+    /// Records an opportunity with the profit of trading one unit of the base
+    /// asset.
+    pub fn record_opportunity(&mut self, profit: Decimal) {
+        self.opportunities += 1;
+        self.hypothetical_profit += profit;
+    }
+
+    /// Records a feed coming up or going down at `now`.
+    pub fn record_feed(&mut self, source: PriceSource, up: bool, now: jiff::Timestamp) {
+        match source {
+            PriceSource::Cex => self.cex_feed.enter(up, now),
+            PriceSource::Dex => self.dex_feed.enter(up, now),
+        }
+    }
+
+    /// Records the market condition assessed at `now`.
+    pub fn record_condition(&mut self, condition: MarketCondition, now: jiff::Timestamp) {
+        self.conditions.enter(condition, now);
+    }
+
+    /// Closes the period `[start, end)`, returning its report and resetting
+    /// the statistics for the period starting at `next_start`. Feed and
+    /// condition states carry over into the next period.
+    pub fn roll(
+        &mut self,
+        start: jiff::Timestamp,
+        end: jiff::Timestamp,
+        next_start: jiff::Timestamp,
+    ) -> SymbolReport {
+        self.cex_feed.close(end);
+        self.dex_feed.close(end);
+        self.conditions.close(end);
+
+        let period = end.duration_since(start).unsigned_abs();
+        let report = SymbolReport {
+            spread_bps: self.spread.report(),
+            opportunities: OpportunityReport {
+                count: self.opportunities,
+                hypothetical_profit: self.hypothetical_profit.round_dp(2).normalize(),
+            },
+            feed_uptime_pct: FeedUptimeReport {
+                cex: uptime_pct(self.cex_feed.total(true), period),
+                dex: uptime_pct(self.dex_feed.total(true), period),
+            },
+            condition_secs: ConditionReport {
+                normal: self.conditions.total(MarketCondition::Normal).as_secs(),
+                volatile: self.conditions.total(MarketCondition::Volatile).as_secs(),
+                arbitrage: self.conditions.total(MarketCondition::Arbitrage).as_secs(),
+            },
+        };
+
+        self.spread = SpreadDistribution::default();
+        self.opportunities = 0;
+        self.hypothetical_profit = Decimal::ZERO;
+        self.cex_feed.restart(next_start);
+        self.dex_feed.restart(next_start);
+        self.conditions.restart(next_start);
+        report
+    }
+}
+
+fn uptime_pct(up: Duration, period: Duration) -> Decimal {
+    if period.is_zero() {
+        return Decimal::ZERO;
+    }
+    let pct = Decimal::from(up.as_millis() as u64) * Decimal::ONE_HUNDRED
+        / Decimal::from(period.as_millis() as u64);
+    pct.round_dp(2).normalize()
+}
+
+/// Report of a single symbol over a report period.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SymbolReport {
+    pub spread_bps: SpreadReport,
+    pub opportunities: OpportunityReport,
+    pub feed_uptime_pct: FeedUptimeReport,
+    pub condition_secs: ConditionReport,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SpreadReport {
+    pub samples: u64,
+    pub min_bps: Option<Decimal>,
+    pub max_bps: Option<Decimal>,
+    pub mean_bps: Option<Decimal>,
+    pub buckets: Vec<SpreadBucket>,
+}
+
+/// Number of spreads up to `upper_bps`, unbounded if `None`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SpreadBucket {
+    pub upper_bps: Option<u32>,
+    pub count: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct OpportunityReport {
+    pub count: u64,
+    /// Sum of the price differences of all opportunities, i.e. the profit of
+    /// trading one unit of the base asset on each of them
+    pub hypothetical_profit: Decimal,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FeedUptimeReport {
+    pub cex: Decimal,
+    pub dex: Decimal,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ConditionReport {
+    pub normal: u64,
+    pub volatile: u64,
+    pub arbitrage: u64,
+}
@@ -1,26 +1,71 @@
-use std::sync::Arc;
+use std::{collections::HashMap, env, sync::Arc, time::Duration};
 
 use alloy::{
     contract,
-    primitives::Address,
-    providers::ProviderBuilder,
+    primitives::{Address, B256, U256},
+    providers::{DynProvider, Provider, ProviderBuilder},
     transports::{http::reqwest::Url, ws},
 };
 use futures::future::join_all;
-use sikkara_adapters::{CoinbaseWsClient, UniswapV4StateViewManager};
-use sikkara_core::{AppResult, Collector, EngineRunner, ExponentialBackoff, Runner};
+use rust_decimal::Decimal;
+use sikkara_adapters::{
+    signer_from_key, signer_from_keystore, ApprovalManager, BinanceWsClient, CoinbaseCredentials,
+    CoinbaseTradeClient, CoinbaseWsClient, PoolSlotDataStream, PrivateRelay, ReceiptMonitor,
+    TokenApproval, TxSubmitter, UniswapV3StateViewManager, UniswapV4MultiPoolWatcher,
+    UniswapV4Quoter, UniswapV4StateViewManager, UniversalRouter, WatchedPool,
+};
+use sikkara_core::{
+    metrics::RpcMetrics, AppError, AppResult, Collector, CollectorLiveness, EmaCalculator,
+    EngineRunner, Runner, Secret, SystemClock,
+};
 use sikkara_wsclient::WsConsumer;
-use tokio::sync::{broadcast, mpsc};
+use tokio::{
+    sync::{broadcast, mpsc, watch},
+    task::JoinHandle,
+};
+use tracing::{info, info_span, warn, Instrument, Span};
 
 use crate::{
-    collectors::{PoolFeedCollector, PriceFeedCollector},
-    config::{BotConfig, CexConfig, PoolConfig},
-    engine::{ArbitrageEngine, InternalAction, InternalEvent, Pool},
-    strategy::LoggingBotStrategy,
+    collectors::{
+        BalanceCollector, BalanceToken, ExecutionEventCollector, HeartbeatMonitor,
+        OwnFillCollector, PoolFeedCollector, PriceFeedCollector, SnapshotRecorder, SnapshotWriter,
+    },
+    config::{
+        BalanceConfig, BotConfig, CexConfig, CoinbaseTradeConfig, ExecutionConfig, ExecutionMode,
+        MarketMakingConfig, PoolConfig, PoolTrigger, SignerConfig, SubmissionConfig, TokenConfig,
+    },
+    engine::{
+        AggregatedPriceFeed, ArbitrageEngine, DivergenceTracker, InternalAction, InternalEvent,
+        PollPolicy, Pool, PoolSymbol, PriceFeed, PriceHistoryHandle, Token, UniswapV3PoolFeed,
+        UniswapV4PoolFeed, UniswapV4WatchedPoolFeed,
+    },
+    event_stream::{EventStreamHub, EventStreamPublisher, EventStreamServer},
+    executors::{
+        to_units, AlertDispatcher, AlertExecutor, AuditExecutor, AuditLog, CexExecutor,
+        DexExecutor, ExecutionPlanner, HindsightExecutor, PaperExecutor, TelegramClient,
+        TelegramCommandHandler,
+    },
+    halt::{HaltGuard, HaltState, HealthServer},
+    positions::{BalanceBook, DailyPnl, Reconciler},
+    report::HourlyReporter,
+    strategy::{LoggingBotStrategy, RoundTripFees, SimulationCsvWriter},
 };
 
-#[derive(Debug, Clone)]
-pub struct BotRunner {}
+#[derive(Debug, Clone, Default)]
+pub struct BotRunner {
+    /// Configurations reloaded while running, if reloadable
+    config_reloads: Option<broadcast::Sender<BotConfig>>,
+}
+
+impl BotRunner {
+    /// Applies the market making parameters of the configurations broadcast
+    /// through `reloads` to the running strategies, without reconnecting any
+    /// feed.
+    pub fn with_config_reloads(mut self, reloads: broadcast::Sender<BotConfig>) -> Self {
+        self.config_reloads = Some(reloads);
+        self
+    }
+}
 
 #[async_trait::async_trait]
 impl Runner<BotConfig> for BotRunner {
@@ -29,89 +74,485 @@ impl Runner<BotConfig> for BotRunner {
     // TODO: Break this funtion down. Hard to read.
     async fn run(
         self,
-        parameters: BotConfig,
+        mut parameters: BotConfig,
         shutdown: tokio_util::sync::CancellationToken,
     ) -> AppResult<()> {
-        let (ws_message_sender, ws_message_receiver) = mpsc::channel(100);
-        let (message_broadcaster, _) = broadcast::channel(100);
+        parameters.validate()?;
+        // Reloaded configurations are compared to the configured one, whose
+        // decimals are not resolved yet
+        let configured = parameters.clone();
+
+        // Read the decimals of the tokens configured as 0 before anything converts
+        // amounts
+        resolve_token_decimals(&mut parameters.pools).await?;
+
+        // Paper trades are only logged, nothing may be submitted alongside
+        let live = parameters
+            .execution
+            .as_ref()
+            .is_some_and(|config| config.mode == ExecutionMode::Live);
+        if parameters.paper_trading && live {
+            return Err(AppError::ConfigError(
+                "paper trading cannot be combined with live execution".to_string(),
+            )
+            .into());
+        }
+
+        // Setup the transaction submission first, refusing to go live without a signer
+        let submitter = match &parameters.execution {
+            Some(config) => tx_submitter(config)?.map(Arc::new),
+            None => None,
+        };
+        let cex_trade_client = match &parameters.execution {
+            Some(config) => cex_trade_client(config)?,
+            None => None,
+        };
+        let dex_router = match &parameters.execution {
+            Some(config) => dex_router(config)?,
+            None => None,
+        };
 
-        let client = match &parameters.cex {
-            CexConfig::Coinbase { ws_url } => {
-                CoinbaseWsClient::new(ws_url.clone(), ws_message_sender, message_broadcaster)
+        // Ensure the tokens swapped on the DEX are approved before going live
+        if let (Some(config), Some(submitter), Some(router)) =
+            (&parameters.execution, &submitter, dex_router)
+        {
+            if let Some(approvals) = approval_manager(config, submitter.clone(), router)? {
+                approvals.ensure().await?;
+            }
+        }
+
+        // One client per exchange, each feeding every pool
+        let mut runner_tasks = Vec::with_capacity(parameters.pools.len() + parameters.cex.len());
+        let mut clients = Vec::with_capacity(parameters.cex.len());
+        for cex in &parameters.cex {
+            let (client, consumer) = cex_client(cex, shutdown.child_token())?;
+            clients.push((client, cex.clone()));
+            runner_tasks.push(consumer);
+        }
+
+        // Watch the receipts of submitted swaps, shared across pools like the nonces
+        let execution_dex = parameters
+            .execution
+            .as_ref()
+            .and_then(|config| config.dex.as_ref());
+        let receipt_monitor = match (execution_dex, &submitter, dex_router) {
+            (Some(dex), Some(submitter), Some(_)) => {
+                let monitor = Arc::new(ReceiptMonitor::new(
+                    submitter.provider().clone(),
+                    submitter.address(),
+                    dex.confirmations,
+                    dex.confirm_timeout_blocks,
+                ));
+                runner_tasks.push(monitor.clone().spawn(
+                    Duration::from_millis(dex.receipt_poll_interval_ms),
+                    shutdown.child_token(),
+                ));
+                Some(monitor)
+            },
+            _ => None,
+        };
+
+        // Setup the optional external event stream
+        let event_stream_hub = match &parameters.event_stream {
+            Some(config) => {
+                let hub = EventStreamHub::new(config.client_queue_size);
+                let server = EventStreamServer::bind(&config.listen_addr, hub.clone()).await?;
+                runner_tasks.push(server.spawn(shutdown.child_token()));
+                Some(hub)
+            },
+            None => None,
+        };
+
+        // Setup the optional alerting, shared across pools for rate limiting and muting
+        let alert_dispatcher = match &parameters.alerts {
+            Some(config) => {
+                let mut dispatcher = AlertDispatcher::new(config.clone());
+                if let Some(telegram) = &config.telegram {
+                    let token = env::var(&telegram.bot_token_env).map_err(|_| {
+                        AppError::ConfigError(format!(
+                            "telegram bot token not found in environment variable {}",
+                            telegram.bot_token_env
+                        ))
+                    })?;
+                    let client = TelegramClient::new(&telegram.api_url, &token, telegram.chat_id);
+                    dispatcher = dispatcher.with_telegram(client.clone());
+                    let commands = TelegramCommandHandler::new(
+                        client,
+                        dispatcher.clone(),
+                        telegram.poll_timeout_secs,
+                        config.retry_min_delay_secs,
+                        config.retry_max_delay_secs,
+                    );
+                    runner_tasks.push(commands.spawn(shutdown.child_token()));
+                }
+                Some(dispatcher)
             },
+            None => None,
         };
-        let mut consumer = WsConsumer {
-            ws_url: client.ws_url().to_string(),
-            callback: client.clone(),
-            heartbeat_millis: 5000,
-            backoff: ExponentialBackoff::default(),
-            receiver: ws_message_receiver,
+
+        // Setup the kill switch shared across pools, cleared by operators only
+        let mut halt = HaltState::new();
+        if let Some(dispatcher) = &alert_dispatcher {
+            halt = halt.with_alerts(dispatcher.clone());
+        }
+        runner_tasks.push(halt.spawn_signal_handler(shutdown.child_token())?);
+
+        // Setup the optional daily loss limit, tracked across pools
+        let daily_pnl = parameters.risk.as_ref().map(|config| {
+            DailyPnl::new(config.max_daily_loss, config.day_boundary_hour, Arc::new(SystemClock))
+        });
+        if let Some(config) = &parameters.health {
+            let mut server = HealthServer::bind(&config.listen_addr, halt.clone()).await?;
+            if let Some(pnl) = &daily_pnl {
+                server = server.with_pnl(pnl.clone());
+            }
+            runner_tasks.push(server.spawn(shutdown.child_token()));
+        }
+
+        // Setup the optional hourly reports, shared across pools
+        let reporter = parameters
+            .reports
+            .as_ref()
+            .map(|config| HourlyReporter::new(&config.output_dir, Arc::new(SystemClock)));
+
+        // Setup the optional audit log, a single chain shared across pools
+        let audit_log = match &parameters.audit {
+            Some(config) => Some(AuditLog::open(&config.output_dir, Arc::new(SystemClock))?),
+            None => None,
         };
 
-        let mut runner_tasks = Vec::with_capacity(parameters.pools.len());
-        let child_token = shutdown.child_token();
-        runner_tasks.push(consumer.spawn(child_token));
+        // Setup the optional simulation export, a single writer shared across pools
+        let simulation_exporter = parameters.market_making.export.as_ref().map(|config| {
+            let writer = SimulationCsvWriter::new(
+                &config.output_dir,
+                config.queue_size,
+                Duration::from_secs(config.flush_interval_secs),
+            );
+            let exporter = writer.exporter();
+            runner_tasks.push(writer.spawn(shutdown.child_token()));
+            exporter
+        });
+
+        // Setup the optional pool state snapshots, a single writer shared across pools
+        let snapshot_writer = parameters.snapshots.as_ref().map(|config| {
+            SnapshotWriter::new(
+                &config.output_dir,
+                config.queue_size,
+                Duration::from_secs(config.flush_interval_secs),
+            )
+        });
+
+        // Track the liveness of all collectors, shared across pools
+        let liveness = CollectorLiveness::new(
+            Arc::new(SystemClock),
+            Duration::from_secs(parameters.liveness.staleness_threshold_secs),
+            Duration::from_secs(parameters.liveness.update_interval_secs),
+        );
+        runner_tasks.push(liveness.clone().spawn(shutdown.child_token()));
+
+        // Push the market making parameters of reloaded configurations to the
+        // strategies of all pools
+        let market_making_updates = self.config_reloads.as_ref().map(|reloads| {
+            let (updates, receiver) = watch::channel(parameters.market_making.clone());
+            runner_tasks.push(spawn_config_reloads(
+                configured,
+                reloads.subscribe(),
+                updates,
+                shutdown.child_token(),
+            ));
+            receiver
+        });
+
+        // Watch the pools polled through the same node together, in a single
+        // call per interval
+        let (mut watched_pools, watcher_tasks) =
+            spawn_pool_watchers(&parameters.pools, snapshot_writer.as_ref(), &shutdown).await?;
+        runner_tasks.extend(watcher_tasks);
 
         for pool in &parameters.pools {
+            let span = pool_span(pool);
             let mut runner = EngineRunner::<InternalEvent, InternalAction>::new(
                 pool.symbol().to_string(),
                 500,
                 500,
             );
+            runner.set_collector_liveness(liveness.clone());
 
-            // Setup the engine
-            let engine = ArbitrageEngine::new(
-                LoggingBotStrategy::new(pool.symbol_owned(), parameters.market_making.clone()),
-                pool.symbol().to_string(),
-            );
+            // Setup the engine, feeding the price history read by the strategy
+            let history = PriceHistoryHandle::new(parameters.price_history_capacity);
+            let price_history = history.reader();
+            let mut strategy =
+                LoggingBotStrategy::new(pool.symbol_owned(), parameters.market_making.clone())
+                    .with_price_history(price_history.clone())
+                    .with_pool(&pool_of(pool));
+            if let Some(config) = &parameters.execution {
+                strategy = strategy.with_trade_size(config.trade_size);
+            }
+            if let Some(exporter) = &simulation_exporter {
+                strategy = strategy.with_exporter(exporter.clone());
+            }
+            if let Some(alpha) = parameters.market_making.dex_ema_alpha {
+                strategy = strategy.with_dex_ema(EmaCalculator::new(alpha)?);
+            }
+            if parameters.paper_trading {
+                strategy = strategy.with_paper_trading();
+            }
+            if let Some(updates) = &market_making_updates {
+                strategy = strategy.with_market_making_updates(updates.clone());
+            }
+            if let Some(fees) = &parameters.fees {
+                let gas_cost_usd = parameters.market_making.gas_cost_usd();
+                strategy =
+                    strategy.with_fees(RoundTripFees::new(fees, pool.fee_tier(), gas_cost_usd));
+            }
+            // Read the pool on every block, or poll it faster while its price
+            // diverges from the CEX if enabled
+            let poll_policy = match (pool.trigger(), pool.fast_poll_interval()) {
+                (PoolTrigger::Block, _) => PollPolicy::Blocks,
+                (PoolTrigger::Timer, Some(fast_interval)) => {
+                    let divergence = DivergenceTracker::new();
+                    strategy = strategy.with_divergence_tracker(divergence.clone());
+                    PollPolicy::Adaptive {
+                        fast_interval,
+                        threshold_bps: Decimal::from(
+                            parameters.market_making.arbitrage_threshold_bps,
+                        ),
+                        divergence,
+                    }
+                },
+                (PoolTrigger::Timer, None) => PollPolicy::Fixed,
+            };
+            // Track the balances held if enabled, skewing the simulated ranges
+            // and checked before every leg
+            let balances = parameters.balances.as_ref().map(|_| BalanceBook::new());
+            if let Some(balances) = &balances {
+                strategy = strategy.with_balances(balances.clone());
+            }
+            let mut engine = ArbitrageEngine::new(strategy, pool.symbol().to_string())
+                .with_price_history(history);
+            if let Some(balances) = &balances {
+                engine = engine.with_balances(balances.clone());
+            }
             runner.add_engine(Box::new(engine));
 
-            // Setup the price feed collector
-            let price_feed_collector = PriceFeedCollector::new(pool.symbol_owned(), client.clone());
-            runner.add_collector(Box::new(price_feed_collector));
+            // Accumulate the hourly report alongside the arbitrage engine if enabled
+            if let Some(reporter) = &reporter {
+                let report_engine =
+                    reporter.engine(pool.symbol_owned(), parameters.market_making.clone());
+                runner.add_engine(Box::new(report_engine.clone()));
+                runner.add_executor(Box::new(report_engine));
+            }
+
+            // Setup the price feed collector of the median of all the exchanges
+            let stale_after = parameters
+                .liveness
+                .price_stale_after_secs
+                .map(Duration::from_secs);
+            if let Some(config) = &parameters.price_aggregation {
+                let feeds = clients
+                    .iter()
+                    .map(|(client, _)| client.price_feed())
+                    .collect();
+                let mut collector = PriceFeedCollector::new(
+                    pool.symbol_owned(),
+                    AggregatedPriceFeed::new(feeds, config),
+                );
+                collector.stale_after = stale_after;
+                runner.add_collector(Box::new(collector));
+            }
 
-            // Setup the pool feed collector
-            let PoolConfig::UniswapV4 {
-                address,
-                symbol,
-                token_0,
-                token_1,
-                fee_tier,
-                node_url,
-                hook_address,
-                tick_spacing,
-                scaling,
-            } = pool;
-            let url = Url::parse(node_url).expect("Invalid node URL");
-            let provider = ProviderBuilder::new().connect_http(url);
-            let contract_address =
-                Address::parse_checksummed(address, None).expect("Invalid contract address");
-            let state_manager =
-                UniswapV4StateViewManager::new(Arc::new(provider), contract_address);
-
-            let hook = if hook_address.is_none() {
-                Address::ZERO
-            } else {
-                Address::parse_checksummed(hook_address.as_ref().unwrap(), None)
-                    .expect("Invalid hook address")
-            };
-            let pool = Pool {
-                symbol: symbol.clone(),
-                token_0: token_0.into(),
-                token_1: token_1.into(),
-                fee_tier: *fee_tier,
-                tick_spacing: *tick_spacing,
-                hook,
-                scaling: *scaling,
-            };
-            let pool_feed_collector = PoolFeedCollector::new(pool, state_manager);
-            runner.add_collector(Box::new(pool_feed_collector));
+            for (client, cex) in &clients {
+                // Setup the price feed collector of the exchange, unless aggregated
+                if parameters.price_aggregation.is_none() {
+                    runner.add_collector(
+                        client.price_feed_collector(pool.symbol_owned(), stale_after),
+                    );
+                }
+
+                if let (
+                    CexClient::Coinbase(client),
+                    CexConfig::Coinbase { heartbeat_timeout_secs, auth, .. },
+                ) = (client, cex)
+                {
+                    // Report the price feed down once its heartbeats stop
+                    runner.add_collector(Box::new(HeartbeatMonitor::new(
+                        pool.symbol_owned(),
+                        client.clone(),
+                        Duration::from_secs(*heartbeat_timeout_secs),
+                    )));
+
+                    // Collect the fills of our own orders if the feed is authenticated
+                    if auth.is_some() {
+                        runner.add_collector(Box::new(OwnFillCollector::new(
+                            pool.symbol_owned(),
+                            client.clone(),
+                        )));
+                    }
+                }
+            }
+
+            // Record opportunities, suppressions and executions in the audit log if enabled
+            if let Some(log) = &audit_log {
+                let audit = AuditExecutor::new(pool.symbol().to_string(), log.clone());
+                runner.add_engine(Box::new(audit.clone()));
+                runner.add_executor(Box::new(audit));
+            }
+
+            // Rebroadcast events and actions to external consumers if enabled
+            if let Some(hub) = &event_stream_hub {
+                let publisher = EventStreamPublisher::new(pool.symbol().to_string(), hub.clone());
+                runner.add_engine(Box::new(publisher.clone()));
+                runner.add_executor(Box::new(publisher));
+            }
+
+            // Alert on opportunities and feed outages if enabled
+            if let Some(dispatcher) = &alert_dispatcher {
+                let alerts = AlertExecutor::new(pool.symbol().to_string(), dispatcher.clone());
+                runner.add_engine(Box::new(alerts.clone()));
+                runner.add_executor(Box::new(alerts));
+            }
+
+            // Halt once the daily loss limit is exceeded if enabled
+            if let Some(pnl) = &daily_pnl {
+                runner.add_engine(Box::new(pnl.engine(pool.symbol().to_string())));
+            }
+
+            // Execute both legs of opportunities, on paper unless live
+            if let Some(config) = &parameters.execution {
+                if config.mode == ExecutionMode::Live && !pool.supports_execution() {
+                    return Err(AppError::ConfigError(format!(
+                        "live execution is not supported for pool {}",
+                        pool.address()
+                    ))
+                    .into());
+                }
+                match (&cex_trade_client, &submitter, dex_router, &config.dex, &receipt_monitor) {
+                    (Some(client), Some(submitter), Some(router), Some(dex), Some(receipts))
+                        if pool.supports_execution() =>
+                    {
+                        let (collector, events) =
+                            ExecutionEventCollector::new(pool.symbol().to_string());
+                        let order_updates = collector.order_updates();
+                        runner.add_collector(Box::new(collector));
+
+                        // Reconcile the order updates of both legs into positions
+                        let mut reconciler = Reconciler::new(pool.symbol().to_string());
+                        if let Some(size) = config.max_unhedged_size {
+                            reconciler = reconciler.with_max_unhedged(size);
+                        }
+                        if let Some(pnl) = &daily_pnl {
+                            reconciler = reconciler.with_pnl(pnl.clone());
+                        }
+                        runner.add_engine(Box::new(reconciler.clone()));
+                        runner.add_executor(Box::new(reconciler.clone()));
+
+                        let cex = CexExecutor::new(pool.symbol().to_string(), client.clone())
+                            .with_order_updates(order_updates.clone());
+                        let dex = DexExecutor::new(
+                            pool_of(pool),
+                            submitter.clone(),
+                            router,
+                            dex.max_slippage_bps,
+                            Duration::from_secs(dex.deadline_secs),
+                            receipts.clone(),
+                            events.clone(),
+                        )
+                        .with_order_updates(order_updates);
+                        let mut planner = ExecutionPlanner::new(
+                            pool.symbol().to_string(),
+                            Arc::new(cex),
+                            Arc::new(dex),
+                            events,
+                        )
+                        .with_ordering(config.leg_ordering)
+                        .with_leg_timeouts(
+                            Duration::from_secs(config.cex_leg_timeout_secs),
+                            Duration::from_secs(config.dex_leg_timeout_secs),
+                        )
+                        .with_risk_check(Arc::new(halt.clone()))
+                        .with_risk_check(Arc::new(reconciler));
+                        if let Some(balances) = &balances {
+                            planner = planner.with_risk_check(Arc::new(
+                                balances.clone().with_wallet(submitter.address()),
+                            ));
+                        }
+                        if let Some(max_age_ms) = config.max_opportunity_age_ms {
+                            planner =
+                                planner.with_max_opportunity_age(Duration::from_millis(max_age_ms));
+                        }
+                        if let Some(min_spread_bps) = config.revalidate_min_spread_bps {
+                            planner =
+                                planner.with_revalidation(price_history.clone(), min_spread_bps);
+                        }
+                        runner.add_executor(Box::new(HaltGuard::new(
+                            Box::new(planner),
+                            halt.clone(),
+                        )));
+                    },
+                    _ if config.mode == ExecutionMode::DryRun => {
+                        let mut paper = PaperExecutor::new(pool.symbol().to_string());
+                        if let Some(pnl) = &daily_pnl {
+                            paper = paper.with_pnl(pnl.clone());
+                        }
+                        runner
+                            .add_executor(Box::new(HaltGuard::new(Box::new(paper), halt.clone())));
+
+                        // Evaluate paper executions against the prices which followed
+                        let mut hindsight = HindsightExecutor::new(
+                            pool.symbol().to_string(),
+                            price_history,
+                            Arc::new(SystemClock),
+                            Duration::from_millis(config.hindsight.latency_ms),
+                            Duration::from_secs(config.hindsight.window_secs),
+                        );
+                        if let Some(log) = &audit_log {
+                            hindsight = hindsight.with_audit_log(log.clone());
+                        }
+                        runner.add_engine(Box::new(hindsight.clone()));
+                        runner.add_executor(Box::new(HaltGuard::new(
+                            Box::new(hindsight),
+                            halt.clone(),
+                        )));
+                    },
+                    _ => {},
+                }
+            }
+
+            // Track the balances held if enabled
+            if let Some(config) = &parameters.balances {
+                runner.add_collector(balance_collector(
+                    pool.symbol().to_string(),
+                    config,
+                    parameters.execution.as_ref(),
+                )?);
+            }
+
+            // Setup the pool feed collector, recording its raw states if enabled,
+            // unless the pool is watched along with the other pools of its node
+            match watched_pools.remove(&pool_of(pool).compute_pool_id()) {
+                Some(stream) => runner.add_collector(Box::new(PoolFeedCollector::new(
+                    pool_of(pool),
+                    UniswapV4WatchedPoolFeed::new(stream),
+                ))),
+                None => {
+                    let recorder = snapshot_writer
+                        .as_ref()
+                        .map(|writer| writer.recorder(pool.symbol().to_string()));
+                    runner.add_collector(pool_feed_collector(pool, recorder, poll_policy).await?);
+                },
+            }
 
             // Run all tasks
             let parameters_clone = parameters.clone();
             let child_token = shutdown.child_token();
-            runner_tasks
-                .push(tokio::spawn(async move { runner.run(parameters_clone, child_token).await }));
+            runner_tasks.push(tokio::spawn(
+                async move { runner.run(parameters_clone, child_token).await }.instrument(span),
+            ));
+        }
+
+        // The writer stops once all recorders are dropped or on shutdown
+        if let Some(writer) = snapshot_writer {
+            runner_tasks.push(writer.spawn(shutdown.child_token()));
         }
 
         // Wait for all tasks to complete
@@ -132,3 +573,954 @@ impl Runner<BotConfig> for BotRunner {
         Ok(())
     }
 }
+
+/// Sends the market making parameters of every configuration received from
+/// `reloads` through `updates` until `shutdown`, warning about the settings
+/// which changed from the `configured` ones but require a restart.
+fn spawn_config_reloads(
+    configured: BotConfig,
+    mut reloads: broadcast::Receiver<BotConfig>,
+    updates: watch::Sender<MarketMakingConfig>,
+    shutdown: tokio_util::sync::CancellationToken,
+) -> JoinHandle<AppResult<()>> {
+    tokio::spawn(async move {
+        loop {
+            let reloaded = tokio::select! {
+                reloaded = reloads.recv() => reloaded,
+                _ = shutdown.cancelled() => return Ok(()),
+            };
+            let config = match reloaded {
+                Ok(config) => config,
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    warn!("skipped {} reloaded configurations", skipped);
+                    continue;
+                },
+                Err(broadcast::error::RecvError::Closed) => return Ok(()),
+            };
+            for setting in configured.restart_required_changes(&config) {
+                warn!(
+                    "{} changed in the reloaded configuration, a restart is required to apply it",
+                    setting
+                );
+            }
+            updates.send_replace(config.market_making);
+            info!("market making parameters reloaded");
+        }
+    })
+}
+
+/// Websocket client of the CEX the prices are compared with, see
+/// [`CexConfig`].
+#[derive(Debug, Clone)]
+pub(crate) enum CexClient {
+    Coinbase(CoinbaseWsClient),
+    Binance(BinanceWsClient),
+}
+
+impl CexClient {
+    /// Returns the collector of the price feed of the pool on the CEX, reported
+    /// down once no ticker arrived within `stale_after` if any.
+    pub(crate) fn price_feed_collector(
+        &self,
+        symbol: PoolSymbol,
+        stale_after: Option<Duration>,
+    ) -> Box<dyn Collector<InternalEvent>> {
+        match self {
+            CexClient::Coinbase(client) => {
+                let mut collector = PriceFeedCollector::new(symbol, client.clone());
+                collector.stale_after = stale_after;
+                Box::new(collector)
+            },
+            CexClient::Binance(client) => {
+                let mut collector = PriceFeedCollector::new(symbol, client.clone());
+                collector.stale_after = stale_after;
+                Box::new(collector)
+            },
+        }
+    }
+
+    /// Returns the price feed of the CEX.
+    pub(crate) fn price_feed(&self) -> Box<dyn PriceFeed + Send + Sync> {
+        match self {
+            CexClient::Coinbase(client) => Box::new(client.clone()),
+            CexClient::Binance(client) => Box::new(client.clone()),
+        }
+    }
+}
+
+/// Creates the CEX websocket client and spawns the consumer maintaining its
+/// connection until `shutdown`.
+///
+/// The credentials of authenticated subscriptions are read from the
+/// environment.
+pub(crate) fn cex_client(
+    config: &CexConfig,
+    shutdown: tokio_util::sync::CancellationToken,
+) -> AppResult<(CexClient, JoinHandle<AppResult<()>>)> {
+    let (ws_message_sender, ws_message_receiver) = mpsc::channel(100);
+
+    match config {
+        CexConfig::Coinbase {
+            ws_url, unlimited_reconnects, reconnect, auth, channel_mode, ..
+        } => {
+            let (message_broadcaster, _) = broadcast::channel(100);
+            let client = match auth {
+                Some(auth) => {
+                    let credentials = CoinbaseCredentials {
+                        api_key: Secret::from_env(&auth.api_key_env)?.expose().to_string(),
+                        api_secret: Secret::from_env(&auth.api_secret_env)?,
+                        passphrase: Secret::from_env(&auth.passphrase_env)?,
+                    };
+                    CoinbaseWsClient::new_with_auth(
+                        ws_url.clone(),
+                        ws_message_sender,
+                        message_broadcaster,
+                        credentials,
+                    )
+                },
+                None => {
+                    CoinbaseWsClient::new(ws_url.clone(), ws_message_sender, message_broadcaster)
+                },
+            }
+            .with_channel_mode(*channel_mode);
+            let consumer = WsConsumer::new(
+                client.ws_url().to_string(),
+                client.clone(),
+                5000,
+                reconnect.backoff(*unlimited_reconnects),
+                ws_message_receiver,
+            );
+            Ok((CexClient::Coinbase(client), consumer.spawn(shutdown)))
+        },
+        CexConfig::Binance { ws_url, unlimited_reconnects, reconnect } => {
+            let (message_broadcaster, _) = broadcast::channel(100);
+            let client =
+                BinanceWsClient::new(ws_url.clone(), ws_message_sender, message_broadcaster);
+            let consumer = WsConsumer::new(
+                client.ws_url().to_string(),
+                client.callback(),
+                5000,
+                reconnect.backoff(*unlimited_reconnects),
+                ws_message_receiver,
+            );
+            Ok((CexClient::Binance(client), consumer.spawn(shutdown)))
+        },
+    }
+}
+
+/// Reads the decimals of the pool tokens configured as 0 from their contracts,
+/// see [`TokenConfig::auto_detect_decimals`].
+pub(crate) async fn resolve_token_decimals(pools: &mut [PoolConfig]) -> AppResult<()> {
+    for pool in pools {
+        let (PoolConfig::UniswapV4 { symbol, token_0, token_1, node_url, .. }
+        | PoolConfig::UniswapV3 { symbol, token_0, token_1, node_url, .. }) = pool;
+        if !token_0.auto_detect_decimals() && !token_1.auto_detect_decimals() {
+            continue;
+        }
+
+        Url::parse(node_url)
+            .map_err(|e| AppError::ConfigError(format!("invalid node url {}: {}", node_url, e)))?;
+        let (provider, _) = node_provider(node_url).await?;
+        for token in [token_0, token_1] {
+            if !token.auto_detect_decimals() {
+                continue;
+            }
+            let address = Address::parse_checksummed(&token.address, None).map_err(|e| {
+                AppError::ConfigError(format!("invalid token address {}: {}", token.address, e))
+            })?;
+            token.decimals = TokenConfig::fetch_decimals(&provider, address).await?;
+            info!("token {} of {} has {} decimals", token.address, symbol, token.decimals);
+        }
+    }
+    Ok(())
+}
+
+/// Creates the collector of the on-chain price of a pool polled according to
+/// `poll_policy`, handing its raw states to the recorder if any.
+///
+/// A websocket node URL is connected to right away, so that the feed can
+/// subscribe to the node.
+pub(crate) async fn pool_feed_collector(
+    pool: &PoolConfig,
+    recorder: Option<SnapshotRecorder>,
+    poll_policy: PollPolicy,
+) -> AppResult<Box<dyn Collector<InternalEvent>>> {
+    let (PoolConfig::UniswapV4 { address, node_url, .. }
+    | PoolConfig::UniswapV3 { address, node_url, .. }) = pool;
+    let (provider, rpc_metrics) = node_provider(node_url).await?;
+    let contract_address =
+        Address::parse_checksummed(address, None).expect("Invalid contract address");
+    if let PoolConfig::UniswapV3 { .. } = pool {
+        if recorder.is_some() {
+            warn!("snapshots are not recorded for Uniswap V3 pool {}", address);
+        }
+        let state_manager =
+            UniswapV3StateViewManager::new(Arc::new(provider)).with_observer(Arc::new(rpc_metrics));
+        let feed = UniswapV3PoolFeed::new(state_manager, contract_address);
+        return Ok(Box::new(PoolFeedCollector::new(pool_of(pool), feed)));
+    }
+    // Pinned to a block, so that every price tells how old the state is
+    let (provider, rpc_metrics) = (Arc::new(provider), Arc::new(rpc_metrics));
+    let mut state_manager = UniswapV4StateViewManager::new(provider.clone(), contract_address)
+        .with_observer(rpc_metrics.clone())
+        .with_block_state();
+    if let Some(recorder) = recorder {
+        state_manager = state_manager.with_sink(Arc::new(recorder));
+    }
+    let mut feed = UniswapV4PoolFeed::new(state_manager).with_poll_policy(poll_policy);
+    if let Some((quote_size, quoter_address)) = pool.quote()? {
+        let quoter_address =
+            Address::parse_checksummed(quoter_address, None).expect("Invalid quoter address");
+        let quoter = UniswapV4Quoter::new(provider, quoter_address).with_observer(rpc_metrics);
+        feed = feed.with_quoter(quoter, quote_size);
+    }
+    Ok(Box::new(PoolFeedCollector::new(pool_of(pool), feed)))
+}
+
+/// Connects to a node, returning its provider along with the metrics of its
+/// RPC calls, labelled with the host of the node only since its URL may carry
+/// an API key.
+///
+/// A websocket node URL is connected to right away, so that the feed can
+/// subscribe to the node.
+async fn node_provider(node_url: &str) -> AppResult<(impl Provider + 'static, RpcMetrics)> {
+    let url = Url::parse(node_url).expect("Invalid node URL");
+    let host = url.host_str().unwrap_or("unknown").to_string();
+    let rpc_metrics = RpcMetrics::new(&host);
+    let provider = match url.scheme() {
+        "ws" | "wss" => ProviderBuilder::new()
+            .connect_ws(ws::WsConnect::new(url.as_str()))
+            .await
+            .map_err(|e| {
+                AppError::WebSocketError(format!("failed to connect to {}: {}", host, e))
+            })?,
+        _ => ProviderBuilder::new().connect_http(url),
+    };
+    Ok((provider, rpc_metrics))
+}
+
+/// Starts watching the Uniswap V4 pools polled at a fixed interval through
+/// the same node together, with a single multicall per interval for all the
+/// pools of the node, returning the stream of each of these pools by pool id
+/// along with the tasks watching them.
+///
+/// Pools alone on their node, read on every block, polled adaptively or
+/// quoted are left to their own feed. The pools of a node are polled at the
+/// shortest of their intervals, recording their raw states if enabled.
+async fn spawn_pool_watchers(
+    pools: &[PoolConfig],
+    snapshot_writer: Option<&SnapshotWriter>,
+    shutdown: &tokio_util::sync::CancellationToken,
+) -> AppResult<(HashMap<B256, PoolSlotDataStream>, Vec<JoinHandle<AppResult<()>>>)> {
+    let mut pools_by_node: HashMap<&str, Vec<&PoolConfig>> = HashMap::new();
+    for pool in pools {
+        if let PoolConfig::UniswapV4 {
+            node_url,
+            trigger: PoolTrigger::Timer,
+            quote_size: None,
+            ..
+        } = pool
+        {
+            if pool.fast_poll_interval().is_none() {
+                pools_by_node
+                    .entry(node_url.as_str())
+                    .or_default()
+                    .push(pool);
+            }
+        }
+    }
+
+    let mut streams = HashMap::new();
+    let mut tasks = Vec::new();
+    for (node_url, pools) in pools_by_node {
+        let [first, _, ..] = pools.as_slice() else { continue };
+        let PoolConfig::UniswapV4 { address, .. } = first else { continue };
+        let (provider, rpc_metrics) = node_provider(node_url).await?;
+        let contract_address =
+            Address::parse_checksummed(address, None).expect("Invalid contract address");
+        // Pinned to a block, so that every price tells how old the state is
+        let manager = UniswapV4StateViewManager::new(Arc::new(provider), contract_address)
+            .with_observer(Arc::new(rpc_metrics))
+            .with_block_state();
+
+        let watched_pools = pools
+            .iter()
+            .map(|config| {
+                let pool = pool_of(config);
+                let mut watched = WatchedPool::new(
+                    pool.compute_pool_id(),
+                    pool.token_0.decimals,
+                    pool.token_1.decimals,
+                    pool.token_0.address < pool.token_1.address,
+                );
+                if let Some(writer) = snapshot_writer {
+                    let recorder = writer.recorder(pool.symbol.to_string());
+                    watched = watched.with_sink(Arc::new(recorder));
+                }
+                watched
+            })
+            .collect::<Vec<_>>();
+        let poll_interval = pools
+            .iter()
+            .map(|pool| pool.poll_interval())
+            .min()
+            .unwrap_or_default();
+        info!(
+            pools = watched_pools.len(),
+            "watching the pools of the same node together every {:?}", poll_interval
+        );
+        let watcher = UniswapV4MultiPoolWatcher::new(manager, watched_pools, poll_interval);
+        let (node_streams, task) = watcher.spawn(shutdown.child_token());
+        streams.extend(node_streams);
+        tasks.push(task);
+    }
+    Ok((streams, tasks))
+}
+
+/// Returns the pool of a pool configuration.
+pub(crate) fn pool_of(pool: &PoolConfig) -> Pool {
+    let (symbol, token_0, token_1, fee_tier, hook_address, tick_spacing, scaling) = match pool {
+        PoolConfig::UniswapV4 {
+            symbol,
+            token_0,
+            token_1,
+            fee_tier,
+            hook_address,
+            tick_spacing,
+            scaling,
+            ..
+        } => (symbol, token_0, token_1, fee_tier, hook_address, *tick_spacing, scaling),
+        // Uniswap V3 derives the tick spacing from the fee tier and has no hooks
+        PoolConfig::UniswapV3 { symbol, token_0, token_1, fee_tier, scaling, .. } => {
+            let tick_spacing = match fee_tier {
+                100 => 1,
+                500 => 10,
+                3000 => 60,
+                _ => 200,
+            };
+            (symbol, token_0, token_1, fee_tier, &None, tick_spacing, scaling)
+        },
+    };
+    let hook = if hook_address.is_none() {
+        Address::ZERO
+    } else {
+        Address::parse_checksummed(hook_address.as_ref().unwrap(), None)
+            .expect("Invalid hook address")
+    };
+    Pool {
+        symbol: symbol.clone(),
+        token_0: token_0.into(),
+        token_1: token_1.into(),
+        fee_tier: *fee_tier,
+        tick_spacing,
+        hook,
+        scaling: *scaling,
+        poll_interval: pool.poll_interval(),
+    }
+}
+
+/// Creates the transaction submitter of the configured signer, if any.
+///
+/// Fails in live mode without a signer, so that going live is always an
+/// explicit decision.
+pub(crate) fn tx_submitter(
+    config: &ExecutionConfig,
+) -> AppResult<Option<TxSubmitter<DynProvider>>> {
+    let signer = match (&config.signer, config.mode) {
+        (Some(SignerConfig::PrivateKey { key_env }), _) => {
+            signer_from_key(&Secret::from_env(key_env)?)?
+        },
+        (Some(SignerConfig::Keystore { path, password_env }), _) => {
+            signer_from_keystore(path, &Secret::from_env(password_env)?)?
+        },
+        (None, ExecutionMode::DryRun) => return Ok(None),
+        (None, ExecutionMode::Live) => {
+            return Err(AppError::ConfigError(
+                "live execution requires an explicitly configured signer".to_string(),
+            )
+            .into());
+        },
+    };
+    let url = Url::parse(&config.rpc_url)
+        .map_err(|e| AppError::ConfigError(format!("invalid execution rpc_url: {}", e)))?;
+    let provider = ProviderBuilder::new().connect_http(url).erased();
+    let relay = match &config.submission {
+        SubmissionConfig::Public => None,
+        SubmissionConfig::PrivateRelay { relay_url, fallback_after_blocks, auth_key_env } => {
+            let auth = match auth_key_env {
+                Some(key_env) => signer_from_key(&Secret::from_env(key_env)?)?,
+                None => signer.clone(),
+            };
+            Some((PrivateRelay::new(relay_url).with_auth_signer(auth), *fallback_after_blocks))
+        },
+    };
+    let mut submitter =
+        TxSubmitter::new(Arc::new(provider), signer, config.chain_id, config.fee_estimator()?);
+    if let Some((relay, fallback_after_blocks)) = relay {
+        info!(relay = relay.url(), fallback_after_blocks, "submitting transactions privately");
+        submitter = submitter.with_private_relay(relay, fallback_after_blocks);
+    }
+    info!(
+        address = %submitter.address(),
+        chain_id = config.chain_id,
+        mode = ?config.mode,
+        "transaction signer loaded"
+    );
+    Ok(Some(submitter))
+}
+
+/// Creates the client placing the CEX leg of opportunities in live mode.
+pub(crate) fn cex_trade_client(config: &ExecutionConfig) -> AppResult<Option<CoinbaseTradeClient>> {
+    if config.mode == ExecutionMode::DryRun {
+        return Ok(None);
+    }
+    let Some(coinbase) = &config.coinbase else {
+        return Err(AppError::ConfigError(
+            "live execution requires configured coinbase credentials".to_string(),
+        )
+        .into());
+    };
+    Ok(Some(coinbase_trade_client(coinbase)?))
+}
+
+/// Creates an authenticated Coinbase client from the credentials in the
+/// environment.
+fn coinbase_trade_client(coinbase: &CoinbaseTradeConfig) -> AppResult<CoinbaseTradeClient> {
+    let api_key = Secret::from_env(&coinbase.api_key_env)?;
+    let api_secret = Secret::from_env(&coinbase.api_secret_env)?;
+    Ok(CoinbaseTradeClient::new(&coinbase.api_url, api_key.expose().to_string(), api_secret))
+}
+
+/// Creates the collector of the balances of a pool, reading the Coinbase
+/// balances as well if credentials are configured for execution.
+pub(crate) fn balance_collector(
+    pool: String,
+    config: &BalanceConfig,
+    execution: Option<&ExecutionConfig>,
+) -> AppResult<Box<dyn Collector<InternalEvent>>> {
+    let url = Url::parse(&config.rpc_url)
+        .map_err(|e| AppError::ConfigError(format!("invalid balances rpc_url: {}", e)))?;
+    let provider = ProviderBuilder::new().connect_http(url);
+    let addresses = config
+        .addresses
+        .iter()
+        .map(|address| {
+            address.parse::<Address>().map_err(|e| {
+                AppError::ConfigError(format!("invalid balance address {}: {}", address, e))
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let tokens = config
+        .tokens
+        .iter()
+        .map(|token| BalanceToken { asset: token.asset.clone(), token: (&token.token).into() })
+        .collect();
+    let mut collector = BalanceCollector::new(
+        pool,
+        Arc::new(provider),
+        addresses,
+        tokens,
+        Duration::from_secs(config.poll_interval_secs),
+    );
+    if let Some(coinbase) = execution.and_then(|config| config.coinbase.as_ref()) {
+        collector = collector.with_coinbase(coinbase_trade_client(coinbase)?);
+    }
+    Ok(Box::new(collector))
+}
+
+/// Returns the router the DEX leg of opportunities is swapped through in live
+/// mode.
+pub(crate) fn dex_router(config: &ExecutionConfig) -> AppResult<Option<UniversalRouter>> {
+    if config.mode == ExecutionMode::DryRun {
+        return Ok(None);
+    }
+    let Some(dex) = &config.dex else {
+        return Err(AppError::ConfigError(
+            "live execution requires a configured dex router".to_string(),
+        )
+        .into());
+    };
+    let address = dex.router_address.parse::<Address>().map_err(|e| {
+        AppError::ConfigError(format!("invalid dex router_address {}: {}", dex.router_address, e))
+    })?;
+    Ok(Some(UniversalRouter::new(address)))
+}
+
+/// Creates the manager of the token approvals the swaps through `router`
+/// depend on, none if no approvals are configured.
+pub(crate) fn approval_manager(
+    config: &ExecutionConfig,
+    submitter: Arc<TxSubmitter<DynProvider>>,
+    router: UniversalRouter,
+) -> AppResult<Option<ApprovalManager<DynProvider>>> {
+    let Some(approvals) = &config.approvals else {
+        warn!("no token approvals configured, swaps may fail without allowances");
+        return Ok(None);
+    };
+    let permit2 = approvals.permit2_address.parse::<Address>().map_err(|e| {
+        AppError::ConfigError(format!(
+            "invalid approvals permit2_address {}: {}",
+            approvals.permit2_address, e
+        ))
+    })?;
+    let tokens = approvals
+        .tokens
+        .iter()
+        .map(|config| {
+            let token: Token = (&config.token).into();
+            let units = |amount: Decimal| {
+                to_units(amount, &token).map(U256::from).ok_or_else(|| {
+                    AppError::ConfigError(format!(
+                        "approval amount {} does not fit token {}",
+                        amount, token.address
+                    ))
+                })
+            };
+            Ok(TokenApproval {
+                token: token.address,
+                min_allowance: units(config.min_allowance.unwrap_or(config.cap))?,
+                cap: units(config.cap)?,
+            })
+        })
+        .collect::<AppResult<Vec<_>>>()?;
+    let manager = ApprovalManager::new(submitter, router.address(), tokens)
+        .with_permit2(permit2)
+        .with_auto_approve(config.auto_approve)
+        .with_timeouts(
+            Duration::from_secs(approvals.permit2_expiration_secs),
+            Duration::from_secs(approvals.confirm_timeout_secs),
+        )
+        .with_cache(&approvals.cache_path);
+    Ok(Some(manager))
+}
+
+/// Span the pipeline of a pool runs in, so that every log line emitted within
+/// it carries the pool it belongs to.
+pub(crate) fn pool_span(pool: &PoolConfig) -> Span {
+    info_span!("pool", symbol = %pool.symbol(), pool_address = pool.address())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{io::Write, sync::Mutex, time::Duration};
+
+    use futures::{stream, SinkExt, StreamExt};
+    use rust_decimal_macros::dec;
+    use serde_json::{json, Value};
+    use tokio_tungstenite::tungstenite::Message;
+    use tokio_util::sync::CancellationToken;
+    use tracing_subscriber::prelude::*;
+
+    use super::*;
+    use crate::{
+        config::MarketMakingConfig,
+        engine::{
+            Exchange, PoolFeed, PoolPriceUpdate, PoolSymbol, PoolUpdateStream, PriceFeed,
+            PriceFeedSubscription, Ticker,
+        },
+    };
+
+    /// Log sink shared with the JSON formatter.
+    #[derive(Clone, Default)]
+    struct LogBuffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for LogBuffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> { Ok(()) }
+    }
+
+    impl LogBuffer {
+        fn lines(&self) -> Vec<Value> {
+            String::from_utf8(self.0.lock().unwrap().clone())
+                .unwrap()
+                .lines()
+                .map(|line| serde_json::from_str(line).unwrap())
+                .collect()
+        }
+
+        fn find(&self, message: &str) -> Option<Value> {
+            self.lines().into_iter().find(|line| {
+                line["fields"]["message"]
+                    .as_str()
+                    .is_some_and(|m| m.contains(message))
+            })
+        }
+    }
+
+    struct FakePriceFeed;
+
+    #[async_trait::async_trait]
+    impl PriceFeed for FakePriceFeed {
+        fn exchange(&self) -> Exchange { Exchange::Coinbase }
+
+        async fn subscribe_price_feed(
+            &mut self,
+            symbol: PoolSymbol,
+        ) -> AppResult<PriceFeedSubscription<'_>> {
+            let ticker = Ticker {
+                exchage: Exchange::Coinbase,
+                symbol,
+                price: dec!(2500),
+                timestamp: jiff::Timestamp::now(),
+                bid: None,
+                ask: None,
+                bid_size: None,
+                ask_size: None,
+                volume_24h: None,
+            };
+            Ok(Box::pin(stream::iter([ticker]).chain(stream::pending())))
+        }
+
+        async fn unsubscribe_price_feed(&mut self, _symbol: PoolSymbol) -> AppResult<()> { Ok(()) }
+    }
+
+    struct FakePoolFeed;
+
+    #[async_trait::async_trait]
+    impl PoolFeed for FakePoolFeed {
+        fn exchange(&self) -> &'static str { "uniswap_v4" }
+
+        async fn subscribe_pool_updates(&mut self, pool: Pool) -> AppResult<PoolUpdateStream<'_>> {
+            let update = PoolPriceUpdate {
+                symbol: pool.symbol,
+                price: dec!(2600),
+                timestamp: jiff::Timestamp::now(),
+                block_number: None,
+                state: None,
+                effective_buy_price: None,
+                effective_sell_price: None,
+            };
+            Ok(Box::pin(stream::iter([update]).chain(stream::pending())))
+        }
+
+        async fn unsubscribe_pool_updates(&mut self, _symbol: PoolSymbol) -> AppResult<()> {
+            Ok(())
+        }
+    }
+
+    fn pool_config() -> PoolConfig {
+        serde_json::from_value(json!({
+            "dex": "uniswapv4",
+            "address": "0xA3c0c9b65baD0b08107Aa264b0f3dB444b867A71",
+            "symbol": "ETH-USDC",
+            "token_0": { "address": "0x4200000000000000000000000000000000000006", "decimals": 18 },
+            "token_1": { "address": "0x833589fCD6eDb6E08f4c7C32D4f71b54bdA02913", "decimals": 6 },
+            "fee_tier": 500,
+            "node_url": "http://localhost:8545",
+            "tick_spacing": 10,
+            "scaling": 2
+        }))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_token_decimals_are_resolved_over_websocket_nodes() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let node_url = format!("ws://{}", listener.local_addr().unwrap());
+
+        // Fake node answering every call with 8 decimals
+        let node = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+            while let Some(Ok(message)) = ws.next().await {
+                let Message::Text(text) = message else {
+                    continue;
+                };
+                let request: Value = serde_json::from_str(&text).unwrap();
+                let response = json!({
+                    "jsonrpc": "2.0",
+                    "id": request["id"],
+                    "result": format!("0x{:064x}", 8)
+                });
+                ws.send(Message::Text(response.to_string().into()))
+                    .await
+                    .unwrap();
+            }
+        });
+
+        let mut pools = [serde_json::from_value(json!({
+            "dex": "uniswapv4",
+            "address": "0xA3c0c9b65baD0b08107Aa264b0f3dB444b867A71",
+            "symbol": "ETH-USDC",
+            "token_0": { "address": "0x4200000000000000000000000000000000000006", "decimals": 18 },
+            "token_1": { "address": "0x1000000000000000000000000000000000000001", "decimals": 0 },
+            "fee_tier": 500,
+            "node_url": node_url,
+            "tick_spacing": 10,
+            "scaling": 2
+        }))
+        .unwrap()];
+        resolve_token_decimals(&mut pools).await.unwrap();
+        let PoolConfig::UniswapV4 { token_0, token_1, .. } = &pools[0] else {
+            panic!("expected a Uniswap V4 pool");
+        };
+        assert_eq!(token_0.decimals, 18);
+        assert_eq!(token_1.decimals, 8);
+        node.abort();
+    }
+
+    fn span<'a>(line: &'a Value, name: &str) -> &'a Value {
+        line["spans"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|span| span["name"] == name)
+            .unwrap_or_else(|| panic!("span {} missing in {}", name, line))
+    }
+
+    #[tokio::test]
+    async fn test_pipeline_logs_carry_pool_and_correlation_fields() {
+        let logs = LogBuffer::default();
+        let writer = logs.clone();
+        let subscriber = tracing_subscriber::registry().with(
+            tracing_subscriber::fmt::layer()
+                .json()
+                .with_writer(move || writer.clone()),
+        );
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let config = pool_config();
+        let PoolConfig::UniswapV4 { symbol, token_0, token_1, .. } = &config else {
+            panic!("expected a Uniswap V4 pool");
+        };
+        let pool = Pool {
+            symbol: symbol.clone(),
+            token_0: token_0.into(),
+            token_1: token_1.into(),
+            fee_tier: 500,
+            tick_spacing: 10,
+            hook: Address::ZERO,
+            scaling: 2,
+            poll_interval: Duration::from_secs(5),
+        };
+        let market_making = MarketMakingConfig {
+            base_spread_bps: 50,
+            max_spread_bps: 100,
+            min_spread_bps: 10,
+            gas_price: dec!(0.5),
+            arbitrage_tighten_factor: dec!(0.7),
+            arbitrage_widen_factor: dec!(1.3),
+            arbitrage_threshold_bps: 100,
+            gas_units_per_trade: 150_000,
+            token_price_usd: Decimal::ZERO,
+            volatility_window: 20,
+            volatility_threshold: dec!(0.02),
+            volatility_widen_factor: None,
+            use_twap: false,
+            twap_window_secs: 60,
+            dex_ema_alpha: None,
+            export: None,
+            acceptable_price_impact_bps: 50,
+            capital_usd: Decimal::ZERO,
+            fill_simulation: None,
+            inventory_skew_bps: 0,
+        };
+
+        let mut runner =
+            EngineRunner::<InternalEvent, InternalAction>::new(symbol.to_string(), 16, 16);
+        runner.add_engine(Box::new(ArbitrageEngine::new(
+            LoggingBotStrategy::new(symbol.clone(), market_making),
+            symbol.to_string(),
+        )));
+        runner.add_collector(Box::new(PriceFeedCollector::new(symbol.clone(), FakePriceFeed)));
+        runner.add_collector(Box::new(PoolFeedCollector::new(pool, FakePoolFeed)));
+
+        let shutdown = CancellationToken::new();
+        let handle = tokio::spawn(
+            runner
+                .run((), shutdown.clone())
+                .instrument(pool_span(&config)),
+        );
+        for _ in 0..100 {
+            if logs.find("ARBITRAGE OPPORTUNITY").is_some() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        shutdown.cancel();
+        handle.await.unwrap().unwrap();
+
+        // A strategy log deep inside the pipeline carries the pool and the event
+        let line = logs
+            .find("ARBITRAGE OPPORTUNITY")
+            .expect("strategy log missing");
+        let pool = span(&line, "pool");
+        assert_eq!(pool["symbol"], "ETH-USDC");
+        assert_eq!(pool["pool_address"], "0xA3c0c9b65baD0b08107Aa264b0f3dB444b867A71");
+        assert_eq!(span(&line, "engine")["engine"], "arbitrage_engine_ETH-USDC");
+        assert!(span(&line, "event")["correlation_id"].as_u64().is_some());
+
+        // Collectors carry the exchange and the side of the market
+        let line = logs
+            .find("starting collector with name: pool_feed_collector_ETH-USDC")
+            .expect("collector log missing");
+        assert_eq!(span(&line, "pool")["symbol"], "ETH-USDC");
+        assert_eq!(span(&line, "collector")["exchange"], "uniswap_v4");
+        assert_eq!(span(&line, "collector")["source"], "dex");
+        let line = logs
+            .find("starting collector with name: price_feed_collector")
+            .expect("collector log missing");
+        assert_eq!(span(&line, "collector")["exchange"], "coinbase");
+        assert_eq!(span(&line, "collector")["source"], "cex");
+    }
+
+    fn execution_config(mode: &str, signer: Value) -> ExecutionConfig {
+        serde_json::from_value(json!({
+            "mode": mode,
+            "signer": signer,
+            "chain_id": 8453,
+            "rpc_url": "http://localhost:8545"
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_live_execution_requires_a_signer() {
+        let error = tx_submitter(&execution_config("live", Value::Null)).unwrap_err();
+        assert!(error.to_string().contains("explicitly configured signer"));
+
+        let submitter = tx_submitter(&execution_config("dry_run", Value::Null)).unwrap();
+        assert!(submitter.is_none());
+    }
+
+    #[test]
+    fn test_live_execution_requires_coinbase_credentials() {
+        let error = cex_trade_client(&execution_config("live", Value::Null)).unwrap_err();
+        assert!(error.to_string().contains("coinbase credentials"));
+
+        // Dry runs trade on paper
+        let client = cex_trade_client(&execution_config("dry_run", Value::Null)).unwrap();
+        assert!(client.is_none());
+    }
+
+    #[test]
+    fn test_live_execution_requires_a_dex_router() {
+        let error = dex_router(&execution_config("live", Value::Null)).unwrap_err();
+        assert!(error.to_string().contains("dex router"));
+        assert!(dex_router(&execution_config("dry_run", Value::Null))
+            .unwrap()
+            .is_none());
+
+        let mut config = execution_config("live", Value::Null);
+        config.dex = serde_json::from_value(json!({
+            "router_address": "0x6fF5693b99212Da76ad316178A184AB56D299b43"
+        }))
+        .unwrap();
+        let router = dex_router(&config).unwrap().unwrap();
+        assert_eq!(
+            router.address(),
+            "0x6fF5693b99212Da76ad316178A184AB56D299b43"
+                .parse::<Address>()
+                .unwrap()
+        );
+        assert_eq!(config.dex.unwrap().max_slippage_bps, 50);
+    }
+
+    #[test]
+    fn test_signer_is_loaded_without_logging_the_key() {
+        let logs = LogBuffer::default();
+        let writer = logs.clone();
+        let subscriber = tracing_subscriber::registry().with(
+            tracing_subscriber::fmt::layer()
+                .json()
+                .with_writer(move || writer.clone()),
+        );
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        // The first well known Anvil development key
+        let key = "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+        env::set_var("SIKARRA_TEST_RUNNER_SIGNER_KEY", key);
+        let config = execution_config(
+            "live",
+            json!({ "type": "private_key", "key_env": "SIKARRA_TEST_RUNNER_SIGNER_KEY" }),
+        );
+        let submitter = tx_submitter(&config).unwrap().unwrap();
+
+        assert_eq!(
+            submitter.address(),
+            "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266"
+                .parse::<Address>()
+                .unwrap()
+        );
+        let line = logs
+            .find("transaction signer loaded")
+            .expect("signer log missing");
+        assert_eq!(line["fields"]["chain_id"], 8453);
+        let logged = String::from_utf8(logs.0.lock().unwrap().clone()).unwrap();
+        assert!(!logged.contains(&key[2..]));
+        assert!(!format!("{:?}", submitter).contains(&key[2..]));
+
+        // A missing variable names the variable, never a value
+        let config = execution_config(
+            "live",
+            json!({ "type": "private_key", "key_env": "SIKARRA_TEST_RUNNER_MISSING_KEY" }),
+        );
+        let error = tx_submitter(&config).unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("SIKARRA_TEST_RUNNER_MISSING_KEY"));
+    }
+
+    #[test]
+    fn test_private_relay_submission() {
+        let key = "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+        env::set_var("SIKARRA_TEST_RUNNER_RELAY_SIGNER_KEY", key);
+        let mut config = execution_config(
+            "live",
+            json!({ "type": "private_key", "key_env": "SIKARRA_TEST_RUNNER_RELAY_SIGNER_KEY" }),
+        );
+        assert_eq!(config.submission, SubmissionConfig::Public);
+        assert!(format!("{:?}", tx_submitter(&config).unwrap().unwrap()).contains("private: None"));
+
+        config.submission = serde_json::from_value(json!({
+            "backend": "private_relay",
+            "relay_url": "https://rpc.flashbots.net"
+        }))
+        .unwrap();
+        let debug = format!("{:?}", tx_submitter(&config).unwrap().unwrap());
+        assert!(debug.contains("https://rpc.flashbots.net"));
+        assert!(debug.contains("fallback_after_blocks: 3"));
+        assert!(!debug.contains(&key[2..]));
+    }
+
+    #[test]
+    fn test_approval_amounts_are_converted_to_units() {
+        let key = "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+        env::set_var("SIKARRA_TEST_RUNNER_APPROVAL_SIGNER_KEY", key);
+        let mut config = execution_config(
+            "live",
+            json!({ "type": "private_key", "key_env": "SIKARRA_TEST_RUNNER_APPROVAL_SIGNER_KEY" }),
+        );
+        let submitter = Arc::new(tx_submitter(&config).unwrap().unwrap());
+        let router = UniversalRouter::new(
+            "0x6fF5693b99212Da76ad316178A184AB56D299b43"
+                .parse::<Address>()
+                .unwrap(),
+        );
+        assert!(approval_manager(&config, submitter.clone(), router)
+            .unwrap()
+            .is_none());
+
+        config.approvals = serde_json::from_value(json!({
+            "tokens": [{
+                "address": "0x833589fCD6eDb6E08f4c7C32D4f71b54bdA02913",
+                "decimals": 6,
+                "cap": "10000",
+                "min_allowance": "1000.5"
+            }]
+        }))
+        .unwrap();
+        let manager = approval_manager(&config, submitter, router)
+            .unwrap()
+            .unwrap();
+        let usdc = &manager.tokens()[0];
+        assert_eq!(usdc.cap, U256::from(10_000_000_000u64));
+        assert_eq!(usdc.min_allowance, U256::from(1_000_500_000u64));
+        assert!(!config.auto_approve);
+        assert_eq!(config.approvals.unwrap().cache_path, "approvals.json");
+    }
+}
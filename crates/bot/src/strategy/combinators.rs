@@ -0,0 +1,151 @@
+//! Combinators stacking strategies, e.g. acting only if a volatility check
+//! and an arbitrage check both signal.
+//!
+//! Like the iterator adapters, [`and`] and [`or`] wrap two strategies into one
+//! rather than growing a monolithic strategy. Both inner strategies see every
+//! event, so that their state stays current whatever the other decides.
+
+use crate::{
+    engine::{InternalAction, InternalEvent, PoolSymbol},
+    strategy::BotStrategy,
+};
+
+/// Strategy acting only if both of its strategies do, with the action of the
+/// first. See [`and`].
+#[derive(Debug, Clone)]
+pub struct AndStrategy<A, B> {
+    first: A,
+    second: B,
+}
+
+/// Strategy acting if either of its strategies does, with the action of the
+/// first if both do. See [`or`].
+#[derive(Debug, Clone)]
+pub struct OrStrategy<A, B> {
+    first: A,
+    second: B,
+}
+
+/// Combines two strategies into one acting only if both do.
+pub fn and<A: BotStrategy, B: BotStrategy>(first: A, second: B) -> AndStrategy<A, B> {
+    AndStrategy { first, second }
+}
+
+/// Combines two strategies into one acting if either does.
+pub fn or<A: BotStrategy, B: BotStrategy>(first: A, second: B) -> OrStrategy<A, B> {
+    OrStrategy { first, second }
+}
+
+/// Symbols of both strategies, those of the first first.
+fn union(mut first: Vec<PoolSymbol>, second: Vec<PoolSymbol>) -> Vec<PoolSymbol> {
+    for symbol in second {
+        if !first.contains(&symbol) {
+            first.push(symbol);
+        }
+    }
+    first
+}
+
+impl<A: BotStrategy, B: BotStrategy> BotStrategy for AndStrategy<A, B> {
+    fn symbols(&self) -> Vec<PoolSymbol> { union(self.first.symbols(), self.second.symbols()) }
+
+    fn handle_internal_event(&mut self, event: InternalEvent) -> Option<InternalAction> {
+        let first = self.first.handle_internal_event(event.clone());
+        let second = self.second.handle_internal_event(event);
+        first.filter(|_| second.is_some())
+    }
+}
+
+impl<A: BotStrategy, B: BotStrategy> BotStrategy for OrStrategy<A, B> {
+    fn symbols(&self) -> Vec<PoolSymbol> { union(self.first.symbols(), self.second.symbols()) }
+
+    fn handle_internal_event(&mut self, event: InternalEvent) -> Option<InternalAction> {
+        let first = self.first.handle_internal_event(event.clone());
+        let second = self.second.handle_internal_event(event);
+        first.or(second)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal_macros::dec;
+
+    use super::*;
+    use crate::engine::{Exchange, Ticker};
+
+    /// Strategy halting with its reason on every event if it has one.
+    struct FixedStrategy {
+        symbol: PoolSymbol,
+        reason: Option<&'static str>,
+        handled: usize,
+    }
+
+    impl BotStrategy for FixedStrategy {
+        fn symbols(&self) -> Vec<PoolSymbol> { vec![self.symbol.clone()] }
+
+        fn handle_internal_event(&mut self, _event: InternalEvent) -> Option<InternalAction> {
+            self.handled += 1;
+            self.reason
+                .map(|reason| InternalAction::Halt { reason: reason.to_string() })
+        }
+    }
+
+    fn fixed(reason: Option<&'static str>) -> FixedStrategy {
+        FixedStrategy { symbol: PoolSymbol::ETH_USDC, reason, handled: 0 }
+    }
+
+    fn ticker() -> InternalEvent {
+        InternalEvent::TickerUpdate(Ticker {
+            exchage: Exchange::Coinbase,
+            symbol: PoolSymbol::ETH_USDC,
+            price: dec!(2500),
+            timestamp: jiff::Timestamp::UNIX_EPOCH,
+            bid: None,
+            ask: None,
+            bid_size: None,
+            ask_size: None,
+            volume_24h: None,
+        })
+    }
+
+    fn halt(reason: &str) -> Option<InternalAction> {
+        Some(InternalAction::Halt { reason: reason.to_string() })
+    }
+
+    #[test]
+    fn test_and_acts_only_if_both_strategies_do() {
+        let cases = [
+            (Some("a"), Some("b"), halt("a")),
+            (Some("a"), None, None),
+            (None, Some("b"), None),
+            (None, None, None),
+        ];
+        for (first, second, expected) in cases {
+            let mut strategy = and(fixed(first), fixed(second));
+            assert_eq!(strategy.handle_internal_event(ticker()), expected);
+            assert_eq!((strategy.first.handled, strategy.second.handled), (1, 1));
+        }
+    }
+
+    #[test]
+    fn test_or_acts_if_either_strategy_does() {
+        let cases = [
+            (Some("a"), Some("b"), halt("a")),
+            (Some("a"), None, halt("a")),
+            (None, Some("b"), halt("b")),
+            (None, None, None),
+        ];
+        for (first, second, expected) in cases {
+            let mut strategy = or(fixed(first), fixed(second));
+            assert_eq!(strategy.handle_internal_event(ticker()), expected);
+            assert_eq!((strategy.first.handled, strategy.second.handled), (1, 1));
+        }
+    }
+
+    #[test]
+    fn test_composed_strategies_handle_the_symbols_of_both() {
+        let usdt = FixedStrategy { symbol: PoolSymbol::ETH_USDT, reason: None, handled: 0 };
+        let strategy = or(and(fixed(None), fixed(None)), usdt);
+        assert_eq!(strategy.symbols(), vec![PoolSymbol::ETH_USDC, PoolSymbol::ETH_USDT]);
+    }
+}
@@ -0,0 +1,232 @@
+//! Triangular arbitrage between two pools trading the same base asset against
+//! different quote assets, e.g. ETH-USDC and ETH-USDT.
+//!
+//! The CEX prices of both pairs imply the rate between the two quote assets.
+//! Converted at that rate, the DEX price of the first pool should match the
+//! DEX price of the second, and a divergence means the base asset is cheaper
+//! on one of the pools.
+
+use rust_decimal::Decimal;
+use tracing::info;
+
+use crate::{
+    engine::{FeedState, InternalAction, InternalEvent, PoolSymbol, PriceSource},
+    strategy::BotStrategy,
+};
+
+/// Latest CEX and DEX prices of one of the pools.
+#[derive(Debug, Clone)]
+struct PoolQuotes {
+    symbol: PoolSymbol,
+    cex_price: Option<Decimal>,
+    dex_price: Option<Decimal>,
+}
+
+impl PoolQuotes {
+    fn new(symbol: PoolSymbol) -> Self { Self { symbol, cex_price: None, dex_price: None } }
+}
+
+/// Divergence between the DEX prices of two pools beyond the threshold.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CrossPoolOpportunity {
+    /// Pool the base asset is cheaper on
+    pub buy: PoolSymbol,
+    /// Pool the base asset is dearer on
+    pub sell: PoolSymbol,
+    /// Price of the quote asset of the second pool in the quote asset of the
+    /// first, implied by the CEX prices
+    pub quote_rate: Decimal,
+    /// Divergence between the DEX prices in basis points of the second pool's
+    /// price
+    pub spread_bps: Decimal,
+    pub detected_at: jiff::Timestamp,
+}
+
+/// A strategy logging the triangular spread between the DEX prices of two
+/// pools.
+///
+/// No executor trades across pools yet, so opportunities are only logged.
+pub struct CrossPoolArbitrageStrategy {
+    first: PoolQuotes,
+    second: PoolQuotes,
+    /// Spread in basis points above which an opportunity is signalled
+    threshold_bps: Decimal,
+}
+
+impl CrossPoolArbitrageStrategy {
+    pub fn new(first: PoolSymbol, second: PoolSymbol, threshold_bps: Decimal) -> Self {
+        Self { first: PoolQuotes::new(first), second: PoolQuotes::new(second), threshold_bps }
+    }
+
+    fn quotes_mut(&mut self, symbol: &PoolSymbol) -> Option<&mut PoolQuotes> {
+        [&mut self.first, &mut self.second]
+            .into_iter()
+            .find(|quotes| &quotes.symbol == symbol)
+    }
+
+    /// Returns the opportunity if the DEX price of the first pool, converted
+    /// into the quote asset of the second, diverges from the DEX price of the
+    /// second pool by more than the threshold.
+    pub fn check_triangular_spread(&self, now: jiff::Timestamp) -> Option<CrossPoolOpportunity> {
+        let first_cex = self.first.cex_price?;
+        let second_cex = self.second.cex_price?;
+        let first_dex = self.first.dex_price?;
+        let second_dex = self.second.dex_price?;
+        if second_cex.is_zero() || second_dex.is_zero() {
+            return None;
+        }
+
+        let quote_rate = first_cex / second_cex;
+        if quote_rate.is_zero() {
+            return None;
+        }
+        let implied_price = first_dex / quote_rate;
+        let spread_bps = (implied_price - second_dex) / second_dex * Decimal::new(10000, 0);
+        if spread_bps.abs() <= self.threshold_bps {
+            return None;
+        }
+
+        let (buy, sell) = if spread_bps > Decimal::ZERO {
+            (self.second.symbol.clone(), self.first.symbol.clone())
+        } else {
+            (self.first.symbol.clone(), self.second.symbol.clone())
+        };
+        Some(CrossPoolOpportunity {
+            buy,
+            sell,
+            quote_rate,
+            spread_bps: spread_bps.abs(),
+            detected_at: now,
+        })
+    }
+}
+
+impl BotStrategy for CrossPoolArbitrageStrategy {
+    fn symbols(&self) -> Vec<PoolSymbol> {
+        vec![self.first.symbol.clone(), self.second.symbol.clone()]
+    }
+
+    fn handle_internal_event(&mut self, event: InternalEvent) -> Option<InternalAction> {
+        let now = match event {
+            InternalEvent::TickerUpdate(ticker) => {
+                self.quotes_mut(&ticker.symbol)?.cex_price = Some(ticker.price);
+                ticker.timestamp
+            },
+            InternalEvent::PoolPriceUpdate(update) => {
+                self.quotes_mut(&update.symbol)?.dex_price = Some(update.price);
+                update.timestamp
+            },
+            // Forget the stale price of a feed down until it is up again
+            InternalEvent::FeedStatus(status) if status.state == FeedState::Down => {
+                let quotes = self.quotes_mut(&status.symbol)?;
+                match status.source {
+                    PriceSource::Cex => quotes.cex_price = None,
+                    PriceSource::Dex => quotes.dex_price = None,
+                }
+                return None;
+            },
+            _ => return None,
+        };
+
+        if let Some(opportunity) = self.check_triangular_spread(now) {
+            info!(
+                "🔺 CROSS-POOL OPPORTUNITY: Buy {} → Sell {} | Spread: {:.2} bps | Quote Rate: {:.6}",
+                opportunity.buy, opportunity.sell, opportunity.spread_bps, opportunity.quote_rate
+            );
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal_macros::dec;
+
+    use super::*;
+    use crate::engine::{Exchange, FeedStatus, PoolPriceUpdate, Ticker};
+
+    fn strategy() -> CrossPoolArbitrageStrategy {
+        CrossPoolArbitrageStrategy::new(PoolSymbol::ETH_USDC, PoolSymbol::ETH_USDT, dec!(20))
+    }
+
+    fn ticker(symbol: PoolSymbol, price: Decimal) -> InternalEvent {
+        InternalEvent::TickerUpdate(Ticker {
+            exchage: Exchange::Coinbase,
+            symbol,
+            price,
+            timestamp: jiff::Timestamp::UNIX_EPOCH,
+            bid: None,
+            ask: None,
+            bid_size: None,
+            ask_size: None,
+            volume_24h: None,
+        })
+    }
+
+    fn pool_price(symbol: PoolSymbol, price: Decimal) -> InternalEvent {
+        InternalEvent::PoolPriceUpdate(PoolPriceUpdate {
+            symbol,
+            price,
+            timestamp: jiff::Timestamp::UNIX_EPOCH,
+            block_number: None,
+            state: None,
+            effective_buy_price: None,
+            effective_sell_price: None,
+        })
+    }
+
+    /// Feeds CEX prices implying 1 USDT = 1.001 USDC.
+    fn with_cex_prices(mut strategy: CrossPoolArbitrageStrategy) -> CrossPoolArbitrageStrategy {
+        strategy.handle_internal_event(ticker(PoolSymbol::ETH_USDC, dec!(2502.5)));
+        strategy.handle_internal_event(ticker(PoolSymbol::ETH_USDT, dec!(2500)));
+        strategy
+    }
+
+    #[test]
+    fn test_no_opportunity_within_the_threshold() {
+        let mut strategy = with_cex_prices(strategy());
+        strategy.handle_internal_event(pool_price(PoolSymbol::ETH_USDC, dec!(2502.5)));
+        strategy.handle_internal_event(pool_price(PoolSymbol::ETH_USDT, dec!(2501)));
+        assert_eq!(strategy.check_triangular_spread(jiff::Timestamp::UNIX_EPOCH), None);
+    }
+
+    #[test]
+    fn test_divergence_beyond_the_threshold_signals_an_opportunity() {
+        let mut strategy = with_cex_prices(strategy());
+        // 2527.525 USDC is 2525 USDT, 1% above the ETH-USDT pool
+        strategy.handle_internal_event(pool_price(PoolSymbol::ETH_USDC, dec!(2527.525)));
+        strategy.handle_internal_event(pool_price(PoolSymbol::ETH_USDT, dec!(2500)));
+
+        let opportunity = strategy
+            .check_triangular_spread(jiff::Timestamp::UNIX_EPOCH)
+            .unwrap();
+        assert_eq!(opportunity.buy, PoolSymbol::ETH_USDT);
+        assert_eq!(opportunity.sell, PoolSymbol::ETH_USDC);
+        assert_eq!(opportunity.quote_rate, dec!(1.001));
+        assert_eq!(opportunity.spread_bps, dec!(100));
+
+        // and the other way round once the ETH-USDT pool is dearer
+        strategy.handle_internal_event(pool_price(PoolSymbol::ETH_USDT, dec!(2550.5)));
+        let opportunity = strategy
+            .check_triangular_spread(jiff::Timestamp::UNIX_EPOCH)
+            .unwrap();
+        assert_eq!(opportunity.buy, PoolSymbol::ETH_USDC);
+        assert_eq!(opportunity.sell, PoolSymbol::ETH_USDT);
+    }
+
+    #[test]
+    fn test_no_opportunity_from_the_stale_price_of_a_feed_down() {
+        let mut strategy = with_cex_prices(strategy());
+        strategy.handle_internal_event(pool_price(PoolSymbol::ETH_USDC, dec!(2527.525)));
+        strategy.handle_internal_event(pool_price(PoolSymbol::ETH_USDT, dec!(2500)));
+        strategy.handle_internal_event(InternalEvent::FeedStatus(FeedStatus {
+            feed: "pool_price_collector_ETH-USDT".to_string(),
+            source: PriceSource::Dex,
+            symbol: PoolSymbol::ETH_USDT,
+            state: FeedState::Down,
+            reason: "stream ended".to_string(),
+            timestamp: jiff::Timestamp::UNIX_EPOCH,
+        }));
+        assert_eq!(strategy.check_triangular_spread(jiff::Timestamp::UNIX_EPOCH), None);
+    }
+}
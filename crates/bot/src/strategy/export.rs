@@ -0,0 +1,269 @@
+//! CSV export of the market making simulation.
+//!
+//! Every [`MarketMakingRange`] the simulator produces can be handed to a
+//! [`SimulationExporter`], which queues it without blocking the strategy. A
+//! single [`SimulationCsvWriter`] task drains the queue on a blocking thread
+//! into one file per symbol per UTC day,
+//! `<output_dir>/mm-<SYMBOL>-YYYY-MM-DD.csv`, see [`DailyFileWriter`].
+
+use std::{path::PathBuf, time::Duration};
+
+use rust_decimal::Decimal;
+use serde::Serialize;
+use sikkara_core::{AppError, AppResult};
+use tokio_util::sync::CancellationToken;
+
+use crate::{
+    daily_files::{DailyFileFormat, DailyFileQueue, DailyFileWriter, DailyRow},
+    engine::{MarketCondition, MarketMakingRange},
+};
+
+/// Columns of the CSV files, in the order of the fields of [`SimulationRow`].
+const HEADER: &str = "timestamp,symbol,fair_value,bid_price,ask_price,bid_spread_bps,\
+                      ask_spread_bps,total_range_width,market_condition,break_even_size,\
+                      gas_cost,reasoning";
+
+/// A simulated range as written to the CSV files.
+#[derive(Debug, Clone, Serialize)]
+struct SimulationRow {
+    #[serde(with = "sikkara_core::timestamp_with_tz_serializer")]
+    timestamp: jiff::Timestamp,
+    symbol: String,
+    fair_value: Decimal,
+    bid_price: Decimal,
+    ask_price: Decimal,
+    bid_spread_bps: u32,
+    ask_spread_bps: u32,
+    total_range_width: Decimal,
+    market_condition: MarketCondition,
+    break_even_size: Option<Decimal>,
+    gas_cost: Decimal,
+    reasoning: String,
+}
+
+impl SimulationRow {
+    fn new(timestamp: jiff::Timestamp, range: &MarketMakingRange) -> Self {
+        Self {
+            timestamp,
+            symbol: range.symbol.to_string(),
+            fair_value: range.fair_value,
+            bid_price: range.bid_price,
+            ask_price: range.ask_price,
+            bid_spread_bps: range.bid_spread_bps,
+            ask_spread_bps: range.ask_spread_bps,
+            total_range_width: range.total_range_width,
+            market_condition: range.market_condition,
+            break_even_size: range.break_even_size,
+            gas_cost: range.gas_cost,
+            reasoning: range.reasoning.clone(),
+        }
+    }
+}
+
+impl DailyRow for SimulationRow {
+    fn key(&self) -> &str { &self.symbol }
+
+    fn timestamp(&self) -> jiff::Timestamp { self.timestamp }
+}
+
+/// Queues simulated ranges for the [`SimulationCsvWriter`].
+///
+/// Cloning is cheap, all clones feed the same writer. Ranges are dropped
+/// rather than blocking the strategy when the writer falls behind.
+#[derive(Debug, Clone)]
+pub struct SimulationExporter {
+    queue: DailyFileQueue<SimulationRow>,
+}
+
+impl SimulationExporter {
+    /// Queues a range simulated at `timestamp`.
+    pub fn record(&self, timestamp: jiff::Timestamp, range: &MarketMakingRange) {
+        self.queue.push(SimulationRow::new(timestamp, range));
+    }
+
+    /// Returns the number of ranges dropped because the queue was full.
+    pub fn dropped(&self) -> u64 { self.queue.dropped() }
+}
+
+/// Writes the queued ranges to daily CSV files per symbol.
+#[derive(Debug)]
+pub struct SimulationCsvWriter {
+    writer: DailyFileWriter<SimulationRow>,
+}
+
+impl SimulationCsvWriter {
+    /// Creates a writer to `output_dir`, queueing up to `queue_size` ranges
+    /// and flushing the files every `flush_interval`.
+    pub fn new(
+        output_dir: impl Into<PathBuf>,
+        queue_size: usize,
+        flush_interval: Duration,
+    ) -> Self {
+        let format = DailyFileFormat {
+            name: "Simulation export",
+            rows: "simulated ranges",
+            prefix: "mm-",
+            extension: ".csv",
+            header: Some(HEADER),
+            format_row: write_csv_row,
+        };
+        Self { writer: DailyFileWriter::new(output_dir, format, queue_size, flush_interval) }
+    }
+
+    /// Returns an exporter feeding this writer.
+    pub fn exporter(&self) -> SimulationExporter {
+        SimulationExporter { queue: self.writer.queue() }
+    }
+
+    /// Spawns the writer on a blocking thread until shutdown is requested,
+    /// at which point the queued ranges are written and the files flushed.
+    pub fn spawn(self, shutdown: CancellationToken) -> tokio::task::JoinHandle<AppResult<()>> {
+        self.writer.spawn(shutdown)
+    }
+}
+
+/// Appends a row to the buffer as a CSV record, without the header.
+fn write_csv_row(row: &SimulationRow, buffer: &mut Vec<u8>) -> AppResult<()> {
+    let mut writer = csv::WriterBuilder::new()
+        .has_headers(false)
+        .from_writer(buffer);
+    writer.serialize(row).map_err(csv_error)?;
+    writer.flush()?;
+    Ok(())
+}
+
+fn csv_error(error: csv::Error) -> AppError {
+    AppError::SerializationError(format!("failed to write simulation row: {}", error))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use rust_decimal_macros::dec;
+
+    use super::*;
+    use crate::{engine::PoolSymbol, strategy::MarketMakingSimulator};
+
+    fn read_rows(path: &std::path::Path) -> (csv::StringRecord, Vec<csv::StringRecord>) {
+        let mut reader = csv::Reader::from_path(path).unwrap();
+        let headers = reader.headers().unwrap().clone();
+        let rows = reader.records().map(|row| row.unwrap()).collect();
+        (headers, rows)
+    }
+
+    #[tokio::test]
+    async fn test_day_of_ranges_is_written_and_rotated() {
+        let dir = tempfile::tempdir().unwrap();
+        let writer = SimulationCsvWriter::new(dir.path(), 1024, Duration::from_millis(10));
+        let exporter = writer.exporter();
+
+        // A range every 10 minutes for a day, starting at noon
+        let start: jiff::Timestamp = "2025-02-12T12:00:00Z".parse().unwrap();
+        let eth = MarketMakingSimulator::new_with_default(PoolSymbol::ETH_USDC);
+        let btc = MarketMakingSimulator::new_with_default(PoolSymbol::USDC_CBBTC);
+        for step in 0..144 {
+            let timestamp = start + jiff::SignedDuration::from_mins(10 * step);
+            let dex_price = if step % 2 == 0 { dec!(2500) } else { dec!(2550) };
+            exporter.record(timestamp, &eth.calculate_ranges(dec!(2500), Some(dex_price)));
+            exporter.record(timestamp, &btc.calculate_ranges(dec!(97000), None));
+        }
+
+        let shutdown = CancellationToken::new();
+        let task = writer.spawn(shutdown.clone());
+        shutdown.cancel();
+        task.await.unwrap().unwrap();
+        assert_eq!(exporter.dropped(), 0);
+
+        let mut files = fs::read_dir(dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect::<Vec<_>>();
+        files.sort();
+        assert_eq!(
+            files,
+            vec![
+                "mm-ETH-USDC-2025-02-12.csv",
+                "mm-ETH-USDC-2025-02-13.csv",
+                "mm-USDC-CBBTC-2025-02-12.csv",
+                "mm-USDC-CBBTC-2025-02-13.csv",
+            ]
+        );
+
+        let (headers, rows) = read_rows(&dir.path().join("mm-ETH-USDC-2025-02-12.csv"));
+        assert_eq!(
+            headers.iter().collect::<Vec<_>>(),
+            vec![
+                "timestamp",
+                "symbol",
+                "fair_value",
+                "bid_price",
+                "ask_price",
+                "bid_spread_bps",
+                "ask_spread_bps",
+                "total_range_width",
+                "market_condition",
+                "break_even_size",
+                "gas_cost",
+                "reasoning",
+            ]
+        );
+        assert_eq!(rows.len(), 72);
+        assert_eq!(&rows[0][0], "2025-02-12T12:00:00Z");
+        assert_eq!(&rows[0][1], "ETH-USDC");
+        assert_eq!(&rows[0][8], "normal");
+        assert_eq!(&rows[0][10], "0.5");
+        assert_eq!(&rows[71][0], "2025-02-12T23:50:00Z");
+        // The 2% DEX premium skews the spreads, and the reasoning is quoted
+        assert_eq!(&rows[1][8], "arbitrage");
+        assert!(rows[1][11].contains("asymmetric spreads"));
+
+        let range = eth.calculate_ranges(dec!(2500), Some(dec!(2500)));
+        assert_eq!(&rows[0][3], range.bid_price.to_string());
+        assert_eq!(&rows[0][9], range.break_even_size.unwrap().to_string());
+
+        let (_, rows) = read_rows(&dir.path().join("mm-ETH-USDC-2025-02-13.csv"));
+        assert_eq!(rows.len(), 72);
+        assert_eq!(&rows[0][0], "2025-02-13T00:00:00Z");
+    }
+
+    #[tokio::test]
+    async fn test_ranges_are_flushed_under_steady_traffic() {
+        let dir = tempfile::tempdir().unwrap();
+        let writer = SimulationCsvWriter::new(dir.path(), 1024, Duration::from_millis(10));
+        let exporter = writer.exporter();
+        let shutdown = CancellationToken::new();
+        let task = writer.spawn(shutdown.clone());
+
+        // Ranges keep arriving within the flush interval, and fewer than fill
+        // the buffer of the CSV writer
+        let range = MarketMakingSimulator::new_with_default(PoolSymbol::ETH_USDC)
+            .calculate_ranges(dec!(2500), None);
+        let timestamp: jiff::Timestamp = "2025-02-12T12:00:00Z".parse().unwrap();
+        for _ in 0..10 {
+            exporter.record(timestamp, &range);
+            tokio::time::sleep(Duration::from_millis(3)).await;
+        }
+        let (_, rows) = read_rows(&dir.path().join("mm-ETH-USDC-2025-02-12.csv"));
+        assert!(!rows.is_empty());
+
+        shutdown.cancel();
+        task.await.unwrap().unwrap();
+        let (_, rows) = read_rows(&dir.path().join("mm-ETH-USDC-2025-02-12.csv"));
+        assert_eq!(rows.len(), 10);
+    }
+
+    #[tokio::test]
+    async fn test_ranges_are_dropped_when_the_queue_is_full() {
+        let dir = tempfile::tempdir().unwrap();
+        let writer = SimulationCsvWriter::new(dir.path(), 1, Duration::from_millis(10));
+        let exporter = writer.exporter();
+        let range = MarketMakingSimulator::new_with_default(PoolSymbol::ETH_USDC)
+            .calculate_ranges(dec!(2500), None);
+        let timestamp = "2025-02-12T12:00:00Z".parse().unwrap();
+
+        exporter.record(timestamp, &range);
+        exporter.record(timestamp, &range);
+        assert_eq!(exporter.dropped(), 1);
+    }
+}
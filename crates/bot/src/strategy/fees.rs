@@ -0,0 +1,102 @@
+//! Fees of an arbitrage round trip, buying on one venue and selling on the
+//! other.
+
+use rust_decimal::Decimal;
+
+use crate::config::FeeConfig;
+
+/// Fees paid by a round trip between the CEX and a pool, and the net profit
+/// it must exceed to be worth trading.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RoundTripFees {
+    /// Taker fee of the CEX leg in basis points
+    cex_taker_bps: Decimal,
+    /// Fee tier of the pool in hundredths of a basis point, e.g. 500 for 0.05%
+    dex_fee_tier: u32,
+    /// Gas of the DEX leg in USD
+    gas_cost_usd: Decimal,
+    min_profit_usd: Decimal,
+}
+
+impl RoundTripFees {
+    pub fn new(config: &FeeConfig, dex_fee_tier: u32, gas_cost_usd: Decimal) -> Self {
+        Self {
+            cex_taker_bps: config.cex_taker_bps,
+            dex_fee_tier,
+            gas_cost_usd,
+            min_profit_usd: config.min_profit_usd,
+        }
+    }
+
+    /// LP fee of the pool as a fraction of the swapped notional.
+    pub fn dex_fee_rate(&self) -> Decimal {
+        Decimal::from(self.dex_fee_tier) / Decimal::new(1_000_000, 0)
+    }
+
+    /// Profit in USD of trading `size` of the base asset across the two
+    /// prices, net of the CEX taker fee, the pool LP fee and the gas.
+    pub fn net_profit(&self, cex_price: Decimal, dex_price: Decimal, size: Decimal) -> Decimal {
+        let gross = (cex_price - dex_price).abs() * size;
+        let cex_fee = cex_price * size * self.cex_taker_bps / Decimal::new(10000, 0);
+        let dex_fee = dex_price * size * self.dex_fee_rate();
+        gross - cex_fee - dex_fee - self.gas_cost_usd
+    }
+
+    /// Returns the net profit of the round trip if it is positive and exceeds
+    /// the minimum profit.
+    pub fn worthwhile_profit(
+        &self,
+        cex_price: Decimal,
+        dex_price: Decimal,
+        size: Decimal,
+    ) -> Option<Decimal> {
+        let profit = self.net_profit(cex_price, dex_price, size);
+        (profit > Decimal::ZERO && profit > self.min_profit_usd).then_some(profit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal_macros::dec;
+
+    use super::*;
+
+    fn fees(min_profit_usd: Decimal, gas_cost_usd: Decimal) -> RoundTripFees {
+        let config = FeeConfig { cex_taker_bps: dec!(60), min_profit_usd };
+        RoundTripFees::new(&config, 500, gas_cost_usd)
+    }
+
+    #[test]
+    fn test_fee_tier_is_in_hundredths_of_a_basis_point() {
+        assert_eq!(fees(Decimal::ZERO, Decimal::ZERO).dex_fee_rate(), dec!(0.0005));
+        let config = FeeConfig { cex_taker_bps: Decimal::ZERO, min_profit_usd: Decimal::ZERO };
+        assert_eq!(RoundTripFees::new(&config, 3000, Decimal::ZERO).dex_fee_rate(), dec!(0.003));
+    }
+
+    #[test]
+    fn test_net_profit_breaks_even_once_the_spread_pays_the_fees() {
+        // A round trip of 1 ETH around 2000 pays 12 to the CEX, about 1 to the
+        // LP and 2 of gas, so the prices must be about 15 apart to break even
+        let fees = fees(Decimal::ZERO, dec!(2));
+        assert_eq!(fees.net_profit(dec!(2000), dec!(2000), dec!(1)), dec!(-15));
+        assert_eq!(fees.net_profit(dec!(2000), dec!(1985), dec!(1)), dec!(0.0075));
+        assert_eq!(fees.net_profit(dec!(2000), dec!(1985.01), dec!(1)), dec!(-0.002505));
+
+        // Worth nothing at break even, and a loss is never worth trading
+        assert_eq!(fees.worthwhile_profit(dec!(2000), dec!(1985.01), dec!(1)), None);
+        assert_eq!(fees.worthwhile_profit(dec!(2000), dec!(2000), dec!(1)), None);
+        assert_eq!(fees.worthwhile_profit(dec!(2000), dec!(1985), dec!(1)), Some(dec!(0.0075)));
+    }
+
+    #[test]
+    fn test_profit_must_exceed_the_minimum() {
+        let fees = fees(dec!(5), Decimal::ZERO);
+        // 20 apart leaves 20 - 12 - 0.99 = 7.01, 18 apart 18 - 12 - 0.991 = 5.009
+        assert_eq!(fees.worthwhile_profit(dec!(2000), dec!(1980), dec!(1)), Some(dec!(7.01)));
+        assert_eq!(fees.worthwhile_profit(dec!(2000), dec!(1982), dec!(1)), Some(dec!(5.009)));
+        // 17.98 apart leaves 17.98 - 12 - 0.99101 = 4.98899
+        assert_eq!(fees.worthwhile_profit(dec!(2000), dec!(1982.02), dec!(1)), None);
+        // Selling on the DEX pays the same fees
+        assert_eq!(fees.worthwhile_profit(dec!(1980), dec!(2000), dec!(1)), Some(dec!(7.12)));
+    }
+}
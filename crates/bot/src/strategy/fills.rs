@@ -0,0 +1,217 @@
+//! Fills of the simulated market making ranges, as if the bid and the ask of
+//! the last range were resting orders hit by the CEX price.
+
+use rust_decimal::Decimal;
+
+/// Side of a simulated fill, from the point of view of the market maker.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FillSide {
+    Buy,
+    Sell,
+}
+
+/// Simulated fill of a side of the last quoted range.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SimulatedFill {
+    pub side: FillSide,
+    pub price: Decimal,
+    /// Filled size in the base asset
+    pub size: Decimal,
+    /// Edge of the fill over the fair value the range was quoted around, in
+    /// the quote asset
+    pub spread_capture: Decimal,
+}
+
+/// Bid and ask of a range, around its fair value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Quote {
+    bid: Decimal,
+    ask: Decimal,
+    fair_value: Decimal,
+}
+
+/// Inventory, average entry, cash and spread capture of the fills of the
+/// simulated ranges.
+///
+/// A quote fills at most once: a fill consumes it until the next range is
+/// quoted. Fills are cut so that the inventory stays within the maximum, long
+/// or short, and a side whose inventory is at the maximum does not fill.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FillSimulator {
+    /// Size of every fill, in the base asset
+    clip_size: Decimal,
+    /// Largest long or short inventory, in the base asset
+    max_inventory: Decimal,
+    quote: Option<Quote>,
+    /// Position in the base asset, negative if short
+    inventory: Decimal,
+    /// Average price the open position was entered at, zero if flat
+    average_entry: Decimal,
+    /// Quote asset received by sales less the quote asset paid by purchases
+    cash: Decimal,
+    spread_capture: Decimal,
+    fill_count: u64,
+}
+
+impl FillSimulator {
+    pub fn new(clip_size: Decimal, max_inventory: Decimal) -> Self {
+        Self {
+            clip_size,
+            max_inventory,
+            quote: None,
+            inventory: Decimal::ZERO,
+            average_entry: Decimal::ZERO,
+            cash: Decimal::ZERO,
+            spread_capture: Decimal::ZERO,
+            fill_count: 0,
+        }
+    }
+
+    /// Quotes the bid and the ask of a new range around `fair_value`,
+    /// replacing the last quote.
+    pub fn quote(&mut self, bid: Decimal, ask: Decimal, fair_value: Decimal) {
+        self.quote = Some(Quote { bid, ask, fair_value });
+    }
+
+    /// Fills the last quote if `price` crossed it, buying at the bid if at or
+    /// below it and selling at the ask if at or above it.
+    pub fn on_price(&mut self, price: Decimal) -> Option<SimulatedFill> {
+        let quote = self.quote?;
+        let (side, fill_price, room) = if price <= quote.bid {
+            (FillSide::Buy, quote.bid, self.max_inventory - self.inventory)
+        } else if price >= quote.ask {
+            (FillSide::Sell, quote.ask, self.max_inventory + self.inventory)
+        } else {
+            return None;
+        };
+        let size = self.clip_size.min(room);
+        if size <= Decimal::ZERO {
+            return None;
+        }
+
+        let signed_size = match side {
+            FillSide::Buy => size,
+            FillSide::Sell => -size,
+        };
+        let spread_capture = (quote.fair_value - fill_price) * signed_size;
+        self.enter(signed_size, fill_price);
+        self.cash -= signed_size * fill_price;
+        self.spread_capture += spread_capture;
+        self.fill_count += 1;
+        self.quote = None;
+        Some(SimulatedFill { side, price: fill_price, size, spread_capture })
+    }
+
+    /// Moves the inventory by `signed_size` at `price`, averaging the entry
+    /// of a position increased, keeping that of a position reduced, and
+    /// entering a position flipped at `price`.
+    fn enter(&mut self, signed_size: Decimal, price: Decimal) {
+        let inventory = self.inventory + signed_size;
+        if inventory.is_zero() {
+            self.average_entry = Decimal::ZERO;
+        } else if self.inventory.is_zero()
+            || self.inventory.is_sign_positive() == signed_size.is_sign_positive()
+        {
+            self.average_entry =
+                (self.average_entry * self.inventory + price * signed_size) / inventory;
+        } else if self.inventory.is_sign_positive() != inventory.is_sign_positive() {
+            self.average_entry = price;
+        }
+        self.inventory = inventory;
+    }
+
+    pub fn inventory(&self) -> Decimal { self.inventory }
+
+    /// Average price the open position was entered at, None if flat.
+    pub fn average_entry(&self) -> Option<Decimal> {
+        (!self.inventory.is_zero()).then_some(self.average_entry)
+    }
+
+    pub fn cash(&self) -> Decimal { self.cash }
+
+    /// Edge of all the fills over the fair values, in the quote asset.
+    pub fn spread_capture(&self) -> Decimal { self.spread_capture }
+
+    pub fn fill_count(&self) -> u64 { self.fill_count }
+
+    /// Profit and loss in the quote asset, the inventory being marked at
+    /// `mark_price`.
+    pub fn pnl(&self, mark_price: Decimal) -> Decimal { self.cash + self.inventory * mark_price }
+}
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal_macros::dec;
+
+    use super::*;
+
+    #[test]
+    fn test_fills_track_the_position() {
+        let mut fills = FillSimulator::new(dec!(1), dec!(2));
+        // Nothing fills before a range is quoted, nor within the range
+        assert_eq!(fills.on_price(dec!(2400)), None);
+        fills.quote(dec!(2490), dec!(2510), dec!(2500));
+        assert_eq!(fills.on_price(dec!(2495)), None);
+
+        let fill = fills.on_price(dec!(2489)).unwrap();
+        assert_eq!(
+            fill,
+            SimulatedFill {
+                side: FillSide::Buy,
+                price: dec!(2490),
+                size: dec!(1),
+                spread_capture: dec!(10)
+            }
+        );
+        // The quote filled once
+        assert_eq!(fills.on_price(dec!(2480)), None);
+
+        fills.quote(dec!(2470), dec!(2490), dec!(2480));
+        fills.on_price(dec!(2470)).unwrap();
+        assert_eq!(fills.inventory(), dec!(2));
+        assert_eq!(fills.average_entry(), Some(dec!(2480)));
+
+        // At the maximum inventory the bid no longer fills, but the ask does
+        fills.quote(dec!(2460), dec!(2480), dec!(2470));
+        assert_eq!(fills.on_price(dec!(2455)), None);
+        let fill = fills.on_price(dec!(2485)).unwrap();
+        assert_eq!((fill.side, fill.price), (FillSide::Sell, dec!(2480)));
+        // Reducing the position keeps its entry
+        assert_eq!(fills.inventory(), dec!(1));
+        assert_eq!(fills.average_entry(), Some(dec!(2480)));
+
+        fills.quote(dec!(2490), dec!(2510), dec!(2500));
+        fills.on_price(dec!(2515)).unwrap();
+        assert_eq!(fills.inventory(), Decimal::ZERO);
+        assert_eq!(fills.average_entry(), None);
+        assert_eq!(fills.fill_count(), 4);
+        assert_eq!(fills.cash(), dec!(30));
+        assert_eq!(fills.pnl(dec!(2600)), dec!(30));
+        assert_eq!(fills.spread_capture(), dec!(40));
+    }
+
+    #[test]
+    fn test_fills_are_cut_to_the_maximum_inventory() {
+        let mut fills = FillSimulator::new(dec!(1), dec!(1.5));
+        fills.quote(dec!(2490), dec!(2510), dec!(2500));
+        fills.on_price(dec!(2520)).unwrap();
+        fills.quote(dec!(2500), dec!(2520), dec!(2510));
+        let fill = fills.on_price(dec!(2520)).unwrap();
+        assert_eq!(fill.size, dec!(0.5));
+        assert_eq!(fills.inventory(), dec!(-1.5));
+        assert_eq!(fills.average_entry().unwrap().round_dp(2), dec!(2513.33));
+
+        // Reducing the short keeps its entry, buying back more than it flips
+        // the position at the fill
+        fills.quote(dec!(2490), dec!(2510), dec!(2500));
+        fills.on_price(dec!(2490)).unwrap();
+        assert_eq!(fills.inventory(), dec!(-0.5));
+        assert_eq!(fills.average_entry().unwrap().round_dp(2), dec!(2513.33));
+        fills.quote(dec!(2480), dec!(2500), dec!(2490));
+        fills.on_price(dec!(2470)).unwrap();
+        assert_eq!(fills.inventory(), dec!(0.5));
+        assert_eq!(fills.average_entry(), Some(dec!(2480)));
+        // 40 on the short bought back, and 10 on the long marked at 2500
+        assert_eq!(fills.pnl(dec!(2500)), dec!(50));
+    }
+}
@@ -1,65 +1,514 @@
-use rust_decimal::Decimal;
+use std::{
+    collections::{HashMap, HashSet},
+    time::Duration,
+};
+
+use rust_decimal::{prelude::ToPrimitive, Decimal};
+use sikkara_adapters::PoolSlotData;
+use sikkara_core::EmaCalculator;
+use tokio::sync::watch;
 use tracing::{info, warn};
 
 use crate::{
     config::MarketMakingConfig,
-    engine::{InternalEvent, MarketCondition, PoolSymbol},
-    strategy::{market_making::MarketMakingSimulator, BotStrategy},
+    engine::{
+        ArbitrageDirection, ArbitrageOpportunity, DivergenceTracker, Exchange, FeedState,
+        InternalAction, InternalEvent, MarketCondition, MarketMakingRange, Pool, PoolSymbol,
+        PriceHistoryReader, PriceSource, SuppressedOpportunity, SuppressionReason, TwapCalculator,
+    },
+    positions::BalanceBook,
+    strategy::{
+        market_making::{MarketMakingSimulator, TickGrid},
+        BotStrategy, FillSimulator, PaperTradeLedger, RoundTripFees, SimulationExporter,
+        UpdateSkewTracker,
+    },
 };
 
+/// Interval at which the CEX/DEX update skew summary is logged
+const SKEW_SUMMARY_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Window over which the realized CEX volatility is logged
+const VOLATILITY_WINDOW: Duration = Duration::from_secs(5 * 60);
+
 /// A simple logging arbitrage strategy that logs if an arbitrage opportunity
 /// exists and simulates market making ranges
 pub struct LoggingBotStrategy {
     symbol: PoolSymbol,
+    /// Last price and time of every exchange feeding the strategy
+    cex_prices: HashMap<Exchange, (Decimal, jiff::Timestamp)>,
     last_cex_price: Option<Decimal>,
     last_dex_price: Option<Decimal>,
+    last_cex_timestamp: Option<jiff::Timestamp>,
+    last_dex_timestamp: Option<jiff::Timestamp>,
+    /// Block the last DEX price was read at, if known
+    last_dex_block: Option<u64>,
+    /// Pool state the last DEX price was derived from, if known
+    last_dex_state: Option<PoolSlotData>,
+    /// Prices buying and selling on the pool last executed at, if quoted
+    last_effective_buy_price: Option<Decimal>,
+    last_effective_sell_price: Option<Decimal>,
     simulator: MarketMakingSimulator,
+    skew: UpdateSkewTracker,
+    history: Option<PriceHistoryReader>,
+    exporter: Option<SimulationExporter>,
+    /// Size recommended for every opportunity, in the base asset
+    trade_size: Decimal,
+    /// Largest move of the pool price in basis points that trading
+    /// `trade_size` on the pool may cause
+    acceptable_price_impact_bps: u32,
+    /// Feeds reported down, whose last price is stale
+    down_feeds: HashSet<PriceSource>,
+    /// Average of the CEX prices used as the fair value instead of the last
+    /// tick, if enabled
+    twap: Option<TwapCalculator>,
+    /// Average of the pool prices detecting arbitrage instead of the last
+    /// pool price, if enabled
+    dex_ema: Option<EmaCalculator>,
+    /// Trades the strategy would have made, if paper trading
+    ledger: Option<PaperTradeLedger>,
+    /// Fees deducted from the profit of opportunities, the raw price
+    /// difference being signalled otherwise
+    fees: Option<RoundTripFees>,
+    /// Divergence between the prices shared with the pool feed, if it polls
+    /// adaptively
+    divergence: Option<DivergenceTracker>,
+    /// Fills of the simulated ranges by the CEX price, if simulated
+    fills: Option<FillSimulator>,
+    /// Number of price updates between two summaries of the simulated position
+    fill_summary_interval: u64,
+    /// Number of price updates since the last summary of the simulated
+    /// position
+    updates_since_fill_summary: u64,
+    /// Market making parameters of the reloaded configurations, if reloaded
+    market_making_updates: Option<watch::Receiver<MarketMakingConfig>>,
+    /// Balances held, skewing the simulated ranges by the inventory if
+    /// tracked
+    balances: Option<BalanceBook>,
 }
 
 impl LoggingBotStrategy {
     pub fn new(symbol: PoolSymbol, config: MarketMakingConfig) -> Self {
+        let twap = config
+            .use_twap
+            .then(|| TwapCalculator::new(Duration::from_secs(config.twap_window_secs)));
+        let skew = UpdateSkewTracker::new(symbol.clone(), SKEW_SUMMARY_INTERVAL);
+        let acceptable_price_impact_bps = config.acceptable_price_impact_bps;
+        let fills = config
+            .fill_simulation
+            .as_ref()
+            .map(|fills| FillSimulator::new(fills.clip_size, fills.max_inventory));
+        let fill_summary_interval = config
+            .fill_simulation
+            .as_ref()
+            .map_or(0, |fills| fills.summary_interval);
         let simulator = MarketMakingSimulator::new(symbol.clone(), config);
-        Self { symbol, last_cex_price: None, last_dex_price: None, simulator }
+        Self {
+            symbol,
+            cex_prices: HashMap::new(),
+            last_cex_price: None,
+            last_dex_price: None,
+            last_cex_timestamp: None,
+            last_dex_timestamp: None,
+            last_dex_block: None,
+            last_dex_state: None,
+            last_effective_buy_price: None,
+            last_effective_sell_price: None,
+            simulator,
+            skew,
+            history: None,
+            exporter: None,
+            trade_size: Decimal::ZERO,
+            acceptable_price_impact_bps,
+            down_feeds: HashSet::new(),
+            twap,
+            dex_ema: None,
+            ledger: None,
+            fees: None,
+            divergence: None,
+            fills,
+            fill_summary_interval,
+            updates_since_fill_summary: 0,
+            market_making_updates: None,
+            balances: None,
+        }
+    }
+
+    /// Uses the given price history to log the realized volatility.
+    pub fn with_price_history(mut self, history: PriceHistoryReader) -> Self {
+        self.history = Some(history);
+        self
+    }
+
+    /// Exports every simulated range to CSV through the given exporter.
+    pub fn with_exporter(mut self, exporter: SimulationExporter) -> Self {
+        self.exporter = Some(exporter);
+        self
+    }
+
+    /// Recommends trading `trade_size` of the base asset on every opportunity,
+    /// whose gas cost the simulated spreads cover.
+    pub fn with_trade_size(mut self, trade_size: Decimal) -> Self {
+        self.trade_size = trade_size;
+        self.simulator.trade_size = trade_size;
+        self
+    }
+
+    /// Aligns the simulated ranges to the ticks of `pool`.
+    pub fn with_pool(mut self, pool: &Pool) -> Self {
+        self.simulator.tick_grid = Some(TickGrid::from(pool));
+        self
+    }
+
+    /// Detects arbitrage on the moving average of the pool prices computed by
+    /// `ema`, so that a single spike does not signal an opportunity.
+    pub fn with_dex_ema(mut self, ema: EmaCalculator) -> Self {
+        self.dex_ema = Some(ema);
+        self
+    }
+
+    /// Signals only the opportunities whose profit net of `fees` is worth
+    /// trading.
+    pub fn with_fees(mut self, fees: RoundTripFees) -> Self {
+        self.fees = Some(fees);
+        self
+    }
+
+    /// Records the divergence between the prices in `divergence`, from which
+    /// the pool feed adapts its polling.
+    pub fn with_divergence_tracker(mut self, divergence: DivergenceTracker) -> Self {
+        self.divergence = Some(divergence);
+        self
+    }
+
+    /// Logs the trade of every opportunity and accounts it in a paper ledger,
+    /// whose summary is logged once the strategy stops.
+    pub fn with_paper_trading(mut self) -> Self {
+        self.ledger = Some(PaperTradeLedger::new());
+        self
+    }
+
+    /// Skews the simulated ranges by the inventory of the base and quote
+    /// assets held across all venues of `balances`.
+    pub fn with_balances(mut self, balances: BalanceBook) -> Self {
+        self.balances = Some(balances);
+        self
+    }
+
+    /// Applies the spreads, thresholds, factors, gas and capital of the market
+    /// making parameters sent through `updates` from the next event on.
+    pub fn with_market_making_updates(
+        mut self,
+        updates: watch::Receiver<MarketMakingConfig>,
+    ) -> Self {
+        self.market_making_updates = Some(updates);
+        self
+    }
+
+    /// Applies the market making parameters reloaded since the last event, if
+    /// any.
+    fn apply_market_making_updates(&mut self) {
+        let Some(updates) = &mut self.market_making_updates else { return };
+        if !updates.has_changed().unwrap_or(false) {
+            return;
+        }
+        let config = updates.borrow_and_update().clone();
+        self.simulator.update_parameters(&config);
+        self.acceptable_price_impact_bps = config.acceptable_price_impact_bps;
+        info!(
+            "🔄 Market making parameters reloaded: {} bps base spread ({} - {} bps) | Symbol: {}",
+            config.base_spread_bps, config.min_spread_bps, config.max_spread_bps, self.symbol
+        );
+    }
+
+    /// Hands the inventory of the base and quote assets to the simulator once
+    /// the balances of both are known.
+    fn update_inventory(&mut self) {
+        let Some(balances) = &self.balances else { return };
+        let base = balances.held(self.symbol.base_asset());
+        let quote = balances.held(self.symbol.quote_asset());
+        if let (Some(base), Some(quote)) = (base, quote) {
+            self.simulator.set_inventory(base, quote);
+        }
+    }
+
+    /// Realized volatility of the CEX price over [`VOLATILITY_WINDOW`] in basis
+    /// points, i.e. the root mean square of the returns between ticks.
+    fn realized_volatility_bps(&self) -> Option<f64> {
+        let returns = self
+            .history
+            .as_ref()?
+            .read()
+            .returns(PriceSource::Cex, VOLATILITY_WINDOW);
+        if returns.is_empty() {
+            return None;
+        }
+        let sum_squares = returns
+            .iter()
+            .filter_map(|r| r.to_f64())
+            .map(|r| r * r)
+            .sum::<f64>();
+        Some((sum_squares / returns.len() as f64).sqrt() * 10_000.0)
     }
 
     /// Check for arbitrage opportunities and run market making simulation
-    fn check_arbitrage_and_simulate_mm(&self) {
+    fn check_arbitrage_and_simulate_mm(&mut self, now: jiff::Timestamp) -> Option<InternalAction> {
         if let (Some(cex_price), Some(dex_price)) = (self.last_cex_price, self.last_dex_price) {
-            // 1. Check for simple arbitrage opportunities
-            self.log_arbitrage_opportunity(cex_price, dex_price);
+            // 0. Measure how far apart in time the paired quotes are
+            if let Some(dex_timestamp) = self.last_dex_timestamp {
+                self.skew.record_skew(dex_timestamp);
+            }
+            self.skew.maybe_summarize(now);
+            if let Some(divergence) = &self.divergence {
+                divergence.record(cex_price, dex_price);
+            }
+
+            // 1. Check for simple arbitrage opportunities, at the price the trade
+            // on the pool would execute at if quoted
+            let executable_price = self.executable_dex_price(cex_price, dex_price);
+            let opportunity = self.log_arbitrage_opportunity(cex_price, executable_price);
+
+            // 2. Run market making simulation, quoting its range to the
+            // simulated fills
+            let mm_range = self.run_market_making_simulation(cex_price, dex_price, now);
+            if let Some(fills) = &mut self.fills {
+                fills.quote(mm_range.bid_price, mm_range.ask_price, mm_range.fair_value);
+            }
+            self.maybe_summarize_fills();
 
-            // 2. Run market making simulation
-            self.run_market_making_simulation(cex_price, dex_price);
+            let action = opportunity.map(|opportunity| self.suppress_if_unsafe(opportunity));
+            if let Some(InternalAction::Opportunity(opportunity)) = &action {
+                self.record_paper_trade(opportunity);
+            }
+            return action;
+        }
+        None
+    }
+
+    /// Logs the trade of an opportunity and accounts it, if paper trading.
+    fn record_paper_trade(&mut self, opportunity: &ArbitrageOpportunity) {
+        let Some(ledger) = &mut self.ledger else { return };
+        let profit = ledger.record(opportunity);
+        info!(
+            "📝 PAPER TRADE: {:?} {} {} | Expected Profit: ${:.2} | Symbol: {}",
+            opportunity.direction,
+            opportunity.recommended_size,
+            self.symbol.base_asset(),
+            profit,
+            self.symbol
+        );
+    }
+
+    /// Fills the last simulated range if the CEX `price` crossed it, logging
+    /// the fill.
+    fn simulate_fill(&mut self, price: Decimal) {
+        let Some(fills) = &mut self.fills else { return };
+        let Some(fill) = fills.on_price(price) else { return };
+        info!(
+            "🧪 SIMULATED FILL: {:?} {} {} @ ${:.2} | Spread Capture: ${:.2} | Inventory: {} {} | Symbol: {}",
+            fill.side,
+            fill.size,
+            self.symbol.base_asset(),
+            fill.price,
+            fill.spread_capture,
+            fills.inventory(),
+            self.symbol.base_asset(),
+            self.symbol
+        );
+    }
+
+    /// Logs the simulated position every `fill_summary_interval` price updates.
+    fn maybe_summarize_fills(&mut self) {
+        if self.fills.is_none() {
+            return;
+        }
+        self.updates_since_fill_summary += 1;
+        if self.updates_since_fill_summary >= self.fill_summary_interval {
+            self.updates_since_fill_summary = 0;
+            self.log_fill_summary();
+        }
+    }
+
+    /// Logs the simulated position, marked at the last CEX price.
+    fn log_fill_summary(&self) {
+        let (Some(fills), Some(mark_price)) = (&self.fills, self.last_cex_price) else {
+            return;
+        };
+        info!(
+            "📦 SIMULATED POSITION: {} {} | Avg Entry: ${:.2} | Cash: ${:.2} | Spread Capture: ${:.2} | PnL: ${:.2} | Fills: {} | Symbol: {}",
+            fills.inventory(),
+            self.symbol.base_asset(),
+            fills.average_entry().unwrap_or_default(),
+            fills.cash(),
+            fills.spread_capture(),
+            fills.pnl(mark_price),
+            fills.fill_count(),
+            self.symbol
+        );
+    }
+
+    /// Suppresses an opportunity priced from a feed that is down, or whose
+    /// trade would move the pool price beyond the acceptable impact.
+    fn suppress_if_unsafe(&self, opportunity: ArbitrageOpportunity) -> InternalAction {
+        let down = [PriceSource::Cex, PriceSource::Dex]
+            .into_iter()
+            .find(|source| self.down_feeds.contains(source));
+        let reason = match down {
+            Some(source) => Some(SuppressionReason::FeedDown { source }),
+            None => self
+                .price_impact_bps(&opportunity)
+                .filter(|impact_bps| *impact_bps > self.acceptable_price_impact_bps)
+                .map(|impact_bps| SuppressionReason::PriceImpact { impact_bps }),
+        };
+        match reason {
+            Some(reason) => {
+                warn!("🚫 Opportunity suppressed: {} | Symbol: {}", reason, self.symbol);
+                InternalAction::Suppressed(SuppressedOpportunity { opportunity, reason })
+            },
+            None => InternalAction::Opportunity(opportunity),
+        }
+    }
+
+    /// Price impact in basis points of trading the recommended size of an
+    /// opportunity on the pool, estimated from the last pool state. None
+    /// without a trade size or a pool state to estimate it from.
+    fn price_impact_bps(&self, opportunity: &ArbitrageOpportunity) -> Option<u32> {
+        let state = self.last_dex_state.as_ref()?;
+        if opportunity.recommended_size.is_zero() {
+            return None;
+        }
+
+        // The pool price is of the base asset, which is token 0 if inverted
+        let (amount_in, zero_for_one) = match opportunity.direction {
+            // Selling the base asset bought on the CEX
+            ArbitrageDirection::BuyCexSellDex => (opportunity.recommended_size, state.invert),
+            // Paying the quote asset for the base asset sold on the CEX
+            ArbitrageDirection::BuyDexSellCex => {
+                (opportunity.recommended_size * opportunity.dex_price, !state.invert)
+            },
+        };
+        let price = Decimal::from_str_exact(&state.spot_price.to_fixed(18, None)).ok()?;
+        let new_price = match state.estimate_swap_output(amount_in, zero_for_one, None) {
+            Ok((_, new_price)) => new_price,
+            Err(e) => {
+                warn!("Failed to estimate the price impact on {}: {}", self.symbol, e);
+                return None;
+            },
+        };
+        if price.is_zero() {
+            return None;
+        }
+        ((new_price - price).abs() / price * Decimal::new(10000, 0))
+            .round()
+            .to_u32()
+    }
+
+    /// Price an arbitrage against `cex_price` would trade at on the pool: the
+    /// effective price of buying on the pool below the CEX price, or of
+    /// selling on it above, if quoted, and `dex_price` otherwise.
+    ///
+    /// An effective price beyond the CEX price leaves no opportunity, so it is
+    /// capped at the CEX price.
+    fn executable_dex_price(&self, cex_price: Decimal, dex_price: Decimal) -> Decimal {
+        if cex_price > dex_price {
+            self.last_effective_buy_price
+                .map_or(dex_price, |price| price.min(cex_price))
+        } else {
+            self.last_effective_sell_price
+                .map_or(dex_price, |price| price.max(cex_price))
         }
     }
 
-    /// Log arbitrage opportunities
+    /// Log arbitrage opportunities, returning the opportunity if one exists
     #[allow(clippy::comparison_chain)]
-    fn log_arbitrage_opportunity(&self, cex_price: Decimal, dex_price: Decimal) {
+    fn log_arbitrage_opportunity(
+        &self,
+        cex_price: Decimal,
+        dex_price: Decimal,
+    ) -> Option<ArbitrageOpportunity> {
         let diff = (cex_price - dex_price).abs();
         let profit_pct = (diff / cex_price) * Decimal::new(100, 0);
 
-        // Only log if there's a meaningful difference (e.g., > 0.1%)
-        if profit_pct > Decimal::new(10, 2) {
-            // 0.1%
-            if cex_price > dex_price {
-                info!(
-                    "🚀 ARBITRAGE OPPORTUNITY: Buy DEX ${:.2} → Sell CEX ${:.2} | Profit: ${:.2} ({:.2}%) | Symbol: {}",
-                    dex_price, cex_price, diff, profit_pct, self.symbol
-                );
-            } else if dex_price > cex_price {
-                info!(
-                    "🚀 ARBITRAGE OPPORTUNITY: Buy CEX ${:.2} → Sell DEX ${:.2} | Profit: ${:.2} ({:.2}%) | Symbol: {}",
-                    cex_price, dex_price, diff, profit_pct, self.symbol
-                );
-            }
+        let (net_bps, net_profit) = match &self.fees {
+            // Only log if the round trip is worth its fees, priced at one unit
+            // of the base asset without a trade size
+            Some(fees) => {
+                let size = if self.trade_size.is_zero() { Decimal::ONE } else { self.trade_size };
+                let net_profit = fees.worthwhile_profit(cex_price, dex_price, size)?;
+                let net_bps = net_profit / (cex_price * size) * Decimal::new(10000, 0);
+                (net_bps, Some(net_profit))
+            },
+            // Only log if there's a meaningful difference (e.g., > 0.1%)
+            None if profit_pct > Decimal::new(10, 2) => (profit_pct * Decimal::new(100, 0), None),
+            None => return None,
+        };
+
+        let detected_at = jiff::Timestamp::now();
+        let dex_age = self.dex_price_age(detected_at);
+        let direction = if cex_price > dex_price {
+            info!(
+                "🚀 ARBITRAGE OPPORTUNITY: Buy DEX ${:.2} → Sell CEX ${:.2} | Profit: ${:.2} ({:.2}%) | Symbol: {} | DEX Age: {}",
+                dex_price, cex_price, diff, profit_pct, self.symbol, dex_age
+            );
+            ArbitrageDirection::BuyDexSellCex
+        } else if dex_price > cex_price {
+            info!(
+                "🚀 ARBITRAGE OPPORTUNITY: Buy CEX ${:.2} → Sell DEX ${:.2} | Profit: ${:.2} ({:.2}%) | Symbol: {} | DEX Age: {}",
+                cex_price, dex_price, diff, profit_pct, self.symbol, dex_age
+            );
+            ArbitrageDirection::BuyCexSellDex
+        } else {
+            return None;
+        };
+        if let Some(net_profit) = net_profit {
+            info!(
+                "💸 Net Profit after fees: ${:.2} ({:.2} bps) | Symbol: {}",
+                net_profit, net_bps, self.symbol
+            );
         }
+
+        Some(ArbitrageOpportunity {
+            symbol: self.symbol.clone(),
+            direction,
+            cex_price,
+            dex_price,
+            net_bps,
+            recommended_size: self.trade_size,
+            detected_at,
+            cex_quote_at: self.last_cex_timestamp.unwrap_or(detected_at),
+            dex_quote_at: self.last_dex_timestamp.unwrap_or(detected_at),
+        })
     }
 
-    /// Run market making simulation and log results
-    fn run_market_making_simulation(&self, cex_price: Decimal, dex_price: Decimal) {
+    /// Describes how old the last DEX price is at `now`, e.g. `1200ms (block
+    /// 22745131)`.
+    fn dex_price_age(&self, now: jiff::Timestamp) -> String {
+        let Some(observed_at) = self.last_dex_timestamp else {
+            return "unknown".to_string();
+        };
+        let age = format!("{}ms", now.duration_since(observed_at).as_millis());
+        match self.last_dex_block {
+            Some(block_number) => format!("{} (block {})", age, block_number),
+            None => age,
+        }
+    }
+
+    /// Run market making simulation and log results, returning the range
+    fn run_market_making_simulation(
+        &self,
+        cex_price: Decimal,
+        dex_price: Decimal,
+        now: jiff::Timestamp,
+    ) -> MarketMakingRange {
         // Calculate optimal market making ranges
         let mm_range = self.simulator.calculate_ranges(cex_price, Some(dex_price));
+        if let Some(exporter) = &self.exporter {
+            exporter.record(now, &mm_range);
+        }
+        info!(
+            symbol = %self.symbol,
+            mm_range = ?serde_json::to_value(&mm_range).ok(),
+            "market making range"
+        );
 
         // Log the simulation results
         info!("🎯 MARKET MAKING SIMULATION");
@@ -75,6 +524,18 @@ impl LoggingBotStrategy {
             mm_range.ask_price,
             mm_range.ask_spread_bps
         );
+        if let (Some(lower), Some(upper)) = (mm_range.tick_lower, mm_range.tick_upper) {
+            info!("Tick Range: [{}, {}]", lower, upper);
+        }
+        if let (Some(base), Some(quote)) = (mm_range.amount_token0, mm_range.amount_token1) {
+            info!(
+                "Deposit: {:.6} {} + {:.2} {}",
+                base,
+                self.symbol.base_asset(),
+                quote,
+                self.symbol.quote_asset()
+            );
+        }
 
         info!(
             "Range Width: ${:.2} ({:.2}% of fair value)",
@@ -84,6 +545,10 @@ impl LoggingBotStrategy {
 
         info!("Strategy Logic: {}", mm_range.reasoning);
 
+        if let Some(volatility_bps) = self.realized_volatility_bps() {
+            info!("📈 Realized Volatility (5m): {:.2} bps per tick", volatility_bps);
+        }
+
         // Log market condition assessment
         match mm_range.market_condition {
             MarketCondition::Normal => {
@@ -99,15 +564,16 @@ impl LoggingBotStrategy {
 
         // Calculate and log potential profits for different trade sizes
         self.log_potential_profits(&mm_range);
+        mm_range
     }
 
     /// Log potential profits for different trade sizes
-    fn log_potential_profits(&self, mm_range: &crate::engine::MarketMakingRange) {
+    fn log_potential_profits(&self, mm_range: &MarketMakingRange) {
         let trade_sizes = [
-            (Decimal::new(1, 0), format!("1 {}", self.symbol.get_base_asset())),
-            (Decimal::new(5, 0), format!("5 {}", self.symbol.get_base_asset())),
-            (Decimal::new(10, 0), format!("10 {}", self.symbol.get_base_asset())),
-            (Decimal::new(50, 0), format!("50 {}", self.symbol.get_base_asset())),
+            (Decimal::new(1, 0), format!("1 {}", self.symbol.base_asset())),
+            (Decimal::new(5, 0), format!("5 {}", self.symbol.base_asset())),
+            (Decimal::new(10, 0), format!("10 {}", self.symbol.base_asset())),
+            (Decimal::new(50, 0), format!("50 {}", self.symbol.base_asset())),
         ];
 
         let mut profit_info = String::from("💰 Potential MM Profits: ");
@@ -121,13 +587,37 @@ impl LoggingBotStrategy {
         profit_info.truncate(profit_info.len() - 2);
 
         info!("{}", profit_info);
+
+        if let Some(max_size) = self.max_size_within_price_impact() {
+            info!(
+                "💧 Max size within {} bps price impact: {:.4} {} | Symbol: {}",
+                self.acceptable_price_impact_bps,
+                max_size,
+                self.symbol.base_asset(),
+                self.symbol
+            );
+        }
+    }
+
+    /// Largest size of the base asset that can be sold on the pool within the
+    /// acceptable price impact, estimated from the last pool state. None
+    /// without a pool state to estimate it from.
+    fn max_size_within_price_impact(&self) -> Option<Decimal> {
+        let state = self.last_dex_state.as_ref()?;
+        let amount_token0 = state.max_trade_within_slippage(self.acceptable_price_impact_bps)?;
+        // The pool price is of the base asset, which is token 0 if inverted
+        if state.invert {
+            return Some(amount_token0);
+        }
+        let price = Decimal::from_str_exact(&state.spot_price.to_fixed(18, None)).ok()?;
+        amount_token0.checked_div(price)
     }
 
     /// Calculate potential market making profit for a given trade size
     fn calculate_mm_profit(
         &self,
         trade_size_eth: Decimal,
-        mm_range: &crate::engine::MarketMakingRange,
+        mm_range: &MarketMakingRange,
     ) -> Decimal {
         // Simplified calculation: average spread * trade size
         // In reality, this would depend on actual fills and market conditions
@@ -137,19 +627,548 @@ impl LoggingBotStrategy {
 }
 
 impl BotStrategy for LoggingBotStrategy {
-    fn handle_internal_event(&mut self, event: InternalEvent) {
+    fn symbols(&self) -> Vec<PoolSymbol> { vec![self.symbol.clone()] }
+
+    fn handle_internal_event(&mut self, event: InternalEvent) -> Option<InternalAction> {
+        self.apply_market_making_updates();
         match event {
             InternalEvent::TickerUpdate(ticker) if ticker.symbol == self.symbol => {
-                self.last_cex_price = Some(ticker.price);
-                self.check_arbitrage_and_simulate_mm();
+                // The fair value follows the most recently updated exchange, so
+                // a ticker older than the last one of another exchange is only
+                // remembered
+                let superseded = self
+                    .cex_prices
+                    .values()
+                    .any(|(_, timestamp)| *timestamp > ticker.timestamp);
+                self.cex_prices
+                    .insert(ticker.exchage.clone(), (ticker.price, ticker.timestamp));
+                if superseded {
+                    return None;
+                }
+
+                self.last_cex_price = match &mut self.twap {
+                    Some(twap) => {
+                        twap.push(ticker.price, ticker.timestamp);
+                        twap.twap()
+                    },
+                    None => Some(ticker.price),
+                };
+                self.last_cex_timestamp = Some(ticker.timestamp);
+                // The range quoted before this price is the one it may fill
+                self.simulate_fill(ticker.price);
+                self.skew.record_cex_update(ticker.timestamp);
+                self.simulator.record_cex_price(ticker.price);
+                self.check_arbitrage_and_simulate_mm(ticker.timestamp)
             },
             InternalEvent::PoolPriceUpdate(update) if update.symbol == self.symbol => {
-                self.last_dex_price = Some(update.price);
-                self.check_arbitrage_and_simulate_mm();
+                self.last_dex_price = match &mut self.dex_ema {
+                    Some(ema) => Some(ema.update(update.price)),
+                    None => Some(update.price),
+                };
+                self.last_dex_timestamp = Some(update.timestamp);
+                self.last_dex_block = update.block_number;
+                self.last_dex_state = update.state;
+                self.last_effective_buy_price = update.effective_buy_price;
+                self.last_effective_sell_price = update.effective_sell_price;
+                self.check_arbitrage_and_simulate_mm(update.timestamp)
+            },
+            InternalEvent::FeedStatus(status) if status.symbol == self.symbol => {
+                match status.state {
+                    FeedState::Up => self.down_feeds.remove(&status.source),
+                    FeedState::Down => self.down_feeds.insert(status.source),
+                };
+                None
+            },
+            InternalEvent::BalanceUpdate(_) => {
+                self.update_inventory();
+                None
             },
+            InternalEvent::Execution(_)
+            | InternalEvent::OrderUpdate(_)
+            | InternalEvent::OwnFill(_) => None,
             _ => {
                 unreachable!("Unexpected event for LoggingBotStrategy: {:?}", event);
             },
         }
     }
+
+    fn on_stop(&mut self) {
+        self.log_fill_summary();
+        if let Some(ledger) = &self.ledger {
+            info!(
+                "📒 PAPER TRADING SUMMARY: {} trades | Volume: {} {} | PnL: ${:.2} | Symbol: {}",
+                ledger.trade_count(),
+                ledger.volume(),
+                self.symbol.base_asset(),
+                ledger.pnl(),
+                self.symbol
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal_macros::dec;
+
+    use super::*;
+    use crate::{
+        config::{FeeConfig, FillSimulationConfig},
+        engine::{BalanceUpdate, BalanceVenue, FeedStatus, PoolPriceUpdate, Ticker},
+    };
+
+    fn config() -> MarketMakingConfig {
+        MarketMakingConfig {
+            base_spread_bps: 50,
+            max_spread_bps: 100,
+            min_spread_bps: 10,
+            gas_price: dec!(0.5),
+            arbitrage_tighten_factor: dec!(0.7),
+            arbitrage_widen_factor: dec!(1.3),
+            arbitrage_threshold_bps: 100,
+            gas_units_per_trade: 150_000,
+            token_price_usd: Decimal::ZERO,
+            volatility_window: 20,
+            volatility_threshold: dec!(0.02),
+            volatility_widen_factor: None,
+            use_twap: false,
+            twap_window_secs: 60,
+            dex_ema_alpha: None,
+            export: None,
+            acceptable_price_impact_bps: 50,
+            capital_usd: Decimal::ZERO,
+            fill_simulation: None,
+            inventory_skew_bps: 0,
+        }
+    }
+
+    fn strategy() -> LoggingBotStrategy { LoggingBotStrategy::new(PoolSymbol::ETH_USDC, config()) }
+
+    fn ticker(timestamp: &str) -> InternalEvent {
+        InternalEvent::TickerUpdate(Ticker {
+            exchage: Exchange::Coinbase,
+            symbol: PoolSymbol::ETH_USDC,
+            price: dec!(2500),
+            timestamp: timestamp.parse().unwrap(),
+            bid: None,
+            ask: None,
+            bid_size: None,
+            ask_size: None,
+            volume_24h: None,
+        })
+    }
+
+    fn pool_update(timestamp: &str) -> InternalEvent {
+        InternalEvent::PoolPriceUpdate(PoolPriceUpdate {
+            symbol: PoolSymbol::ETH_USDC,
+            price: dec!(2500),
+            timestamp: timestamp.parse().unwrap(),
+            block_number: None,
+            state: None,
+            effective_buy_price: None,
+            effective_sell_price: None,
+        })
+    }
+
+    #[test]
+    fn test_tracked_inventory_skews_the_simulated_ranges() {
+        let balances = BalanceBook::new();
+        let config = MarketMakingConfig { inventory_skew_bps: 20, ..config() };
+        let mut strategy =
+            LoggingBotStrategy::new(PoolSymbol::ETH_USDC, config).with_balances(balances.clone());
+        let balance = |asset: &str, total: Decimal| BalanceUpdate {
+            venue: BalanceVenue::Cex(Exchange::Coinbase),
+            asset: asset.to_string(),
+            free: total,
+            total,
+            timestamp: "2025-02-12T21:12:30Z".parse().unwrap(),
+        };
+
+        // The engine records every balance before handing it over
+        for update in [balance("ETH", dec!(1)), balance("USDC", dec!(500))] {
+            balances.record(&update);
+            strategy.handle_internal_event(InternalEvent::BalanceUpdate(update));
+        }
+        assert_eq!(strategy.simulator.inventory, Some((dec!(1), dec!(500))));
+
+        // Long ETH, the ask is tightened to sell it
+        let range = strategy.simulator.calculate_ranges(dec!(2500), None);
+        assert_eq!((range.bid_spread_bps, range.ask_spread_bps), (63, 37));
+    }
+
+    #[test]
+    fn test_update_skew_is_recorded_on_quote_pairing() {
+        let mut strategy = strategy();
+
+        // Nothing is paired until both sides have quoted
+        strategy.handle_internal_event(ticker("2025-02-12T21:12:30Z"));
+        assert_eq!(strategy.skew.percentile(50), None);
+
+        strategy.handle_internal_event(pool_update("2025-02-12T21:12:30.400Z"));
+        strategy.handle_internal_event(ticker("2025-02-12T21:12:33Z"));
+        strategy.handle_internal_event(pool_update("2025-02-12T21:12:35Z"));
+
+        // 400ms, then the same pool update paired with a newer ticker is still
+        // nearest to the first one, then 2s behind the latest ticker
+        assert_eq!(strategy.skew.percentile(0), Some(Duration::from_millis(400)));
+        assert_eq!(strategy.skew.percentile(50), Some(Duration::from_millis(400)));
+        assert_eq!(strategy.skew.percentile(100), Some(Duration::from_secs(2)));
+    }
+
+    #[test]
+    fn test_cex_price_is_the_twap_if_enabled() {
+        let ticker = |price, timestamp: &str| {
+            InternalEvent::TickerUpdate(Ticker {
+                exchage: Exchange::Coinbase,
+                symbol: PoolSymbol::ETH_USDC,
+                price,
+                timestamp: timestamp.parse().unwrap(),
+                bid: None,
+                ask: None,
+                bid_size: None,
+                ask_size: None,
+                volume_24h: None,
+            })
+        };
+        let mut raw = strategy();
+        let mut twap = LoggingBotStrategy::new(
+            PoolSymbol::ETH_USDC,
+            MarketMakingConfig { use_twap: true, ..config() },
+        );
+        for strategy in [&mut raw, &mut twap] {
+            strategy.handle_internal_event(ticker(dec!(2500), "2025-02-12T21:12:00Z"));
+            strategy.handle_internal_event(ticker(dec!(2540), "2025-02-12T21:12:30Z"));
+        }
+        assert_eq!(raw.last_cex_price, Some(dec!(2540)));
+        assert_eq!(twap.last_cex_price, Some(dec!(2520)));
+
+        // Ticks older than the window no longer weigh in
+        twap.handle_internal_event(ticker(dec!(2560), "2025-02-12T21:13:01Z"));
+        assert_eq!(twap.last_cex_price, Some(dec!(2550)));
+    }
+
+    #[test]
+    fn test_cex_price_is_the_most_recently_updated_exchange() {
+        let ticker = |exchage, price, timestamp: &str| {
+            InternalEvent::TickerUpdate(Ticker {
+                exchage,
+                symbol: PoolSymbol::ETH_USDC,
+                price,
+                timestamp: timestamp.parse().unwrap(),
+                bid: None,
+                ask: None,
+                bid_size: None,
+                ask_size: None,
+                volume_24h: None,
+            })
+        };
+        let mut strategy = strategy();
+        strategy.handle_internal_event(ticker(
+            Exchange::Coinbase,
+            dec!(2500),
+            "2025-02-12T21:12:00Z",
+        ));
+        strategy.handle_internal_event(ticker(
+            Exchange::Binance,
+            dec!(2510),
+            "2025-02-12T21:12:02Z",
+        ));
+        assert_eq!(strategy.last_cex_price, Some(dec!(2510)));
+
+        // A late ticker is remembered without displacing the newer price
+        strategy.handle_internal_event(ticker(
+            Exchange::Coinbase,
+            dec!(2505),
+            "2025-02-12T21:12:01Z",
+        ));
+        assert_eq!(strategy.last_cex_price, Some(dec!(2510)));
+        assert_eq!(strategy.cex_prices[&Exchange::Coinbase].0, dec!(2505));
+
+        strategy.handle_internal_event(ticker(
+            Exchange::Coinbase,
+            dec!(2520),
+            "2025-02-12T21:12:03Z",
+        ));
+        assert_eq!(strategy.last_cex_price, Some(dec!(2520)));
+        assert_eq!(strategy.last_cex_timestamp, Some("2025-02-12T21:12:03Z".parse().unwrap()));
+    }
+
+    #[test]
+    fn test_dex_price_spikes_are_smoothed_by_the_ema() {
+        let pool_update = |price| {
+            InternalEvent::PoolPriceUpdate(PoolPriceUpdate {
+                symbol: PoolSymbol::ETH_USDC,
+                price,
+                timestamp: "2025-02-12T21:12:30Z".parse().unwrap(),
+                block_number: None,
+                state: None,
+                effective_buy_price: None,
+                effective_sell_price: None,
+            })
+        };
+        let mut strategy = strategy().with_dex_ema(EmaCalculator::new(dec!(0.2)).unwrap());
+        strategy.handle_internal_event(ticker("2025-02-12T21:12:30Z"));
+        strategy.handle_internal_event(pool_update(dec!(2500)));
+
+        // A 0.4% spike of the pool only moves its average by 0.08%, short of an
+        // opportunity
+        let action = strategy.handle_internal_event(pool_update(dec!(2510)));
+        assert!(action.is_none());
+        assert_eq!(strategy.last_dex_price, Some(dec!(2502)));
+    }
+
+    #[test]
+    fn test_paper_trades_are_accounted_in_the_ledger() {
+        let mut strategy = strategy().with_trade_size(dec!(2)).with_paper_trading();
+        strategy.handle_internal_event(ticker("2025-02-12T21:12:30Z"));
+        let action =
+            strategy.handle_internal_event(InternalEvent::PoolPriceUpdate(PoolPriceUpdate {
+                symbol: PoolSymbol::ETH_USDC,
+                price: dec!(2475),
+                timestamp: "2025-02-12T21:12:31Z".parse().unwrap(),
+                block_number: None,
+                state: None,
+                effective_buy_price: None,
+                effective_sell_price: None,
+            }));
+        assert!(matches!(action, Some(InternalAction::Opportunity(_))));
+
+        // A 1% spread on 2 ETH at 2500
+        let ledger = strategy.ledger.as_ref().unwrap();
+        assert_eq!(ledger.trade_count(), 1);
+        assert_eq!(ledger.volume(), dec!(2));
+        assert_eq!(ledger.pnl(), dec!(50));
+        strategy.on_stop();
+    }
+
+    #[test]
+    fn test_opportunities_must_be_worth_their_fees() {
+        let fees = FeeConfig { cex_taker_bps: dec!(60), min_profit_usd: Decimal::ZERO };
+        let mut strategy = strategy().with_fees(RoundTripFees::new(&fees, 500, Decimal::ZERO));
+        let pool_price = |price| {
+            InternalEvent::PoolPriceUpdate(PoolPriceUpdate {
+                symbol: PoolSymbol::ETH_USDC,
+                price,
+                timestamp: "2025-02-12T21:12:31Z".parse().unwrap(),
+                block_number: None,
+                state: None,
+                effective_buy_price: None,
+                effective_sell_price: None,
+            })
+        };
+        strategy.handle_internal_event(ticker("2025-02-12T21:12:30Z"));
+
+        // 25 apart pays 15 to the CEX and 1.2375 to the LP per ETH
+        let action = strategy.handle_internal_event(pool_price(dec!(2475)));
+        let Some(InternalAction::Opportunity(opportunity)) = action else {
+            panic!("expected opportunity, got {:?}", action)
+        };
+        assert_eq!(opportunity.net_bps, dec!(35.05));
+
+        // 15 apart is a loss once the fees are paid
+        assert_eq!(strategy.handle_internal_event(pool_price(dec!(2485))), None);
+    }
+
+    #[test]
+    fn test_opportunities_are_suppressed_while_a_feed_is_down() {
+        let mut strategy = strategy();
+        let feed_status = |state| {
+            InternalEvent::FeedStatus(FeedStatus {
+                feed: "pool_feed_collector_ETH-USDC".to_string(),
+                source: PriceSource::Dex,
+                symbol: PoolSymbol::ETH_USDC,
+                state,
+                reason: "stream ended".to_string(),
+                timestamp: "2025-02-12T21:12:31Z".parse().unwrap(),
+            })
+        };
+        let ticker = |price, timestamp: &str| {
+            InternalEvent::TickerUpdate(Ticker {
+                exchage: Exchange::Coinbase,
+                symbol: PoolSymbol::ETH_USDC,
+                price,
+                timestamp: timestamp.parse().unwrap(),
+                bid: None,
+                ask: None,
+                bid_size: None,
+                ask_size: None,
+                volume_24h: None,
+            })
+        };
+        strategy.handle_internal_event(ticker(dec!(2500), "2025-02-12T21:12:30Z"));
+        strategy.handle_internal_event(pool_update("2025-02-12T21:12:30Z"));
+        strategy.handle_internal_event(feed_status(FeedState::Down));
+
+        // The stale DEX price is 80 bps below the CEX price
+        let action = strategy.handle_internal_event(ticker(dec!(2520), "2025-02-12T21:12:33Z"));
+        let Some(InternalAction::Suppressed(suppressed)) = action else {
+            panic!("expected suppression, got {:?}", action)
+        };
+        assert_eq!(suppressed.reason, SuppressionReason::FeedDown { source: PriceSource::Dex });
+        assert_eq!(suppressed.opportunity.cex_price, dec!(2520));
+
+        strategy.handle_internal_event(feed_status(FeedState::Up));
+        let action = strategy.handle_internal_event(ticker(dec!(2520), "2025-02-12T21:12:34Z"));
+        let Some(InternalAction::Opportunity(opportunity)) = action else {
+            panic!("expected opportunity, got {:?}", action)
+        };
+        // Priced from the latest ticker and the older pool update
+        assert_eq!(opportunity.cex_quote_at, "2025-02-12T21:12:34Z".parse().unwrap());
+        assert_eq!(opportunity.dex_quote_at, "2025-02-12T21:12:30Z".parse().unwrap());
+    }
+
+    #[test]
+    fn test_opportunities_are_suppressed_beyond_the_acceptable_price_impact() {
+        // ETH-USDC at 2500 with about 4.15e18 of liquidity, where selling
+        // 100 ETH moves the price by about 24 bps
+        let state: PoolSlotData = serde_json::from_str(
+            r#"{"timestamp":"2025-06-20T14:03:05Z","block_number":22745131,"sqrt_price_x96":"0x346dc5d63886594af4f0d","tick":-198080,"protocol_fee":0,"lp_fee":500,"liquidity":4151392815226375618,"token_0_decimals":18,"token_1_decimals":6,"invert":true}"#,
+        )
+        .unwrap();
+        let signal = |trade_size, acceptable_price_impact_bps| {
+            let config = MarketMakingConfig { acceptable_price_impact_bps, ..config() };
+            let mut strategy =
+                LoggingBotStrategy::new(PoolSymbol::ETH_USDC, config).with_trade_size(trade_size);
+            strategy.handle_internal_event(ticker("2025-02-12T21:12:30Z"));
+            strategy.handle_internal_event(InternalEvent::PoolPriceUpdate(PoolPriceUpdate {
+                symbol: PoolSymbol::ETH_USDC,
+                price: dec!(2525),
+                timestamp: "2025-02-12T21:12:31Z".parse().unwrap(),
+                block_number: None,
+                state: Some(state.clone()),
+                effective_buy_price: None,
+                effective_sell_price: None,
+            }))
+        };
+
+        // Selling 1 ETH on the pool barely moves it
+        assert!(matches!(signal(dec!(1), 10), Some(InternalAction::Opportunity(_))));
+
+        let action = signal(dec!(100), 10);
+        let Some(InternalAction::Suppressed(suppressed)) = action else {
+            panic!("expected suppression, got {:?}", action)
+        };
+        assert_eq!(suppressed.reason, SuppressionReason::PriceImpact { impact_bps: 24 });
+        assert!(matches!(signal(dec!(100), 50), Some(InternalAction::Opportunity(_))));
+    }
+
+    #[test]
+    fn test_max_size_within_the_acceptable_price_impact() {
+        let state: PoolSlotData = serde_json::from_str(
+            r#"{"timestamp":"2025-06-20T14:03:05Z","block_number":22745131,"sqrt_price_x96":"0x346dc5d63886594af4f0d","tick":-198080,"protocol_fee":0,"lp_fee":500,"liquidity":4151392815226375618,"token_0_decimals":18,"token_1_decimals":6,"invert":true}"#,
+        )
+        .unwrap();
+        let mut strategy = LoggingBotStrategy::new(PoolSymbol::ETH_USDC, config());
+        assert_eq!(strategy.max_size_within_price_impact(), None);
+
+        strategy.handle_internal_event(InternalEvent::PoolPriceUpdate(PoolPriceUpdate {
+            symbol: PoolSymbol::ETH_USDC,
+            price: dec!(2525),
+            timestamp: "2025-02-12T21:12:31Z".parse().unwrap(),
+            block_number: None,
+            state: Some(state),
+            effective_buy_price: None,
+            effective_sell_price: None,
+        }));
+        // About 208 ETH moves the pool by the acceptable 50 bps
+        let max_size = strategy.max_size_within_price_impact().unwrap();
+        assert_eq!(max_size.round_dp(2), dec!(208.35));
+    }
+
+    #[test]
+    fn test_opportunities_are_detected_at_the_effective_pool_prices() {
+        let signal = |effective_sell_price| {
+            let mut strategy = strategy();
+            strategy.handle_internal_event(ticker("2025-02-12T21:12:30Z"));
+            strategy.handle_internal_event(InternalEvent::PoolPriceUpdate(PoolPriceUpdate {
+                symbol: PoolSymbol::ETH_USDC,
+                price: dec!(2525),
+                timestamp: "2025-02-12T21:12:31Z".parse().unwrap(),
+                block_number: None,
+                state: None,
+                effective_buy_price: Some(dec!(2530)),
+                effective_sell_price,
+            }))
+        };
+
+        // Selling on the pool above the CEX price, at the spot price if not quoted
+        let Some(InternalAction::Opportunity(opportunity)) = signal(None) else {
+            panic!("expected an opportunity")
+        };
+        assert_eq!(opportunity.dex_price, dec!(2525));
+        let Some(InternalAction::Opportunity(opportunity)) = signal(Some(dec!(2510))) else {
+            panic!("expected an opportunity")
+        };
+        assert_eq!(opportunity.direction, ArbitrageDirection::BuyCexSellDex);
+        assert_eq!(opportunity.dex_price, dec!(2510));
+        // and none once the price impact and fees eat the difference
+        assert!(signal(Some(dec!(2499))).is_none());
+    }
+
+    #[test]
+    fn test_reloaded_market_making_parameters_apply_from_the_next_event() {
+        let (updates, receiver) = watch::channel(config());
+        let mut strategy = strategy().with_market_making_updates(receiver);
+        strategy.handle_internal_event(ticker("2025-02-12T21:12:30Z"));
+        assert_eq!(strategy.simulator.base_spread_bps, 50);
+
+        updates.send_replace(MarketMakingConfig {
+            base_spread_bps: 80,
+            max_spread_bps: 150,
+            acceptable_price_impact_bps: 20,
+            ..config()
+        });
+        strategy.handle_internal_event(pool_update("2025-02-12T21:12:31Z"));
+        assert_eq!(strategy.simulator.base_spread_bps, 80);
+        assert_eq!(strategy.simulator.max_spread_bps, 150);
+        assert_eq!(strategy.acceptable_price_impact_bps, 20);
+        // while the recorded prices are kept
+        assert_eq!(strategy.last_cex_price, Some(dec!(2500)));
+    }
+
+    #[test]
+    fn test_ranges_are_filled_by_the_cex_price() {
+        let fill_simulation = FillSimulationConfig {
+            clip_size: dec!(1),
+            max_inventory: dec!(2),
+            summary_interval: 2,
+        };
+        let config = MarketMakingConfig { fill_simulation: Some(fill_simulation), ..config() };
+        let mut strategy = LoggingBotStrategy::new(PoolSymbol::ETH_USDC, config);
+        let ticker = |price, timestamp: &str| {
+            InternalEvent::TickerUpdate(Ticker {
+                exchage: Exchange::Coinbase,
+                symbol: PoolSymbol::ETH_USDC,
+                price,
+                timestamp: timestamp.parse().unwrap(),
+                bid: None,
+                ask: None,
+                bid_size: None,
+                ask_size: None,
+                volume_24h: None,
+            })
+        };
+        strategy.handle_internal_event(pool_update("2025-02-12T21:12:30Z"));
+
+        // Quoted 50 bps around every price: bought at 2487.5, sold at 2492.4
+        // and 2507.475, the price within the last range filling nothing
+        let prices = [
+            (dec!(2500), "2025-02-12T21:12:31Z"),
+            (dec!(2480), "2025-02-12T21:12:32Z"),
+            (dec!(2495), "2025-02-12T21:12:33Z"),
+            (dec!(2510), "2025-02-12T21:12:34Z"),
+            (dec!(2505), "2025-02-12T21:12:35Z"),
+        ];
+        for (price, timestamp) in prices {
+            strategy.handle_internal_event(ticker(price, timestamp));
+        }
+        let fills = strategy.fills.as_ref().unwrap();
+        assert_eq!(fills.fill_count(), 3);
+        assert_eq!(fills.inventory(), dec!(-1));
+        assert_eq!(fills.average_entry(), Some(dec!(2507.475)));
+        assert_eq!(fills.cash(), dec!(2512.375));
+        assert_eq!(fills.spread_capture(), dec!(37.375));
+        assert_eq!(fills.pnl(dec!(2505)), dec!(7.375));
+        strategy.on_stop();
+    }
 }
@@ -1,11 +1,51 @@
-use rust_decimal::{prelude::ToPrimitive, Decimal};
+use std::collections::VecDeque;
+
+use alloy::primitives::U256;
+use rust_decimal::{
+    prelude::{FromPrimitive, ToPrimitive},
+    Decimal,
+};
 use rust_decimal_macros::dec;
+use sikkara_adapters::{
+    amounts_for_liquidity, price_to_sqrt_price_x96, price_to_tick, sqrt_price_x96_at_tick, MAX_TICK,
+};
 
 use crate::{
     config::MarketMakingConfig,
-    engine::{MarketCondition, MarketMakingRange, PoolSymbol},
+    engine::{MarketCondition, MarketMakingRange, Pool, PoolSymbol},
 };
 
+/// Liquidity the amounts of a range are sized from
+const REFERENCE_LIQUIDITY: u128 = 1_000_000_000_000_000_000;
+
+/// Converts an amount in the smallest unit of an asset to whole units.
+fn to_whole_units(amount: U256, decimals: u8) -> Option<Decimal> {
+    let amount = i128::try_from(amount).ok()?;
+    Decimal::try_from_i128_with_scale(amount, decimals as u32).ok()
+}
+
+/// Tick grid of the pool a range is placed on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TickGrid {
+    pub tick_spacing: i32,
+    pub base_decimals: u8,
+    pub quote_decimals: u8,
+    /// Whether the base asset is the currency 0 of the pool, whose ticks are
+    /// then prices of the base asset rather than of the quote asset
+    pub base_is_currency_0: bool,
+}
+
+impl From<&Pool> for TickGrid {
+    fn from(pool: &Pool) -> Self {
+        Self {
+            tick_spacing: pool.tick_spacing,
+            base_decimals: pool.token_0.decimals,
+            quote_decimals: pool.token_1.decimals,
+            base_is_currency_0: pool.token_0.address < pool.token_1.address,
+        }
+    }
+}
+
 /// A market making simulator calculates optimal bid/ask price ranges for
 /// providing liquidity on a decentralized exchange (DEX). It uses the
 /// centralized exchange (CEX) prices as a fair value references and dynamically
@@ -28,7 +68,26 @@ use crate::{
 ///   to the CEX price.
 /// - `arbitrage_threshold_bps`: The threshold in basis points for triggering
 ///   arbitrage opportunities. If the price difference between CEX and DEX
-/// - `gas_price`: The current gas price in the network,
+/// - `gas_price`: The current gas price in the network, in gwei.
+/// - `gas_units_per_trade`: The gas used by a trade placing the range.
+/// - `token_price_usd`: The price of the native token paying for gas, the gas
+///   cost is not part of the spreads if zero.
+/// - `trade_size`: The size of a trade in the base asset, the gas cost is not
+///   part of the spreads if zero.
+/// - `volatility_threshold`: The coefficient of variation of the recent CEX
+///   prices above which the market is volatile, e.g. 0.02 for 2%.
+/// - `volatility_widen_factor`: The factor both spreads are widened by while
+///   the market is volatile.
+/// - `entry_price`: The price the liquidity was provided at, if any. The
+///   spreads then cover at least the impermanent loss since.
+/// - `tick_grid`: The tick grid of the pool, if known. The ranges then carry
+///   the ticks a position placing them would span.
+/// - `capital_usd`: The capital deposited in a range, the ranges carry the
+///   amounts of both assets it takes if set along with the tick grid.
+/// - `inventory_skew_bps`: The largest shift of the spreads towards the side
+///   rebalancing the inventory, reached once a single asset is held.
+/// - `inventory`: The base and quote assets held, if known. The spreads are
+///   then skewed by how unbalanced they are.
 #[derive(Debug, Clone)]
 pub struct MarketMakingSimulator {
     pub symbol: PoolSymbol,
@@ -39,6 +98,17 @@ pub struct MarketMakingSimulator {
     pub arbitrage_tighten_factor: Decimal,
     pub arbitrage_widen_factor: Decimal,
     pub gas_price: Decimal,
+    pub gas_units_per_trade: u64,
+    pub token_price_usd: Decimal,
+    pub trade_size: Decimal,
+    pub volatility_threshold: Decimal,
+    pub volatility_widen_factor: Decimal,
+    pub entry_price: Option<Decimal>,
+    pub tick_grid: Option<TickGrid>,
+    pub capital_usd: Decimal,
+    pub inventory_skew_bps: u32,
+    pub inventory: Option<(Decimal, Decimal)>,
+    volatility: VolatilityTracker,
 }
 
 impl MarketMakingSimulator {
@@ -52,8 +122,19 @@ impl MarketMakingSimulator {
             min_spread_bps: 25,
             arbitrage_threshold_bps: 100,
             gas_price: dec!(0.5),
+            gas_units_per_trade: 150_000,
+            token_price_usd: Decimal::ZERO,
+            trade_size: Decimal::ZERO,
             arbitrage_tighten_factor: dec!(0.7),
             arbitrage_widen_factor: dec!(1.3),
+            volatility_threshold: dec!(0.02),
+            volatility_widen_factor: dec!(1.3),
+            entry_price: None,
+            tick_grid: None,
+            capital_usd: Decimal::ZERO,
+            inventory_skew_bps: 0,
+            inventory: None,
+            volatility: VolatilityTracker::new(20),
         }
     }
 
@@ -66,11 +147,84 @@ impl MarketMakingSimulator {
             min_spread_bps: config.min_spread_bps,
             arbitrage_threshold_bps: config.arbitrage_threshold_bps,
             gas_price: config.gas_price,
+            gas_units_per_trade: config.gas_units_per_trade,
+            token_price_usd: config.token_price_usd,
+            trade_size: Decimal::ZERO,
             arbitrage_tighten_factor: config.arbitrage_tighten_factor,
             arbitrage_widen_factor: config.arbitrage_widen_factor,
+            volatility_threshold: config.volatility_threshold,
+            volatility_widen_factor: config
+                .volatility_widen_factor
+                .unwrap_or(config.arbitrage_widen_factor),
+            entry_price: None,
+            tick_grid: None,
+            capital_usd: config.capital_usd,
+            inventory_skew_bps: config.inventory_skew_bps,
+            inventory: None,
+            volatility: VolatilityTracker::new(config.volatility_window),
         }
     }
 
+    /// Replaces the spreads, thresholds, factors, gas, capital and inventory
+    /// skew with those of `config`, keeping the recorded CEX prices, the trade
+    /// size, the entry price, the tick grid and the inventory.
+    pub fn update_parameters(&mut self, config: &MarketMakingConfig) {
+        self.base_spread_bps = config.base_spread_bps;
+        self.max_spread_bps = config.max_spread_bps;
+        self.min_spread_bps = config.min_spread_bps;
+        self.arbitrage_threshold_bps = config.arbitrage_threshold_bps;
+        self.gas_price = config.gas_price;
+        self.gas_units_per_trade = config.gas_units_per_trade;
+        self.token_price_usd = config.token_price_usd;
+        self.arbitrage_tighten_factor = config.arbitrage_tighten_factor;
+        self.arbitrage_widen_factor = config.arbitrage_widen_factor;
+        self.volatility_threshold = config.volatility_threshold;
+        self.volatility_widen_factor = config
+            .volatility_widen_factor
+            .unwrap_or(config.arbitrage_widen_factor);
+        self.capital_usd = config.capital_usd;
+        self.inventory_skew_bps = config.inventory_skew_bps;
+    }
+
+    /// Covers the gas cost of trading `trade_size` of the base asset in the
+    /// spreads.
+    pub fn with_trade_size(mut self, trade_size: Decimal) -> Self {
+        self.trade_size = trade_size;
+        self
+    }
+
+    /// Covers the impermanent loss of liquidity provided at `entry_price` in
+    /// the spreads.
+    pub fn with_entry_price(mut self, entry_price: Decimal) -> Self {
+        self.entry_price = Some(entry_price);
+        self
+    }
+
+    /// Aligns the ranges to the ticks of a pool on the given grid.
+    pub fn with_tick_grid(mut self, tick_grid: TickGrid) -> Self {
+        self.tick_grid = Some(tick_grid);
+        self
+    }
+
+    /// Skews the spreads by the `base` and `quote` assets held.
+    pub fn set_inventory(&mut self, base: Decimal, quote: Decimal) {
+        self.inventory = Some((base, quote));
+    }
+
+    /// Imbalance of the inventory valued at `cex_price`, from -1 when only the
+    /// quote asset is held to 1 when only the base asset is. None if the
+    /// inventory is unknown or empty.
+    pub fn inventory_imbalance(&self, cex_price: Decimal) -> Option<Decimal> {
+        let (base, quote) = self.inventory?;
+        let base_value = base * cex_price;
+        let total = base_value + quote;
+        (total > Decimal::ZERO).then(|| (base_value - quote) / total)
+    }
+
+    /// Records a CEX price sample, from which the volatility of the market is
+    /// assessed.
+    pub fn record_cex_price(&mut self, cex_price: Decimal) { self.volatility.record(cex_price); }
+
     /// Calculate the optimal market making ranges based on current market
     /// conditions
     pub fn calculate_ranges(
@@ -94,6 +248,18 @@ impl MarketMakingSimulator {
         let ask_price = cex_price * (Decimal::ONE + ask_spread_decimal);
         let total_range_width = ask_price - bid_price;
 
+        // Size at which the half spread captured per unit pays for the gas
+        let gas_cost = self.gas_price;
+        let half_spread = total_range_width / Decimal::TWO;
+        let break_even_size = (!half_spread.is_zero()).then(|| gas_cost / half_spread);
+        let ticks = self.range_ticks(bid_price, ask_price);
+        let (tick_lower, tick_upper) = ticks.unzip();
+        // Deposited at the pool price, the fair value standing in if unknown
+        let pool_price = dex_price.unwrap_or(cex_price);
+        let (amount_token0, amount_token1) = ticks
+            .and_then(|(lower, upper)| self.range_amounts(pool_price, lower, upper))
+            .unzip();
+
         // Generate strategy reasoning
         let reasoning = self.explain_strategy(
             &market_condition,
@@ -111,17 +277,116 @@ impl MarketMakingSimulator {
             bid_spread_bps,
             ask_spread_bps,
             total_range_width,
+            gas_cost,
+            break_even_size,
+            tick_lower,
+            tick_upper,
+            amount_token0,
+            amount_token1,
             reasoning,
             market_condition,
         }
     }
 
+    /// Ticks of the pool spanning `bid_price` to `ask_price`, rounded outward
+    /// to the tick spacing and clamped to the usable ticks, the lower tick
+    /// always strictly below the upper one.
+    fn range_ticks(&self, bid_price: Decimal, ask_price: Decimal) -> Option<(i32, i32)> {
+        let grid = self.tick_grid?;
+        let spacing = grid.tick_spacing;
+        if spacing <= 0 {
+            return None;
+        }
+        let (low, high) = match grid.base_is_currency_0 {
+            true => (
+                price_to_tick(bid_price, grid.base_decimals, grid.quote_decimals).ok()?,
+                price_to_tick(ask_price, grid.base_decimals, grid.quote_decimals).ok()?,
+            ),
+            // Ticks are prices of the quote asset, the ask being the lowest
+            false => {
+                let tick = |price: Decimal| {
+                    let price = Decimal::ONE.checked_div(price)?;
+                    price_to_tick(price, grid.quote_decimals, grid.base_decimals).ok()
+                };
+                (tick(ask_price)?, tick(bid_price)?)
+            },
+        };
+
+        // The ticks price_to_tick returns are at or below the prices
+        let max_usable = MAX_TICK.div_euclid(spacing) * spacing;
+        let lower = (low.div_euclid(spacing) * spacing).max(-max_usable);
+        let upper = (high.div_euclid(spacing) * spacing + spacing).min(max_usable);
+        match lower < upper {
+            true => Some((lower, upper)),
+            false if upper == max_usable => Some((upper - spacing, upper)),
+            false => Some((lower, lower + spacing)),
+        }
+    }
+
+    /// Amounts of the base and quote assets depositing `capital_usd` between
+    /// `tick_lower` and `tick_upper` takes at `pool_price`. The quote asset is
+    /// taken as USD, as for the prices of the ranges.
+    fn range_amounts(
+        &self,
+        pool_price: Decimal,
+        tick_lower: i32,
+        tick_upper: i32,
+    ) -> Option<(Decimal, Decimal)> {
+        let grid = self.tick_grid?;
+        if self.capital_usd <= Decimal::ZERO || pool_price <= Decimal::ZERO {
+            return None;
+        }
+        // The pool prices currency 0 in currency 1
+        let sqrt_price = match grid.base_is_currency_0 {
+            true => price_to_sqrt_price_x96(pool_price, grid.base_decimals, grid.quote_decimals),
+            false => price_to_sqrt_price_x96(
+                Decimal::ONE / pool_price,
+                grid.quote_decimals,
+                grid.base_decimals,
+            ),
+        }
+        .ok()?;
+        let sqrt_price_lower = sqrt_price_x96_at_tick(tick_lower).ok()?;
+        let sqrt_price_upper = sqrt_price_x96_at_tick(tick_upper).ok()?;
+        let amounts = |liquidity: u128| {
+            let (amount_0, amount_1) =
+                amounts_for_liquidity(sqrt_price, sqrt_price_lower, sqrt_price_upper, liquidity)
+                    .ok()?;
+            let (base, quote) = match grid.base_is_currency_0 {
+                true => (amount_0, amount_1),
+                false => (amount_1, amount_0),
+            };
+            Some((
+                to_whole_units(base, grid.base_decimals)?,
+                to_whole_units(quote, grid.quote_decimals)?,
+            ))
+        };
+
+        // The amounts grow linearly with the liquidity, whose value at a
+        // reference liquidity sizes the liquidity of the capital
+        let (base, quote) = amounts(REFERENCE_LIQUIDITY)?;
+        let value = base * pool_price + quote;
+        if value.is_zero() {
+            return None;
+        }
+        let liquidity = Decimal::from(REFERENCE_LIQUIDITY)
+            .checked_mul(self.capital_usd)?
+            .checked_div(value)?
+            .to_u128()?;
+        amounts(liquidity)
+    }
+
     /// Assess the current market conditions
-    fn assess_market_conditions(
+    pub fn assess_market_conditions(
         &self,
         cex_price: Decimal,
         dex_price: Option<Decimal>,
     ) -> MarketCondition {
+        let cov = self.volatility.coefficient_of_variation();
+        if cov.is_some_and(|cov| cov > self.volatility_threshold) {
+            return MarketCondition::Volatile;
+        }
+
         if let Some(dex) = dex_price {
             // Calculate price difference in basis points
             let price_diff_bps = ((dex - cex_price).abs() / cex_price) * Decimal::new(10000, 0);
@@ -133,6 +398,45 @@ impl MarketMakingSimulator {
         MarketCondition::Normal
     }
 
+    /// Gas cost of a trade of `trade_size` at `cex_price` in basis points of
+    /// its notional, rounded up.
+    pub fn gas_cost_bps(&self, cex_price: Decimal, trade_size: Decimal) -> u32 {
+        let notional = trade_size * cex_price;
+        if notional.is_zero() {
+            return 0;
+        }
+        let gas_price = self.gas_price * Decimal::new(1, 9); // gwei to native token
+        let gas_cost = gas_price * Decimal::from(self.gas_units_per_trade) * self.token_price_usd;
+        (gas_cost / notional * Decimal::new(10000, 0))
+            .ceil()
+            .to_u32()
+            .unwrap_or(u32::MAX)
+    }
+
+    /// Impermanent loss of liquidity provided at `entry_price` once the price
+    /// moved to `current_price`, relative to holding the tokens:
+    /// `2 * sqrt(r) / (1 + r) - 1` with `r = current_price / entry_price`.
+    ///
+    /// Zero if the price did not move and negative otherwise, e.g. -0.2 once
+    /// the price quadrupled.
+    pub fn impermanent_loss(&self, entry_price: Decimal, current_price: Decimal) -> Decimal {
+        if entry_price <= Decimal::ZERO || current_price <= Decimal::ZERO {
+            return Decimal::ZERO;
+        }
+        let ratio = current_price / entry_price;
+        let Some(sqrt_ratio) = ratio.to_f64().and_then(|r| Decimal::from_f64(r.sqrt())) else {
+            return Decimal::ZERO;
+        };
+        Decimal::TWO * sqrt_ratio / (Decimal::ONE + ratio) - Decimal::ONE
+    }
+
+    /// Minimum spread in basis points covering the impermanent loss of
+    /// liquidity provided at `entry_price`, rounded up.
+    pub fn breakeven_spread_bps(&self, entry_price: Decimal, current_price: Decimal) -> u32 {
+        let loss = -self.impermanent_loss(entry_price, current_price);
+        (loss * Decimal::new(10000, 0)).ceil().to_u32().unwrap_or(0)
+    }
+
     /// Calculate the optimal bid/ask spreads based on market conditions
     fn calculate_spreads(
         &self,
@@ -164,7 +468,35 @@ impl MarketMakingSimulator {
                     }
                 }
             },
-            MarketCondition::Volatile => {},
+            MarketCondition::Volatile => {
+                bid_spread =
+                    self.apply_arbitrage_adjustment(bid_spread, self.volatility_widen_factor);
+                ask_spread =
+                    self.apply_arbitrage_adjustment(ask_spread, self.volatility_widen_factor);
+            },
+        }
+
+        // Quote the side rebalancing the inventory tighter, a long inventory
+        // tightening the ask and widening the bid
+        if let Some(imbalance) = self.inventory_imbalance(cex_price) {
+            let skew = (imbalance * Decimal::from(self.inventory_skew_bps))
+                .round()
+                .to_i32()
+                .unwrap_or(0);
+            bid_spread = bid_spread.saturating_add_signed(skew);
+            ask_spread = ask_spread.saturating_add_signed(-skew);
+        }
+
+        // Cover the gas cost of the trade on both sides
+        let gas_cost_bps = self.gas_cost_bps(cex_price, self.trade_size);
+        bid_spread = bid_spread.saturating_add(gas_cost_bps);
+        ask_spread = ask_spread.saturating_add(gas_cost_bps);
+
+        // Cover the impermanent loss since the liquidity was provided
+        if let Some(entry_price) = self.entry_price {
+            let floor = self.breakeven_spread_bps(entry_price, cex_price);
+            bid_spread = bid_spread.max(floor);
+            ask_spread = ask_spread.max(floor);
         }
 
         // Apply bounds
@@ -222,3 +554,312 @@ impl MarketMakingSimulator {
         reasoning
     }
 }
+
+/// Ring buffer of the last CEX price samples measuring how volatile the market
+/// is.
+#[derive(Debug, Clone)]
+pub struct VolatilityTracker {
+    window: usize,
+    samples: VecDeque<Decimal>,
+}
+
+impl VolatilityTracker {
+    /// Creates a tracker keeping the last `window` samples.
+    pub fn new(window: usize) -> Self { Self { window, samples: VecDeque::with_capacity(window) } }
+
+    /// Records a price sample, evicting the oldest one once the window is
+    /// full.
+    pub fn record(&mut self, price: Decimal) {
+        if self.samples.len() == self.window {
+            self.samples.pop_front();
+        }
+        if self.window > 0 {
+            self.samples.push_back(price);
+        }
+    }
+
+    /// Coefficient of variation of the samples, i.e. their standard deviation
+    /// over their mean, if at least two samples were recorded.
+    pub fn coefficient_of_variation(&self) -> Option<Decimal> {
+        if self.samples.len() < 2 {
+            return None;
+        }
+        let count = Decimal::from(self.samples.len());
+        let mean = self.samples.iter().sum::<Decimal>() / count;
+        if mean.is_zero() {
+            return None;
+        }
+        let variance = self
+            .samples
+            .iter()
+            .map(|price| (price - mean) * (price - mean))
+            .sum::<Decimal>()
+            / count;
+        let std_dev = Decimal::from_f64(variance.to_f64()?.sqrt())?;
+        Some(std_dev / mean)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_volatility_tracker_keeps_the_last_samples() {
+        let mut tracker = VolatilityTracker::new(3);
+        tracker.record(dec!(100));
+        assert_eq!(tracker.coefficient_of_variation(), None);
+
+        tracker.record(dec!(50));
+        for _ in 0..3 {
+            tracker.record(dec!(2500));
+        }
+        assert_eq!(tracker.coefficient_of_variation(), Some(Decimal::ZERO));
+
+        tracker.record(dec!(2600));
+        tracker.record(dec!(2400));
+        // Samples of 2500, 2600 and 2400: a standard deviation of ~81.65
+        let cov = tracker.coefficient_of_variation().unwrap();
+        assert_eq!(cov.round_dp(4), dec!(0.0327));
+    }
+
+    #[test]
+    fn test_gas_cost_is_added_to_both_spreads() {
+        let mut simulator = MarketMakingSimulator::new_with_default(PoolSymbol::ETH_USDC);
+        simulator.gas_price = dec!(20);
+        simulator.token_price_usd = dec!(2500);
+        // 20 gwei for 150k gas at $2500 is $7.50, i.e. 30 bps of 1 ETH
+        assert_eq!(simulator.gas_cost_bps(dec!(2500), dec!(1)), 30);
+        assert_eq!(simulator.gas_cost_bps(dec!(2500), dec!(7)), 5);
+        assert_eq!(simulator.gas_cost_bps(dec!(2500), Decimal::ZERO), 0);
+
+        let range = simulator.calculate_ranges(dec!(2500), None);
+        assert_eq!((range.bid_spread_bps, range.ask_spread_bps), (50, 50));
+
+        let simulator = simulator.with_trade_size(dec!(1));
+        let range = simulator.calculate_ranges(dec!(2500), None);
+        assert_eq!((range.bid_spread_bps, range.ask_spread_bps), (80, 80));
+
+        // but the spreads stay within bounds
+        let range = simulator
+            .with_trade_size(dec!(0.1))
+            .calculate_ranges(dec!(2500), None);
+        assert_eq!((range.bid_spread_bps, range.ask_spread_bps), (200, 200));
+    }
+
+    #[test]
+    fn test_inventory_skews_the_spreads_towards_rebalancing() {
+        let mut simulator = MarketMakingSimulator::new_with_default(PoolSymbol::ETH_USDC);
+        simulator.inventory_skew_bps = 20;
+        let spreads = |simulator: &MarketMakingSimulator| {
+            let range = simulator.calculate_ranges(dec!(2500), None);
+            (range.bid_spread_bps, range.ask_spread_bps)
+        };
+        assert_eq!(spreads(&simulator), (50, 50));
+
+        // Holding as much of both assets does not skew
+        simulator.set_inventory(dec!(1), dec!(2500));
+        assert_eq!(simulator.inventory_imbalance(dec!(2500)), Some(Decimal::ZERO));
+        assert_eq!(spreads(&simulator), (50, 50));
+
+        // $2500 of ETH against $500 of USDC, an imbalance of 2/3, sells ETH
+        simulator.set_inventory(dec!(1), dec!(500));
+        assert_eq!(spreads(&simulator), (63, 37));
+
+        // Holding USDC only buys ETH by the full skew
+        simulator.set_inventory(Decimal::ZERO, dec!(1000));
+        assert_eq!(simulator.inventory_imbalance(dec!(2500)), Some(Decimal::NEGATIVE_ONE));
+        assert_eq!(spreads(&simulator), (30, 70));
+
+        // Nothing held, nothing to rebalance
+        simulator.set_inventory(Decimal::ZERO, Decimal::ZERO);
+        assert_eq!(simulator.inventory_imbalance(dec!(2500)), None);
+        assert_eq!(spreads(&simulator), (50, 50));
+    }
+
+    #[test]
+    fn test_impermanent_loss_is_zero_without_deviation_and_negative_otherwise() {
+        let simulator = MarketMakingSimulator::new_with_default(PoolSymbol::ETH_USDC);
+        let ratios = [
+            dec!(0.01),
+            dec!(0.25),
+            dec!(0.5),
+            dec!(0.9),
+            dec!(0.999),
+            dec!(1.001),
+            dec!(1.1),
+            dec!(2),
+            dec!(4),
+            dec!(100),
+        ];
+        for entry_price in [dec!(0.5), dec!(1), dec!(2500), dec!(97000)] {
+            assert_eq!(simulator.impermanent_loss(entry_price, entry_price), Decimal::ZERO);
+            assert_eq!(simulator.breakeven_spread_bps(entry_price, entry_price), 0);
+
+            for ratio in ratios {
+                let loss = simulator.impermanent_loss(entry_price, entry_price * ratio);
+                assert!(loss < Decimal::ZERO, "{} at a ratio of {}", loss, ratio);
+                assert!(loss > -Decimal::ONE, "{} at a ratio of {}", loss, ratio);
+                // A move by a factor is as costly as the inverse move
+                let inverse = simulator.impermanent_loss(entry_price, entry_price / ratio);
+                assert!((loss - inverse).abs() < dec!(0.000000000001));
+                assert!(simulator.breakeven_spread_bps(entry_price, entry_price * ratio) > 0);
+            }
+        }
+
+        assert_eq!(simulator.impermanent_loss(dec!(2500), dec!(10000)), dec!(-0.2));
+        assert_eq!(simulator.breakeven_spread_bps(dec!(2500), dec!(10000)), 2000);
+    }
+
+    #[test]
+    fn test_impermanent_loss_is_a_spread_floor() {
+        let simulator = MarketMakingSimulator::new_with_default(PoolSymbol::ETH_USDC);
+        // A 10% move costs 12 bps, less than the base spread
+        let range = simulator
+            .clone()
+            .with_entry_price(dec!(2500))
+            .calculate_ranges(dec!(2750), None);
+        assert_eq!((range.bid_spread_bps, range.ask_spread_bps), (50, 50));
+
+        // whereas a 30% move costs 86 bps
+        let range = simulator
+            .with_entry_price(dec!(2500))
+            .calculate_ranges(dec!(3250), None);
+        assert_eq!((range.bid_spread_bps, range.ask_spread_bps), (86, 86));
+    }
+
+    #[test]
+    fn test_volatile_market_widens_both_spreads() {
+        let mut simulator = MarketMakingSimulator::new_with_default(PoolSymbol::ETH_USDC);
+        for price in [dec!(2500), dec!(2501), dec!(2499)] {
+            simulator.record_cex_price(price);
+        }
+        // A DEX premium of 2% is an arbitrage in a calm market
+        let range = simulator.calculate_ranges(dec!(2500), Some(dec!(2550)));
+        assert_eq!(range.market_condition, MarketCondition::Arbitrage);
+
+        for price in [dec!(2300), dec!(2700), dec!(2400), dec!(2600)] {
+            simulator.record_cex_price(price);
+        }
+        // whereas volatility takes precedence once prices swing
+        let range = simulator.calculate_ranges(dec!(2500), Some(dec!(2550)));
+        assert_eq!(range.market_condition, MarketCondition::Volatile);
+        assert_eq!((range.bid_spread_bps, range.ask_spread_bps), (65, 65));
+    }
+
+    #[test]
+    fn test_volatility_spike_widens_spreads_until_it_leaves_the_window() {
+        let mut simulator = MarketMakingSimulator::new_with_default(PoolSymbol::ETH_USDC);
+        simulator.volatility = VolatilityTracker::new(5);
+        simulator.volatility_widen_factor = dec!(3);
+        let calm = [dec!(2500), dec!(2501), dec!(2499), dec!(2500), dec!(2502)];
+        let mut conditions = Vec::new();
+        for price in calm.into_iter().chain([dec!(2300), dec!(2700)]).chain(calm) {
+            simulator.record_cex_price(price);
+            let range = simulator.calculate_ranges(price, None);
+            conditions.push((range.market_condition, range.bid_spread_bps, range.ask_spread_bps));
+        }
+        let normal = (MarketCondition::Normal, 50, 50);
+        let volatile = (MarketCondition::Volatile, 150, 150);
+        assert_eq!(conditions[..5], [normal; 5]);
+        // The spike keeps the market volatile for as long as it is in the window
+        assert_eq!(conditions[5..11], [volatile; 6]);
+        assert_eq!(conditions[11..], [normal; 1]);
+
+        // Widened spreads stay within the maximum spread
+        simulator.volatility_widen_factor = dec!(5);
+        for price in [dec!(2300), dec!(2700)] {
+            simulator.record_cex_price(price);
+        }
+        let range = simulator.calculate_ranges(dec!(2500), None);
+        assert_eq!((range.bid_spread_bps, range.ask_spread_bps), (200, 200));
+    }
+
+    fn eth_usdc_grid(tick_spacing: i32, base_is_currency_0: bool) -> TickGrid {
+        TickGrid { tick_spacing, base_decimals: 18, quote_decimals: 6, base_is_currency_0 }
+    }
+
+    #[test]
+    fn test_ranges_span_the_ticks_of_their_prices() {
+        // 2487.5 and 2512.5 fall in ticks -198130 and -198030
+        let range = MarketMakingSimulator::new_with_default(PoolSymbol::ETH_USDC)
+            .with_tick_grid(eth_usdc_grid(10, true))
+            .calculate_ranges(dec!(2500), None);
+        assert_eq!((range.tick_lower, range.tick_upper), (Some(-198130), Some(-198020)));
+        let range = MarketMakingSimulator::new_with_default(PoolSymbol::ETH_USDC)
+            .with_tick_grid(eth_usdc_grid(60, true))
+            .calculate_ranges(dec!(2500), None);
+        assert_eq!((range.tick_lower, range.tick_upper), (Some(-198180), Some(-198000)));
+
+        // Ticks of a pool whose currency 0 is the quote asset price the quote asset
+        let range = MarketMakingSimulator::new_with_default(PoolSymbol::ETH_USDC)
+            .with_tick_grid(eth_usdc_grid(10, false))
+            .calculate_ranges(dec!(2500), None);
+        assert_eq!((range.tick_lower, range.tick_upper), (Some(198020), Some(198130)));
+
+        // Without the grid of the pool
+        let range = MarketMakingSimulator::new_with_default(PoolSymbol::ETH_USDC)
+            .calculate_ranges(dec!(2500), None);
+        assert_eq!((range.tick_lower, range.tick_upper), (None, None));
+    }
+
+    #[test]
+    fn test_range_ticks_stay_apart_and_within_the_tick_range() {
+        let mut simulator = MarketMakingSimulator::new_with_default(PoolSymbol::ETH_USDC)
+            .with_tick_grid(eth_usdc_grid(10, true));
+        simulator.base_spread_bps = 0;
+        simulator.min_spread_bps = 0;
+        let range = simulator.calculate_ranges(dec!(2500), None);
+        assert_eq!(range.bid_price, range.ask_price);
+        assert_eq!((range.tick_lower, range.tick_upper), (Some(-198080), Some(-198070)));
+
+        // Prices below the lowest tick clamp to the lowest usable ticks
+        let simulator = MarketMakingSimulator::new_with_default(PoolSymbol::ETH_USDC)
+            .with_tick_grid(TickGrid { quote_decimals: 0, ..eth_usdc_grid(10, true) });
+        let range = simulator.calculate_ranges(Decimal::new(1, 22), None);
+        assert_eq!((range.tick_lower, range.tick_upper), (Some(-887270), Some(-887260)));
+    }
+
+    #[test]
+    fn test_capital_is_split_between_the_assets_at_the_pool_price() {
+        let mut simulator = MarketMakingSimulator::new_with_default(PoolSymbol::ETH_USDC)
+            .with_tick_grid(eth_usdc_grid(10, true));
+        simulator.capital_usd = dec!(10000);
+
+        // Both assets are deposited while the pool price is within the range
+        let range = simulator.calculate_ranges(dec!(2500), None);
+        let (base, quote) = (range.amount_token0.unwrap(), range.amount_token1.unwrap());
+        assert_eq!((base.round_dp(4), quote.round_dp(2)), (dec!(2.1690), dec!(4577.45)));
+        assert_eq!((base * dec!(2500) + quote).round_dp(2), dec!(10000));
+
+        // but the quote asset only above it, and the base asset only below it
+        let range = simulator.calculate_ranges(dec!(2500), Some(dec!(2600)));
+        assert_eq!(range.amount_token0, Some(Decimal::ZERO));
+        assert_eq!(range.amount_token1.unwrap().round_dp(2), dec!(10000));
+        let range = simulator.calculate_ranges(dec!(2500), Some(dec!(2400)));
+        assert_eq!(range.amount_token0.unwrap().round_dp(4), dec!(4.1667));
+        assert_eq!(range.amount_token1, Some(Decimal::ZERO));
+
+        // whichever currency of the pool the base asset is
+        simulator.tick_grid = Some(eth_usdc_grid(10, false));
+        let range = simulator.calculate_ranges(dec!(2500), None);
+        let (base, quote) = (range.amount_token0.unwrap(), range.amount_token1.unwrap());
+        assert_eq!((base * dec!(2500) + quote).round_dp(2), dec!(10000));
+        assert_eq!(base.round_dp(4), dec!(2.1690));
+
+        // No amounts without capital
+        simulator.capital_usd = Decimal::ZERO;
+        let range = simulator.calculate_ranges(dec!(2500), None);
+        assert_eq!((range.amount_token0, range.amount_token1), (None, None));
+    }
+
+    #[test]
+    fn test_ranges_serialize_to_json() {
+        let simulator = MarketMakingSimulator::new_with_default(PoolSymbol::ETH_USDC);
+        let range = simulator.calculate_ranges(dec!(2500), None);
+        let json = serde_json::to_value(&range).unwrap();
+        assert_eq!(json["bid_spread_bps"], 50);
+        assert_eq!(json["market_condition"], "normal");
+        assert_eq!(json["reasoning"], range.reasoning.as_str());
+    }
+}
@@ -1,10 +1,41 @@
+mod combinators;
+pub use combinators::{and, or, AndStrategy, OrStrategy};
+
+mod cross_pool;
+pub use cross_pool::{CrossPoolArbitrageStrategy, CrossPoolOpportunity};
+
+mod export;
+pub use export::{SimulationCsvWriter, SimulationExporter};
+
+mod fees;
+pub use fees::RoundTripFees;
+
+mod fills;
+pub use fills::{FillSide, FillSimulator, SimulatedFill};
+
 mod logging;
 pub use logging::LoggingBotStrategy;
 
 mod market_making;
+pub use market_making::{MarketMakingSimulator, TickGrid};
 
-use crate::engine::InternalEvent;
+mod paper;
+pub use paper::PaperTradeLedger;
+
+mod skew;
+pub use skew::{SkewSummary, UpdateSkewTracker, UPDATE_SKEW_METRIC};
+
+use crate::engine::{InternalAction, InternalEvent, PoolSymbol};
 
 pub trait BotStrategy: Send + Sync {
-    fn handle_internal_event(&mut self, event: InternalEvent);
+    /// Returns the symbols whose events the strategy handles, the engine
+    /// dispatching it no event about any other symbol.
+    fn symbols(&self) -> Vec<PoolSymbol>;
+
+    /// Handles an internal event, returning an action if the strategy decides
+    /// one should be taken.
+    fn handle_internal_event(&mut self, event: InternalEvent) -> Option<InternalAction>;
+
+    /// Called once the engine stopped handing events to the strategy.
+    fn on_stop(&mut self) {}
 }
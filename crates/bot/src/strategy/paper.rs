@@ -0,0 +1,74 @@
+//! Ledger of the trades a strategy would have made in paper trading mode.
+
+use rust_decimal::Decimal;
+
+use crate::engine::ArbitrageOpportunity;
+
+/// Simulated profit and loss, trade count and volume of the opportunities a
+/// strategy signalled, as if every one was filled at its quoted prices.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PaperTradeLedger {
+    trade_count: u64,
+    /// Traded size in the base asset, per leg
+    volume: Decimal,
+    /// Expected profit in the quote asset
+    pnl: Decimal,
+}
+
+impl PaperTradeLedger {
+    pub fn new() -> Self { Self::default() }
+
+    /// Records the trade of an opportunity, returning its expected profit in
+    /// the quote asset.
+    pub fn record(&mut self, opportunity: &ArbitrageOpportunity) -> Decimal {
+        let profit = opportunity.net_bps / Decimal::new(10000, 0)
+            * opportunity.cex_price
+            * opportunity.recommended_size;
+        self.trade_count += 1;
+        self.volume += opportunity.recommended_size;
+        self.pnl += profit;
+        profit
+    }
+
+    pub fn trade_count(&self) -> u64 { self.trade_count }
+
+    pub fn volume(&self) -> Decimal { self.volume }
+
+    pub fn pnl(&self) -> Decimal { self.pnl }
+}
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal_macros::dec;
+
+    use super::*;
+    use crate::engine::{ArbitrageDirection, PoolSymbol};
+
+    fn opportunity(net_bps: Decimal, size: Decimal) -> ArbitrageOpportunity {
+        let at = jiff::Timestamp::UNIX_EPOCH;
+        ArbitrageOpportunity {
+            symbol: PoolSymbol::ETH_USDC,
+            direction: ArbitrageDirection::BuyDexSellCex,
+            cex_price: dec!(2500),
+            dex_price: dec!(2475),
+            net_bps,
+            recommended_size: size,
+            detected_at: at,
+            cex_quote_at: at,
+            dex_quote_at: at,
+        }
+    }
+
+    #[test]
+    fn test_ledger_accumulates_the_simulated_trades() {
+        let mut ledger = PaperTradeLedger::new();
+        assert_eq!(ledger.record(&opportunity(dec!(100), dec!(0.5))), dec!(12.5));
+        assert_eq!(ledger.record(&opportunity(dec!(20), dec!(2))), dec!(10));
+        // Without a trade size nothing is traded
+        assert_eq!(ledger.record(&opportunity(dec!(50), Decimal::ZERO)), Decimal::ZERO);
+
+        assert_eq!(ledger.trade_count(), 3);
+        assert_eq!(ledger.volume(), dec!(2.5));
+        assert_eq!(ledger.pnl(), dec!(22.5));
+    }
+}
@@ -0,0 +1,209 @@
+//! CEX/DEX update skew measurement.
+//!
+//! Every time a strategy pairs a CEX and a DEX quote, the time between the pool
+//! update and the nearest-in-time CEX ticker is recorded. A large skew means
+//! opportunities are evaluated against stale pool state, i.e. the pool polling
+//! interval is the bottleneck.
+
+use std::{collections::VecDeque, time::Duration};
+
+use sikkara_core::metrics::{Metric, Subsystem, SymbolLabels};
+use tracing::info;
+
+use crate::engine::PoolSymbol;
+
+/// Histogram the skews are exported to, in seconds.
+pub const UPDATE_SKEW_METRIC: Metric<SymbolLabels> =
+    Metric::new(Subsystem::Strategy, "update_skew_seconds");
+
+/// Number of recent CEX ticker timestamps kept to find the nearest one.
+const MAX_CEX_TIMESTAMPS: usize = 256;
+
+/// Percentiles of the skews recorded within a summary interval.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SkewSummary {
+    pub samples: usize,
+    pub p50: Duration,
+    pub p95: Duration,
+}
+
+/// Tracks the skew between pool updates and CEX tickers of a symbol.
+#[derive(Debug)]
+pub struct UpdateSkewTracker {
+    symbol: PoolSymbol,
+    cex_timestamps: VecDeque<jiff::Timestamp>,
+    /// Skews recorded since the last summary
+    samples: Vec<Duration>,
+    summary_interval: Duration,
+    last_summary_at: Option<jiff::Timestamp>,
+}
+
+impl UpdateSkewTracker {
+    pub fn new(symbol: PoolSymbol, summary_interval: Duration) -> Self {
+        Self {
+            symbol,
+            cex_timestamps: VecDeque::with_capacity(MAX_CEX_TIMESTAMPS),
+            samples: Vec::new(),
+            summary_interval,
+            last_summary_at: None,
+        }
+    }
+
+    /// Records the timestamp of a CEX ticker.
+    pub fn record_cex_update(&mut self, timestamp: jiff::Timestamp) {
+        if self.cex_timestamps.len() == MAX_CEX_TIMESTAMPS {
+            self.cex_timestamps.pop_front();
+        }
+        self.cex_timestamps.push_back(timestamp);
+    }
+
+    /// Records the skew between a pool update and the nearest-in-time CEX
+    /// ticker, returning it. Returns `None` if no CEX ticker was seen yet.
+    pub fn record_skew(&mut self, dex_timestamp: jiff::Timestamp) -> Option<Duration> {
+        let skew = self
+            .cex_timestamps
+            .iter()
+            .map(|cex_timestamp| dex_timestamp.duration_since(*cex_timestamp).unsigned_abs())
+            .min()?;
+        self.samples.push(skew);
+        UPDATE_SKEW_METRIC
+            .histogram(SymbolLabels { symbol: self.symbol.to_string() })
+            .record(skew.as_secs_f64());
+        Some(skew)
+    }
+
+    /// Returns the given percentile of the skews recorded since the last
+    /// summary, using the nearest-rank method.
+    pub fn percentile(&self, percentile: u8) -> Option<Duration> {
+        if self.samples.is_empty() {
+            return None;
+        }
+        let mut sorted = self.samples.clone();
+        sorted.sort_unstable();
+        let rank = (percentile as usize * sorted.len()).div_ceil(100).max(1);
+        Some(sorted[rank.min(sorted.len()) - 1])
+    }
+
+    /// Logs and returns the skew percentiles once per summary interval,
+    /// starting a new interval afterwards.
+    pub fn maybe_summarize(&mut self, now: jiff::Timestamp) -> Option<SkewSummary> {
+        let Some(last_summary_at) = self.last_summary_at else {
+            self.last_summary_at = Some(now);
+            return None;
+        };
+        if now.duration_since(last_summary_at).unsigned_abs() < self.summary_interval {
+            return None;
+        }
+        self.last_summary_at = Some(now);
+
+        let summary = SkewSummary {
+            samples: self.samples.len(),
+            p50: self.percentile(50)?,
+            p95: self.percentile(95)?,
+        };
+        self.samples.clear();
+        info!(
+            "⏱️ CEX/DEX update skew | Symbol: {} | p50: {}ms | p95: {}ms | samples: {}",
+            self.symbol,
+            summary.p50.as_millis(),
+            summary.p95.as_millis(),
+            summary.samples
+        );
+        Some(summary)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use metrics_exporter_prometheus::PrometheusBuilder;
+    use metrics_util::debugging::{DebugValue, DebuggingRecorder};
+
+    use super::*;
+
+    fn at(secs: i64, millis: i64) -> jiff::Timestamp {
+        jiff::Timestamp::from_millisecond(1_739_394_753_000 + secs * 1000 + millis).unwrap()
+    }
+
+    #[test]
+    fn test_skew_is_measured_against_nearest_cex_ticker() {
+        let mut tracker = UpdateSkewTracker::new(PoolSymbol::ETH_USDC, Duration::from_secs(60));
+        assert_eq!(tracker.record_skew(at(0, 0)), None);
+
+        tracker.record_cex_update(at(0, 0));
+        tracker.record_cex_update(at(1, 0));
+        tracker.record_cex_update(at(2, 0));
+
+        // Pool update slightly after the second ticker
+        assert_eq!(tracker.record_skew(at(1, 300)), Some(Duration::from_millis(300)));
+        // Pool update lagging behind the latest ticker
+        assert_eq!(tracker.record_skew(at(2, 900)), Some(Duration::from_millis(900)));
+        // Pool update observed before the ticker it is nearest to
+        assert_eq!(tracker.record_skew(at(1, 800)), Some(Duration::from_millis(200)));
+    }
+
+    #[test]
+    fn test_percentiles_and_periodic_summary() {
+        let mut tracker = UpdateSkewTracker::new(PoolSymbol::ETH_USDC, Duration::from_secs(60));
+        assert_eq!(tracker.maybe_summarize(at(0, 0)), None);
+
+        tracker.record_cex_update(at(0, 0));
+        for millis in 1..=100 {
+            tracker.record_skew(at(0, millis * 10));
+        }
+        assert_eq!(tracker.percentile(50), Some(Duration::from_millis(500)));
+        assert_eq!(tracker.percentile(95), Some(Duration::from_millis(950)));
+
+        // Not yet due
+        assert_eq!(tracker.maybe_summarize(at(59, 0)), None);
+        assert_eq!(
+            tracker.maybe_summarize(at(60, 0)),
+            Some(SkewSummary {
+                samples: 100,
+                p50: Duration::from_millis(500),
+                p95: Duration::from_millis(950),
+            })
+        );
+        assert_eq!(tracker.percentile(50), None);
+        assert_eq!(tracker.maybe_summarize(at(120, 0)), None);
+    }
+
+    #[test]
+    fn test_skews_are_exported_as_histogram_per_symbol() {
+        let recorder = DebuggingRecorder::new();
+        let snapshotter = recorder.snapshotter();
+        metrics::with_local_recorder(&recorder, || {
+            let mut tracker = UpdateSkewTracker::new(PoolSymbol::ETH_USDC, Duration::from_secs(60));
+            tracker.record_cex_update(at(0, 0));
+            tracker.record_skew(at(0, 250));
+            tracker.record_skew(at(1, 500));
+        });
+
+        let snapshot = snapshotter.snapshot().into_vec();
+        assert_eq!(snapshot.len(), 1);
+        let (key, _, _, value) = &snapshot[0];
+        assert_eq!(key.key().name(), UPDATE_SKEW_METRIC.name());
+        assert!(key
+            .key()
+            .labels()
+            .any(|label| label.key() == "symbol" && label.value() == "ETH-USDC"));
+        let DebugValue::Histogram(values) = value else { panic!("expected histogram") };
+        let values = values.iter().map(|v| v.into_inner()).collect::<Vec<_>>();
+        assert_eq!(values, vec![0.25, 1.5]);
+    }
+
+    #[test]
+    fn test_scrape_uses_taxonomy_name() {
+        let recorder = PrometheusBuilder::new().build_recorder();
+        let handle = recorder.handle();
+        metrics::with_local_recorder(&recorder, || {
+            let mut tracker = UpdateSkewTracker::new(PoolSymbol::ETH_USDC, Duration::from_secs(60));
+            tracker.record_cex_update(at(0, 0));
+            tracker.record_skew(at(0, 250));
+        });
+
+        let scrape = handle.render();
+        assert!(
+            scrape.contains("sikarra_strategy_update_skew_seconds_count{symbol=\"ETH-USDC\"} 1")
+        );
+    }
+}
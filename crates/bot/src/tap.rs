@@ -0,0 +1,388 @@
+//! Event tap for strategy development.
+//!
+//! The [`TapRunner`] starts only the collectors of the selected pools and
+//! replaces the engines with a [`TapSink`] printing every [`InternalEvent`],
+//! along with the CEX/DEX spread, to stdout. No strategy runs and no action is
+//! produced.
+
+use futures::future::join_all;
+use rust_decimal::Decimal;
+use sikkara_core::{AppError, AppResult, Engine, EngineRunner, Runner};
+use tracing::Instrument;
+
+use crate::{
+    config::{BotConfig, PoolConfig, PoolTrigger},
+    engine::{ExecutionStatus, FeedState, InternalAction, InternalEvent, PollPolicy},
+    runner::{cex_client, pool_feed_collector, pool_span, resolve_token_decimals},
+};
+
+const RESET: &str = "\x1b[0m";
+const BOLD: &str = "\x1b[1m";
+const RED: &str = "\x1b[31m";
+const GREEN: &str = "\x1b[32m";
+const YELLOW: &str = "\x1b[33m";
+const MAGENTA: &str = "\x1b[35m";
+const CYAN: &str = "\x1b[36m";
+
+/// Output format of the tap.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TapFormat {
+    /// Aligned columns, colorized if `color` is set
+    Pretty { color: bool },
+    /// One JSON object per line
+    Json,
+}
+
+/// Runs the collectors of the selected pools, printing their events.
+#[derive(Debug, Clone)]
+pub struct TapRunner {
+    /// Symbols to tap, all configured pools if empty
+    symbols: Vec<String>,
+    format: TapFormat,
+}
+
+impl TapRunner {
+    pub fn new(symbols: Vec<String>, format: TapFormat) -> Self { Self { symbols, format } }
+
+    /// Returns the configured pools matching the selected symbols.
+    fn select_pools<'a>(&self, pools: &'a [PoolConfig]) -> AppResult<Vec<&'a PoolConfig>> {
+        if let Some(unknown) = self.symbols.iter().find(|symbol| {
+            !pools
+                .iter()
+                .any(|pool| pool.symbol().to_string() == **symbol)
+        }) {
+            let available = pools
+                .iter()
+                .map(|pool| pool.symbol().to_string())
+                .collect::<Vec<_>>()
+                .join(", ");
+            return Err(AppError::ConfigError(format!(
+                "no pool configured for symbol {}, available: {}",
+                unknown, available
+            ))
+            .into());
+        }
+        Ok(pools
+            .iter()
+            .filter(|pool| {
+                self.symbols.is_empty() || self.symbols.contains(&pool.symbol().to_string())
+            })
+            .collect())
+    }
+}
+
+#[async_trait::async_trait]
+impl Runner<BotConfig> for TapRunner {
+    fn name(&self) -> &str { "tap_runner" }
+
+    async fn run(
+        self,
+        mut parameters: BotConfig,
+        shutdown: tokio_util::sync::CancellationToken,
+    ) -> AppResult<()> {
+        resolve_token_decimals(&mut parameters.pools).await?;
+        let pools = self.select_pools(&parameters.pools)?;
+        let mut tasks = Vec::with_capacity(pools.len() + parameters.cex.len());
+        let mut clients = Vec::with_capacity(parameters.cex.len());
+        for cex in &parameters.cex {
+            let (client, consumer) = cex_client(cex, shutdown.child_token())?;
+            clients.push(client);
+            tasks.push(consumer);
+        }
+
+        for pool in pools {
+            let span = pool_span(pool);
+            let mut runner = EngineRunner::<InternalEvent, InternalAction>::new(
+                pool.symbol().to_string(),
+                500,
+                500,
+            );
+            runner.add_engine(Box::new(TapSink::new(pool.symbol().to_string(), self.format)));
+            for client in &clients {
+                runner.add_collector(client.price_feed_collector(pool.symbol_owned(), None));
+            }
+            let poll_policy = match pool.trigger() {
+                PoolTrigger::Timer => PollPolicy::Fixed,
+                PoolTrigger::Block => PollPolicy::Blocks,
+            };
+            runner.add_collector(pool_feed_collector(pool, None, poll_policy).await?);
+
+            let parameters = parameters.clone();
+            let child_token = shutdown.child_token();
+            tasks.push(tokio::spawn(
+                async move { runner.run(parameters, child_token).await }.instrument(span),
+            ));
+        }
+
+        for result in join_all(tasks).await {
+            result??;
+        }
+        Ok(())
+    }
+}
+
+/// Engine printing every event it receives, never producing actions.
+#[derive(Debug)]
+pub struct TapSink {
+    name: String,
+    format: TapFormat,
+    last_cex_price: Option<Decimal>,
+    last_dex_price: Option<Decimal>,
+}
+
+impl TapSink {
+    pub fn new(pool: String, format: TapFormat) -> Self {
+        Self {
+            name: format!("tap_sink_{}", pool),
+            format,
+            last_cex_price: None,
+            last_dex_price: None,
+        }
+    }
+
+    /// Records the price carried by an event, returning the spread of the DEX
+    /// over the CEX price in basis points once both are known.
+    fn observe(&mut self, event: &InternalEvent) -> Option<Decimal> {
+        match event {
+            InternalEvent::TickerUpdate(ticker) => self.last_cex_price = Some(ticker.price),
+            InternalEvent::PoolPriceUpdate(update) => self.last_dex_price = Some(update.price),
+            InternalEvent::FeedStatus(_)
+            | InternalEvent::Execution(_)
+            | InternalEvent::BalanceUpdate(_)
+            | InternalEvent::OrderUpdate(_)
+            | InternalEvent::OwnFill(_) => return None,
+        }
+        spread_bps(self.last_cex_price?, self.last_dex_price?)
+    }
+}
+
+#[async_trait::async_trait]
+impl Engine<InternalEvent, InternalAction> for TapSink {
+    fn id(&self) -> &str { &self.name }
+
+    async fn process_event(&mut self, event: InternalEvent) -> AppResult<Option<InternalAction>> {
+        let spread = self.observe(&event);
+        println!("{}", format_event(&event, spread, self.format));
+        Ok(None)
+    }
+}
+
+/// Spread of the DEX over the CEX price in basis points.
+pub fn spread_bps(cex_price: Decimal, dex_price: Decimal) -> Option<Decimal> {
+    if cex_price.is_zero() {
+        return None;
+    }
+    Some((dex_price - cex_price) / cex_price * Decimal::from(10_000))
+}
+
+/// Formats an event as a single line.
+pub fn format_event(
+    event: &InternalEvent,
+    spread_bps: Option<Decimal>,
+    format: TapFormat,
+) -> String {
+    let color = match format {
+        TapFormat::Json => {
+            return serde_json::json!({
+                "event": event,
+                "spread_bps": spread_bps.map(|spread| spread.round_dp(2).normalize()),
+            })
+            .to_string();
+        },
+        TapFormat::Pretty { color } => color,
+    };
+    let paint = |text: String, style: &str| {
+        if color {
+            format!("{}{}{}", style, text, RESET)
+        } else {
+            text
+        }
+    };
+
+    let (timestamp, kind, symbol, detail) = match event {
+        InternalEvent::TickerUpdate(ticker) => (
+            ticker.timestamp,
+            paint(format!("{:<4}", "CEX"), CYAN),
+            ticker.symbol.to_string(),
+            format!("{:>12.2}", ticker.price.round_dp(2)),
+        ),
+        InternalEvent::PoolPriceUpdate(update) => (
+            update.timestamp,
+            paint(format!("{:<4}", "DEX"), MAGENTA),
+            update.symbol.to_string(),
+            format!("{:>12.2}", update.price.round_dp(2)),
+        ),
+        InternalEvent::FeedStatus(status) => {
+            let state = match status.state {
+                FeedState::Up => paint(format!("{:<4}", "UP"), GREEN),
+                FeedState::Down => paint(format!("{:<4}", "DOWN"), RED),
+            };
+            (
+                status.timestamp,
+                paint(format!("{:<4}", "FEED"), BOLD),
+                status.symbol.to_string(),
+                format!("{} {}  {} ({})", status.source, state, status.reason, status.feed),
+            )
+        },
+        InternalEvent::Execution(execution) => {
+            let detail = match &execution.status {
+                ExecutionStatus::Planned { ordering } => format!("planned {:?}", ordering),
+                ExecutionStatus::Blocked { leg, reason } => {
+                    paint(format!("{} leg blocked: {}", leg, reason), RED)
+                },
+                ExecutionStatus::LegStarted { leg, intent } => {
+                    format!("{} leg started ({:?})", leg, intent)
+                },
+                ExecutionStatus::LegFilled { leg, intent, size, reference } => {
+                    format!("{} leg filled {} ({:?}, {})", leg, size, intent, reference)
+                },
+                ExecutionStatus::LegFailed { reason, .. } => paint(reason.clone(), RED),
+                ExecutionStatus::Completed { size } => paint(format!("completed {}", size), GREEN),
+                ExecutionStatus::Aborted { reason } => paint(format!("aborted: {}", reason), RED),
+                ExecutionStatus::Expired { reason, .. } => {
+                    paint(format!("expired: {}", reason), YELLOW)
+                },
+                ExecutionStatus::Submitted { tx_hash, nonce } => {
+                    format!("{} submitted (nonce {})", tx_hash, nonce)
+                },
+                ExecutionStatus::Confirmed { tx_hash, block_number, .. } => {
+                    paint(format!("{} confirmed in block {}", tx_hash, block_number), GREEN)
+                },
+                ExecutionStatus::Reorged { tx_hash, block_number } => {
+                    paint(format!("{} reorged out of block {}", tx_hash, block_number), YELLOW)
+                },
+                ExecutionStatus::Failed { kind, reason, .. } => {
+                    paint(format!("failed ({:?}): {}", kind, reason), RED)
+                },
+            };
+            (
+                execution.timestamp,
+                paint(format!("{:<4}", "EXEC"), BOLD),
+                execution.symbol.to_string(),
+                detail,
+            )
+        },
+        InternalEvent::BalanceUpdate(balance) => (
+            balance.timestamp,
+            paint(format!("{:<4}", "BAL"), BOLD),
+            balance.asset.clone(),
+            format!("{} free {} total {}", balance.venue, balance.free, balance.total),
+        ),
+        InternalEvent::OwnFill(fill) => (
+            fill.timestamp,
+            paint(format!("{:<4}", "FILL"), BOLD),
+            fill.symbol.to_string(),
+            format!(
+                "{} {:?} {} at {} ({})",
+                fill.exchange, fill.side, fill.size, fill.price, fill.order_id
+            ),
+        ),
+        InternalEvent::OrderUpdate(order) => (
+            order.timestamp,
+            paint(format!("{:<4}", "ORD"), BOLD),
+            order.symbol.to_string(),
+            format!(
+                "{} {:?} {} {:?} filled {}",
+                order.venue, order.side, order.order_id, order.state, order.filled
+            ),
+        ),
+    };
+
+    let mut line =
+        format!("{}  {}  {:<10}  {}", timestamp.strftime("%H:%M:%S%.3f"), kind, symbol, detail);
+    if let Some(spread) = spread_bps {
+        let spread = spread.round_dp(2);
+        let sign = if spread.is_sign_positive() && !spread.is_zero() { "+" } else { "" };
+        line.push_str(&paint(format!("  {:>10} bps", format!("{}{:.2}", sign, spread)), YELLOW));
+    }
+    line
+}
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal_macros::dec;
+
+    use super::*;
+    use crate::engine::{Exchange, FeedStatus, PoolPriceUpdate, PoolSymbol, PriceSource, Ticker};
+
+    fn ticker() -> InternalEvent {
+        InternalEvent::TickerUpdate(Ticker {
+            exchage: Exchange::Coinbase,
+            symbol: PoolSymbol::ETH_USDC,
+            price: dec!(2500.004),
+            timestamp: "2025-02-12T21:12:30.4Z".parse().unwrap(),
+            bid: None,
+            ask: None,
+            bid_size: None,
+            ask_size: None,
+            volume_24h: None,
+        })
+    }
+
+    fn pool_update() -> InternalEvent {
+        InternalEvent::PoolPriceUpdate(PoolPriceUpdate {
+            symbol: PoolSymbol::ETH_USDC,
+            price: dec!(2497.5),
+            timestamp: "2025-02-12T21:12:31Z".parse().unwrap(),
+            block_number: None,
+            state: None,
+            effective_buy_price: None,
+            effective_sell_price: None,
+        })
+    }
+
+    fn feed_down() -> InternalEvent {
+        InternalEvent::FeedStatus(FeedStatus {
+            feed: "pool_feed_collector_ETH-USDC".to_string(),
+            source: PriceSource::Dex,
+            symbol: PoolSymbol::ETH_USDC,
+            state: FeedState::Down,
+            reason: "stream ended".to_string(),
+            timestamp: "2025-02-12T21:12:32Z".parse().unwrap(),
+        })
+    }
+
+    #[test]
+    fn test_pretty_format_aligns_columns() {
+        let format = TapFormat::Pretty { color: false };
+        assert_eq!(
+            format_event(&ticker(), None, format),
+            "21:12:30.400  CEX   ETH-USDC         2500.00"
+        );
+        assert_eq!(
+            format_event(&pool_update(), Some(dec!(-10.004)), format),
+            "21:12:31.000  DEX   ETH-USDC         2497.50      -10.00 bps"
+        );
+        assert_eq!(
+            format_event(&feed_down(), None, format),
+            "21:12:32.000  FEED  ETH-USDC    dex DOWN  stream ended (pool_feed_collector_ETH-USDC)"
+        );
+    }
+
+    #[test]
+    fn test_pretty_format_colorizes() {
+        let line = format_event(&ticker(), Some(dec!(12.5)), TapFormat::Pretty { color: true });
+        assert_eq!(
+            line,
+            "21:12:30.400  \x1b[36mCEX \x1b[0m  ETH-USDC         2500.00\x1b[33m      +12.50 bps\x1b[0m"
+        );
+    }
+
+    #[test]
+    fn test_json_format() {
+        let line = format_event(&pool_update(), Some(dec!(-10.004)), TapFormat::Json);
+        let value: serde_json::Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(value["event"]["type"], "pool_price_update");
+        assert_eq!(value["event"]["symbol"], "ETH-USDC");
+        assert_eq!(value["spread_bps"], "-10");
+    }
+
+    #[test]
+    fn test_sink_computes_spread_once_both_prices_are_known() {
+        let mut sink = TapSink::new("ETH-USDC".to_string(), TapFormat::Json);
+        assert_eq!(sink.observe(&ticker()), None);
+        let spread = sink.observe(&pool_update()).unwrap();
+        assert_eq!(spread.round_dp(2), dec!(-10.02));
+        assert_eq!(sink.observe(&feed_down()), None);
+    }
+}
@@ -1,12 +1,36 @@
+use std::time::{Duration, Instant};
+
+/// Policy deciding how long to wait before retrying a failed operation.
+pub trait BackoffStrategy {
+    /// Returns the delay to wait before the next retry, none once no retry is
+    /// left.
+    fn next_delay(&mut self) -> Option<Duration>;
+
+    /// Starts over after a success.
+    fn reset(&mut self);
+}
+
 /// A utility for implementing exponential backoff retry logic.
+///
+/// Yields `min`, `min * factor`, `min * factor^2`, ... capped at `max`, for at
+/// most `retries` delays or forever if
+/// [`unlimited`](ExponentialBackoff::unlimited). With a
+/// [`max_elapsed`](ExponentialBackoff::with_max_elapsed) time, it also stops
+/// once that much time has passed since the first delay. The sequence starts
+/// over after a [`reset`](BackoffStrategy::reset).
 #[derive(Debug, Clone)]
 pub struct ExponentialBackoff {
-    retries: u8,
-    min_secs: u32,
-    max_secs: u32,
+    /// Maximum number of delays, unlimited if none
+    retries: Option<u32>,
+    min: Duration,
+    max: Duration,
     factor: u32,
-    counter: u8,
-    value_secs: u32,
+    counter: u32,
+    value: Duration,
+    /// Time after which no delay is returned, counted from the first one
+    max_elapsed: Option<Duration>,
+    /// Instant of the first delay since the last reset
+    start: Option<Instant>,
 }
 
 impl Default for ExponentialBackoff {
@@ -19,57 +43,250 @@ impl Default for ExponentialBackoff {
 }
 
 impl ExponentialBackoff {
-    /// Creates a new `ExponentialBackoff` instance with the specified
-    /// parameters.
-    pub fn new(retries: u8, min_secs: u32, max_secs: u32, factor: u32) -> Self {
-        Self { retries, min_secs, max_secs, factor, counter: 0, value_secs: min_secs }
+    /// Creates a new `ExponentialBackoff` instance yielding at most `retries`
+    /// delays in whole seconds.
+    pub fn new(retries: u32, min_secs: u32, max_secs: u32, factor: u32) -> Self {
+        Self::from_durations(
+            Some(retries),
+            Duration::from_secs(min_secs.into()),
+            Duration::from_secs(max_secs.into()),
+            factor,
+        )
     }
 
-    /// Resets the backoff counter to zero.
-    pub fn reset(&mut self) {
-        self.counter = 0;
-        self.value_secs = 0;
+    /// Creates a new `ExponentialBackoff` instance with delays of any
+    /// resolution, e.g. starting at 250ms, yielding at most `retries` delays
+    /// or forever if none.
+    pub fn from_durations(retries: Option<u32>, min: Duration, max: Duration, factor: u32) -> Self {
+        Self { retries, min, max, factor, counter: 0, value: min, max_elapsed: None, start: None }
     }
 
-    /// Returns the current backoff value in seconds.
-    pub fn value_secs(&self) -> u32 { self.value_secs }
+    /// Creates a new `ExponentialBackoff` instance which never runs out of
+    /// retries, e.g. to keep reconnecting a long-lived connection.
+    pub fn unlimited(min: Duration, max: Duration, factor: u32) -> Self {
+        Self::from_durations(None, min, max, factor)
+    }
+
+    /// Stops returning delays once `max_elapsed` has passed since the first
+    /// one, whatever the retries left, e.g. to give up reconnecting after 5
+    /// minutes.
+    pub fn with_max_elapsed(mut self, max_elapsed: Duration) -> Self {
+        self.max_elapsed = Some(max_elapsed);
+        self
+    }
+
+    /// Returns the time elapsed since the first delay, zero before it.
+    pub fn elapsed(&self) -> Duration {
+        self.start.map(|start| start.elapsed()).unwrap_or_default()
+    }
+
+    /// Returns true if the backoff never runs out of retries.
+    pub fn is_unlimited(&self) -> bool { self.retries.is_none() }
+
+    /// Returns the next delay.
+    pub fn value(&self) -> Duration { self.value }
+
+    /// Returns the next delay in whole seconds.
+    pub fn value_secs(&self) -> u32 { self.value.as_secs() as u32 }
 
     /// Get iteration count
-    pub fn get_iteration_count(&self) -> u8 { self.counter }
+    pub fn get_iteration_count(&self) -> u32 { self.counter }
 }
 
-impl Iterator for ExponentialBackoff {
-    type Item = u32;
-
-    /// Returns the next backoff value, incrementing the counter and applying
-    /// exponential backoff logic.
-    fn next(&mut self) -> Option<Self::Item> {
-        // Check if we have reached the maximum number of retries
-        // If retries is set to 9, it means unlimited number of retries
-        // If retries is > 0 and we have reached or exceeded the retry limit, stop
-        // iteration.
-        if self.retries > 0 && self.counter >= self.retries {
+impl BackoffStrategy for ExponentialBackoff {
+    /// Returns the delay to wait before the next retry, incrementing the
+    /// counter and applying exponential backoff logic, none once the retries
+    /// are exhausted.
+    fn next_delay(&mut self) -> Option<Duration> {
+        // Stop once the retry limit is reached, if any
+        if self.retries.is_some_and(|retries| self.counter >= retries) {
+            return None;
+        }
+
+        // Stop once the time budget is spent, if any
+        let start = *self.start.get_or_insert_with(Instant::now);
+        if self
+            .max_elapsed
+            .is_some_and(|max_elapsed| start.elapsed() > max_elapsed)
+        {
             return None;
         }
 
-        // Store the current value to return.
-        let value = self.value_secs;
-        self.counter += 1;
-        self.value_secs = match self.counter {
-            // First iteration, use minimum delay
-            0 => self.min_secs,
-            // Applu exponential backoff for subsequent iterations
-            _ => {
-                let next_value = self.value_secs * self.factor;
-                if next_value > self.max_secs {
-                    self.max_secs
-                } else {
-                    next_value
-                }
-            },
-        };
-        // Return the backoff value for this iteration.
-        // The caller should wait for value seconds before the next retry
+        // Store the current value to return, the next one is multiplied by the
+        // factor up to the maximum delay
+        let value = self.value;
+        self.counter = self.counter.saturating_add(1);
+        self.value = self
+            .value
+            .checked_mul(self.factor)
+            .unwrap_or(self.max)
+            .min(self.max);
         Some(value)
     }
+
+    /// Resets the backoff, so the next delay is `min` again.
+    fn reset(&mut self) {
+        self.counter = 0;
+        self.value = self.min;
+        self.start = None;
+    }
+}
+
+impl Iterator for ExponentialBackoff {
+    type Item = Duration;
+
+    /// Returns the next delay, see [`BackoffStrategy::next_delay`].
+    fn next(&mut self) -> Option<Self::Item> { self.next_delay() }
+}
+
+/// Backoff waiting the same delay before every retry, e.g. in tests.
+#[derive(Debug, Clone)]
+pub struct ConstantBackoff {
+    delay: Duration,
+    retries: Option<u32>,
+    counter: u32,
+}
+
+impl ConstantBackoff {
+    /// Creates a backoff waiting `delay` before at most `retries` retries, or
+    /// before every retry if none.
+    pub fn new(delay: Duration, retries: Option<u32>) -> Self {
+        Self { delay, retries, counter: 0 }
+    }
+}
+
+impl BackoffStrategy for ConstantBackoff {
+    fn next_delay(&mut self) -> Option<Duration> {
+        if self.retries.is_some_and(|retries| self.counter >= retries) {
+            return None;
+        }
+        self.counter = self.counter.saturating_add(1);
+        Some(self.delay)
+    }
+
+    fn reset(&mut self) { self.counter = 0; }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn secs(values: &[u64]) -> Vec<Duration> {
+        values
+            .iter()
+            .map(|secs| Duration::from_secs(*secs))
+            .collect()
+    }
+
+    fn millis(values: &[u64]) -> Vec<Duration> {
+        values
+            .iter()
+            .map(|millis| Duration::from_millis(*millis))
+            .collect()
+    }
+
+    #[test]
+    fn test_sequence_is_capped_at_max() {
+        let backoff = ExponentialBackoff::new(7, 1, 10, 2);
+        assert_eq!(backoff.collect::<Vec<_>>(), secs(&[1, 2, 4, 8, 10, 10, 10]));
+    }
+
+    #[test]
+    fn test_reset_mid_sequence_restarts_at_min() {
+        let mut backoff = ExponentialBackoff::new(10, 2, 60, 3);
+        assert_eq!(backoff.by_ref().take(3).collect::<Vec<_>>(), secs(&[2, 6, 18]));
+
+        backoff.reset();
+        assert_eq!(backoff.value_secs(), 2);
+        assert_eq!(backoff.get_iteration_count(), 0);
+        assert_eq!(backoff.by_ref().take(3).collect::<Vec<_>>(), secs(&[2, 6, 18]));
+
+        // Resetting again behaves the same
+        backoff.reset();
+        assert_eq!(backoff.next_delay(), Some(Duration::from_secs(2)));
+    }
+
+    #[test]
+    fn test_retries_are_exhausted() {
+        let mut backoff = ExponentialBackoff::new(3, 1, 60, 2);
+        assert_eq!(backoff.by_ref().collect::<Vec<_>>(), secs(&[1, 2, 4]));
+        assert_eq!(backoff.next_delay(), None);
+
+        // A reset allows as many retries again
+        backoff.reset();
+        assert_eq!(backoff.count(), 3);
+    }
+
+    #[test]
+    fn test_millisecond_delays() {
+        let backoff = ExponentialBackoff::from_durations(
+            Some(4),
+            Duration::from_millis(250),
+            Duration::from_secs(5),
+            2,
+        );
+        assert_eq!(backoff.collect::<Vec<_>>(), millis(&[250, 500, 1000, 2000]));
+    }
+
+    #[test]
+    fn test_fractional_max_caps_the_sequence() {
+        let mut backoff = ExponentialBackoff::from_durations(
+            Some(5),
+            Duration::from_millis(300),
+            Duration::from_millis(1500),
+            3,
+        );
+        assert_eq!(backoff.by_ref().collect::<Vec<_>>(), millis(&[300, 900, 1500, 1500, 1500]));
+        assert_eq!(backoff.value(), Duration::from_millis(1500));
+        assert_eq!(backoff.value_secs(), 1);
+    }
+
+    #[test]
+    fn test_no_retry() {
+        let mut backoff = ExponentialBackoff::new(0, 1, 60, 2);
+        assert_eq!(backoff.next_delay(), None);
+    }
+
+    #[test]
+    fn test_unlimited_backoff_never_runs_out() {
+        let mut backoff =
+            ExponentialBackoff::unlimited(Duration::from_millis(100), Duration::from_secs(1), 2);
+        assert!(backoff.is_unlimited());
+        // Well past the range of the former u8 counter
+        let delays = backoff.by_ref().take(1_000).collect::<Vec<_>>();
+        assert_eq!(delays.len(), 1_000);
+        assert_eq!(delays[..5], millis(&[100, 200, 400, 800, 1000]));
+        assert_eq!(delays.last(), Some(&Duration::from_secs(1)));
+        assert_eq!(backoff.get_iteration_count(), 1_000);
+    }
+
+    #[test]
+    fn test_max_elapsed_stops_before_retries_are_exhausted() {
+        let mut backoff =
+            ExponentialBackoff::unlimited(Duration::from_millis(1), Duration::from_millis(1), 2)
+                .with_max_elapsed(Duration::from_millis(50));
+        assert_eq!(backoff.elapsed(), Duration::ZERO);
+        assert_eq!(backoff.next_delay(), Some(Duration::from_millis(1)));
+        assert_eq!(backoff.next_delay(), Some(Duration::from_millis(1)));
+
+        std::thread::sleep(Duration::from_millis(60));
+        assert!(backoff.elapsed() > Duration::from_millis(50));
+        assert_eq!(backoff.next_delay(), None);
+
+        // A reset restarts the clock
+        backoff.reset();
+        assert_eq!(backoff.elapsed(), Duration::ZERO);
+        assert_eq!(backoff.next_delay(), Some(Duration::from_millis(1)));
+    }
+
+    #[test]
+    fn test_constant_backoff() {
+        let mut backoff = ConstantBackoff::new(Duration::from_millis(10), Some(2));
+        assert_eq!(backoff.next_delay(), Some(Duration::from_millis(10)));
+        assert_eq!(backoff.next_delay(), Some(Duration::from_millis(10)));
+        assert_eq!(backoff.next_delay(), None);
+
+        backoff.reset();
+        assert_eq!(backoff.next_delay(), Some(Duration::from_millis(10)));
+    }
 }
@@ -0,0 +1,80 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+/// A source of the current time.
+///
+/// Components depending on wall clock time take a [`Clock`] so tests can drive
+/// time explicitly with a [`MockClock`].
+pub trait Clock: Send + Sync + std::fmt::Debug {
+    /// Returns the current time.
+    fn now(&self) -> jiff::Timestamp;
+}
+
+/// The system wall clock.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> jiff::Timestamp { jiff::Timestamp::now() }
+}
+
+/// A manually driven clock for tests.
+///
+/// Cloning is cheap, all clones share the same time.
+#[derive(Debug, Clone)]
+pub struct MockClock {
+    now: Arc<Mutex<jiff::Timestamp>>,
+}
+
+impl MockClock {
+    /// Creates a new `MockClock` starting at the given time.
+    pub fn new(start: jiff::Timestamp) -> Self { Self { now: Arc::new(Mutex::new(start)) } }
+
+    /// Sets the current time.
+    pub fn set(&self, now: jiff::Timestamp) {
+        *self
+            .now
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = now;
+    }
+
+    /// Moves the current time forward by the given duration.
+    pub fn advance(&self, duration: Duration) {
+        let mut now = self
+            .now
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        *now = now
+            .checked_add(duration)
+            .expect("mock clock advanced out of range");
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> jiff::Timestamp {
+        *self
+            .now
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mock_clock_is_shared_between_clones() {
+        let start: jiff::Timestamp = "2025-02-12T21:00:00Z".parse().unwrap();
+        let clock = MockClock::new(start);
+        let clone = clock.clone();
+
+        clone.advance(Duration::from_secs(90));
+        assert_eq!(clock.now().to_string(), "2025-02-12T21:01:30Z");
+
+        clock.set(start);
+        assert_eq!(clone.now(), start);
+    }
+}
@@ -0,0 +1,261 @@
+use std::path::{Path, PathBuf};
+
+use serde::de::DeserializeOwned;
+use tokio::sync::broadcast;
+use tracing::warn;
+
+use crate::{AppError, AppResult};
+
+/// Number of reloaded configurations a subscriber may lag behind by
+const RELOAD_CHANNEL_CAPACITY: usize = 4;
+
+/// Format of a configuration file.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ConfigFormat {
+    Json,
+    Toml,
+    /// Detected from the extension of the file, JSON unless it is `.toml`
+    #[default]
+    Auto,
+}
+
+impl ConfigFormat {
+    /// Resolves the format of the file at `path`, detecting it from the
+    /// extension of the file if `Auto`.
+    fn of(self, path: &Path) -> Self {
+        match self {
+            ConfigFormat::Auto => match path.extension().and_then(|ext| ext.to_str()) {
+                Some(ext) if ext.eq_ignore_ascii_case("toml") => ConfigFormat::Toml,
+                _ => ConfigFormat::Json,
+            },
+            format => format,
+        }
+    }
+}
+
+/// Reads and deserializes the configuration file at `path` in the given
+/// format, after replacing the `${ENV_VAR_NAME}` tokens of the file with the
+/// values of the environment variables, `$${` escaping a literal `${`.
+pub fn load_config<T: DeserializeOwned>(path: &Path, format: ConfigFormat) -> AppResult<T> {
+    let content = std::fs::read_to_string(path).map_err(|e| {
+        AppError::ConfigError(format!("cannot read configuration file {}: {}", path.display(), e))
+    })?;
+    let content = substitute_env_vars(&content, |name| std::env::var(name).ok())?;
+    // The substituted values may be secrets, so errors do not quote the file
+    let config = match format.of(path) {
+        ConfigFormat::Toml => toml::from_str(&content).map_err(|e| e.message().to_string()),
+        _ => serde_json::from_str(&content).map_err(|e| e.to_string()),
+    };
+    config.map_err(|e| {
+        AppError::ConfigError(format!("invalid configuration file {}: {}", path.display(), e))
+            .into()
+    })
+}
+
+/// Reloader of a configuration file, re-reading and validating the file on
+/// demand, e.g. on SIGHUP, and broadcasting the reloaded configuration to the
+/// subscribed components.
+pub struct ConfigReloader<T> {
+    path: PathBuf,
+    format: ConfigFormat,
+    validate: fn(&T) -> AppResult<()>,
+    sender: broadcast::Sender<T>,
+}
+
+impl<T: Clone> ConfigReloader<T> {
+    /// Reloads the configuration file at `path` in the given format.
+    pub fn new(path: impl Into<PathBuf>, format: ConfigFormat) -> Self {
+        let (sender, _) = broadcast::channel(RELOAD_CHANNEL_CAPACITY);
+        Self { path: path.into(), format, validate: |_| Ok(()), sender }
+    }
+
+    /// Broadcasts only the configurations `validate` accepts.
+    pub fn with_validation(mut self, validate: fn(&T) -> AppResult<()>) -> Self {
+        self.validate = validate;
+        self
+    }
+
+    /// Sender the reloaded configurations are broadcast through, to subscribe
+    /// to them.
+    pub fn sender(&self) -> broadcast::Sender<T> { self.sender.clone() }
+}
+
+impl<T: DeserializeOwned> ConfigReloader<T> {
+    /// Re-reads and validates the configuration file, broadcasting it to the
+    /// subscribers. An invalid file is not broadcast.
+    pub fn reload(&self) -> AppResult<()> {
+        let config = load_config(&self.path, self.format)?;
+        (self.validate)(&config)?;
+        if self.sender.send(config).is_err() {
+            warn!("configuration reloaded without any subscriber");
+        }
+        Ok(())
+    }
+}
+
+/// Replaces the `${ENV_VAR_NAME}` tokens of `content` with the values `lookup`
+/// returns for them, failing on the first variable that is not set. `$${`
+/// escapes a literal `${`.
+fn substitute_env_vars(
+    content: &str,
+    lookup: impl Fn(&str) -> Option<String>,
+) -> AppResult<String> {
+    let mut substituted = String::with_capacity(content.len());
+    let mut rest = content;
+    while let Some(start) = rest.find('$') {
+        substituted.push_str(&rest[..start]);
+        rest = &rest[start..];
+        if let Some(escaped) = rest.strip_prefix("$${") {
+            substituted.push_str("${");
+            rest = escaped;
+        } else if let Some(token) = rest.strip_prefix("${") {
+            let end = token.find('}').ok_or_else(|| {
+                AppError::ConfigError("unterminated ${ in configuration".to_string())
+            })?;
+            let name = &token[..end];
+            let value = lookup(name)
+                .ok_or_else(|| AppError::ConfigError(format!("missing env var: {}", name)))?;
+            substituted.push_str(&value);
+            rest = &token[end + 1..];
+        } else {
+            substituted.push('$');
+            rest = &rest[1..];
+        }
+    }
+    substituted.push_str(rest);
+    Ok(substituted)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use super::*;
+
+    #[derive(Debug, Clone, PartialEq, Deserialize)]
+    struct PoolConfig {
+        symbol: String,
+        fee_tier: u32,
+        hook_address: Option<String>,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Deserialize)]
+    struct Config {
+        pools: Vec<PoolConfig>,
+    }
+
+    fn expected() -> Config {
+        Config {
+            pools: vec![PoolConfig {
+                symbol: "ETH-USDC".to_string(),
+                fee_tier: 500,
+                hook_address: None,
+            }],
+        }
+    }
+
+    const JSON: &str = r#"{ "pools": [{ "symbol": "ETH-USDC", "fee_tier": 500 }] }"#;
+
+    const TOML: &str = r#"
+        [[pools]]
+        symbol = "ETH-USDC"
+        fee_tier = 500
+    "#;
+
+    #[test]
+    fn test_format_is_detected_from_the_extension() {
+        let dir = tempfile::tempdir().unwrap();
+        for (name, content) in [("bot.json", JSON), ("bot.toml", TOML), ("bot.TOML", TOML)] {
+            let path = dir.path().join(name);
+            std::fs::write(&path, content).unwrap();
+            let config: Config = load_config(&path, ConfigFormat::Auto).unwrap();
+            assert_eq!(config, expected(), "{}", name);
+        }
+
+        // Files without a known extension are JSON, unless told otherwise
+        let path = dir.path().join("bot.conf");
+        std::fs::write(&path, TOML).unwrap();
+        assert!(load_config::<Config>(&path, ConfigFormat::Auto).is_err());
+        let config: Config = load_config(&path, ConfigFormat::Toml).unwrap();
+        assert_eq!(config, expected());
+    }
+
+    fn lookup(name: &str) -> Option<String> {
+        match name {
+            "NODE_URL" => Some("https://mainnet.infura.io/v3/key".to_string()),
+            "EMPTY" => Some(String::new()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_env_vars_are_substituted() {
+        let substitute = |content| substitute_env_vars(content, lookup).unwrap();
+        assert_eq!(
+            substitute(r#"{ "node_url": "${NODE_URL}", "ws_url": "wss://${EMPTY}host" }"#),
+            r#"{ "node_url": "https://mainnet.infura.io/v3/key", "ws_url": "wss://host" }"#
+        );
+        // Escaped and lone dollars are kept as they are
+        assert_eq!(substitute("$${NODE_URL} costs $5 $"), "${NODE_URL} costs $5 $");
+    }
+
+    #[test]
+    fn test_missing_env_vars_are_config_errors() {
+        let error = substitute_env_vars("${NODE_URL} ${API_KEY}", lookup).unwrap_err();
+        assert_eq!(error.to_string(), "Configuration error: missing env var: API_KEY");
+        assert!(matches!(error.downcast_ref(), Some(AppError::ConfigError(_))));
+        assert!(substitute_env_vars("${NODE_URL", lookup).is_err());
+    }
+
+    #[test]
+    fn test_config_files_are_substituted() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("bot.json");
+        std::fs::write(
+            &path,
+            r#"{ "pools": [{ "symbol": "${SIKKARA_TEST_SYMBOL}", "fee_tier": 500 }] }"#,
+        )
+        .unwrap();
+        assert!(load_config::<Config>(&path, ConfigFormat::Auto).is_err());
+        std::env::set_var("SIKKARA_TEST_SYMBOL", "ETH-USDC");
+        let config: Config = load_config(&path, ConfigFormat::Auto).unwrap();
+        assert_eq!(config, expected());
+    }
+
+    #[test]
+    fn test_reloaded_configs_are_broadcast() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("bot.toml");
+        std::fs::write(&path, TOML).unwrap();
+        let reloader =
+            ConfigReloader::<Config>::new(&path, ConfigFormat::Auto).with_validation(|config| {
+                match config.pools.is_empty() {
+                    true => Err(AppError::ConfigError("no pool".to_string()).into()),
+                    false => Ok(()),
+                }
+            });
+        let mut reloads = reloader.sender().subscribe();
+        reloader.reload().unwrap();
+        assert_eq!(reloads.try_recv().unwrap(), expected());
+
+        // Invalid configurations are kept from the subscribers
+        std::fs::write(&path, "pools = []").unwrap();
+        assert!(reloader.reload().is_err());
+        std::fs::write(&path, "pools = ").unwrap();
+        assert!(reloader.reload().is_err());
+        assert!(reloads.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_invalid_files_are_config_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("bot.toml");
+        let error = load_config::<Config>(&path, ConfigFormat::Auto).unwrap_err();
+        assert!(error.to_string().contains("cannot read configuration file"));
+
+        std::fs::write(&path, JSON).unwrap();
+        let error = load_config::<Config>(&path, ConfigFormat::Auto).unwrap_err();
+        assert!(error.to_string().contains("invalid configuration file"));
+        assert!(matches!(error.downcast_ref(), Some(AppError::ConfigError(_))));
+    }
+}
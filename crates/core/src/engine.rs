@@ -1,4 +1,11 @@
-use std::{pin::Pin, sync::Arc};
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
 
 use futures::{Stream, StreamExt};
 use tokio::{
@@ -8,9 +15,9 @@ use tokio::{
     },
     task::JoinSet,
 };
-use tracing::{error, event, info, warn};
+use tracing::{error, event, info, info_span, warn, Instrument, Span};
 
-use crate::{error::AppResult, runner::Runner};
+use crate::{error::AppResult, liveness::CollectorLiveness, runner::Runner};
 
 /// A core processing trait that handles events and produces actions which can
 /// be executed by the application.
@@ -23,6 +30,11 @@ pub trait Engine<Event, Action>: Send + Sync {
     /// It returns a vector of actions, which would ideally be executed by
     /// the application.
     async fn process_event(&mut self, event: Event) -> AppResult<Option<Action>>;
+
+    /// Called once after the engine stopped receiving events, either because
+    /// shutdown was requested or the event channel closed. Engines holding
+    /// buffered state should flush it here.
+    async fn on_stop(&mut self) -> AppResult<()> { Ok(()) }
 }
 
 /// A specialzed stream type for event collectors in the application.
@@ -44,6 +56,15 @@ pub trait Collector<Event>: Send + Sync {
 
     /// Unsubscribe from the event stream of this collector
     async fn unsubscribe_event_stream(&mut self) -> AppResult<()>;
+
+    /// Span the collector runs in. Collectors may override it to attach
+    /// fields such as the exchange they are collecting from.
+    fn span(&self) -> Span { info_span!("collector", collector = self.name()) }
+
+    /// Whether the liveness of the collector is tracked. Collectors only
+    /// yielding events sporadically, which would be reported down while idle,
+    /// opt out.
+    fn tracks_liveness(&self) -> bool { true }
 }
 
 /// A trait that executes actions produced by the engine.
@@ -60,6 +81,28 @@ pub trait Executor<Action>: Send + Sync {
     async fn execute_actions(&mut self, actions: Vec<Action>) -> AppResult<()>;
 }
 
+/// Source of the correlation ids attached to every collected event.
+static NEXT_CORRELATION_ID: AtomicU64 = AtomicU64::new(1);
+
+tokio::task_local! {
+    static CORRELATION_ID: u64;
+}
+
+/// Returns the correlation id of the event being processed by an [`Engine`]
+/// or of the action being executed by an [`Executor`], none outside of them.
+///
+/// Executors emitting follow-up events, e.g. the outcome of a transaction,
+/// attach it so those can be traced back to the originating event.
+pub fn current_correlation_id() -> Option<u64> { CORRELATION_ID.try_with(|id| *id).ok() }
+
+/// An event or action tagged with the correlation id of the event it
+/// originates from, so log lines across the pipeline can be correlated.
+#[derive(Debug, Clone)]
+struct Correlated<T> {
+    correlation_id: u64,
+    inner: T,
+}
+
 /// A high level orchestrator that connects the event collection, processing,
 /// and execution components of the application.
 ///
@@ -67,6 +110,10 @@ pub trait Executor<Action>: Send + Sync {
 ///     1. Collecting events from multiple [`Collector`]
 ///     2. Passing these events to the [`Engine`] components for processing
 ///     3. Forwarding the resulting actions to the [`Executor`] for execution
+///
+/// Every component runs in its own span, a child of the span the runner is run
+/// in. Events are processed and actions executed within an `event` span
+/// carrying the `correlation_id` of the originating event.
 #[allow(unused)]
 pub struct EngineRunner<Event, Action> {
     name: String,
@@ -75,6 +122,7 @@ pub struct EngineRunner<Event, Action> {
     executors: Vec<Box<dyn Executor<Action>>>,
     event_channel_capacity: usize,
     action_channel_capacity: usize,
+    liveness: Option<CollectorLiveness>,
 }
 
 impl<Event, Action> EngineRunner<Event, Action> {
@@ -92,6 +140,7 @@ impl<Event, Action> EngineRunner<Event, Action> {
             executors: Vec::new(),
             event_channel_capacity,
             action_channel_capacity,
+            liveness: None,
         }
     }
 
@@ -109,6 +158,13 @@ impl<Event, Action> EngineRunner<Event, Action> {
     pub fn add_executor(&mut self, executor: Box<dyn Executor<Action>>) {
         self.executors.push(executor);
     }
+
+    /// Track the liveness of the collectors, keyed by collector name. The
+    /// liveness may be shared between runners, its metrics are exported by
+    /// the task spawned with [`CollectorLiveness::spawn`].
+    pub fn set_collector_liveness(&mut self, liveness: CollectorLiveness) {
+        self.liveness = Some(liveness);
+    }
 }
 
 #[async_trait::async_trait]
@@ -125,9 +181,11 @@ where
         parameters: P,
         shutdown: tokio_util::sync::CancellationToken,
     ) -> AppResult<()> {
-        let (event_sender, event_receiver): (Sender<Event>, Receiver<Event>) =
-            broadcast::channel(self.event_channel_capacity);
-        let (action_sender, _): (Sender<Action>, _) =
+        let (event_sender, event_receiver): (
+            Sender<Correlated<Event>>,
+            Receiver<Correlated<Event>>,
+        ) = broadcast::channel(self.event_channel_capacity);
+        let (action_sender, _): (Sender<Correlated<Action>>, _) =
             broadcast::channel(self.action_channel_capacity);
 
         let mut join_set = JoinSet::new();
@@ -136,13 +194,17 @@ where
         for mut executor in self.executors {
             let mut action_receiver = action_sender.subscribe();
             let executor_shutdown = shutdown.child_token();
-            join_set.spawn(async move {
+            let span = info_span!("executor", executor = executor.id());
+            let task_name = format!("{}/executor/{}", self.name, executor.id());
+            spawn_named(&mut join_set, &task_name, async move {
                 info!("starting executor with id: {}", executor.id());
                 loop {
                     tokio::select! {
                         action = action_receiver.recv() => match action {
                             Ok(action) => {
-                                if let Err(e) = executor.execute_actions(vec![action]).await {
+                                let span = info_span!("event", correlation_id = action.correlation_id);
+                                let execution = executor.execute_actions(vec![action.inner]).instrument(span);
+                                if let Err(e) = CORRELATION_ID.scope(action.correlation_id, execution).await {
                                     error!("executor {} failed to execute actions: {}", executor.id(), e);
                                 }
                             },
@@ -160,7 +222,7 @@ where
                         }
                     }
                 }
-            });
+            }.instrument(span));
         }
 
         // Spawn engines in separate tasks
@@ -168,14 +230,19 @@ where
             let mut event_receiver = event_sender.subscribe();
             let action_sender = action_sender.clone();
             let engine_shutdown = shutdown.child_token();
-            join_set.spawn(async move {
+            let span = info_span!("engine", engine = engine.id());
+            let task_name = format!("{}/engine/{}", self.name, engine.id());
+            spawn_named(&mut join_set, &task_name, async move {
                 info!("starting engine with id: {}", engine.id());
                 loop {
                     tokio::select! {
                         event = event_receiver.recv() => match event {
                             Ok(event) => {
-                                if let Ok(Some(actions)) = engine.process_event(event).await {
-                                    if let Err(e) = action_sender.send(actions) {
+                                let correlation_id = event.correlation_id;
+                                let span = info_span!("event", correlation_id);
+                                let processing = engine.process_event(event.inner).instrument(span);
+                                if let Ok(Some(action)) = CORRELATION_ID.scope(correlation_id, processing).await {
+                                    if let Err(e) = action_sender.send(Correlated { correlation_id, inner: action }) {
                                         error!("engine {} failed to send actions: {}", engine.id(), e);
                                     }
                                 }
@@ -195,16 +262,28 @@ where
                     }
                 }
 
-            });
+                if let Err(e) = engine.on_stop().await {
+                    error!("engine {} failed to stop: {}", engine.id(), e);
+                }
+            }.instrument(span));
         }
 
         /// Spawn the collectors in separate tasks.
         for mut collector in self.collectors {
             let event_sender = event_sender.clone();
             let collector_shutdown = shutdown.child_token();
-            join_set.spawn(async move {
+            let liveness = self
+                .liveness
+                .clone()
+                .filter(|_| collector.tracks_liveness());
+            let span = collector.span();
+            let task_name = format!("{}/collector/{}", self.name, collector.name());
+            spawn_named(&mut join_set, &task_name, async move {
                 let collector_name = collector.name().to_string();
                 info!("starting collector with name: {}", collector_name);
+                if let Some(liveness) = &liveness {
+                    liveness.register(&collector_name);
+                }
                 let mut event_stream = match collector.subscribe_event_stream().await {
                     Ok(stream) => stream,
                     Err(e) => {
@@ -216,7 +295,11 @@ where
                     tokio::select! {
                         event = event_stream.next() => match event {
                             Some(event) => {
-                                if let Err(e) = event_sender.send(event) {
+                                if let Some(liveness) = &liveness {
+                                    liveness.record_event(&collector_name);
+                                }
+                                let correlation_id = NEXT_CORRELATION_ID.fetch_add(1, Ordering::Relaxed);
+                                if let Err(e) = event_sender.send(Correlated { correlation_id, inner: event }) {
                                     error!("collector {} failed to send event: {}", collector_name, e);
                                 }
                             },
@@ -236,7 +319,7 @@ where
                         }
                     }
                 }
-            });
+            }.instrument(span));
         }
 
         // Run until shutdowm, log all errors and return the last one
@@ -253,3 +336,136 @@ where
         last_result.unwrap_or(Ok(())).map_err(anyhow::Error::from)
     }
 }
+
+/// Spawns a task named `<runner>/<component>/<id>`, so it is identifiable in
+/// tokio-console. Tasks are only named when built with `tokio_unstable` and
+/// the `console` feature, which enables the task builder of tokio.
+fn spawn_named<F>(join_set: &mut JoinSet<F::Output>, name: &str, task: F)
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    #[cfg(all(tokio_unstable, feature = "console"))]
+    join_set
+        .build_task()
+        .name(name)
+        .spawn(task)
+        .expect("failed to spawn task");
+    #[cfg(not(all(tokio_unstable, feature = "console")))]
+    {
+        let _ = name;
+        join_set.spawn(task);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    };
+
+    use tokio_util::sync::CancellationToken;
+
+    use super::*;
+    use crate::{Clock, MockClock};
+
+    struct StoppableEngine {
+        stopped: Arc<AtomicBool>,
+    }
+
+    #[async_trait::async_trait]
+    impl Engine<u32, u32> for StoppableEngine {
+        fn id(&self) -> &str { "stoppable_engine" }
+
+        async fn process_event(&mut self, event: u32) -> AppResult<Option<u32>> { Ok(Some(event)) }
+
+        async fn on_stop(&mut self) -> AppResult<()> {
+            self.stopped.store(true, Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_engines_are_stopped_on_shutdown() {
+        let stopped = Arc::new(AtomicBool::new(false));
+        let mut runner = EngineRunner::<u32, u32>::new("test".to_string(), 8, 8);
+        runner.add_engine(Box::new(StoppableEngine { stopped: stopped.clone() }));
+
+        let shutdown = CancellationToken::new();
+        let handle = tokio::spawn(runner.run((), shutdown.clone()));
+        shutdown.cancel();
+        handle.await.unwrap().unwrap();
+
+        assert!(stopped.load(Ordering::SeqCst));
+    }
+
+    struct FiniteCollector {
+        events: Vec<u32>,
+    }
+
+    #[async_trait::async_trait]
+    impl Collector<u32> for FiniteCollector {
+        fn name(&self) -> &str { "finite_collector" }
+
+        async fn subscribe_event_stream(&mut self) -> AppResult<CollectorStream<'_, u32>> {
+            Ok(Box::pin(futures::stream::iter(self.events.clone())))
+        }
+
+        async fn unsubscribe_event_stream(&mut self) -> AppResult<()> { Ok(()) }
+    }
+
+    #[tokio::test]
+    async fn test_collector_events_are_tracked_by_liveness() {
+        let clock = MockClock::new("2025-02-12T21:00:00Z".parse().unwrap());
+        let liveness = CollectorLiveness::new(
+            Arc::new(clock.clone()),
+            std::time::Duration::from_secs(30),
+            std::time::Duration::from_secs(5),
+        );
+        let mut runner = EngineRunner::<u32, u32>::new("test".to_string(), 8, 8);
+        runner.add_collector(Box::new(FiniteCollector { events: vec![1, 2] }));
+        runner.set_collector_liveness(liveness.clone());
+
+        // The collector stream ends, stopping the runner
+        runner.run((), CancellationToken::new()).await.unwrap();
+
+        let statuses = liveness.statuses();
+        assert_eq!(statuses.len(), 1);
+        assert_eq!(statuses[0].name, "finite_collector");
+        assert_eq!(statuses[0].last_event_at, Some(clock.now()));
+        assert!(liveness.is_ready());
+    }
+
+    struct CorrelatedExecutor {
+        seen: mpsc::UnboundedSender<Option<u64>>,
+    }
+
+    #[async_trait::async_trait]
+    impl Executor<u32> for CorrelatedExecutor {
+        fn id(&self) -> &str { "correlated_executor" }
+
+        async fn execute_actions(&mut self, _actions: Vec<u32>) -> AppResult<()> {
+            self.seen.send(current_correlation_id()).unwrap();
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_executors_see_the_correlation_id_of_their_action() {
+        let (seen, mut seen_receiver) = mpsc::unbounded_channel();
+        let mut runner = EngineRunner::<u32, u32>::new("test".to_string(), 8, 8);
+        runner.add_collector(Box::new(FiniteCollector { events: vec![1] }));
+        runner.add_engine(Box::new(StoppableEngine { stopped: Arc::new(AtomicBool::new(false)) }));
+        runner.add_executor(Box::new(CorrelatedExecutor { seen }));
+
+        let shutdown = CancellationToken::new();
+        let handle = tokio::spawn(runner.run((), shutdown.clone()));
+        let correlation_id = seen_receiver.recv().await.unwrap();
+        shutdown.cancel();
+        handle.await.unwrap().unwrap();
+
+        assert!(correlation_id.is_some());
+        assert_eq!(current_correlation_id(), None);
+    }
+}
@@ -11,4 +11,26 @@ pub enum AppError {
 
     #[error("WebSocket error: {0}")]
     WebSocketError(String),
+
+    /// An error that occurs while talking to an HTTP endpoint
+    #[error("HTTP error: {0}")]
+    HttpError(String),
+
+    /// An error caused by an invalid or incomplete configuration
+    #[error("Configuration error: {0}")]
+    ConfigError(String),
+
+    /// An error caused by data failing an integrity check, e.g. a broken
+    /// checksum chain
+    #[error("Integrity error: {0}")]
+    IntegrityError(String),
+
+    /// An error that occurs while serializing data to an external format
+    #[error("Serialization error: {0}")]
+    SerializationError(String),
+
+    /// An error that occurs while building, signing or submitting a
+    /// transaction
+    #[error("Transaction error: {0}")]
+    TransactionError(String),
 }
@@ -1,6 +1,14 @@
+mod clock;
+pub use clock::{Clock, MockClock, SystemClock};
+
+mod config;
+pub use config::{load_config, ConfigFormat, ConfigReloader};
+
 #[allow(unused)]
 mod engine;
-pub use engine::{Collector, CollectorStream, Engine, EngineRunner};
+pub use engine::{
+    current_correlation_id, Collector, CollectorStream, Engine, EngineRunner, Executor,
+};
 
 #[allow(unused)]
 mod error;
@@ -12,10 +20,25 @@ pub use runner::Runner;
 
 #[allow(unused)]
 mod backoff;
-pub use backoff::ExponentialBackoff;
+pub use backoff::{BackoffStrategy, ConstantBackoff, ExponentialBackoff};
+
+#[allow(unused)]
+mod rate_limiter;
+pub use rate_limiter::RateLimiter;
+
+mod liveness;
+pub use liveness::{CollectorLabels, CollectorLiveness, CollectorStatus};
+
+pub mod metrics;
+
+mod math;
+pub use math::EmaCalculator;
 
 mod runtime;
-pub use runtime::run;
+pub use runtime::{run, run_with_reload};
+
+mod secret;
+pub use secret::Secret;
 
 #[allow(unused)]
 mod utils;
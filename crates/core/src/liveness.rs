@@ -0,0 +1,313 @@
+//! Liveness of the collectors run by [`EngineRunner`]s.
+//!
+//! A collector is up while its last event is no older than the staleness
+//! threshold. The runner records every collected event, and the time spent up
+//! and down is accounted exactly at every event and periodic update, so
+//! whether a feed is alive can be answered from metrics alone:
+//!
+//! - `sikarra_collector_last_event_age_seconds`: gauge of the time since the
+//!   last event, or since the collector started if none was collected yet.
+//! - `sikarra_collector_uptime_seconds_total` /
+//!   `sikarra_collector_downtime_seconds_total`: cumulative time up and down.
+//!
+//! [`EngineRunner`]: crate::EngineRunner
+
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use metrics::Label;
+use tokio_util::sync::CancellationToken;
+
+use crate::{
+    metrics::{LabelSet, Metric, Subsystem},
+    AppResult, Clock,
+};
+
+const LAST_EVENT_AGE: Metric<CollectorLabels> =
+    Metric::new(Subsystem::Collector, "last_event_age_seconds");
+const UPTIME: Metric<CollectorLabels> = Metric::new(Subsystem::Collector, "uptime_seconds_total");
+const DOWNTIME: Metric<CollectorLabels> =
+    Metric::new(Subsystem::Collector, "downtime_seconds_total");
+
+/// Labels of a metric measured per collector.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CollectorLabels {
+    pub collector: String,
+}
+
+impl LabelSet for CollectorLabels {
+    fn into_labels(self) -> Vec<Label> {
+        assert!(!self.collector.is_empty(), "metric label `collector` must not be empty");
+        vec![Label::new("collector", self.collector)]
+    }
+}
+
+/// Liveness of a single collector.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CollectorStatus {
+    pub name: String,
+    pub last_event_at: Option<jiff::Timestamp>,
+    /// Time since the last event, or since the collector started
+    pub last_event_age: Duration,
+    pub up: bool,
+    pub uptime: Duration,
+    pub downtime: Duration,
+}
+
+#[derive(Debug, Clone)]
+struct CollectorState {
+    started_at: jiff::Timestamp,
+    last_event_at: Option<jiff::Timestamp>,
+    accounted_until: jiff::Timestamp,
+    uptime: Duration,
+    downtime: Duration,
+}
+
+impl CollectorState {
+    fn new(now: jiff::Timestamp) -> Self {
+        Self {
+            started_at: now,
+            last_event_at: None,
+            accounted_until: now,
+            uptime: Duration::ZERO,
+            downtime: Duration::ZERO,
+        }
+    }
+
+    /// Accounts the time since the last accounting as up until the last event
+    /// went stale, and as down afterwards.
+    fn account(&mut self, now: jiff::Timestamp, staleness: Duration) {
+        if now <= self.accounted_until {
+            return;
+        }
+        let elapsed = now.duration_since(self.accounted_until).unsigned_abs();
+        let up = match self.last_event_at {
+            Some(last_event_at) => {
+                let stale_at = last_event_at.checked_add(staleness).unwrap_or(now);
+                if stale_at > self.accounted_until {
+                    stale_at
+                        .min(now)
+                        .duration_since(self.accounted_until)
+                        .unsigned_abs()
+                } else {
+                    Duration::ZERO
+                }
+            },
+            None => Duration::ZERO,
+        };
+        self.uptime += up;
+        self.downtime += elapsed - up;
+        self.accounted_until = now;
+    }
+
+    fn last_event_age(&self, now: jiff::Timestamp) -> Duration {
+        now.duration_since(self.last_event_at.unwrap_or(self.started_at))
+            .unsigned_abs()
+    }
+}
+
+/// Tracks the liveness of collectors by name.
+///
+/// Cloning is cheap, all clones share the same state so the liveness can be
+/// handed to both runners and readiness checks.
+#[derive(Debug, Clone)]
+pub struct CollectorLiveness {
+    clock: Arc<dyn Clock>,
+    staleness: Duration,
+    update_interval: Duration,
+    collectors: Arc<Mutex<BTreeMap<String, CollectorState>>>,
+}
+
+impl CollectorLiveness {
+    /// Creates a new liveness tracker considering collectors down once their
+    /// last event is older than `staleness`, exporting the metrics every
+    /// `update_interval`.
+    pub fn new(clock: Arc<dyn Clock>, staleness: Duration, update_interval: Duration) -> Self {
+        Self {
+            clock,
+            staleness,
+            update_interval,
+            collectors: Arc::new(Mutex::new(BTreeMap::new())),
+        }
+    }
+
+    /// Starts tracking a collector, which is down until its first event.
+    pub fn register(&self, name: &str) {
+        let now = self.clock.now();
+        self.lock()
+            .entry(name.to_string())
+            .or_insert_with(|| CollectorState::new(now));
+    }
+
+    /// Records an event yielded by a collector.
+    pub fn record_event(&self, name: &str) {
+        let now = self.clock.now();
+        let mut collectors = self.lock();
+        let state = collectors
+            .entry(name.to_string())
+            .or_insert_with(|| CollectorState::new(now));
+        state.account(now, self.staleness);
+        state.last_event_at = Some(now);
+    }
+
+    /// Accounts the time elapsed since the last update and exports the
+    /// metrics of all collectors.
+    pub fn update(&self) {
+        for status in self.statuses() {
+            let labels = || CollectorLabels { collector: status.name.clone() };
+            LAST_EVENT_AGE
+                .gauge(labels())
+                .set(status.last_event_age.as_secs_f64());
+            UPTIME.counter(labels()).absolute(status.uptime.as_secs());
+            DOWNTIME
+                .counter(labels())
+                .absolute(status.downtime.as_secs());
+        }
+    }
+
+    /// Returns the liveness of all collectors, ordered by name.
+    pub fn statuses(&self) -> Vec<CollectorStatus> {
+        let now = self.clock.now();
+        let mut collectors = self.lock();
+        collectors
+            .iter_mut()
+            .map(|(name, state)| {
+                state.account(now, self.staleness);
+                let last_event_age = state.last_event_age(now);
+                CollectorStatus {
+                    name: name.clone(),
+                    last_event_at: state.last_event_at,
+                    last_event_age,
+                    up: state.last_event_at.is_some() && last_event_age <= self.staleness,
+                    uptime: state.uptime,
+                    downtime: state.downtime,
+                }
+            })
+            .collect()
+    }
+
+    /// Returns true if at least one collector is tracked and all of them are
+    /// up.
+    pub fn is_ready(&self) -> bool {
+        let statuses = self.statuses();
+        !statuses.is_empty() && statuses.iter().all(|status| status.up)
+    }
+
+    /// Spawns a task updating the metrics every update interval until
+    /// shutdown is requested.
+    pub fn spawn(self, shutdown: CancellationToken) -> tokio::task::JoinHandle<AppResult<()>> {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(self.update_interval);
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => self.update(),
+                    _ = shutdown.cancelled() => return Ok(()),
+                }
+            }
+        })
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, BTreeMap<String, CollectorState>> {
+        self.collectors
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use metrics_exporter_prometheus::PrometheusBuilder;
+
+    use super::*;
+    use crate::MockClock;
+
+    fn liveness() -> (MockClock, CollectorLiveness) {
+        let clock = MockClock::new("2025-02-12T21:00:00Z".parse().unwrap());
+        let liveness = CollectorLiveness::new(
+            Arc::new(clock.clone()),
+            Duration::from_secs(30),
+            Duration::from_secs(5),
+        );
+        (clock, liveness)
+    }
+
+    fn status_of(liveness: &CollectorLiveness, name: &str) -> CollectorStatus {
+        liveness
+            .statuses()
+            .into_iter()
+            .find(|status| status.name == name)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_collector_without_events_is_down() {
+        let (clock, liveness) = liveness();
+        liveness.register("price_feed_collector_ETH-USDC");
+        clock.advance(Duration::from_secs(45));
+
+        let status = status_of(&liveness, "price_feed_collector_ETH-USDC");
+        assert_eq!(status.last_event_at, None);
+        assert_eq!(status.last_event_age, Duration::from_secs(45));
+        assert!(!status.up);
+        assert_eq!(status.uptime, Duration::ZERO);
+        assert_eq!(status.downtime, Duration::from_secs(45));
+        assert!(!liveness.is_ready());
+    }
+
+    #[test]
+    fn test_uptime_is_accounted_until_events_go_stale() {
+        let (clock, liveness) = liveness();
+        liveness.register("price_feed_collector_ETH-USDC");
+
+        // Down for 10s until the first event, then up for 10s
+        clock.advance(Duration::from_secs(10));
+        liveness.record_event("price_feed_collector_ETH-USDC");
+        clock.advance(Duration::from_secs(10));
+        liveness.record_event("price_feed_collector_ETH-USDC");
+        let status = status_of(&liveness, "price_feed_collector_ETH-USDC");
+        assert!(status.up);
+        assert!(liveness.is_ready());
+        assert_eq!(status.last_event_age, Duration::ZERO);
+        assert_eq!(status.uptime, Duration::from_secs(10));
+        assert_eq!(status.downtime, Duration::from_secs(10));
+
+        // Without events the collector stays up for 30s, then goes down
+        clock.advance(Duration::from_secs(50));
+        let status = status_of(&liveness, "price_feed_collector_ETH-USDC");
+        assert!(!status.up);
+        assert_eq!(status.last_event_age, Duration::from_secs(50));
+        assert_eq!(status.uptime, Duration::from_secs(40));
+        assert_eq!(status.downtime, Duration::from_secs(30));
+
+        // Recovering accounts the gap as down
+        clock.advance(Duration::from_secs(5));
+        liveness.record_event("price_feed_collector_ETH-USDC");
+        clock.advance(Duration::from_secs(5));
+        let status = status_of(&liveness, "price_feed_collector_ETH-USDC");
+        assert!(status.up);
+        assert_eq!(status.uptime, Duration::from_secs(45));
+        assert_eq!(status.downtime, Duration::from_secs(35));
+    }
+
+    #[test]
+    fn test_update_exports_age_and_uptime() {
+        let (clock, liveness) = liveness();
+        let recorder = PrometheusBuilder::new().build_recorder();
+        let handle = recorder.handle();
+        metrics::with_local_recorder(&recorder, || {
+            liveness.register("pool_feed_collector_ETH-USDC");
+            liveness.record_event("pool_feed_collector_ETH-USDC");
+            clock.advance(Duration::from_secs(40));
+            liveness.update();
+        });
+
+        let scrape = handle.render();
+        let collector = "{collector=\"pool_feed_collector_ETH-USDC\"}";
+        assert!(scrape.contains(&format!("sikarra_collector_last_event_age_seconds{collector} 40")));
+        assert!(scrape.contains(&format!("sikarra_collector_uptime_seconds_total{collector} 30")));
+        assert!(scrape.contains(&format!("sikarra_collector_downtime_seconds_total{collector} 10")));
+    }
+}
@@ -0,0 +1,78 @@
+use rust_decimal::Decimal;
+
+use crate::{AppError, AppResult};
+
+/// Exponential moving average of a series of prices.
+///
+/// Every update moves the average by `alpha` of the distance to the new
+/// price, so a higher `alpha` follows the prices more closely and an `alpha`
+/// of 1 is the last price.
+#[derive(Debug, Clone)]
+pub struct EmaCalculator {
+    /// Smoothing factor, within `(0, 1]`
+    alpha: Decimal,
+    current: Option<Decimal>,
+}
+
+impl EmaCalculator {
+    /// Creates a calculator with the smoothing factor `alpha`, which must be
+    /// within `(0, 1]`.
+    pub fn new(alpha: Decimal) -> AppResult<Self> {
+        if alpha <= Decimal::ZERO || alpha > Decimal::ONE {
+            return Err(AppError::ConfigError(format!(
+                "EMA smoothing factor must be within (0, 1], got {}",
+                alpha
+            ))
+            .into());
+        }
+        Ok(Self { alpha, current: None })
+    }
+
+    /// Folds `price` into the average and returns the new average, the first
+    /// price being the average itself.
+    pub fn update(&mut self, price: Decimal) -> Decimal {
+        let ema = match self.current {
+            Some(current) => current + self.alpha * (price - current),
+            None => price,
+        };
+        self.current = Some(ema);
+        ema
+    }
+
+    /// Forgets the prices seen so far.
+    pub fn reset(&mut self) { self.current = None; }
+
+    /// Returns the current average, none before the first update.
+    pub fn value(&self) -> Option<Decimal> { self.current }
+}
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal_macros::dec;
+
+    use super::*;
+
+    #[test]
+    fn test_ema_smooths_spikes() {
+        let mut ema = EmaCalculator::new(dec!(0.2)).unwrap();
+        assert_eq!(ema.value(), None);
+        assert_eq!(ema.update(dec!(2500)), dec!(2500));
+        // A spike only moves the average by a fifth of its size
+        assert_eq!(ema.update(dec!(2600)), dec!(2520));
+        assert_eq!(ema.update(dec!(2500)), dec!(2516));
+        assert_eq!(ema.value(), Some(dec!(2516)));
+
+        ema.reset();
+        assert_eq!(ema.value(), None);
+        assert_eq!(ema.update(dec!(2600)), dec!(2600));
+    }
+
+    #[test]
+    fn test_smoothing_factor_must_be_within_bounds() {
+        assert!(EmaCalculator::new(Decimal::ZERO).is_err());
+        assert!(EmaCalculator::new(dec!(1.5)).is_err());
+        let mut ema = EmaCalculator::new(Decimal::ONE).unwrap();
+        ema.update(dec!(2500));
+        assert_eq!(ema.update(dec!(2600)), dec!(2600));
+    }
+}
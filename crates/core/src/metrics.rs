@@ -0,0 +1,262 @@
+//! Metric naming taxonomy.
+//!
+//! Every metric exported by sikarra is named `sikarra_<subsystem>_<name>` and
+//! carries a fixed set of labels. Metrics are declared once as a [`Metric`],
+//! typed by the [`LabelSet`] they require, so registering a metric without all
+//! of its labels does not compile:
+//!
+//! ```compile_fail
+//! use sikkara_core::metrics::{Metric, Subsystem, VenueLabels};
+//!
+//! const TICKS: Metric<VenueLabels> = Metric::new(Subsystem::Collector, "ticks_total");
+//!
+//! // Missing the `venue` label
+//! TICKS.counter(VenueLabels { symbol: "ETH-USDC".to_string() });
+//! ```
+//!
+//! Label values are checked at registration and must not be empty.
+//!
+//! Adapters are instrumented through lightweight observer traits such as
+//! [`RpcObserver`], implemented here on top of the taxonomy, so they do not
+//! depend on how metrics are exported.
+
+use std::{marker::PhantomData, time::Duration};
+
+use metrics::Label;
+
+/// Prefix of all metric names.
+pub const METRIC_PREFIX: &str = "sikarra";
+
+/// Part of the system a metric belongs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Subsystem {
+    Wsclient,
+    Adapter,
+    Collector,
+    Engine,
+    Strategy,
+    Executor,
+}
+
+impl Subsystem {
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Subsystem::Wsclient => "wsclient",
+            Subsystem::Adapter => "adapter",
+            Subsystem::Collector => "collector",
+            Subsystem::Engine => "engine",
+            Subsystem::Strategy => "strategy",
+            Subsystem::Executor => "executor",
+        }
+    }
+}
+
+/// The labels a metric must be registered with.
+pub trait LabelSet {
+    /// Returns the labels, panicking if any of them is empty.
+    fn into_labels(self) -> Vec<Label>;
+}
+
+/// Labels of a metric measured per symbol.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SymbolLabels {
+    pub symbol: String,
+}
+
+impl LabelSet for SymbolLabels {
+    fn into_labels(self) -> Vec<Label> { vec![required("symbol", self.symbol)] }
+}
+
+/// Labels of a metric measured per symbol on an exchange or venue.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VenueLabels {
+    pub symbol: String,
+    pub venue: String,
+}
+
+impl LabelSet for VenueLabels {
+    fn into_labels(self) -> Vec<Label> {
+        vec![required("symbol", self.symbol), required("venue", self.venue)]
+    }
+}
+
+/// Labels of a metric measured per on-chain pool.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PoolLabels {
+    pub symbol: String,
+    pub venue: String,
+    pub pool: String,
+}
+
+impl LabelSet for PoolLabels {
+    fn into_labels(self) -> Vec<Label> {
+        vec![
+            required("symbol", self.symbol),
+            required("venue", self.venue),
+            required("pool", self.pool),
+        ]
+    }
+}
+
+/// Labels of a metric measured per RPC method and endpoint.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RpcLabels {
+    pub method: String,
+    /// Host of the RPC endpoint, never the full URL which may embed API keys
+    pub endpoint: String,
+}
+
+impl LabelSet for RpcLabels {
+    fn into_labels(self) -> Vec<Label> {
+        vec![required("method", self.method), required("endpoint", self.endpoint)]
+    }
+}
+
+fn required(key: &'static str, value: String) -> Label {
+    assert!(!value.is_empty(), "metric label `{key}` must not be empty");
+    Label::new(key, value)
+}
+
+/// Declaration of a metric requiring the labels `L`.
+#[derive(Debug)]
+pub struct Metric<L> {
+    subsystem: Subsystem,
+    name: &'static str,
+    _labels: PhantomData<fn() -> L>,
+}
+
+impl<L> Metric<L> {
+    /// Declares a metric. The name must be lowercase snake case, otherwise
+    /// evaluating the declaration in a `const` fails compilation.
+    pub const fn new(subsystem: Subsystem, name: &'static str) -> Self {
+        assert!(is_snake_case(name), "metric names must be lowercase snake case");
+        Self { subsystem, name, _labels: PhantomData }
+    }
+
+    pub const fn subsystem(&self) -> Subsystem { self.subsystem }
+
+    /// Returns the exported name, i.e. `sikarra_<subsystem>_<name>`.
+    pub fn name(&self) -> String {
+        format!("{METRIC_PREFIX}_{}_{}", self.subsystem.as_str(), self.name)
+    }
+}
+
+impl<L: LabelSet> Metric<L> {
+    /// Registers the metric as a counter with the given labels.
+    pub fn counter(&self, labels: L) -> metrics::Counter {
+        metrics::counter!(self.name(), labels.into_labels())
+    }
+
+    /// Registers the metric as a gauge with the given labels.
+    pub fn gauge(&self, labels: L) -> metrics::Gauge {
+        metrics::gauge!(self.name(), labels.into_labels())
+    }
+
+    /// Registers the metric as a histogram with the given labels.
+    pub fn histogram(&self, labels: L) -> metrics::Histogram {
+        metrics::histogram!(self.name(), labels.into_labels())
+    }
+}
+
+const RPC_DURATION: Metric<RpcLabels> = Metric::new(Subsystem::Adapter, "rpc_duration_seconds");
+const RPC_ERRORS: Metric<RpcLabels> = Metric::new(Subsystem::Adapter, "rpc_errors_total");
+
+/// Observer of the RPC calls made by an adapter.
+pub trait RpcObserver: Send + Sync + std::fmt::Debug {
+    /// Observes a completed call of `method`, successful or not.
+    fn observe(&self, method: &'static str, duration: Duration, success: bool);
+}
+
+/// Records RPC calls of an endpoint as `sikarra_adapter_rpc_duration_seconds`
+/// histograms and `sikarra_adapter_rpc_errors_total` counters, labelled by
+/// method and endpoint host.
+#[derive(Debug, Clone)]
+pub struct RpcMetrics {
+    endpoint: String,
+}
+
+impl RpcMetrics {
+    pub fn new(endpoint: impl Into<String>) -> Self { Self { endpoint: endpoint.into() } }
+}
+
+impl RpcObserver for RpcMetrics {
+    fn observe(&self, method: &'static str, duration: Duration, success: bool) {
+        let labels = || RpcLabels { method: method.to_string(), endpoint: self.endpoint.clone() };
+        RPC_DURATION
+            .histogram(labels())
+            .record(duration.as_secs_f64());
+        if !success {
+            RPC_ERRORS.counter(labels()).increment(1);
+        }
+    }
+}
+
+const fn is_snake_case(name: &str) -> bool {
+    let bytes = name.as_bytes();
+    if bytes.is_empty() || bytes[0] == b'_' || bytes[bytes.len() - 1] == b'_' {
+        return false;
+    }
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'a'..=b'z' | b'0'..=b'9' | b'_' => {},
+            _ => return false,
+        }
+        i += 1;
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use metrics_exporter_prometheus::PrometheusBuilder;
+
+    use super::*;
+
+    const TICKS: Metric<VenueLabels> = Metric::new(Subsystem::Collector, "ticks_total");
+    const POOL_LIQUIDITY: Metric<PoolLabels> = Metric::new(Subsystem::Adapter, "pool_liquidity");
+
+    fn eth_usdc(venue: &str) -> VenueLabels {
+        VenueLabels { symbol: "ETH-USDC".to_string(), venue: venue.to_string() }
+    }
+
+    #[test]
+    fn test_metric_names_follow_taxonomy() {
+        assert_eq!(TICKS.name(), "sikarra_collector_ticks_total");
+        assert_eq!(POOL_LIQUIDITY.name(), "sikarra_adapter_pool_liquidity");
+        assert!(is_snake_case("update_skew_seconds"));
+        assert!(!is_snake_case("UpdateSkew"));
+        assert!(!is_snake_case("_total"));
+        assert!(!is_snake_case(""));
+    }
+
+    #[test]
+    #[should_panic(expected = "metric label `venue` must not be empty")]
+    fn test_registration_panics_on_empty_label() {
+        let recorder = PrometheusBuilder::new().build_recorder();
+        metrics::with_local_recorder(&recorder, || TICKS.counter(eth_usdc("")).increment(1));
+    }
+
+    #[test]
+    fn test_scrape_exports_names_and_labels() {
+        let recorder = PrometheusBuilder::new().build_recorder();
+        let handle = recorder.handle();
+        metrics::with_local_recorder(&recorder, || {
+            TICKS.counter(eth_usdc("coinbase")).increment(3);
+            POOL_LIQUIDITY
+                .gauge(PoolLabels {
+                    symbol: "ETH-USDC".to_string(),
+                    venue: "uniswap_v4".to_string(),
+                    pool: "0x21c6".to_string(),
+                })
+                .set(42.0);
+        });
+
+        let scrape = handle.render();
+        assert!(scrape
+            .contains("sikarra_collector_ticks_total{symbol=\"ETH-USDC\",venue=\"coinbase\"} 3"));
+        assert!(scrape.contains(
+            "sikarra_adapter_pool_liquidity{symbol=\"ETH-USDC\",venue=\"uniswap_v4\",pool=\"0x21c6\"} 42"
+        ));
+    }
+}
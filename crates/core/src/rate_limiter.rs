@@ -0,0 +1,90 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    hash::Hash,
+    time::{Duration, Instant},
+};
+
+/// A keyed sliding window rate limiter.
+///
+/// Each key is allowed at most `max_events` acquisitions within any window of
+/// length `period`. Keys are tracked independently, so one noisy key cannot
+/// starve the others.
+#[derive(Debug, Clone)]
+pub struct RateLimiter<K> {
+    max_events: usize,
+    period: Duration,
+    windows: HashMap<K, VecDeque<Instant>>,
+}
+
+impl<K> RateLimiter<K>
+where
+    K: Eq + Hash,
+{
+    /// Creates a new `RateLimiter` allowing `max_events` per `period` for each
+    /// key.
+    pub fn new(max_events: usize, period: Duration) -> Self {
+        Self { max_events, period, windows: HashMap::new() }
+    }
+
+    /// Attempts to acquire a permit for the given key at the current instant.
+    ///
+    /// Returns `true` if the event is allowed, `false` if the key is currently
+    /// rate limited.
+    pub fn try_acquire(&mut self, key: K) -> bool { self.try_acquire_at(key, Instant::now()) }
+
+    /// Attempts to acquire a permit for the given key at the given instant.
+    pub fn try_acquire_at(&mut self, key: K, now: Instant) -> bool {
+        let window = self.windows.entry(key).or_default();
+
+        // Evict the events which fell out of the sliding window
+        while let Some(oldest) = window.front() {
+            if now.saturating_duration_since(*oldest) >= self.period {
+                window.pop_front();
+            } else {
+                break;
+            }
+        }
+
+        if window.len() < self.max_events {
+            window.push_back(now);
+            true
+        } else {
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_allows_up_to_max_events_per_period() {
+        let mut limiter = RateLimiter::new(2, Duration::from_secs(60));
+        let start = Instant::now();
+
+        assert!(limiter.try_acquire_at("opportunity", start));
+        assert!(limiter.try_acquire_at("opportunity", start + Duration::from_secs(1)));
+        assert!(!limiter.try_acquire_at("opportunity", start + Duration::from_secs(2)));
+    }
+
+    #[test]
+    fn test_window_slides() {
+        let mut limiter = RateLimiter::new(1, Duration::from_secs(10));
+        let start = Instant::now();
+
+        assert!(limiter.try_acquire_at("feed_down", start));
+        assert!(!limiter.try_acquire_at("feed_down", start + Duration::from_secs(9)));
+        assert!(limiter.try_acquire_at("feed_down", start + Duration::from_secs(10)));
+    }
+
+    #[test]
+    fn test_keys_are_independent() {
+        let mut limiter = RateLimiter::new(1, Duration::from_secs(60));
+        let start = Instant::now();
+
+        assert!(limiter.try_acquire_at("opportunity", start));
+        assert!(!limiter.try_acquire_at("opportunity", start));
+        assert!(limiter.try_acquire_at("kill_switch", start));
+    }
+}
@@ -1,21 +1,36 @@
 use std::time::Duration;
 
+use serde::de::DeserializeOwned;
 use tokio::{
-    signal::unix::{signal, SignalKind},
+    signal::unix::{signal, Signal, SignalKind},
     time::timeout,
 };
 use tokio_util::sync::CancellationToken;
 use tracing::{error, info};
 use tracing_subscriber::prelude::*;
 
-use crate::{AppResult, Runner};
+use crate::{AppResult, ConfigReloader, Runner};
 
 const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(10);
 const ENV_LOG_LEVEL: &str = "LOG_LEVEL";
 const ENV_LOG_JSON: &str = "LOG_JSON";
+#[cfg(feature = "console")]
+const ENV_TOKIO_CONSOLE: &str = "TOKIO_CONSOLE";
 
 pub fn run<P, R>(params: P, runner: R)
 where
+    P: DeserializeOwned,
+    R: Runner<P> + Send,
+{
+    run_with_reload(params, runner, None)
+}
+
+/// Runs like [`run`], reloading the configuration through `reloader` on
+/// SIGHUP if given. A configuration failing to reload is logged, the runner
+/// keeping the current one.
+pub fn run_with_reload<P, R>(params: P, runner: R, reloader: Option<ConfigReloader<P>>)
+where
+    P: DeserializeOwned,
     R: Runner<P> + Send,
 {
     // Setup telemetry for logging
@@ -33,16 +48,36 @@ where
             let shutdown = CancellationToken::new();
             let mut run = runner.run(params, shutdown.child_token());
 
-            // Handle shutdown signals
+            // Handle shutdown signals, and reload signals if reloading. SIGHUP
+            // keeps terminating the process otherwise
             let mut sigterm = signal(SignalKind::terminate())?;
             let mut sigint = signal(SignalKind::interrupt())?;
-            tokio::select! {
-                res = run.as_mut() => match res {
-                        Ok(_) => info!("runner exited cleanly"),
-                        Err(e) => error!("runner terminated with error: {:#}", e),
+            let mut sighup = match &reloader {
+                Some(_) => Some(signal(SignalKind::hangup())?),
+                None => None,
+            };
+            loop {
+                tokio::select! {
+                    res = run.as_mut() => {
+                        match res {
+                            Ok(_) => info!("runner exited cleanly"),
+                            Err(e) => error!("runner terminated with error: {:#}", e),
+                        }
+                        break;
                     },
-                _ = sigterm.recv() => (),
-                _ = sigint.recv() => (),
+                    _ = sigterm.recv() => break,
+                    _ = sigint.recv() => break,
+                    Some(()) = hangup(&mut sighup) => {
+                        info!("reloading the configuration...");
+                        let reloaded = reloader.as_ref().map(|reloader| reloader.reload());
+                        if let Some(Err(e)) = reloaded {
+                            error!(
+                                "failed to reload the configuration, keeping the current one: {:#}",
+                                e
+                            );
+                        }
+                    },
+                }
             }
             info!("shutting down...");
             shutdown.cancel();
@@ -64,6 +99,14 @@ where
         .expect("runtime failed to run");
 }
 
+/// Waits for the next SIGHUP, forever if not listening to it.
+async fn hangup(sighup: &mut Option<Signal>) -> Option<()> {
+    match sighup {
+        Some(sighup) => sighup.recv().await,
+        None => std::future::pending().await,
+    }
+}
+
 fn setup_telemetry() -> AppResult<()> {
     let log_level = std::env::var(ENV_LOG_LEVEL).unwrap_or_else(|_| "info".to_string());
     let log_filter = tracing_subscriber::EnvFilter::builder()
@@ -80,17 +123,52 @@ fn setup_telemetry() -> AppResult<()> {
             .with_thread_names(true)
             .with_thread_ids(true);
         tracing_subscriber::registry()
-            .with(log_filter)
-            .with(log_formatter)
+            .with(console_layer())
+            .with(log_formatter.with_filter(log_filter))
             .init();
     } else {
         let log_formatter = tracing_subscriber::fmt::layer()
             .with_thread_names(true)
             .with_thread_ids(true);
         tracing_subscriber::registry()
-            .with(log_filter)
-            .with(log_formatter)
+            .with(console_layer())
+            .with(log_formatter.with_filter(log_filter))
             .init();
     };
     Ok(())
 }
+
+/// Returns the tokio-console layer if enabled through `TOKIO_CONSOLE=1`. It
+/// records the runtime's own spans, so the log filter only applies to the log
+/// formatter.
+#[cfg(feature = "console")]
+fn console_layer<S>() -> Option<impl tracing_subscriber::Layer<S>>
+where
+    S: tracing::Subscriber + for<'a> tracing_subscriber::registry::LookupSpan<'a>,
+{
+    if std::env::var(ENV_TOKIO_CONSOLE).is_ok_and(|value| value == "1") {
+        Some(
+            console_subscriber::ConsoleLayer::builder()
+                .with_default_env()
+                .spawn(),
+        )
+    } else {
+        None
+    }
+}
+
+#[cfg(not(feature = "console"))]
+fn console_layer() -> Option<tracing_subscriber::layer::Identity> { None }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_setup_telemetry() {
+        #[cfg(feature = "console")]
+        std::env::set_var(ENV_TOKIO_CONSOLE, "1");
+        setup_telemetry().unwrap();
+        info!("telemetry is set up");
+    }
+}
@@ -0,0 +1,51 @@
+use std::fmt;
+
+use crate::AppError;
+
+/// A secret value, such as a private key or an API token.
+///
+/// Secrets are read from the environment rather than from configuration files
+/// and are redacted when formatted, so they cannot leak into logs.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Secret(String);
+
+impl Secret {
+    pub fn new(value: impl Into<String>) -> Self { Self(value.into()) }
+
+    /// Reads the secret from the environment variable `var`.
+    pub fn from_env(var: &str) -> Result<Self, AppError> {
+        std::env::var(var).map(Self).map_err(|_| {
+            AppError::ConfigError(format!("secret not found in environment variable {}", var))
+        })
+    }
+
+    /// Returns the secret value, which must not be logged.
+    pub fn expose(&self) -> &str { &self.0 }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result { f.write_str("Secret(***)") }
+}
+
+impl fmt::Display for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result { f.write_str("***") }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_secret_is_redacted() {
+        let secret = Secret::new("0xac0974bec39a17e36ba4a6b4d238ff944bacb478");
+        assert_eq!(format!("{:?}", secret), "Secret(***)");
+        assert_eq!(secret.to_string(), "***");
+        assert_eq!(secret.expose(), "0xac0974bec39a17e36ba4a6b4d238ff944bacb478");
+    }
+
+    #[test]
+    fn test_missing_secret_names_the_variable() {
+        let error = Secret::from_env("SIKARRA_TEST_MISSING_SECRET").unwrap_err();
+        assert!(error.to_string().contains("SIKARRA_TEST_MISSING_SECRET"));
+    }
+}
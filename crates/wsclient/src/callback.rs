@@ -24,6 +24,28 @@ pub trait WsCallback {
     /// closed.
     async fn on_connect(&mut self, timestamp: jiff::Timestamp) -> AppResult<()>;
 
+    /// Called when the connection is established again after it was lost.
+    ///
+    /// This method is invoked right after
+    /// [`on_connect`](WsCallback::on_connect) on every connection but the
+    /// first one, so state the server forgot with the previous connection,
+    /// e.g. subscriptions, can be initialized again. The default
+    /// implementation does nothing.
+    ///
+    /// # Parameters
+    ///
+    /// * `attempt` - The number of connection attempts it took to reconnect
+    /// * `timestamp` - The exact time when the connection was established
+    ///
+    /// # Returns
+    ///
+    /// Returns `Ok(())` if the reconnection handling succeeds, or an error if
+    /// re-initialization fails. Returning an error may cause the connection to
+    /// be closed.
+    async fn on_reconnect(&mut self, attempt: u32, timestamp: jiff::Timestamp) -> AppResult<()> {
+        Ok(())
+    }
+
     /// Called when a message is received from the WebSocket connection.
     ///
     /// This method handles all incoming WebSocket messages, including text,
@@ -47,12 +47,12 @@ impl WsClient {
 
         let receiver = self.receiver.take().unwrap();
 
-        Ok(WsConsumer {
-            ws_url: self.ws_url.clone(),
+        Ok(WsConsumer::new(
+            self.ws_url.clone(),
             callback,
-            heartbeat_millis: self.heartbeat_millis,
-            backoff: ExponentialBackoff::default(),
+            self.heartbeat_millis,
+            ExponentialBackoff::default(),
             receiver,
-        })
+        ))
     }
 }
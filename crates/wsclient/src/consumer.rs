@@ -1,46 +1,200 @@
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
 use futures_util::{SinkExt, StreamExt};
-use sikkara_core::{AppError, AppResult, ExponentialBackoff};
+use sikkara_core::{AppError, AppResult, BackoffStrategy, ExponentialBackoff};
 use tokio::sync::mpsc;
 use tokio_tungstenite::{connect_async, tungstenite::Message, WebSocketStream};
 use tokio_util::sync::CancellationToken;
-use tracing::{error, info};
+use tracing::{debug, error, info, warn};
 
 use crate::{callback::WsCallback, consumer};
 
-pub struct WsConsumer<C>
+/// Maintains the connection of a websocket, reconnecting after the delays of
+/// its backoff strategy `B`.
+pub struct WsConsumer<C, B = ExponentialBackoff>
 where
     C: WsCallback + Clone,
+    B: BackoffStrategy,
 {
     pub ws_url: String,
     pub callback: C,
     pub heartbeat_millis: u64,
-    pub backoff: ExponentialBackoff,
+    pub backoff: B,
     pub receiver: mpsc::Receiver<Message>,
+    /// Time without any message after which the connection is considered
+    /// lost, see [`with_idle_timeout`](WsConsumer::with_idle_timeout)
+    pub idle_timeout: Option<Duration>,
+    /// Whether a ping frame is sent on every heartbeat, keeping NAT mappings
+    /// alive and exercising the pong path of the server
+    pub send_ping: bool,
+    /// Maximum number of messages queued to be written at once, see
+    /// [`with_outbound_queue`](WsConsumer::with_outbound_queue)
+    pub max_outbound_queue: usize,
+    pub queue_overflow: QueueOverflowPolicy,
+    stats: Arc<Mutex<WsConsumerStats>>,
+}
+
+/// Statistics of the messages received by a [`WsConsumer`], across
+/// reconnections.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct WsConsumerStats {
+    pub messages_received: u64,
+    pub bytes_received: u64,
+    /// Messages the callback failed to handle, which drops the connection
+    pub messages_dropped: u64,
+    pub last_message_at: Option<jiff::Timestamp>,
+    /// Average time the callback took to handle a message, a consumer falling
+    /// behind once it nears the interval between messages
+    pub avg_latency_micros: f64,
 }
 
-impl<C> WsConsumer<C>
+impl WsConsumerStats {
+    fn record(&mut self, bytes: usize, received_at: jiff::Timestamp, latency_micros: f64) {
+        self.messages_received += 1;
+        self.bytes_received += bytes as u64;
+        self.last_message_at = Some(received_at);
+        self.avg_latency_micros +=
+            (latency_micros - self.avg_latency_micros) / self.messages_received as f64;
+    }
+}
+
+/// What a [`WsConsumer`] does with the messages to write beyond its maximum
+/// outbound queue.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum QueueOverflowPolicy {
+    /// Leaves them in the channel until the queue is written, so senders are
+    /// flow controlled by the capacity of the channel
+    #[default]
+    Block,
+    /// Drops the messages beyond the queue, keeping the oldest ones
+    DropNewest,
+    /// Drops the oldest messages of the queue, keeping the newest ones
+    DropOldest,
+}
+
+impl QueueOverflowPolicy {
+    /// Queues a message, returns it back if the queue is full and it has to
+    /// wait, see [`QueueOverflowPolicy::Block`].
+    fn enqueue(
+        &self,
+        queue: &mut VecDeque<Message>,
+        max_len: usize,
+        message: Message,
+    ) -> Option<Message> {
+        if queue.len() < max_len {
+            queue.push_back(message);
+            return None;
+        }
+        match self {
+            QueueOverflowPolicy::Block => return Some(message),
+            QueueOverflowPolicy::DropNewest => {
+                warn!("outbound queue is full, dropping message: {:?}", message);
+            },
+            QueueOverflowPolicy::DropOldest => {
+                if let Some(oldest) = queue.pop_front() {
+                    warn!("outbound queue is full, dropping message: {:?}", oldest);
+                }
+                queue.push_back(message);
+            },
+        }
+        None
+    }
+}
+
+/// Consumer reconnecting with an [`ExponentialBackoff`].
+pub type WsConsumerDefault<C> = WsConsumer<C, ExponentialBackoff>;
+
+impl<C, B> WsConsumer<C, B>
 where
     C: WsCallback + Clone + Send + 'static,
+    B: BackoffStrategy + Send + 'static,
 {
+    pub fn new(
+        ws_url: String,
+        callback: C,
+        heartbeat_millis: u64,
+        backoff: B,
+        receiver: mpsc::Receiver<Message>,
+    ) -> Self {
+        WsConsumer {
+            ws_url,
+            callback,
+            heartbeat_millis,
+            backoff,
+            receiver,
+            idle_timeout: None,
+            send_ping: true,
+            max_outbound_queue: usize::MAX,
+            queue_overflow: QueueOverflowPolicy::Block,
+            stats: Arc::new(Mutex::new(WsConsumerStats::default())),
+        }
+    }
+
+    /// Reconnects once no message was received for `idle_timeout`, e.g. when
+    /// the server stopped sending without closing the connection.
+    ///
+    /// The timeout is checked on every heartbeat, so it is detected up to
+    /// `heartbeat_millis` late and should be longer than the heartbeat
+    /// interval, as well as the interval the server sends messages at.
+    pub fn with_idle_timeout(mut self, idle_timeout: Duration) -> Self {
+        self.idle_timeout = Some(idle_timeout);
+        self
+    }
+
+    /// Sets whether a ping frame is sent on every heartbeat, which is the
+    /// default.
+    pub fn with_send_ping(mut self, send_ping: bool) -> Self {
+        self.send_ping = send_ping;
+        self
+    }
+
+    /// Writes at most `max_outbound_queue` of the messages pending in the
+    /// channel at once, e.g. those accumulated while reconnecting, handling
+    /// the others according to the `queue_overflow` policy. Lossy policies
+    /// may drop requests the server needs, such as subscriptions.
+    pub fn with_outbound_queue(
+        mut self,
+        max_outbound_queue: usize,
+        queue_overflow: QueueOverflowPolicy,
+    ) -> Self {
+        self.max_outbound_queue = max_outbound_queue.max(1);
+        self.queue_overflow = queue_overflow;
+        self
+    }
+
+    /// Returns the statistics of the messages received so far.
+    pub fn stats(&self) -> WsConsumerStats { self.stats_handle().lock().unwrap().clone() }
+
+    /// Returns the shared statistics, to keep reading them once the consumer
+    /// is spawned.
+    pub fn stats_handle(&self) -> Arc<Mutex<WsConsumerStats>> { self.stats.clone() }
+
     pub async fn run(&mut self, shutdown: CancellationToken) -> AppResult<()> {
+        // Connection attempts since the last connection, which was lost if any
+        let mut attempt: u32 = 0;
+        let mut connected_before = false;
+
         loop {
-            match self.backoff.next() {
-                Some(backoff_secs) => {
-                    if backoff_secs > 0 {
-                        tokio::time::sleep(tokio::time::Duration::from_secs(backoff_secs as u64))
-                            .await;
+            match self.backoff.next_delay() {
+                Some(delay) => {
+                    if !delay.is_zero() {
+                        tokio::time::sleep(delay).await;
                     }
                 },
                 None => {
                     return Err(AppError::WebSocketError(format!(
-                        "failed to connect to {} after {} retries",
-                        self.ws_url,
-                        self.backoff.get_iteration_count()
+                        "failed to connect to {}, no retry left",
+                        self.ws_url
                     ))
                     .into());
                 },
             }
 
+            attempt = attempt.saturating_add(1);
             info!("connecting to websocket at {}", self.ws_url);
             let ws_stream = match connect_async(&self.ws_url).await {
                 Ok((ws_stream, _)) => {
@@ -54,7 +208,13 @@ where
                 },
             };
 
-            let stream_result = self.stream(ws_stream, shutdown.child_token()).await;
+            let reconnect_attempt = connected_before.then_some(attempt);
+            connected_before = true;
+            attempt = 0;
+
+            let stream_result = self
+                .stream(ws_stream, reconnect_attempt, shutdown.child_token())
+                .await;
             self.callback.on_disconnect()?;
 
             match stream_result {
@@ -70,16 +230,26 @@ where
         }
     }
 
+    /// Streams the messages of a connection until it is lost or shut down,
+    /// `reconnect_attempt` being the number of attempts it took to reconnect
+    /// if an earlier connection was lost.
     async fn stream<S>(
         &mut self,
         mut ws_stream: WebSocketStream<S>,
+        reconnect_attempt: Option<u32>,
         shutdown: CancellationToken,
     ) -> AppResult<()>
     where
         S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin + Send + 'static,
     {
-        self.callback.on_connect(jiff::Timestamp::now()).await?;
+        let connected_at = jiff::Timestamp::now();
+        self.callback.on_connect(connected_at).await?;
+        if let Some(attempt) = reconnect_attempt {
+            info!("reconnected to websocket at {} after {} attempts", self.ws_url, attempt);
+            self.callback.on_reconnect(attempt, connected_at).await?;
+        }
         let mut num_message_since_last_heartbeat = 0;
+        let mut last_received = Instant::now();
         let mut heartbeat =
             tokio::time::interval(tokio::time::Duration::from_millis(self.heartbeat_millis));
 
@@ -101,8 +271,21 @@ where
                             match result {
                                 Ok(message) => {
                                     let recieved_time = jiff::Timestamp::now();
+                                    let bytes = message.len();
                                     num_message_since_last_heartbeat += 1;
-                                    self.callback.on_message(message, recieved_time).await?;
+                                    last_received = Instant::now();
+                                    let started = Instant::now();
+                                    let result = self.callback.on_message(message, recieved_time).await;
+                                    let latency_micros = started.elapsed().as_secs_f64() * 1e6;
+
+                                    {
+                                        let mut stats = self.stats.lock().unwrap();
+                                        stats.record(bytes, recieved_time, latency_micros);
+                                        if result.is_err() {
+                                            stats.messages_dropped += 1;
+                                        }
+                                    }
+                                    result?;
                                 },
                                 Err(e) => {
                                     return Err(AppError::WebSocketError(format!("websocket streaming error: {}", e)).into());
@@ -119,9 +302,25 @@ where
                     // Handle the incoming message from the receiver aka sending a request to the websocket
                     match result {
                         Some(message) => {
-                            info!("sending message to websocket: {:?}", message);
-                            if let Err(e) = ws_stream.send(message).await {
-                                return Err(AppError::WebSocketError(format!("failed to send message: {}", e)).into());
+                            // Queue the messages pending as well, up to the maximum
+                            let mut queue = VecDeque::new();
+                            let mut blocked = None;
+                            let mut next = Some(message);
+                            while let Some(message) = next {
+                                blocked = self.queue_overflow.enqueue(&mut queue, self.max_outbound_queue, message);
+                                if blocked.is_some() {
+                                    // Already out of the channel, it is written last
+                                    break;
+                                }
+                                next = self.receiver.try_recv().ok();
+                            }
+
+                            for message in queue.into_iter().chain(blocked) {
+                                // Only the size, requests may carry credentials
+                                debug!("sending {} bytes to websocket", message.len());
+                                if let Err(e) = ws_stream.send(message).await {
+                                    return Err(AppError::WebSocketError(format!("failed to send message: {}", e)).into());
+                                }
                             }
                         },
                         None => {
@@ -133,11 +332,19 @@ where
 
                 _ = heartbeat.tick() => {
                     // Heartbeat tick
+                    if self.send_ping {
+                        if let Err(e) = ws_stream.send(Message::Ping(Default::default())).await {
+                            return Err(AppError::WebSocketError(format!("failed to send ping: {}", e)).into());
+                        }
+                    }
                     let _ = self.callback.on_heartbeat();
                     if num_message_since_last_heartbeat > 0 {
                         info!("number of messages received since last heartbeat: {}", num_message_since_last_heartbeat);
                         num_message_since_last_heartbeat = 0;
                     }
+                    if self.idle_timeout.is_some_and(|idle_timeout| last_received.elapsed() > idle_timeout) {
+                        return Err(AppError::WebSocketError("idle timeout".to_string()).into());
+                    }
                 }
             }
         }
@@ -148,3 +355,140 @@ where
         tokio::spawn(async move { consumer.run(shutdown).await })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    #[derive(Clone)]
+    struct NoopCallback;
+
+    #[async_trait::async_trait]
+    impl WsCallback for NoopCallback {
+        async fn on_connect(&mut self, timestamp: jiff::Timestamp) -> AppResult<()> { Ok(()) }
+
+        async fn on_message(
+            &mut self,
+            message: Message,
+            receive_at: jiff::Timestamp,
+        ) -> AppResult<()> {
+            Ok(())
+        }
+
+        fn on_disconnect(&mut self) -> AppResult<()> { Ok(()) }
+
+        fn on_heartbeat(&mut self) -> AppResult<()> { Ok(()) }
+    }
+
+    fn text(value: &str) -> Message { Message::text(value) }
+
+    fn enqueue_all(policy: QueueOverflowPolicy, values: &[&str]) -> (Vec<Message>, Vec<Message>) {
+        let mut queue = VecDeque::new();
+        let blocked = values
+            .iter()
+            .filter_map(|value| policy.enqueue(&mut queue, 2, text(value)))
+            .collect();
+        (queue.into_iter().collect(), blocked)
+    }
+
+    #[test]
+    fn test_queue_overflow_policies() {
+        let values = ["a", "b", "c", "d"];
+        assert_eq!(
+            enqueue_all(QueueOverflowPolicy::Block, &values),
+            (vec![text("a"), text("b")], vec![text("c"), text("d")])
+        );
+        assert_eq!(
+            enqueue_all(QueueOverflowPolicy::DropNewest, &values),
+            (vec![text("a"), text("b")], vec![])
+        );
+        assert_eq!(
+            enqueue_all(QueueOverflowPolicy::DropOldest, &values),
+            (vec![text("c"), text("d")], vec![])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_heartbeat_sends_ping() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let ws_url = format!("ws://{}", listener.local_addr().unwrap());
+        let server = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+            while let Some(Ok(message)) = ws.next().await {
+                if message.is_ping() {
+                    return true;
+                }
+            }
+            false
+        });
+
+        let (_sender, receiver) = mpsc::channel(1);
+        let mut consumer = WsConsumer::new(
+            ws_url.clone(),
+            NoopCallback,
+            10,
+            ExponentialBackoff::default(),
+            receiver,
+        );
+        let (ws_stream, _) = connect_async(&ws_url).await.unwrap();
+        let stream = consumer.stream(ws_stream, None, CancellationToken::new());
+        tokio::pin!(stream);
+
+        let received_ping = tokio::select! {
+            received_ping = server => received_ping.unwrap(),
+            result = &mut stream => panic!("stream ended with {:?}", result),
+        };
+        assert!(received_ping);
+    }
+
+    #[tokio::test]
+    async fn test_idle_connection_is_dropped() {
+        // Server accepting the connection and never sending anything
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let ws_url = format!("ws://{}", listener.local_addr().unwrap());
+        let server = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+            while let Some(Ok(_)) = ws.next().await {}
+        });
+
+        let (_sender, receiver) = mpsc::channel(1);
+        let mut consumer = WsConsumer::new(
+            ws_url.clone(),
+            NoopCallback,
+            10,
+            ExponentialBackoff::default(),
+            receiver,
+        )
+        .with_idle_timeout(Duration::from_millis(50))
+        // Pongs to the pings would keep the connection active
+        .with_send_ping(false);
+        let (ws_stream, _) = connect_async(&ws_url).await.unwrap();
+
+        let result = tokio::time::timeout(
+            Duration::from_secs(5),
+            consumer.stream(ws_stream, None, CancellationToken::new()),
+        )
+        .await
+        .expect("idle connection not dropped");
+        assert!(result.unwrap_err().to_string().contains("idle timeout"));
+        server.abort();
+    }
+
+    #[test]
+    fn test_stats_average_latency() {
+        let mut stats = WsConsumerStats::default();
+        let received_at = jiff::Timestamp::from_second(1_700_000_000).unwrap();
+        stats.record(100, received_at, 10.0);
+        stats.record(50, received_at, 20.0);
+        stats.record(30, received_at, 60.0);
+
+        assert_eq!(stats.messages_received, 3);
+        assert_eq!(stats.bytes_received, 180);
+        assert_eq!(stats.last_message_at, Some(received_at));
+        assert_eq!(stats.avg_latency_micros, 30.0);
+    }
+}
@@ -4,7 +4,7 @@ pub use callback::WsCallback;
 
 #[allow(unused)]
 mod consumer;
-pub use consumer::WsConsumer;
+pub use consumer::{QueueOverflowPolicy, WsConsumer, WsConsumerDefault, WsConsumerStats};
 
 #[allow(unused)]
 mod client;